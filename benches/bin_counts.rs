@@ -0,0 +1,95 @@
+// Image recalculation of 4096x4096 histograms with the sparse and the dense bin counts, at fill
+// fractions below and above DENSE_FRACTION where the automatic storage turns dense. The image is
+// what `recalculate_image` redraws, `data_2_image` is all of it but the texture upload.
+//
+//     cargo bench --bench bin_counts
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use spectrix::histoer::histo2d::bin_counts::{Storage, DENSE_FRACTION};
+use spectrix::histoer::histo2d::histogram2d::Histogram2D;
+
+const BINS: usize = 4096;
+const FRACTIONS: [f64; 4] = [0.001, 0.02, 0.25, 0.8];
+const REPEATS: usize = 3;
+
+// Small xorshift generator so the runs are repeatable without another dependency
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// About `fraction` of the bins filled with 1 to 100 counts
+fn histogram(fraction: f64, storage: Storage) -> Histogram2D {
+    let mut hist = Histogram2D::new("bench", (BINS, BINS), ((0.0, 4096.0), (0.0, 4096.0)));
+    hist.plot_settings.storage = storage;
+    if storage == Storage::Dense {
+        hist.bins.counts.to_dense(BINS, BINS);
+    }
+
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for y in 0..BINS {
+        for x in 0..BINS {
+            if rng.unit() < fraction {
+                let count = hist.bins.counts.add(x, y, 1 + (rng.next() >> 32) % 100);
+                hist.bins.min_count = hist.bins.min_count.min(count);
+                hist.bins.max_count = hist.bins.max_count.max(count);
+            }
+        }
+    }
+    hist
+}
+
+// Fastest of the repeats, the first one also pays for the allocations
+fn time(mut run: impl FnMut()) -> Duration {
+    (0..REPEATS)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    println!(
+        "{}x{} bins, dense above {}% filled with the automatic storage, best of {} runs",
+        BINS,
+        BINS,
+        DENSE_FRACTION * 100.0,
+        REPEATS
+    );
+
+    for fraction in FRACTIONS {
+        for (name, storage) in [("sparse", Storage::Sparse), ("dense", Storage::Dense)] {
+            let start = Instant::now();
+            let hist = histogram(fraction, storage);
+            let fill_time = start.elapsed();
+
+            let image_time = time(|| {
+                black_box(hist.data_2_image());
+            });
+
+            println!(
+                "{:>5.1}% filled, {:<6} counts: image {:>8.1} ms, fill {:>8.1} ms, {:>7.1} MB",
+                fraction * 100.0,
+                name,
+                image_time.as_secs_f64() * 1e3,
+                fill_time.as_secs_f64() * 1e3,
+                hist.bins.counts.bytes() as f64 / 1_048_576.0
+            );
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// Transforms applied when a plot is drawn, the stored bins are never changed.
+// On the y axis the drawn points are transformed (like log y) and the tick labels
+// and cursor show the original value. On the x axis the bins stay where they are
+// and the tick labels and cursor show the transformed value, e.g. channel -> time of flight.
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum AxisTransform {
+    #[default]
+    Linear,
+    Sqrt,
+    Logit, // ln(v / (1 - v)) for fractions like efficiencies
+    Reciprocal {
+        scale: f64,
+        offset: f64,
+        power: i32,
+    }, // scale / (v - offset)^power, e.g. TOF <-> energy
+}
+
+impl AxisTransform {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AxisTransform::Linear => "Linear",
+            AxisTransform::Sqrt => "Sqrt",
+            AxisTransform::Logit => "Logit",
+            AxisTransform::Reciprocal { .. } => "k / (v - v0)^n",
+        }
+    }
+
+    pub fn is_linear(&self) -> bool {
+        *self == AxisTransform::Linear
+    }
+
+    pub fn forward(&self, value: f64) -> f64 {
+        match *self {
+            AxisTransform::Linear => value,
+            AxisTransform::Sqrt => value.signum() * value.abs().sqrt(),
+            AxisTransform::Logit => {
+                let p = value.clamp(1e-9, 1.0 - 1e-9);
+                (p / (1.0 - p)).ln()
+            }
+            AxisTransform::Reciprocal {
+                scale,
+                offset,
+                power,
+            } => scale / (value - offset).powi(power),
+        }
+    }
+
+    pub fn inverse(&self, value: f64) -> f64 {
+        match *self {
+            AxisTransform::Linear => value,
+            AxisTransform::Sqrt => value * value.abs(),
+            AxisTransform::Logit => 1.0 / (1.0 + (-value).exp()),
+            AxisTransform::Reciprocal {
+                scale,
+                offset,
+                power,
+            } => offset + (scale / value).powf(1.0 / power.max(1) as f64),
+        }
+    }
+
+    // Returns true when the transform changed
+    pub fn ui(&mut self, ui: &mut egui::Ui, id: &str, choices: &[AxisTransform]) -> bool {
+        let before = *self;
+
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(self.label())
+            .show_ui(ui, |ui| {
+                for choice in choices {
+                    let selected = std::mem::discriminant(self) == std::mem::discriminant(choice);
+                    if ui.selectable_label(selected, choice.label()).clicked() && !selected {
+                        *self = *choice;
+                    }
+                }
+            });
+
+        if let AxisTransform::Reciprocal {
+            scale,
+            offset,
+            power,
+        } = self
+        {
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(scale).speed(1.0).prefix("k: "));
+                ui.add(egui::DragValue::new(offset).speed(1.0).prefix("v0: "));
+                ui.add(egui::DragValue::new(power).range(1..=4).prefix("n: "));
+            });
+        }
+
+        *self != before
+    }
+}
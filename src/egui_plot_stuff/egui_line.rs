@@ -1,6 +1,7 @@
 use egui::{Color32, DragValue, Slider, Stroke, Ui};
 use egui_plot::{Line, LineStyle, PlotPoint, PlotPoints, PlotUi};
 
+use crate::egui_plot_stuff::axis_transform::AxisTransform;
 use crate::egui_plot_stuff::colors::{Rgb, COLOR_OPTIONS};
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -9,6 +10,8 @@ pub struct EguiLine {
     pub name_in_legend: bool,
     pub log_y: bool,
     pub log_x: bool,
+    #[serde(default)]
+    pub y_transform: AxisTransform, // used when log_y is off
     pub name: String,
     pub highlighted: bool,
     pub stroke: Stroke,
@@ -34,6 +37,7 @@ impl Default for EguiLine {
             name_in_legend: false,
             log_y: false,
             log_x: false,
+            y_transform: AxisTransform::Linear,
             name: "Line".to_string(),
             highlighted: false,
             stroke: Stroke::new(1.0, Color32::from_rgb(120, 47, 64)),
@@ -88,8 +92,10 @@ impl EguiLine {
                     };
                     let y = if self.log_y && y > 0.0 {
                         y.log10().max(0.0001)
-                    } else {
+                    } else if self.log_y {
                         y
+                    } else {
+                        self.y_transform.forward(y)
                     };
                     PlotPoint::new(x, y)
                 })
@@ -1,4 +1,8 @@
+use super::axis_transform::AxisTransform;
+use super::tick_format::TickFormat;
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct EguiPlotSettings {
     pub legend: bool,
     pub log_x: bool,
@@ -20,6 +24,25 @@ pub struct EguiPlotSettings {
     pub reset_axis: bool,
     pub x_label: String,
     pub y_label: String,
+    pub x_bounds: (f64, f64), // manual bounds, in data units even when the axis is log
+    pub y_bounds: (f64, f64),
+    pub lock_bounds: bool, // keep the manual bounds, also used by reset_axis
+    #[serde(default)]
+    pub x_transform: AxisTransform, // shown on the x labels and cursor, the bins are not moved
+    #[serde(default)]
+    pub y_transform: AxisTransform, // applied to the drawn points, ignored with log y
+    #[serde(default)]
+    pub x_ticks: TickFormat,
+    #[serde(default)]
+    pub y_ticks: TickFormat,
+    #[serde(default = "default_decade_bounds")]
+    pub decade_bounds: bool, // resetting a log y axis goes out to whole decades
+    #[serde(skip)]
+    pub x_calibration: Option<[f64; 3]>, // a x^2 + b x + c shown on the x labels, set by the owner every frame
+    #[serde(skip)]
+    pub apply_bounds: bool,
+    #[serde(skip)]
+    pub current_bounds: Option<egui_plot::PlotBounds>,
 }
 
 impl Default for EguiPlotSettings {
@@ -45,10 +68,25 @@ impl Default for EguiPlotSettings {
             reset_axis: false,
             x_label: String::new(),
             y_label: String::new(),
+            x_bounds: (0.0, 4096.0),
+            y_bounds: (0.0, 100.0),
+            lock_bounds: false,
+            x_transform: AxisTransform::Linear,
+            y_transform: AxisTransform::Linear,
+            x_ticks: TickFormat::Auto,
+            y_ticks: TickFormat::Auto,
+            decade_bounds: true,
+            x_calibration: None,
+            apply_bounds: false,
+            current_bounds: None,
         }
     }
 }
 
+fn default_decade_bounds() -> bool {
+    true
+}
+
 impl EguiPlotSettings {
     pub fn menu_button(&mut self, ui: &mut egui::Ui) {
         ui.menu_button("Plot Maniupulation Settings", |ui| {
@@ -89,38 +127,218 @@ impl EguiPlotSettings {
         });
     }
 
+    // Log scales, tick labels, and grid of a pane
+    pub fn axes_ui(&mut self, ui: &mut egui::Ui, id: &str) {
+        ui.menu_button("Axes", |ui| {
+            ui.horizontal(|ui| {
+                if ui.checkbox(&mut self.log_x, "Log X").changed() {
+                    self.reset_axis = true;
+                }
+                if ui.checkbox(&mut self.log_y, "Log Y").changed() {
+                    self.reset_axis = true;
+                }
+            });
+            if ui
+                .checkbox(&mut self.decade_bounds, "Decade Bounds")
+                .on_hover_text("Reset a log y axis to whole decades around the data")
+                .changed()
+            {
+                self.reset_axis = self.log_y;
+            }
+
+            ui.separator();
+
+            egui::Grid::new(format!("{}_axes", id))
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("X Label");
+                    ui.text_edit_singleline(&mut self.x_label);
+                    ui.end_row();
+
+                    ui.label("Y Label");
+                    ui.text_edit_singleline(&mut self.y_label);
+                    ui.end_row();
+
+                    ui.label("X Ticks");
+                    self.x_ticks.ui(ui, &format!("{}_x_ticks", id));
+                    ui.end_row();
+
+                    ui.label("Y Ticks");
+                    self.y_ticks.ui(ui, &format!("{}_y_ticks", id));
+                    ui.end_row();
+                });
+
+            ui.separator();
+
+            ui.checkbox(&mut self.show_grid, "Show Grid");
+            ui.add_enabled(
+                self.show_grid,
+                egui::Checkbox::new(&mut self.sharp_grid_lines, "Sharp Grid Lines"),
+            );
+            ui.add_enabled(
+                self.show_grid,
+                egui::Checkbox::new(&mut self.clamp_grid, "Clamp Grid"),
+            )
+            .on_hover_text("Only draw the grid where there is data");
+
+            ui.separator();
+
+            if ui.button("Reset Axis").clicked() {
+                self.reset_axis = true;
+            }
+        });
+    }
+
+    // Numeric entry of the plot bounds, for exact and reproducible views
+    pub fn bounds_ui(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Axis Bounds", |ui| {
+            egui::Grid::new("axis_bounds")
+                .num_columns(3)
+                .show(ui, |ui| {
+                    ui.label("X");
+                    ui.add(
+                        egui::DragValue::new(&mut self.x_bounds.0)
+                            .speed(1.0)
+                            .prefix("min: "),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut self.x_bounds.1)
+                            .speed(1.0)
+                            .prefix("max: "),
+                    );
+                    ui.end_row();
+
+                    ui.label("Y");
+                    ui.add(
+                        egui::DragValue::new(&mut self.y_bounds.0)
+                            .speed(1.0)
+                            .prefix("min: "),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut self.y_bounds.1)
+                            .speed(1.0)
+                            .prefix("max: "),
+                    );
+                    ui.end_row();
+                });
+
+            ui.horizontal(|ui| {
+                if ui.button("Apply").clicked() {
+                    self.apply_bounds = true;
+                }
+
+                if ui
+                    .button("Use Current View")
+                    .on_hover_text("Copy the bounds currently shown in the plot")
+                    .clicked()
+                {
+                    if let Some(bounds) = &self.current_bounds {
+                        let from_plot = |value: f64, log: bool, transform: AxisTransform| {
+                            if log {
+                                10.0f64.powf(value)
+                            } else {
+                                transform.inverse(value)
+                            }
+                        };
+                        self.x_bounds = (
+                            from_plot(bounds.min()[0], self.log_x, AxisTransform::Linear),
+                            from_plot(bounds.max()[0], self.log_x, AxisTransform::Linear),
+                        );
+                        self.y_bounds = (
+                            from_plot(bounds.min()[1], self.log_y, self.y_transform),
+                            from_plot(bounds.max()[1], self.log_y, self.y_transform),
+                        );
+                    }
+                }
+            });
+
+            if ui
+                .checkbox(&mut self.lock_bounds, "Lock")
+                .on_hover_text("Keep these bounds, zooming and dragging are disabled")
+                .changed()
+            {
+                self.apply_bounds = self.lock_bounds;
+            }
+        });
+    }
+
+    fn manual_bounds(&self) -> egui_plot::PlotBounds {
+        let to_plot = |value: f64, log: bool, transform: AxisTransform| {
+            if log {
+                value.max(1e-10).log10()
+            } else {
+                transform.forward(value)
+            }
+        };
+        egui_plot::PlotBounds::from_min_max(
+            [
+                to_plot(self.x_bounds.0, self.log_x, AxisTransform::Linear),
+                to_plot(self.y_bounds.0, self.log_y, self.y_transform),
+            ],
+            [
+                to_plot(self.x_bounds.1, self.log_x, AxisTransform::Linear),
+                to_plot(self.y_bounds.1, self.log_y, self.y_transform),
+            ],
+        )
+    }
+
+    // Sets the manual bounds when applied or locked, returns true if the bounds were set
+    pub fn update_bounds(&mut self, plot_ui: &mut egui_plot::PlotUi) -> bool {
+        self.current_bounds = Some(plot_ui.plot_bounds());
+
+        if self.apply_bounds || self.lock_bounds {
+            self.apply_bounds = false;
+            self.reset_axis = false;
+            plot_ui.set_plot_bounds(self.manual_bounds());
+            return true;
+        }
+
+        false
+    }
+
     // some function i can call that adds the settings to the plot
     pub fn apply_to_plot<'a>(&mut self, plot: egui_plot::Plot<'a>) -> egui_plot::Plot<'a> {
         let log_x = self.log_x;
         let log_y = self.log_y;
+        let x_transform = self.x_transform;
+        let y_transform = self.y_transform;
+        let x_calibration = self.x_calibration;
+        let x_label = move |x: f64| {
+            let x = match x_calibration {
+                Some([a, b, c]) => (a * x + b) * x + c,
+                None => x,
+            };
+            x_transform.forward(x)
+        };
+        let x_relabeled = !x_transform.is_linear() || x_calibration.is_some();
 
         let plot = plot
             .show_x(self.show_x_value)
             .show_y(self.show_y_value)
             .center_x_axis(self.center_x_axis)
             .center_y_axis(self.center_y_axis)
-            .allow_zoom(self.allow_zoom)
-            .allow_boxed_zoom(self.allow_boxed_zoom)
-            .allow_drag(self.allow_drag)
-            .allow_scroll(self.allow_scroll)
+            .allow_zoom(self.allow_zoom && !self.lock_bounds)
+            .allow_boxed_zoom(self.allow_boxed_zoom && !self.lock_bounds)
+            .allow_drag(self.allow_drag && !self.lock_bounds)
+            .allow_scroll(self.allow_scroll && !self.lock_bounds)
             .clamp_grid(self.clamp_grid)
             .show_grid(self.show_grid)
             .sharp_grid_lines(self.sharp_grid_lines)
             .show_background(self.show_background)
             .auto_bounds(egui::Vec2b::new(true, true))
-            .allow_double_click_reset(self.allow_double_click_reset)
+            .allow_double_click_reset(self.allow_double_click_reset && !self.lock_bounds)
             .x_axis_label(self.x_label.clone())
             .y_axis_label(self.y_label.clone())
             .label_formatter(move |name, value| {
                 let x = if log_x {
-                    10.0f64.powf(value.x)
+                    x_label(10.0f64.powf(value.x))
                 } else {
-                    value.x
+                    x_label(value.x)
                 };
                 let y = if log_y {
                     10.0f64.powf(value.y)
                 } else {
-                    value.y
+                    y_transform.inverse(value.y)
                 };
                 if !name.is_empty() {
                     format!("{name}: {x:.2}, {y:.2}")
@@ -136,16 +354,44 @@ impl EguiPlotSettings {
         };
 
         let max_size = 4;
+        let x_ticks = self.x_ticks;
+        let y_ticks = self.y_ticks;
         let plot = if log_x {
             plot.x_grid_spacer(log_axis_spacer)
-                .x_axis_formatter(move |gm, bounds| log_axis_formatter(gm, bounds, max_size))
+                .x_axis_formatter(move |gm, bounds| {
+                    x_ticks
+                        .format(10.0f64.powf(gm.value))
+                        .unwrap_or_else(|| log_axis_formatter(gm, bounds, max_size))
+                })
+        } else if x_relabeled {
+            plot.x_axis_formatter(move |gm, _| {
+                let value = x_label(gm.value);
+                x_ticks
+                    .format(value)
+                    .unwrap_or_else(|| transformed_axis_formatter(value))
+            })
+        } else if !x_ticks.is_auto() {
+            plot.x_axis_formatter(move |gm, _| x_ticks.format(gm.value).unwrap_or_default())
         } else {
             plot
         };
 
         let plot = if log_y {
             plot.y_grid_spacer(log_axis_spacer)
-                .y_axis_formatter(move |gm, bounds| log_axis_formatter(gm, bounds, max_size))
+                .y_axis_formatter(move |gm, bounds| {
+                    y_ticks
+                        .format(10.0f64.powf(gm.value))
+                        .unwrap_or_else(|| log_axis_formatter(gm, bounds, max_size))
+                })
+        } else if !y_transform.is_linear() {
+            plot.y_axis_formatter(move |gm, _| {
+                let value = y_transform.inverse(gm.value);
+                y_ticks
+                    .format(value)
+                    .unwrap_or_else(|| transformed_axis_formatter(value))
+            })
+        } else if !y_ticks.is_auto() {
+            plot.y_axis_formatter(move |gm, _| y_ticks.format(gm.value).unwrap_or_default())
         } else {
             plot
         };
@@ -153,10 +399,58 @@ impl EguiPlotSettings {
         plot
     }
 
+    // Per pane axis transforms, returns true when one changed
+    pub fn transform_ui(&mut self, ui: &mut egui::Ui, id: &str) -> bool {
+        let mut changed = false;
+
+        ui.menu_button("Axis Transforms", |ui| {
+            ui.label("X (labels and cursor)");
+            changed |= self.x_transform.ui(
+                ui,
+                &format!("{}_x_transform", id),
+                &[
+                    AxisTransform::Linear,
+                    AxisTransform::Sqrt,
+                    AxisTransform::Reciprocal {
+                        scale: 1.0,
+                        offset: 0.0,
+                        power: 1,
+                    },
+                ],
+            );
+
+            ui.separator();
+
+            ui.label("Y (drawn values)");
+            ui.add_enabled_ui(!self.log_y, |ui| {
+                changed |= self.y_transform.ui(
+                    ui,
+                    &format!("{}_y_transform", id),
+                    &[
+                        AxisTransform::Linear,
+                        AxisTransform::Sqrt,
+                        AxisTransform::Logit,
+                    ],
+                );
+            })
+            .response
+            .on_disabled_hover_text("Turn off log y to use a transform");
+        });
+
+        if changed {
+            self.reset_axis = true;
+        }
+        changed
+    }
+
     pub fn reset_axis_lims(&mut self, plot_ui: &mut egui_plot::PlotUi) {
         if self.reset_axis {
             self.reset_axis = false;
-            plot_ui.set_auto_bounds(egui::Vec2b::new(true, true));
+            if self.lock_bounds {
+                plot_ui.set_plot_bounds(self.manual_bounds());
+            } else {
+                plot_ui.set_auto_bounds(egui::Vec2b::new(true, true));
+            }
         }
     }
 }
@@ -203,3 +497,14 @@ fn log_axis_formatter(
         format!("{value:.precision$e}")
     }
 }
+
+fn transformed_axis_formatter(value: f64) -> String {
+    if !value.is_finite() {
+        String::new()
+    } else if value != 0.0 && (value.abs() >= 1e5 || value.abs() < 1e-2) {
+        format!("{value:.2e}")
+    } else {
+        let text = format!("{value:.3}");
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+}
@@ -1,5 +1,5 @@
 use egui::{Color32, DragValue, Id, Slider, Stroke, Ui};
-use egui_plot::{LineStyle, PlotResponse, PlotUi, Polygon};
+use egui_plot::{LineStyle, PlotPoint, PlotResponse, PlotUi, Polygon};
 use geo::Contains;
 
 use crate::egui_plot_stuff::colors::{Rgb, COLOR_OPTIONS};
@@ -17,19 +17,27 @@ pub struct EguiPolygon {
     pub style: Option<LineStyle>,
     pub style_length: f32,
     pub vertices: Vec<[f64; 2]>,
+    #[serde(default)]
+    pub parts: Vec<Vec<[f64; 2]>>, // more polygons of the same cut, combined with OR
     // Use Rgb struct for custom RGB values
     pub color_rgb: Rgb,
     pub stroke_rgb: Rgb,
 
     pub interactive_clicking: bool,
     pub interactive_dragging: bool,
+    #[serde(default)]
+    pub freehand: bool, // new polygons are drawn by dragging instead of clicking
 
     #[serde(skip)]
     temp_vertex: Option<Vec<[f64; 2]>>,
     #[serde(skip)]
     pub is_dragging: bool,
     #[serde(skip)]
-    dragged_vertex_index: Option<usize>,
+    dragged_vertex_index: Option<(usize, usize)>, // polygon, vertex
+    #[serde(skip)]
+    active_part: usize, // polygon that clicked vertices are added to, 0 is `vertices`
+    #[serde(skip)]
+    lasso: Option<egui::Pos2>, // screen position of the last freehand vertex while drawing
 }
 
 impl Default for EguiPolygon {
@@ -45,14 +53,18 @@ impl Default for EguiPolygon {
             style: Some(LineStyle::Solid),
             style_length: 15.0,
             vertices: vec![],
+            parts: vec![],
             color_rgb: Rgb::from_color32(Color32::RED),
             stroke_rgb: Rgb::from_color32(Color32::RED),
 
             interactive_clicking: false,
             interactive_dragging: true,
+            freehand: false,
             temp_vertex: None,
             is_dragging: false,
             dragged_vertex_index: None,
+            active_part: 0,
+            lasso: None,
         }
     }
 }
@@ -66,25 +78,152 @@ impl EguiPolygon {
         }
     }
 
-    fn to_geo_polygon(&self) -> geo::Polygon<f64> {
-        let exterior_coords: Vec<_> = self.vertices.iter().map(|&[x, y]| (x, y)).collect();
-        let exterior_line_string = geo::LineString::from(exterior_coords);
-        geo::Polygon::new(exterior_line_string, vec![])
+    // Every polygon of the shape, `vertices` first
+    pub fn polygons(&self) -> impl Iterator<Item = &Vec<[f64; 2]>> {
+        std::iter::once(&self.vertices).chain(&self.parts)
+    }
+
+    fn polygon_mut(&mut self, part: usize) -> Option<&mut Vec<[f64; 2]>> {
+        if part == 0 {
+            Some(&mut self.vertices)
+        } else {
+            self.parts.get_mut(part - 1)
+        }
+    }
+
+    pub fn vertices_mut(&mut self) -> impl Iterator<Item = &mut [f64; 2]> {
+        self.vertices
+            .iter_mut()
+            .chain(self.parts.iter_mut().flatten())
+    }
+
+    pub fn to_geo(&self) -> geo::MultiPolygon<f64> {
+        geo::MultiPolygon::new(
+            self.polygons()
+                .filter(|vertices| vertices.len() >= 3)
+                .map(|vertices| {
+                    let exterior: Vec<_> = vertices.iter().map(|&[x, y]| (x, y)).collect();
+                    geo::Polygon::new(geo::LineString::from(exterior), vec![])
+                })
+                .collect(),
+        )
     }
 
     pub fn is_inside(&self, x: f64, y: f64) -> bool {
-        let point = geo::Point::new(x, y);
-        let polygon = self.to_geo_polygon();
-        polygon.contains(&point)
+        self.to_geo().contains(&geo::Point::new(x, y))
+    }
+
+    // Freehand drawing, or clicking to add vertices, is in progress
+    pub fn is_drawing(&self) -> bool {
+        self.interactive_clicking && self.draw
+    }
+
+    // Starts another polygon of the shape, drawn like the first
+    pub fn add_polygon(&mut self) {
+        if self.vertices.is_empty() {
+            self.active_part = 0;
+        } else {
+            self.parts.retain(|part| !part.is_empty());
+            self.parts.push(Vec::new());
+            self.active_part = self.parts.len();
+        }
+        self.interactive_clicking = true;
+    }
+
+    pub fn remove_vertex(&mut self, part: usize, index: usize) {
+        if let Some(vertices) = self.polygon_mut(part) {
+            if index < vertices.len() {
+                vertices.remove(index);
+            }
+        }
+        // an emptied extra polygon is dropped, the first one moves up when it is emptied
+        if part > 0 && self.parts.get(part - 1).is_some_and(|part| part.is_empty()) {
+            self.parts.remove(part - 1);
+        } else if part == 0 && self.vertices.is_empty() && !self.parts.is_empty() {
+            self.vertices = self.parts.remove(0);
+        }
+        self.active_part = self.active_part.min(self.parts.len());
+    }
+
+    // Closest vertex to a screen position as (polygon, vertex, distance in points)
+    fn closest_vertex(
+        &self,
+        transform: &egui_plot::PlotTransform,
+        pos: egui::Pos2,
+    ) -> Option<(usize, usize, f32)> {
+        self.polygons()
+            .enumerate()
+            .flat_map(|(part, vertices)| {
+                vertices.iter().enumerate().map(move |(index, &[x, y])| {
+                    let screen = transform.position_from_point(&PlotPoint::new(x, y));
+                    (part, index, screen.distance(pos))
+                })
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+    }
+
+    // Closest edge to a screen position as (polygon, index to insert at, distance in points)
+    fn closest_edge(
+        &self,
+        transform: &egui_plot::PlotTransform,
+        pos: egui::Pos2,
+    ) -> Option<(usize, usize, f32)> {
+        self.polygons()
+            .enumerate()
+            .filter(|(_, vertices)| vertices.len() >= 2)
+            .flat_map(|(part, vertices)| {
+                let screen: Vec<egui::Pos2> = vertices
+                    .iter()
+                    .map(|&[x, y]| transform.position_from_point(&PlotPoint::new(x, y)))
+                    .collect();
+                (0..screen.len()).map(move |index| {
+                    let (a, b) = (screen[index], screen[(index + 1) % screen.len()]);
+                    let t = ((pos - a).dot(b - a) / (b - a).length_sq().max(f32::EPSILON))
+                        .clamp(0.0, 1.0);
+                    (part, index + 1, (a + t * (b - a)).distance(pos))
+                })
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+    }
+
+    // Freehand drawing: press and drag to trace the polygon, it is finished on release
+    fn lasso(&mut self, plot_response: &PlotResponse<()>, pos: egui::Pos2, value: [f64; 2]) {
+        const SPACING: f32 = 4.0; // points between traced vertices
+
+        let response = &plot_response.response;
+        if response.drag_started_by(egui::PointerButton::Primary) {
+            if let Some(vertices) = self.polygon_mut(self.active_part) {
+                vertices.clear();
+                vertices.push(value);
+                self.lasso = Some(pos);
+            }
+        } else if let Some(last) = self.lasso {
+            if last.distance(pos) >= SPACING {
+                if let Some(vertices) = self.polygon_mut(self.active_part) {
+                    vertices.push(value);
+                }
+                self.lasso = Some(pos);
+            }
+            if response.drag_stopped() {
+                self.lasso = None;
+                self.interactive_clicking = false;
+            }
+        }
     }
 
     pub fn handle_interactions(&mut self, plot_response: &PlotResponse<()>) {
         let pointer_state = plot_response.response.ctx.input(|i| i.pointer.clone());
+        let modifiers = plot_response.response.ctx.input(|i| i.modifiers);
+        let transform = &plot_response.transform;
         if let Some(pointer_pos) = pointer_state.hover_pos() {
-            let x_value = plot_response.transform.value_from_position(pointer_pos).x;
-            let y_value = plot_response.transform.value_from_position(pointer_pos).y;
+            let x_value = transform.value_from_position(pointer_pos).x;
+            let y_value = transform.value_from_position(pointer_pos).y;
 
-            if self.interactive_clicking && self.draw {
+            if self.interactive_clicking && self.draw && self.freehand {
+                self.temp_vertex = None;
+                self.lasso(plot_response, pointer_pos, [x_value, y_value]);
+                return;
+            } else if self.interactive_clicking && self.draw {
                 self.temp_vertex = Some(vec![[x_value, y_value]]);
                 if plot_response.response.clicked() {
                     self.add_vertex(x_value, y_value);
@@ -98,32 +237,46 @@ impl EguiPolygon {
                 self.temp_vertex = None;
             }
 
+            // shift click inserts a vertex on the closest edge, alt click removes a vertex
+            const PICK_DISTANCE: f32 = 10.0;
+            if self.interactive_dragging
+                && self.draw
+                && !self.interactive_clicking
+                && plot_response.response.clicked()
+            {
+                if modifiers.shift {
+                    if let Some((part, index, distance)) = self.closest_edge(transform, pointer_pos)
+                    {
+                        if distance <= PICK_DISTANCE {
+                            if let Some(vertices) = self.polygon_mut(part) {
+                                vertices.insert(index, [x_value, y_value]);
+                            }
+                        }
+                    }
+                } else if modifiers.alt {
+                    if let Some((part, index, distance)) =
+                        self.closest_vertex(transform, pointer_pos)
+                    {
+                        if distance <= PICK_DISTANCE {
+                            self.remove_vertex(part, index);
+                        }
+                    }
+                }
+            }
+
             if self.interactive_dragging && self.draw {
                 if let Some(hovered_id) = plot_response.hovered_plot_item {
                     if hovered_id == Id::new(self.name.clone()) {
                         self.highlighted = true;
 
-                        //find the closest vertex to the pointer
-                        let closest_index = self
-                            .vertices
-                            .iter()
-                            .enumerate()
-                            .min_by(|(_, a), (_, b)| {
-                                let dist_a = (a[0] - x_value).powi(2) + (a[1] - y_value).powi(2);
-                                let dist_b = (b[0] - x_value).powi(2) + (b[1] - y_value).powi(2);
-                                dist_a.partial_cmp(&dist_b).unwrap()
-                            })
-                            .map(|(index, _)| index);
-
-                        log::info!(
-                            "Closest index: {:?}, (x,y)={:?}",
-                            closest_index,
-                            self.vertices[closest_index.unwrap()]
-                        );
-
-                        if pointer_state.button_pressed(egui::PointerButton::Primary) {
+                        if pointer_state.button_pressed(egui::PointerButton::Primary)
+                            && !modifiers.shift
+                            && !modifiers.alt
+                        {
                             self.is_dragging = true;
-                            self.dragged_vertex_index = closest_index;
+                            self.dragged_vertex_index = self
+                                .closest_vertex(transform, pointer_pos)
+                                .map(|(part, index, _)| (part, index));
                         }
                     } else {
                         self.highlighted = false;
@@ -131,8 +284,13 @@ impl EguiPolygon {
                 }
 
                 if self.is_dragging {
-                    if let Some(index) = self.dragged_vertex_index {
-                        self.vertices[index] = [x_value, y_value];
+                    if let Some((part, index)) = self.dragged_vertex_index {
+                        if let Some(vertex) = self
+                            .polygon_mut(part)
+                            .and_then(|vertices| vertices.get_mut(index))
+                        {
+                            *vertex = [x_value, y_value];
+                        }
                     }
                     if pointer_state.button_released(egui::PointerButton::Primary) {
                         self.is_dragging = false;
@@ -142,15 +300,24 @@ impl EguiPolygon {
             }
         } else if pointer_state.button_released(egui::PointerButton::Primary) {
             self.is_dragging = false;
+            if self.lasso.take().is_some() {
+                self.interactive_clicking = false;
+            }
         }
     }
 
     pub fn add_vertex(&mut self, x: f64, y: f64) {
-        self.vertices.push([x, y]);
+        let part = self.active_part;
+        match self.polygon_mut(part) {
+            Some(vertices) => vertices.push([x, y]),
+            None => self.vertices.push([x, y]),
+        }
     }
 
     pub fn clear_vertices(&mut self) {
         self.vertices.clear();
+        self.parts.clear();
+        self.active_part = 0;
     }
 
     pub fn draw(&mut self, plot_ui: &mut PlotUi) {
@@ -164,26 +331,30 @@ impl EguiPolygon {
                 plot_ui.points(temp_vertex_points);
             }
 
-            let mut polygon = Polygon::new(self.vertices.clone())
-                .highlight(self.highlighted)
-                .stroke(self.stroke)
-                .width(self.width)
-                .fill_color(Color32::TRANSPARENT)
-                .id(Id::new(self.name.clone()));
+            for (part, vertices) in self.polygons().enumerate() {
+                let mut polygon = Polygon::new(vertices.clone())
+                    .highlight(self.highlighted)
+                    .stroke(self.stroke)
+                    .width(self.width)
+                    .fill_color(Color32::TRANSPARENT)
+                    .id(Id::new(self.name.clone()));
+
+                // one legend entry for all polygons
+                if self.name_in_legend && part == 0 {
+                    polygon = polygon.name(self.name.clone());
+                }
 
-            if self.name_in_legend {
-                polygon = polygon.name(self.name.clone());
-            }
+                if self.style.is_some() {
+                    polygon = polygon.style(self.style.unwrap());
+                }
 
-            if self.style.is_some() {
-                polygon = polygon.style(self.style.unwrap());
+                plot_ui.polygon(polygon);
             }
 
-            plot_ui.polygon(polygon);
-
             // if the user can drag the vertices, draw the vertices
             if self.interactive_dragging {
-                let vertices_points = egui_plot::Points::new(self.vertices.clone())
+                let vertices: Vec<[f64; 2]> = self.polygons().flatten().copied().collect();
+                let vertices_points = egui_plot::Points::new(vertices)
                     .radius(5.0)
                     .color(self.stroke.color)
                     .id(Id::new(self.name.clone()))
@@ -206,7 +377,21 @@ impl EguiPolygon {
                 ui.checkbox(
                     &mut self.interactive_dragging,
                     "Interactive Dragging Vertices",
+                )
+                .on_hover_text(
+                    "Shift click inserts a vertex on the closest edge, Alt click removes a vertex",
                 );
+                ui.checkbox(&mut self.freehand, "Freehand")
+                    .on_hover_text("Draw new polygons by pressing and dragging around the region");
+                if ui
+                    .button("Add Polygon")
+                    .on_hover_text(
+                        "Draw another polygon, a point inside any of them is inside the cut",
+                    )
+                    .clicked()
+                {
+                    self.add_polygon();
+                }
                 ui.checkbox(&mut self.name_in_legend, "Name in Legend")
                     .on_hover_text("Show in legend");
                 ui.checkbox(&mut self.highlighted, "Highlighted");
@@ -252,12 +437,25 @@ impl EguiPolygon {
         ui.menu_button(self.name.to_string(), |ui| {
             ui.text_edit_singleline(&mut self.name);
 
-            ui.label("Vertices (X,Y)");
-            for (index, vertex) in self.vertices.iter().enumerate() {
-                ui.horizontal(|ui| {
-                    ui.label(format!("Vertex {}", index));
-                    ui.label(format!("({:.2}, {:.2})", vertex[0], vertex[1]));
-                });
+            let mut remove = None;
+            let count = 1 + self.parts.len();
+            for (part, vertices) in self.polygons().enumerate() {
+                if count > 1 {
+                    ui.label(format!("Polygon {}", part));
+                }
+                ui.label("Vertices (X,Y)");
+                for (index, vertex) in vertices.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Vertex {}", index));
+                        ui.label(format!("({:.2}, {:.2})", vertex[0], vertex[1]));
+                        if ui.small_button("🗙").clicked() {
+                            remove = Some((part, index));
+                        }
+                    });
+                }
+            }
+            if let Some((part, index)) = remove {
+                self.remove_vertex(part, index);
             }
         });
     }
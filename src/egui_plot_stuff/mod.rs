@@ -1,3 +1,4 @@
+pub mod axis_transform;
 pub mod colors;
 pub mod egui_horizontal_line;
 pub mod egui_image;
@@ -5,3 +6,4 @@ pub mod egui_line;
 pub mod egui_plot_settings;
 pub mod egui_polygon;
 pub mod egui_vertical_line;
+pub mod tick_format;
@@ -0,0 +1,75 @@
+// Format of the tick labels of an axis. Auto keeps the labels egui_plot, or the log and
+// transform formatters, would draw.
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum TickFormat {
+    #[default]
+    Auto,
+    Integer,
+    Fixed {
+        decimals: usize,
+    },
+    Scientific {
+        decimals: usize,
+    },
+}
+
+impl TickFormat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TickFormat::Auto => "Auto",
+            TickFormat::Integer => "Integer",
+            TickFormat::Fixed { .. } => "Fixed",
+            TickFormat::Scientific { .. } => "Scientific",
+        }
+    }
+
+    pub fn is_auto(&self) -> bool {
+        *self == TickFormat::Auto
+    }
+
+    // None for Auto
+    pub fn format(&self, value: f64) -> Option<String> {
+        if !value.is_finite() {
+            return Some(String::new());
+        }
+        match *self {
+            TickFormat::Auto => None,
+            TickFormat::Integer => Some(format!("{:.0}", value)),
+            TickFormat::Fixed { decimals } => Some(format!("{:.*}", decimals, value)),
+            TickFormat::Scientific { decimals } => Some(format!("{:.*e}", decimals, value)),
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, id: &str) -> bool {
+        let before = *self;
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt(id)
+                .selected_text(self.label())
+                .show_ui(ui, |ui| {
+                    for choice in [
+                        TickFormat::Auto,
+                        TickFormat::Integer,
+                        TickFormat::Fixed { decimals: 2 },
+                        TickFormat::Scientific { decimals: 1 },
+                    ] {
+                        let selected =
+                            std::mem::discriminant(self) == std::mem::discriminant(&choice);
+                        if ui.selectable_label(selected, choice.label()).clicked() && !selected {
+                            *self = choice;
+                        }
+                    }
+                });
+
+            if let TickFormat::Fixed { decimals } | TickFormat::Scientific { decimals } = self {
+                ui.add(
+                    egui::DragValue::new(decimals)
+                        .range(0..=8)
+                        .suffix(" decimals"),
+                );
+            }
+        });
+
+        *self != before
+    }
+}
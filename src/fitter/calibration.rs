@@ -0,0 +1,216 @@
+use super::common::weighted_polyfit;
+
+// Quadratic energy calibration of a histogram axis: E = a x^2 + b x + c.
+// Only the displayed values are converted, the bins and fits stay in channels.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Calibration {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub unit: String,
+    pub active: bool,
+    #[serde(default)]
+    pub quadratic: bool, // order of the fit to the assigned peak energies
+    #[serde(skip)]
+    pub last_fit: Option<CalibrationFit>,
+}
+
+// Result of a fit to (centroid, energy) pairs, applied with `Calibration::apply_fit`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationFit {
+    pub coefficients: [f64; 3],   // a, b, c
+    pub residuals: Vec<[f64; 3]>, // centroid, fit - assigned energy, uncertainty
+    pub chi2_ndf: f64,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration {
+            a: 0.0,
+            b: 1.0,
+            c: 0.0,
+            unit: "keV".to_string(),
+            active: false,
+            quadratic: false,
+            last_fit: None,
+        }
+    }
+}
+
+impl Calibration {
+    pub fn coefficients(&self) -> [f64; 3] {
+        [self.a, self.b, self.c]
+    }
+
+    pub fn calibrate(&self, x: f64) -> f64 {
+        (self.a * x + self.b) * x + self.c
+    }
+
+    // Uncalibrated x of a calibrated value, the root of the quadratic nearest to the
+    // linear solution
+    pub fn uncalibrate(&self, value: f64) -> Option<f64> {
+        if self.a == 0.0 {
+            return (self.b != 0.0).then(|| (value - self.c) / self.b);
+        }
+
+        let discriminant = self.b * self.b - 4.0 * self.a * (self.c - value);
+        if discriminant < 0.0 {
+            return None;
+        }
+        let roots = [
+            (-self.b + discriminant.sqrt()) / (2.0 * self.a),
+            (-self.b - discriminant.sqrt()) / (2.0 * self.a),
+        ];
+        let linear = if self.b == 0.0 {
+            0.0
+        } else {
+            (value - self.c) / self.b
+        };
+        roots
+            .into_iter()
+            .min_by(|x, y| (x - linear).abs().total_cmp(&(y - linear).abs()))
+    }
+
+    // dE/dx, used to convert widths and uncertainties
+    pub fn slope(&self, x: f64) -> f64 {
+        2.0 * self.a * x + self.b
+    }
+
+    // Converts a width like the FWHM of a peak at x
+    pub fn width(&self, x: f64, width: f64) -> f64 {
+        self.slope(x).abs() * width
+    }
+
+    // Weighted fit to (centroid, centroid uncertainty, energy, energy uncertainty) points.
+    // The centroid uncertainty is carried into energy with the slope of an unweighted first pass.
+    pub fn fit(&mut self, points: &[[f64; 4]]) -> Result<(), String> {
+        let order = if self.quadratic { 2 } else { 1 };
+        if points.len() <= order {
+            return Err(format!(
+                "Need at least {} peaks with an assigned energy for a {} calibration",
+                order + 1,
+                if self.quadratic {
+                    "quadratic"
+                } else {
+                    "linear"
+                }
+            ));
+        }
+
+        let unweighted: Vec<[f64; 3]> = points.iter().map(|p| [p[0], p[2], 1.0]).collect();
+        let first = weighted_polyfit(&unweighted, order).ok_or("Calibration fit is singular")?;
+        let slope = |x: f64| first[1] + if order == 2 { 2.0 * first[2] * x } else { 0.0 };
+
+        let sigma = |p: &[f64; 4]| (p[3].powi(2) + (slope(p[0]) * p[1]).powi(2)).sqrt();
+        let weighted: Vec<[f64; 3]> = points
+            .iter()
+            .map(|p| {
+                let sigma = sigma(p);
+                [
+                    p[0],
+                    p[2],
+                    if sigma > 0.0 {
+                        1.0 / sigma.powi(2)
+                    } else {
+                        1.0
+                    },
+                ]
+            })
+            .collect();
+        let coefficients =
+            weighted_polyfit(&weighted, order).ok_or("Calibration fit is singular")?;
+        let coefficients = [
+            coefficients.get(2).copied().unwrap_or(0.0),
+            coefficients[1],
+            coefficients[0],
+        ];
+
+        let calibrate = |x: f64| (coefficients[0] * x + coefficients[1]) * x + coefficients[2];
+        let residuals: Vec<[f64; 3]> = points
+            .iter()
+            .map(|p| [p[0], calibrate(p[0]) - p[2], sigma(p)])
+            .collect();
+        let chi2: f64 = residuals
+            .iter()
+            .zip(&weighted)
+            .map(|(residual, point)| residual[1].powi(2) * point[2])
+            .sum();
+
+        self.last_fit = Some(CalibrationFit {
+            coefficients,
+            residuals,
+            chi2_ndf: chi2 / (points.len() - order - 1).max(1) as f64,
+        });
+        Ok(())
+    }
+
+    // Uses the last fit as the calibration of the axis
+    pub fn apply_fit(&mut self) {
+        if let Some(fit) = &self.last_fit {
+            [self.a, self.b, self.c] = fit.coefficients;
+            self.active = true;
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Energy Calibration");
+        ui.label("E = a·x² + b·x + c");
+
+        egui::Grid::new("calibration_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("a");
+                ui.add(egui::DragValue::new(&mut self.a).speed(1e-6));
+                ui.end_row();
+
+                ui.label("b");
+                ui.add(egui::DragValue::new(&mut self.b).speed(1e-3));
+                ui.end_row();
+
+                ui.label("c");
+                ui.add(egui::DragValue::new(&mut self.c).speed(0.1));
+                ui.end_row();
+
+                ui.label("Unit");
+                ui.add(egui::TextEdit::singleline(&mut self.unit).desired_width(60.0));
+                ui.end_row();
+            });
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.active, "Show calibrated axis")
+                .on_hover_text("Axis labels, cursor, and fit results in calibrated units. The bins are not changed.");
+
+            if ui.button("Reset").clicked() {
+                *self = Calibration::default();
+            }
+        });
+    }
+
+    // Residuals of the last fit with their uncertainties
+    pub fn residuals_plot(&self, ui: &mut egui::Ui, id: &str) {
+        let Some(fit) = &self.last_fit else {
+            return;
+        };
+
+        egui_plot::Plot::new(id)
+            .height(150.0)
+            .width(300.0)
+            .x_axis_label("Centroid")
+            .y_axis_label(format!("Residual [{}]", self.unit))
+            .show(ui, |plot_ui| {
+                for &[x, residual, sigma] in &fit.residuals {
+                    plot_ui.line(
+                        egui_plot::Line::new(vec![[x, residual - sigma], [x, residual + sigma]])
+                            .color(egui::Color32::GRAY),
+                    );
+                }
+                let points: Vec<[f64; 2]> = fit
+                    .residuals
+                    .iter()
+                    .map(|&[x, residual, _]| [x, residual])
+                    .collect();
+                plot_ui.points(egui_plot::Points::new(points).radius(3.0));
+                plot_ui.hline(egui_plot::HLine::new(0.0).color(egui::Color32::DARK_GRAY));
+            });
+    }
+}
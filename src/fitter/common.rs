@@ -7,7 +7,9 @@ pub struct Data {
 #[derive(PartialEq, Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct Parameter {
     pub name: String,
+    #[serde(deserialize_with = "lower_bound")]
     pub min: f64,
+    #[serde(deserialize_with = "upper_bound")]
     pub max: f64,
     pub initial_guess: f64,
     pub vary: bool,
@@ -15,6 +17,30 @@ pub struct Parameter {
     pub uncertainty: Option<f64>,
 }
 
+// JSON has no infinity, serde_json writes an unbounded limit as null
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Bound {
+    Value(f64),
+    Unbounded(()),
+}
+
+fn lower_bound<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    use serde::Deserialize;
+    Ok(match Bound::deserialize(deserializer)? {
+        Bound::Value(value) => value,
+        Bound::Unbounded(()) => f64::NEG_INFINITY,
+    })
+}
+
+fn upper_bound<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    use serde::Deserialize;
+    Ok(match Bound::deserialize(deserializer)? {
+        Bound::Value(value) => value,
+        Bound::Unbounded(()) => f64::INFINITY,
+    })
+}
+
 impl Default for Parameter {
     fn default() -> Self {
         Parameter {
@@ -66,3 +92,54 @@ impl Parameter {
         }
     }
 }
+
+// Weighted least squares polynomial of the given order through (x, y, weight) points,
+// returns the coefficients a_0 ... a_order or None when the system is singular
+pub fn weighted_polyfit(points: &[[f64; 3]], order: usize) -> Option<Vec<f64>> {
+    let terms = order + 1;
+    if points.len() < terms {
+        return None;
+    }
+
+    // normal equations A^T W A c = A^T W y
+    let mut matrix = vec![vec![0.0; terms + 1]; terms];
+    for &[x, y, weight] in points {
+        let powers: Vec<f64> = (0..terms).map(|i| x.powi(i as i32)).collect();
+        for row in 0..terms {
+            for column in 0..terms {
+                matrix[row][column] += weight * powers[row] * powers[column];
+            }
+            matrix[row][terms] += weight * powers[row] * y;
+        }
+    }
+
+    solve(matrix)
+}
+
+// Gauss-Jordan elimination with partial pivoting on an augmented matrix
+fn solve(mut matrix: Vec<Vec<f64>>) -> Option<Vec<f64>> {
+    let n = matrix.len();
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+        if matrix[pivot][column].abs() < 1e-300 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+
+        for row in 0..n {
+            if row != column {
+                let factor = matrix[row][column] / matrix[column][column];
+                for k in column..=n {
+                    matrix[row][k] -= factor * matrix[column][k];
+                }
+            }
+        }
+    }
+
+    Some(
+        (0..n)
+            .map(|row| matrix[row][n] / matrix[row][row])
+            .collect(),
+    )
+}
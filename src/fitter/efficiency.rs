@@ -0,0 +1,195 @@
+// Detector efficiency vs energy, fit as a polynomial in log-log space:
+//     ln(eff) = sum_i a_i * ln(E / E0)^i
+// to measured (energy, efficiency, uncertainty) points, the usual form for HPGe detectors.
+
+use super::common::weighted_polyfit;
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct EfficiencyCurve {
+    pub points: Vec<[f64; 3]>, // energy, efficiency, uncertainty
+    pub order: usize,
+    pub e0: f64,                // reference energy
+    pub coefficients: Vec<f64>, // a_0 ... a_order, empty until fit
+    pub relative: bool,         // normalize to the largest efficiency of the points
+    pub show_corrected: bool,   // draw the efficiency corrected spectrum over the histogram
+}
+
+impl Default for EfficiencyCurve {
+    fn default() -> Self {
+        EfficiencyCurve {
+            points: Vec::new(),
+            order: 3,
+            e0: 1000.0,
+            coefficients: Vec::new(),
+            relative: true,
+            show_corrected: false,
+        }
+    }
+}
+
+impl EfficiencyCurve {
+    pub fn is_fit(&self) -> bool {
+        !self.coefficients.is_empty()
+    }
+
+    fn absolute(&self, energy: f64) -> Option<f64> {
+        if !self.is_fit() || energy <= 0.0 {
+            return None;
+        }
+
+        let x = (energy / self.e0).ln();
+        let ln_eff = self
+            .coefficients
+            .iter()
+            .rev()
+            .fold(0.0, |sum, coefficient| sum * x + coefficient);
+        Some(ln_eff.exp())
+    }
+
+    // Efficiency at an energy, relative to the largest efficiency of the points when `relative` is set
+    pub fn evaluate(&self, energy: f64) -> Option<f64> {
+        let efficiency = self.absolute(energy)?;
+        if !self.relative {
+            return Some(efficiency);
+        }
+
+        let max = self
+            .points
+            .iter()
+            .filter_map(|point| self.absolute(point[0]))
+            .fold(0.0, f64::max);
+        (max > 0.0).then(|| efficiency / max)
+    }
+
+    // Weighted least squares in log space, the weight of a point is 1/σ_ln(eff)^2
+    pub fn fit(&mut self) -> Result<(), String> {
+        let points: Vec<&[f64; 3]> = self
+            .points
+            .iter()
+            .filter(|point| point[0] > 0.0 && point[1] > 0.0)
+            .collect();
+        let terms = self.order + 1;
+        if points.len() < terms {
+            return Err(format!(
+                "Need at least {} points with positive energy and efficiency for order {}",
+                terms, self.order
+            ));
+        }
+
+        let weighted: Vec<[f64; 3]> = points
+            .iter()
+            .map(|point| {
+                let sigma = if point[2] > 0.0 {
+                    point[2] / point[1]
+                } else {
+                    1.0
+                };
+                [
+                    (point[0] / self.e0).ln(),
+                    point[1].ln(),
+                    1.0 / (sigma * sigma),
+                ]
+            })
+            .collect();
+
+        self.coefficients =
+            weighted_polyfit(&weighted, self.order).ok_or("Efficiency fit is singular")?;
+        Ok(())
+    }
+
+    // Reads "energy, efficiency[, uncertainty]" lines, other lines like a header are skipped
+    pub fn load_csv(&mut self, path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path)?;
+        let points: Vec<[f64; 3]> = text
+            .lines()
+            .filter_map(|line| {
+                let values: Vec<f64> = line
+                    .split(|c: char| c == ',' || c == '\t' || c == ';' || c.is_whitespace())
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.parse::<f64>())
+                    .collect::<Result<_, _>>()
+                    .ok()?;
+                match values[..] {
+                    [energy, efficiency] => Some([energy, efficiency, 0.0]),
+                    [energy, efficiency, uncertainty, ..] => {
+                        Some([energy, efficiency, uncertainty])
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+
+        if points.is_empty() {
+            return Err(format!("No efficiency points found in {}", path.display()).into());
+        }
+
+        self.points = points;
+        self.coefficients.clear();
+        Ok(())
+    }
+
+    pub fn menu_button(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Efficiency", |ui| {
+            ui.heading("Efficiency Curve");
+
+            if ui
+                .button("Load Points…")
+                .on_hover_text("CSV with energy, efficiency, and optionally the uncertainty")
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("CSV", &["csv", "txt", "dat"])
+                    .pick_file()
+                {
+                    match self.load_csv(&path) {
+                        Ok(_) => {
+                            if let Err(e) = self.fit() {
+                                log::error!("{}", e);
+                            }
+                        }
+                        Err(e) => log::error!("Error loading efficiency points: {}", e),
+                    }
+                }
+            }
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut self.order)
+                        .range(1..=6)
+                        .prefix("Order: "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.e0)
+                        .speed(10.0)
+                        .range(1.0..=f64::INFINITY)
+                        .prefix("E0: "),
+                );
+                if ui
+                    .add_enabled(!self.points.is_empty(), egui::Button::new("Fit"))
+                    .clicked()
+                {
+                    if let Err(e) = self.fit() {
+                        log::error!("{}", e);
+                    }
+                }
+            });
+
+            ui.label(format!("{} points", self.points.len()));
+            if self.is_fit() {
+                let coefficients: Vec<String> = self
+                    .coefficients
+                    .iter()
+                    .map(|coefficient| format!("{:.4e}", coefficient))
+                    .collect();
+                ui.label(format!("a = [{}]", coefficients.join(", ")));
+            }
+
+            ui.checkbox(&mut self.relative, "Relative efficiency")
+                .on_hover_text("Normalize to the largest efficiency of the points");
+            ui.add_enabled(
+                self.is_fit(),
+                egui::Checkbox::new(&mut self.show_corrected, "Show corrected spectrum"),
+            );
+        });
+    }
+}
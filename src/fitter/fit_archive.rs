@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use super::main_fitter::{FitResult, Fitter};
+
+// Every stored fit of one histogram in one JSON file, named after the histogram by default.
+// The fits keep their regions, backgrounds, peaks, and the lmfit ModelResult text of each fit
+// (what lmfit writes to a .sav file), so a fit archive can be loaded into another session or
+// the results reopened in Python.
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct FitArchive {
+    pub histogram: String,
+    pub fits: Vec<Fitter>,
+}
+
+impl FitArchive {
+    pub fn new(histogram: &str, fits: &[Fitter]) -> Self {
+        FitArchive {
+            histogram: histogram.to_string(),
+            fits: fits.to_vec(),
+        }
+    }
+
+    pub fn file_name(histogram: &str) -> String {
+        format!("{}.fits.json", histogram.replace('/', "_"))
+    }
+
+    // Fits with an embedded lmfit result
+    pub fn lmfit_results(&self) -> usize {
+        self.fits
+            .iter()
+            .filter(|fit| {
+                matches!(&fit.fit_result, Some(FitResult::Gaussian(result)) if !result.lmfit_result.is_empty())
+            })
+            .count()
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)?;
+        log::info!(
+            "Saved {} fits of '{}' to {}",
+            self.fits.len(),
+            self.histogram,
+            path.display()
+        );
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&text)?)
+    }
+}
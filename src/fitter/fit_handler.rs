@@ -3,14 +3,25 @@ use rfd::FileDialog;
 use std::fs::File;
 use std::io::{Read, Write};
 
+use super::calibration::Calibration;
+use super::fit_archive::FitArchive;
 use super::fit_settings::FitSettings;
-use super::main_fitter::Fitter;
+use super::main_fitter::{unit_label, Fitter};
+use super::regression::FitRegression;
+use crate::egui_plot_stuff::axis_transform::AxisTransform;
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct Fits {
     pub temp_fit: Option<Fitter>,
     pub stored_fits: Vec<Fitter>,
     pub settings: FitSettings,
+    #[serde(skip)]
+    pub regression: FitRegression,
+    #[serde(skip)]
+    pub calibration: Option<Calibration>, // set by the histogram when its axis is calibrated
+    #[serde(skip)]
+    pub removed: Vec<(usize, Fitter)>, // stored fits removed in the UI, collected for undo
 }
 
 impl Default for Fits {
@@ -26,6 +37,9 @@ impl Fits {
             // temp_background_fit: None,
             stored_fits: Vec::new(),
             settings: FitSettings::default(),
+            regression: FitRegression::default(),
+            calibration: None,
+            removed: Vec::new(),
         }
     }
 
@@ -51,6 +65,26 @@ impl Fits {
         }
     }
 
+    pub fn set_y_transform(&mut self, transform: AxisTransform) {
+        if let Some(temp_fit) = &mut self.temp_fit {
+            temp_fit.set_y_transform(transform);
+        }
+
+        for fit in &mut self.stored_fits {
+            fit.set_y_transform(transform);
+        }
+    }
+
+    pub fn update_fit_points(&mut self) {
+        if let Some(temp_fit) = &mut self.temp_fit {
+            temp_fit.update_fit_points(&self.settings);
+        }
+
+        for fit in &mut self.stored_fits {
+            fit.update_fit_points(&self.settings);
+        }
+    }
+
     pub fn set_stored_fits_background_color(&mut self, color: egui::Color32) {
         for fit in &mut self.stored_fits {
             fit.background_line.color = color;
@@ -89,46 +123,96 @@ impl Fits {
         self.update_visibility();
     }
 
-    fn save_to_file(&self) {
-        if let Some(path) = FileDialog::new().add_filter("JSON", &["json"]).save_file() {
-            let file = File::create(path);
-            match file {
-                Ok(mut file) => {
-                    let json = serde_json::to_string(self).expect("Failed to serialize fits");
-                    file.write_all(json.as_bytes())
-                        .expect("Failed to write file");
-                }
-                Err(e) => {
-                    log::error!("Error creating file: {:?}", e);
-                }
+    // Saves the stored fits as a fit archive of the histogram
+    fn save_to_file(&self, histogram: &str) {
+        if let Some(path) = FileDialog::new()
+            .set_file_name(FitArchive::file_name(histogram))
+            .add_filter("JSON", &["json"])
+            .save_file()
+        {
+            if let Err(e) = FitArchive::new(histogram, &self.stored_fits).write(&path) {
+                log::error!("Error saving fits: {}", e);
             }
         }
     }
 
     fn load_from_file(&mut self) {
         if let Some(path) = FileDialog::new().add_filter("JSON", &["json"]).pick_file() {
-            let file = File::open(path);
-            match file {
+            if let Err(e) = self.load_archive(&path) {
+                log::error!("Error loading fits from {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    // Appends the fits of a fit archive, or of a file of the older format with the whole
+    // fit state, to the stored fits
+    pub fn load_archive(
+        &mut self,
+        path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut contents = String::new();
+        File::open(path)?.read_to_string(&mut contents)?;
+
+        if let Ok(archive) = serde_json::from_str::<FitArchive>(&contents) {
+            self.add_fits(archive.fits);
+            return Ok(());
+        }
+
+        let loaded_fits: Fits = serde_json::from_str(&contents)?;
+        self.add_fits(loaded_fits.stored_fits);
+        self.temp_fit = loaded_fits.temp_fit; // override temp_fit
+        Ok(())
+    }
+
+    fn add_fits(&mut self, fits: Vec<Fitter>) {
+        for mut fit in fits {
+            fit.update_fit_points(&self.settings);
+            self.stored_fits.push(fit);
+        }
+    }
+
+    // Writes the stored fits as a flat JSON summary (regions, backgrounds, and peak values with uncertainties)
+    fn export_summary_json(&self) {
+        if let Some(path) = FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .set_file_name("fit_summary.json")
+            .save_file()
+        {
+            let summary: Vec<serde_json::Value> = self
+                .stored_fits
+                .iter()
+                .map(|fit| fit.summary_json())
+                .collect();
+
+            let json = match serde_json::to_string_pretty(&summary) {
+                Ok(json) => json,
+                Err(e) => {
+                    log::error!("Failed to serialize fit summary: {:?}", e);
+                    return;
+                }
+            };
+
+            match File::create(path) {
                 Ok(mut file) => {
-                    let mut contents = String::new();
-                    file.read_to_string(&mut contents)
-                        .expect("Failed to read file");
-                    let loaded_fits: Fits =
-                        serde_json::from_str(&contents).expect("Failed to deserialize fits");
-                    self.stored_fits.extend(loaded_fits.stored_fits); // Append loaded fits to current stored fits
-                    self.temp_fit = loaded_fits.temp_fit; // override temp_fit
+                    if let Err(e) = file.write_all(json.as_bytes()) {
+                        log::error!("Error writing fit summary: {:?}", e);
+                    }
                 }
                 Err(e) => {
-                    log::error!("Error opening file: {:?}", e);
+                    log::error!("Error creating file: {:?}", e);
                 }
             }
         }
     }
 
-    pub fn save_and_load_ui(&mut self, ui: &mut egui::Ui) {
+    pub fn save_and_load_ui(&mut self, ui: &mut egui::Ui, histogram: &str) {
         ui.horizontal(|ui| {
-            if ui.button("Save Fits").clicked() {
-                self.save_to_file();
+            if ui
+                .add_enabled(!self.stored_fits.is_empty(), egui::Button::new("Save Fits"))
+                .on_hover_text("Save the stored fits, with their lmfit results, as one fit archive")
+                .clicked()
+            {
+                self.save_to_file(histogram);
             }
 
             ui.separator();
@@ -136,6 +220,19 @@ impl Fits {
             if ui.button("Load Fits").clicked() {
                 self.load_from_file();
             }
+
+            ui.separator();
+
+            if ui
+                .add_enabled(
+                    !self.stored_fits.is_empty(),
+                    egui::Button::new("Export Summary"),
+                )
+                .on_hover_text("Export the stored fit results as structured JSON")
+                .clicked()
+            {
+                self.export_summary_json();
+            }
         });
     }
 
@@ -166,13 +263,14 @@ impl Fits {
         egui::Grid::new("fit_params_grid")
             .striped(true)
             .show(ui, |ui| {
+                let calibration = self.calibration.as_ref();
                 ui.label("Fit");
                 ui.label("Peak");
-                ui.label("Mean");
-                ui.label("FWHM");
+                ui.label(unit_label("Mean", calibration));
+                ui.label(unit_label("FWHM", calibration));
                 ui.label("Area");
                 ui.label("Amplitude");
-                ui.label("Sigma");
+                ui.label(unit_label("Sigma", calibration));
 
                 ui.end_row();
 
@@ -180,7 +278,7 @@ impl Fits {
                     ui.label("Temp");
 
                     if let Some(temp_fit) = &mut self.temp_fit {
-                        temp_fit.fitter_stats(ui, true);
+                        temp_fit.fitter_stats(ui, true, calibration);
                     }
                 }
 
@@ -197,13 +295,14 @@ impl Fits {
 
                             ui.separator();
                         });
-                        fit.fitter_stats(ui, true);
+                        fit.fitter_stats(ui, true, calibration);
                     }
                 }
             });
 
         if let Some(index) = to_remove {
-            self.stored_fits.remove(index);
+            let fit = self.stored_fits.remove(index);
+            self.removed.push((index, fit));
         }
     }
 
@@ -219,14 +318,18 @@ impl Fits {
         }
     }
 
-    pub fn fit_context_menu_ui(&mut self, ui: &mut egui::Ui) {
+    pub fn fit_context_menu_ui(&mut self, ui: &mut egui::Ui, histogram: &str) {
         ui.menu_button("Fits", |ui| {
-            self.save_and_load_ui(ui);
+            self.save_and_load_ui(ui, histogram);
 
             ui.separator();
 
             self.settings.menu_ui(ui);
 
+            if self.settings.curve_ui(ui) {
+                self.update_fit_points();
+            }
+
             egui::ScrollArea::vertical()
                 .max_height(300.0)
                 .id_salt("Context menu fit stats grid")
@@ -237,11 +340,11 @@ impl Fits {
             ui.separator();
 
             if let Some(temp_fit) = &mut self.temp_fit {
-                temp_fit.fit_result_ui(ui);
+                temp_fit.fit_result_ui(ui, self.calibration.as_ref());
             }
 
             for fit in &mut self.stored_fits {
-                fit.fit_result_ui(ui);
+                fit.fit_result_ui(ui, self.calibration.as_ref());
             }
         });
     }
@@ -1,23 +1,44 @@
-use crate::fitter::main_fitter::BackgroundModel;
+use crate::fitter::main_fitter::{BackgroundModel, FitBackend};
 use crate::fitter::models::exponential::ExponentialParameters;
 use crate::fitter::models::linear::LinearParameters;
+use crate::fitter::models::peak_shape::PeakShape;
 use crate::fitter::models::powerlaw::PowerLawParameters;
 use crate::fitter::models::quadratic::QuadraticParameters;
+use crate::fitter::models::step::StepParameters;
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct FitSettings {
     pub show_decomposition: bool,
     pub show_composition: bool,
     pub show_background: bool,
     pub show_fit_stats: bool,
+    #[serde(default = "default_show_estimates")]
+    pub show_estimates: bool,
     pub fit_stats_height: f32,
+    #[serde(default = "default_decomposition_points")]
+    pub decomposition_points: usize,
+    #[serde(default = "default_decomposition_extent")]
+    pub decomposition_extent: f64, // number of sigma drawn on each side of the mean
+    #[serde(default)]
+    pub full_region_decomposition: bool,
+    #[serde(default = "default_composition_points_per_bin")]
+    pub composition_points_per_bin: usize,
+    #[serde(default)]
+    pub extend_composition: bool,
     pub equal_stddev: bool,
     pub free_position: bool,
+    #[serde(default)]
+    pub backend: FitBackend,
+    #[serde(default)]
+    pub shape: PeakShape,
     pub background_model: BackgroundModel,
     pub linear_params: LinearParameters,
     pub quadratic_params: QuadraticParameters,
     pub power_law_params: PowerLawParameters,
     pub exponential_params: ExponentialParameters,
+    #[serde(default)]
+    pub step_params: StepParameters,
 }
 
 impl Default for FitSettings {
@@ -27,19 +48,95 @@ impl Default for FitSettings {
             show_composition: true,
             show_background: true,
             show_fit_stats: false,
+            show_estimates: true,
             fit_stats_height: 0.0,
+            decomposition_points: 100,
+            decomposition_extent: 5.0,
+            full_region_decomposition: false,
+            composition_points_per_bin: 5,
+            extend_composition: false,
             equal_stddev: true,
             free_position: true,
+            backend: FitBackend::default(),
+            shape: PeakShape::default(),
             background_model: BackgroundModel::Linear(LinearParameters::default()),
             linear_params: LinearParameters::default(),
             quadratic_params: QuadraticParameters::default(),
             power_law_params: PowerLawParameters::default(),
             exponential_params: ExponentialParameters::default(),
+            step_params: StepParameters::default(),
         }
     }
 }
 
+fn default_show_estimates() -> bool {
+    true
+}
+
+fn default_decomposition_points() -> usize {
+    100
+}
+
+fn default_decomposition_extent() -> f64 {
+    5.0
+}
+
+fn default_composition_points_per_bin() -> usize {
+    5
+}
+
 impl FitSettings {
+    // Returns true when the fit lines need to be regenerated
+    pub fn curve_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            ui.label("Decomposition: ");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.decomposition_points)
+                        .speed(1.0)
+                        .range(10..=100_000)
+                        .suffix(" points"),
+                )
+                .changed();
+            changed |= ui
+                .add_enabled(
+                    !self.full_region_decomposition,
+                    egui::DragValue::new(&mut self.decomposition_extent)
+                        .speed(0.1)
+                        .range(0.5..=f64::INFINITY)
+                        .prefix("±")
+                        .suffix(" σ"),
+                )
+                .changed();
+            changed |= ui
+                .checkbox(&mut self.full_region_decomposition, "Full Region")
+                .on_hover_text("Draw each peak across the whole fit region")
+                .changed();
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Composition: ");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.composition_points_per_bin)
+                        .speed(1.0)
+                        .range(1..=1000)
+                        .suffix(" points/bin"),
+                )
+                .changed();
+            changed |= ui
+                .checkbox(&mut self.extend_composition, "Extend")
+                .on_hover_text("Draw the composition out to the decomposition extent when peaks reach past the fit region")
+                .changed();
+        });
+
+        ui.separator();
+
+        changed
+    }
+
     pub fn menu_ui(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.label("Fit Stats: ");
@@ -56,6 +153,9 @@ impl FitSettings {
             .on_hover_text("Set the height of the fit statistics grid to see more fits at once");
         });
 
+        ui.checkbox(&mut self.show_estimates, "Show Pre-Fit Estimates")
+            .on_hover_text("Show the centroid, FWHM, and area estimated from the data between the region markers");
+
         ui.separator();
 
         ui.horizontal(|ui| {
@@ -81,6 +181,17 @@ impl FitSettings {
                 BackgroundModel::Exponential(self.exponential_params.clone()),
                 "Exponential",
             );
+            ui.radio_value(
+                &mut self.background_model,
+                BackgroundModel::Step(self.step_params.clone()),
+                "Step",
+            )
+            .on_hover_text("Complementary error function step, for peaks on a Compton edge");
+            ui.radio_value(
+                &mut self.background_model,
+                BackgroundModel::StepLinear(self.step_params.clone()),
+                "Step + Linear",
+            );
             ui.radio_value(&mut self.background_model, BackgroundModel::None, "None");
         });
 
@@ -104,6 +215,18 @@ impl FitSettings {
             self.exponential_params = params.clone();
         }
 
+        match &mut self.background_model {
+            BackgroundModel::Step(params) => {
+                params.ui(ui, false);
+                self.step_params = params.clone();
+            }
+            BackgroundModel::StepLinear(params) => {
+                params.ui(ui, true);
+                self.step_params = params.clone();
+            }
+            _ => {}
+        }
+
         ui.separator();
 
         ui.horizontal(|ui| {
@@ -114,6 +237,24 @@ impl FitSettings {
                 .on_hover_text("Allow the position of the Gaussian to be free");
         });
 
+        ui.horizontal(|ui| {
+            ui.label("Peak Shape: ");
+            self.shape.ui(ui);
+        })
+        .response
+        .on_hover_text("Line shape of the next fits, stored with each fit region. The tail and Voigt parameters are shared by the peaks of a region");
+
+        ui.horizontal(|ui| {
+            ui.label("Backend: ");
+            ui.add_enabled_ui(cfg!(feature = "python"), |ui| {
+                ui.radio_value(&mut self.backend, FitBackend::Lmfit, "lmfit")
+                    .on_hover_text("Fit with lmfit in Python")
+                    .on_disabled_hover_text("Needs a build with `--features python`");
+            });
+            ui.radio_value(&mut self.backend, FitBackend::Native, "Native")
+                .on_hover_text("Levenberg-Marquardt in Rust, no Python needed");
+        });
+
         ui.separator();
 
         ui.horizontal(|ui| {
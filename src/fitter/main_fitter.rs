@@ -1,9 +1,14 @@
-use super::common::Data;
+use super::calibration::Calibration;
+use super::common::{Data, Parameter};
+use super::fit_settings::FitSettings;
 use super::models::exponential::{ExponentialFitter, ExponentialParameters};
 use super::models::gaussian::GaussianFitter;
 use super::models::linear::{LinearFitter, LinearParameters};
+use super::models::peak_shape::PeakShape;
 use super::models::powerlaw::{PowerLawFitter, PowerLawParameters};
 use super::models::quadratic::{QuadraticFitter, QuadraticParameters};
+use super::models::step::{StepFitter, StepParameters};
+use crate::egui_plot_stuff::axis_transform::AxisTransform;
 use crate::egui_plot_stuff::egui_line::EguiLine;
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
@@ -12,6 +17,98 @@ pub enum FitModel {
     None,
 }
 
+// The native backend runs in Rust. lmfit needs a build with the "python" feature and a Python
+// environment with lmfit installed, fits asking for it use the native backend otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum FitBackend {
+    Lmfit,
+    #[default]
+    Native,
+}
+
+// Models that can be fit with either backend
+pub trait BackendFit {
+    #[cfg(feature = "python")]
+    fn lmfit(&mut self) -> pyo3::PyResult<()>;
+    fn native_fit(&mut self) -> Result<(), String>;
+
+    fn fit_with(&mut self, backend: FitBackend) -> Result<(), String> {
+        if backend == FitBackend::Lmfit {
+            #[cfg(feature = "python")]
+            return self.lmfit().map_err(|e| e.to_string());
+            #[cfg(not(feature = "python"))]
+            log::warn!("Built without the python feature, fitting with the native backend");
+        }
+        self.native_fit()
+    }
+}
+
+impl BackendFit for GaussianFitter {
+    #[cfg(feature = "python")]
+    fn lmfit(&mut self) -> pyo3::PyResult<()> {
+        GaussianFitter::lmfit(self)
+    }
+
+    fn native_fit(&mut self) -> Result<(), String> {
+        GaussianFitter::native_fit(self)
+    }
+}
+
+impl BackendFit for LinearFitter {
+    #[cfg(feature = "python")]
+    fn lmfit(&mut self) -> pyo3::PyResult<()> {
+        LinearFitter::lmfit(self)
+    }
+
+    fn native_fit(&mut self) -> Result<(), String> {
+        LinearFitter::native_fit(self)
+    }
+}
+
+impl BackendFit for QuadraticFitter {
+    #[cfg(feature = "python")]
+    fn lmfit(&mut self) -> pyo3::PyResult<()> {
+        QuadraticFitter::lmfit(self)
+    }
+
+    fn native_fit(&mut self) -> Result<(), String> {
+        QuadraticFitter::native_fit(self)
+    }
+}
+
+impl BackendFit for PowerLawFitter {
+    #[cfg(feature = "python")]
+    fn lmfit(&mut self) -> pyo3::PyResult<()> {
+        PowerLawFitter::lmfit(self)
+    }
+
+    fn native_fit(&mut self) -> Result<(), String> {
+        PowerLawFitter::native_fit(self)
+    }
+}
+
+impl BackendFit for ExponentialFitter {
+    #[cfg(feature = "python")]
+    fn lmfit(&mut self) -> pyo3::PyResult<()> {
+        ExponentialFitter::lmfit(self)
+    }
+
+    fn native_fit(&mut self) -> Result<(), String> {
+        ExponentialFitter::native_fit(self)
+    }
+}
+
+impl BackendFit for StepFitter {
+    #[cfg(feature = "python")]
+    fn lmfit(&mut self) -> pyo3::PyResult<()> {
+        StepFitter::lmfit(self)
+    }
+
+    fn native_fit(&mut self) -> Result<(), String> {
+        StepFitter::native_fit(self)
+    }
+}
+
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub enum FitResult {
     Gaussian(GaussianFitter),
@@ -23,6 +120,8 @@ pub enum BackgroundModel {
     Quadratic(QuadraticParameters),
     PowerLaw(PowerLawParameters),
     Exponential(ExponentialParameters),
+    Step(StepParameters),
+    StepLinear(StepParameters),
     None,
 }
 
@@ -32,6 +131,7 @@ pub enum BackgroundResult {
     Quadratic(QuadraticFitter),
     PowerLaw(PowerLawFitter),
     Exponential(ExponentialFitter),
+    Step(StepFitter),
 }
 
 impl BackgroundResult {
@@ -41,6 +141,28 @@ impl BackgroundResult {
             BackgroundResult::Quadratic(fit) => fit.fit_points.clone(),
             BackgroundResult::PowerLaw(fit) => fit.fit_points.clone(),
             BackgroundResult::Exponential(fit) => fit.fit_points.clone(),
+            BackgroundResult::Step(fit) => fit.fit_points.clone(),
+        }
+    }
+
+    // Points the background was fit to, empty when it was fit together with the peaks
+    pub fn data(&self) -> &Data {
+        match self {
+            BackgroundResult::Linear(fit) => &fit.data,
+            BackgroundResult::Quadratic(fit) => &fit.data,
+            BackgroundResult::PowerLaw(fit) => &fit.data,
+            BackgroundResult::Exponential(fit) => &fit.data,
+            BackgroundResult::Step(fit) => &fit.data,
+        }
+    }
+
+    pub fn evaluate(&self, x: f64) -> f64 {
+        match self {
+            BackgroundResult::Linear(fit) => fit.evaluate(x),
+            BackgroundResult::Quadratic(fit) => fit.evaluate(x),
+            BackgroundResult::PowerLaw(fit) => fit.evaluate(x),
+            BackgroundResult::Exponential(fit) => fit.evaluate(x),
+            BackgroundResult::Step(fit) => fit.evaluate(x),
         }
     }
 }
@@ -56,6 +178,10 @@ pub struct Fitter {
 
     pub fit_model: FitModel,
     pub fit_result: Option<FitResult>,
+    #[serde(default)]
+    pub backend: FitBackend,
+    #[serde(default)]
+    pub shape: PeakShape,
 
     pub background_line: EguiLine,
     pub composition_line: EguiLine,
@@ -75,6 +201,8 @@ impl Fitter {
 
             fit_model: FitModel::None,
             fit_result: None,
+            backend: FitBackend::default(),
+            shape: PeakShape::default(),
 
             background_line: EguiLine::new(egui::Color32::GREEN),
             composition_line: EguiLine::new(egui::Color32::BLUE),
@@ -95,7 +223,11 @@ impl Fitter {
                     *bin_width,
                 );
 
-                match fit.lmfit() {
+                fit.fit_settings.shape = self.shape;
+
+                let result = fit.fit_with(self.backend);
+
+                match result {
                     Ok(_) => {
                         self.composition_line.points = fit.fit_points.clone();
                         for fit in &fit.fit_result {
@@ -134,7 +266,7 @@ impl Fitter {
                 fit.paramaters = params.clone(); // Ensure LinearParameters are used
 
                 // Perform the fit
-                match fit.lmfit() {
+                match fit.fit_with(self.backend) {
                     Ok(_) => {
                         self.background_line.points = fit.fit_points.clone();
                         self.background_result = Some(BackgroundResult::Linear(fit));
@@ -151,7 +283,7 @@ impl Fitter {
                 fit.paramaters = params.clone(); // Ensure QuadraticParameters are used
 
                 // Perform the fit
-                match fit.lmfit() {
+                match fit.fit_with(self.backend) {
                     Ok(_) => {
                         self.background_line.points = fit.fit_points.clone();
                         self.background_result = Some(BackgroundResult::Quadratic(fit));
@@ -168,7 +300,7 @@ impl Fitter {
                 fit.paramaters = params.clone(); // Ensure PowerLawParameters are used
 
                 // Perform the fit
-                match fit.lmfit() {
+                match fit.fit_with(self.backend) {
                     Ok(_) => {
                         self.background_line.points = fit.fit_points.clone();
                         self.background_result = Some(BackgroundResult::PowerLaw(fit));
@@ -185,7 +317,7 @@ impl Fitter {
                 fit.paramaters = params.clone(); // Ensure ExponentialParameters are used
 
                 // Perform the fit
-                match fit.lmfit() {
+                match fit.fit_with(self.backend) {
                     Ok(_) => {
                         self.background_line.points = fit.fit_points.clone();
                         self.background_result = Some(BackgroundResult::Exponential(fit));
@@ -195,6 +327,22 @@ impl Fitter {
                     }
                 }
             }
+            BackgroundModel::Step(params) | BackgroundModel::StepLinear(params) => {
+                let linear = matches!(self.background_model, BackgroundModel::StepLinear(_));
+                let mut fit = StepFitter::new(self.data.clone(), linear);
+                fit.paramaters = params.clone();
+
+                // Perform the fit
+                match fit.fit_with(self.backend) {
+                    Ok(_) => {
+                        self.background_line.points = fit.fit_points.clone();
+                        self.background_result = Some(BackgroundResult::Step(fit));
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                    }
+                }
+            }
             BackgroundModel::None => {
                 log::info!("No background fitting required for 'None'");
             }
@@ -212,15 +360,8 @@ impl Fitter {
         let background_fit = self.background_result.as_ref()?;
 
         // Generate background values for each x_data point
-        let background_values: Vec<f64> = x_data
-            .iter()
-            .map(|&x| match background_fit {
-                BackgroundResult::Linear(fit) => fit.evaluate(x),
-                BackgroundResult::Quadratic(fit) => fit.evaluate(x),
-                BackgroundResult::PowerLaw(fit) => fit.evaluate(x),
-                BackgroundResult::Exponential(fit) => fit.evaluate(x),
-            })
-            .collect();
+        let background_values: Vec<f64> =
+            x_data.iter().map(|&x| background_fit.evaluate(x)).collect();
 
         // Subtract the background values from the actual y_data
         let corrected_y_data: Vec<f64> = y_data
@@ -285,7 +426,7 @@ impl Fitter {
         self.name = name;
     }
 
-    pub fn fit_result_ui(&mut self, ui: &mut egui::Ui) {
+    pub fn fit_result_ui(&mut self, ui: &mut egui::Ui, calibration: Option<&Calibration>) {
         ui.collapsing(self.name.clone(), |ui| {
             egui::ScrollArea::vertical()
                 .min_scrolled_height(300.0)
@@ -307,6 +448,9 @@ impl Fitter {
                             BackgroundResult::Exponential(fit) => {
                                 fit.ui(ui);
                             }
+                            BackgroundResult::Step(fit) => {
+                                fit.ui(ui);
+                            }
                         }
                         ui.horizontal(|ui| {
                             ui.label("Line");
@@ -319,15 +463,15 @@ impl Fitter {
                             .striped(true)
                             .show(ui, |ui| {
                                 ui.label("Peak");
-                                ui.label("Mean");
-                                ui.label("FWHM");
+                                ui.label(unit_label("Mean", calibration));
+                                ui.label(unit_label("FWHM", calibration));
                                 ui.label("Area");
                                 ui.label("Amplitude");
-                                ui.label("Sigma");
+                                ui.label(unit_label("Sigma", calibration));
 
                                 ui.end_row();
 
-                                self.fitter_stats(ui, false);
+                                self.fitter_stats(ui, false, calibration);
                             });
 
                         for line in &mut self.decomposition_lines {
@@ -340,16 +484,158 @@ impl Fitter {
         });
     }
 
-    pub fn fitter_stats(&mut self, ui: &mut egui::Ui, skip_one: bool) {
+    pub fn fitter_stats(
+        &mut self,
+        ui: &mut egui::Ui,
+        skip_one: bool,
+        calibration: Option<&Calibration>,
+    ) {
         if let Some(fit_result) = &self.fit_result {
             match fit_result {
                 FitResult::Gaussian(fit) => {
-                    fit.fit_params_ui(ui, skip_one);
+                    fit.fit_params_ui(ui, skip_one, calibration);
                 }
             }
         }
     }
 
+    // Regenerate the decomposition and composition lines with the curve settings
+    pub fn update_fit_points(&mut self, settings: &FitSettings) {
+        let Some(FitResult::Gaussian(fit)) = &mut self.fit_result else {
+            return;
+        };
+
+        let (Some(&region_min), Some(&region_max)) = (self.data.x.first(), self.data.x.last())
+        else {
+            return;
+        };
+
+        let extent = settings.decomposition_extent;
+        let mut composition_min = region_min;
+        let mut composition_max = region_max;
+
+        for (index, peak) in fit.fit_result.iter_mut().enumerate() {
+            let Some((low, high)) = peak.extent(extent) else {
+                continue;
+            };
+
+            if settings.extend_composition {
+                composition_min = composition_min.min(low);
+                composition_max = composition_max.max(high);
+            }
+
+            if settings.full_region_decomposition {
+                peak.generate_fit_points_in_range(
+                    region_min,
+                    region_max,
+                    settings.decomposition_points,
+                );
+            } else {
+                peak.generate_fit_points_in_range(low, high, settings.decomposition_points);
+            }
+
+            if let Some(line) = self.decomposition_lines.get_mut(index) {
+                line.points = peak.fit_points.clone();
+            }
+        }
+
+        let num_points = (settings.composition_points_per_bin * self.data.x.len()).max(2);
+        let step = (composition_max - composition_min) / (num_points - 1) as f64;
+        fit.fit_points = (0..num_points)
+            .map(|i| {
+                let x = composition_min + i as f64 * step;
+                let background = self
+                    .background_result
+                    .as_ref()
+                    .map_or(0.0, |background| background.evaluate(x));
+                let peaks: f64 = fit.fit_result.iter().map(|peak| peak.evaluate(x)).sum();
+                [x, background + peaks]
+            })
+            .collect();
+
+        self.composition_line.points = fit.fit_points.clone();
+    }
+
+    // Structured summary of the fit for downstream analysis
+    pub fn summary_json(&self) -> serde_json::Value {
+        fn parameter(p: &Parameter) -> serde_json::Value {
+            serde_json::json!({
+                "value": p.value,
+                "uncertainty": p.uncertainty,
+            })
+        }
+
+        let region = match (self.data.x.first(), self.data.x.last()) {
+            (Some(start), Some(end)) => serde_json::json!([start, end]),
+            _ => serde_json::Value::Null,
+        };
+
+        let background = match &self.background_result {
+            Some(BackgroundResult::Linear(fit)) => serde_json::json!({
+                "model": "linear",
+                "slope": parameter(&fit.paramaters.slope),
+                "intercept": parameter(&fit.paramaters.intercept),
+            }),
+            Some(BackgroundResult::Quadratic(fit)) => serde_json::json!({
+                "model": "quadratic",
+                "a": parameter(&fit.paramaters.a),
+                "b": parameter(&fit.paramaters.b),
+                "c": parameter(&fit.paramaters.c),
+            }),
+            Some(BackgroundResult::PowerLaw(fit)) => serde_json::json!({
+                "model": "powerlaw",
+                "amplitude": parameter(&fit.paramaters.amplitude),
+                "exponent": parameter(&fit.paramaters.exponent),
+            }),
+            Some(BackgroundResult::Exponential(fit)) => serde_json::json!({
+                "model": "exponential",
+                "amplitude": parameter(&fit.paramaters.amplitude),
+                "decay": parameter(&fit.paramaters.decay),
+            }),
+            Some(BackgroundResult::Step(fit)) => {
+                let mut background = serde_json::json!({
+                    "model": if fit.linear { "step_linear" } else { "step" },
+                });
+                for param in fit.paramaters.parameters(fit.linear) {
+                    background[param.name.as_str()] = parameter(&param);
+                }
+                background
+            }
+            None => serde_json::Value::Null,
+        };
+
+        let peaks: Vec<serde_json::Value> = match &self.fit_result {
+            Some(FitResult::Gaussian(fit)) => fit
+                .fit_result
+                .iter()
+                .map(|peak| {
+                    serde_json::json!({
+                        "mean": parameter(&peak.mean),
+                        "fwhm": parameter(&peak.fwhm),
+                        "area": parameter(&peak.area),
+                        "amplitude": parameter(&peak.amplitude),
+                        "sigma": parameter(&peak.sigma),
+                        "shape": peak.shape.label(),
+                        "shape_params": peak
+                            .shape_params
+                            .iter()
+                            .map(|param| (param.name.clone(), parameter(param)))
+                            .collect::<serde_json::Map<_, _>>(),
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        serde_json::json!({
+            "name": self.name,
+            "region": region,
+            "peak_markers": self.get_peak_markers(),
+            "background": background,
+            "peaks": peaks,
+        })
+    }
+
     // Draw the background, decomposition, and composition lines
     pub fn draw(&self, plot_ui: &mut egui_plot::PlotUi) {
         for line in &self.decomposition_lines {
@@ -374,4 +660,22 @@ impl Fitter {
         self.background_line.log_y = log_y;
         self.background_line.log_x = log_x;
     }
+
+    pub fn set_y_transform(&mut self, transform: AxisTransform) {
+        for line in &mut self.decomposition_lines {
+            line.y_transform = transform;
+        }
+        self.composition_line.y_transform = transform;
+        self.background_line.y_transform = transform;
+    }
+}
+
+// Column header with the calibrated unit, e.g. "Mean [keV]"
+pub fn unit_label(name: &str, calibration: Option<&Calibration>) -> String {
+    match calibration {
+        Some(calibration) if !calibration.unit.is_empty() => {
+            format!("{} [{}]", name, calibration.unit)
+        }
+        _ => name.to_string(),
+    }
 }
@@ -1,5 +1,9 @@
+pub mod calibration;
 pub mod common;
+pub mod efficiency;
+pub mod fit_archive;
 pub mod fit_handler;
 pub mod fit_settings;
 pub mod main_fitter;
 pub mod models;
+pub mod regression;
@@ -0,0 +1,232 @@
+use levenberg_marquardt::{LeastSquaresProblem, LevenbergMarquardt};
+use nalgebra::{DMatrix, DVector, Dyn, Owned};
+
+use super::step::step;
+use crate::fitter::common::{Data, Parameter};
+use crate::fitter::main_fitter::BackgroundModel;
+
+// Background models fit on their own to the data between the background markers, without
+// Python. The parameters that vary are fit, the others keep their initial guess.
+
+pub fn background_parameters(model: &BackgroundModel, data: &Data) -> Vec<Parameter> {
+    match model {
+        BackgroundModel::Linear(params) => vec![params.slope.clone(), params.intercept.clone()],
+        BackgroundModel::Quadratic(params) => {
+            vec![params.a.clone(), params.b.clone(), params.c.clone()]
+        }
+        BackgroundModel::Exponential(params) => {
+            vec![params.amplitude.clone(), params.decay.clone()]
+        }
+        BackgroundModel::PowerLaw(params) => {
+            vec![params.amplitude.clone(), params.exponent.clone()]
+        }
+        BackgroundModel::Step(params) => params.with_estimates(data).parameters(false),
+        BackgroundModel::StepLinear(params) => params.with_estimates(data).parameters(true),
+        BackgroundModel::None => Vec::new(),
+    }
+}
+
+// Value of the model at x, with the parameters in the order of `background_parameters`
+pub fn background_at(model: &BackgroundModel, values: &[f64], x: f64) -> f64 {
+    match model {
+        BackgroundModel::Linear(_) => values[0] * x + values[1],
+        BackgroundModel::Quadratic(_) => values[0] * x * x + values[1] * x + values[2],
+        BackgroundModel::Exponential(_) => values[0] * (-x / values[1]).exp(),
+        BackgroundModel::PowerLaw(_) => values[0] * x.powf(values[1]),
+        BackgroundModel::Step(_) => step(x, values[0], values[1], values[2].max(1e-9)),
+        BackgroundModel::StepLinear(_) => {
+            step(x, values[0], values[1], values[2].max(1e-9)) + values[3] * x + values[4]
+        }
+        BackgroundModel::None => 0.0,
+    }
+}
+
+struct Background {
+    x: Vec<f64>,
+    y: Vec<f64>,
+    model: BackgroundModel,
+    parameters: Vec<Parameter>,
+    params: DVector<f64>, // the parameters that vary
+}
+
+impl Background {
+    fn values(&self, params: &DVector<f64>) -> Vec<f64> {
+        let mut index = 0;
+        self.parameters
+            .iter()
+            .map(|param| {
+                if param.vary {
+                    index += 1;
+                    params[index - 1].clamp(param.min, param.max)
+                } else {
+                    param.initial_guess
+                }
+            })
+            .collect()
+    }
+
+    fn residuals_at(&self, params: &DVector<f64>) -> DVector<f64> {
+        let values = self.values(params);
+        DVector::from_iterator(
+            self.x.len(),
+            self.x
+                .iter()
+                .zip(&self.y)
+                .map(|(&x, &y)| background_at(&self.model, &values, x) - y),
+        )
+    }
+
+    // Central differences, like the peak fit
+    fn jacobian_at(&self, params: &DVector<f64>) -> DMatrix<f64> {
+        let mut jacobian = DMatrix::zeros(self.x.len(), params.len());
+        for column in 0..params.len() {
+            let step = 1e-6 * params[column].abs().max(1e-3);
+            let mut up = params.clone();
+            let mut down = params.clone();
+            up[column] += step;
+            down[column] -= step;
+            let difference = (self.residuals_at(&up) - self.residuals_at(&down)) / (2.0 * step);
+            jacobian.set_column(column, &difference);
+        }
+        jacobian
+    }
+}
+
+impl LeastSquaresProblem<f64, Dyn, Dyn> for Background {
+    type ResidualStorage = Owned<f64, Dyn>;
+    type JacobianStorage = Owned<f64, Dyn, Dyn>;
+    type ParameterStorage = Owned<f64, Dyn>;
+
+    fn set_params(&mut self, params: &DVector<f64>) {
+        self.params.copy_from(params);
+    }
+
+    fn params(&self) -> DVector<f64> {
+        self.params.clone()
+    }
+
+    fn residuals(&self) -> Option<DVector<f64>> {
+        Some(self.residuals_at(&self.params))
+    }
+
+    fn jacobian(&self) -> Option<DMatrix<f64>> {
+        Some(self.jacobian_at(&self.params))
+    }
+}
+
+pub struct BackgroundFit {
+    pub values: Vec<(f64, f64)>, // value and uncertainty, in the order of `background_parameters`
+    pub fit_points: Vec<[f64; 2]>,
+    pub fit_report: String,
+}
+
+// Levenberg-Marquardt fit of the model to the data. Uncertainties come from the covariance
+// matrix scaled by the reduced chi-square, the fixed parameters have none.
+pub fn native_background_fit(
+    model: &BackgroundModel,
+    data: &Data,
+) -> Result<BackgroundFit, String> {
+    let (Some(&x_first), Some(&x_last)) = (data.x.first(), data.x.last()) else {
+        return Err("No data to fit".to_string());
+    };
+
+    let parameters = background_parameters(model, data);
+    let initial: Vec<f64> = parameters
+        .iter()
+        .filter(|param| param.vary)
+        .map(|param| param.initial_guess)
+        .collect();
+
+    let points = data.x.len();
+    let variables = initial.len();
+    if points <= variables {
+        return Err(format!(
+            "{} points are not enough to fit {} parameters",
+            points, variables
+        ));
+    }
+
+    let problem = Background {
+        x: data.x.clone(),
+        y: data.y.clone(),
+        model: model.clone(),
+        parameters,
+        params: DVector::from_vec(initial),
+    };
+
+    let (problem, report) = LevenbergMarquardt::new()
+        .with_patience(1000)
+        .minimize(problem);
+    if !report.termination.was_successful() {
+        return Err(format!(
+            "Background fit did not converge: {:?}",
+            report.termination
+        ));
+    }
+
+    let params = problem.params.clone();
+    let chi2 = problem.residuals_at(&params).norm_squared();
+    let reduced_chi2 = chi2 / (points - variables) as f64;
+
+    let jacobian = problem.jacobian_at(&params);
+    let covariance = (jacobian.transpose() * &jacobian)
+        .try_inverse()
+        .map(|inverse| inverse * reduced_chi2);
+    if covariance.is_none() {
+        log::warn!("Covariance matrix is singular, uncertainties are not available");
+    }
+
+    let mut index = 0;
+    let values: Vec<(f64, f64)> = problem
+        .values(&params)
+        .into_iter()
+        .zip(&problem.parameters)
+        .map(|(value, param)| {
+            if !param.vary {
+                return (value, 0.0);
+            }
+            index += 1;
+            let variance = covariance
+                .as_ref()
+                .map_or(0.0, |c| c[(index - 1, index - 1)]);
+            (value, variance.max(0.0).sqrt())
+        })
+        .collect();
+
+    let fitted: Vec<f64> = values.iter().map(|&(value, _)| value).collect();
+    let line_points = 5 * points;
+    let step = (x_last - x_first) / (line_points - 1).max(1) as f64;
+    let fit_points = (0..line_points)
+        .map(|i| {
+            let x = x_first + i as f64 * step;
+            [x, background_at(model, &fitted, x)]
+        })
+        .collect();
+
+    let mut fit_report = format!(
+        "[[Model]]\n    background, native Levenberg-Marquardt\n\
+         [[Fit Statistics]]\n    \
+         # function evals   = {}\n    \
+         # data points      = {}\n    \
+         # variables        = {}\n    \
+         chi-square         = {:.6}\n    \
+         reduced chi-square = {:.6}\n    \
+         termination        = {:?}\n\
+         [[Variables]]\n",
+        report.number_of_evaluations, points, variables, chi2, reduced_chi2, report.termination,
+    );
+    for (param, &(value, uncertainty)) in problem.parameters.iter().zip(&values) {
+        let name = &param.name;
+        if param.vary {
+            fit_report.push_str(&format!("    {name}: {value:.6} +/- {uncertainty:.6}\n"));
+        } else {
+            fit_report.push_str(&format!("    {name}: {value:.6} (fixed)\n"));
+        }
+    }
+
+    Ok(BackgroundFit {
+        values,
+        fit_points,
+        fit_report,
+    })
+}
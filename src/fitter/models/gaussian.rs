@@ -1,10 +1,14 @@
+use crate::fitter::calibration::Calibration;
 use crate::fitter::common::{Data, Parameter};
 use crate::fitter::main_fitter::{BackgroundModel, BackgroundResult};
 use crate::fitter::models::exponential::ExponentialFitter;
 use crate::fitter::models::linear::LinearFitter;
+use crate::fitter::models::peak_shape::PeakShape;
 use crate::fitter::models::powerlaw::PowerLawFitter;
 use crate::fitter::models::quadratic::QuadraticFitter;
+use crate::fitter::models::step::StepFitter;
 
+#[cfg(feature = "python")]
 use pyo3::{
     prelude::*,
     types::{PyDict, PyModule},
@@ -17,9 +21,22 @@ pub struct GaussianParameters {
     pub sigma: Parameter,
     pub fwhm: Parameter,
     pub area: Parameter,
+    #[serde(default = "energy_parameter")]
+    pub energy: Parameter, // assigned energy of the peak, used for calibrations
+    #[serde(default)]
+    pub shape: PeakShape,
+    #[serde(default)]
+    pub shape_params: Vec<Parameter>, // shared by the peaks of the fit
     pub fit_points: Vec<[f64; 2]>, // Vector of (x, y) points representing the Gaussian curve
 }
 
+fn energy_parameter() -> Parameter {
+    Parameter {
+        name: "energy".to_string(),
+        ..Default::default()
+    }
+}
+
 impl Default for GaussianParameters {
     fn default() -> Self {
         GaussianParameters {
@@ -43,6 +60,9 @@ impl Default for GaussianParameters {
                 name: "area".to_string(),
                 ..Default::default()
             },
+            energy: energy_parameter(),
+            shape: PeakShape::default(),
+            shape_params: Vec::new(),
             fit_points: Vec::new(),
         }
     }
@@ -87,6 +107,9 @@ impl GaussianParameters {
                 uncertainty: Some(area.1),
                 ..Default::default()
             },
+            energy: energy_parameter(),
+            shape: PeakShape::default(),
+            shape_params: Vec::new(),
             fit_points: Vec::new(),
         }
     }
@@ -94,39 +117,95 @@ impl GaussianParameters {
     /// Function to generate fit points 5 sigma out from the mean.
     /// Fit points are generated in the range [mean - 5 * sigma, mean + 5 * sigma].
     pub fn generate_fit_points(&mut self, num_points: usize) {
-        if let (Some(mean), Some(sigma)) = (self.mean.value, self.sigma.value) {
-            let range_min = mean - 5.0 * sigma;
-            let range_max = mean + 5.0 * sigma;
+        if let Some((low, high)) = self.extent(5.0) {
+            self.generate_fit_points_in_range(low, high, num_points);
+        }
+    }
+
+    // Range the peak is drawn over, `extent` widths on each side of the mean
+    pub fn extent(&self, extent: f64) -> Option<(f64, f64)> {
+        let (mean, sigma) = (self.mean.value?, self.sigma.value?);
+        let (below, above) = self.shape.extent(sigma, &self.shape_values());
+        Some((mean - extent * below, mean + extent * above))
+    }
+
+    pub fn shape_values(&self) -> Vec<f64> {
+        self.shape_params
+            .iter()
+            .map(|param| param.value.unwrap_or(param.initial_guess))
+            .collect()
+    }
+
+    /// Function to generate fit points between range_min and range_max.
+    pub fn generate_fit_points_in_range(
+        &mut self,
+        range_min: f64,
+        range_max: f64,
+        num_points: usize,
+    ) {
+        if self.mean.value.is_some() && self.sigma.value.is_some() {
+            let num_points = num_points.max(1);
             let step_size = (range_max - range_min) / (num_points as f64);
 
-            self.fit_points.clear();
-            for i in 0..=num_points {
-                let x = range_min + i as f64 * step_size;
-                let y = self.amplitude.value.unwrap_or(1.0)
-                    * (-((x - mean).powi(2)) / (2.0 * sigma.powi(2))).exp();
-                self.fit_points.push([x, y]);
-            }
+            self.fit_points = (0..=num_points)
+                .map(|i| {
+                    let x = range_min + i as f64 * step_size;
+                    [x, self.evaluate(x)]
+                })
+                .collect();
         }
     }
 
-    pub fn params_ui(&self, ui: &mut egui::Ui) {
-        ui.label(format!(
-            "{:.2} ± {:.2}",
-            self.mean.value.unwrap_or(0.0),
-            self.mean.uncertainty.unwrap_or(0.0)
-        ));
+    pub fn evaluate(&self, x: f64) -> f64 {
+        match (self.mean.value, self.sigma.value) {
+            (Some(mean), Some(sigma)) => self.shape.evaluate(
+                x,
+                self.amplitude.value.unwrap_or(1.0),
+                mean,
+                sigma,
+                &self.shape_values(),
+            ),
+            _ => 0.0,
+        }
+    }
+
+    // Mean, FWHM, and sigma are shown in calibrated units when a calibration is given
+    pub fn params_ui(&self, ui: &mut egui::Ui, calibration: Option<&Calibration>) {
+        let mean = self.mean.value.unwrap_or(0.0);
+        let (mean_value, mean_uncertainty, width) = match calibration {
+            Some(calibration) => (
+                calibration.calibrate(mean),
+                calibration.width(mean, self.mean.uncertainty.unwrap_or(0.0)),
+                calibration.width(mean, 1.0),
+            ),
+            None => (mean, self.mean.uncertainty.unwrap_or(0.0), 1.0),
+        };
+
+        ui.label(format!("{:.2} ± {:.2}", mean_value, mean_uncertainty));
 
         ui.label(format!(
             "{:.2} ± {:.2}",
-            self.fwhm.value.unwrap_or(0.0),
-            self.fwhm.uncertainty.unwrap_or(0.0)
+            width * self.fwhm.value.unwrap_or(0.0),
+            width * self.fwhm.uncertainty.unwrap_or(0.0)
         ));
 
-        ui.label(format!(
+        let area = ui.label(format!(
             "{:.2} ± {:.2}",
             self.area.value.unwrap_or(0.0),
             self.area.uncertainty.unwrap_or(0.0)
         ));
+        if self.shape != PeakShape::Gaussian {
+            let mut text = self.shape.label().to_string();
+            for param in &self.shape_params {
+                text.push_str(&format!(
+                    "\n{}: {:.3} ± {:.3}",
+                    param.name,
+                    param.value.unwrap_or(0.0),
+                    param.uncertainty.unwrap_or(0.0)
+                ));
+            }
+            area.on_hover_text(text);
+        }
 
         ui.label(format!(
             "{:.2} ± {:.2}",
@@ -136,8 +215,8 @@ impl GaussianParameters {
 
         ui.label(format!(
             "{:.2} ± {:.2}",
-            self.sigma.value.unwrap_or(0.0),
-            self.sigma.uncertainty.unwrap_or(0.0)
+            width * self.sigma.value.unwrap_or(0.0),
+            width * self.sigma.uncertainty.unwrap_or(0.0)
         ));
     }
 }
@@ -147,8 +226,13 @@ pub struct GaussianFitSettings {
     pub equal_stdev: bool,
     pub free_position: bool,
     pub bin_width: f64,
+    #[serde(default)]
+    pub shape: PeakShape,
 }
 
+// amplitude, mean, sigma, fwhm and area of a peak, each followed by its uncertainty
+pub type PeakValues = (f64, f64, f64, f64, f64, f64, f64, f64, f64, f64);
+
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct GaussianFitter {
     pub data: Data,
@@ -159,6 +243,8 @@ pub struct GaussianFitter {
     pub fit_result: Vec<GaussianParameters>,
     pub fit_points: Vec<[f64; 2]>,
     pub fit_report: String,
+    #[serde(default)]
+    pub lmfit_result: String, // ModelResult.dumps(), the contents of an lmfit .sav file
 }
 
 impl GaussianFitter {
@@ -180,13 +266,16 @@ impl GaussianFitter {
                 equal_stdev,
                 free_position,
                 bin_width,
+                shape: PeakShape::default(),
             },
             fit_result: Vec::new(),
             fit_points: Vec::new(),
             fit_report: String::new(),
+            lmfit_result: String::new(),
         }
     }
 
+    #[cfg(feature = "python")]
     pub fn lmfit(&mut self) -> PyResult<()> {
         Python::with_gil(|py| {
             // let sys = py.import_bound("sys")?;
@@ -214,12 +303,77 @@ import numpy as np
 import lmfit
 # from sigfig import round
 
+from scipy.special import erfc
+
 def gaussian(x, amplitude, mean, sigma):
     return amplitude * np.exp(-(x - mean)**2 / (2 * sigma**2))
 
+def tailed(x, amplitude, mean, sigma, tail_fraction, tail_decay):
+    dx = x - mean
+    tail = np.exp(dx / tail_decay) * erfc(dx / (np.sqrt(2) * sigma) + sigma / (np.sqrt(2) * tail_decay))
+    return amplitude * ((1 - tail_fraction) * np.exp(-dx**2 / (2 * sigma**2)) + tail_fraction * tail)
+
+def voigt_parts(sigma, gamma):
+    fg = 2.35482 * sigma
+    fl = 2 * gamma
+    fwhm = (fg**5 + 2.69269 * fg**4 * fl + 2.42843 * fg**3 * fl**2 + 4.47163 * fg**2 * fl**3 + 0.07842 * fg * fl**4 + fl**5)**0.2
+    ratio = fl / fwhm
+    eta = 1.36603 * ratio - 0.47719 * ratio**2 + 0.11116 * ratio**3
+    return fwhm, eta
+
+def voigt(x, amplitude, mean, sigma, gamma):
+    fwhm, eta = voigt_parts(sigma, gamma)
+    dx = x - mean
+    half = fwhm / 2
+    return amplitude * (eta * half**2 / (dx**2 + half**2) + (1 - eta) * np.exp(-dx**2 / (2 * (fwhm / 2.35482)**2)))
+
+def lorentzian(x, amplitude, mean, sigma):
+    return amplitude * sigma**2 / ((x - mean)**2 + sigma**2)
+
+# backgrounds for peaks on a Compton edge
+def step(x, amplitude, center, width):
+    return 0.5 * amplitude * erfc((x - center) / (np.sqrt(2) * width))
+
+def step_linear(x, amplitude, center, width, slope, intercept):
+    return step(x, amplitude, center, width) + slope * x + intercept
+
+# fwhm and area (in x units) of each shape, used in the parameter expressions
+def gaussian_fwhm(sigma):
+    return 2.35482 * sigma
+
+def gaussian_area(amplitude, sigma):
+    return amplitude * np.sqrt(2 * np.pi) * sigma
+
+def tailed_fwhm(sigma, tail_fraction, tail_decay):
+    return 2.35482 * sigma
+
+def tailed_area(amplitude, sigma, tail_fraction, tail_decay):
+    tail_area = 2 * tail_decay * np.exp(-0.5 * (sigma / tail_decay)**2)
+    return amplitude * ((1 - tail_fraction) * np.sqrt(2 * np.pi) * sigma + tail_fraction * tail_area)
+
+def voigt_fwhm(sigma, gamma):
+    return voigt_parts(sigma, gamma)[0]
+
+def voigt_area(amplitude, sigma, gamma):
+    fwhm, eta = voigt_parts(sigma, gamma)
+    return amplitude * (eta * np.pi * fwhm / 2 + (1 - eta) * fwhm / 2 * np.sqrt(np.pi / np.log(2)))
+
+def lorentzian_fwhm(sigma):
+    return 2 * sigma
+
+def lorentzian_area(amplitude, sigma):
+    return amplitude * np.pi * sigma
+
+SHAPES = {
+    'gaussian': (gaussian, []),
+    'tailed': (tailed, [('tail_fraction', 0.1, 0.0, 1.0), ('tail_decay', 10.0, 1e-6, np.inf)]),
+    'voigt': (voigt, [('gamma', 5.0, 0.0, np.inf)]),
+    'lorentzian': (lorentzian, []),
+}
+
 def MultipleGaussianFit(x_data: list, y_data: list, peak_markers: list, bin_width: float,
                         equal_sigma: bool = True, free_position: bool = True,
-                        background_params: dict = None):
+                        background_params: dict = None, shape: str = 'gaussian'):
     """
     Multiple Gaussian fit function with background model support.
     
@@ -277,13 +431,26 @@ def MultipleGaussianFit(x_data: list, y_data: list, peak_markers: list, bin_widt
         params = model.make_params(amplitude=amplitude[3], exponent=exponent[3])
         params['bg_amplitude'].set(min=amplitude[1], max=amplitude[2], value=amplitude[3], vary=amplitude[4])
         params['bg_exponent'].set(min=exponent[1], max=exponent[2], value=exponent[3], vary=exponent[4])
+    elif bg_type in ('step', 'step_linear'):
+        model = lmfit.Model(step_linear if bg_type == 'step_linear' else step, prefix='bg_')
+        params = model.make_params()
+        for name, min_value, max_value, value, vary in background_params.get('step'):
+            params[f'bg_{name}'].set(min=min_value, max=max_value, value=value, vary=vary)
     elif bg_type == 'none':
         model = None
         params = lmfit.Parameters()
     else:
         raise ValueError("Unsupported background model")
 
-    first_gaussian = lmfit.Model(gaussian, prefix=f'g0_')
+    for name in ('fwhm', 'area'):
+        params._asteval.symtable[f'{shape}_{name}'] = globals()[f'{shape}_{name}']
+
+    peak_function, shape_params = SHAPES[shape]
+
+    def width_args(i):
+        return ''.join(f', g{i}_{name}' for name, *_ in shape_params)
+
+    first_gaussian = lmfit.Model(peak_function, prefix=f'g0_')
 
     if model is None:
         model = first_gaussian
@@ -303,10 +470,14 @@ def MultipleGaussianFit(x_data: list, y_data: list, peak_markers: list, bin_widt
     params['g0_sigma'].set(min=0)  # Initial constraint for the first Gaussian's sigma
     params[f"g0_amplitude"].set(min=0)
 
-    params.add(f'g0_fwhm', expr=f'2.35482 * g0_sigma')  # FWHM = 2 * sqrt(2 * ln(2)) * sigma
+    # the shape parameters of the first peak are shared by the others
+    for name, value, min_value, max_value in shape_params:
+        params[f'g0_{name}'].set(value=value, min=min_value, max=max_value)
+
+    params.add(f'g0_fwhm', expr=f'{shape}_fwhm(g0_sigma{width_args(0)})')
     params[f"g0_fwhm"].set(min=0)
 
-    params.add(f'g0_area', expr=f'g0_amplitude * sqrt(2 * pi) * g0_sigma / {bin_width}')  # Area under the Gaussian
+    params.add(f'g0_area', expr=f'{shape}_area(g0_amplitude, g0_sigma{width_args(0)}) / {bin_width}')  # Area under the peak
     params[f"g0_area"].set(min=0)
 
     if not free_position:
@@ -316,7 +487,7 @@ def MultipleGaussianFit(x_data: list, y_data: list, peak_markers: list, bin_widt
 
     # Add additional Gaussians
     for i, peak in enumerate(peak_markers[1:], start=1):
-        g = lmfit.Model(gaussian, prefix=f'g{i}_')
+        g = lmfit.Model(peak_function, prefix=f'g{i}_')
         model += g
 
         estimated_amplitude = 1000
@@ -326,10 +497,13 @@ def MultipleGaussianFit(x_data: list, y_data: list, peak_markers: list, bin_widt
         max_mean = peak_markers[i+1] if i + 1 < len(peak_markers) else x_data[-1]
         params[f'g{i}_mean'].set(min=min_mean, max=max_mean)
 
-        params.add(f'g{i}_fwhm', expr=f'2.35482 * g{i}_sigma')
+        for name, *_ in shape_params:
+            params[f'g{i}_{name}'].set(expr=f'g0_{name}')
+
+        params.add(f'g{i}_fwhm', expr=f'{shape}_fwhm(g{i}_sigma{width_args(i)})')
         params[f"g{i}_fwhm"].set(min=0)
 
-        params.add(f'g{i}_area', expr=f'g{i}_amplitude * sqrt(2 * pi) * g{i}_sigma / {bin_width}')
+        params.add(f'g{i}_area', expr=f'{shape}_area(g{i}_amplitude, g{i}_sigma{width_args(i)}) / {bin_width}')
         params[f"g{i}_area"].set(min=0)
 
         if equal_sigma:
@@ -397,7 +571,18 @@ def MultipleGaussianFit(x_data: list, y_data: list, peak_markers: list, bin_widt
 
     fit_report = str(result.fit_report())
 
-    return gaussian_params, background_params, x_data_line, y_data_line, fit_report
+    peak_shape_params = []
+    for name, *_ in shape_params:
+        parameter = result.params[f'g0_{name}']
+        peak_shape_params.append((name, float(parameter.value), parameter.stderr or 0.0))
+
+    # the text lmfit.model.save_modelresult writes, empty when the model can't be serialized
+    try:
+        saved = result.dumps()
+    except Exception:
+        saved = ''
+
+    return gaussian_params, background_params, x_data_line, y_data_line, fit_report, peak_shape_params, saved
 "#;
 
             // Compile the Python code into a module
@@ -662,6 +847,33 @@ def MultipleGaussianFit(x_data: list, y_data: list, peak_markers: list, bin_widt
                         )?;
                     }
                 }
+                BackgroundModel::Step(ref params) | BackgroundModel::StepLinear(ref params) => {
+                    let linear = matches!(self.background_model, BackgroundModel::StepLinear(_));
+                    let step_params: Vec<(String, f64, f64, f64, bool)> =
+                        if let Some(BackgroundResult::Step(ref fitter)) = &self.background_result {
+                            // Use the fitted values and hold them fixed
+                            fitter
+                                .paramaters
+                                .parameters(linear)
+                                .into_iter()
+                                .map(|p| {
+                                    let value = p.value.unwrap_or(p.initial_guess);
+                                    (p.name, p.min, p.max, value, false)
+                                })
+                                .collect()
+                        } else {
+                            params
+                                .with_estimates(&self.data)
+                                .parameters(linear)
+                                .into_iter()
+                                .map(|p| (p.name, p.min, p.max, p.initial_guess, p.vary))
+                                .collect()
+                        };
+
+                    background_params
+                        .set_item("bg_type", if linear { "step_linear" } else { "step" })?;
+                    background_params.set_item("step", step_params)?;
+                }
                 BackgroundModel::None => {
                     background_params.set_item("bg_type", "none")?;
                 }
@@ -677,148 +889,211 @@ def MultipleGaussianFit(x_data: list, y_data: list, peak_markers: list, bin_widt
                 equal_sigma,
                 free_position,
                 background_params,
+                self.fit_settings.shape.python_name(),
             ))?;
 
-            let gaussian_params =
-                result
-                    .get_item(0)?
-                    .extract::<Vec<(f64, f64, f64, f64, f64, f64, f64, f64, f64, f64)>>()?;
+            let gaussian_params = result.get_item(0)?.extract::<Vec<PeakValues>>()?;
             let background_params = result.get_item(1)?.extract::<Vec<(String, f64, f64)>>()?;
             let x_composition = result.get_item(2)?.extract::<Vec<f64>>()?;
             let y_composition = result.get_item(3)?.extract::<Vec<f64>>()?;
             let fit_report = result.get_item(4)?.extract::<String>()?;
+            let shape_params = result.get_item(5)?.extract::<Vec<(String, f64, f64)>>()?;
+            let saved = result.get_item(6)?.extract::<String>()?;
 
-            self.peak_markers.clear();
+            self.set_result(
+                gaussian_params,
+                shape_params,
+                background_params,
+                x_composition,
+                y_composition,
+                fit_report,
+            );
+            self.lmfit_result = saved;
 
-            for (amp, amp_err, mean, mean_err, sigma, sigma_err, fwhm, fwhm_err, area, area_err) in
-                gaussian_params
-            {
-                log::info!("Amplitude: {:.3} ± {:.3}, Mean: {:.3} ± {:.3}, Sigma: {:.3} ± {:.3}, FWHM: {:.3} ± {:.3}, Area: {:.3} ± {:.3}", 
-                            amp, amp_err, mean, mean_err, sigma, sigma_err, fwhm, fwhm_err, area, area_err);
+            Ok(())
+        })
+    }
 
-                self.peak_markers.push(mean);
+    // Stores the peaks, the background when it was fit together with them, and the composition
+    pub fn set_result(
+        &mut self,
+        gaussian_params: Vec<PeakValues>,
+        shape_params: Vec<(String, f64, f64)>,
+        background_params: Vec<(String, f64, f64)>,
+        x_composition: Vec<f64>,
+        y_composition: Vec<f64>,
+        fit_report: String,
+    ) {
+        self.peak_markers.clear();
+
+        for (amp, amp_err, mean, mean_err, sigma, sigma_err, fwhm, fwhm_err, area, area_err) in
+            gaussian_params
+        {
+            log::info!("Amplitude: {:.3} ± {:.3}, Mean: {:.3} ± {:.3}, Sigma: {:.3} ± {:.3}, FWHM: {:.3} ± {:.3}, Area: {:.3} ± {:.3}", 
+                        amp, amp_err, mean, mean_err, sigma, sigma_err, fwhm, fwhm_err, area, area_err);
+
+            self.peak_markers.push(mean);
+
+            // Create the GaussianParameters for each set of values
+            let mut gaussian_param = GaussianParameters::new(
+                (amp, amp_err),
+                (mean, mean_err),
+                (sigma, sigma_err),
+                (fwhm, fwhm_err),
+                (area, area_err),
+            );
+            gaussian_param.shape = self.fit_settings.shape;
+            gaussian_param.shape_params = shape_params
+                .iter()
+                .map(|(name, value, uncertainty)| Parameter {
+                    name: name.clone(),
+                    value: Some(*value),
+                    uncertainty: Some(*uncertainty),
+                    ..Default::default()
+                })
+                .collect();
 
-                // Create the GaussianParameters for each set of values
-                let mut gaussian_param = GaussianParameters::new(
-                    (amp, amp_err),
-                    (mean, mean_err),
-                    (sigma, sigma_err),
-                    (fwhm, fwhm_err),
-                    (area, area_err),
-                );
+            // Generate the fit points for this Gaussian, using 100 points (or as many as needed)
+            gaussian_param.generate_fit_points(100);
 
-                // Generate the fit points for this Gaussian, using 100 points (or as many as needed)
-                gaussian_param.generate_fit_points(100);
+            self.fit_result.push(gaussian_param);
+        }
 
-                self.fit_result.push(gaussian_param);
-            }
+        if self.background_result.is_none() {
+            let min_x = self.data.x.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max_x = self
+                .data
+                .x
+                .iter()
+                .cloned()
+                .fold(f64::NEG_INFINITY, f64::max);
 
-            if self.background_result.is_none() {
-                let min_x = x_data.iter().cloned().fold(f64::INFINITY, f64::min);
-                let max_x = x_data.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-
-                match self.background_model {
-                    // Handle the Linear case
-                    BackgroundModel::Linear(_) => {
-                        let slope = background_params[0].1;
-                        let slope_err = background_params[0].2;
-                        let intercept = background_params[1].1;
-                        let intercept_err = background_params[1].2;
-
-                        let linear_fitter = LinearFitter::new_from_parameters(
-                            (slope, slope_err),
-                            (intercept, intercept_err),
-                            min_x,
-                            max_x,
-                        );
-
-                        self.background_result = Some(BackgroundResult::Linear(linear_fitter));
-                    }
+            match self.background_model {
+                // Handle the Linear case
+                BackgroundModel::Linear(_) => {
+                    let slope = background_params[0].1;
+                    let slope_err = background_params[0].2;
+                    let intercept = background_params[1].1;
+                    let intercept_err = background_params[1].2;
+
+                    let linear_fitter = LinearFitter::new_from_parameters(
+                        (slope, slope_err),
+                        (intercept, intercept_err),
+                        min_x,
+                        max_x,
+                    );
+
+                    self.background_result = Some(BackgroundResult::Linear(linear_fitter));
+                }
 
-                    // Handle the Exponential case
-                    BackgroundModel::Exponential(_) => {
-                        let amplitude = background_params[0].1;
-                        let amplitude_err = background_params[0].2;
-                        let decay = background_params[1].1;
-                        let decay_err = background_params[1].2;
-
-                        let exponential_fitter = ExponentialFitter::new_from_parameters(
-                            (amplitude, amplitude_err),
-                            (decay, decay_err),
-                            min_x,
-                            max_x,
-                        );
-
-                        self.background_result =
-                            Some(BackgroundResult::Exponential(exponential_fitter));
-                    }
+                // Handle the Exponential case
+                BackgroundModel::Exponential(_) => {
+                    let amplitude = background_params[0].1;
+                    let amplitude_err = background_params[0].2;
+                    let decay = background_params[1].1;
+                    let decay_err = background_params[1].2;
+
+                    let exponential_fitter = ExponentialFitter::new_from_parameters(
+                        (amplitude, amplitude_err),
+                        (decay, decay_err),
+                        min_x,
+                        max_x,
+                    );
+
+                    self.background_result =
+                        Some(BackgroundResult::Exponential(exponential_fitter));
+                }
 
-                    // Handle the Quadratic case (to be implemented similarly)
-                    BackgroundModel::Quadratic(_) => {
-                        let a = background_params[0].1;
-                        let a_err = background_params[0].2;
-                        let b = background_params[1].1;
-                        let b_err = background_params[1].2;
-                        let c = background_params[2].1;
-                        let c_err = background_params[2].2;
-
-                        let quadratic_fitter = QuadraticFitter::new_from_parameters(
-                            (a, a_err),
-                            (b, b_err),
-                            (c, c_err),
-                            min_x,
-                            max_x,
-                        );
-
-                        self.background_result =
-                            Some(BackgroundResult::Quadratic(quadratic_fitter));
-                    }
+                // Handle the Quadratic case (to be implemented similarly)
+                BackgroundModel::Quadratic(_) => {
+                    let a = background_params[0].1;
+                    let a_err = background_params[0].2;
+                    let b = background_params[1].1;
+                    let b_err = background_params[1].2;
+                    let c = background_params[2].1;
+                    let c_err = background_params[2].2;
+
+                    let quadratic_fitter = QuadraticFitter::new_from_parameters(
+                        (a, a_err),
+                        (b, b_err),
+                        (c, c_err),
+                        min_x,
+                        max_x,
+                    );
+
+                    self.background_result = Some(BackgroundResult::Quadratic(quadratic_fitter));
+                }
 
-                    // Handle the PowerLaw case (to be implemented similarly)
-                    BackgroundModel::PowerLaw(_) => {
-                        let amplitude = background_params[0].1;
-                        let amplitude_err = background_params[0].2;
-                        let exponent = background_params[1].1;
-                        let exponent_err = background_params[1].2;
-
-                        let powerlaw_fitter = PowerLawFitter::new_from_parameters(
-                            (amplitude, amplitude_err),
-                            (exponent, exponent_err),
-                            min_x,
-                            max_x,
-                        );
-
-                        self.background_result = Some(BackgroundResult::PowerLaw(powerlaw_fitter));
-                    }
+                // Handle the PowerLaw case (to be implemented similarly)
+                BackgroundModel::PowerLaw(_) => {
+                    let amplitude = background_params[0].1;
+                    let amplitude_err = background_params[0].2;
+                    let exponent = background_params[1].1;
+                    let exponent_err = background_params[1].2;
+
+                    let powerlaw_fitter = PowerLawFitter::new_from_parameters(
+                        (amplitude, amplitude_err),
+                        (exponent, exponent_err),
+                        min_x,
+                        max_x,
+                    );
+
+                    self.background_result = Some(BackgroundResult::PowerLaw(powerlaw_fitter));
+                }
+
+                BackgroundModel::Step(_) | BackgroundModel::StepLinear(_) => {
+                    let linear = matches!(self.background_model, BackgroundModel::StepLinear(_));
+                    let values: Vec<(f64, f64)> = background_params
+                        .iter()
+                        .map(|(_, value, uncertainty)| (*value, *uncertainty))
+                        .collect();
 
-                    BackgroundModel::None => {}
+                    let step_fitter =
+                        StepFitter::new_from_parameters(&values, linear, min_x, max_x);
+
+                    self.background_result = Some(BackgroundResult::Step(step_fitter));
                 }
-            }
 
-            // Create the composition line
-            let fit_points = x_composition
-                .iter()
-                .zip(y_composition.iter())
-                .map(|(&x, &y)| [x, y])
-                .collect();
-            self.fit_points = fit_points;
+                BackgroundModel::None => {}
+            }
+        }
 
-            self.fit_report = fit_report;
+        // Create the composition line
+        let fit_points = x_composition
+            .iter()
+            .zip(y_composition.iter())
+            .map(|(&x, &y)| [x, y])
+            .collect();
+        self.fit_points = fit_points;
 
-            Ok(())
-        })
+        self.fit_report = fit_report;
     }
 
-    pub fn fit_params_ui(&self, ui: &mut egui::Ui, skip_one: bool) {
+    pub fn fit_params_ui(
+        &self,
+        ui: &mut egui::Ui,
+        skip_one: bool,
+        calibration: Option<&Calibration>,
+    ) {
         for (i, params) in self.fit_result.iter().enumerate() {
             if skip_one && i != 0 {
                 ui.label("");
             }
             ui.label(format!("{}", i));
-            params.params_ui(ui);
+            params.params_ui(ui, calibration);
 
             if i == 0 {
                 ui.menu_button("Fit Report", |ui| {
+                    if !self.lmfit_result.is_empty()
+                        && ui
+                            .button("Save lmfit Result…")
+                            .on_hover_text("Write the lmfit ModelResult as a .sav file, load it with lmfit.model.load_modelresult")
+                            .clicked()
+                    {
+                        self.save_lmfit_result();
+                        ui.close_menu();
+                    }
+
                     egui::ScrollArea::vertical().show(ui, |ui| {
                         ui.horizontal_wrapped(|ui| {
                             ui.label(self.fit_report.clone());
@@ -830,4 +1105,16 @@ def MultipleGaussianFit(x_data: list, y_data: list, peak_markers: list, bin_widt
             ui.end_row();
         }
     }
+
+    fn save_lmfit_result(&self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name("fit.sav")
+            .add_filter("lmfit ModelResult", &["sav"])
+            .save_file()
+        {
+            if let Err(e) = std::fs::write(&path, &self.lmfit_result) {
+                log::error!("Error writing {}: {}", path.display(), e);
+            }
+        }
+    }
 }
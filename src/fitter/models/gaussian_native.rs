@@ -0,0 +1,456 @@
+use levenberg_marquardt::{LeastSquaresProblem, LevenbergMarquardt};
+use nalgebra::{DMatrix, DVector, Dyn, Owned};
+
+use super::background_native::{background_at, background_parameters};
+use super::gaussian::{GaussianFitter, PeakValues};
+use super::peak_shape::PeakShape;
+use crate::fitter::common::Parameter;
+use crate::fitter::main_fitter::{BackgroundModel, BackgroundResult};
+
+// Sum of peaks on a background, the same model the lmfit backend builds.
+// Parameters are laid out as the amplitudes, the means when they are free,
+// one sigma (equal) or one per peak, the shared shape parameters, then the
+// free background parameters.
+struct MultipleGaussian {
+    x: Vec<f64>,
+    y: Vec<f64>,
+    markers: Vec<f64>,
+    mean_bounds: Vec<(f64, f64)>,
+    equal_sigma: bool,
+    free_position: bool,
+    shape: PeakShape,
+    background_model: BackgroundModel,
+    background: Vec<Parameter>, // value holds the fixed value for the ones that do not vary
+    params: DVector<f64>,
+}
+
+impl MultipleGaussian {
+    fn peaks(&self) -> usize {
+        self.markers.len()
+    }
+
+    fn mean_index(&self, peak: usize) -> Option<usize> {
+        self.free_position.then_some(self.peaks() + peak)
+    }
+
+    fn sigma_index(&self, peak: usize) -> usize {
+        let start = if self.free_position {
+            2 * self.peaks()
+        } else {
+            self.peaks()
+        };
+        if self.equal_sigma {
+            start
+        } else {
+            start + peak
+        }
+    }
+
+    fn shape_start(&self) -> usize {
+        self.sigma_index(0) + if self.equal_sigma { 1 } else { self.peaks() }
+    }
+
+    fn background_start(&self) -> usize {
+        self.shape_start() + self.shape.parameter_names().len()
+    }
+
+    fn amplitude(&self, params: &DVector<f64>, peak: usize) -> f64 {
+        params[peak].abs()
+    }
+
+    fn mean(&self, params: &DVector<f64>, peak: usize) -> f64 {
+        match self.mean_index(peak) {
+            Some(index) => {
+                let (min, max) = self.mean_bounds[peak];
+                params[index].clamp(min, max)
+            }
+            None => self.markers[peak],
+        }
+    }
+
+    fn sigma(&self, params: &DVector<f64>, peak: usize) -> f64 {
+        params[self.sigma_index(peak)].abs()
+    }
+
+    // Tail fraction between 0 and 1, widths positive
+    fn shape_values(&self, params: &DVector<f64>) -> Vec<f64> {
+        let start = self.shape_start();
+        self.shape
+            .parameter_names()
+            .iter()
+            .enumerate()
+            .map(|(index, &name)| {
+                let value = params[start + index];
+                if name == "tail_fraction" {
+                    value.clamp(0.0, 1.0)
+                } else {
+                    value.abs().max(1e-9)
+                }
+            })
+            .collect()
+    }
+
+    fn background_values(&self, params: &DVector<f64>) -> Vec<f64> {
+        let mut index = self.background_start();
+        self.background
+            .iter()
+            .map(|param| {
+                if param.vary {
+                    index += 1;
+                    params[index - 1].clamp(param.min, param.max)
+                } else {
+                    param.value.unwrap_or(param.initial_guess)
+                }
+            })
+            .collect()
+    }
+
+    fn background_at(&self, values: &[f64], x: f64) -> f64 {
+        background_at(&self.background_model, values, x)
+    }
+
+    fn evaluate(&self, params: &DVector<f64>, x: f64) -> f64 {
+        let shape = self.shape_values(params);
+        let peaks: f64 = (0..self.peaks())
+            .map(|peak| {
+                self.shape.evaluate(
+                    x,
+                    self.amplitude(params, peak),
+                    self.mean(params, peak),
+                    self.sigma(params, peak),
+                    &shape,
+                )
+            })
+            .sum();
+        peaks + self.background_at(&self.background_values(params), x)
+    }
+
+    fn residuals_at(&self, params: &DVector<f64>) -> DVector<f64> {
+        DVector::from_iterator(
+            self.x.len(),
+            self.x
+                .iter()
+                .zip(&self.y)
+                .map(|(&x, &y)| self.evaluate(params, x) - y),
+        )
+    }
+
+    // Central differences, the background models are not all smooth in every parameter
+    fn jacobian_at(&self, params: &DVector<f64>) -> DMatrix<f64> {
+        let mut jacobian = DMatrix::zeros(self.x.len(), params.len());
+        for column in 0..params.len() {
+            let step = 1e-6 * params[column].abs().max(1e-3);
+            let mut up = params.clone();
+            let mut down = params.clone();
+            up[column] += step;
+            down[column] -= step;
+            let difference = (self.residuals_at(&up) - self.residuals_at(&down)) / (2.0 * step);
+            jacobian.set_column(column, &difference);
+        }
+        jacobian
+    }
+
+    // Uncertainty of a quantity derived from the parameters, g^T C g with a numerical gradient
+    fn propagate(
+        &self,
+        params: &DVector<f64>,
+        covariance: Option<&DMatrix<f64>>,
+        quantity: impl Fn(&DVector<f64>) -> f64,
+    ) -> f64 {
+        let Some(covariance) = covariance else {
+            return 0.0;
+        };
+        let gradient = DVector::from_iterator(
+            params.len(),
+            (0..params.len()).map(|column| {
+                let step = 1e-6 * params[column].abs().max(1e-3);
+                let mut up = params.clone();
+                let mut down = params.clone();
+                up[column] += step;
+                down[column] -= step;
+                (quantity(&up) - quantity(&down)) / (2.0 * step)
+            }),
+        );
+        (gradient.transpose() * covariance * &gradient)[(0, 0)]
+            .max(0.0)
+            .sqrt()
+    }
+}
+
+impl LeastSquaresProblem<f64, Dyn, Dyn> for MultipleGaussian {
+    type ResidualStorage = Owned<f64, Dyn>;
+    type JacobianStorage = Owned<f64, Dyn, Dyn>;
+    type ParameterStorage = Owned<f64, Dyn>;
+
+    fn set_params(&mut self, params: &DVector<f64>) {
+        self.params.copy_from(params);
+    }
+
+    fn params(&self) -> DVector<f64> {
+        self.params.clone()
+    }
+
+    fn residuals(&self) -> Option<DVector<f64>> {
+        Some(self.residuals_at(&self.params))
+    }
+
+    fn jacobian(&self) -> Option<DMatrix<f64>> {
+        Some(self.jacobian_at(&self.params))
+    }
+}
+
+fn fitted_background_parameters(result: &BackgroundResult) -> Vec<Parameter> {
+    match result {
+        BackgroundResult::Linear(fit) => {
+            background_parameters(&BackgroundModel::Linear(fit.paramaters.clone()), &fit.data)
+        }
+        BackgroundResult::Quadratic(fit) => background_parameters(
+            &BackgroundModel::Quadratic(fit.paramaters.clone()),
+            &fit.data,
+        ),
+        BackgroundResult::Exponential(fit) => background_parameters(
+            &BackgroundModel::Exponential(fit.paramaters.clone()),
+            &fit.data,
+        ),
+        BackgroundResult::PowerLaw(fit) => background_parameters(
+            &BackgroundModel::PowerLaw(fit.paramaters.clone()),
+            &fit.data,
+        ),
+        BackgroundResult::Step(fit) => fit.paramaters.parameters(fit.linear),
+    }
+}
+
+impl GaussianFitter {
+    // Levenberg-Marquardt fit without Python. Uncertainties come from the covariance
+    // matrix (J^T J)^-1 scaled by the reduced chi-square, like lmfit does by default.
+    pub fn native_fit(&mut self) -> Result<(), String> {
+        let x = self.data.x.clone();
+        let y = self.data.y.clone();
+        let (Some(&x_first), Some(&x_last)) = (x.first(), x.last()) else {
+            return Err("No data to fit".to_string());
+        };
+
+        let mut markers = self.peak_markers.clone();
+        if markers.is_empty() {
+            let max_index = (0..y.len())
+                .max_by(|&a, &b| y[a].total_cmp(&y[b]))
+                .unwrap_or(0);
+            markers.push(x[max_index]);
+        }
+        markers.sort_by(f64::total_cmp);
+
+        let mean_bounds: Vec<(f64, f64)> = (0..markers.len())
+            .map(|peak| {
+                let min = if peak == 0 {
+                    x_first
+                } else {
+                    markers[peak - 1]
+                };
+                let max = markers.get(peak + 1).copied().unwrap_or(x_last);
+                (min, max)
+            })
+            .collect();
+
+        // a background fit beforehand is held fixed
+        let background = match &self.background_result {
+            Some(result) => fitted_background_parameters(result)
+                .into_iter()
+                .map(|param| Parameter {
+                    vary: false,
+                    ..param
+                })
+                .collect(),
+            None => background_parameters(&self.background_model, &self.data),
+        };
+
+        let mut model = MultipleGaussian {
+            x: x.clone(),
+            y: y.clone(),
+            markers: markers.clone(),
+            mean_bounds,
+            equal_sigma: self.fit_settings.equal_stdev,
+            free_position: self.fit_settings.free_position,
+            shape: self.fit_settings.shape,
+            background_model: self.background_model.clone(),
+            background,
+            params: DVector::zeros(0),
+        };
+
+        // initial guesses from the data at the markers
+        let background_guess: Vec<f64> = model
+            .background
+            .iter()
+            .map(|param| param.value.unwrap_or(param.initial_guess))
+            .collect();
+        let sigma_guess =
+            ((x_last - x_first) / (8.0 * markers.len() as f64)).max(self.fit_settings.bin_width);
+        let mut initial = Vec::new();
+        for &marker in &markers {
+            let nearest = (0..x.len())
+                .min_by(|&a, &b| (x[a] - marker).abs().total_cmp(&(x[b] - marker).abs()))
+                .unwrap_or(0);
+            let amplitude = y[nearest] - model.background_at(&background_guess, marker);
+            initial.push(amplitude.max(1.0));
+        }
+        if model.free_position {
+            initial.extend(&markers);
+        }
+        let sigmas = if model.equal_sigma { 1 } else { markers.len() };
+        initial.extend(std::iter::repeat(sigma_guess).take(sigmas));
+        initial.extend(model.shape.initial_parameters(sigma_guess));
+        for param in &model.background {
+            if param.vary {
+                initial.push(param.initial_guess);
+            }
+        }
+        model.params = DVector::from_vec(initial);
+
+        let points = x.len();
+        let variables = model.params.len();
+        if points <= variables {
+            return Err(format!(
+                "{} points are not enough to fit {} parameters",
+                points, variables
+            ));
+        }
+
+        log::info!(
+            "Fitting {} model with the native backend",
+            model.shape.label()
+        );
+
+        let (model, report) = LevenbergMarquardt::new()
+            .with_patience(1000)
+            .minimize(model);
+        if !report.termination.was_successful() {
+            return Err(format!("Fit did not converge: {:?}", report.termination));
+        }
+
+        let params = model.params.clone();
+        let residuals = model.residuals_at(&params);
+        let chi2 = residuals.norm_squared();
+        let reduced_chi2 = chi2 / (points - variables) as f64;
+
+        let jacobian = model.jacobian_at(&params);
+        let covariance = (jacobian.transpose() * &jacobian)
+            .try_inverse()
+            .map(|inverse| inverse * reduced_chi2);
+        if covariance.is_none() {
+            log::warn!("Covariance matrix is singular, uncertainties are not available");
+        }
+        let variance = |index: usize| covariance.as_ref().map_or(0.0, |c| c[(index, index)]);
+        let error = |index: usize| variance(index).max(0.0).sqrt();
+
+        let bin_width = self.fit_settings.bin_width;
+        let fwhm = |p: &DVector<f64>, peak: usize| {
+            model
+                .shape
+                .fwhm(model.sigma(p, peak), &model.shape_values(p))
+        };
+        let area = |p: &DVector<f64>, peak: usize| {
+            model.shape.area(
+                model.amplitude(p, peak),
+                model.sigma(p, peak),
+                &model.shape_values(p),
+            ) / bin_width
+        };
+
+        let gaussian_params: Vec<PeakValues> = (0..markers.len())
+            .map(|peak| {
+                (
+                    model.amplitude(&params, peak),
+                    error(peak),
+                    model.mean(&params, peak),
+                    model.mean_index(peak).map_or(0.0, error),
+                    model.sigma(&params, peak),
+                    error(model.sigma_index(peak)),
+                    fwhm(&params, peak),
+                    model.propagate(&params, covariance.as_ref(), |p| fwhm(p, peak)),
+                    area(&params, peak),
+                    model.propagate(&params, covariance.as_ref(), |p| area(p, peak)),
+                )
+            })
+            .collect();
+
+        let shape_params: Vec<(String, f64, f64)> = model
+            .shape
+            .parameter_names()
+            .iter()
+            .zip(model.shape_values(&params))
+            .enumerate()
+            .map(|(index, (name, value))| {
+                (name.to_string(), value, error(model.shape_start() + index))
+            })
+            .collect();
+
+        let background_values = model.background_values(&params);
+        let mut background_index = model.background_start();
+        let background_params: Vec<(String, f64, f64)> = model
+            .background
+            .iter()
+            .zip(&background_values)
+            .map(|(param, &value)| {
+                let uncertainty = if param.vary {
+                    background_index += 1;
+                    error(background_index - 1)
+                } else {
+                    0.0
+                };
+                (format!("bg_{}", param.name), value, uncertainty)
+            })
+            .collect();
+
+        let line_points = 5 * points;
+        let step = (x_last - x_first) / (line_points - 1).max(1) as f64;
+        let (x_composition, y_composition): (Vec<f64>, Vec<f64>) = (0..line_points)
+            .map(|i| {
+                let x = x_first + i as f64 * step;
+                (x, model.evaluate(&params, x))
+            })
+            .unzip();
+
+        let mut fit_report = format!(
+            "[[Model]]\n    {} {} peak(s), native Levenberg-Marquardt\n\
+             [[Fit Statistics]]\n    \
+             # function evals   = {}\n    \
+             # data points      = {}\n    \
+             # variables        = {}\n    \
+             chi-square         = {:.6}\n    \
+             reduced chi-square = {:.6}\n    \
+             termination        = {:?}\n\
+             [[Variables]]\n",
+            markers.len(),
+            model.shape.label(),
+            report.number_of_evaluations,
+            points,
+            variables,
+            chi2,
+            reduced_chi2,
+            report.termination,
+        );
+        for (peak, values) in gaussian_params.iter().enumerate() {
+            let (amplitude, amplitude_err, mean, mean_err, sigma, sigma_err, .., area, area_err) =
+                *values;
+            fit_report.push_str(&format!(
+                "    g{peak}_amplitude: {amplitude:.6} +/- {amplitude_err:.6}\n    \
+                 g{peak}_mean:      {mean:.6} +/- {mean_err:.6}\n    \
+                 g{peak}_sigma:     {sigma:.6} +/- {sigma_err:.6}\n    \
+                 g{peak}_area:      {area:.6} +/- {area_err:.6}\n"
+            ));
+        }
+        for (name, value, uncertainty) in shape_params.iter().chain(&background_params) {
+            fit_report.push_str(&format!("    {name}: {value:.6} +/- {uncertainty:.6}\n"));
+        }
+
+        self.set_result(
+            gaussian_params,
+            shape_params,
+            background_params,
+            x_composition,
+            y_composition,
+            fit_report,
+        );
+
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
 use crate::fitter::common::{Data, Parameter};
+use crate::fitter::main_fitter::BackgroundModel;
+use crate::fitter::models::background_native::native_background_fit;
+#[cfg(feature = "python")]
 use pyo3::{prelude::*, types::PyModule};
 
 #[derive(PartialEq, Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -107,6 +110,22 @@ impl LinearFitter {
         }
     }
 
+    pub fn native_fit(&mut self) -> Result<(), String> {
+        let model = BackgroundModel::Linear(self.paramaters.clone());
+        let fit = native_background_fit(&model, &self.data)?;
+
+        let params = &mut self.paramaters;
+        let targets = [&mut params.slope, &mut params.intercept];
+        for (param, &(value, uncertainty)) in targets.into_iter().zip(&fit.values) {
+            param.value = Some(value);
+            param.uncertainty = Some(uncertainty);
+        }
+        self.fit_points = fit.fit_points;
+        self.fit_report = fit.fit_report;
+        Ok(())
+    }
+
+    #[cfg(feature = "python")]
     pub fn lmfit(&mut self) -> PyResult<()> {
         log::info!("Fitting data with a linear line using `lmfit`.");
         Python::with_gil(|py| {
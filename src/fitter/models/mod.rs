@@ -1,5 +1,9 @@
+pub mod background_native;
 pub mod exponential;
 pub mod gaussian;
+pub mod gaussian_native;
 pub mod linear;
+pub mod peak_shape;
 pub mod powerlaw;
 pub mod quadratic;
+pub mod step;
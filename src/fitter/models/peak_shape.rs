@@ -0,0 +1,181 @@
+use std::f64::consts::{LN_2, PI, SQRT_2};
+
+const FWHM_PER_SIGMA: f64 = 2.35482;
+
+// Line shape of the peaks in a fit region. Every shape has a height-like amplitude,
+// a mean, and a width `sigma`, the extra parameters are shared by the peaks of a region.
+//  Gaussian:   A exp(-(x - mean)^2 / 2 sigma^2)
+//  Tailed:     GF3 style, (1 - R) Gaussian + R A exp((x - mean) / beta) erfc((x - mean) / (sqrt(2) sigma) + sigma / (sqrt(2) beta))
+//  Voigt:      pseudo-Voigt of a Gaussian sigma and a Lorentzian half width gamma
+//  Lorentzian: A sigma^2 / ((x - mean)^2 + sigma^2), sigma is the half width
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum PeakShape {
+    #[default]
+    Gaussian,
+    Tailed,
+    Voigt,
+    Lorentzian,
+}
+
+impl PeakShape {
+    pub const ALL: [PeakShape; 4] = [
+        PeakShape::Gaussian,
+        PeakShape::Tailed,
+        PeakShape::Voigt,
+        PeakShape::Lorentzian,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PeakShape::Gaussian => "Gaussian",
+            PeakShape::Tailed => "Gaussian + Tail",
+            PeakShape::Voigt => "Voigt",
+            PeakShape::Lorentzian => "Lorentzian",
+        }
+    }
+
+    // Names of the extra shape parameters
+    pub fn parameter_names(&self) -> &'static [&'static str] {
+        match self {
+            PeakShape::Tailed => &["tail_fraction", "tail_decay"],
+            PeakShape::Voigt => &["gamma"],
+            PeakShape::Gaussian | PeakShape::Lorentzian => &[],
+        }
+    }
+
+    // Starting values of the extra parameters for a peak of width sigma
+    pub fn initial_parameters(&self, sigma: f64) -> Vec<f64> {
+        match self {
+            PeakShape::Tailed => vec![0.1, sigma],
+            PeakShape::Voigt => vec![0.5 * sigma],
+            PeakShape::Gaussian | PeakShape::Lorentzian => Vec::new(),
+        }
+    }
+
+    // Name of the shape in the lmfit script
+    #[cfg(feature = "python")]
+    pub fn python_name(&self) -> &'static str {
+        match self {
+            PeakShape::Gaussian => "gaussian",
+            PeakShape::Tailed => "tailed",
+            PeakShape::Voigt => "voigt",
+            PeakShape::Lorentzian => "lorentzian",
+        }
+    }
+
+    pub fn evaluate(&self, x: f64, amplitude: f64, mean: f64, sigma: f64, extra: &[f64]) -> f64 {
+        let dx = x - mean;
+        match self {
+            PeakShape::Gaussian => amplitude * gaussian(dx, sigma),
+            PeakShape::Tailed => {
+                let (fraction, decay) = (extra[0], extra[1]);
+                let tail = exp_erfc(dx / decay, dx / (SQRT_2 * sigma) + sigma / (SQRT_2 * decay));
+                amplitude * ((1.0 - fraction) * gaussian(dx, sigma) + fraction * tail)
+            }
+            PeakShape::Voigt => {
+                let (fwhm, eta) = pseudo_voigt(sigma, extra[0]);
+                let gaussian_sigma = fwhm / FWHM_PER_SIGMA;
+                amplitude
+                    * (eta * lorentzian(dx, 0.5 * fwhm)
+                        + (1.0 - eta) * gaussian(dx, gaussian_sigma))
+            }
+            PeakShape::Lorentzian => amplitude * lorentzian(dx, sigma),
+        }
+    }
+
+    pub fn fwhm(&self, sigma: f64, extra: &[f64]) -> f64 {
+        match self {
+            PeakShape::Gaussian | PeakShape::Tailed => FWHM_PER_SIGMA * sigma,
+            PeakShape::Voigt => pseudo_voigt(sigma, extra[0]).0,
+            PeakShape::Lorentzian => 2.0 * sigma,
+        }
+    }
+
+    // Integral over x, divide by the bin width for counts
+    pub fn area(&self, amplitude: f64, sigma: f64, extra: &[f64]) -> f64 {
+        let gaussian_area = |sigma: f64| sigma * (2.0 * PI).sqrt();
+        match self {
+            PeakShape::Gaussian => amplitude * gaussian_area(sigma),
+            PeakShape::Tailed => {
+                let (fraction, decay) = (extra[0], extra[1]);
+                let tail_area = 2.0 * decay * (-0.5 * (sigma / decay).powi(2)).exp();
+                amplitude * ((1.0 - fraction) * gaussian_area(sigma) + fraction * tail_area)
+            }
+            PeakShape::Voigt => {
+                let (fwhm, eta) = pseudo_voigt(sigma, extra[0]);
+                amplitude * (eta * PI * 0.5 * fwhm + (1.0 - eta) * 0.5 * fwhm * (PI / LN_2).sqrt())
+            }
+            PeakShape::Lorentzian => amplitude * PI * sigma,
+        }
+    }
+
+    // Widths below and above the mean that the decomposition extent is a multiple of
+    pub fn extent(&self, sigma: f64, extra: &[f64]) -> (f64, f64) {
+        match self {
+            PeakShape::Tailed => (sigma + extra[1].abs(), sigma),
+            PeakShape::Voigt => {
+                let width = 0.5 * self.fwhm(sigma, extra);
+                (width, width)
+            }
+            PeakShape::Gaussian | PeakShape::Lorentzian => (sigma, sigma),
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_id_salt("peak_shape")
+            .selected_text(self.label())
+            .show_ui(ui, |ui| {
+                for shape in PeakShape::ALL {
+                    ui.selectable_value(self, shape, shape.label());
+                }
+            });
+    }
+}
+
+fn gaussian(dx: f64, sigma: f64) -> f64 {
+    (-0.5 * (dx / sigma).powi(2)).exp()
+}
+
+fn lorentzian(dx: f64, half_width: f64) -> f64 {
+    half_width * half_width / (dx * dx + half_width * half_width)
+}
+
+// FWHM and Lorentzian fraction of the pseudo-Voigt (Thompson, Cox, and Hastings 1987)
+fn pseudo_voigt(sigma: f64, gamma: f64) -> (f64, f64) {
+    let fg = FWHM_PER_SIGMA * sigma;
+    let fl = 2.0 * gamma;
+    let fwhm = (fg.powi(5)
+        + 2.69269 * fg.powi(4) * fl
+        + 2.42843 * fg.powi(3) * fl.powi(2)
+        + 4.47163 * fg.powi(2) * fl.powi(3)
+        + 0.07842 * fg * fl.powi(4)
+        + fl.powi(5))
+    .powf(0.2);
+    let ratio = fl / fwhm;
+    let eta = 1.36603 * ratio - 0.47719 * ratio.powi(2) + 0.11116 * ratio.powi(3);
+    (fwhm, eta)
+}
+
+pub fn erfc(x: f64) -> f64 {
+    exp_erfc(0.0, x)
+}
+
+// exp(a) erfc(x) without overflowing when a is large and erfc(x) is tiny.
+// erfc has a fractional error below 1.2e-7 (Numerical Recipes erfcc).
+fn exp_erfc(a: f64, x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let exponent = -z * z - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    if x >= 0.0 {
+        t * (a + exponent).exp()
+    } else {
+        2.0 * a.exp() - t * (a + exponent).exp()
+    }
+}
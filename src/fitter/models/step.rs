@@ -0,0 +1,321 @@
+use crate::fitter::common::{Data, Parameter};
+use crate::fitter::main_fitter::BackgroundModel;
+use crate::fitter::models::background_native::native_background_fit;
+use crate::fitter::models::peak_shape::erfc;
+#[cfg(feature = "python")]
+use pyo3::{prelude::*, types::PyModule};
+
+// Step under a peak from the Compton continuum: amplitude / 2 erfc((x - center) / (sqrt(2) width)),
+// high on the low energy side. The linear terms are only used by the step + linear model.
+// An initial guess of zero for the amplitude, center, or width is estimated from the data.
+#[derive(PartialEq, Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct StepParameters {
+    pub amplitude: Parameter,
+    pub center: Parameter,
+    pub width: Parameter,
+    pub slope: Parameter,
+    pub intercept: Parameter,
+}
+
+impl Default for StepParameters {
+    fn default() -> Self {
+        StepParameters {
+            amplitude: Parameter {
+                name: "amplitude".to_string(),
+                ..Default::default()
+            },
+            center: Parameter {
+                name: "center".to_string(),
+                ..Default::default()
+            },
+            width: Parameter {
+                name: "width".to_string(),
+                min: 0.0,
+                ..Default::default()
+            },
+            slope: Parameter {
+                name: "slope".to_string(),
+                ..Default::default()
+            },
+            intercept: Parameter {
+                name: "intercept".to_string(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl StepParameters {
+    pub fn ui(&mut self, ui: &mut egui::Ui, linear: bool) {
+        ui.horizontal(|ui| {
+            ui.label("Fit Parameters");
+            if ui.small_button("Reset").clicked() {
+                *self = StepParameters::default();
+            }
+        });
+        // create a grid for the param
+        egui::Grid::new("Step_params_grid")
+            .striped(true)
+            .num_columns(5)
+            .show(ui, |ui| {
+                ui.label("Parameter");
+                ui.label("Initial Guess");
+                ui.label("Min");
+                ui.label("Max");
+                ui.label("Vary");
+                ui.end_row();
+                self.amplitude.ui(ui);
+                ui.end_row();
+                self.center.ui(ui);
+                ui.end_row();
+                self.width.ui(ui);
+                if linear {
+                    ui.end_row();
+                    self.slope.ui(ui);
+                    ui.end_row();
+                    self.intercept.ui(ui);
+                }
+            });
+    }
+
+    // The parameters of the model in the order they are fit
+    pub fn parameters(&self, linear: bool) -> Vec<Parameter> {
+        let mut parameters = vec![
+            self.amplitude.clone(),
+            self.center.clone(),
+            self.width.clone(),
+        ];
+        if linear {
+            parameters.extend([self.slope.clone(), self.intercept.clone()]);
+        }
+        parameters
+    }
+
+    // Replaces zero guesses for the step with estimates from the ends of the data
+    pub fn with_estimates(&self, data: &Data) -> StepParameters {
+        let mut params = self.clone();
+        let (Some(&first), Some(&last)) = (data.x.first(), data.x.last()) else {
+            return params;
+        };
+
+        let edge = (data.y.len() / 10).max(1);
+        let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len().max(1) as f64;
+        let low = mean(&data.y[..edge.min(data.y.len())]);
+        let high = mean(&data.y[data.y.len().saturating_sub(edge)..]);
+
+        if params.amplitude.initial_guess == 0.0 {
+            params.amplitude.initial_guess = (low - high).max(0.0);
+        }
+        if params.center.initial_guess == 0.0 {
+            params.center.initial_guess = 0.5 * (first + last);
+        }
+        if params.width.initial_guess == 0.0 {
+            params.width.initial_guess = ((last - first) / 20.0).abs().max(f64::EPSILON);
+        }
+        if params.intercept.initial_guess == 0.0 {
+            params.intercept.initial_guess = high;
+        }
+        params
+    }
+}
+
+pub fn step(x: f64, amplitude: f64, center: f64, width: f64) -> f64 {
+    0.5 * amplitude * erfc((x - center) / (std::f64::consts::SQRT_2 * width))
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct StepFitter {
+    pub data: Data,
+    pub paramaters: StepParameters,
+    pub linear: bool, // step + linear
+    pub fit_points: Vec<[f64; 2]>,
+    pub fit_report: String,
+}
+
+impl StepFitter {
+    pub fn new(data: Data, linear: bool) -> Self {
+        StepFitter {
+            data,
+            paramaters: StepParameters::default(),
+            linear,
+            fit_points: Vec::new(),
+            fit_report: String::new(),
+        }
+    }
+
+    // values are (value, uncertainty) in the order of `StepParameters::parameters`
+    pub fn new_from_parameters(
+        values: &[(f64, f64)],
+        linear: bool,
+        min_x: f64,
+        max_x: f64,
+    ) -> Self {
+        let mut fitter = StepFitter {
+            data: Data::default(),
+            paramaters: StepParameters::default(),
+            linear,
+            fit_points: Vec::new(),
+            fit_report: "Fitter with other model".to_string(),
+        };
+        fitter.set_values(values);
+
+        let num_points = 100;
+        let step_size = (max_x - min_x) / (num_points as f64);
+        fitter.fit_points = (0..=num_points)
+            .map(|i| {
+                let x = min_x + i as f64 * step_size;
+                [x, fitter.evaluate(x)]
+            })
+            .collect();
+
+        fitter
+    }
+
+    fn set_values(&mut self, values: &[(f64, f64)]) {
+        let params = &mut self.paramaters;
+        let targets = [
+            &mut params.amplitude,
+            &mut params.center,
+            &mut params.width,
+            &mut params.slope,
+            &mut params.intercept,
+        ];
+        for (param, &(value, uncertainty)) in targets.into_iter().zip(values) {
+            param.value = Some(value);
+            param.uncertainty = Some(uncertainty);
+        }
+    }
+
+    pub fn native_fit(&mut self) -> Result<(), String> {
+        let model = if self.linear {
+            BackgroundModel::StepLinear(self.paramaters.clone())
+        } else {
+            BackgroundModel::Step(self.paramaters.clone())
+        };
+        let fit = native_background_fit(&model, &self.data)?;
+
+        self.set_values(&fit.values);
+        self.fit_points = fit.fit_points;
+        self.fit_report = fit.fit_report;
+        Ok(())
+    }
+
+    #[cfg(feature = "python")]
+    pub fn lmfit(&mut self) -> PyResult<()> {
+        log::info!("Fitting data with a step using `lmfit`.");
+        Python::with_gil(|py| {
+            match py.import_bound("lmfit") {
+                Ok(_) => {}
+                Err(_) => {
+                    eprintln!("Error: `lmfit` module could not be found. Make sure you are using the correct Python environment with `lmfit` installed.");
+                    return Err(PyErr::new::<pyo3::exceptions::PyImportError, _>(
+                        "`lmfit` module not available",
+                    ));
+                }
+            }
+
+            // Define the Python code as a module
+            let code = r#"
+import lmfit
+import numpy as np
+from scipy.special import erfc
+
+def step(x, amplitude, center, width):
+    return 0.5 * amplitude * erfc((x - center) / (np.sqrt(2) * width))
+
+def step_linear(x, amplitude, center, width, slope, intercept):
+    return step(x, amplitude, center, width) + slope * x + intercept
+
+def StepFit(x_data: list, y_data: list, params_in: list, linear: bool):
+    # params_in = [(name, min, max, initial_guess, vary), ...]
+    model = lmfit.Model(step_linear if linear else step)
+    params = model.make_params()
+    for name, min_value, max_value, value, vary in params_in:
+        params[name].set(min=min_value, max=max_value, value=value, vary=vary)
+
+    result = model.fit(y_data, params, x=x_data)
+
+    print(result.fit_report())
+
+    params = []
+    for name, *_ in params_in:
+        error = result.params[name].stderr
+        params.append((name, float(result.params[name].value), float(error) if error is not None else 0.0))
+
+    x = np.linspace(x_data[0], x_data[-1], 5 * len(x_data))
+    y = result.eval(x=x)
+
+    fit_report = str(result.fit_report())
+
+    return params, x, y, fit_report
+"#;
+
+            // Compile the Python code into a module
+            let module = PyModule::from_code_bound(py, code, "Step.py", "Step")?;
+
+            let params: Vec<(String, f64, f64, f64, bool)> = self
+                .paramaters
+                .with_estimates(&self.data)
+                .parameters(self.linear)
+                .into_iter()
+                .map(|p| (p.name, p.min, p.max, p.initial_guess, p.vary))
+                .collect();
+
+            let result = module.getattr("StepFit")?.call1((
+                self.data.x.clone(),
+                self.data.y.clone(),
+                params,
+                self.linear,
+            ))?;
+
+            let params = result.get_item(0)?.extract::<Vec<(String, f64, f64)>>()?;
+            let x = result.get_item(1)?.extract::<Vec<f64>>()?;
+            let y = result.get_item(2)?.extract::<Vec<f64>>()?;
+            let fit_report = result.get_item(3)?.extract::<String>()?;
+
+            let values: Vec<(f64, f64)> = params.iter().map(|p| (p.1, p.2)).collect();
+            self.set_values(&values);
+
+            self.fit_points = x.iter().zip(y.iter()).map(|(&x, &y)| [x, y]).collect();
+            self.fit_report = fit_report;
+
+            Ok(())
+        })
+    }
+
+    pub fn evaluate(&self, x: f64) -> f64 {
+        let params = &self.paramaters;
+        let mut y = step(
+            x,
+            params.amplitude.value.unwrap_or(0.0),
+            params.center.value.unwrap_or(0.0),
+            params.width.value.unwrap_or(1.0),
+        );
+        if self.linear {
+            y += params.slope.value.unwrap_or(0.0) * x + params.intercept.value.unwrap_or(0.0);
+        }
+        y
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        // add menu button for the fit report
+        ui.horizontal(|ui| {
+            for param in self.paramaters.parameters(self.linear) {
+                if let Some(value) = param.value {
+                    ui.label(format!(
+                        "{}: {:.3} ± {:.3}",
+                        param.name,
+                        value,
+                        param.uncertainty.unwrap_or(0.0)
+                    ));
+                    ui.separator();
+                }
+            }
+            ui.menu_button("Fit Report", |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(self.fit_report.clone());
+                });
+            });
+        });
+    }
+}
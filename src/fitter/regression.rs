@@ -0,0 +1,273 @@
+use rfd::FileDialog;
+
+use std::fs::File;
+use std::io::Write;
+
+use super::common::Parameter;
+use super::main_fitter::{FitResult, Fitter};
+
+#[derive(Debug, Clone)]
+pub struct RegressionRow {
+    pub fit: String,
+    pub peak: usize,
+    pub parameter: String,
+    pub old: (f64, f64), // value, uncertainty
+    pub new: (f64, f64),
+    pub flagged: bool,
+}
+
+impl RegressionRow {
+    fn change_sigma(&self) -> f64 {
+        let uncertainty = (self.old.1.powi(2) + self.new.1.powi(2)).sqrt();
+        if uncertainty > 0.0 {
+            (self.new.0 - self.old.0).abs() / uncertainty
+        } else {
+            0.0
+        }
+    }
+
+    fn change_percent(&self) -> f64 {
+        if self.old.0 != 0.0 {
+            100.0 * (self.new.0 - self.old.0).abs() / self.old.0.abs()
+        } else {
+            0.0
+        }
+    }
+}
+
+// Re-runs stored fits on the current histogram and compares the parameters to the stored values
+#[derive(Debug, Clone)]
+pub struct FitRegression {
+    pub show: bool,
+    pub threshold_sigma: f64,
+    pub threshold_percent: f64,
+    pub rows: Vec<RegressionRow>,
+    pub refits: Vec<Fitter>,
+}
+
+impl Default for FitRegression {
+    fn default() -> Self {
+        FitRegression {
+            show: false,
+            threshold_sigma: 3.0,
+            threshold_percent: 1.0,
+            rows: Vec::new(),
+            refits: Vec::new(),
+        }
+    }
+}
+
+impl FitRegression {
+    pub fn compare(&mut self, old_fits: &[Fitter], new_fits: Vec<Fitter>) {
+        self.rows.clear();
+
+        for (old_fit, new_fit) in old_fits.iter().zip(new_fits.iter()) {
+            let (Some(FitResult::Gaussian(old)), Some(FitResult::Gaussian(new))) =
+                (&old_fit.fit_result, &new_fit.fit_result)
+            else {
+                log::error!("Refit of '{}' did not produce a result", old_fit.name);
+                continue;
+            };
+
+            for (peak, (old_peak, new_peak)) in
+                old.fit_result.iter().zip(new.fit_result.iter()).enumerate()
+            {
+                let pairs: [(&Parameter, &Parameter); 4] = [
+                    (&old_peak.mean, &new_peak.mean),
+                    (&old_peak.fwhm, &new_peak.fwhm),
+                    (&old_peak.area, &new_peak.area),
+                    (&old_peak.amplitude, &new_peak.amplitude),
+                ];
+
+                for (old_parameter, new_parameter) in pairs {
+                    let mut row = RegressionRow {
+                        fit: old_fit.name.clone(),
+                        peak,
+                        parameter: old_parameter.name.clone(),
+                        old: (
+                            old_parameter.value.unwrap_or(0.0),
+                            old_parameter.uncertainty.unwrap_or(0.0),
+                        ),
+                        new: (
+                            new_parameter.value.unwrap_or(0.0),
+                            new_parameter.uncertainty.unwrap_or(0.0),
+                        ),
+                        flagged: false,
+                    };
+                    row.flagged = row.change_sigma() > self.threshold_sigma
+                        || row.change_percent() > self.threshold_percent;
+                    self.rows.push(row);
+                }
+            }
+
+            if old.fit_result.len() != new.fit_result.len() {
+                log::error!(
+                    "Refit of '{}' found {} peaks, stored fit has {}",
+                    old_fit.name,
+                    new.fit_result.len(),
+                    old.fit_result.len()
+                );
+            }
+        }
+
+        let flagged = self.rows.iter().filter(|row| row.flagged).count();
+        log::info!(
+            "Fit regression: {} parameters compared, {} flagged",
+            self.rows.len(),
+            flagged
+        );
+
+        self.refits = new_fits;
+        self.show = true;
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "fit,peak,parameter,old,old_uncertainty,new,new_uncertainty,change_sigma,change_percent,flagged\n",
+        );
+        for row in &self.rows {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{:.3},{:.3},{}\n",
+                row.fit,
+                row.peak,
+                row.parameter,
+                row.old.0,
+                row.old.1,
+                row.new.0,
+                row.new.1,
+                row.change_sigma(),
+                row.change_percent(),
+                row.flagged
+            ));
+        }
+        csv
+    }
+
+    fn save_csv(&self) {
+        if let Some(path) = FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("fit_regression.csv")
+            .save_file()
+        {
+            match File::create(path) {
+                Ok(mut file) => {
+                    if let Err(e) = file.write_all(self.to_csv().as_bytes()) {
+                        log::error!("Error writing fit regression table: {:?}", e);
+                    }
+                }
+                Err(e) => {
+                    log::error!("Error creating file: {:?}", e);
+                }
+            }
+        }
+    }
+
+    // Returns true when the user asked for the stored fits to be refit
+    pub fn menu_button(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut run = false;
+
+        ui.menu_button("Fit Regression", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Flag changes above");
+                ui.add(
+                    egui::DragValue::new(&mut self.threshold_sigma)
+                        .speed(0.1)
+                        .range(0.0..=f64::INFINITY)
+                        .suffix(" σ"),
+                );
+                ui.label("or");
+                ui.add(
+                    egui::DragValue::new(&mut self.threshold_percent)
+                        .speed(0.1)
+                        .range(0.0..=f64::INFINITY)
+                        .suffix(" %"),
+                );
+            });
+
+            if ui
+                .button("Refit Stored Fits")
+                .on_hover_text(
+                    "Re-run every stored fit on the current counts and compare the parameters",
+                )
+                .clicked()
+            {
+                run = true;
+            }
+        });
+
+        run
+    }
+
+    // Returns the refits when the user accepts them in place of the stored fits
+    pub fn ui(&mut self, ui: &mut egui::Ui, name: &str) -> Option<Vec<Fitter>> {
+        if !self.show {
+            return None;
+        }
+
+        let mut accepted = None;
+        let mut show = self.show;
+        let ctx = ui.ctx().clone();
+        egui::Window::new(format!("Fit Regression: {}", name))
+            .open(&mut show)
+            .show(&ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let flagged = self.rows.iter().filter(|row| row.flagged).count();
+                    ui.label(format!("{} of {} flagged", flagged, self.rows.len()));
+
+                    ui.separator();
+
+                    if ui.button("Save CSV").clicked() {
+                        self.save_csv();
+                    }
+
+                    if ui
+                        .add_enabled(!self.refits.is_empty(), egui::Button::new("Accept Refits"))
+                        .on_hover_text("Replace the stored fits with the new results")
+                        .clicked()
+                    {
+                        accepted = Some(std::mem::take(&mut self.refits));
+                    }
+                });
+
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new(format!("fit_regression_grid_{}", name))
+                        .striped(true)
+                        .num_columns(6)
+                        .show(ui, |ui| {
+                            ui.label("Fit");
+                            ui.label("Peak");
+                            ui.label("Parameter");
+                            ui.label("Old");
+                            ui.label("New");
+                            ui.label("Change");
+                            ui.end_row();
+
+                            for row in &self.rows {
+                                ui.label(&row.fit);
+                                ui.label(format!("{}", row.peak));
+                                ui.label(&row.parameter);
+                                ui.label(format!("{:.3} ± {:.3}", row.old.0, row.old.1));
+                                ui.label(format!("{:.3} ± {:.3}", row.new.0, row.new.1));
+
+                                let change = format!(
+                                    "{:.2}σ ({:.2}%)",
+                                    row.change_sigma(),
+                                    row.change_percent()
+                                );
+                                if row.flagged {
+                                    ui.colored_label(egui::Color32::RED, change);
+                                } else {
+                                    ui.label(change);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+        self.show = show;
+
+        accepted
+    }
+}
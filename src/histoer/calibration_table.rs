@@ -0,0 +1,230 @@
+use fnv::FnvHashMap;
+use polars::prelude::*;
+
+use super::column_script::compile_script;
+
+// Per-channel calibrations of an array, e.g. 128 strips of a silicon detector. A table is a
+// CSV of `channel, a, b, c` rows, and the calibrated column is
+//
+//     alias = a * value**2 + b * value + c
+//
+// with the coefficients of the channel of the row. Rows with only `channel, b, c` are linear.
+// Lines starting with # and a header line are skipped. Rows of a channel missing from the
+// table get -1e6 so they fall out of the histograms. The channel and value are column
+// expressions like the column scripts, e.g. "Board * 16 + Channel".
+
+const UNCALIBRATED: f64 = -1e6;
+
+// A missing coefficient is 0, so rows with only b and c are linear like in the CSV
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ChannelCoefficients {
+    pub channel: i64,
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct CalibrationTable {
+    pub alias: String,
+    pub channel: String,
+    pub value: String,
+    pub file: String, // CSV the coefficients were loaded from
+    pub coefficients: Vec<ChannelCoefficients>,
+}
+
+impl Default for CalibrationTable {
+    fn default() -> Self {
+        CalibrationTable {
+            alias: "Energy".to_string(),
+            channel: "Channel".to_string(),
+            value: "ADC".to_string(),
+            file: String::new(),
+            coefficients: Vec::new(),
+        }
+    }
+}
+
+impl CalibrationTable {
+    pub fn compile(&self) -> Result<Expr, String> {
+        if self.coefficients.is_empty() {
+            return Err("No coefficients loaded".to_string());
+        }
+        let channel = compile_script(&self.channel).map_err(|e| format!("Channel: {}", e))?;
+        let value = compile_script(&self.value).map_err(|e| format!("Value: {}", e))?;
+
+        let coefficients: FnvHashMap<i64, (f64, f64, f64)> = self
+            .coefficients
+            .iter()
+            .map(|row| (row.channel, (row.a, row.b, row.c)))
+            .collect();
+
+        let calibrated = channel.cast(DataType::Float64).map_many(
+            move |columns| {
+                let channels = columns[0].f64()?;
+                let values = columns[1].f64()?;
+                let calibrated: Float64Chunked = channels
+                    .into_iter()
+                    .zip(values)
+                    .map(|(channel, value)| {
+                        let (channel, value) = (channel?, value?);
+                        Some(match coefficients.get(&(channel.round() as i64)) {
+                            Some((a, b, c)) => a * value * value + b * value + c,
+                            None => UNCALIBRATED,
+                        })
+                    })
+                    .collect();
+                Ok(Some(calibrated.into_column()))
+            },
+            &[value.cast(DataType::Float64)],
+            GetOutput::from_type(DataType::Float64),
+        );
+        Ok(calibrated.alias(self.alias.as_str()))
+    }
+
+    pub fn load(&mut self, path: &std::path::Path) -> Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        self.coefficients = parse_coefficients(&text)?;
+        self.file = path.display().to_string();
+        log::info!(
+            "Loaded {} channel calibrations from {}",
+            self.coefficients.len(),
+            self.file
+        );
+        Ok(())
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.alias)
+                    .hint_text("Alias")
+                    .desired_width(100.0),
+            );
+            ui.label("=");
+            ui.label("a·v² + b·v + c of");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.channel)
+                    .hint_text("Channel")
+                    .desired_width(100.0),
+            )
+            .on_hover_text("Channel of the row, picks the coefficients");
+            ui.label("with v =");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.value)
+                    .hint_text("Value")
+                    .desired_width(100.0),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Load CSV").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("CSV Files", &["csv", "txt", "dat"])
+                    .pick_file()
+                {
+                    if let Err(e) = self.load(&path) {
+                        log::error!("Failed to load calibration table: {}", e);
+                    }
+                }
+            }
+
+            if !self.file.is_empty()
+                && ui
+                    .button("Reload")
+                    .on_hover_text(self.file.clone())
+                    .clicked()
+            {
+                let path = std::path::PathBuf::from(&self.file);
+                if let Err(e) = self.load(&path) {
+                    log::error!("Failed to reload calibration table: {}", e);
+                }
+            }
+
+            if self.coefficients.is_empty() {
+                ui.colored_label(egui::Color32::RED, "No coefficients loaded");
+            } else {
+                ui.label(format!("{} channels", self.coefficients.len()));
+            }
+        });
+
+        if !self.coefficients.is_empty() {
+            egui::CollapsingHeader::new("Coefficients")
+                .id_salt(("calibration_coefficients", &self.alias))
+                .show(ui, |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new(("calibration_table", &self.alias))
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    ui.label("Channel");
+                                    ui.label("a");
+                                    ui.label("b");
+                                    ui.label("c");
+                                    ui.end_row();
+                                    for row in &mut self.coefficients {
+                                        ui.label(row.channel.to_string());
+                                        ui.add(egui::DragValue::new(&mut row.a).speed(1e-6));
+                                        ui.add(egui::DragValue::new(&mut row.b).speed(1e-3));
+                                        ui.add(egui::DragValue::new(&mut row.c).speed(1e-2));
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                });
+        }
+    }
+}
+
+// Rows of `channel, a, b, c` or `channel, b, c`, split by commas, tabs, or spaces
+fn parse_coefficients(text: &str) -> Result<Vec<ChannelCoefficients>, String> {
+    let mut coefficients: Vec<ChannelCoefficients> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line
+            .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+            .filter(|field| !field.is_empty())
+            .collect();
+        let values: Result<Vec<f64>, _> = fields.iter().map(|field| field.parse::<f64>()).collect();
+        let values = match values {
+            Ok(values) => values,
+            // a header
+            Err(_) if coefficients.is_empty() => continue,
+            Err(_) => return Err(format!("Line {}: '{}' is not a number", number + 1, line)),
+        };
+
+        let (channel, a, b, c) = match values[..] {
+            [channel, a, b, c] => (channel, a, b, c),
+            [channel, b, c] => (channel, 0.0, b, c),
+            _ => {
+                return Err(format!(
+                    "Line {}: expected channel, a, b, c but found {} values",
+                    number + 1,
+                    values.len()
+                ))
+            }
+        };
+        let channel = channel.round() as i64;
+        if coefficients.iter().any(|row| row.channel == channel) {
+            return Err(format!(
+                "Line {}: channel {} is repeated",
+                number + 1,
+                channel
+            ));
+        }
+        coefficients.push(ChannelCoefficients { channel, a, b, c });
+    }
+
+    if coefficients.is_empty() {
+        return Err("The file has no coefficients".to_string());
+    }
+    coefficients.sort_by_key(|row| row.channel);
+    Ok(coefficients)
+}
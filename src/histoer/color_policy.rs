@@ -0,0 +1,158 @@
+use egui::Color32;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::histo1d::histogram1d::Histogram;
+use crate::egui_plot_stuff::colors::Rgb;
+
+// Colors handed out in order, skipping the grays and white of the line color options
+const POLICY_COLORS: &[Color32] = &[
+    Color32::from_rgb(120, 47, 64),
+    Color32::from_rgb(92, 184, 176),
+    Color32::BLUE,
+    Color32::RED,
+    Color32::DARK_GREEN,
+    Color32::from_rgb(206, 184, 136),
+    Color32::BROWN,
+    Color32::DARK_BLUE,
+    Color32::LIGHT_BLUE,
+    Color32::GREEN,
+    Color32::KHAKI,
+    Color32::DARK_RED,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub enum ColorBy {
+    #[default]
+    Pane, // every pane keeps its own color
+    Cut,                // panes filled with the same cuts share a color
+    NameSegment(usize), // panes with the same part of the name (e.g. "run_12/...") share a color
+}
+
+// Central color assignment for 1D panes so the same cut or run has the same color in every pane
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ColorPolicy {
+    pub color_by: ColorBy,
+    pub shared_legend: bool, // one legend above the panes instead of one per pane
+    pub cut_labels: HashMap<String, String>, // histogram name -> cuts it was filled with
+    #[serde(skip)]
+    pub legend: Vec<(String, Color32)>,
+}
+
+impl ColorPolicy {
+    fn key(&self, name: &str) -> Option<String> {
+        match self.color_by {
+            ColorBy::Pane => None,
+            ColorBy::Cut => Some(
+                self.cut_labels
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| "No Cuts".to_string()),
+            ),
+            ColorBy::NameSegment(index) => name.split('/').nth(index).map(|s| s.to_string()),
+        }
+    }
+
+    pub fn set_cut_label(&mut self, name: &str, cut_names: Vec<String>) {
+        let label = if cut_names.is_empty() {
+            "No Cuts".to_string()
+        } else {
+            cut_names.join(" & ")
+        };
+        self.cut_labels.insert(name.to_string(), label);
+    }
+
+    // Recolors the panes, keys are sorted so the assignment does not depend on the pane order
+    pub fn apply(&mut self, hists: &[Arc<Mutex<Box<Histogram>>>]) {
+        let keyed: Vec<_> = hists
+            .iter()
+            .map(|hist| {
+                let key = self.key(&hist.lock().unwrap().name);
+                (hist, key)
+            })
+            .collect();
+
+        let mut keys: Vec<String> = keyed.iter().filter_map(|(_, key)| key.clone()).collect();
+        keys.sort();
+        keys.dedup();
+
+        self.legend = keys
+            .iter()
+            .enumerate()
+            .map(|(index, key)| (key.clone(), POLICY_COLORS[index % POLICY_COLORS.len()]))
+            .collect();
+
+        for (hist, key) in keyed {
+            let mut hist = hist.lock().unwrap();
+            if let Some((_, color)) = key.and_then(|key| {
+                self.legend
+                    .iter()
+                    .find(|(legend_key, _)| *legend_key == key)
+            }) {
+                hist.line.color = *color;
+                hist.line.color_rgb = Rgb::from_color32(*color);
+            }
+            hist.plot_settings.egui_settings.legend = !self.shared_legend;
+        }
+    }
+
+    // Returns true when the policy changed and the panes need to be recolored
+    pub fn menu_button(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui.menu_button("Colors", |ui| {
+            ui.heading("1D Colors");
+
+            let before = self.color_by;
+            ui.radio_value(&mut self.color_by, ColorBy::Pane, "Per pane");
+            ui.radio_value(&mut self.color_by, ColorBy::Cut, "By cut");
+
+            ui.horizontal(|ui| {
+                let mut segment = match self.color_by {
+                    ColorBy::NameSegment(index) => index,
+                    _ => 0,
+                };
+                let selected = matches!(self.color_by, ColorBy::NameSegment(_));
+                if ui.radio(selected, "By name part").clicked() {
+                    self.color_by = ColorBy::NameSegment(segment);
+                }
+                if ui
+                    .add_enabled(
+                        selected,
+                        egui::DragValue::new(&mut segment).range(0..=16).prefix("#"),
+                    )
+                    .on_hover_text("Index of the '/' separated part of the name, e.g. 0 for the run in 'run_12/Energy'")
+                    .changed()
+                {
+                    self.color_by = ColorBy::NameSegment(segment);
+                }
+            });
+
+            changed |= self.color_by != before;
+
+            ui.separator();
+
+            changed |= ui
+                .checkbox(&mut self.shared_legend, "Shared legend")
+                .changed();
+        });
+
+        changed
+    }
+
+    pub fn legend_ui(&self, ui: &mut egui::Ui) {
+        if !self.shared_legend || self.legend.is_empty() {
+            return;
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            for (key, color) in &self.legend {
+                let (rect, _) =
+                    ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                ui.painter().rect_filled(rect, 2.0, *color);
+                ui.label(key);
+                ui.add_space(8.0);
+            }
+        });
+    }
+}
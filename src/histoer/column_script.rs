@@ -0,0 +1,472 @@
+use polars::prelude::*;
+
+// Small scripts for computed columns, written like Rhai:
+//
+//     // radius on the detector, rows outside of it are dropped from the histograms
+//     let r = sqrt(X1**2 + X2**2);
+//     if r < 10 && Energy > 0 { r } else { -1e6 }
+//
+// A script is a list of `let` statements followed by the value of the column. The script is
+// compiled once per fill into a single Polars expression, so it runs on whole columns.
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ColumnScript {
+    pub alias: String,
+    pub script: String,
+}
+
+impl ColumnScript {
+    pub fn compile(&self) -> Result<Expr, String> {
+        Ok(compile_script(&self.script)?.alias(self.alias.as_str()))
+    }
+}
+
+// Functions available in column scripts, with their number of arguments
+pub const FUNCTIONS: [(&str, usize); 19] = [
+    ("abs", 1),
+    ("sqrt", 1),
+    ("exp", 1),
+    ("log", 1),
+    ("log10", 1),
+    ("sin", 1),
+    ("cos", 1),
+    ("tan", 1),
+    ("asin", 1),
+    ("acos", 1),
+    ("atan", 1),
+    ("floor", 1),
+    ("ceil", 1),
+    ("round", 1),
+    ("sign", 1),
+    ("atan2", 2),
+    ("min", 2),
+    ("max", 2),
+    ("clamp", 3),
+];
+
+pub fn compile_script(script: &str) -> Result<Expr, String> {
+    let tokens = tokenize(script)?;
+    if tokens.is_empty() {
+        return Err("The script is empty".to_string());
+    }
+
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        variables: Vec::new(),
+    };
+    let value = parser.block()?;
+    match parser.peek() {
+        None => Ok(value),
+        Some(token) => Err(format!("Unexpected {}", token)),
+    }
+}
+
+// Polars expression of a call to one of the script functions
+pub fn function_expr(name: &str, mut args: Vec<Expr>) -> Result<Expr, String> {
+    let Some((_, arity)) = FUNCTIONS.iter().find(|(function, _)| *function == name) else {
+        return Err(format!("Unknown function '{}'", name));
+    };
+    if args.len() != *arity {
+        return Err(format!(
+            "'{}' takes {} argument{}, found {}",
+            name,
+            arity,
+            if *arity == 1 { "" } else { "s" },
+            args.len()
+        ));
+    }
+
+    let x = args.remove(0);
+    Ok(match name {
+        // abs, min, max, and sign with when/then so no extra polars features are required
+        "abs" => when(x.clone().lt(lit(0.0)))
+            .then(lit(0.0) - x.clone())
+            .otherwise(x),
+        "sqrt" => x.sqrt(),
+        "exp" => elementwise(x, f64::exp),
+        "log" => elementwise(x, f64::ln),
+        "log10" => elementwise(x, f64::log10),
+        "sin" => elementwise(x, f64::sin),
+        "cos" => elementwise(x, f64::cos),
+        "tan" => elementwise(x, f64::tan),
+        "asin" => elementwise(x, f64::asin),
+        "acos" => elementwise(x, f64::acos),
+        "atan" => elementwise(x, f64::atan),
+        "floor" => elementwise(x, f64::floor),
+        "ceil" => elementwise(x, f64::ceil),
+        "round" => elementwise(x, f64::round),
+        "sign" => when(x.clone().gt(lit(0.0)))
+            .then(lit(1.0))
+            .when(x.lt(lit(0.0)))
+            .then(lit(-1.0))
+            .otherwise(lit(0.0)),
+        "atan2" => atan2(x, args.remove(0)),
+        "min" => {
+            let y = args.remove(0);
+            when(x.clone().lt_eq(y.clone())).then(x).otherwise(y)
+        }
+        "max" => {
+            let y = args.remove(0);
+            when(x.clone().gt_eq(y.clone())).then(x).otherwise(y)
+        }
+        "clamp" => {
+            let (low, high) = (args.remove(0), args.remove(0));
+            when(x.clone().lt(low.clone()))
+                .then(low)
+                .when(x.clone().gt(high.clone()))
+                .then(high)
+                .otherwise(x)
+        }
+        _ => unreachable!(),
+    })
+}
+
+// Applies `f` to every value of the column as a float
+fn elementwise(expr: Expr, f: fn(f64) -> f64) -> Expr {
+    expr.cast(DataType::Float64).map(
+        move |column| Ok(Some(column.f64()?.apply_values(f).into_column())),
+        GetOutput::from_type(DataType::Float64),
+    )
+}
+
+// Angle of (x, y) in (-pi, pi], from atan(y / x) and the quadrant
+fn atan2(y: Expr, x: Expr) -> Expr {
+    let pi = std::f64::consts::PI;
+    let angle = elementwise(y.clone() / x.clone(), f64::atan);
+    when(x.clone().gt(lit(0.0)))
+        .then(angle.clone())
+        .when(x.clone().lt(lit(0.0)).and(y.clone().gt_eq(lit(0.0))))
+        .then(angle.clone() + lit(pi))
+        .when(x.lt(lit(0.0)))
+        .then(angle - lit(pi))
+        .when(y.clone().gt(lit(0.0)))
+        .then(lit(pi / 2.0))
+        .when(y.lt(lit(0.0)))
+        .then(lit(-pi / 2.0))
+        .otherwise(lit(0.0))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Let,
+    If,
+    Else,
+    True,
+    False,
+    Op(&'static str),
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Comma,
+    Assign,
+    Semicolon,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Number(value) => write!(f, "'{}'", value),
+            Token::Let => write!(f, "'let'"),
+            Token::If => write!(f, "'if'"),
+            Token::Else => write!(f, "'else'"),
+            Token::True => write!(f, "'true'"),
+            Token::False => write!(f, "'false'"),
+            Token::Op(op) => write!(f, "'{}'", op),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::LBrace => write!(f, "'{{'"),
+            Token::RBrace => write!(f, "'}}'"),
+            Token::Comma => write!(f, "','"),
+            Token::Assign => write!(f, "'='"),
+            Token::Semicolon => write!(f, "';'"),
+        }
+    }
+}
+
+// Operators, longest first so "**" is not read as two "*"
+const OPERATORS: [&str; 15] = [
+    "**", "==", "!=", ">=", "<=", "&&", "||", "+", "-", "*", "/", "%", ">", "<", "!",
+];
+
+fn tokenize(script: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = script.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '/' if next == Some('/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '(' | ')' | '{' | '}' | ',' | ';' => {
+                tokens.push(match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    '{' => Token::LBrace,
+                    '}' => Token::RBrace,
+                    ',' => Token::Comma,
+                    _ => Token::Semicolon,
+                });
+                i += 1;
+            }
+            '=' if next != Some('=') => {
+                tokens.push(Token::Assign);
+                i += 1;
+            }
+            c if c.is_ascii_digit() || (c == '.' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let start = i;
+                i += 1;
+                while i < chars.len() {
+                    let c = chars[i];
+                    let exponent_sign = (c == '-' || c == '+') && matches!(chars[i - 1], 'e' | 'E');
+                    if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let text: String = chars[start..i].iter().collect();
+                let value = text
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid number '{}'", text))?;
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                tokens.push(match text.as_str() {
+                    "let" => Token::Let,
+                    "if" => Token::If,
+                    "else" => Token::Else,
+                    "true" => Token::True,
+                    "false" => Token::False,
+                    _ => Token::Ident(text),
+                });
+            }
+            _ => {
+                let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+                let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) else {
+                    return Err(format!("Unexpected character '{}'", c));
+                };
+                tokens.push(Token::Op(op));
+                i += op.len();
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    variables: Vec<(String, Expr)>, // `let` bindings in scope, latest last
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("Expected {}, found {}", expected, token)),
+            None => Err(format!("Expected {} at the end of the script", expected)),
+        }
+    }
+
+    fn eat_op(&mut self, ops: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    // `let` statements and the value they lead to, the bindings end with the block
+    fn block(&mut self) -> Result<Expr, String> {
+        let scope = self.variables.len();
+        while self.peek() == Some(&Token::Let) {
+            self.pos += 1;
+            let name = match self.next() {
+                Some(Token::Ident(name)) => name,
+                Some(token) => return Err(format!("Expected a variable name, found {}", token)),
+                None => return Err("Expected a variable name at the end of the script".into()),
+            };
+            self.expect(Token::Assign)?;
+            let value = self.expression()?;
+            self.expect(Token::Semicolon)?;
+            self.variables.push((name, value));
+        }
+
+        let value = self.expression()?;
+        // a trailing semicolon after the value is allowed
+        if self.peek() == Some(&Token::Semicolon) {
+            self.pos += 1;
+        }
+        self.variables.truncate(scope);
+        Ok(value)
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.eat_op(&["||"]).is_some() {
+            left = left.or(self.and()?);
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.comparison()?;
+        while self.eat_op(&["&&"]).is_some() {
+            left = left.and(self.comparison()?);
+        }
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.sum()?;
+        let Some(op) = self.eat_op(&["==", "!=", ">=", "<=", ">", "<"]) else {
+            return Ok(left);
+        };
+        let right = self.sum()?;
+        Ok(match op {
+            "==" => left.eq(right),
+            "!=" => left.neq(right),
+            ">=" => left.gt_eq(right),
+            "<=" => left.lt_eq(right),
+            ">" => left.gt(right),
+            _ => left.lt(right),
+        })
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut left = self.product()?;
+        while let Some(op) = self.eat_op(&["+", "-"]) {
+            let right = self.product()?;
+            left = if op == "+" {
+                left + right
+            } else {
+                left - right
+            };
+        }
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        while let Some(op) = self.eat_op(&["*", "/", "%"]) {
+            let right = self.unary()?;
+            left = match op {
+                "*" => left * right,
+                "/" => left / right,
+                _ => left % right,
+            };
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.eat_op(&["-", "!"]) {
+            Some("-") => Ok(lit(0.0) - self.unary()?),
+            Some(_) => Ok(self.unary()?.not()),
+            None => self.power(),
+        }
+    }
+
+    // `**` binds tighter than a leading minus and is right associative: -2**2**3 = -(2**(2**3))
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.primary()?;
+        if self.eat_op(&["**"]).is_some() {
+            let exponent = self.unary()?;
+            return Ok(base.pow(exponent));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(lit(value)),
+            Some(Token::True) => Ok(lit(true)),
+            Some(Token::False) => Ok(lit(false)),
+            Some(Token::LParen) => {
+                let inner = self.expression()?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Some(Token::If) => self.if_else(),
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::LParen) => {
+                self.pos += 1;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    args.push(self.expression()?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.pos += 1;
+                        args.push(self.expression()?);
+                    }
+                }
+                self.expect(Token::RParen)?;
+                function_expr(&name, args)
+            }
+            Some(Token::Ident(name)) => {
+                if let Some((_, value)) = self.variables.iter().rev().find(|(v, _)| *v == name) {
+                    Ok(value.clone())
+                } else if name == "pi" {
+                    Ok(lit(std::f64::consts::PI))
+                } else {
+                    Ok(col(name.as_str()))
+                }
+            }
+            Some(token) => Err(format!("Expected a value, found {}", token)),
+            None => Err("Expected a value at the end of the script".to_string()),
+        }
+    }
+
+    // `if cond { a } else if cond { b } else { c }`, the `if` is already read
+    fn if_else(&mut self) -> Result<Expr, String> {
+        let condition = self.expression()?;
+        self.expect(Token::LBrace)?;
+        let then = self.block()?;
+        self.expect(Token::RBrace)?;
+
+        if self.next() != Some(Token::Else) {
+            return Err("An 'if' needs an 'else' to give the column a value".to_string());
+        }
+        let otherwise = if self.peek() == Some(&Token::If) {
+            self.pos += 1;
+            self.if_else()?
+        } else {
+            self.expect(Token::LBrace)?;
+            let otherwise = self.block()?;
+            self.expect(Token::RBrace)?;
+            otherwise
+        };
+
+        Ok(when(condition).then(then).otherwise(otherwise))
+    }
+}
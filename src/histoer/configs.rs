@@ -1,19 +1,111 @@
-use super::cuts::{Cut, Cuts};
+use super::calibration_table::CalibrationTable;
+use super::column_script::{self, ColumnScript};
+use super::cut_stats::CutStats;
+use super::cuts::{Cut, Cut2D, Cuts};
 use super::histogrammer::Histogrammer;
+use super::variables::Variable;
+
+use std::collections::HashMap;
 
 use egui_extras::{Column, TableBuilder};
 
-// Enum to encapsulate 1D and 2D histogram configurations
+// Enum to encapsulate 1D, 2D, 3D, and N-dimensional histogram configurations
 #[derive(Clone, serde::Deserialize, serde::Serialize, Debug)]
 pub enum Config {
     Hist1D(Hist1DConfig),
     Hist2D(Hist2DConfig),
+    Hist3D(Hist3DConfig),
+    HistND(HistNDConfig),
+}
+impl Config {
+    pub fn name(&self) -> &str {
+        match self {
+            Config::Hist1D(hist1d) => &hist1d.name,
+            Config::Hist2D(hist2d) => &hist2d.name,
+            Config::Hist3D(hist3d) => &hist3d.name,
+            Config::HistND(histnd) => &histnd.name,
+        }
+    }
+
+    pub fn cuts(&self) -> &Cuts {
+        match self {
+            Config::Hist1D(hist1d) => &hist1d.cuts,
+            Config::Hist2D(hist2d) => &hist2d.cuts,
+            Config::Hist3D(hist3d) => &hist3d.cuts,
+            Config::HistND(histnd) => &histnd.cuts,
+        }
+    }
+
+    pub fn calculate(&self) -> bool {
+        match self {
+            Config::Hist1D(hist1d) => hist1d.calculate,
+            Config::Hist2D(hist2d) => hist2d.calculate,
+            Config::Hist3D(hist3d) => hist3d.calculate,
+            Config::HistND(histnd) => histnd.calculate,
+        }
+    }
+
+    // Key describing what the histogram is filled with, used to find duplicate definitions
+    fn duplicate_key(&self) -> String {
+        match self {
+            Config::Hist1D(hist1d) => format!(
+                "1d|{}|{:?}|{}|{}|{}",
+                hist1d.column_name,
+                hist1d.range,
+                hist1d.bins,
+                hist1d.edges.trim(),
+                hist1d.cuts.generate_key()
+            ),
+            Config::Hist2D(hist2d) => format!(
+                "2d|{}|{}|{:?}|{:?}|{:?}|{}",
+                hist2d.x_column_name,
+                hist2d.y_column_name,
+                hist2d.x_range,
+                hist2d.y_range,
+                hist2d.bins,
+                hist2d.cuts.generate_key()
+            ),
+            Config::Hist3D(hist3d) => format!(
+                "3d|{}|{}|{}|{:?}|{:?}|{}",
+                hist3d.x_column_name,
+                hist3d.y_column_name,
+                hist3d.z_column_name,
+                hist3d.ranges,
+                hist3d.bins,
+                hist3d.cuts.generate_key()
+            ),
+            Config::HistND(histnd) => format!(
+                "nd|{}|{:?}|{:?}|{}",
+                histnd.column_names.join("|"),
+                histnd.ranges,
+                histnd.bins,
+                histnd.cuts.generate_key()
+            ),
+        }
+    }
 }
+
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+#[serde(default)]
 pub struct Configs {
+    #[serde(default)]
     pub configs: Vec<Config>,
+    #[serde(default)]
     pub columns: Vec<(String, String)>,
+    #[serde(default)]
+    pub column_scripts: Vec<ColumnScript>,
+    #[serde(default)]
+    pub calibration_tables: Vec<CalibrationTable>,
+    #[serde(default)]
     pub cuts: Cuts,
+    #[serde(default)]
+    pub variables: Vec<Variable>,
+    #[serde(skip)]
+    pub aliases: Vec<(String, String)>, // (duplicate name, name of the histogram it is filled from)
+    #[serde(default)]
+    pub time_column: String, // optional column the time window applies to
+    #[serde(default)]
+    pub time_window: Option<(f64, f64)>, // [start, end) of the time column, all rows when None
 }
 
 impl Configs {
@@ -55,6 +147,33 @@ impl Configs {
         self.configs.push(Config::Hist2D(config));
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn hist3d(
+        &mut self,
+        name: &str,
+        x_column_name: &str,
+        y_column_name: &str,
+        z_column_name: &str,
+        ranges: [(f64, f64); 3],
+        bins: (usize, usize, usize),
+        cuts: Option<Cuts>,
+    ) {
+        let mut config = Hist3DConfig::new(
+            name,
+            x_column_name,
+            y_column_name,
+            z_column_name,
+            ranges,
+            bins,
+        );
+
+        if let Some(cuts) = cuts {
+            config.cuts = cuts;
+        }
+
+        self.configs.push(Config::Hist3D(config));
+    }
+
     pub fn merge(&mut self, other: Configs) -> &mut Self {
         // Merge configurations
         for config in other.configs {
@@ -114,6 +233,34 @@ impl Configs {
                         self.configs.push(Config::Hist2D(other_hist2d.clone()));
                     }
                 }
+                Config::Hist3D(other_hist3d) => {
+                    if self
+                        .configs
+                        .iter()
+                        .any(|c| matches!(c, Config::Hist3D(h) if h.name == other_hist3d.name))
+                    {
+                        log::error!(
+                            "Conflict detected for Hist3D '{}', a 3D histogram with that name already exists.",
+                            other_hist3d.name
+                        );
+                    } else {
+                        self.configs.push(Config::Hist3D(other_hist3d.clone()));
+                    }
+                }
+                Config::HistND(other_histnd) => {
+                    if self
+                        .configs
+                        .iter()
+                        .any(|c| matches!(c, Config::HistND(h) if h.name == other_histnd.name))
+                    {
+                        log::error!(
+                            "Conflict detected for HistND '{}', an ND histogram with that name already exists.",
+                            other_histnd.name
+                        );
+                    } else {
+                        self.configs.push(Config::HistND(other_histnd.clone()));
+                    }
+                }
             }
         }
 
@@ -133,20 +280,187 @@ impl Configs {
             }
         }
 
+        // Merge column scripts
+        for script in other.column_scripts {
+            if let Some(existing) = self.column_scripts.iter().find(|s| s.alias == script.alias) {
+                if existing.script != script.script {
+                    log::error!(
+                        "Conflict detected for column script '{}', a script with that alias already exists.",
+                        script.alias
+                    );
+                }
+            } else {
+                self.column_scripts.push(script);
+            }
+        }
+
+        // Merge calibration tables
+        for table in other.calibration_tables {
+            if let Some(existing) = self
+                .calibration_tables
+                .iter()
+                .find(|t| t.alias == table.alias)
+            {
+                if *existing != table {
+                    log::error!(
+                        "Conflict detected for calibration table '{}', a table with that alias already exists.",
+                        table.alias
+                    );
+                }
+            } else {
+                self.calibration_tables.push(table);
+            }
+        }
+
+        // Merge variables
+        for variable in other.variables {
+            if let Some(existing) = self.variables.iter().find(|v| v.name == variable.name) {
+                if existing.value != variable.value {
+                    log::error!(
+                        "Conflict detected for variable '{}': Existing value {}, New value {}.",
+                        variable.name,
+                        existing.value,
+                        variable.value
+                    );
+                }
+            } else {
+                self.variables.push(variable);
+            }
+        }
+
         // Merge cuts
         self.cuts.merge(&other.cuts);
 
         self
     }
 
-    pub fn valid_configs(&mut self, lf: &mut LazyFrame) -> Configs {
-        // Add new computed columns to the LazyFrame
+    // Names of the workspace cuts that select other events than in `filled`, the cuts of
+    // the last fill
+    pub fn changed_cuts(&self, filled: &[Cut]) -> Vec<String> {
+        self.cuts
+            .cuts
+            .iter()
+            .filter(|cut| {
+                !filled
+                    .iter()
+                    .any(|old| old.name() == cut.name() && old.same_selection(cut))
+            })
+            .map(|cut| cut.name().to_string())
+            .collect()
+    }
+
+    // Cuts of the histograms that cannot be parsed, one message per histogram and cut
+    pub fn cut_errors(&self) -> Vec<String> {
+        self.configs
+            .iter()
+            .filter(|config| config.calculate())
+            .flat_map(|config| {
+                config
+                    .cuts()
+                    .errors()
+                    .into_iter()
+                    .map(move |e| format!("{} in '{}'", e, config.name()))
+            })
+            .collect()
+    }
+
+    // Names of the histograms that use any of the cuts
+    pub fn affected_by(&self, cut_names: &[String]) -> Vec<String> {
+        self.configs
+            .iter()
+            .filter(|config| {
+                config
+                    .cuts()
+                    .cuts
+                    .iter()
+                    .any(|cut| cut_names.iter().any(|name| name == cut.name()))
+            })
+            .map(|config| config.name().to_string())
+            .collect()
+    }
+
+    // The configs of the named histograms, with the columns and cuts they may need
+    pub fn only(&self, names: &[String]) -> Configs {
+        let mut configs = self.clone();
+        configs
+            .configs
+            .retain(|config| names.iter().any(|name| name == config.name()));
+        configs
+    }
+
+    // Resets the panes of the configs and their duplicates, other panes keep their counts
+    pub fn reset_panes(&self, h: &mut Histogrammer) {
+        let names = self
+            .configs
+            .iter()
+            .map(|config| config.name())
+            .chain(self.aliases.iter().map(|(alias, _)| alias.as_str()));
+        for name in names {
+            if let Some(id) = h.find_existing_histogram(name) {
+                h.reset_histogram(id);
+            }
+        }
+    }
+
+    // Adds a cut drawn on a pane to the workspace cuts, a cut promoted again replaces the
+    // polygon and columns of the earlier one
+    pub fn promote_cut(&mut self, cut: Cut2D) {
+        let name = cut.polygon.name.clone();
+        match self.cuts.cuts.iter_mut().find(|c| c.name() == name) {
+            Some(Cut::Cut2D(existing)) => {
+                let active = existing.active;
+                *existing = cut;
+                existing.active = active;
+                log::info!("Updated workspace cut '{}'", name);
+            }
+            Some(Cut::Cut1D(_)) => {
+                log::error!("A 1D cut named '{}' already exists in the workspace", name);
+            }
+            None => {
+                self.cuts.cuts.push(Cut::Cut2D(cut));
+                log::info!("Added workspace cut '{}'", name);
+            }
+        }
+    }
+
+    // Add new computed columns to the LazyFrame
+    pub fn add_computed_columns(&self, lf: &mut LazyFrame) {
         for (expression, alias) in &self.columns {
             if let Err(e) = add_computed_column(lf, expression, alias) {
                 log::error!("Error adding computed column '{}': {}", alias, e);
             }
         }
 
+        // calibrated columns come next so scripts can use them
+        for table in &self.calibration_tables {
+            match table.compile() {
+                Ok(expr) => *lf = lf.clone().with_column(expr),
+                Err(e) => log::error!("Error in calibration table '{}': {}", table.alias, e),
+            }
+        }
+
+        // scripts come after the expressions so they can use those columns
+        for script in &self.column_scripts {
+            match script.compile() {
+                Ok(expr) => *lf = lf.clone().with_column(expr),
+                Err(e) => log::error!("Error in column script '{}': {}", script.alias, e),
+            }
+        }
+    }
+
+    // Aliases of the columns made by expressions and scripts
+    pub fn computed_column_names(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|(_, alias)| alias.clone())
+            .chain(self.calibration_tables.iter().map(|t| t.alias.clone()))
+            .chain(self.column_scripts.iter().map(|s| s.alias.clone()))
+            .collect()
+    }
+
+    pub fn valid_configs(&mut self, lf: &mut LazyFrame) -> Configs {
+        self.add_computed_columns(lf);
+
         // Get the column names from the LazyFrame
         let column_names = match get_column_names_from_lazyframe(lf) {
             Ok(names) => names,
@@ -195,7 +509,7 @@ impl Configs {
                                 .collect();
 
                             let mut validated_hist1d = hist1d.clone();
-                            validated_hist1d.cuts = Cuts::new(valid_hist_cuts);
+                            validated_hist1d.cuts.cuts = valid_hist_cuts;
                             validated_hist1d.cuts.parse_conditions();
                             valid_configs.push(Config::Hist1D(validated_hist1d));
                         } else {
@@ -249,6 +563,95 @@ impl Configs {
                         }
                     }
                 }
+                Config::Hist3D(hist3d) => {
+                    if hist3d.calculate {
+                        if column_names.contains(&hist3d.x_column_name)
+                            && column_names.contains(&hist3d.y_column_name)
+                            && column_names.contains(&hist3d.z_column_name)
+                        {
+                            // Validate cuts for the histogram
+                            let valid_hist_cuts: Vec<Cut> = hist3d
+                                .cuts
+                                .cuts
+                                .iter()
+                                .filter(|cut| {
+                                    let required_columns = cut.required_columns();
+                                    for column in required_columns {
+                                        if !column_names.contains(&column) {
+                                            log::error!(
+                                                "Invalid cut '{}' for 3D histogram '{}': Missing column '{}'",
+                                                cut.name(),
+                                                hist3d.name,
+                                                column
+                                            );
+                                            return false;
+                                        }
+                                    }
+                                    true
+                                })
+                                .cloned()
+                                .collect();
+
+                            let mut validated_hist3d = hist3d.clone();
+                            validated_hist3d.cuts.cuts = valid_hist_cuts;
+                            validated_hist3d.cuts.parse_conditions();
+                            valid_configs.push(Config::Hist3D(validated_hist3d));
+                        } else {
+                            log::error!(
+                                "Invalid 3D histogram '{}': Missing column(s) '{}', '{}', '{}'",
+                                hist3d.name,
+                                hist3d.x_column_name,
+                                hist3d.y_column_name,
+                                hist3d.z_column_name
+                            );
+                        }
+                    }
+                }
+                Config::HistND(histnd) => {
+                    if histnd.calculate {
+                        if let Err(e) = histnd.check_dimensions() {
+                            log::error!("Invalid ND histogram '{}': {}", histnd.name, e);
+                        } else if let Some(missing) = histnd
+                            .column_names
+                            .iter()
+                            .find(|column| !column_names.contains(column))
+                        {
+                            log::error!(
+                                "Invalid ND histogram '{}': Missing column '{}'",
+                                histnd.name,
+                                missing
+                            );
+                        } else {
+                            // Validate cuts for the histogram
+                            let valid_hist_cuts: Vec<Cut> = histnd
+                                .cuts
+                                .cuts
+                                .iter()
+                                .filter(|cut| {
+                                    let required_columns = cut.required_columns();
+                                    for column in required_columns {
+                                        if !column_names.contains(&column) {
+                                            log::error!(
+                                                "Invalid cut '{}' for ND histogram '{}': Missing column '{}'",
+                                                cut.name(),
+                                                histnd.name,
+                                                column
+                                            );
+                                            return false;
+                                        }
+                                    }
+                                    true
+                                })
+                                .cloned()
+                                .collect();
+
+                            let mut validated_histnd = histnd.clone();
+                            validated_histnd.cuts.cuts = valid_hist_cuts;
+                            validated_histnd.cuts.parse_conditions();
+                            valid_configs.push(Config::HistND(validated_histnd));
+                        }
+                    }
+                }
             }
         }
 
@@ -273,26 +676,91 @@ impl Configs {
             }
         }
 
+        // Detect identical histogram definitions so each one is only filled once
+        let (valid_configs, aliases) = Self::remove_duplicates(valid_configs);
+
         // Return a new Configs instance with validated configurations and cuts
         Configs {
             configs: valid_configs,
             columns: self.columns.clone(),
+            column_scripts: self.column_scripts.clone(),
+            calibration_tables: self.calibration_tables.clone(),
             cuts: valid_cuts,
+            variables: self.variables.clone(),
+            aliases,
+            time_column: self.time_column.clone(),
+            time_window: self.time_window,
+        }
+    }
+
+    fn remove_duplicates(configs: Vec<Config>) -> (Vec<Config>, Vec<(String, String)>) {
+        let mut unique_configs: Vec<Config> = Vec::new();
+        let mut aliases = Vec::new();
+        let mut seen: HashMap<String, String> = HashMap::new(); // key -> histogram name
+
+        for config in configs {
+            let key = config.duplicate_key();
+            if let Some(source) = seen.get(&key) {
+                if source != config.name() {
+                    log::info!(
+                        "Histogram '{}' is identical to '{}' and will be filled from it",
+                        config.name(),
+                        source
+                    );
+                    aliases.push((config.name().to_string(), source.clone()));
+                }
+            } else {
+                seen.insert(key, config.name().to_string());
+                unique_configs.push(config);
+            }
         }
+
+        (unique_configs, aliases)
     }
 
     pub fn check_and_add_panes(&self, h: &mut Histogrammer) {
         // reset all existings panes
         h.reset_histograms();
+        self.add_missing_panes(h);
+    }
+
+    pub fn add_missing_panes(&self, h: &mut Histogrammer) {
+        // duplicates get their own pane with the same binning as the histogram they are filled from
+        let alias_configs: Vec<Config> = self
+            .aliases
+            .iter()
+            .filter_map(|(alias, source)| {
+                self.configs
+                    .iter()
+                    .find(|config| config.name() == source)
+                    .map(|config| {
+                        let mut config = config.clone();
+                        match &mut config {
+                            Config::Hist1D(hist1d) => hist1d.name = alias.clone(),
+                            Config::Hist2D(hist2d) => hist2d.name = alias.clone(),
+                            Config::Hist3D(hist3d) => hist3d.name = alias.clone(),
+                            Config::HistND(histnd) => histnd.name = alias.clone(),
+                        }
+                        config
+                    })
+            })
+            .collect();
 
         // add panes that do not already exist in the histogrammer
-        for config in &self.configs {
+        for config in self.configs.iter().chain(alias_configs.iter()) {
             match config {
                 Config::Hist1D(hist1d) => {
                     if let Some(_id) = h.find_existing_histogram(&hist1d.name) {
                         log::info!("Histogram {} already exists", hist1d.name);
                     } else {
-                        h.add_hist1d(&hist1d.name, hist1d.bins, (hist1d.range.0, hist1d.range.1));
+                        match hist1d.bin_edges() {
+                            Some(edges) => h.add_hist1d_with_edges(&hist1d.name, &edges),
+                            None => h.add_hist1d(
+                                &hist1d.name,
+                                hist1d.bins,
+                                (hist1d.range.0, hist1d.range.1),
+                            ),
+                        }
                     }
                 }
                 Config::Hist2D(hist2d) => {
@@ -309,6 +777,20 @@ impl Configs {
                         );
                     }
                 }
+                Config::Hist3D(hist3d) => {
+                    if let Some(_id) = h.find_existing_histogram(&hist3d.name) {
+                        log::info!("Histogram {} already exists", hist3d.name);
+                    } else {
+                        h.add_hist3d(&hist3d.name, hist3d.bins, hist3d.ranges);
+                    }
+                }
+                Config::HistND(histnd) => {
+                    if let Some(_id) = h.find_existing_histogram(&histnd.name) {
+                        log::info!("Histogram {} already exists", histnd.name);
+                    } else {
+                        h.add_histnd(&histnd.name, &histnd.bins, &histnd.ranges);
+                    }
+                }
             }
         }
     }
@@ -327,6 +809,16 @@ impl Configs {
                     used_column_names.push(hist2d.y_column_name.clone());
                     used_column_names.extend(hist2d.cuts.required_columns());
                 }
+                Config::Hist3D(hist3d) => {
+                    used_column_names.push(hist3d.x_column_name.clone());
+                    used_column_names.push(hist3d.y_column_name.clone());
+                    used_column_names.push(hist3d.z_column_name.clone());
+                    used_column_names.extend(hist3d.cuts.required_columns());
+                }
+                Config::HistND(histnd) => {
+                    used_column_names.extend(histnd.column_names.iter().cloned());
+                    used_column_names.extend(histnd.cuts.required_columns());
+                }
             }
         }
 
@@ -354,13 +846,29 @@ impl Configs {
                         expanded_configs.push(Config::Hist2D(expanded_config));
                     }
                 }
+                Config::Hist3D(config) => {
+                    if config.calculate {
+                        expanded_configs.push(Config::Hist3D(config.clone()));
+                    }
+                }
+                Config::HistND(config) => {
+                    if config.calculate {
+                        expanded_configs.push(Config::HistND(config.clone()));
+                    }
+                }
             }
         }
 
         Configs {
             configs: expanded_configs,
             columns: self.columns.clone(),
+            column_scripts: self.column_scripts.clone(),
+            calibration_tables: self.calibration_tables.clone(),
             cuts: self.cuts.clone(),
+            variables: self.variables.clone(),
+            aliases: Vec::new(),
+            time_column: self.time_column.clone(),
+            time_window: self.time_window,
         }
     }
 
@@ -368,6 +876,44 @@ impl Configs {
         self.configs.is_empty()
     }
 
+    // Keeps the rows inside the time window, e.g. to replay part of a run
+    pub fn time_gated(&self, lf: LazyFrame) -> LazyFrame {
+        match self.time_gate(&lf) {
+            Some(gate) => lf.filter(gate),
+            None => lf,
+        }
+    }
+
+    // Filter of the time window, None when all rows are filled. A slice after the filter
+    // cannot be pushed down to the scan, so the fill slices first and gates each batch.
+    pub fn time_gate(&self, lf: &LazyFrame) -> Option<Expr> {
+        let (start, end) = self.time_window?;
+        if self.time_column.is_empty() {
+            return None;
+        }
+
+        let has_column = lf
+            .clone()
+            .collect_schema()
+            .is_ok_and(|schema| schema.contains(&self.time_column));
+        if !has_column {
+            log::error!(
+                "Time column '{}' not found, filling all rows",
+                self.time_column
+            );
+            return None;
+        }
+
+        log::info!(
+            "Filling rows with {} in [{}, {})",
+            self.time_column,
+            start,
+            end
+        );
+        let time = col(&self.time_column).cast(DataType::Float64);
+        Some(time.clone().gt_eq(lit(start)).and(time.lt(lit(end))))
+    }
+
     pub fn config_ui(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.label("Histograms");
@@ -381,6 +927,7 @@ impl Configs {
                     cuts: Cuts::default(),
                     calculate: true,
                     enabled: true,
+                    edges: String::new(),
                 }));
             }
 
@@ -398,6 +945,26 @@ impl Configs {
                 }));
             }
 
+            if ui.button("+3D").clicked() {
+                self.configs.push(Config::Hist3D(Hist3DConfig::new(
+                    "",
+                    "",
+                    "",
+                    "",
+                    [(0.0, 4096.0); 3],
+                    (256, 256, 256),
+                )));
+            }
+
+            if ui.button("+ND").clicked() {
+                self.configs.push(Config::HistND(HistNDConfig::new(
+                    "",
+                    &["", ""],
+                    &[(0.0, 4096.0); 2],
+                    &[512; 2],
+                )));
+            }
+
             ui.separator();
 
             if ui.button("Remove All").clicked() {
@@ -450,11 +1017,19 @@ impl Configs {
                             Config::Hist2D(_) => {
                                 ui.label(format!("{index}"));
                             }
+                            Config::Hist3D(_) => {
+                                ui.label(format!("{index}"));
+                            }
+                            Config::HistND(_) => {
+                                ui.label(format!("{index}"));
+                            }
                         });
 
                         match config {
                             Config::Hist1D(config) => config.table_row(&mut row, &mut self.cuts),
                             Config::Hist2D(config) => config.table_row(&mut row, &mut self.cuts),
+                            Config::Hist3D(config) => config.table_row(&mut row, &mut self.cuts),
+                            Config::HistND(config) => config.table_row(&mut row, &mut self.cuts),
                         }
 
                         row.col(|ui| {
@@ -540,11 +1115,116 @@ impl Configs {
                 self.columns.remove(index);
             }
         }
+
+        self.calibration_table_ui(ui);
+
+        self.column_script_ui(ui);
+    }
+
+    // Columns calibrated with the coefficients of the channel of each row
+    fn calibration_table_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Calibration Tables");
+
+            if ui.button("+").clicked() {
+                self.calibration_tables.push(CalibrationTable::default());
+            }
+
+            ui.separator();
+
+            if ui.button("Remove All").clicked() {
+                self.calibration_tables.clear();
+            }
+        })
+        .response
+        .on_hover_text("A CSV of channel, a, b, c rows makes a column a·v² + b·v + c with the coefficients of the channel of each row");
+
+        let mut index_to_remove = None;
+        for (index, table) in self.calibration_tables.iter_mut().enumerate() {
+            ui.push_id(("calibration_table", index), |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("X").clicked() {
+                        index_to_remove = Some(index);
+                    }
+                    ui.vertical(|ui| table.ui(ui));
+                });
+            });
+        }
+
+        if let Some(index) = index_to_remove {
+            self.calibration_tables.remove(index);
+        }
+    }
+
+    // Scripts with let, if/else, and math functions for columns an expression can't describe
+    fn column_script_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Column Scripts");
+
+            if ui.button("+").clicked() {
+                self.column_scripts.push(ColumnScript::default());
+            }
+
+            ui.separator();
+
+            if ui.button("Remove All").clicked() {
+                self.column_scripts.clear();
+            }
+        })
+        .response
+        .on_hover_text(format!(
+            "let r = sqrt(X1**2 + X2**2);\nif r < 10 {{ r }} else {{ -1e6 }}\n\nFunctions: {}",
+            column_script::FUNCTIONS
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+
+        let mut index_to_remove = None;
+        for (index, script) in self.column_scripts.iter_mut().enumerate() {
+            ui.push_id(("column_script", index), |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut script.alias)
+                            .hint_text("Alias")
+                            .clip_text(false),
+                    );
+                    if ui.button("X").clicked() {
+                        index_to_remove = Some(index);
+                    }
+                });
+
+                ui.add(
+                    egui::TextEdit::multiline(&mut script.script)
+                        .code_editor()
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY)
+                        .hint_text("if X1 > 0 { sqrt(X1) } else { -1e6 }"),
+                );
+
+                if !script.script.trim().is_empty() {
+                    if let Err(e) = column_script::compile_script(&script.script) {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+                }
+            });
+        }
+
+        if let Some(index) = index_to_remove {
+            self.column_scripts.remove(index);
+        }
     }
 
-    pub fn cut_ui(&mut self, ui: &mut egui::Ui) {
+    pub fn cut_ui(&mut self, ui: &mut egui::Ui, cut_stats: &CutStats) {
         self.cuts.ui(ui);
 
+        if !self.cuts.is_empty() {
+            ui.collapsing("Cut Statistics", |ui| {
+                cut_stats.ui(ui);
+            });
+        }
+
         // verify/sync cuts with histograms
         for hist_config in &mut self.configs {
             match hist_config {
@@ -586,31 +1266,80 @@ impl Configs {
                         .cuts
                         .retain(|cut| self.cuts.cuts.iter().any(|c| c.name() == cut.name()));
                 }
+                Config::Hist3D(hist3d) => {
+                    for hist_cut in &mut hist3d.cuts.cuts {
+                        if let Some(updated_cut) = self
+                            .cuts
+                            .cuts
+                            .iter()
+                            .find(|cut| cut.name() == hist_cut.name())
+                        {
+                            // Replace the cut if the operation or content has changed
+                            *hist_cut = updated_cut.clone();
+                        }
+                    }
+
+                    // Remove cuts that no longer exist in `self.cuts`
+                    hist3d
+                        .cuts
+                        .cuts
+                        .retain(|cut| self.cuts.cuts.iter().any(|c| c.name() == cut.name()));
+                }
+                Config::HistND(histnd) => {
+                    for hist_cut in &mut histnd.cuts.cuts {
+                        if let Some(updated_cut) = self
+                            .cuts
+                            .cuts
+                            .iter()
+                            .find(|cut| cut.name() == hist_cut.name())
+                        {
+                            // Replace the cut if the operation or content has changed
+                            *hist_cut = updated_cut.clone();
+                        }
+                    }
+
+                    // Remove cuts that no longer exist in `self.cuts`
+                    histnd
+                        .cuts
+                        .cuts
+                        .retain(|cut| self.cuts.cuts.iter().any(|c| c.name() == cut.name()));
+                }
             }
         }
     }
 
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
+    // `cut_stats` are the passed events of each set of cuts in the last fill
+    pub fn ui(&mut self, ui: &mut egui::Ui, cut_stats: &CutStats) {
         self.column_ui(ui);
 
         ui.separator();
 
-        self.cut_ui(ui);
+        self.cut_ui(ui, cut_stats);
 
         ui.separator();
 
         self.config_ui(ui);
     }
 }
+// Lets hand written scripts leave out the flags that are usually set
+fn default_true() -> bool {
+    true
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
 pub struct Hist1DConfig {
     pub name: String,        // Histogram display name
     pub column_name: String, // Data column to fill from
     pub range: (f64, f64),   // Range for the histogram
     pub bins: usize,         // Number of bins
-    pub cuts: Cuts,          // Cuts for the histogram
-    pub calculate: bool,     // Whether to calculate the histogram
-    pub enabled: bool,       // Whether to let the user interact with the histogram
+    #[serde(default)]
+    pub cuts: Cuts, // Cuts for the histogram
+    #[serde(default = "default_true")]
+    pub calculate: bool, // Whether to calculate the histogram
+    #[serde(default = "default_true")]
+    pub enabled: bool, // Whether to let the user interact with the histogram
+    #[serde(default)]
+    pub edges: String, // Explicit bin edges, overrides the range and bins when set
 }
 
 impl Hist1DConfig {
@@ -623,9 +1352,68 @@ impl Hist1DConfig {
             cuts: Cuts::default(),
             calculate: true,
             enabled: true,
+            edges: String::new(),
         }
     }
 
+    // Parse the bin edges, either a list like "0, 10, 50, 100" or "log(min, max, bins)"
+    pub fn bin_edges(&self) -> Option<Vec<f64>> {
+        let text = self.edges.trim();
+        if text.is_empty() {
+            return None;
+        }
+
+        let edges = if let Some(args) = text
+            .strip_prefix("log(")
+            .and_then(|args| args.strip_suffix(')'))
+        {
+            let values: Vec<f64> = args
+                .split(',')
+                .filter_map(|value| value.trim().parse().ok())
+                .collect();
+            match values[..] {
+                [min, max, bins] if min > 0.0 && max > min && bins >= 1.0 => {
+                    let bins = bins as usize;
+                    let (log_min, log_max) = (min.log10(), max.log10());
+                    (0..=bins)
+                        .map(|i| 10f64.powf(log_min + (log_max - log_min) * i as f64 / bins as f64))
+                        .collect()
+                }
+                _ => {
+                    log::error!(
+                        "Invalid log bins '{}' for histogram '{}', expected log(min, max, bins) with min > 0",
+                        text,
+                        self.name
+                    );
+                    return None;
+                }
+            }
+        } else {
+            let values: Result<Vec<f64>, _> = text
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|value| !value.is_empty())
+                .map(|value| value.parse::<f64>())
+                .collect();
+            match values {
+                Ok(values) => values,
+                Err(e) => {
+                    log::error!("Invalid bin edges for histogram '{}': {}", self.name, e);
+                    return None;
+                }
+            }
+        };
+
+        if edges.len() < 2 || edges.windows(2).any(|pair| pair[1] <= pair[0]) {
+            log::error!(
+                "Invalid bin edges for histogram '{}': need at least two increasing edges",
+                self.name
+            );
+            return None;
+        }
+
+        Some(edges)
+    }
+
     pub fn table_row(&mut self, row: &mut egui_extras::TableRow<'_, '_>, cuts: &mut Cuts) {
         row.col(|ui| {
             ui.add_enabled(
@@ -665,7 +1453,28 @@ impl Hist1DConfig {
         });
 
         row.col(|ui| {
-            ui.add_enabled(self.enabled, egui::DragValue::new(&mut self.bins).speed(1));
+            ui.horizontal(|ui| {
+                ui.add_enabled(
+                    self.enabled && self.edges.trim().is_empty(),
+                    egui::DragValue::new(&mut self.bins).speed(1),
+                );
+
+                ui.add_enabled_ui(self.enabled, |ui| {
+                    ui.menu_button("Edges", |ui| {
+                        ui.label("Variable width bins, overrides the range and bins");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.edges)
+                                .hint_text("0, 10, 50, 100 or log(1, 1000, 100)"),
+                        );
+                    })
+                    .response
+                    .on_hover_text(if self.edges.trim().is_empty() {
+                        "Fixed width bins"
+                    } else {
+                        &self.edges
+                    });
+                });
+            });
         });
 
         row.col(|ui| {
@@ -680,27 +1489,38 @@ impl Hist1DConfig {
                             .iter()
                             .any(|selected_cut| selected_cut == cut);
 
-                        match cut {
-                            Cut::Cut1D(cut1d) => {
-                                if ui.checkbox(&mut is_selected, &cut1d.name).clicked() {
-                                    if is_selected && !self.cuts.cuts.contains(cut) {
-                                        self.cuts.cuts.push(cut.clone());
-                                    } else if !is_selected {
-                                        self.cuts.cuts.retain(|selected_cut| selected_cut != cut);
+                        ui.horizontal(|ui| {
+                            match cut {
+                                Cut::Cut1D(cut1d) => {
+                                    if ui.checkbox(&mut is_selected, &cut1d.name).clicked() {
+                                        if is_selected && !self.cuts.cuts.contains(cut) {
+                                            self.cuts.cuts.push(cut.clone());
+                                        } else if !is_selected {
+                                            self.cuts
+                                                .cuts
+                                                .retain(|selected_cut| selected_cut != cut);
+                                        }
                                     }
                                 }
-                            }
-                            Cut::Cut2D(cut2d) => {
-                                if ui.checkbox(&mut is_selected, &cut2d.polygon.name).clicked() {
-                                    if is_selected && !self.cuts.cuts.contains(cut) {
-                                        self.cuts.cuts.push(cut.clone());
-                                    } else if !is_selected {
-                                        self.cuts.cuts.retain(|selected_cut| selected_cut != cut);
+                                Cut::Cut2D(cut2d) => {
+                                    if ui.checkbox(&mut is_selected, &cut2d.polygon.name).clicked()
+                                    {
+                                        if is_selected && !self.cuts.cuts.contains(cut) {
+                                            self.cuts.cuts.push(cut.clone());
+                                        } else if !is_selected {
+                                            self.cuts
+                                                .cuts
+                                                .retain(|selected_cut| selected_cut != cut);
+                                        }
                                     }
                                 }
                             }
-                        }
+
+                            self.cuts.veto_ui(ui, cut.name());
+                        });
                     }
+
+                    self.cuts.expression_ui(ui, cuts);
                 });
         });
 
@@ -795,9 +1615,12 @@ pub struct Hist2DConfig {
     pub x_range: (f64, f64),   // Range for X-axis
     pub y_range: (f64, f64),   // Range for Y-axis
     pub bins: (usize, usize),  // Number of bins for X and Y axes
-    pub cuts: Cuts,            // Cuts for the histogram
-    pub calculate: bool,       // Whether to calculate the histogram
-    pub enabled: bool,         // Whether to let the user interact with the histogram
+    #[serde(default)]
+    pub cuts: Cuts, // Cuts for the histogram
+    #[serde(default = "default_true")]
+    pub calculate: bool, // Whether to calculate the histogram
+    #[serde(default = "default_true")]
+    pub enabled: bool, // Whether to let the user interact with the histogram
 }
 
 impl Hist2DConfig {
@@ -911,27 +1734,38 @@ impl Hist2DConfig {
                             .iter()
                             .any(|selected_cut| selected_cut == cut);
 
-                        match cut {
-                            Cut::Cut1D(cut1d) => {
-                                if ui.checkbox(&mut is_selected, &cut1d.name).clicked() {
-                                    if is_selected && !self.cuts.cuts.contains(cut) {
-                                        self.cuts.cuts.push(cut.clone());
-                                    } else if !is_selected {
-                                        self.cuts.cuts.retain(|selected_cut| selected_cut != cut);
+                        ui.horizontal(|ui| {
+                            match cut {
+                                Cut::Cut1D(cut1d) => {
+                                    if ui.checkbox(&mut is_selected, &cut1d.name).clicked() {
+                                        if is_selected && !self.cuts.cuts.contains(cut) {
+                                            self.cuts.cuts.push(cut.clone());
+                                        } else if !is_selected {
+                                            self.cuts
+                                                .cuts
+                                                .retain(|selected_cut| selected_cut != cut);
+                                        }
                                     }
                                 }
-                            }
-                            Cut::Cut2D(cut2d) => {
-                                if ui.checkbox(&mut is_selected, &cut2d.polygon.name).clicked() {
-                                    if is_selected && !self.cuts.cuts.contains(cut) {
-                                        self.cuts.cuts.push(cut.clone());
-                                    } else if !is_selected {
-                                        self.cuts.cuts.retain(|selected_cut| selected_cut != cut);
+                                Cut::Cut2D(cut2d) => {
+                                    if ui.checkbox(&mut is_selected, &cut2d.polygon.name).clicked()
+                                    {
+                                        if is_selected && !self.cuts.cuts.contains(cut) {
+                                            self.cuts.cuts.push(cut.clone());
+                                        } else if !is_selected {
+                                            self.cuts
+                                                .cuts
+                                                .retain(|selected_cut| selected_cut != cut);
+                                        }
                                     }
                                 }
                             }
-                        }
+
+                            self.cuts.veto_ui(ui, cut.name());
+                        });
                     }
+
+                    self.cuts.expression_ui(ui, cuts);
                 });
         });
 
@@ -1051,11 +1885,309 @@ impl Hist2DConfig {
     }
 }
 
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct Hist3DConfig {
+    pub name: String,                // Histogram display name
+    pub x_column_name: String,       // Data column for X-axis
+    pub y_column_name: String,       // Data column for Y-axis
+    pub z_column_name: String,       // Data column for Z-axis
+    pub ranges: [(f64, f64); 3],     // Ranges for the X, Y, and Z axes
+    pub bins: (usize, usize, usize), // Number of bins for the X, Y, and Z axes
+    #[serde(default)]
+    pub cuts: Cuts, // Cuts for the histogram
+    #[serde(default = "default_true")]
+    pub calculate: bool, // Whether to calculate the histogram
+    #[serde(default = "default_true")]
+    pub enabled: bool, // Whether to let the user interact with the histogram
+}
+
+impl Hist3DConfig {
+    pub fn new(
+        name: &str,
+        x_column_name: &str,
+        y_column_name: &str,
+        z_column_name: &str,
+        ranges: [(f64, f64); 3],
+        bins: (usize, usize, usize),
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            x_column_name: x_column_name.to_string(),
+            y_column_name: y_column_name.to_string(),
+            z_column_name: z_column_name.to_string(),
+            ranges,
+            bins,
+            cuts: Cuts::default(),
+            calculate: true,
+            enabled: true,
+        }
+    }
+
+    pub fn table_row(&mut self, row: &mut egui_extras::TableRow<'_, '_>, cuts: &mut Cuts) {
+        row.col(|ui| {
+            ui.add_enabled(
+                self.enabled,
+                egui::TextEdit::singleline(&mut self.name)
+                    .hint_text("Name")
+                    .clip_text(false),
+            );
+        });
+
+        row.col(|ui| {
+            ui.vertical(|ui| {
+                for (column, hint) in [
+                    (&mut self.x_column_name, "X Column Name"),
+                    (&mut self.y_column_name, "Y Column Name"),
+                    (&mut self.z_column_name, "Z Column Name"),
+                ] {
+                    ui.add_enabled(
+                        self.enabled,
+                        egui::TextEdit::singleline(column)
+                            .hint_text(hint)
+                            .clip_text(false),
+                    );
+                }
+            });
+        });
+
+        row.col(|ui| {
+            ui.vertical(|ui| {
+                for range in &mut self.ranges {
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(
+                            self.enabled,
+                            egui::DragValue::new(&mut range.0)
+                                .speed(0.1)
+                                .prefix("(")
+                                .suffix(","),
+                        );
+                        ui.add_enabled(
+                            self.enabled,
+                            egui::DragValue::new(&mut range.1)
+                                .speed(0.1)
+                                .prefix(" ")
+                                .suffix(")"),
+                        );
+                    });
+                }
+            });
+        });
+
+        row.col(|ui| {
+            ui.vertical(|ui| {
+                for bins in [&mut self.bins.0, &mut self.bins.1, &mut self.bins.2] {
+                    ui.add_enabled(self.enabled, egui::DragValue::new(bins).speed(1));
+                }
+            });
+        });
+
+        row.col(|ui| {
+            egui::ComboBox::from_id_salt(format!("cut_select_3d_{}", self.name))
+                .selected_text("Select cuts")
+                .width(ui.available_width())
+                .show_ui(ui, |ui| {
+                    for cut in &cuts.cuts {
+                        let mut is_selected = self
+                            .cuts
+                            .cuts
+                            .iter()
+                            .any(|selected_cut| selected_cut == cut);
+
+                        ui.horizontal(|ui| {
+                            let name = match cut {
+                                Cut::Cut1D(cut1d) => &cut1d.name,
+                                Cut::Cut2D(cut2d) => &cut2d.polygon.name,
+                            };
+
+                            if ui.checkbox(&mut is_selected, name).clicked() {
+                                if is_selected && !self.cuts.cuts.contains(cut) {
+                                    self.cuts.cuts.push(cut.clone());
+                                } else if !is_selected {
+                                    self.cuts.cuts.retain(|selected_cut| selected_cut != cut);
+                                }
+                            }
+
+                            self.cuts.veto_ui(ui, cut.name());
+                        });
+                    }
+
+                    self.cuts.expression_ui(ui, cuts);
+                });
+        });
+
+        row.col(|ui| {
+            ui.add_enabled(self.enabled, egui::Checkbox::new(&mut self.calculate, ""));
+        });
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct HistNDConfig {
+    pub name: String,              // Histogram display name
+    pub column_names: Vec<String>, // Data column for each axis
+    pub ranges: Vec<(f64, f64)>,   // Range for each axis
+    pub bins: Vec<usize>,          // Number of bins for each axis
+    #[serde(default)]
+    pub cuts: Cuts, // Cuts for the histogram
+    #[serde(default = "default_true")]
+    pub calculate: bool, // Whether to calculate the histogram
+    #[serde(default = "default_true")]
+    pub enabled: bool, // Whether to let the user interact with the histogram
+}
+
+impl HistNDConfig {
+    pub fn new(name: &str, column_names: &[&str], ranges: &[(f64, f64)], bins: &[usize]) -> Self {
+        Self {
+            name: name.to_string(),
+            column_names: column_names.iter().map(|c| c.to_string()).collect(),
+            ranges: ranges.to_vec(),
+            bins: bins.to_vec(),
+            cuts: Cuts::default(),
+            calculate: true,
+            enabled: true,
+        }
+    }
+
+    pub fn check_dimensions(&self) -> Result<(), String> {
+        if self.column_names.is_empty() {
+            return Err("No columns".to_string());
+        }
+        if self.ranges.len() != self.column_names.len()
+            || self.bins.len() != self.column_names.len()
+        {
+            return Err("Every column needs a range and a number of bins".to_string());
+        }
+        if self.bins.contains(&0) {
+            return Err("Every axis needs at least one bin".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn table_row(&mut self, row: &mut egui_extras::TableRow<'_, '_>, cuts: &mut Cuts) {
+        row.col(|ui| {
+            ui.add_enabled(
+                self.enabled,
+                egui::TextEdit::singleline(&mut self.name)
+                    .hint_text("Name")
+                    .clip_text(false),
+            );
+        });
+
+        let mut remove_axis = None;
+        row.col(|ui| {
+            ui.vertical(|ui| {
+                for (axis, column) in self.column_names.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(
+                            self.enabled,
+                            egui::TextEdit::singleline(column)
+                                .hint_text(format!("Axis {} Column Name", axis))
+                                .clip_text(false),
+                        );
+                        if ui
+                            .add_enabled(self.enabled, egui::Button::new("-").small())
+                            .clicked()
+                        {
+                            remove_axis = Some(axis);
+                        }
+                    });
+                }
+
+                if ui
+                    .add_enabled(self.enabled, egui::Button::new("+ Axis").small())
+                    .clicked()
+                {
+                    self.column_names.push(String::new());
+                    self.ranges.push((0.0, 4096.0));
+                    self.bins.push(512);
+                }
+            });
+        });
+
+        if let Some(axis) = remove_axis {
+            self.column_names.remove(axis);
+            self.ranges.remove(axis);
+            self.bins.remove(axis);
+        }
+
+        row.col(|ui| {
+            ui.vertical(|ui| {
+                for range in &mut self.ranges {
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(
+                            self.enabled,
+                            egui::DragValue::new(&mut range.0)
+                                .speed(0.1)
+                                .prefix("(")
+                                .suffix(","),
+                        );
+                        ui.add_enabled(
+                            self.enabled,
+                            egui::DragValue::new(&mut range.1)
+                                .speed(0.1)
+                                .prefix(" ")
+                                .suffix(")"),
+                        );
+                    });
+                }
+            });
+        });
+
+        row.col(|ui| {
+            ui.vertical(|ui| {
+                for bins in &mut self.bins {
+                    ui.add_enabled(self.enabled, egui::DragValue::new(bins).speed(1));
+                }
+            });
+        });
+
+        row.col(|ui| {
+            egui::ComboBox::from_id_salt(format!("cut_select_nd_{}", self.name))
+                .selected_text("Select cuts")
+                .width(ui.available_width())
+                .show_ui(ui, |ui| {
+                    for cut in &cuts.cuts {
+                        let mut is_selected = self
+                            .cuts
+                            .cuts
+                            .iter()
+                            .any(|selected_cut| selected_cut == cut);
+
+                        ui.horizontal(|ui| {
+                            let name = match cut {
+                                Cut::Cut1D(cut1d) => &cut1d.name,
+                                Cut::Cut2D(cut2d) => &cut2d.polygon.name,
+                            };
+
+                            if ui.checkbox(&mut is_selected, name).clicked() {
+                                if is_selected && !self.cuts.cuts.contains(cut) {
+                                    self.cuts.cuts.push(cut.clone());
+                                } else if !is_selected {
+                                    self.cuts.cuts.retain(|selected_cut| selected_cut != cut);
+                                }
+                            }
+
+                            self.cuts.veto_ui(ui, cut.name());
+                        });
+                    }
+
+                    self.cuts.expression_ui(ui, cuts);
+                });
+        });
+
+        row.col(|ui| {
+            ui.add_enabled(self.enabled, egui::Checkbox::new(&mut self.calculate, ""));
+        });
+    }
+}
+
 use polars::prelude::*;
 use regex::Regex;
 
+// Arithmetic (+, -, *, /, **), comparisons that give 1 (true) or 0 (false), and calls to
+// the column script functions, e.g. "max(abs(X1), sqrt(X2)) * (Energy > 100)"
 fn expr_from_string(expression: &str) -> Result<Expr, PolarsError> {
-    let re = Regex::new(r"(-?\d+\.?\d*|\w+|\*\*|[+*/()-])").unwrap();
+    let re = Regex::new(r"(-?\d+\.?\d*|\w+|\*\*|==|!=|>=|<=|[<>+*/(),-])").unwrap();
     let tokens: Vec<String> = re
         .find_iter(expression)
         .map(|m| m.as_str().to_string())
@@ -1063,6 +2195,7 @@ fn expr_from_string(expression: &str) -> Result<Expr, PolarsError> {
 
     let mut expr_stack: Vec<Expr> = Vec::new();
     let mut op_stack: Vec<String> = Vec::new();
+    let mut arg_counts: Vec<usize> = Vec::new(); // arguments of the open function calls
 
     log::debug!("Starting evaluation of expression: '{}'", expression);
     log::debug!("Tokens: {:?}", tokens);
@@ -1071,7 +2204,7 @@ fn expr_from_string(expression: &str) -> Result<Expr, PolarsError> {
     while i < tokens.len() {
         let token = &tokens[i];
         match token.as_str() {
-            "+" | "-" | "*" | "/" | "**" => {
+            "+" | "-" | "*" | "/" | "**" | "==" | "!=" | ">=" | "<=" | ">" | "<" => {
                 // Handle consecutive operators like "- -" or "- +"
                 if i < tokens.len() - 1 && (tokens[i + 1] == "-" || tokens[i + 1] == "+") {
                     // Collapse consecutive operators into one
@@ -1103,9 +2236,32 @@ fn expr_from_string(expression: &str) -> Result<Expr, PolarsError> {
                 }
                 op_stack.push(token.clone());
             }
+            _ if tokens.get(i + 1).is_some_and(|next| next == "(")
+                && token.starts_with(|c: char| c.is_alphabetic() || c == '_') =>
+            {
+                // function call, the name waits under its "(" until the ")"
+                op_stack.push(format!("fn:{}", token));
+                arg_counts.push(usize::from(tokens.get(i + 2).is_some_and(|t| t != ")")));
+            }
             "(" => {
                 op_stack.push(token.clone());
             }
+            "," => {
+                while let Some(op) = op_stack.last() {
+                    if op == "(" {
+                        break;
+                    }
+                    apply_op(&mut expr_stack, op_stack.pop().unwrap().as_str());
+                }
+                match arg_counts.last_mut() {
+                    Some(count) => *count += 1,
+                    None => {
+                        return Err(PolarsError::ComputeError(
+                            format!("',' outside of a function call in '{}'", expression).into(),
+                        ))
+                    }
+                }
+            }
             ")" => {
                 while let Some(op) = op_stack.pop() {
                     if op == "(" {
@@ -1113,6 +2269,20 @@ fn expr_from_string(expression: &str) -> Result<Expr, PolarsError> {
                     }
                     apply_op(&mut expr_stack, &op);
                 }
+                if let Some(name) = op_stack.last().and_then(|op| op.strip_prefix("fn:")) {
+                    let name = name.to_string();
+                    op_stack.pop();
+                    let count = arg_counts.pop().unwrap_or(0);
+                    if expr_stack.len() < count {
+                        return Err(PolarsError::ComputeError(
+                            format!("Missing arguments of '{}'", name).into(),
+                        ));
+                    }
+                    let args = expr_stack.split_off(expr_stack.len() - count);
+                    let result = column_script::function_expr(&name, args)
+                        .map_err(|e| PolarsError::ComputeError(e.into()))?;
+                    expr_stack.push(result);
+                }
             }
             _ if token.parse::<f64>().is_ok() => {
                 let number = token.parse::<f64>().unwrap();
@@ -1139,9 +2309,10 @@ fn expr_from_string(expression: &str) -> Result<Expr, PolarsError> {
 
 fn precedence(op: &str) -> i32 {
     match op {
-        "+" | "-" => 1,
-        "*" | "/" => 2,
-        "**" => 3,
+        "==" | "!=" | ">=" | "<=" | ">" | "<" => 1,
+        "+" | "-" => 2,
+        "*" | "/" => 3,
+        "**" => 4,
         _ => 0,
     }
 }
@@ -1149,6 +2320,7 @@ fn precedence(op: &str) -> i32 {
 fn is_left_associative(op: &str) -> bool {
     match op {
         "+" | "-" | "*" | "/" => true,
+        "==" | "!=" | ">=" | "<=" | ">" | "<" => true,
         "**" => false, // Exponentiation is right-associative
         _ => false,
     }
@@ -1169,6 +2341,13 @@ fn apply_op(expr_stack: &mut Vec<Expr>, operator: &str) {
         "*" => left * right,
         "/" => left / right,
         "**" => left.pow(right),
+        // comparisons are stored as 1.0 and 0.0 like every other column
+        "==" => left.eq(right).cast(DataType::Float64),
+        "!=" => left.neq(right).cast(DataType::Float64),
+        ">=" => left.gt_eq(right).cast(DataType::Float64),
+        "<=" => left.lt_eq(right).cast(DataType::Float64),
+        ">" => left.gt(right).cast(DataType::Float64),
+        "<" => left.lt(right).cast(DataType::Float64),
         _ => {
             log::error!("Unknown operator: '{}'", operator);
             return;
@@ -0,0 +1,524 @@
+use polars::prelude::*;
+use regex::Regex;
+use std::sync::OnceLock;
+
+// Boolean expressions used by 1D cuts, e.g. "(X1 != -1e6 & abs(X2) < 5) | !(Xavg > 100)".
+// Expressions are parsed once and compiled into a Polars expression to build the fill mask.
+// Expressions written for the earlier parser, "column op number" conditions that all have to
+// pass, e.g. "X1 != -1e6, X2 == -1e6", are still read that way.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Gt,
+    Lt,
+    GtEq,
+    LtEq,
+    Eq,
+    NotEq,
+}
+
+impl CompareOp {
+    fn apply(&self, a: f64, b: f64) -> bool {
+        match self {
+            CompareOp::Gt => a > b,
+            CompareOp::Lt => a < b,
+            CompareOp::GtEq => a >= b,
+            CompareOp::LtEq => a <= b,
+            CompareOp::Eq => a == b,
+            CompareOp::NotEq => a != b,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Column(String),
+    Literal(f64),
+    Abs(Box<Operand>),
+}
+
+impl Operand {
+    fn to_expr(&self) -> Expr {
+        match self {
+            Operand::Column(name) => col(name.as_str()),
+            Operand::Literal(value) => lit(*value),
+            Operand::Abs(operand) => {
+                // written with when/then so the polars "abs" feature is not required
+                let expr = operand.to_expr();
+                when(expr.clone().lt(lit(0.0)))
+                    .then(lit(0.0) - expr.clone())
+                    .otherwise(expr)
+            }
+        }
+    }
+
+    fn evaluate(&self, df: &DataFrame, row_idx: usize) -> Option<f64> {
+        match self {
+            Operand::Column(name) => match df.column(name).and_then(|c| c.f64()) {
+                Ok(column) => column.get(row_idx),
+                Err(_) => {
+                    log::error!("Column not found: {}", name);
+                    None
+                }
+            },
+            Operand::Literal(value) => Some(*value),
+            Operand::Abs(operand) => operand.evaluate(df, row_idx).map(f64::abs),
+        }
+    }
+
+    fn columns(&self, columns: &mut Vec<String>) {
+        match self {
+            Operand::Column(name) => {
+                if !columns.contains(name) {
+                    columns.push(name.clone());
+                }
+            }
+            Operand::Literal(_) => {}
+            Operand::Abs(operand) => operand.columns(columns),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CutExpression {
+    True, // empty expression, every row passes
+    Compare(Operand, CompareOp, Operand),
+    And(Box<CutExpression>, Box<CutExpression>),
+    Or(Box<CutExpression>, Box<CutExpression>),
+    Not(Box<CutExpression>),
+}
+
+impl CutExpression {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        Self::parse_tokens(expression).or_else(|e| Self::parse_legacy(expression).ok_or(e))
+    }
+
+    fn parse_tokens(expression: &str) -> Result<Self, String> {
+        let tokens = tokenize(expression)?;
+        if tokens.is_empty() {
+            return Ok(CutExpression::True);
+        }
+
+        let mut parser = Parser { tokens, pos: 0 };
+        let parsed = parser.or()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(format!(
+                "Unexpected '{:?}' in '{}'",
+                parser.tokens[parser.pos], expression
+            ));
+        }
+        Ok(parsed)
+    }
+
+    // Simple comparisons joined by &, commas, "and", or spaces, as the earlier parser read them.
+    // Anything else (|, !, parentheses, abs, or a term that is not a comparison) is None, so
+    // the error of the tokenizer is shown instead of a cut that silently drops terms.
+    fn parse_legacy(expression: &str) -> Option<Self> {
+        static LEGACY_RE: OnceLock<(Regex, Regex)> = OnceLock::new();
+        let (condition_re, legacy_re) = LEGACY_RE.get_or_init(|| {
+            let condition = r"(?P<column>\w+)\s*(?P<op>>=|<=|!=|==|>|<)\s*(?P<value>-?\d+(\.\d+)?(e-?\d+)?|nan|inf)";
+            let unnamed = r"\w+\s*(>=|<=|!=|==|>|<)\s*(-?\d+(\.\d+)?(e-?\d+)?|nan|inf)";
+            let separator = r"(\s*(&&|&|,|\band\b)\s*|\s+)";
+            (
+                Regex::new(condition).unwrap(),
+                Regex::new(&format!(r"^\s*{unnamed}({separator}{unnamed})*\s*$")).unwrap(),
+            )
+        });
+
+        if !legacy_re.is_match(expression) {
+            return None;
+        }
+
+        condition_re
+            .captures_iter(expression)
+            .map(|caps| {
+                let op = match &caps["op"] {
+                    ">=" => CompareOp::GtEq,
+                    "<=" => CompareOp::LtEq,
+                    "!=" => CompareOp::NotEq,
+                    "==" => CompareOp::Eq,
+                    ">" => CompareOp::Gt,
+                    _ => CompareOp::Lt,
+                };
+                let value = caps["value"].parse().unwrap_or(f64::NAN);
+                CutExpression::Compare(
+                    Operand::Column(caps["column"].to_string()),
+                    op,
+                    Operand::Literal(value),
+                )
+            })
+            .reduce(|a, b| CutExpression::And(Box::new(a), Box::new(b)))
+    }
+
+    pub fn to_expr(&self) -> Expr {
+        match self {
+            CutExpression::True => lit(true),
+            CutExpression::Compare(a, op, b) => {
+                let (a, b) = (a.to_expr(), b.to_expr());
+                match op {
+                    CompareOp::Gt => a.gt(b),
+                    CompareOp::Lt => a.lt(b),
+                    CompareOp::GtEq => a.gt_eq(b),
+                    CompareOp::LtEq => a.lt_eq(b),
+                    CompareOp::Eq => a.eq(b),
+                    CompareOp::NotEq => a.neq(b),
+                }
+            }
+            CutExpression::And(a, b) => a.to_expr().and(b.to_expr()),
+            CutExpression::Or(a, b) => a.to_expr().or(b.to_expr()),
+            CutExpression::Not(a) => a.to_expr().not(),
+        }
+    }
+
+    pub fn evaluate(&self, df: &DataFrame, row_idx: usize) -> bool {
+        match self {
+            CutExpression::True => true,
+            CutExpression::Compare(a, op, b) => {
+                match (a.evaluate(df, row_idx), b.evaluate(df, row_idx)) {
+                    (Some(a), Some(b)) => op.apply(a, b),
+                    _ => false,
+                }
+            }
+            CutExpression::And(a, b) => a.evaluate(df, row_idx) && b.evaluate(df, row_idx),
+            CutExpression::Or(a, b) => a.evaluate(df, row_idx) || b.evaluate(df, row_idx),
+            CutExpression::Not(a) => !a.evaluate(df, row_idx),
+        }
+    }
+
+    pub fn create_mask(&self, df: &DataFrame) -> Result<BooleanChunked, PolarsError> {
+        expr_mask(df, self.to_expr())
+    }
+
+    pub fn columns(&self) -> Vec<String> {
+        let mut columns = Vec::new();
+        self.collect_columns(&mut columns);
+        columns
+    }
+
+    fn collect_columns(&self, columns: &mut Vec<String>) {
+        match self {
+            CutExpression::True => {}
+            CutExpression::Compare(a, _, b) => {
+                a.columns(columns);
+                b.columns(columns);
+            }
+            CutExpression::And(a, b) | CutExpression::Or(a, b) => {
+                a.collect_columns(columns);
+                b.collect_columns(columns);
+            }
+            CutExpression::Not(a) => a.collect_columns(columns),
+        }
+    }
+}
+
+// Evaluates a boolean expression over the whole frame, nulls do not pass
+pub fn expr_mask(df: &DataFrame, expr: Expr) -> Result<BooleanChunked, PolarsError> {
+    let mask = df
+        .clone()
+        .lazy()
+        .with_column(expr.alias("__cut_mask"))
+        .select([col("__cut_mask")])
+        .collect()?;
+
+    Ok(mask
+        .column("__cut_mask")?
+        .bool()?
+        .into_iter()
+        .map(|value| value.unwrap_or(false))
+        .collect())
+}
+
+// Logical combination of named cuts, e.g. "pid & !timing_bad | beam_on".
+// Uses the same operators and precedence as the cut expressions: ! before & before |.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CutLogic {
+    Cut(String),
+    And(Box<CutLogic>, Box<CutLogic>),
+    Or(Box<CutLogic>, Box<CutLogic>),
+    Not(Box<CutLogic>),
+}
+
+impl CutLogic {
+    // None for an empty expression
+    pub fn parse(expression: &str) -> Result<Option<Self>, String> {
+        let tokens = tokenize(expression)?;
+        if tokens.is_empty() {
+            return Ok(None);
+        }
+
+        let mut parser = Parser { tokens, pos: 0 };
+        let parsed = parser.logic_or()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(format!(
+                "Unexpected '{:?}' in '{}'",
+                parser.tokens[parser.pos], expression
+            ));
+        }
+        Ok(Some(parsed))
+    }
+
+    // `cut` gives the boolean expression of a cut from its name
+    pub fn to_expr(&self, cut: &dyn Fn(&str) -> Result<Expr, String>) -> Result<Expr, String> {
+        Ok(match self {
+            CutLogic::Cut(name) => cut(name)?,
+            CutLogic::And(a, b) => a.to_expr(cut)?.and(b.to_expr(cut)?),
+            CutLogic::Or(a, b) => a.to_expr(cut)?.or(b.to_expr(cut)?),
+            CutLogic::Not(a) => a.to_expr(cut)?.not(),
+        })
+    }
+
+    // Names of the cuts used by the expression, without duplicates
+    pub fn names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        self.collect_names(&mut names);
+        names
+    }
+
+    fn collect_names(&self, names: &mut Vec<String>) {
+        match self {
+            CutLogic::Cut(name) => {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            CutLogic::And(a, b) | CutLogic::Or(a, b) => {
+                a.collect_names(names);
+                b.collect_names(names);
+            }
+            CutLogic::Not(a) => a.collect_names(names),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Compare(CompareOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            '&' => {
+                tokens.push(Token::And);
+                i += if next == Some('&') { 2 } else { 1 };
+            }
+            '|' => {
+                tokens.push(Token::Or);
+                i += if next == Some('|') { 2 } else { 1 };
+            }
+            '!' if next == Some('=') => {
+                tokens.push(Token::Compare(CompareOp::NotEq));
+                i += 2;
+            }
+            '!' => {
+                tokens.push(Token::Not);
+                i += 1;
+            }
+            '=' if next == Some('=') => {
+                tokens.push(Token::Compare(CompareOp::Eq));
+                i += 2;
+            }
+            '>' | '<' => {
+                let op = match (c, next == Some('=')) {
+                    ('>', true) => CompareOp::GtEq,
+                    ('>', false) => CompareOp::Gt,
+                    ('<', true) => CompareOp::LtEq,
+                    _ => CompareOp::Lt,
+                };
+                tokens.push(Token::Compare(op));
+                i += if next == Some('=') { 2 } else { 1 };
+            }
+            c if c.is_ascii_digit()
+                || c == '.'
+                || (c == '-' && next.is_some_and(|n| n.is_ascii_digit() || n == '.')) =>
+            {
+                let start = i;
+                i += 1;
+                while i < chars.len() {
+                    let c = chars[i];
+                    let exponent_sign = (c == '-' || c == '+') && matches!(chars[i - 1], 'e' | 'E');
+                    if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let text: String = chars[start..i].iter().collect();
+                let value = text
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid number '{}'", text))?;
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                match text.as_str() {
+                    "nan" => tokens.push(Token::Number(f64::NAN)),
+                    "inf" => tokens.push(Token::Number(f64::INFINITY)),
+                    _ => tokens.push(Token::Ident(text)),
+                }
+            }
+            _ => return Err(format!("Unexpected character '{}'", c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("Expected {:?}, found {:?}", expected, token)),
+            None => Err(format!(
+                "Expected {:?} at the end of the expression",
+                expected
+            )),
+        }
+    }
+
+    fn or(&mut self) -> Result<CutExpression, String> {
+        let mut left = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.and()?;
+            left = CutExpression::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<CutExpression, String> {
+        let mut left = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            let right = self.unary()?;
+            left = CutExpression::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<CutExpression, String> {
+        match self.peek() {
+            Some(Token::Not) => {
+                self.pos += 1;
+                Ok(CutExpression::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let inner = self.or()?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            _ => self.comparison(),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<CutExpression, String> {
+        let left = self.operand()?;
+        let op = match self.peek() {
+            Some(Token::Compare(op)) => *op,
+            // a column on its own is a flag, e.g. one made with "Energy > 100" in Column Creation
+            None | Some(Token::And | Token::Or | Token::RParen) => {
+                return Ok(CutExpression::Compare(
+                    left,
+                    CompareOp::NotEq,
+                    Operand::Literal(0.0),
+                ));
+            }
+            Some(token) => return Err(format!("Expected a comparison, found {:?}", token)),
+        };
+        self.pos += 1;
+        let right = self.operand()?;
+        Ok(CutExpression::Compare(left, op, right))
+    }
+
+    fn logic_or(&mut self) -> Result<CutLogic, String> {
+        let mut left = self.logic_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.logic_and()?;
+            left = CutLogic::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn logic_and(&mut self) -> Result<CutLogic, String> {
+        let mut left = self.logic_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            let right = self.logic_unary()?;
+            left = CutLogic::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn logic_unary(&mut self) -> Result<CutLogic, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(CutLogic::Not(Box::new(self.logic_unary()?))),
+            Some(Token::LParen) => {
+                let inner = self.logic_or()?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Some(Token::Ident(name)) => Ok(CutLogic::Cut(name)),
+            Some(token) => Err(format!("Expected a cut name, found {:?}", token)),
+            None => Err("Expected a cut name at the end of the expression".to_string()),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Operand::Literal(value)),
+            Some(Token::Ident(name)) if name == "abs" && self.peek() == Some(&Token::LParen) => {
+                self.pos += 1;
+                let inner = self.operand()?;
+                self.expect(Token::RParen)?;
+                Ok(Operand::Abs(Box::new(inner)))
+            }
+            Some(Token::Ident(name)) => Ok(Operand::Column(name)),
+            Some(token) => Err(format!("Expected a column or number, found {:?}", token)),
+            None => Err("Expected a column or number at the end of the expression".to_string()),
+        }
+    }
+}
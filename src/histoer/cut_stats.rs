@@ -0,0 +1,103 @@
+use egui_extras::{Column, TableBuilder};
+
+use super::cuts::Cuts;
+use super::fill_progress::format_rows;
+
+// Below this fraction of passed events a cut is shown in red, e.g. a cut in the wrong units
+const LOW_EFFICIENCY: f64 = 0.01;
+
+#[derive(Debug, Clone, Default)]
+pub struct CutCount {
+    pub key: String,   // Cuts::generate_key of the cut set
+    pub label: String, // the cut names or their expression
+    pub passed: usize,
+    pub total: usize,
+}
+
+impl CutCount {
+    pub fn efficiency(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.passed as f64 / self.total as f64
+        }
+    }
+}
+
+// Events that passed each distinct set of cuts of the histograms, counted while filling.
+// Shared with the fill thread, cleared when a new fill starts.
+#[derive(Debug, Clone, Default)]
+pub struct CutStats {
+    pub counts: Vec<CutCount>,
+}
+
+impl CutStats {
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+
+    pub fn add(&mut self, cuts: &Cuts, passed: usize, total: usize) {
+        let key = cuts.generate_key();
+        match self.counts.iter_mut().find(|count| count.key == key) {
+            Some(count) => {
+                count.passed += passed;
+                count.total += total;
+            }
+            None => self.counts.push(CutCount {
+                key,
+                label: cuts.label(),
+                passed,
+                total,
+            }),
+        }
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        if self.counts.is_empty() {
+            ui.label("Fill the histograms to see how many events pass each set of cuts");
+            return;
+        }
+
+        TableBuilder::new(ui)
+            .id_salt("cut_stats_table")
+            .column(Column::auto()) // Cuts
+            .column(Column::auto()) // Passed
+            .column(Column::auto()) // Failed
+            .column(Column::remainder()) // Efficiency
+            .striped(true)
+            .vscroll(false)
+            .header(20.0, |mut header| {
+                for title in ["Cuts", "Passed", "Failed", "Efficiency"] {
+                    header.col(|ui| {
+                        ui.label(title);
+                    });
+                }
+            })
+            .body(|mut body| {
+                for count in &self.counts {
+                    body.row(18.0, |mut row| {
+                        row.col(|ui| {
+                            ui.label(&count.label);
+                        });
+                        row.col(|ui| {
+                            ui.label(format_rows(count.passed));
+                        });
+                        row.col(|ui| {
+                            ui.label(format_rows(count.total - count.passed));
+                        });
+                        row.col(|ui| {
+                            let efficiency = count.efficiency();
+                            let text = format!("{:.2}%", 100.0 * efficiency);
+                            if efficiency < LOW_EFFICIENCY {
+                                ui.colored_label(egui::Color32::RED, text).on_hover_text(
+                                    "Less than 1% of the events pass, check the units of the cuts",
+                                );
+                            } else {
+                                ui.label(text);
+                            }
+                        });
+                    });
+                }
+            });
+    }
+}
@@ -1,14 +1,16 @@
 use geo::Contains;
-use regex::Regex;
 use std::fs::File;
 use std::io::{BufReader, Write};
 use std::ops::BitAnd;
 
 use polars::prelude::*;
 
+use super::cut_expression::{expr_mask, CutExpression, CutLogic};
 use crate::egui_plot_stuff::egui_polygon::EguiPolygon;
 use egui_extras::{Column, TableBuilder};
 
+// 2D cuts carry the editor state of their polygon, there are only a few cuts
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
 pub enum Cut {
     Cut1D(Cut1D),
@@ -55,16 +57,47 @@ impl Cut {
     pub fn new_1d(name: &str, expression: &str) -> Self {
         Cut::Cut1D(Cut1D::new(name, expression))
     }
+
+    // Whether both cuts pass the same events, the drawing and editor state are ignored
+    pub fn same_selection(&self, other: &Cut) -> bool {
+        match (self, other) {
+            (Cut::Cut1D(a), Cut::Cut1D(b)) => a.expression == b.expression && a.active == b.active,
+            (Cut::Cut2D(a), Cut::Cut2D(b)) => {
+                a.x_column == b.x_column
+                    && a.y_column == b.y_column
+                    && a.active == b.active
+                    && a.polygon.vertices == b.polygon.vertices
+                    && a.polygon.parts == b.polygon.parts
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+#[serde(default)]
 pub struct Cuts {
     pub cuts: Vec<Cut>,
+    // Logical combination of the cuts by name, e.g. "pid & !timing_bad | beam_on".
+    // Cuts not named in it are still required, empty means every cut is required.
+    #[serde(default)]
+    pub expression: String,
+    // Names of the cuts that are inverted, only the events that fail them are filled
+    #[serde(default)]
+    pub vetoes: Vec<String>,
 }
 
 impl Cuts {
     pub fn new(cuts: Vec<Cut>) -> Self {
-        Self { cuts }
+        Self {
+            cuts,
+            expression: String::new(),
+            vetoes: Vec::new(),
+        }
+    }
+
+    pub fn is_veto(&self, name: &str) -> bool {
+        self.vetoes.iter().any(|veto| veto == name)
     }
 
     pub fn get_active_cuts(&self) -> Cuts {
@@ -119,6 +152,27 @@ impl Cuts {
         }
     }
 
+    // Expressions that cannot be parsed, a histogram with them would not be filled
+    pub fn errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = self
+            .cuts
+            .iter()
+            .filter_map(|cut| match cut {
+                Cut::Cut1D(cut1d) => cut1d
+                    .error()
+                    .map(|e| format!("Invalid cut '{}': {}", cut1d.name, e)),
+                Cut::Cut2D(_) => None,
+            })
+            .collect();
+        if let Err(e) = CutLogic::parse(&self.expression) {
+            errors.push(format!(
+                "Invalid cut expression '{}': {}",
+                self.expression, e
+            ));
+        }
+        errors
+    }
+
     pub fn ui(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.label("Cuts");
@@ -246,11 +300,18 @@ impl Cuts {
     ) -> Result<BooleanChunked, PolarsError> {
         let masks: Vec<BooleanChunked> = cuts
             .iter()
-            .map(|cut| match cut {
-                Cut::Cut1D(cut1d) => cut1d.create_mask(df),
-                Cut::Cut2D(cut2d) => cut2d.create_mask(df),
+            .map(|cut| {
+                let mask = match cut {
+                    Cut::Cut1D(cut1d) => cut1d.create_mask(df)?,
+                    Cut::Cut2D(cut2d) => cut2d.create_mask(df)?,
+                };
+                Ok(if self.is_veto(cut.name()) {
+                    !mask
+                } else {
+                    mask
+                })
             })
-            .collect::<Result<Vec<_>, _>>()?;
+            .collect::<Result<Vec<_>, PolarsError>>()?;
 
         // Combine all masks with a logical AND
         let combined_mask = masks
@@ -261,6 +322,79 @@ impl Cuts {
         Ok(combined_mask)
     }
 
+    // Mask of the expression compiled into one Polars expression. 2D cuts are evaluated
+    // first and added to the frame as temporary columns.
+    pub fn create_expression_mask(
+        &self,
+        df: &DataFrame,
+        logic: &CutLogic,
+    ) -> Result<BooleanChunked, PolarsError> {
+        let column_name = |name: &str| format!("__cut_{}", name);
+        let find = |name: &str| self.cuts.iter().find(|cut| cut.name() == name);
+
+        let mut frame = df.clone();
+        for name in logic.names() {
+            if let Some(Cut::Cut2D(cut2d)) = find(&name) {
+                let mask = cut2d.create_mask(df)?.with_name(column_name(&name).into());
+                frame.with_column(mask.into_series())?;
+            }
+        }
+
+        let expr = logic
+            .to_expr(&|name| {
+                let expr = match find(name) {
+                    Some(Cut::Cut1D(cut1d)) => cut1d
+                        .parsed_expression
+                        .as_ref()
+                        .map(|expression| expression.to_expr())
+                        .ok_or_else(|| format!("Expression not parsed for cut '{}'", name))?,
+                    Some(Cut::Cut2D(_)) => col(column_name(name)),
+                    None => return Err(format!("Cut '{}' is not selected", name)),
+                };
+                Ok(if self.is_veto(name) { expr.not() } else { expr })
+            })
+            .map_err(|e| PolarsError::ComputeError(e.into()))?;
+
+        expr_mask(&frame, expr)
+    }
+
+    // Combined mask of all cuts, built once per chunk when filling. None when there are no cuts.
+    pub fn mask(&self, df: &DataFrame) -> Result<Option<Vec<bool>>, PolarsError> {
+        let logic = CutLogic::parse(&self.expression).map_err(|e| {
+            PolarsError::ComputeError(format!("Invalid cut expression: {}", e).into())
+        })?;
+
+        if self.cuts.is_empty() && logic.is_none() {
+            return Ok(None);
+        }
+
+        let mask = match logic {
+            Some(logic) => {
+                let names = logic.names();
+                let others: Vec<&Cut> = self
+                    .cuts
+                    .iter()
+                    .filter(|cut| !names.iter().any(|name| name == cut.name()))
+                    .collect();
+                let mask = self.create_expression_mask(df, &logic)?;
+                if others.is_empty() {
+                    mask
+                } else {
+                    mask.bitand(self.create_combined_mask(df, &others)?)
+                }
+            }
+            None => {
+                let cuts: Vec<&Cut> = self.cuts.iter().collect();
+                self.create_combined_mask(df, &cuts)?
+            }
+        };
+        Ok(Some(
+            mask.into_iter()
+                .map(|value| value.unwrap_or(false))
+                .collect(),
+        ))
+    }
+
     pub fn required_columns(&self) -> Vec<String> {
         self.cuts
             .iter()
@@ -271,8 +405,106 @@ impl Cuts {
     pub fn generate_key(&self) -> String {
         let mut cut_names: Vec<String> =
             self.cuts.iter().map(|cut| cut.name().to_string()).collect();
+        for name in &mut cut_names {
+            if self.is_veto(name) {
+                name.insert(0, '!');
+            }
+        }
         cut_names.sort(); // Ensure consistent ordering
-        cut_names.join(",") // Create a comma-separated key
+        let key = cut_names.join(","); // Create a comma-separated key
+        if self.expression.trim().is_empty() {
+            key
+        } else {
+            format!("{}:{}", key, self.expression.trim())
+        }
+    }
+
+    // Readable name of the cuts, the expression when there is one
+    pub fn label(&self) -> String {
+        let names: Vec<String> = self
+            .cuts
+            .iter()
+            .map(|cut| match self.is_veto(cut.name()) {
+                true => format!("!{}", cut.name()),
+                false => cut.name().to_string(),
+            })
+            .collect();
+        match (self.expression.trim(), names.is_empty()) {
+            ("", _) => names.join(" & "),
+            (expression, true) => expression.to_string(),
+            (expression, false) => {
+                let others: Vec<String> = match CutLogic::parse(expression) {
+                    Ok(Some(logic)) => {
+                        let used = logic.names();
+                        names
+                            .into_iter()
+                            .filter(|name| {
+                                !used.iter().any(|used| used == name.trim_start_matches('!'))
+                            })
+                            .collect()
+                    }
+                    _ => names,
+                };
+                if others.is_empty() {
+                    expression.to_string()
+                } else {
+                    format!("({}) & {}", expression, others.join(" & "))
+                }
+            }
+        }
+    }
+
+    // "Veto" next to a cut in the cut selection of a histogram
+    pub fn veto_ui(&mut self, ui: &mut egui::Ui, name: &str) {
+        let selected = self.cuts.iter().any(|cut| cut.name() == name);
+        if !selected {
+            self.vetoes.retain(|veto| veto != name);
+        }
+
+        let mut veto = self.is_veto(name);
+        if ui
+            .add_enabled(selected, egui::Checkbox::new(&mut veto, "Veto"))
+            .on_hover_text("Fill only the events that fail this cut")
+            .changed()
+        {
+            if veto {
+                self.vetoes.push(name.to_string());
+            } else {
+                self.vetoes.retain(|v| v != name);
+            }
+        }
+    }
+
+    // Text field for the expression, shown under the cut selection of a histogram.
+    // Cuts named in the expression are selected from `available` as they are typed.
+    pub fn expression_ui(&mut self, ui: &mut egui::Ui, available: &Cuts) {
+        ui.separator();
+        ui.add(
+            egui::TextEdit::singleline(&mut self.expression)
+                .hint_text("Combine, e.g. pid & !bad | beam")
+                .clip_text(false),
+        )
+        .on_hover_text(CUT_LOGIC_HELP);
+
+        match CutLogic::parse(&self.expression) {
+            Ok(Some(logic)) => {
+                for name in logic.names() {
+                    if self.cuts.iter().any(|cut| cut.name() == name) {
+                        continue;
+                    }
+                    match available.cuts.iter().find(|cut| cut.name() == name) {
+                        Some(cut) => self.cuts.push(cut.clone()),
+                        None => {
+                            ui.colored_label(egui::Color32::RED, format!("Unknown cut '{}'", name));
+                        }
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+        }
     }
 }
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
@@ -372,7 +604,7 @@ impl Cut2D {
     }
 
     pub fn create_mask(&self, df: &DataFrame) -> Result<BooleanChunked, PolarsError> {
-        let polygon = self.to_geo_polygon();
+        let polygon = self.polygon.to_geo();
         let x_col = df.column(&self.x_column)?.f64()?;
         let y_col = df.column(&self.y_column)?.f64()?;
 
@@ -412,16 +644,9 @@ impl Cut2D {
         Ok(())
     }
 
-    fn to_geo_polygon(&self) -> geo::Polygon<f64> {
-        let exterior_coords: Vec<_> = self.polygon.vertices.iter().map(|&[x, y]| (x, y)).collect();
-        let exterior_line_string = geo::LineString::from(exterior_coords);
-        geo::Polygon::new(exterior_line_string, vec![])
-    }
-
+    // Inside any of the polygons of the cut
     pub fn is_inside(&self, x: f64, y: f64) -> bool {
-        let point = geo::Point::new(x, y);
-        let polygon = self.to_geo_polygon();
-        polygon.contains(&point)
+        self.polygon.is_inside(x, y)
     }
 
     pub fn draw(&mut self, plot_ui: &mut egui_plot::PlotUi) {
@@ -432,8 +657,9 @@ impl Cut2D {
         self.polygon.handle_interactions(plot_response);
     }
 
+    // Also while a freehand polygon can be drawn, so the plot does not move with the pointer
     pub fn is_dragging(&self) -> bool {
-        self.polygon.is_dragging
+        self.polygon.is_dragging || (self.polygon.freehand && self.polygon.is_drawing())
     }
 
     pub fn is_clicking(&self) -> bool {
@@ -445,21 +671,22 @@ impl Cut2D {
     }
 }
 
-// Struct to hold each parsed condition
-#[derive(Debug, Clone, PartialEq)]
-pub struct ParsedCondition {
-    pub column_name: String,
-    pub operator: String,
-    pub literal_value: f64,
+const CUT_EXPRESSION_HELP: &str = "Comparisons (>, <, >=, <=, ==, !=) between columns and numbers combined with & (and), | (or), ! (not), and parentheses. abs(column) is also supported. A column on its own passes when it is not 0, e.g. a flag made with a comparison in Column Creation.\nExample: (Xavg > 0 | X1 != -1e6) & abs(Theta) < 5";
+
+const CUT_LOGIC_HELP: &str = "Cut names combined with & (and), | (or), ! (not), and parentheses. Names may only contain letters, digits, and underscores. Selected cuts that are not named are still required.\nExample: pid & !timing_bad | beam_on";
+
+fn default_active() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
 pub struct Cut1D {
     pub name: String,
     pub expression: String, // Logical expression to evaluate, e.g., "X1 != -1e6 & X2 == -1e6"
+    #[serde(default = "default_active")]
     pub active: bool,
     #[serde(skip)] // Skip during serialization
-    pub parsed_conditions: Option<Vec<ParsedCondition>>, // Cache parsed conditions
+    pub parsed_expression: Option<CutExpression>, // Cache parsed expression
 }
 
 impl Cut1D {
@@ -468,7 +695,7 @@ impl Cut1D {
             name: name.to_string(),
             expression: expression.to_string(),
             active: true,
-            parsed_conditions: None,
+            parsed_expression: None,
         }
     }
 
@@ -481,11 +708,15 @@ impl Cut1D {
             );
         });
         row.col(|ui| {
-            ui.add(
-                egui::TextEdit::singleline(&mut self.expression)
-                    .hint_text("Expression")
-                    .clip_text(false),
-            );
+            let error = self.error();
+            let mut text_edit = egui::TextEdit::singleline(&mut self.expression)
+                .hint_text("Expression")
+                .clip_text(false);
+            if error.is_some() {
+                text_edit = text_edit.text_color(egui::Color32::RED);
+            }
+            ui.add(text_edit)
+                .on_hover_text(error.unwrap_or(CUT_EXPRESSION_HELP.to_string()));
         });
         row.col(|ui| {
             ui.add(egui::Checkbox::new(&mut self.active, ""));
@@ -503,145 +734,53 @@ impl Cut1D {
             egui::TextEdit::singleline(&mut self.expression)
                 .hint_text("Expression")
                 .clip_text(false),
-        );
+        )
+        .on_hover_text(CUT_EXPRESSION_HELP);
+
+        if let Some(e) = self.error() {
+            ui.colored_label(egui::Color32::RED, e);
+        }
+    }
+
+    pub fn error(&self) -> Option<String> {
+        CutExpression::parse(&self.expression).err()
     }
 
     pub fn required_columns(&self) -> Vec<String> {
-        self.parsed_conditions
+        self.parsed_expression
             .as_ref()
-            .map_or(vec![], |conditions| {
-                conditions
-                    .iter()
-                    .map(|cond| cond.column_name.clone())
-                    .collect()
-            })
+            .map_or(vec![], |expression| expression.columns())
     }
 
-    // Parse and cache conditions
+    // Parse and cache the expression
     pub fn parse_conditions(&mut self) {
-        self.parsed_conditions = None; // Reset parsed conditions
-
-        let condition_re = Regex::new(
-            r"(?P<column>\w+)\s*(?P<op>>=|<=|!=|==|>|<)\s*(?P<value>-?\d+(\.\d+)?(e-?\d+)?|nan|inf)"
-        ).unwrap();
-
-        let mut conditions = Vec::new();
-        for caps in condition_re.captures_iter(&self.expression) {
-            let column_name = caps["column"].to_string();
-            let operator = caps["op"].to_string();
-            let literal_value: f64 = caps["value"].parse().unwrap();
-
-            conditions.push(ParsedCondition {
-                column_name,
-                operator,
-                literal_value,
-            });
-        }
-        self.parsed_conditions = Some(conditions);
+        self.parsed_expression = match CutExpression::parse(&self.expression) {
+            Ok(expression) => Some(expression),
+            Err(e) => {
+                log::error!("Failed to parse cut '{}': {}", self.name, e);
+                None
+            }
+        };
 
-        log::info!("Parsed conditions: {:?}", self.parsed_conditions);
+        log::info!("Parsed expression: {:?}", self.parsed_expression);
     }
 
-    // Validate a row using cached conditions
+    // Validate a row using the cached expression
     pub fn valid(&self, df: &DataFrame, row_idx: usize) -> bool {
-        if let Some(conditions) = &self.parsed_conditions {
-            // Iterate through all parsed conditions
-            for condition in conditions {
-                if let Ok(column) = df.column(&condition.column_name).and_then(|c| c.f64()) {
-                    if let Some(value) = column.get(row_idx) {
-                        match condition.operator.as_str() {
-                            ">" => {
-                                if value.partial_cmp(&condition.literal_value)
-                                    != Some(std::cmp::Ordering::Greater)
-                                {
-                                    return false;
-                                }
-                            }
-                            "<" => {
-                                if value.partial_cmp(&condition.literal_value)
-                                    != Some(std::cmp::Ordering::Less)
-                                {
-                                    return false;
-                                }
-                            }
-                            ">=" => {
-                                if value.partial_cmp(&condition.literal_value)
-                                    == Some(std::cmp::Ordering::Less)
-                                {
-                                    return false;
-                                }
-                            }
-                            "<=" => {
-                                if value.partial_cmp(&condition.literal_value)
-                                    == Some(std::cmp::Ordering::Greater)
-                                {
-                                    return false;
-                                }
-                            }
-                            "==" => {
-                                if value.partial_cmp(&condition.literal_value)
-                                    != Some(std::cmp::Ordering::Equal)
-                                {
-                                    return false;
-                                }
-                            }
-                            "!=" => {
-                                if value.partial_cmp(&condition.literal_value)
-                                    == Some(std::cmp::Ordering::Equal)
-                                {
-                                    return false;
-                                }
-                            }
-                            _ => {
-                                log::error!("Unknown operator: {}", condition.operator);
-                                return false;
-                            }
-                        }
-                    } else {
-                        return false; // Missing value for row
-                    }
-                } else {
-                    log::error!("Column not found: {}", condition.column_name);
-                    return false; // Missing column
-                }
-            }
-            true // All conditions passed
+        if let Some(expression) = &self.parsed_expression {
+            expression.evaluate(df, row_idx)
         } else {
-            log::error!("No parsed conditions for Cut1D '{}'", self.name);
+            log::error!("No parsed expression for Cut1D '{}'", self.name);
             false // Parsing failed or was not performed
         }
     }
 
     pub fn create_mask(&self, df: &DataFrame) -> Result<BooleanChunked, PolarsError> {
-        if let Some(conditions) = &self.parsed_conditions {
-            let mut masks = Vec::new();
-            for condition in conditions {
-                let column = df.column(&condition.column_name)?.f64()?;
-                let mask = match condition.operator.as_str() {
-                    ">" => column.gt(condition.literal_value),
-                    "<" => column.lt(condition.literal_value),
-                    ">=" => column.gt_eq(condition.literal_value),
-                    "<=" => column.lt_eq(condition.literal_value),
-                    "==" => column.equal(condition.literal_value),
-                    "!=" => column.not_equal(condition.literal_value),
-                    _ => {
-                        return Err(PolarsError::ComputeError(
-                            format!("Unknown operator: {}", condition.operator).into(),
-                        ))
-                    }
-                };
-                masks.push(mask);
-            }
-
-            // Combine all masks with a logical AND
-            let combined_mask = masks
-                .into_iter()
-                .reduce(|a, b| a.bitand(b))
-                .unwrap_or_else(|| BooleanChunked::from_slice("".into(), &[]));
-            Ok(combined_mask)
+        if let Some(expression) = &self.parsed_expression {
+            expression.create_mask(df)
         } else {
             Err(PolarsError::ComputeError(
-                "Conditions not parsed for Cut1D".into(),
+                format!("Expression not parsed for Cut1D '{}'", self.name).into(),
             ))
         }
     }
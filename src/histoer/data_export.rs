@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use super::histo1d::histogram1d::Histogram;
+use super::histo2d::histogram2d::Histogram2D;
+
+// Bin data of a single pane as CSV or JSON for plotting elsewhere (numpy, pandas, a
+// spreadsheet). The current bins are written, so a rebinned pane is exported rebinned.
+// 2D histograms are written as a sparse list of the filled bins.
+
+fn save_path(name: &str) -> Option<std::path::PathBuf> {
+    rfd::FileDialog::new()
+        .set_file_name(format!("{}.csv", name.replace('/', "_")))
+        .add_filter("CSV", &["csv"])
+        .add_filter("JSON", &["json"])
+        .save_file()
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+impl Histogram {
+    // Bin edges, centers, counts, and uncertainties, with the calibrated center when
+    // the calibration is active
+    pub fn data_csv(&self) -> String {
+        let calibration = &self.plot_settings.calibration;
+        let mut csv = String::from("bin_low,bin_high,bin_center,count,error");
+        if calibration.active {
+            csv.push_str(",calibrated_center");
+        }
+        csv.push('\n');
+
+        for index in 0..self.bins.len() {
+            csv.push_str(&format!(
+                "{},{},{},{},{}",
+                self.bin_edge(index),
+                self.bin_edge(index + 1),
+                self.bin_center(index),
+                self.bin_content(index),
+                self.bin_error(index)
+            ));
+            if calibration.active {
+                csv.push_str(&format!(
+                    ",{}",
+                    calibration.calibrate(self.bin_center(index))
+                ));
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn data_json(&self) -> serde_json::Value {
+        let bins = 0..self.bins.len();
+        let mut json = serde_json::json!({
+            "name": self.name,
+            "edges": (0..=self.bins.len()).map(|index| self.bin_edge(index)).collect::<Vec<_>>(),
+            "bin_centers": bins.clone().map(|index| self.bin_center(index)).collect::<Vec<_>>(),
+            "counts": bins.clone().map(|index| self.bin_content(index)).collect::<Vec<_>>(),
+            "errors": bins.clone().map(|index| self.bin_error(index)).collect::<Vec<_>>(),
+            "underflow": self.underflow,
+            "overflow": self.overflow,
+        });
+
+        let calibration = &self.plot_settings.calibration;
+        if calibration.active {
+            json["calibrated_centers"] = bins
+                .map(|index| calibration.calibrate(self.bin_center(index)))
+                .collect::<Vec<_>>()
+                .into();
+        }
+        json
+    }
+
+    pub fn export_data(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(path) = save_path(&self.name) else {
+            return Ok(());
+        };
+
+        let text = if is_json(&path) {
+            serde_json::to_string_pretty(&self.data_json())?
+        } else {
+            self.data_csv()
+        };
+        std::fs::write(&path, text)?;
+
+        log::info!("Exported '{}' to {}", self.name, path.display());
+        Ok(())
+    }
+}
+
+impl Histogram2D {
+    // Filled bins as (x, y, count), ordered by row
+    fn filled_bins(&self) -> Vec<(f64, f64, u64)> {
+        let mut counts: Vec<_> = self.bins.counts.iter().collect();
+        counts.sort_by_key(|&((x, y), _)| (y, x));
+        counts
+            .into_iter()
+            .map(|((x, y), count)| {
+                (
+                    self.range.x.min + (x as f64 + 0.5) * self.bins.x_width,
+                    self.range.y.min + (y as f64 + 0.5) * self.bins.y_width,
+                    count,
+                )
+            })
+            .collect()
+    }
+
+    pub fn data_csv(&self) -> String {
+        let mut csv = String::from("x_center,y_center,count\n");
+        for (x, y, count) in self.filled_bins() {
+            csv.push_str(&format!("{},{},{}\n", x, y, count));
+        }
+        csv
+    }
+
+    pub fn data_json(&self) -> serde_json::Value {
+        let bins = self.filled_bins();
+        serde_json::json!({
+            "name": self.name,
+            "bins": (self.bins.x, self.bins.y),
+            "range": ((self.range.x.min, self.range.x.max), (self.range.y.min, self.range.y.max)),
+            "x_centers": bins.iter().map(|bin| bin.0).collect::<Vec<_>>(),
+            "y_centers": bins.iter().map(|bin| bin.1).collect::<Vec<_>>(),
+            "counts": bins.iter().map(|bin| bin.2).collect::<Vec<_>>(),
+            "underflow": self.underflow,
+            "overflow": self.overflow,
+        })
+    }
+
+    pub fn export_data(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(path) = save_path(&self.name) else {
+            return Ok(());
+        };
+
+        let text = if is_json(&path) {
+            serde_json::to_string_pretty(&self.data_json())?
+        } else {
+            self.data_csv()
+        };
+        std::fs::write(&path, text)?;
+
+        log::info!("Exported '{}' to {}", self.name, path.display());
+        Ok(())
+    }
+}
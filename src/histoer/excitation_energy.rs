@@ -0,0 +1,257 @@
+use super::column_script::{compile_script, ColumnScript};
+use super::configs::{Config, Configs};
+use crate::util::file_columns::RUN_NUMBER;
+use crate::util::kinematics::{FocalPlane, Reaction};
+
+// Excitation energy of the residual from the focal plane position, for spectrographs like the
+// SE-SPS. The position is turned into a bending radius with the focal plane calibration,
+// rho = x / D + rho0, then into the ejectile momentum with the field, and the excitation energy
+// follows from the missing mass of the reaction. Runs taken at another beam energy or field are
+// listed with their own values and picked by the RunNumber column (File columns in the
+// processor). The conversion is written as a column script so it runs on whole columns.
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct RunSettings {
+    pub run: u64,
+    pub beam_energy: f64, // MeV
+    pub field: f64,       // kG
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct ExcitationEnergy {
+    pub active: bool,
+    pub alias: String,
+    pub position: String, // focal plane column in mm
+    pub reaction: Reaction,
+    pub focal_plane: FocalPlane,
+    pub runs: Vec<RunSettings>,
+    pub range: (f64, f64), // keV
+    pub bins: usize,
+}
+
+impl Default for ExcitationEnergy {
+    fn default() -> Self {
+        ExcitationEnergy {
+            active: false,
+            alias: "Ex".to_string(),
+            position: "Xavg".to_string(),
+            reaction: Reaction::default(),
+            focal_plane: FocalPlane::default(),
+            runs: Vec::new(),
+            range: (-1000.0, 10000.0),
+            bins: 1100,
+        }
+    }
+}
+
+impl ExcitationEnergy {
+    // Value of the default run, or of the listed run of the row
+    fn per_run(&self, default: f64, value: impl Fn(&RunSettings) -> f64) -> String {
+        let mut script = String::new();
+        for run in &self.runs {
+            script.push_str(&format!(
+                "if {} == {} {{ {} }} else ",
+                RUN_NUMBER,
+                run.run,
+                value(run)
+            ));
+        }
+        if self.runs.is_empty() {
+            format!("{}", default)
+        } else {
+            format!("{}{{ {} }}", script, default)
+        }
+    }
+
+    pub fn script(&self) -> Result<String, String> {
+        let mut reaction = self.reaction.clone();
+        let kinematics = reaction.kinematics()?;
+        let center = self
+            .focal_plane
+            .center(&kinematics)
+            .ok_or("The ground state is not reached at this beam energy and angle")?;
+        if self.focal_plane.dispersion == 0.0 {
+            return Err("The dispersion is 0".to_string());
+        }
+
+        let x = &self.position;
+        let mut script = format!(
+            "// Excitation energy in keV of {} from {}\n",
+            reaction.label(),
+            x
+        );
+        script.push_str(&format!(
+            "let rho = ({}) / {} + {};\n",
+            x, self.focal_plane.dispersion, center
+        ));
+        script.push_str(&format!(
+            "let T = {};\n",
+            self.per_run(self.reaction.beam_energy, |run| run.beam_energy)
+        ));
+        script.push_str(&format!(
+            "let B = {};\n",
+            self.per_run(self.reaction.field, |run| run.field)
+        ));
+        script.push_str(&format!(
+            "let p = rho * B * {};\n",
+            kinematics.momentum_per_rho()
+        ));
+        script.push_str(&format!(
+            "let E = T + {};\n",
+            kinematics.projectile + kinematics.target
+        ));
+        script.push_str(&format!(
+            "let P = sqrt(T * (T + {}));\n",
+            2.0 * kinematics.projectile
+        ));
+        script.push_str(&format!(
+            "let EB = E - sqrt(p**2 + {});\n",
+            kinematics.ejectile.powi(2)
+        ));
+        script.push_str(&format!(
+            "let m2 = EB**2 - (P**2 + p**2 - 2 * P * p * {});\n",
+            kinematics.angle.to_radians().cos()
+        ));
+        script.push_str(&format!(
+            "if ({}) == -1e6 || m2 <= 0 {{ -1e6 }} else {{ (sqrt(m2) - {}) * 1000 }}",
+            x, kinematics.residual
+        ));
+        Ok(script)
+    }
+
+    // Adds the column script, replacing one with the same alias, and its 1D histogram if it is
+    // not there yet
+    pub fn add_to(&self, configs: &mut Configs) {
+        let script = match self.script() {
+            Ok(script) => ColumnScript {
+                alias: self.alias.clone(),
+                script,
+            },
+            Err(e) => {
+                log::error!("Error in the excitation energy column: {}", e);
+                return;
+            }
+        };
+        match configs
+            .column_scripts
+            .iter_mut()
+            .find(|existing| existing.alias == self.alias)
+        {
+            Some(existing) => *existing = script,
+            None => configs.column_scripts.push(script),
+        }
+
+        let name = format!("SE-SPS/Focal Plane/{}", self.alias);
+        let exists = configs
+            .configs
+            .iter()
+            .any(|config| matches!(config, Config::Hist1D(hist) if hist.name == name));
+        if !exists {
+            configs.hist1d(&name, &self.alias, self.range, self.bins, None);
+        }
+    }
+
+    fn runs_ui(&mut self, ui: &mut egui::Ui) {
+        let mut remove = None;
+        egui::Grid::new("excitation_energy_runs")
+            .striped(true)
+            .show(ui, |ui| {
+                for (index, run) in self.runs.iter_mut().enumerate() {
+                    ui.add(egui::DragValue::new(&mut run.run).prefix("Run "));
+                    ui.add(
+                        egui::DragValue::new(&mut run.beam_energy)
+                            .speed(0.01)
+                            .range(0.0..=f64::INFINITY)
+                            .suffix(" MeV"),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut run.field)
+                            .speed(0.001)
+                            .range(0.0..=f64::INFINITY)
+                            .suffix(" kG"),
+                    );
+                    if ui.button("🗙").clicked() {
+                        remove = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+
+        if let Some(index) = remove {
+            self.runs.remove(index);
+        }
+
+        if ui
+            .button("Add Run")
+            .on_hover_text("Beam energy and field of a run that differ from the reaction, matched by the RunNumber column of the File columns")
+            .clicked()
+        {
+            let run = self.runs.last().map_or(0, |run| run.run + 1);
+            self.runs.push(RunSettings {
+                run,
+                beam_energy: self.reaction.beam_energy,
+                field: self.reaction.field,
+            });
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.active, "Excitation energy column")
+            .on_hover_text(
+            "Column of the excitation energy of the residual in keV from the focal plane position",
+        );
+        if !self.active {
+            return;
+        }
+
+        egui::Grid::new("excitation_energy").show(ui, |ui| {
+            ui.label("Alias:");
+            ui.add(egui::TextEdit::singleline(&mut self.alias).desired_width(150.0));
+            ui.end_row();
+
+            ui.label("Position:");
+            ui.add(egui::TextEdit::singleline(&mut self.position).desired_width(150.0))
+                .on_hover_text("Focal plane position column in mm");
+            ui.end_row();
+
+            ui.label("Histogram:");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut self.range.0)
+                        .speed(1.0)
+                        .prefix("("),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.range.1)
+                        .speed(1.0)
+                        .suffix(") keV"),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.bins)
+                        .range(1..=usize::MAX)
+                        .suffix(" bins"),
+                );
+            });
+            ui.end_row();
+        });
+
+        self.reaction.ui(ui);
+        let kinematics = self.reaction.kinematics().ok();
+        self.focal_plane.ui(ui, kinematics.as_ref());
+
+        ui.collapsing("Runs", |ui| self.runs_ui(ui));
+
+        match self
+            .script()
+            .and_then(|script| compile_script(&script).map(|_| script))
+        {
+            Ok(script) => {
+                ui.label("Script").on_hover_text(script);
+            }
+            Err(e) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use egui::{Color32, Pos2, Rect};
+
+use super::scene::{Anchor, Item};
+use super::settings::FigureSettings;
+
+// Data to figure mapping of one plot panel, with the frame, ticks, and labels around it
+#[derive(Debug, Clone)]
+pub struct Axes {
+    pub rect: Rect,
+    pub x: (f64, f64),
+    pub y: (f64, f64), // data units, also with log y
+    pub log_y: bool,
+}
+
+// Space around the plot area for the tick labels, axis labels, and title
+pub fn plot_rect(outer: Rect, settings: &FigureSettings, title: bool) -> Rect {
+    let size = settings.font_size;
+    Rect::from_min_max(
+        Pos2::new(
+            outer.min.x + 5.5 * size,
+            outer.min.y + if title { 2.0 } else { 0.8 } * size,
+        ),
+        Pos2::new(outer.max.x - 2.0 * size, outer.max.y - 3.2 * size),
+    )
+}
+
+impl Axes {
+    fn transform_y(&self, y: f64) -> f64 {
+        if self.log_y {
+            y.max(f64::MIN_POSITIVE).log10()
+        } else {
+            y
+        }
+    }
+
+    // Values outside the axes are moved to its edge
+    pub fn to_screen(&self, x: f64, y: f64) -> Pos2 {
+        let (y0, y1) = (self.transform_y(self.y.0), self.transform_y(self.y.1));
+        let fx = ((x - self.x.0) / (self.x.1 - self.x.0)).clamp(0.0, 1.0);
+        let fy = ((self.transform_y(y) - y0) / (y1 - y0)).clamp(0.0, 1.0);
+        Pos2::new(
+            self.rect.min.x + fx as f32 * self.rect.width(),
+            self.rect.max.y - fy as f32 * self.rect.height(),
+        )
+    }
+
+    pub fn frame(
+        &self,
+        settings: &FigureSettings,
+        title: &str,
+        x_label: &str,
+        y_label: &str,
+    ) -> Vec<Item> {
+        let size = settings.font_size;
+        let black = Color32::BLACK;
+        let tick = 0.4 * size;
+        let mut items = Vec::new();
+
+        let r = self.rect;
+        items.push(Item::Line {
+            points: vec![
+                r.left_top(),
+                r.right_top(),
+                r.right_bottom(),
+                r.left_bottom(),
+                r.left_top(),
+            ],
+            color: black,
+            width: 1.0,
+        });
+
+        for (value, label) in ticks(self.x.0, self.x.1, false) {
+            let x = self.to_screen(value, self.y.0).x;
+            items.push(Item::Line {
+                points: vec![Pos2::new(x, r.max.y), Pos2::new(x, r.max.y - tick)],
+                color: black,
+                width: 1.0,
+            });
+            items.push(Item::Text {
+                pos: Pos2::new(x, r.max.y + 1.2 * size),
+                text: label,
+                size,
+                color: black,
+                anchor: Anchor::Middle,
+                vertical: false,
+            });
+        }
+
+        for (value, label) in ticks(self.y.0, self.y.1, self.log_y) {
+            let y = self.to_screen(self.x.0, value).y;
+            items.push(Item::Line {
+                points: vec![Pos2::new(r.min.x, y), Pos2::new(r.min.x + tick, y)],
+                color: black,
+                width: 1.0,
+            });
+            items.push(Item::Text {
+                pos: Pos2::new(r.min.x - 0.3 * size, y + 0.35 * size),
+                text: label,
+                size,
+                color: black,
+                anchor: Anchor::End,
+                vertical: false,
+            });
+        }
+
+        if !x_label.is_empty() {
+            items.push(Item::Text {
+                pos: Pos2::new(r.center().x, r.max.y + 2.6 * size),
+                text: x_label.to_string(),
+                size,
+                color: black,
+                anchor: Anchor::Middle,
+                vertical: false,
+            });
+        }
+        if !y_label.is_empty() {
+            items.push(Item::Text {
+                pos: Pos2::new(r.min.x - 4.6 * size, r.center().y),
+                text: y_label.to_string(),
+                size,
+                color: black,
+                anchor: Anchor::Middle,
+                vertical: true,
+            });
+        }
+        if !title.is_empty() {
+            items.push(Item::Text {
+                pos: Pos2::new(r.center().x, r.min.y - 0.6 * size),
+                text: title.to_string(),
+                size: 1.1 * size,
+                color: black,
+                anchor: Anchor::Middle,
+                vertical: false,
+            });
+        }
+
+        items
+    }
+}
+
+// Tick positions and labels, decades on a log axis
+pub fn ticks(min: f64, max: f64, log: bool) -> Vec<(f64, String)> {
+    if !(min.is_finite() && max.is_finite()) || max <= min {
+        return Vec::new();
+    }
+
+    if log && min > 0.0 {
+        let (low, high) = (min.log10().ceil() as i32, max.log10().floor() as i32);
+        return (low..=high)
+            .map(|power| {
+                let value = 10f64.powi(power);
+                let label = if (-3..=4).contains(&power) {
+                    format!("{}", value)
+                } else {
+                    format!("1e{}", power)
+                };
+                (value, label)
+            })
+            .collect();
+    }
+
+    // 1, 2, or 5 times a power of ten, about six ticks over the range
+    let rough = (max - min) / 6.0;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= rough)
+        .unwrap_or(10.0 * magnitude);
+
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let scientific = max.abs().max(min.abs()) >= 1e5 || step < 1e-4;
+
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last)
+        .map(|index| {
+            let value = index as f64 * step;
+            let label = if value == 0.0 {
+                "0".to_string()
+            } else if scientific {
+                format!("{:.1e}", value)
+            } else {
+                format!("{:.*}", decimals, value)
+            };
+            (value, label)
+        })
+        .collect()
+}
@@ -0,0 +1,414 @@
+use egui::{Color32, Pos2, Rect};
+
+use super::axes::plot_rect;
+use super::scene::{Anchor, Item, Scene};
+use super::settings::{save_dialog, save_scene, FigureSettings};
+use crate::histoer::pane::Pane;
+
+// Text placed in one panel, at a fraction of the plot area from its bottom left corner
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct Annotation {
+    pub panel: usize,
+    pub text: String,
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Default for Annotation {
+    fn default() -> Self {
+        Annotation {
+            panel: 0,
+            text: String::new(),
+            x: 0.6,
+            y: 0.85,
+        }
+    }
+}
+
+// Several histograms in a grid, exported as one figure. Shared axis labels replace the
+// labels of the panels, panel titles follow the title setting of the figure.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct FigureComposer {
+    pub open: bool,
+    pub panels: Vec<String>, // histogram names, row by row
+    pub columns: usize,
+    pub title: String,
+    pub x_label: String, // shared by every panel when not empty
+    pub y_label: String,
+    pub letters: bool, // (a), (b), ... in the corner of every panel
+    pub annotations: Vec<Annotation>,
+    pub settings: FigureSettings,
+    #[serde(skip)]
+    selected: String,
+    #[serde(skip)]
+    preview: Option<egui::TextureHandle>,
+}
+
+impl Default for FigureComposer {
+    fn default() -> Self {
+        FigureComposer {
+            open: false,
+            panels: Vec::new(),
+            columns: 2,
+            title: String::new(),
+            x_label: String::new(),
+            y_label: String::new(),
+            letters: true,
+            annotations: Vec::new(),
+            settings: FigureSettings {
+                width: 900.0,
+                height: 640.0,
+                ..Default::default()
+            },
+            selected: String::new(),
+            preview: None,
+        }
+    }
+}
+
+fn find_pane<'a>(panes: &'a [Pane], name: &str) -> Option<&'a Pane> {
+    panes.iter().find(|pane| match pane {
+        Pane::Histogram(hist) => hist.lock().unwrap().name == name,
+        Pane::Histogram2D(hist) => hist.lock().unwrap().name == name,
+        _ => false,
+    })
+}
+
+fn letter(index: usize) -> String {
+    let mut label = String::new();
+    let mut index = index;
+    loop {
+        label.insert(0, (b'a' + (index % 26) as u8) as char);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    format!("({})", label)
+}
+
+impl FigureComposer {
+    pub fn rows(&self) -> usize {
+        self.panels.len().div_ceil(self.columns.max(1))
+    }
+
+    pub fn compose(&self, panes: &[Pane]) -> Scene {
+        let settings = &self.settings;
+        let size = settings.font_size;
+        let mut scene = Scene::new(
+            settings.width,
+            settings.height,
+            settings.background(),
+            settings.font,
+        );
+
+        // space of the grid inside the overall title and the shared labels
+        let mut grid = Rect::from_min_size(Pos2::ZERO, egui::vec2(settings.width, settings.height));
+        if !self.title.is_empty() {
+            grid.min.y += 2.0 * size;
+            scene.items.push(Item::Text {
+                pos: Pos2::new(grid.center().x, 1.4 * size),
+                text: self.title.clone(),
+                size: 1.25 * size,
+                color: Color32::BLACK,
+                anchor: Anchor::Middle,
+                vertical: false,
+            });
+        }
+        if !self.x_label.is_empty() {
+            scene.items.push(Item::Text {
+                pos: Pos2::new(grid.center().x, grid.max.y - 0.6 * size),
+                text: self.x_label.clone(),
+                size,
+                color: Color32::BLACK,
+                anchor: Anchor::Middle,
+                vertical: false,
+            });
+            grid.max.y -= 1.2 * size;
+        }
+        if !self.y_label.is_empty() {
+            scene.items.push(Item::Text {
+                pos: Pos2::new(grid.min.x + 1.2 * size, grid.center().y),
+                text: self.y_label.clone(),
+                size,
+                color: Color32::BLACK,
+                anchor: Anchor::Middle,
+                vertical: true,
+            });
+            grid.min.x += 1.2 * size;
+        }
+
+        let columns = self.columns.max(1);
+        let rows = self.rows().max(1);
+        let cell_size = egui::vec2(grid.width() / columns as f32, grid.height() / rows as f32);
+
+        for (index, name) in self.panels.iter().enumerate() {
+            let cell = Rect::from_min_size(
+                grid.min
+                    + egui::vec2(
+                        (index % columns) as f32 * cell_size.x,
+                        (index / columns) as f32 * cell_size.y,
+                    ),
+                cell_size,
+            );
+            let rect = plot_rect(cell, settings, settings.title);
+            let title = if settings.title { name.as_str() } else { "" };
+
+            let (axes, items, labels) = match find_pane(panes, name) {
+                Some(Pane::Histogram(hist)) => {
+                    let hist = hist.lock().unwrap();
+                    let axes = hist.figure_axes(rect);
+                    let items = hist.figure_items(&axes, settings);
+                    let labels = &hist.plot_settings.egui_settings;
+                    (
+                        axes,
+                        items,
+                        (labels.x_label.clone(), labels.y_label.clone()),
+                    )
+                }
+                Some(Pane::Histogram2D(hist)) => {
+                    let hist = hist.lock().unwrap();
+                    let axes = hist.figure_axes(rect, settings);
+                    let items = hist.figure_items(&axes, settings);
+                    let labels = &hist.plot_settings.egui_settings;
+                    (
+                        axes,
+                        items,
+                        (labels.x_label.clone(), labels.y_label.clone()),
+                    )
+                }
+                _ => {
+                    log::warn!("Histogram '{}' of the figure was not found", name);
+                    continue;
+                }
+            };
+
+            scene.items.extend(items);
+            let x_label = if self.x_label.is_empty() {
+                &labels.0
+            } else {
+                ""
+            };
+            let y_label = if self.y_label.is_empty() {
+                &labels.1
+            } else {
+                ""
+            };
+            scene
+                .items
+                .extend(axes.frame(settings, title, x_label, y_label));
+
+            if self.letters {
+                scene.items.push(Item::Text {
+                    pos: Pos2::new(rect.min.x + 0.4 * size, rect.min.y + 1.2 * size),
+                    text: letter(index),
+                    size,
+                    color: Color32::BLACK,
+                    anchor: Anchor::Start,
+                    vertical: false,
+                });
+            }
+
+            for annotation in self.annotations.iter().filter(|a| a.panel == index) {
+                scene.items.push(Item::Text {
+                    pos: Pos2::new(
+                        rect.min.x + annotation.x * rect.width(),
+                        rect.max.y - annotation.y * rect.height(),
+                    ),
+                    text: annotation.text.clone(),
+                    size,
+                    color: Color32::BLACK,
+                    anchor: Anchor::Start,
+                    vertical: false,
+                });
+            }
+        }
+
+        scene
+    }
+
+    fn save(&self, panes: &[Pane], extension: &str) {
+        if let Some(path) = save_dialog("figure", extension) {
+            if let Err(e) = save_scene(&self.compose(panes), &self.settings, &path) {
+                log::error!("Error saving the figure: {}", e);
+            }
+        }
+    }
+
+    fn update_preview(&mut self, ctx: &egui::Context, panes: &[Pane]) {
+        // about 500 px wide whatever the size of the figure
+        let scale = 500.0 / self.settings.width.max(1.0);
+        let image = self.compose(panes).rasterize(scale);
+        self.preview = Some(ctx.load_texture("figure_preview", image, Default::default()));
+    }
+
+    fn panels_ui(&mut self, ui: &mut egui::Ui, names: &[String]) {
+        let mut remove = None;
+        let mut swap = None;
+        let count = self.panels.len();
+        for (index, name) in self.panels.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(letter(index));
+                ui.label(name);
+                if ui.add_enabled(index > 0, egui::Button::new("⏶")).clicked() {
+                    swap = Some((index - 1, index));
+                }
+                if ui
+                    .add_enabled(index + 1 < count, egui::Button::new("⏷"))
+                    .clicked()
+                {
+                    swap = Some((index, index + 1));
+                }
+                if ui.button("🗙").clicked() {
+                    remove = Some(index);
+                }
+            });
+        }
+        if let Some((a, b)) = swap {
+            self.panels.swap(a, b);
+        }
+        if let Some(index) = remove {
+            self.panels.remove(index);
+            self.annotations.retain(|a| a.panel != index);
+            for annotation in &mut self.annotations {
+                if annotation.panel > index {
+                    annotation.panel -= 1;
+                }
+            }
+        }
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("figure_composer_add")
+                .selected_text(&self.selected)
+                .width(200.0)
+                .show_ui(ui, |ui| {
+                    for name in names {
+                        ui.selectable_value(&mut self.selected, name.clone(), name);
+                    }
+                });
+            if ui
+                .add_enabled(!self.selected.is_empty(), egui::Button::new("Add Panel"))
+                .clicked()
+            {
+                self.panels.push(self.selected.clone());
+            }
+        });
+    }
+
+    fn annotations_ui(&mut self, ui: &mut egui::Ui) {
+        let last_panel = self.panels.len().saturating_sub(1);
+        let mut remove = None;
+        for (index, annotation) in self.annotations.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt(("figure_annotation_panel", index))
+                    .selected_text(letter(annotation.panel))
+                    .width(50.0)
+                    .show_ui(ui, |ui| {
+                        for panel in 0..=last_panel {
+                            ui.selectable_value(&mut annotation.panel, panel, letter(panel));
+                        }
+                    });
+                ui.add(egui::TextEdit::singleline(&mut annotation.text).desired_width(160.0));
+                ui.add(
+                    egui::DragValue::new(&mut annotation.x)
+                        .range(0.0..=1.0)
+                        .speed(0.01)
+                        .prefix("x: "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut annotation.y)
+                        .range(0.0..=1.0)
+                        .speed(0.01)
+                        .prefix("y: "),
+                );
+                if ui.button("🗙").clicked() {
+                    remove = Some(index);
+                }
+            });
+        }
+        if let Some(index) = remove {
+            self.annotations.remove(index);
+        }
+        if ui
+            .add_enabled(!self.panels.is_empty(), egui::Button::new("Add Annotation"))
+            .on_hover_text("Positions are fractions of the plot area from its bottom left corner")
+            .clicked()
+        {
+            self.annotations.push(Annotation::default());
+        }
+    }
+
+    // `names` are the histograms that can be added, `panes` every pane of the tree
+    pub fn show(&mut self, ctx: &egui::Context, names: &[String], panes: &[Pane]) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Figure Composer")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.heading("Panels");
+                    self.panels_ui(ui, names);
+                    ui.add(
+                        egui::DragValue::new(&mut self.columns)
+                            .range(1..=8)
+                            .prefix("Columns: "),
+                    )
+                    .on_hover_text(format!("{} rows", self.rows()));
+
+                    ui.separator();
+
+                    egui::Grid::new("figure_composer_labels")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Title");
+                            ui.text_edit_singleline(&mut self.title);
+                            ui.end_row();
+                            ui.label("Shared x label");
+                            ui.text_edit_singleline(&mut self.x_label);
+                            ui.end_row();
+                            ui.label("Shared y label");
+                            ui.text_edit_singleline(&mut self.y_label);
+                            ui.end_row();
+                        });
+                    ui.checkbox(&mut self.letters, "Panel letters");
+
+                    ui.collapsing("Annotations", |ui| {
+                        self.annotations_ui(ui);
+                    });
+
+                    ui.collapsing("Style", |ui| {
+                        self.settings.ui(ui);
+                    });
+
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        let enabled = !self.panels.is_empty();
+                        if ui
+                            .add_enabled(enabled, egui::Button::new("Preview"))
+                            .clicked()
+                        {
+                            self.update_preview(ui.ctx(), panes);
+                        }
+                        for (label, extension) in
+                            [("PNG…", "png"), ("SVG…", "svg"), ("PDF…", "pdf")]
+                        {
+                            if ui.add_enabled(enabled, egui::Button::new(label)).clicked() {
+                                self.save(panes, extension);
+                            }
+                        }
+                    });
+
+                    if let Some(texture) = &self.preview {
+                        ui.add(egui::Image::new(texture).max_width(ui.available_width()));
+                    }
+                });
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,7 @@
+pub mod axes;
+pub mod composer;
+pub mod pdf;
+pub mod plot;
+pub mod raster;
+pub mod scene;
+pub mod settings;
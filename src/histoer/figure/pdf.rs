@@ -0,0 +1,308 @@
+use egui::{Color32, ColorImage};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use super::scene::{Anchor, FontFamily, Item, Scene};
+
+// Single page PDF writer for figures. Text uses the standard Helvetica and Courier fonts
+// so nothing has to be embedded, PDF coordinates have y up so every y is flipped.
+
+// Helvetica advance widths of the printable ASCII characters, per 1000 units of font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, // ' '../
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, // 0..?
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, // @..O
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, // P.._
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, // `..o
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // p..~
+];
+
+// WinAnsi byte of a character, '?' when the standard fonts do not have it
+fn win_ansi(c: char) -> u8 {
+    match c {
+        ' '..='~' => c as u8,
+        '\u{a0}'..='\u{ff}' => c as u32 as u8,
+        '…' => 0x85,
+        '–' => 0x96,
+        '—' => 0x97,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        _ => b'?',
+    }
+}
+
+fn text_width(text: &[u8], font: FontFamily, size: f32) -> f32 {
+    let units: u32 = text
+        .iter()
+        .map(|&byte| match font {
+            FontFamily::Monospace => 600,
+            FontFamily::Sans => match byte {
+                32..=126 => HELVETICA_WIDTHS[(byte - 32) as usize] as u32,
+                _ => 556,
+            },
+        })
+        .sum();
+    units as f32 * size / 1000.0
+}
+
+fn pdf_string(text: &[u8]) -> Vec<u8> {
+    let mut string = vec![b'('];
+    for &byte in text {
+        if matches!(byte, b'(' | b')' | b'\\') {
+            string.push(b'\\');
+        }
+        string.push(byte);
+    }
+    string.push(b')');
+    string
+}
+
+fn rgb(color: Color32) -> String {
+    let [r, g, b, _] = color.to_srgba_unmultiplied();
+    format!(
+        "{:.3} {:.3} {:.3}",
+        r as f32 / 255.0,
+        g as f32 / 255.0,
+        b as f32 / 255.0
+    )
+}
+
+fn alpha(color: Color32) -> u8 {
+    color.to_srgba_unmultiplied()[3]
+}
+
+// Image XObject, with a soft mask when some pixels are not opaque
+fn image_objects(image: &ColorImage, first_id: usize) -> Vec<Vec<u8>> {
+    let [width, height] = image.size;
+    let pixels: Vec<[u8; 4]> = image
+        .pixels
+        .iter()
+        .map(|pixel| pixel.to_srgba_unmultiplied())
+        .collect();
+    let opaque = pixels.iter().all(|pixel| pixel[3] == 255);
+
+    let stream = |dictionary: String, data: Vec<u8>| {
+        let mut object =
+            format!("<< {} /Length {} >>\nstream\n", dictionary, data.len()).into_bytes();
+        object.extend(data);
+        object.extend(b"\nendstream");
+        object
+    };
+
+    let smask = if opaque {
+        String::new()
+    } else {
+        format!(" /SMask {} 0 R", first_id + 1)
+    };
+    let mut objects = vec![stream(
+        format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8{}",
+            width, height, smask
+        ),
+        pixels.iter().flat_map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect(),
+    )];
+    if !opaque {
+        objects.push(stream(
+            format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray /BitsPerComponent 8",
+                width, height
+            ),
+            pixels.iter().map(|pixel| pixel[3]).collect(),
+        ));
+    }
+    objects
+}
+
+impl Scene {
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let h = self.height;
+        let font = match self.font {
+            FontFamily::Sans => "Helvetica",
+            FontFamily::Monospace => "Courier",
+        };
+
+        // objects 1 to 5 are fixed, images and transparency states follow
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+        let mut images = Vec::new();
+        let mut alphas = BTreeSet::new();
+        let mut content: Vec<u8> = Vec::new();
+
+        if let Some(background) = self.background {
+            let _ = writeln!(
+                Bytes(&mut content),
+                "{} rg 0 0 {:.2} {:.2} re f",
+                rgb(background),
+                self.width,
+                h
+            );
+        }
+
+        for item in &self.items {
+            let mut out = Bytes(&mut content);
+            let _ = writeln!(out, "q");
+            match item {
+                Item::Line {
+                    points,
+                    color,
+                    width,
+                } => {
+                    if points.len() >= 2 {
+                        if alpha(*color) < 255 {
+                            alphas.insert(alpha(*color));
+                            let _ = writeln!(out, "/GS{} gs", alpha(*color));
+                        }
+                        let _ = writeln!(out, "{} RG {:.2} w 1 J 1 j", rgb(*color), width);
+                        for (index, point) in points.iter().enumerate() {
+                            let operator = if index == 0 { "m" } else { "l" };
+                            let _ = writeln!(out, "{:.2} {:.2} {}", point.x, h - point.y, operator);
+                        }
+                        let _ = writeln!(out, "S");
+                    }
+                }
+                Item::Rect { rect, fill } => {
+                    if alpha(*fill) < 255 {
+                        alphas.insert(alpha(*fill));
+                        let _ = writeln!(out, "/GS{} gs", alpha(*fill));
+                    }
+                    let _ = writeln!(
+                        out,
+                        "{} rg {:.2} {:.2} {:.2} {:.2} re f",
+                        rgb(*fill),
+                        rect.min.x,
+                        h - rect.max.y,
+                        rect.width(),
+                        rect.height()
+                    );
+                }
+                Item::Image { rect, image } => {
+                    let _ = writeln!(
+                        out,
+                        "{:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{} Do",
+                        rect.width(),
+                        rect.height(),
+                        rect.min.x,
+                        h - rect.max.y,
+                        images.len()
+                    );
+                    images.push(image);
+                }
+                Item::Text {
+                    pos,
+                    text,
+                    size,
+                    color,
+                    anchor,
+                    vertical,
+                } => {
+                    let bytes: Vec<u8> = text.chars().map(win_ansi).collect();
+                    let shift = match anchor {
+                        Anchor::Start => 0.0,
+                        Anchor::Middle => 0.5 * text_width(&bytes, self.font, *size),
+                        Anchor::End => text_width(&bytes, self.font, *size),
+                    };
+                    let (x, y) = (pos.x, h - pos.y);
+                    let matrix = if *vertical {
+                        format!("0 1 -1 0 {:.2} {:.2}", x, y - shift)
+                    } else {
+                        format!("1 0 0 1 {:.2} {:.2}", x - shift, y)
+                    };
+                    if alpha(*color) < 255 {
+                        alphas.insert(alpha(*color));
+                        let _ = writeln!(out, "/GS{} gs", alpha(*color));
+                    }
+                    let _ = write!(out, "BT /F1 {} Tf {} rg {} Tm ", size, rgb(*color), matrix);
+                    out.0.extend(pdf_string(&bytes));
+                    out.0.extend(b" Tj ET\n");
+                }
+            }
+            content.extend(b"Q\n");
+        }
+
+        let mut resources = String::from("/Font << /F1 5 0 R >>");
+        let mut next_id = 6;
+        let mut image_objects_list = Vec::new();
+        if !images.is_empty() {
+            resources.push_str(" /XObject <<");
+            for (index, image) in images.iter().enumerate() {
+                let _ = write!(resources, " /Im{} {} 0 R", index, next_id);
+                let objects = image_objects(image, next_id);
+                next_id += objects.len();
+                image_objects_list.extend(objects);
+            }
+            resources.push_str(" >>");
+        }
+        let mut state_objects = Vec::new();
+        if !alphas.is_empty() {
+            resources.push_str(" /ExtGState <<");
+            for alpha in &alphas {
+                let _ = write!(resources, " /GS{} {} 0 R", alpha, next_id);
+                let value = *alpha as f32 / 255.0;
+                state_objects.push(
+                    format!("<< /Type /ExtGState /CA {:.3} /ca {:.3} >>", value, value)
+                        .into_bytes(),
+                );
+                next_id += 1;
+            }
+            resources.push_str(" >>");
+        }
+
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        objects.push(b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec());
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << {} >> /Contents 4 0 R >>",
+                self.width, h, resources
+            )
+            .into_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend(b"endstream");
+        objects.push(stream);
+        objects.push(
+            format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                font
+            )
+            .into_bytes(),
+        );
+        objects.extend(image_objects_list);
+        objects.extend(state_objects);
+
+        let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend(format!("{} 0 obj\n", index + 1).into_bytes());
+            pdf.extend(object);
+            pdf.extend(b"\nendobj\n");
+        }
+
+        let xref = pdf.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(trailer, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            trailer,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        );
+        pdf.extend(trailer.into_bytes());
+        pdf
+    }
+}
+
+// Lets the content stream be written with `write!`
+struct Bytes<'a>(&'a mut Vec<u8>);
+
+impl Write for Bytes<'_> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
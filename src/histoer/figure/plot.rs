@@ -0,0 +1,191 @@
+use egui::{Pos2, Rect};
+use std::sync::Arc;
+
+use super::axes::{plot_rect, Axes};
+use super::scene::{Item, Scene};
+use super::settings::{save_dialog, save_scene, FigureSettings};
+use crate::egui_plot_stuff::egui_line::EguiLine;
+use crate::histoer::histo1d::histogram1d::Histogram;
+use crate::histoer::histo2d::color_bar::FIGURE_COLOR_BAR_MARGIN;
+use crate::histoer::histo2d::histogram2d::Histogram2D;
+
+// Figures of histogram panes: the histogram in the locked bounds of the pane (or its full
+// range), the stored fits of 1D histograms, and the cuts and color bar of 2D histograms.
+
+fn line_item(axes: &Axes, line: &EguiLine, width: f32) -> Option<Item> {
+    if !line.draw || line.points.len() < 2 {
+        return None;
+    }
+    Some(Item::Line {
+        points: line
+            .points
+            .iter()
+            .map(|point| axes.to_screen(point[0], point[1]))
+            .collect(),
+        color: line.color,
+        width,
+    })
+}
+
+impl Histogram {
+    pub fn figure_axes(&self, rect: Rect) -> Axes {
+        let settings = &self.plot_settings.egui_settings;
+        let log_y = settings.log_y;
+
+        let (x, y) = if settings.lock_bounds {
+            (settings.x_bounds, settings.y_bounds)
+        } else {
+            let contents: Vec<f64> = (0..self.bins.len())
+                .map(|index| self.bin_content(index))
+                .collect();
+            let max = contents.iter().copied().fold(0.0, f64::max).max(1.0);
+            let y = if log_y {
+                let min = contents
+                    .iter()
+                    .copied()
+                    .filter(|&content| content > 0.0)
+                    .fold(f64::INFINITY, f64::min);
+                (if min.is_finite() { 0.5 * min } else { 0.5 }, 2.0 * max)
+            } else {
+                (0.0, 1.1 * max)
+            };
+            (self.range, y)
+        };
+
+        Axes { rect, x, y, log_y }
+    }
+
+    pub fn figure_items(&self, axes: &Axes, settings: &FigureSettings) -> Vec<Item> {
+        // step line from the bottom of the first bin to the bottom of the last
+        let mut points = vec![axes.to_screen(self.bin_edge(0), axes.y.0)];
+        for index in 0..self.bins.len() {
+            let content = self.bin_content(index);
+            points.push(axes.to_screen(self.bin_edge(index), content));
+            points.push(axes.to_screen(self.bin_edge(index + 1), content));
+        }
+        points.push(axes.to_screen(self.bin_edge(self.bins.len()), axes.y.0));
+
+        let mut items = vec![Item::Line {
+            points,
+            color: self.line.color,
+            width: settings.line_width,
+        }];
+
+        let fits = self.fits.temp_fit.iter().chain(&self.fits.stored_fits);
+        for fit in fits {
+            items.extend(line_item(axes, &fit.background_line, settings.line_width));
+            items.extend(line_item(axes, &fit.composition_line, settings.line_width));
+        }
+        items
+    }
+
+    pub fn figure(&self, settings: &FigureSettings) -> Scene {
+        let mut scene = Scene::new(
+            settings.width,
+            settings.height,
+            settings.background(),
+            settings.font,
+        );
+        let outer = Rect::from_min_size(Pos2::ZERO, egui::vec2(settings.width, settings.height));
+        let axes = self.figure_axes(plot_rect(outer, settings, settings.title));
+
+        scene.items.extend(self.figure_items(&axes, settings));
+        let labels = &self.plot_settings.egui_settings;
+        let title = if settings.title {
+            self.name.as_str()
+        } else {
+            ""
+        };
+        scene
+            .items
+            .extend(axes.frame(settings, title, &labels.x_label, &labels.y_label));
+        scene
+    }
+
+    pub fn save_figure(&self, extension: &str) {
+        let settings = &self.plot_settings.figure;
+        if let Some(path) = save_dialog(&self.name, extension) {
+            if let Err(e) = save_scene(&self.figure(settings), settings, &path) {
+                log::error!("Error saving '{}' as an image: {}", self.name, e);
+            }
+        }
+    }
+}
+
+impl Histogram2D {
+    // Narrower than `rect` when the color bar has to fit to its right
+    pub fn figure_axes(&self, rect: Rect, settings: &FigureSettings) -> Axes {
+        let mut rect = rect;
+        if self.plot_settings.color_bar {
+            rect.max.x -= FIGURE_COLOR_BAR_MARGIN * settings.font_size;
+        }
+        Axes {
+            rect,
+            x: (self.range.x.min, self.range.x.max),
+            y: (self.range.y.min, self.range.y.max),
+            log_y: false,
+        }
+    }
+
+    pub fn figure_items(&self, axes: &Axes, settings: &FigureSettings) -> Vec<Item> {
+        let mut items = vec![Item::Image {
+            rect: axes.rect,
+            image: Arc::new(self.data_2_image()),
+        }];
+
+        for cut in &self.plot_settings.cuts {
+            let cut = cut.lock().unwrap();
+            let polygon = &cut.polygon;
+            if !polygon.draw {
+                continue;
+            }
+            for vertices in polygon.polygons().filter(|vertices| vertices.len() >= 2) {
+                let mut points: Vec<Pos2> = vertices
+                    .iter()
+                    .map(|vertex| axes.to_screen(vertex[0], vertex[1]))
+                    .collect();
+                points.push(points[0]);
+                items.push(Item::Line {
+                    points,
+                    color: polygon.stroke.color,
+                    width: settings.line_width,
+                });
+            }
+        }
+
+        items.extend(self.color_bar_items(axes.rect, settings));
+        items
+    }
+
+    pub fn figure(&self, settings: &FigureSettings) -> Scene {
+        let mut scene = Scene::new(
+            settings.width,
+            settings.height,
+            settings.background(),
+            settings.font,
+        );
+        let outer = Rect::from_min_size(Pos2::ZERO, egui::vec2(settings.width, settings.height));
+        let axes = self.figure_axes(plot_rect(outer, settings, settings.title), settings);
+
+        scene.items.extend(self.figure_items(&axes, settings));
+        let labels = &self.plot_settings.egui_settings;
+        let title = if settings.title {
+            self.name.as_str()
+        } else {
+            ""
+        };
+        scene
+            .items
+            .extend(axes.frame(settings, title, &labels.x_label, &labels.y_label));
+        scene
+    }
+
+    pub fn save_figure(&self, extension: &str) {
+        let settings = &self.plot_settings.figure;
+        if let Some(path) = save_dialog(&self.name, extension) {
+            if let Err(e) = save_scene(&self.figure(settings), settings, &path) {
+                log::error!("Error saving '{}' as an image: {}", self.name, e);
+            }
+        }
+    }
+}
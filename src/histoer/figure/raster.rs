@@ -0,0 +1,263 @@
+use ab_glyph::{Font, FontArc, ScaleFont};
+use ab_glyph_rasterizer::{point, Rasterizer};
+use egui::{Color32, ColorImage, Pos2};
+
+use super::scene::{Anchor, FontFamily, Item, Scene};
+
+// Software rasterizer for PNG export, so the resolution does not depend on the screen.
+// Shapes are filled with antialiased coverage and text uses the fonts built into egui.
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 4]>, // premultiplied rgba in 0..=1
+}
+
+impl Canvas {
+    fn blend(&mut self, x: i64, y: i64, color: Color32, coverage: f32) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let coverage = coverage.clamp(0.0, 1.0);
+        if coverage == 0.0 {
+            return;
+        }
+        let [r, g, b, a] = color.to_array().map(|c| c as f32 / 255.0 * coverage);
+        let pixel = &mut self.pixels[y as usize * self.width + x as usize];
+        for (channel, source) in pixel.iter_mut().zip([r, g, b, a]) {
+            *channel = source + *channel * (1.0 - a);
+        }
+    }
+
+    // Nonzero fill of a closed polygon in pixel coordinates
+    fn fill_polygon(&mut self, polygon: &[Pos2], color: Color32) {
+        if polygon.len() < 3 {
+            return;
+        }
+        let min_x = polygon
+            .iter()
+            .map(|p| p.x)
+            .fold(f32::INFINITY, f32::min)
+            .floor();
+        let min_y = polygon
+            .iter()
+            .map(|p| p.y)
+            .fold(f32::INFINITY, f32::min)
+            .floor();
+        let max_x = polygon
+            .iter()
+            .map(|p| p.x)
+            .fold(f32::NEG_INFINITY, f32::max)
+            .ceil();
+        let max_y = polygon
+            .iter()
+            .map(|p| p.y)
+            .fold(f32::NEG_INFINITY, f32::max)
+            .ceil();
+
+        // nothing to draw off the canvas
+        let (min_x, min_y) = (min_x.max(-1.0), min_y.max(-1.0));
+        let (max_x, max_y) = (
+            max_x.min(self.width as f32 + 1.0),
+            max_y.min(self.height as f32 + 1.0),
+        );
+        if max_x <= min_x || max_y <= min_y {
+            return;
+        }
+
+        let (width, height) = ((max_x - min_x) as usize, (max_y - min_y) as usize);
+        let mut rasterizer = Rasterizer::new(width, height);
+        let local = |p: Pos2| {
+            point(
+                (p.x - min_x).clamp(0.0, width as f32),
+                (p.y - min_y).clamp(0.0, height as f32),
+            )
+        };
+        for (index, &p0) in polygon.iter().enumerate() {
+            let p1 = polygon[(index + 1) % polygon.len()];
+            rasterizer.draw_line(local(p0), local(p1));
+        }
+        rasterizer.for_each_pixel_2d(|x, y, coverage| {
+            self.blend(
+                min_x as i64 + x as i64,
+                min_y as i64 + y as i64,
+                color,
+                coverage.min(1.0),
+            );
+        });
+    }
+
+    // Every segment as a rectangle extended by half the width, so corners are filled
+    fn stroke(&mut self, points: &[Pos2], color: Color32, width: f32) {
+        let half = 0.5 * width.max(0.5);
+        for pair in points.windows(2) {
+            let direction = (pair[1] - pair[0]).normalized();
+            if !direction.x.is_finite() || !direction.y.is_finite() {
+                continue;
+            }
+            let normal = direction.rot90() * half;
+            let (start, end) = (pair[0] - direction * half, pair[1] + direction * half);
+            self.fill_polygon(
+                &[start + normal, end + normal, end - normal, start - normal],
+                color,
+            );
+        }
+    }
+
+    fn image(&mut self, rect: egui::Rect, image: &ColorImage) {
+        let [columns, rows] = image.size;
+        if columns == 0 || rows == 0 {
+            return;
+        }
+        let (x0, x1) = (rect.min.x.round() as i64, rect.max.x.round() as i64);
+        let (y0, y1) = (rect.min.y.round() as i64, rect.max.y.round() as i64);
+        for y in y0.max(0)..y1.min(self.height as i64) {
+            let row = ((y - y0) as f32 / (y1 - y0) as f32 * rows as f32) as usize;
+            for x in x0.max(0)..x1.min(self.width as i64) {
+                let column = ((x - x0) as f32 / (x1 - x0) as f32 * columns as f32) as usize;
+                let color = image.pixels[row.min(rows - 1) * columns + column.min(columns - 1)];
+                self.blend(x, y, color, 1.0);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn text(
+        &mut self,
+        font: &FontArc,
+        text: &str,
+        pos: Pos2,
+        size: f32,
+        color: Color32,
+        anchor: Anchor,
+        vertical: bool,
+    ) {
+        let Some(scale) = font.pt_to_px_scale(size) else {
+            return;
+        };
+        let font = font.as_scaled(scale);
+
+        // coverage of the text laid out from the origin of its baseline
+        let mut glyphs = Vec::new();
+        let mut caret = 0.0;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += font.kern(previous, id);
+            }
+            glyphs.push(id.with_scale_and_position(scale, point(caret, 0.0)));
+            caret += font.h_advance(id);
+            previous = Some(id);
+        }
+
+        let shift = match anchor {
+            Anchor::Start => 0.0,
+            Anchor::Middle => 0.5 * caret,
+            Anchor::End => caret,
+        };
+
+        for glyph in glyphs {
+            let Some(outlined) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|x, y, coverage| {
+                let dx = bounds.min.x + x as f32 - shift;
+                let dy = bounds.min.y + y as f32;
+                let (px, py) = if vertical {
+                    (pos.x + dy, pos.y - dx)
+                } else {
+                    (pos.x + dx, pos.y + dy)
+                };
+                self.blend(px.round() as i64, py.round() as i64, color, coverage);
+            });
+        }
+    }
+}
+
+fn font(family: FontFamily) -> Option<FontArc> {
+    let name = match family {
+        FontFamily::Sans => "Ubuntu-Light",
+        FontFamily::Monospace => "Hack",
+    };
+    let definitions = egui::FontDefinitions::default();
+    let data = definitions.font_data.get(name)?;
+    FontArc::try_from_vec(data.font.to_vec()).ok()
+}
+
+impl Scene {
+    // Pixels per point sets the resolution, e.g. 3 for a 300 dpi print of a 100 dpi layout
+    pub fn rasterize(&self, pixels_per_point: f32) -> ColorImage {
+        let scale = pixels_per_point.max(0.1);
+        let width = (self.width * scale).round().max(1.0) as usize;
+        let height = (self.height * scale).round().max(1.0) as usize;
+
+        let background = self
+            .background
+            .map_or([0.0; 4], |color| color.to_array().map(|c| c as f32 / 255.0));
+        let mut canvas = Canvas {
+            width,
+            height,
+            pixels: vec![background; width * height],
+        };
+        let font = font(self.font);
+        let to_pixels = |p: Pos2| Pos2::new(p.x * scale, p.y * scale);
+
+        for item in &self.items {
+            match item {
+                Item::Line {
+                    points,
+                    color,
+                    width,
+                } => {
+                    let points: Vec<Pos2> = points.iter().copied().map(to_pixels).collect();
+                    canvas.stroke(&points, *color, width * scale);
+                }
+                Item::Rect { rect, fill } => {
+                    let (min, max) = (to_pixels(rect.min), to_pixels(rect.max));
+                    canvas.fill_polygon(
+                        &[min, Pos2::new(max.x, min.y), max, Pos2::new(min.x, max.y)],
+                        *fill,
+                    );
+                }
+                Item::Image { rect, image } => {
+                    let rect = egui::Rect::from_min_max(to_pixels(rect.min), to_pixels(rect.max));
+                    canvas.image(rect, image);
+                }
+                Item::Text {
+                    pos,
+                    text,
+                    size,
+                    color,
+                    anchor,
+                    vertical,
+                } => {
+                    if let Some(font) = &font {
+                        canvas.text(
+                            font,
+                            text,
+                            to_pixels(*pos),
+                            size * scale,
+                            *color,
+                            *anchor,
+                            *vertical,
+                        );
+                    }
+                }
+            }
+        }
+
+        ColorImage {
+            size: [width, height],
+            pixels: canvas
+                .pixels
+                .iter()
+                .map(|pixel| {
+                    let [r, g, b, a] = pixel.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+                    Color32::from_rgba_premultiplied(r, g, b, a)
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use egui::{Color32, ColorImage, Pos2, Rect};
+use image::ImageEncoder;
+use std::fmt::Write;
+use std::sync::Arc;
+
+// Vector description of a figure in points (y down), written as SVG or rasterized to PNG.
+// Plots are built from these few primitives so every output looks the same.
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum FontFamily {
+    #[default]
+    Sans,
+    Monospace,
+}
+
+impl FontFamily {
+    pub fn svg_name(&self) -> &'static str {
+        match self {
+            FontFamily::Sans => "Ubuntu, Helvetica, Arial, sans-serif",
+            FontFamily::Monospace => "Hack, Menlo, Consolas, monospace",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    Start,
+    Middle,
+    End,
+}
+
+#[derive(Debug, Clone)]
+pub enum Item {
+    Line {
+        points: Vec<Pos2>,
+        color: Color32,
+        width: f32,
+    },
+    Rect {
+        rect: Rect,
+        fill: Color32,
+    },
+    // Drawn with sharp pixels, the first row at the top
+    Image {
+        rect: Rect,
+        image: Arc<ColorImage>,
+    },
+    // Baseline at `pos`, rotated a quarter turn counterclockwise when vertical
+    Text {
+        pos: Pos2,
+        text: String,
+        size: f32,
+        color: Color32,
+        anchor: Anchor,
+        vertical: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Scene {
+    pub width: f32,
+    pub height: f32,
+    pub background: Option<Color32>, // transparent when None
+    pub font: FontFamily,
+    pub items: Vec<Item>,
+}
+
+impl Scene {
+    pub fn new(width: f32, height: f32, background: Option<Color32>, font: FontFamily) -> Self {
+        Scene {
+            width,
+            height,
+            background,
+            font,
+            items: Vec::new(),
+        }
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="{font}">"#,
+            w = self.width,
+            h = self.height,
+            font = self.font.svg_name()
+        );
+        if let Some(background) = self.background {
+            let _ = writeln!(
+                svg,
+                r#"<rect width="100%" height="100%" {}/>"#,
+                svg_fill(background)
+            );
+        }
+
+        for item in &self.items {
+            match item {
+                Item::Line {
+                    points,
+                    color,
+                    width,
+                } => {
+                    if points.len() < 2 {
+                        continue;
+                    }
+                    let mut path = String::new();
+                    for (index, point) in points.iter().enumerate() {
+                        let command = if index == 0 { 'M' } else { 'L' };
+                        let _ = write!(path, "{}{:.2} {:.2} ", command, point.x, point.y);
+                    }
+                    let _ = writeln!(
+                        svg,
+                        r#"<path d="{}" fill="none" {} stroke-width="{}" stroke-linejoin="round"/>"#,
+                        path.trim_end(),
+                        svg_stroke(*color),
+                        width
+                    );
+                }
+                Item::Rect { rect, fill } => {
+                    let _ = writeln!(
+                        svg,
+                        r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" {}/>"#,
+                        rect.min.x,
+                        rect.min.y,
+                        rect.width(),
+                        rect.height(),
+                        svg_fill(*fill)
+                    );
+                }
+                Item::Image { rect, image } => {
+                    let Ok(png) = encode_png(image) else {
+                        log::error!("Error encoding an image of the figure");
+                        continue;
+                    };
+                    let _ = writeln!(
+                        svg,
+                        r#"<image x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" preserveAspectRatio="none" style="image-rendering:pixelated" href="data:image/png;base64,{}"/>"#,
+                        rect.min.x,
+                        rect.min.y,
+                        rect.width(),
+                        rect.height(),
+                        base64(&png)
+                    );
+                }
+                Item::Text {
+                    pos,
+                    text,
+                    size,
+                    color,
+                    anchor,
+                    vertical,
+                } => {
+                    let anchor = match anchor {
+                        Anchor::Start => "start",
+                        Anchor::Middle => "middle",
+                        Anchor::End => "end",
+                    };
+                    let rotate = if *vertical {
+                        format!(r#" transform="rotate(-90 {:.2} {:.2})""#, pos.x, pos.y)
+                    } else {
+                        String::new()
+                    };
+                    let _ = writeln!(
+                        svg,
+                        r#"<text x="{:.2}" y="{:.2}" font-size="{}" text-anchor="{}" {}{}>{}</text>"#,
+                        pos.x,
+                        pos.y,
+                        size,
+                        anchor,
+                        svg_fill(*color),
+                        rotate,
+                        escape(text)
+                    );
+                }
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+// Straight alpha, egui colors are premultiplied
+fn svg_color(color: Color32) -> (String, f32) {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    (format!("#{:02x}{:02x}{:02x}", r, g, b), a as f32 / 255.0)
+}
+
+fn svg_fill(color: Color32) -> String {
+    let (rgb, alpha) = svg_color(color);
+    format!(r#"fill="{}" fill-opacity="{:.3}""#, rgb, alpha)
+}
+
+fn svg_stroke(color: Color32) -> String {
+    let (rgb, alpha) = svg_color(color);
+    format!(r#"stroke="{}" stroke-opacity="{:.3}""#, rgb, alpha)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn encode_png(image: &ColorImage) -> Result<Vec<u8>, image::ImageError> {
+    let bytes: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_srgba_unmultiplied())
+        .collect();
+    let mut png = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut png).write_image(
+        &bytes,
+        image.size[0] as u32,
+        image.size[1] as u32,
+        image::ExtendedColorType::Rgba8,
+    )?;
+    Ok(png)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for (index, shift) in [18, 12, 6, 0].into_iter().enumerate() {
+            if index <= chunk.len() {
+                text.push(TABLE[(n >> shift & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use super::scene::{encode_png, FontFamily, Scene};
+
+// Size and style of an exported figure. Sizes are in points, the PNG resolution is set
+// separately so the layout does not change with it.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct FigureSettings {
+    pub width: f32,
+    pub height: f32,
+    pub font_size: f32,
+    pub font: FontFamily,
+    pub line_width: f32,
+    pub transparent: bool,
+    pub title: bool,
+    pub pixels_per_point: f32, // PNG resolution
+}
+
+impl Default for FigureSettings {
+    fn default() -> Self {
+        FigureSettings {
+            width: 640.0,
+            height: 420.0,
+            font_size: 14.0,
+            font: FontFamily::Sans,
+            line_width: 1.5,
+            transparent: false,
+            title: true,
+            pixels_per_point: 3.0,
+        }
+    }
+}
+
+impl FigureSettings {
+    pub fn background(&self) -> Option<egui::Color32> {
+        (!self.transparent).then_some(egui::Color32::WHITE)
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.width)
+                    .range(100.0..=4000.0)
+                    .prefix("Width: ")
+                    .suffix(" pt"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.height)
+                    .range(100.0..=4000.0)
+                    .prefix("Height: ")
+                    .suffix(" pt"),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.font_size)
+                    .range(4.0..=72.0)
+                    .speed(0.5)
+                    .prefix("Font: ")
+                    .suffix(" pt"),
+            );
+            ui.radio_value(&mut self.font, FontFamily::Sans, "Sans");
+            ui.radio_value(&mut self.font, FontFamily::Monospace, "Mono");
+        });
+
+        ui.add(
+            egui::DragValue::new(&mut self.line_width)
+                .range(0.25..=10.0)
+                .speed(0.1)
+                .prefix("Line width: "),
+        );
+
+        ui.add(
+            egui::DragValue::new(&mut self.pixels_per_point)
+                .range(0.5..=10.0)
+                .speed(0.1)
+                .prefix("PNG scale: ")
+                .suffix("x"),
+        )
+        .on_hover_text(format!(
+            "Pixels per point, {} x {} px",
+            (self.width * self.pixels_per_point).round(),
+            (self.height * self.pixels_per_point).round()
+        ));
+
+        ui.checkbox(&mut self.title, "Title");
+        ui.checkbox(&mut self.transparent, "Transparent background");
+    }
+
+    // Returns the extension of the format to save, when one was clicked
+    pub fn menu_button(&mut self, ui: &mut egui::Ui) -> Option<&'static str> {
+        let mut save = None;
+        ui.menu_button("Save Image", |ui| {
+            self.ui(ui);
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("PNG…").clicked() {
+                    save = Some("png");
+                }
+                if ui.button("SVG…").clicked() {
+                    save = Some("svg");
+                }
+                if ui.button("PDF…").clicked() {
+                    save = Some("pdf");
+                }
+            });
+            if save.is_some() {
+                ui.close_menu();
+            }
+        });
+        save
+    }
+}
+
+// Writes the scene as PNG, SVG, or PDF, by the extension of the path
+pub fn save_scene(scene: &Scene, settings: &FigureSettings, path: &Path) -> Result<(), String> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let bytes = match extension.as_str() {
+        "svg" => scene.to_svg().into_bytes(),
+        "pdf" => scene.to_pdf(),
+        "png" => {
+            encode_png(&scene.rasterize(settings.pixels_per_point)).map_err(|e| e.to_string())?
+        }
+        other => return Err(format!("Unknown image format '.{}'", other)),
+    };
+    std::fs::write(path, bytes).map_err(|e| e.to_string())?;
+
+    log::info!("Saved figure to {}", path.display());
+    Ok(())
+}
+
+// Save dialog for a figure, `name` is the histogram name
+pub fn save_dialog(name: &str, extension: &str) -> Option<std::path::PathBuf> {
+    let filter = extension.to_uppercase();
+    rfd::FileDialog::new()
+        .set_file_name(format!("{}.{}", name.replace('/', "_"), extension))
+        .add_filter(filter, &[extension])
+        .save_file()
+}
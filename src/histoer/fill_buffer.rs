@@ -0,0 +1,305 @@
+use fnv::FnvHashMap;
+
+use super::histo1d::histogram1d::Histogram;
+use super::histo2d::calibration::AxisCalibration;
+use super::histo2d::histogram2d::Histogram2D;
+use super::histo3d::histogram3d::Histogram3D;
+use super::histond::histogramnd::HistogramND;
+
+// Counts of a slice of rows, filled by one thread without locking the histogram. The buffers of
+// all slices are merged and added to the histogram once the chunk is done. The bins are found
+// the same way as in the fill of each histogram, so the counts are the same as filling it row by
+// row.
+
+#[derive(Clone)]
+pub struct Buffer1D {
+    range: (f64, f64),
+    edges: Vec<f64>,
+    counts: Vec<u64>, // original bins
+    overflow: u64,
+    underflow: u64,
+    auto_range: bool,
+    outside: Vec<f64>, // values outside of the range, filled at the end so the range can grow
+}
+
+impl Buffer1D {
+    pub fn new(hist: &Histogram) -> Self {
+        Buffer1D {
+            range: hist.range,
+            edges: hist.edges.clone(),
+            counts: vec![0; hist.original_bins.len()],
+            overflow: 0,
+            underflow: 0,
+            auto_range: hist.plot_settings.auto_range.enabled,
+            outside: Vec::new(),
+        }
+    }
+
+    pub fn fill(&mut self, value: f64) {
+        if value >= self.range.0 && value < self.range.1 {
+            let index = if self.edges.is_empty() {
+                let width = (self.range.1 - self.range.0) / self.counts.len() as f64;
+                ((value - self.range.0) / width) as usize
+            } else {
+                self.edges.partition_point(|&edge| edge <= value) - 1
+            };
+            if let Some(count) = self.counts.get_mut(index) {
+                *count += 1;
+            }
+        } else if self.auto_range {
+            self.outside.push(value);
+        } else if value >= self.range.1 {
+            self.overflow += 1;
+        } else {
+            self.underflow += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: Buffer1D) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+        self.overflow += other.overflow;
+        self.underflow += other.underflow;
+        self.outside.extend(other.outside);
+    }
+}
+
+impl Histogram {
+    pub fn add_buffer(&mut self, buffer: Buffer1D) {
+        let factor = self.plot_settings.rebin_factor.max(1);
+        for (index, count) in buffer.counts.into_iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            if let Some(bin) = self.original_bins.get_mut(index) {
+                *bin += count;
+            }
+            if let Some(bin) = self.bins.get_mut(index / factor) {
+                *bin += count;
+            }
+        }
+        self.overflow += buffer.overflow;
+        self.underflow += buffer.underflow;
+
+        // after the counts, since extending the range merges the bins
+        for value in buffer.outside {
+            self.fill(value);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Buffer2D {
+    swapped: bool,
+    calibration: (AxisCalibration, AxisCalibration),
+    range: ((f64, f64), (f64, f64)),
+    width: (f64, f64),
+    counts: FnvHashMap<(usize, usize), u64>,
+    overflow: (u64, u64),
+    underflow: (u64, u64),
+}
+
+impl Buffer2D {
+    pub fn new(hist: &Histogram2D) -> Self {
+        let calibration = &hist.plot_settings.calibration;
+        Buffer2D {
+            swapped: hist.swapped,
+            calibration: (calibration.applied_x, calibration.applied_y),
+            range: (
+                (hist.range.x.min, hist.range.x.max),
+                (hist.range.y.min, hist.range.y.max),
+            ),
+            width: (hist.bins.x_width, hist.bins.y_width),
+            counts: FnvHashMap::default(),
+            overflow: (0, 0),
+            underflow: (0, 0),
+        }
+    }
+
+    pub fn fill(&mut self, x: f64, y: f64) {
+        let (x, y) = if self.swapped { (y, x) } else { (x, y) };
+        let x = self.calibration.0.calibrate(x);
+        let y = self.calibration.1.calibrate(y);
+        let ((x_min, x_max), (y_min, y_max)) = self.range;
+
+        if x < x_min {
+            self.underflow.0 += 1;
+        } else if x >= x_max {
+            self.overflow.0 += 1;
+        } else if y < y_min {
+            self.underflow.1 += 1;
+        } else if y >= y_max {
+            self.overflow.1 += 1;
+        } else {
+            let x_index = ((x - x_min) / self.width.0) as usize;
+            let y_index = ((y - y_min) / self.width.1) as usize;
+            *self.counts.entry((x_index, y_index)).or_insert(0) += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: Buffer2D) {
+        for (bin, count) in other.counts {
+            *self.counts.entry(bin).or_insert(0) += count;
+        }
+        self.overflow.0 += other.overflow.0;
+        self.overflow.1 += other.overflow.1;
+        self.underflow.0 += other.underflow.0;
+        self.underflow.1 += other.underflow.1;
+    }
+}
+
+impl Histogram2D {
+    pub fn add_buffer(&mut self, buffer: Buffer2D) {
+        for ((x_index, y_index), count) in buffer.counts {
+            let total = self.bins.counts.add(x_index, y_index, count);
+
+            // the minimum is the count a bin had after its first fill
+            self.bins.min_count = self.bins.min_count.min(total - count + 1);
+            self.bins.max_count = self.bins.max_count.max(total);
+            if total == count && !self.bins.counts.is_dense() {
+                self.bins.update_storage(self.plot_settings.storage);
+            }
+        }
+        self.overflow.0 += buffer.overflow.0;
+        self.overflow.1 += buffer.overflow.1;
+        self.underflow.0 += buffer.underflow.0;
+        self.underflow.1 += buffer.underflow.1;
+    }
+}
+
+#[derive(Clone)]
+pub struct Buffer3D {
+    range: [(f64, f64); 3],
+    bins: [usize; 3],
+    counts: FnvHashMap<(usize, usize, usize), u64>,
+    entries: u64,
+    overflow: u64,
+    underflow: u64,
+}
+
+impl Buffer3D {
+    pub fn new(hist: &Histogram3D) -> Self {
+        Buffer3D {
+            range: hist.range,
+            bins: hist.bins,
+            counts: FnvHashMap::default(),
+            entries: 0,
+            overflow: 0,
+            underflow: 0,
+        }
+    }
+
+    fn bin_index(&self, axis: usize, value: f64) -> usize {
+        let width = (self.range[axis].1 - self.range[axis].0) / self.bins[axis] as f64;
+        ((value - self.range[axis].0) / width) as usize
+    }
+
+    pub fn fill(&mut self, x: f64, y: f64, z: f64) {
+        let values = [x, y, z];
+
+        if (0..3).any(|axis| values[axis] < self.range[axis].0) {
+            self.underflow += 1;
+        } else if (0..3).any(|axis| values[axis] >= self.range[axis].1) {
+            self.overflow += 1;
+        } else {
+            let index = (
+                self.bin_index(0, x),
+                self.bin_index(1, y),
+                self.bin_index(2, z),
+            );
+            *self.counts.entry(index).or_insert(0) += 1;
+            self.entries += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: Buffer3D) {
+        for (bin, count) in other.counts {
+            *self.counts.entry(bin).or_insert(0) += count;
+        }
+        self.entries += other.entries;
+        self.overflow += other.overflow;
+        self.underflow += other.underflow;
+    }
+}
+
+impl Histogram3D {
+    pub fn add_buffer(&mut self, buffer: Buffer3D) {
+        for (bin, count) in buffer.counts {
+            *self.counts.entry(bin).or_insert(0) += count;
+        }
+        self.entries += buffer.entries;
+        self.overflow += buffer.overflow;
+        self.underflow += buffer.underflow;
+    }
+}
+
+#[derive(Clone)]
+pub struct BufferND {
+    ranges: Vec<(f64, f64)>,
+    bins: Vec<usize>,
+    counts: FnvHashMap<Vec<usize>, u64>,
+    entries: u64,
+    overflow: u64,
+    underflow: u64,
+}
+
+impl BufferND {
+    pub fn new(hist: &HistogramND) -> Self {
+        BufferND {
+            ranges: hist.ranges.clone(),
+            bins: hist.bins.clone(),
+            counts: FnvHashMap::default(),
+            entries: 0,
+            overflow: 0,
+            underflow: 0,
+        }
+    }
+
+    pub fn fill(&mut self, values: &[f64]) {
+        if values
+            .iter()
+            .zip(&self.ranges)
+            .any(|(value, range)| *value < range.0)
+        {
+            self.underflow += 1;
+        } else if values
+            .iter()
+            .zip(&self.ranges)
+            .any(|(value, range)| *value >= range.1)
+        {
+            self.overflow += 1;
+        } else {
+            let index: Vec<usize> = values
+                .iter()
+                .zip(self.ranges.iter().zip(&self.bins))
+                .map(|(value, (range, &bins))| {
+                    let width = (range.1 - range.0) / bins as f64;
+                    ((value - range.0) / width) as usize
+                })
+                .collect();
+            *self.counts.entry(index).or_insert(0) += 1;
+            self.entries += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: BufferND) {
+        for (bin, count) in other.counts {
+            *self.counts.entry(bin).or_insert(0) += count;
+        }
+        self.entries += other.entries;
+        self.overflow += other.overflow;
+        self.underflow += other.underflow;
+    }
+}
+
+impl HistogramND {
+    pub fn add_buffer(&mut self, buffer: BufferND) {
+        for (bin, count) in buffer.counts {
+            *self.counts.entry(bin).or_insert(0) += count;
+        }
+        self.entries += buffer.entries;
+        self.overflow += buffer.overflow;
+        self.underflow += buffer.underflow;
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashSet;
+
+// Histograms filled in a first pass over the data, the rest are filled after them.
+// A histogram is filled first when its pane is visible (if enabled) or when the
+// pattern matches its name or the name of one of its cuts.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct FillPriority {
+    pub visible_first: bool,
+    pub pattern: String, // regex, empty matches nothing
+}
+
+impl FillPriority {
+    pub fn is_active(&self) -> bool {
+        self.visible_first || !self.pattern.is_empty()
+    }
+
+    // Returns a test for (histogram name, cut names)
+    pub fn matcher<'a>(
+        &'a self,
+        visible: &'a HashSet<String>,
+    ) -> impl Fn(&str, &[&str]) -> bool + 'a {
+        let re = if self.pattern.is_empty() {
+            None
+        } else {
+            match regex::Regex::new(&self.pattern) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    log::error!("Invalid fill priority pattern '{}': {}", self.pattern, e);
+                    None
+                }
+            }
+        };
+
+        move |name: &str, cuts: &[&str]| {
+            (self.visible_first && visible.contains(name))
+                || re
+                    .as_ref()
+                    .is_some_and(|re| re.is_match(name) || cuts.iter().any(|cut| re.is_match(cut)))
+        }
+    }
+
+    pub fn menu_button(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Fill Priority", |ui| {
+            ui.label("Histograms filled in a first pass, the rest follow in the background");
+
+            ui.checkbox(&mut self.visible_first, "Visible panes first")
+                .on_hover_text("Histograms in the tabs that are shown when the fill starts");
+
+            ui.horizontal(|ui| {
+                ui.label("Pattern: ");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.pattern)
+                        .hint_text("Histogram or cut name (regex)")
+                        .desired_width(200.0),
+                );
+            });
+
+            if ui.button("Clear").clicked() {
+                *self = FillPriority::default();
+            }
+        });
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::{Duration, Instant};
+
+// State of the running fill or export, shared with the worker thread and shown in the
+// status bar. Histograms are filled in groups (prioritized ones first), every histogram
+// of a group is filled with each batch of rows.
+#[derive(Debug, Clone, Default)]
+pub struct FillProgress {
+    pub task: String,
+    pub fraction: f32,
+    pub rows_done: usize,
+    pub rows_total: usize, // rows of all groups, 0 when the task does not count rows
+    pub groups: Vec<Vec<String>>, // histogram names of each group
+    pub group: usize,      // index of the group being filled
+    started: Option<Instant>,
+}
+
+impl FillProgress {
+    pub fn start(task: &str, rows_total: usize, groups: Vec<Vec<String>>) -> Self {
+        FillProgress {
+            task: task.to_string(),
+            rows_total,
+            groups,
+            started: Some(Instant::now()),
+            ..Default::default()
+        }
+    }
+
+    pub fn add_rows(&mut self, rows: usize) {
+        self.rows_done += rows;
+        self.fraction = self.rows_done as f32 / self.rows_total.max(1) as f32;
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started
+            .map_or(Duration::ZERO, |started| started.elapsed())
+    }
+
+    // Linear extrapolation of the time taken so far
+    pub fn eta(&self) -> Option<Duration> {
+        if self.fraction <= 0.0 || self.fraction >= 1.0 {
+            return None;
+        }
+        let elapsed = self.elapsed().as_secs_f64();
+        let remaining = elapsed * (1.0 - self.fraction as f64) / self.fraction as f64;
+        Some(Duration::from_secs_f64(remaining))
+    }
+
+    // Name of the histogram being filled, with the size of the rest of its group
+    pub fn current(&self) -> Option<String> {
+        let group = self.groups.get(self.group)?;
+        let first = group.first()?;
+        Some(match group.len() {
+            1 => first.clone(),
+            n => format!("{} and {} more", first, n - 1),
+        })
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if !self.task.is_empty() {
+                ui.label(&self.task);
+            }
+
+            ui.add(
+                egui::ProgressBar::new(self.fraction)
+                    .desired_width(ui.available_width() / 2.0)
+                    .animate(true)
+                    .show_percentage(),
+            );
+
+            if self.rows_total > 0 {
+                ui.label(format!(
+                    "{} / {} rows",
+                    format_rows(self.rows_done),
+                    format_rows(self.rows_total)
+                ));
+            }
+
+            ui.label(format!("Elapsed: {}", format_duration(self.elapsed())));
+            if let Some(eta) = self.eta() {
+                ui.label(format!("ETA: {}", format_duration(eta)));
+            }
+
+            if let Some(current) = self.current() {
+                ui.separator();
+                ui.label(current).on_hover_ui(|ui| self.status_ui(ui));
+            }
+        });
+    }
+
+    // Every histogram of the fill with whether it is done, being filled, or waiting
+    fn status_ui(&self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .show(ui, |ui| {
+                for (index, group) in self.groups.iter().enumerate() {
+                    let (status, color) = match index.cmp(&self.group) {
+                        std::cmp::Ordering::Less => ("Done", egui::Color32::GREEN),
+                        std::cmp::Ordering::Equal => ("Filling", egui::Color32::YELLOW),
+                        std::cmp::Ordering::Greater => ("Waiting", egui::Color32::GRAY),
+                    };
+                    for name in group {
+                        ui.horizontal(|ui| {
+                            ui.colored_label(color, status);
+                            ui.label(name);
+                        });
+                    }
+                }
+            });
+    }
+}
+
+pub fn format_rows(rows: usize) -> String {
+    match rows {
+        0..1_000 => rows.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", rows as f64 / 1e3),
+        1_000_000..1_000_000_000 => format!("{:.1}M", rows as f64 / 1e6),
+        _ => format!("{:.2}G", rows as f64 / 1e9),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60)
+    } else if seconds >= 60 {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
@@ -0,0 +1,217 @@
+use std::sync::{Arc, Mutex};
+
+use super::histo1d::histogram1d::Histogram;
+use crate::fitter::fit_archive::FitArchive;
+use crate::fitter::main_fitter::FitResult;
+
+// Window with the stored fits of every 1D histogram, opened from the Histogrammer menu. The
+// fits of a histogram are saved and loaded as one fit archive, and "Save All"/"Load Folder"
+// move the fits of the whole workspace as one archive per histogram.
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+pub struct FitLibrary {
+    pub open: bool,
+    pub replace: bool, // loaded fits replace the stored fits instead of adding to them
+    #[serde(skip)]
+    filter: String,
+}
+
+impl FitLibrary {
+    fn save_all(hists: &[Arc<Mutex<Box<Histogram>>>]) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(dir) = rfd::FileDialog::new().pick_folder() else {
+            return Ok(());
+        };
+
+        let mut saved = 0;
+        for hist in hists {
+            let hist = hist.lock().unwrap();
+            if hist.fits.stored_fits.is_empty() {
+                continue;
+            }
+            let archive = FitArchive::new(&hist.name, &hist.fits.stored_fits);
+            archive.write(&dir.join(FitArchive::file_name(&hist.name)))?;
+            saved += 1;
+        }
+        log::info!(
+            "Saved the fits of {} histograms to {}",
+            saved,
+            dir.display()
+        );
+        Ok(())
+    }
+
+    // Loads every fit archive of a folder into the histogram it was saved from
+    fn load_folder(&self, hists: &[Arc<Mutex<Box<Histogram>>>]) -> std::io::Result<()> {
+        let Some(dir) = rfd::FileDialog::new().pick_folder() else {
+            return Ok(());
+        };
+
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let archive = match FitArchive::read(&path) {
+                Ok(archive) => archive,
+                Err(e) => {
+                    log::error!("Skipping {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            let Some(hist) = hists
+                .iter()
+                .find(|hist| hist.lock().unwrap().name == archive.histogram)
+            else {
+                log::error!(
+                    "No histogram named '{}' for the fits in {}",
+                    archive.histogram,
+                    path.display()
+                );
+                continue;
+            };
+
+            let mut hist = hist.lock().unwrap();
+            if self.replace {
+                hist.fits.stored_fits.clear();
+            }
+            if let Err(e) = hist.fits.load_archive(&path) {
+                log::error!("Error loading fits from {}: {}", path.display(), e);
+            }
+        }
+        Ok(())
+    }
+
+    fn histogram_ui(&self, ui: &mut egui::Ui, hist: &mut Histogram) {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    !hist.fits.stored_fits.is_empty(),
+                    egui::Button::new("Save…"),
+                )
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_file_name(FitArchive::file_name(&hist.name))
+                    .add_filter("JSON", &["json"])
+                    .save_file()
+                {
+                    let archive = FitArchive::new(&hist.name, &hist.fits.stored_fits);
+                    if let Err(e) = archive.write(&path) {
+                        log::error!("Error saving fits: {}", e);
+                    }
+                }
+            }
+
+            if ui
+                .button("Load…")
+                .on_hover_text(
+                    "Load a fit archive into this histogram, whichever histogram it was saved from",
+                )
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .pick_file()
+                {
+                    if self.replace {
+                        hist.fits.stored_fits.clear();
+                    }
+                    if let Err(e) = hist.fits.load_archive(&path) {
+                        log::error!("Error loading fits from {}: {}", path.display(), e);
+                    }
+                }
+            }
+        });
+
+        egui::Grid::new(("fit_library_fits", &hist.name))
+            .striped(true)
+            .show(ui, |ui| {
+                for fit in &hist.fits.stored_fits {
+                    ui.label(&fit.name);
+                    ui.label(format!("{:?}", fit.backend));
+                    match &fit.fit_result {
+                        Some(FitResult::Gaussian(result)) => {
+                            let means: Vec<String> = result
+                                .fit_result
+                                .iter()
+                                .filter_map(|peak| peak.mean.value)
+                                .map(|mean| format!("{:.2}", mean))
+                                .collect();
+                            ui.label(format!("Peaks at {}", means.join(", ")));
+                            if result.lmfit_result.is_empty() {
+                                ui.label("");
+                            } else {
+                                ui.label("lmfit result")
+                                    .on_hover_text("The lmfit ModelResult is saved with the fit");
+                            }
+                        }
+                        None => {
+                            ui.label("Not fit");
+                            ui.label("");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, hists: &[Arc<Mutex<Box<Histogram>>>]) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Fit Library")
+            .open(&mut open)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Save All…")
+                        .on_hover_text("One fit archive per histogram with fits in the chosen folder")
+                        .clicked()
+                    {
+                        if let Err(e) = Self::save_all(hists) {
+                            log::error!("Error saving the fit library: {}", e);
+                        }
+                    }
+
+                    if ui
+                        .button("Load Folder…")
+                        .on_hover_text("Load every fit archive of a folder into the histogram it was saved from")
+                        .clicked()
+                    {
+                        if let Err(e) = self.load_folder(hists) {
+                            log::error!("Error loading the fit library: {}", e);
+                        }
+                    }
+
+                    ui.checkbox(&mut self.replace, "Replace")
+                        .on_hover_text("Loaded fits replace the stored fits of the histogram");
+                });
+
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.filter)
+                        .hint_text("Filter histograms"),
+                );
+
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for hist in hists {
+                        let mut hist = hist.lock().unwrap();
+                        if !self.filter.is_empty() && !hist.name.contains(&self.filter) {
+                            continue;
+                        }
+
+                        let fits = hist.fits.stored_fits.len();
+                        egui::CollapsingHeader::new(format!("{} ({} fits)", hist.name, fits))
+                            .id_salt(("fit_library", hist.name.clone()))
+                            .show(ui, |ui| self.histogram_ui(ui, &mut hist));
+                    }
+                });
+            });
+
+        self.open = open;
+    }
+}
@@ -0,0 +1,294 @@
+use egui_extras::{Column, TableBuilder};
+use polars::prelude::{DataFrame, IntoColumn, NamedFrom, ParquetWriter, PolarsResult, Series};
+use std::sync::{Arc, Mutex};
+
+use super::histo1d::histogram1d::Histogram;
+use crate::fitter::common::Parameter;
+use crate::fitter::main_fitter::FitResult;
+
+// One peak of a stored fit
+#[derive(Debug, Clone)]
+pub struct FitRow {
+    pub histogram: String,
+    pub fit: String,
+    pub peak: usize,
+    pub mean: (f64, f64), // value, uncertainty
+    pub fwhm: (f64, f64),
+    pub area: (f64, f64),
+    pub energy: (f64, f64), // NaN when no energy is assigned
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum SortColumn {
+    #[default]
+    Histogram,
+    Fit,
+    Peak,
+    Mean,
+    Fwhm,
+    Area,
+    Energy,
+}
+
+impl SortColumn {
+    const ALL: [SortColumn; 7] = [
+        SortColumn::Histogram,
+        SortColumn::Fit,
+        SortColumn::Peak,
+        SortColumn::Mean,
+        SortColumn::Fwhm,
+        SortColumn::Area,
+        SortColumn::Energy,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SortColumn::Histogram => "Histogram",
+            SortColumn::Fit => "Fit",
+            SortColumn::Peak => "Peak",
+            SortColumn::Mean => "Mean",
+            SortColumn::Fwhm => "FWHM",
+            SortColumn::Area => "Area",
+            SortColumn::Energy => "Energy",
+        }
+    }
+
+    fn compare(&self, a: &FitRow, b: &FitRow) -> std::cmp::Ordering {
+        match self {
+            SortColumn::Histogram => a.histogram.cmp(&b.histogram),
+            SortColumn::Fit => a.fit.cmp(&b.fit),
+            SortColumn::Peak => a.peak.cmp(&b.peak),
+            SortColumn::Mean => a.mean.0.total_cmp(&b.mean.0),
+            SortColumn::Fwhm => a.fwhm.0.total_cmp(&b.fwhm.0),
+            SortColumn::Area => a.area.0.total_cmp(&b.area.0),
+            SortColumn::Energy => a.energy.0.total_cmp(&b.energy.0),
+        }
+    }
+}
+
+// Window with every stored fit of every 1D histogram, opened from the Histogrammer menu
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+pub struct FitSummary {
+    pub open: bool,
+    pub sort: SortColumn,
+    pub descending: bool,
+}
+
+impl FitSummary {
+    pub fn rows(hists: &[Arc<Mutex<Box<Histogram>>>]) -> Vec<FitRow> {
+        fn value(p: &Parameter) -> (f64, f64) {
+            (
+                p.value.unwrap_or(f64::NAN),
+                p.uncertainty.unwrap_or(f64::NAN),
+            )
+        }
+
+        let mut rows = Vec::new();
+        for hist in hists {
+            let hist = hist.lock().unwrap();
+            for fit in &hist.fits.stored_fits {
+                let Some(FitResult::Gaussian(result)) = &fit.fit_result else {
+                    continue;
+                };
+                for (peak, params) in result.fit_result.iter().enumerate() {
+                    rows.push(FitRow {
+                        histogram: hist.name.clone(),
+                        fit: fit.name.clone(),
+                        peak,
+                        mean: value(&params.mean),
+                        fwhm: value(&params.fwhm),
+                        area: value(&params.area),
+                        energy: value(&params.energy),
+                    });
+                }
+            }
+        }
+        rows
+    }
+
+    fn sorted(&self, mut rows: Vec<FitRow>) -> Vec<FitRow> {
+        rows.sort_by(|a, b| {
+            let order = self.sort.compare(a, b);
+            if self.descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        rows
+    }
+
+    pub fn data_frame(rows: &[FitRow]) -> PolarsResult<DataFrame> {
+        let column = |name: &str, values: Vec<f64>| Series::new(name.into(), values).into_column();
+        let pairs =
+            |f: fn(&FitRow) -> (f64, f64)| -> (Vec<f64>, Vec<f64>) { rows.iter().map(f).unzip() };
+        let (mean, mean_uncertainty) = pairs(|row| row.mean);
+        let (fwhm, fwhm_uncertainty) = pairs(|row| row.fwhm);
+        let (area, area_uncertainty) = pairs(|row| row.area);
+        let (energy, energy_uncertainty) = pairs(|row| row.energy);
+
+        DataFrame::new(vec![
+            Series::new(
+                "histogram".into(),
+                rows.iter()
+                    .map(|row| row.histogram.clone())
+                    .collect::<Vec<_>>(),
+            )
+            .into_column(),
+            Series::new(
+                "fit".into(),
+                rows.iter().map(|row| row.fit.clone()).collect::<Vec<_>>(),
+            )
+            .into_column(),
+            Series::new(
+                "peak".into(),
+                rows.iter().map(|row| row.peak as u32).collect::<Vec<_>>(),
+            )
+            .into_column(),
+            column("mean", mean),
+            column("mean_uncertainty", mean_uncertainty),
+            column("fwhm", fwhm),
+            column("fwhm_uncertainty", fwhm_uncertainty),
+            column("area", area),
+            column("area_uncertainty", area_uncertainty),
+            column("energy", energy),
+            column("energy_uncertainty", energy_uncertainty),
+        ])
+    }
+
+    // CSV by default, Parquet when the path ends in .parquet
+    fn export(&self, rows: &[FitRow]) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name("fit_summary.csv")
+            .add_filter("CSV", &["csv"])
+            .add_filter("Parquet", &["parquet"])
+            .save_file()
+        else {
+            return Ok(());
+        };
+
+        if path.extension().and_then(|ext| ext.to_str()) == Some("parquet") {
+            let mut df = Self::data_frame(rows)?;
+            ParquetWriter::new(std::fs::File::create(&path)?).finish(&mut df)?;
+        } else {
+            let number = |v: f64| {
+                if v.is_nan() {
+                    String::new()
+                } else {
+                    v.to_string()
+                }
+            };
+            let mut text = "histogram,fit,peak,mean,mean_uncertainty,fwhm,fwhm_uncertainty,area,area_uncertainty,energy,energy_uncertainty\n".to_string();
+            for row in rows {
+                let mut fields = vec![
+                    format!("\"{}\"", row.histogram.replace('"', "\"\"")),
+                    format!("\"{}\"", row.fit.replace('"', "\"\"")),
+                    row.peak.to_string(),
+                ];
+                for (value, uncertainty) in [row.mean, row.fwhm, row.area, row.energy] {
+                    fields.push(number(value));
+                    fields.push(number(uncertainty));
+                }
+                text.push_str(&fields.join(","));
+                text.push('\n');
+            }
+            std::fs::write(&path, text)?;
+        }
+
+        log::info!("Wrote {} fit rows to {}", rows.len(), path.display());
+        Ok(())
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, hists: &[Arc<Mutex<Box<Histogram>>>]) {
+        if !self.open {
+            return;
+        }
+
+        let rows = self.sorted(Self::rows(hists));
+        let mut open = self.open;
+
+        egui::Window::new("Fit Summary")
+            .open(&mut open)
+            .default_width(700.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} peaks", rows.len()));
+                    ui.separator();
+                    if ui
+                        .add_enabled(!rows.is_empty(), egui::Button::new("Export…"))
+                        .on_hover_text("CSV, or Parquet when the file name ends in .parquet")
+                        .clicked()
+                    {
+                        if let Err(e) = self.export(&rows) {
+                            log::error!("Error exporting the fit summary: {}", e);
+                        }
+                    }
+                });
+
+                ui.separator();
+
+                let uncertain = |(value, uncertainty): (f64, f64)| {
+                    if value.is_nan() {
+                        String::new()
+                    } else if uncertainty.is_nan() {
+                        format!("{:.3}", value)
+                    } else {
+                        format!("{:.3} ± {:.3}", value, uncertainty)
+                    }
+                };
+
+                TableBuilder::new(ui)
+                    .id_salt("fit_summary_table")
+                    .striped(true)
+                    .columns(Column::auto().resizable(true), SortColumn::ALL.len() - 1)
+                    .column(Column::remainder())
+                    .header(20.0, |mut header| {
+                        for column in SortColumn::ALL {
+                            header.col(|ui| {
+                                let arrow = match (self.sort == column, self.descending) {
+                                    (true, false) => " ⏶",
+                                    (true, true) => " ⏷",
+                                    _ => "",
+                                };
+                                if ui
+                                    .selectable_label(
+                                        self.sort == column,
+                                        format!("{}{}", column.label(), arrow),
+                                    )
+                                    .on_hover_text("Sort, click again to reverse")
+                                    .clicked()
+                                {
+                                    if self.sort == column {
+                                        self.descending = !self.descending;
+                                    } else {
+                                        self.sort = column;
+                                        self.descending = false;
+                                    }
+                                }
+                            });
+                        }
+                    })
+                    .body(|body| {
+                        body.rows(18.0, rows.len(), |mut row| {
+                            let fit = &rows[row.index()];
+                            row.col(|ui| {
+                                ui.label(&fit.histogram);
+                            });
+                            row.col(|ui| {
+                                ui.label(&fit.fit);
+                            });
+                            row.col(|ui| {
+                                ui.label(fit.peak.to_string());
+                            });
+                            for values in [fit.mean, fit.fwhm, fit.area, fit.energy] {
+                                row.col(|ui| {
+                                    ui.label(uncertain(values));
+                                });
+                            }
+                        });
+                    });
+            });
+
+        self.open = open;
+    }
+}
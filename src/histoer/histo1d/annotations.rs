@@ -0,0 +1,60 @@
+use super::histogram1d::Histogram;
+
+// Labelled lines written onto a histogram from outside, like the focal plane positions of the
+// states predicted by the kinematics calculator. x is in the units of the histogram bins.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Annotation {
+    pub x: f64,
+    pub label: String,
+}
+
+impl Histogram {
+    pub fn draw_annotations(&self, plot_ui: &mut egui_plot::PlotUi) {
+        let top = plot_ui.plot_bounds().max()[1];
+        for annotation in &self.plot_settings.annotations {
+            let x = if self.line.log_x {
+                if annotation.x <= 0.0 {
+                    continue;
+                }
+                annotation.x.log10()
+            } else {
+                annotation.x
+            };
+
+            plot_ui.vline(
+                egui_plot::VLine::new(x)
+                    .color(egui::Color32::GRAY)
+                    .style(egui_plot::LineStyle::dotted_dense())
+                    .name("Annotations"),
+            );
+            plot_ui.text(
+                egui_plot::Text::new(egui_plot::PlotPoint::new(x, top), &annotation.label)
+                    .color(egui::Color32::GRAY)
+                    .anchor(egui::Align2::LEFT_TOP),
+            );
+        }
+    }
+
+    pub fn annotations_menu(&mut self, ui: &mut egui::Ui) {
+        if self.plot_settings.annotations.is_empty() {
+            return;
+        }
+
+        ui.menu_button("Annotations", |ui| {
+            egui::Grid::new(format!("{}_annotations", self.name))
+                .striped(true)
+                .show(ui, |ui| {
+                    for annotation in &self.plot_settings.annotations {
+                        ui.label(&annotation.label);
+                        ui.label(format!("{:.2}", annotation.x));
+                        ui.end_row();
+                    }
+                });
+
+            if ui.button("Clear").clicked() {
+                self.plot_settings.annotations.clear();
+                ui.close_menu();
+            }
+        });
+    }
+}
@@ -0,0 +1,113 @@
+use super::histogram1d::Histogram;
+
+const MAX_EXTENSIONS: u32 = 10; // the range grows at most 2^10 times
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+pub struct AutoRange {
+    pub enabled: bool,
+    pub extensions: u32, // number of times the range was doubled
+    #[serde(skip)]
+    pub notice: Option<String>,
+}
+
+impl Histogram {
+    // Doubles the range towards the value until it fits. The number of bins is kept by
+    // merging neighbouring bins, an odd number of bins is doubled at the same width instead.
+    // Returns false when the value is further out than the remaining growth allows.
+    pub fn extend_range(&mut self, value: f64) -> bool {
+        if self.is_variable_width() || self.values.is_some() || !value.is_finite() {
+            return false;
+        }
+
+        // check that the value fits before changing anything
+        let (mut low, mut high) = self.range;
+        let mut doublings = 0;
+        while !(low..high).contains(&value) {
+            if self.plot_settings.auto_range.extensions + doublings >= MAX_EXTENSIONS {
+                return false;
+            }
+            let width = high - low;
+            if value >= high {
+                high += width;
+            } else {
+                low -= width;
+            }
+            doublings += 1;
+        }
+
+        for _ in 0..doublings {
+            let count = self.original_bins.len();
+            let width = self.range.1 - self.range.0;
+            let upward = value >= self.range.1;
+
+            let mut grown = if count % 2 == 0 {
+                self.original_bins
+                    .chunks(2)
+                    .map(|pair| pair.iter().sum())
+                    .collect()
+            } else {
+                self.original_bins.clone()
+            };
+            let padding = vec![0; if count % 2 == 0 { count / 2 } else { count }];
+
+            if upward {
+                grown.extend(padding);
+                self.range.1 += width;
+            } else {
+                grown.splice(0..0, padding);
+                self.range.0 -= width;
+            }
+            self.original_bins = grown;
+        }
+
+        let auto_range = &mut self.plot_settings.auto_range;
+        auto_range.extensions += doublings;
+        let notice = format!(
+            "Range of '{}' extended to [{}, {}) with {} bins to fit {}",
+            self.name,
+            self.range.0,
+            self.range.1,
+            self.original_bins.len(),
+            value
+        );
+        log::info!("{}", notice);
+        auto_range.notice = Some(notice);
+
+        self.rebin();
+        true
+    }
+
+    pub fn auto_range_ui(&mut self, ui: &mut egui::Ui) {
+        let auto_range = &mut self.plot_settings.auto_range;
+        ui.add_enabled(
+            self.edges.is_empty() && self.values.is_none(),
+            egui::Checkbox::new(&mut auto_range.enabled, "Extend range while filling"),
+        )
+        .on_hover_text(format!(
+            "Double the range instead of counting values outside it as overflow, up to {}x",
+            1 << MAX_EXTENSIONS
+        ));
+
+        if auto_range.extensions > 0 {
+            ui.label(format!(
+                "Extended {}x so far",
+                1u64 << auto_range.extensions
+            ));
+        }
+    }
+
+    // Shown above the plot after the range was extended
+    pub fn auto_range_status_ui(&mut self, ui: &mut egui::Ui) {
+        let auto_range = &mut self.plot_settings.auto_range;
+        let Some(notice) = auto_range.notice.clone() else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(notice);
+            if ui.small_button("OK").clicked() {
+                auto_range.notice = None;
+            }
+        });
+    }
+}
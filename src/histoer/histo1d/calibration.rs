@@ -0,0 +1,182 @@
+use super::histogram1d::Histogram;
+use crate::fitter::main_fitter::FitResult;
+
+impl Histogram {
+    // (centroid, centroid uncertainty, energy, energy uncertainty) of the stored peaks with an energy
+    pub fn calibration_points(&self) -> Vec<[f64; 4]> {
+        self.fits
+            .stored_fits
+            .iter()
+            .filter_map(|fit| {
+                fit.fit_result
+                    .as_ref()
+                    .map(|FitResult::Gaussian(gaussian)| &gaussian.fit_result)
+            })
+            .flatten()
+            .filter_map(|peak| {
+                Some([
+                    peak.mean.value?,
+                    peak.mean.uncertainty.unwrap_or(0.0),
+                    peak.energy.value?,
+                    peak.energy.uncertainty.unwrap_or(0.0),
+                ])
+            })
+            .collect()
+    }
+
+    pub fn calibration_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Calibration", |ui| {
+            self.plot_settings.calibration.ui(ui);
+
+            if self.fits.stored_fits.is_empty() {
+                return;
+            }
+
+            ui.separator();
+            ui.heading("From Stored Fits");
+            ui.label("Assign energies to the fitted peaks, empty peaks are skipped");
+
+            let suggest = self.energy_suggester();
+            let suggesting = !self.plot_settings.gamma_lines.isotopes.is_empty();
+            if suggesting
+                && ui
+                    .button("Assign Suggestions")
+                    .on_hover_text(
+                        "Give every peak without an energy the nearest gamma line of the chosen isotopes",
+                    )
+                    .clicked()
+            {
+                for fit in &mut self.fits.stored_fits {
+                    let Some(FitResult::Gaussian(gaussian)) = &mut fit.fit_result else {
+                        continue;
+                    };
+                    for peak in &mut gaussian.fit_result {
+                        if peak.energy.value.is_some() {
+                            continue;
+                        }
+                        if let Some(line) = peak.mean.value.and_then(&suggest) {
+                            peak.energy.value = Some(line.energy);
+                            peak.energy.uncertainty = Some(line.uncertainty);
+                        }
+                    }
+                }
+            }
+
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .id_salt(format!("{}_calibration_peaks", self.name))
+                .show(ui, |ui| {
+                    egui::Grid::new(format!("{}_calibration_peaks_grid", self.name))
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label("Fit");
+                            ui.label("Centroid");
+                            ui.label("Energy");
+                            ui.label("±");
+                            if suggesting {
+                                ui.label("Suggestion");
+                            }
+                            ui.end_row();
+
+                            for fit in &mut self.fits.stored_fits {
+                                let Some(FitResult::Gaussian(gaussian)) = &mut fit.fit_result
+                                else {
+                                    continue;
+                                };
+                                for peak in &mut gaussian.fit_result {
+                                    ui.label(&fit.name);
+                                    ui.label(format!(
+                                        "{:.2} ± {:.2}",
+                                        peak.mean.value.unwrap_or(0.0),
+                                        peak.mean.uncertainty.unwrap_or(0.0)
+                                    ));
+
+                                    let mut assigned = peak.energy.value.is_some();
+                                    let mut energy = peak.energy.value.unwrap_or(0.0);
+                                    let mut uncertainty = peak.energy.uncertainty.unwrap_or(0.0);
+                                    ui.horizontal(|ui| {
+                                        ui.checkbox(&mut assigned, "");
+                                        ui.add_enabled(
+                                            assigned,
+                                            egui::DragValue::new(&mut energy).speed(0.1),
+                                        );
+                                    });
+                                    ui.add_enabled(
+                                        assigned,
+                                        egui::DragValue::new(&mut uncertainty)
+                                            .speed(0.01)
+                                            .range(0.0..=f64::INFINITY),
+                                    );
+                                    peak.energy.value = assigned.then_some(energy);
+                                    peak.energy.uncertainty = assigned.then_some(uncertainty);
+
+                                    if suggesting {
+                                        match peak.mean.value.and_then(&suggest) {
+                                            Some(line) => {
+                                                if ui
+                                                    .button(format!(
+                                                        "{} {:.2}",
+                                                        line.isotope, line.energy
+                                                    ))
+                                                    .on_hover_text(format!(
+                                                        "{:.1}% intensity",
+                                                        line.intensity
+                                                    ))
+                                                    .clicked()
+                                                {
+                                                    peak.energy.value = Some(line.energy);
+                                                    peak.energy.uncertainty =
+                                                        Some(line.uncertainty);
+                                                }
+                                            }
+                                            None => {
+                                                ui.label("");
+                                            }
+                                        }
+                                    }
+                                    ui.end_row();
+                                }
+                            }
+                        });
+                });
+
+            let points = self.calibration_points();
+            let calibration = &mut self.plot_settings.calibration;
+
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut calibration.quadratic, false, "Linear");
+                ui.radio_value(&mut calibration.quadratic, true, "Quadratic");
+
+                if ui
+                    .button("Fit")
+                    .on_hover_text(format!("{} peaks with an energy", points.len()))
+                    .clicked()
+                {
+                    if let Err(e) = calibration.fit(&points) {
+                        log::error!("{}", e);
+                    }
+                }
+            });
+
+            if let Some(fit) = &calibration.last_fit {
+                let [a, b, c] = fit.coefficients;
+                ui.label(format!(
+                    "a = {:.4e}, b = {:.6}, c = {:.4}, χ²/ndf = {:.2}",
+                    a, b, c, fit.chi2_ndf
+                ));
+                calibration.residuals_plot(ui, &format!("{}_calibration_residuals", self.name));
+
+                if ui
+                    .button("Apply")
+                    .on_hover_text(
+                        "Use this calibration for the axis and all fits of the histogram",
+                    )
+                    .clicked()
+                {
+                    calibration.apply_fit();
+                    self.plot_settings.egui_settings.reset_axis = true;
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,431 @@
+use super::histogram1d::Histogram;
+use egui_plot::{HLine, Line, PlotPoints, Points};
+
+// The reference histogram, copied from its pane by the Histogrammer every frame
+#[derive(Debug, Clone)]
+pub struct ReferenceLayer {
+    pub name: String,
+    pub color: egui::Color32,
+    pub counts: Vec<f64>,
+    pub errors: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub enum ComparisonMode {
+    #[default]
+    Ratio,
+    Residual, // (data - reference) / uncertainty
+}
+
+impl ComparisonMode {
+    fn label(&self) -> &'static str {
+        match self {
+            ComparisonMode::Ratio => "Data / Reference",
+            ComparisonMode::Residual => "(Data - Reference) / σ",
+        }
+    }
+}
+
+// Compares this histogram to a reference histogram with the same bins, e.g. the same
+// spectrum of an earlier run. The reference is drawn over the data and the ratio or
+// residual is shown in a plot below with the x axis linked.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ComparisonSettings {
+    pub enabled: bool,
+    pub reference: String, // histogram name
+    pub mode: ComparisonMode,
+    pub normalize: bool, // scale the reference to the counts of this histogram
+    #[serde(skip)]
+    pub layer: Option<ReferenceLayer>,
+    #[serde(skip)]
+    pub status: Option<String>, // why there is nothing to compare
+}
+
+impl Default for ComparisonSettings {
+    fn default() -> Self {
+        ComparisonSettings {
+            enabled: false,
+            reference: String::new(),
+            mode: ComparisonMode::Ratio,
+            normalize: true,
+            layer: None,
+            status: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ComparisonStats {
+    pub scale: f64, // applied to the reference
+    pub chi2: f64,
+    pub ndf: usize,
+    pub chi2_p: f64,
+    pub ks_distance: f64,
+    pub ks_p: f64,
+}
+
+// ln Γ(x) with the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.120_865_097_386_618e-2,
+        -0.539_523_938_495_3e-5,
+    ];
+    let mut y = x;
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000_000_000_190_015;
+    for coefficient in COEFFICIENTS {
+        y += 1.0;
+        series += coefficient / y;
+    }
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+// Regularized upper incomplete gamma function Q(a, x), series below a + 1 and
+// continued fraction above
+fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 || a <= 0.0 {
+        return 1.0;
+    }
+    let prefactor = (-x + a * x.ln() - ln_gamma(a)).exp();
+
+    if x < a + 1.0 {
+        let (mut term, mut sum, mut n) = (1.0 / a, 1.0 / a, a);
+        for _ in 0..500 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-14 {
+                break;
+            }
+        }
+        (1.0 - sum * prefactor).clamp(0.0, 1.0)
+    } else {
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..500 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-14 {
+                break;
+            }
+        }
+        (prefactor * h).clamp(0.0, 1.0)
+    }
+}
+
+// Probability of a Kolmogorov distance at least as large, for the effective number of entries
+fn kolmogorov_p(distance: f64, entries: f64) -> f64 {
+    if entries <= 0.0 {
+        return 1.0;
+    }
+    let root = entries.sqrt();
+    let lambda = (root + 0.12 + 0.11 / root) * distance;
+    if lambda < 0.2 {
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    for j in 1..=100 {
+        let term = (-2.0 * (j * j) as f64 * lambda * lambda).exp();
+        sum += if j % 2 == 1 { term } else { -term };
+        if term < 1e-12 {
+            break;
+        }
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+impl ReferenceLayer {
+    // Zero outside the layer, the bins can change before the next copy
+    fn count(&self, index: usize) -> f64 {
+        self.counts.get(index).copied().unwrap_or(0.0)
+    }
+
+    fn error(&self, index: usize) -> f64 {
+        self.errors.get(index).copied().unwrap_or(0.0)
+    }
+}
+
+impl Histogram {
+    pub fn reference_layer(&self) -> ReferenceLayer {
+        ReferenceLayer {
+            name: self.name.clone(),
+            color: self.line.color,
+            counts: (0..self.bins.len())
+                .map(|index| self.bin_content(index))
+                .collect(),
+            errors: (0..self.bins.len())
+                .map(|index| self.bin_error(index))
+                .collect(),
+        }
+    }
+
+    // Bins compared, limited to the region markers when both are set
+    fn comparison_bins(&self) -> std::ops::Range<usize> {
+        let region = self.plot_settings.markers.get_region_marker_positions();
+        if region.len() == 2 {
+            let start = self.get_bin_index(region[0].min(region[1])).unwrap_or(0);
+            let end = self
+                .get_bin_index(region[0].max(region[1]))
+                .unwrap_or(self.bins.len().saturating_sub(1));
+            start..(end + 1).min(self.bins.len())
+        } else {
+            0..self.bins.len()
+        }
+    }
+
+    pub fn comparison_stats(&self) -> Option<ComparisonStats> {
+        let comparison = &self.plot_settings.comparison;
+        let layer = comparison.layer.as_ref()?;
+        let bins = self.comparison_bins();
+        if bins.is_empty() {
+            return None;
+        }
+
+        let data: f64 = bins.clone().map(|index| self.bin_content(index)).sum();
+        let reference: f64 = bins.clone().map(|index| layer.count(index)).sum();
+        let scale = if comparison.normalize && reference > 0.0 {
+            data / reference
+        } else {
+            1.0
+        };
+
+        let (mut chi2, mut used) = (0.0, 0usize);
+        let (mut data_sum, mut reference_sum, mut ks_distance) = (0.0, 0.0, 0.0f64);
+        for index in bins {
+            let (d, r) = (self.bin_content(index), scale * layer.count(index));
+            let variance = self.bin_error(index).powi(2) + (scale * layer.error(index)).powi(2);
+            if variance > 0.0 {
+                chi2 += (d - r).powi(2) / variance;
+                used += 1;
+            }
+
+            data_sum += d;
+            reference_sum += r;
+            if data > 0.0 && reference > 0.0 {
+                ks_distance =
+                    ks_distance.max((data_sum / data - reference_sum / (scale * reference)).abs());
+            }
+        }
+
+        let ndf = used.saturating_sub(comparison.normalize as usize);
+        let chi2_p = if ndf > 0 {
+            gamma_q(0.5 * ndf as f64, 0.5 * chi2)
+        } else {
+            f64::NAN
+        };
+        let entries = if data > 0.0 && reference > 0.0 {
+            data * reference / (data + reference)
+        } else {
+            0.0
+        };
+
+        Some(ComparisonStats {
+            scale,
+            chi2,
+            ndf,
+            chi2_p,
+            ks_distance,
+            ks_p: kolmogorov_p(ks_distance, entries),
+        })
+    }
+
+    // Values of the sub-plot with their uncertainties, None for bins without a value
+    fn comparison_values(&self, scale: f64) -> Vec<Option<(f64, f64)>> {
+        let comparison = &self.plot_settings.comparison;
+        let Some(layer) = &comparison.layer else {
+            return Vec::new();
+        };
+        (0..self.bins.len())
+            .map(|index| {
+                let (d, r) = (self.bin_content(index), scale * layer.count(index));
+                let (d_error, r_error) = (self.bin_error(index), scale * layer.error(index));
+                match comparison.mode {
+                    ComparisonMode::Ratio => (r > 0.0).then(|| {
+                        let error =
+                            ((d_error / r).powi(2) + (d * r_error / (r * r)).powi(2)).sqrt();
+                        (d / r, error)
+                    }),
+                    ComparisonMode::Residual => {
+                        let sigma = (d_error.powi(2) + r_error.powi(2)).sqrt();
+                        (sigma > 0.0).then(|| ((d - r) / sigma, 0.0))
+                    }
+                }
+            })
+            .collect()
+    }
+
+    pub fn comparison_ui(&mut self, ui: &mut egui::Ui) {
+        let comparison = &mut self.plot_settings.comparison;
+        ui.menu_button("Compare", |ui| {
+            ui.checkbox(&mut comparison.enabled, "Compare to reference")
+                .on_hover_text("Draw the reference histogram and the ratio or residual below");
+
+            ui.horizontal(|ui| {
+                ui.label("Reference: ");
+                ui.add(
+                    egui::TextEdit::singleline(&mut comparison.reference)
+                        .hint_text("Histogram name")
+                        .desired_width(200.0),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut comparison.mode, ComparisonMode::Ratio, "Ratio");
+                ui.radio_value(&mut comparison.mode, ComparisonMode::Residual, "Residual")
+                    .on_hover_text("Difference in units of the combined uncertainty");
+            });
+
+            ui.checkbox(&mut comparison.normalize, "Normalize")
+                .on_hover_text(
+                    "Scale the reference to the counts of this histogram, for shape comparisons",
+                );
+
+            if comparison.enabled {
+                if let Some(status) = &comparison.status {
+                    ui.colored_label(egui::Color32::RED, status);
+                }
+            }
+        });
+    }
+
+    // Statistics line above the plots
+    pub fn comparison_stats_ui(&self, ui: &mut egui::Ui) {
+        let comparison = &self.plot_settings.comparison;
+        if !comparison.enabled {
+            return;
+        }
+        if let Some(status) = &comparison.status {
+            ui.colored_label(egui::Color32::RED, status);
+            return;
+        }
+        let Some(stats) = self.comparison_stats() else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(format!("vs {}:", comparison.reference));
+            ui.label(format!(
+                "χ²/ndf: {:.1}/{} (p = {:.3})",
+                stats.chi2, stats.ndf, stats.chi2_p
+            ));
+            ui.separator();
+            ui.label(format!(
+                "KS: D = {:.4} (p = {:.3})",
+                stats.ks_distance, stats.ks_p
+            ));
+            if comparison.normalize {
+                ui.separator();
+                ui.label(format!("Scale: {:.4}", stats.scale));
+            }
+        })
+        .response
+        .on_hover_text(
+            "Between the region markers when they are set. The KS test uses the binned \
+             distributions, so its p-value is approximate.",
+        );
+    }
+
+    // Reference line in the main plot, scaled like the statistics
+    pub fn draw_reference(&self, plot_ui: &mut egui_plot::PlotUi) {
+        let comparison = &self.plot_settings.comparison;
+        let (true, Some(layer)) = (comparison.enabled, &comparison.layer) else {
+            return;
+        };
+        let scale = self.comparison_stats().map_or(1.0, |stats| stats.scale);
+
+        let mut line = self.line.clone();
+        line.name = format!("{} (reference)", layer.name);
+        line.name_in_legend = true;
+        line.color = layer.color;
+        line.stroke.color = layer.color;
+        line.style = Some(egui_plot::LineStyle::dashed_loose());
+        line.points = (0..self.bins.len())
+            .flat_map(|index| {
+                let y_value = scale * layer.count(index);
+                [
+                    [self.bin_edge(index), y_value],
+                    [self.bin_edge(index + 1), y_value],
+                ]
+            })
+            .collect();
+        line.draw(plot_ui);
+    }
+
+    pub fn comparison_active(&self) -> bool {
+        let comparison = &self.plot_settings.comparison;
+        comparison.enabled && comparison.layer.is_some()
+    }
+
+    // Ratio or residual plot below the histogram, with the x axis linked to it
+    pub fn comparison_plot(&self, ui: &mut egui::Ui, link: egui::Id) {
+        let comparison = &self.plot_settings.comparison;
+        let scale = self.comparison_stats().map_or(1.0, |stats| stats.scale);
+        let values = self.comparison_values(scale);
+        let log_x = self.plot_settings.egui_settings.log_x;
+        let transform_x = |x: f64| {
+            if log_x && x > 0.0 {
+                x.log10().max(0.0001)
+            } else {
+                x
+            }
+        };
+
+        let mut settings = self.plot_settings.egui_settings.clone();
+        settings.log_y = false;
+        settings.y_transform = Default::default();
+        settings.legend = false;
+        settings.lock_bounds = false;
+        settings.y_label = comparison.mode.label().to_string();
+
+        let plot = settings
+            .apply_to_plot(egui_plot::Plot::new(format!("{} comparison", self.name)))
+            .link_axis(link, egui::Vec2b::new(true, false))
+            .link_cursor(link, egui::Vec2b::new(true, false));
+
+        let color = self.line.color;
+        plot.show(ui, |plot_ui| {
+            let neutral = match comparison.mode {
+                ComparisonMode::Ratio => 1.0,
+                ComparisonMode::Residual => 0.0,
+            };
+            plot_ui.hline(HLine::new(neutral).color(egui::Color32::GRAY));
+
+            let mut points = Vec::new();
+            for (index, value) in values.iter().enumerate() {
+                let Some((y, error)) = value else {
+                    continue;
+                };
+                let x = transform_x(self.bin_center(index));
+                points.push([x, *y]);
+                if *error > 0.0 {
+                    plot_ui.line(
+                        Line::new(PlotPoints::new(vec![[x, y - error], [x, y + error]]))
+                            .color(color.gamma_multiply(0.6))
+                            .allow_hover(false),
+                    );
+                }
+            }
+            plot_ui.points(Points::new(points).color(color).radius(2.0));
+        });
+    }
+}
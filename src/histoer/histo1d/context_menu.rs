@@ -1,43 +1,90 @@
 use super::histogram1d::Histogram;
+use crate::histoer::jobs::Job;
+use crate::histoer::mca_formats::McaSpectrum;
 
 impl Histogram {
+    fn export_mca(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let spectrum = McaSpectrum::from_histogram(self)?;
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name(format!("{}.Chn", self.name.replace('/', "_")))
+            .add_filter("ORTEC Chn", &["Chn", "chn"])
+            .add_filter("ORTEC Spe", &["Spe", "spe"])
+            .save_file()
+        {
+            spectrum.write(&path)?;
+        }
+        Ok(())
+    }
+
     // Handles the context menu for the histogram
     pub fn context_menu(&mut self, ui: &mut egui::Ui) {
         self.line.menu_button(ui);
         self.plot_settings.settings_ui(ui);
+        self.plot_settings
+            .egui_settings
+            .transform_ui(ui, &self.name);
+        self.calibration_menu(ui);
+        self.gamma_lines_menu(ui);
+        self.annotations_menu(ui);
+        self.stack_ui(ui);
+        self.comparison_ui(ui);
+        if let Some(extension) = self.plot_settings.figure.menu_button(ui) {
+            self.save_figure(extension);
+        }
         self.keybinds_ui(ui);
 
-        self.fits.fit_context_menu_ui(ui);
+        self.fits.fit_context_menu_ui(ui, &self.name);
+        if self.fits.regression.menu_button(ui) {
+            self.submit(Job::RefitStored);
+        }
 
         // Add find peaks button
         ui.separator();
         ui.heading("Peak Finder");
         if ui.button("Detect Peaks")
             .on_hover_text("Takes the settings (adjust below) and finds peaks in the spectrum\nIf there are background markers, it will fit a background before it finds the peaks in between the min and max values. Likewise for region markers.\nKeybind: o").clicked() {
-            self.find_peaks();
+            self.submit(Job::FindPeaks);
+        }
+        if self.plot_settings.find_peaks_settings.menu_button(ui) {
+            self.submit(Job::FindPeaks);
         }
-        self.plot_settings.find_peaks_settings.menu_button(ui);
+
+        ui.separator();
+        self.efficiency_ui(ui);
 
         ui.separator();
         ui.heading("Rebin");
 
-        let possible_factors = self.possible_rebin_factors();
-
-        ui.label("Rebin Factor:");
-
-        ui.horizontal_wrapped(|ui| {
-            for &factor in &possible_factors {
-                if ui
-                    .selectable_label(
-                        self.plot_settings.rebin_factor == factor,
-                        format!("{}", factor),
-                    )
-                    .clicked()
-                {
-                    self.plot_settings.rebin_factor = factor;
-                    self.rebin();
-                }
+        self.rebin_ui(ui);
+        self.auto_range_ui(ui);
+
+        ui.separator();
+        ui.heading("Merge");
+        self.merge_ui(ui);
+
+        ui.separator();
+        if ui
+            .button("Export Data…")
+            .on_hover_text(
+                "Bin edges, centers, counts, and errors as CSV or JSON (by the file extension)",
+            )
+            .clicked()
+        {
+            if let Err(e) = self.export_data() {
+                log::error!("Error exporting '{}': {}", self.name, e);
             }
-        });
+            ui.close_menu();
+        }
+
+        if ui
+            .button("Export MCA Spectrum…")
+            .on_hover_text("ORTEC .Chn or .Spe with the axis calibration, for vendor software")
+            .clicked()
+        {
+            if let Err(e) = self.export_mca() {
+                log::error!("Error exporting '{}': {}", self.name, e);
+            }
+            ui.close_menu();
+        }
     }
 }
@@ -0,0 +1,84 @@
+use super::histogram1d::Histogram;
+use crate::histoer::linked_cursor::{share_cursor, shared_cursor};
+use crate::histoer::stats_box::{format_value, readout_box};
+
+impl Histogram {
+    // Shares the cursor with the other panes of a linked grid, or draws theirs
+    pub fn link_cursor(&mut self, plot_ui: &mut egui_plot::PlotUi) {
+        self.plot_settings.linked_x = None;
+        let Some(link) = self.plot_settings.cursor_link else {
+            return;
+        };
+
+        let log_x = self.plot_settings.egui_settings.log_x;
+        let ctx = plot_ui.ctx().clone();
+        if let Some(cursor) = self.plot_settings.cursor_position {
+            let x = if log_x {
+                10f64.powf(cursor.x)
+            } else {
+                cursor.x
+            };
+            share_cursor(&ctx, link, x);
+        } else if let Some(x) = shared_cursor(&ctx, link) {
+            let plot_x = if log_x {
+                x.max(f64::MIN_POSITIVE).log10()
+            } else {
+                x
+            };
+            plot_ui.vline(
+                egui_plot::VLine::new(plot_x)
+                    .color(plot_ui.ctx().style().visuals.weak_text_color())
+                    .width(1.0),
+            );
+            self.plot_settings.linked_x = Some(x);
+        }
+    }
+
+    // x, y, and bin content under the mouse, or at the linked cursor of another pane
+    pub fn show_crosshair(&self, ui: &egui::Ui, transform: &egui_plot::PlotTransform) {
+        if !self.plot_settings.crosshair {
+            return;
+        }
+
+        let settings = &self.plot_settings.egui_settings;
+        let (title, x, y) = match (
+            self.plot_settings.cursor_position,
+            self.plot_settings.linked_x,
+        ) {
+            (Some(cursor), _) => {
+                let x = if settings.log_x {
+                    10f64.powf(cursor.x)
+                } else {
+                    cursor.x
+                };
+                let y = if settings.log_y {
+                    10f64.powf(cursor.y)
+                } else {
+                    settings.y_transform.inverse(cursor.y)
+                };
+                ("Cursor", x, Some(y))
+            }
+            (None, Some(x)) => ("Linked Cursor", x, None),
+            (None, None) => return,
+        };
+
+        let bin = self
+            .get_bin_index(x)
+            .filter(|&index| index < self.bins.len());
+        let rows = [
+            ("X", format_value(x)),
+            ("Y", y.map(format_value).unwrap_or("-".to_string())),
+            (
+                "Bin",
+                bin.map(|index| index.to_string())
+                    .unwrap_or("-".to_string()),
+            ),
+            (
+                "Content",
+                bin.map(|index| format_value(self.bin_content(index)))
+                    .unwrap_or("-".to_string()),
+            ),
+        ];
+        readout_box(ui, *transform.frame(), title, &rows);
+    }
+}
@@ -0,0 +1,95 @@
+use super::histogram1d::Histogram;
+
+// Histograms with more bins than this are drawn decimated: the bins in view are split into
+// one group per pixel column and only the smallest and largest count of each group are drawn,
+// so every peak and valley stays visible while panning stays fast. The full line points are
+// kept for fits, figures, and exports.
+pub const DOWNSAMPLE_BINS: usize = 100_000;
+
+impl Histogram {
+    pub fn is_downsampled(&self) -> bool {
+        self.plot_settings.downsample && self.bins.len() > DOWNSAMPLE_BINS
+    }
+
+    // Points of the line in view with at most two per pixel column, None when the
+    // histogram is drawn at full resolution
+    pub fn downsampled_points(
+        &self,
+        plot_ui: &egui_plot::PlotUi,
+        content: impl Fn(usize) -> f64,
+    ) -> Option<Vec<[f64; 2]>> {
+        if !self.is_downsampled() {
+            return None;
+        }
+
+        let bounds = plot_ui.plot_bounds();
+        let (x_min, x_max) = if self.line.log_x {
+            (10f64.powf(bounds.min()[0]), 10f64.powf(bounds.max()[0]))
+        } else {
+            (bounds.min()[0], bounds.max()[0])
+        };
+
+        // one bin past each side of the view so the line reaches the plot edges
+        let n = self.bins.len();
+        let first = self.first_bin_above(x_min).saturating_sub(1);
+        let last = (self.first_bin_above(x_max) + 1).min(n);
+        if first >= last {
+            return Some(Vec::new());
+        }
+
+        let columns = (plot_ui.response().rect.width() as usize).max(1);
+        let per_column = (last - first).div_ceil(columns);
+        if per_column <= 2 {
+            return Some(
+                (first..last)
+                    .flat_map(|index| {
+                        let y_value = content(index);
+                        [
+                            [self.bin_edge(index), y_value],
+                            [self.bin_edge(index + 1), y_value],
+                        ]
+                    })
+                    .collect(),
+            );
+        }
+
+        let mut points = Vec::with_capacity(2 * (last - first).div_ceil(per_column));
+        for start in (first..last).step_by(per_column) {
+            let end = (start + per_column).min(last);
+            let (mut low, mut high) = ((start, content(start)), (start, content(start)));
+            for index in start + 1..end {
+                let y_value = content(index);
+                if y_value < low.1 {
+                    low = (index, y_value);
+                }
+                if y_value > high.1 {
+                    high = (index, y_value);
+                }
+            }
+
+            // in the order the bins come, so the line does not double back
+            let (a, b) = if low.0 <= high.0 {
+                (low, high)
+            } else {
+                (high, low)
+            };
+            points.push([self.bin_center(a.0), a.1]);
+            points.push([self.bin_center(b.0), b.1]);
+        }
+        Some(points)
+    }
+
+    // Index of the first bin whose upper edge is above x
+    fn first_bin_above(&self, x: f64) -> usize {
+        let (mut low, mut high) = (0, self.bins.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.bin_edge(mid + 1) <= x {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+}
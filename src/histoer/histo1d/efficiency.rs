@@ -0,0 +1,166 @@
+use super::histogram1d::Histogram;
+use crate::fitter::main_fitter::FitResult;
+
+impl Histogram {
+    // (bin center, corrected content, uncertainty) for the bins the efficiency is defined at
+    pub fn efficiency_corrected(&self) -> Vec<[f64; 3]> {
+        let curve = &self.plot_settings.efficiency;
+        (0..self.bins.len())
+            .filter_map(|index| {
+                let center = self.bin_center(index);
+                let efficiency = curve.evaluate(center).filter(|eff| *eff > 0.0)?;
+                Some([
+                    center,
+                    self.bin_content(index) / efficiency,
+                    self.bin_error(index) / efficiency,
+                ])
+            })
+            .collect()
+    }
+
+    pub fn draw_efficiency_corrected(&self, plot_ui: &mut egui_plot::PlotUi) {
+        let curve = &self.plot_settings.efficiency;
+        if !curve.show_corrected || !curve.is_fit() {
+            return;
+        }
+
+        let mut line = self.line.clone();
+        line.name = format!("{} (efficiency corrected)", self.name);
+        line.name_in_legend = true;
+        line.color = self.line.color.gamma_multiply(0.6);
+        line.stroke.color = line.color;
+        line.points = (0..self.bins.len())
+            .filter_map(|index| {
+                let efficiency = curve
+                    .evaluate(self.bin_center(index))
+                    .filter(|eff| *eff > 0.0)?;
+                let y_value = self.bin_content(index) / efficiency;
+                Some([
+                    [self.bin_edge(index), y_value],
+                    [self.bin_edge(index + 1), y_value],
+                ])
+            })
+            .flatten()
+            .collect();
+        line.draw(plot_ui);
+    }
+
+    // Peak areas of the stored fits divided by the efficiency at the centroid:
+    // (centroid, area, corrected area, corrected uncertainty, intensity relative to the largest in %)
+    pub fn relative_intensities(&self) -> Vec<[f64; 5]> {
+        let curve = &self.plot_settings.efficiency;
+        let mut peaks: Vec<[f64; 5]> = self
+            .fits
+            .stored_fits
+            .iter()
+            .filter_map(|fit| {
+                fit.fit_result
+                    .as_ref()
+                    .map(|FitResult::Gaussian(gaussian)| &gaussian.fit_result)
+            })
+            .flatten()
+            .filter_map(|peak| {
+                let mean = peak.mean.value?;
+                let area = peak.area.value?;
+                let efficiency = curve.evaluate(mean).filter(|eff| *eff > 0.0)?;
+                Some([
+                    mean,
+                    area,
+                    area / efficiency,
+                    peak.area.uncertainty.unwrap_or(0.0) / efficiency,
+                    0.0,
+                ])
+            })
+            .collect();
+
+        let max = peaks.iter().map(|peak| peak[2]).fold(0.0, f64::max);
+        if max > 0.0 {
+            for peak in &mut peaks {
+                peak[4] = 100.0 * peak[2] / max;
+            }
+        }
+        peaks.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        peaks
+    }
+
+    fn export_efficiency_corrected(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name(format!("{}_corrected.csv", self.name.replace('/', "_")))
+            .add_filter("CSV", &["csv"])
+            .save_file()
+        {
+            let mut csv = String::from("bin_center,corrected,uncertainty\n");
+            for [center, value, uncertainty] in self.efficiency_corrected() {
+                csv.push_str(&format!("{},{},{}\n", center, value, uncertainty));
+            }
+            std::fs::write(path, csv)?;
+        }
+        Ok(())
+    }
+
+    pub fn efficiency_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Efficiency");
+
+        ui.horizontal(|ui| {
+            self.plot_settings.efficiency.menu_button(ui);
+
+            if ui
+                .add_enabled(
+                    self.plot_settings.efficiency.is_fit(),
+                    egui::Button::new("Export Corrected…"),
+                )
+                .clicked()
+            {
+                if let Err(e) = self.export_efficiency_corrected() {
+                    log::error!("Error exporting corrected spectrum: {}", e);
+                }
+            }
+        });
+
+        if !self.plot_settings.efficiency.is_fit() || self.fits.stored_fits.is_empty() {
+            return;
+        }
+
+        ui.menu_button("Relative Intensities", |ui| {
+            let peaks = self.relative_intensities();
+
+            if ui
+                .button("Copy")
+                .on_hover_text(
+                    "Copy centroid, area, corrected area, uncertainty, relative intensity",
+                )
+                .clicked()
+            {
+                let text = peaks
+                    .iter()
+                    .map(|peak| {
+                        peak.iter()
+                            .map(|value| value.to_string())
+                            .collect::<Vec<_>>()
+                            .join("\t")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                ui.ctx().copy_text(text);
+            }
+
+            egui::Grid::new(format!("{}_relative_intensities", self.name))
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Centroid");
+                    ui.label("Area");
+                    ui.label("Corrected");
+                    ui.label("Relative [%]");
+                    ui.end_row();
+
+                    for [mean, area, corrected, uncertainty, relative] in &peaks {
+                        ui.label(format!("{:.2}", mean));
+                        ui.label(format!("{:.1}", area));
+                        ui.label(format!("{:.1} ± {:.1}", corrected, uncertainty));
+                        ui.label(format!("{:.2}", relative));
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+}
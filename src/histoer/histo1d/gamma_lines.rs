@@ -0,0 +1,176 @@
+use super::histogram1d::Histogram;
+use crate::util::nuclear_data::{self, GammaLine};
+
+// Gamma lines of chosen isotopes drawn on the spectrum, at their energy on a calibrated axis
+// (or at x when the axis is already in keV), and suggested energies for the fitted peaks
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct GammaLineSettings {
+    pub show: bool,
+    pub labels: bool,
+    pub isotopes: Vec<String>,
+    pub min_intensity: f64, // percent, weaker lines are not drawn or suggested
+    pub tolerance: f64,     // keV between a peak and a line for it to be suggested
+}
+
+impl Default for GammaLineSettings {
+    fn default() -> Self {
+        GammaLineSettings {
+            show: false,
+            labels: true,
+            isotopes: Vec::new(),
+            min_intensity: 1.0,
+            tolerance: 3.0,
+        }
+    }
+}
+
+const COLORS: [egui::Color32; 6] = [
+    egui::Color32::from_rgb(230, 120, 0),
+    egui::Color32::from_rgb(0, 150, 150),
+    egui::Color32::from_rgb(180, 0, 180),
+    egui::Color32::from_rgb(120, 120, 0),
+    egui::Color32::from_rgb(200, 30, 60),
+    egui::Color32::from_rgb(60, 90, 200),
+];
+
+impl GammaLineSettings {
+    fn lines(&self) -> impl Iterator<Item = &'static GammaLine> + '_ {
+        nuclear_data::lines_of(&self.isotopes).filter(|line| line.intensity >= self.min_intensity)
+    }
+}
+
+impl Histogram {
+    // keV per unit of the calibrated axis
+    fn kev_per_unit(&self) -> f64 {
+        let calibration = &self.plot_settings.calibration;
+        if calibration.active && calibration.unit.eq_ignore_ascii_case("MeV") {
+            1000.0
+        } else {
+            1.0
+        }
+    }
+
+    // Plot x of an energy in keV
+    fn gamma_line_x(&self, energy: f64) -> Option<f64> {
+        let value = energy / self.kev_per_unit();
+        let calibration = &self.plot_settings.calibration;
+        let x = if calibration.active {
+            calibration.uncalibrate(value)?
+        } else {
+            value
+        };
+
+        if self.line.log_x {
+            (x > 0.0).then(|| x.log10())
+        } else {
+            Some(x)
+        }
+    }
+
+    pub fn draw_gamma_lines(&self, plot_ui: &mut egui_plot::PlotUi) {
+        let settings = &self.plot_settings.gamma_lines;
+        if !settings.show {
+            return;
+        }
+
+        let top = plot_ui.plot_bounds().max()[1];
+        for line in settings.lines() {
+            let Some(x) = self.gamma_line_x(line.energy) else {
+                continue;
+            };
+            let index = settings
+                .isotopes
+                .iter()
+                .position(|isotope| *isotope == line.isotope)
+                .unwrap_or(0);
+            let color = COLORS[index % COLORS.len()];
+
+            // lines of an isotope share one legend entry
+            plot_ui.vline(
+                egui_plot::VLine::new(x)
+                    .color(color)
+                    .style(egui_plot::LineStyle::dashed_loose())
+                    .name(&line.isotope),
+            );
+            if settings.labels {
+                plot_ui.text(
+                    egui_plot::Text::new(
+                        egui_plot::PlotPoint::new(x, top),
+                        format!("{} {:.1}", line.isotope, line.energy),
+                    )
+                    .color(color)
+                    .anchor(egui::Align2::LEFT_TOP),
+                );
+            }
+        }
+    }
+
+    // Line of the chosen isotopes nearest to a peak at x, None when none is within the
+    // tolerance. Cloned out of the histogram so the fits can be edited while suggesting.
+    pub fn energy_suggester(&self) -> impl Fn(f64) -> Option<&'static GammaLine> {
+        let settings = self.plot_settings.gamma_lines.clone();
+        let calibration = self.plot_settings.calibration.clone();
+        let kev_per_unit = self.kev_per_unit();
+        move |x| {
+            let energy = if calibration.active {
+                calibration.calibrate(x)
+            } else {
+                x
+            } * kev_per_unit;
+            settings
+                .lines()
+                .filter(|line| (line.energy - energy).abs() <= settings.tolerance)
+                .min_by(|a, b| {
+                    (a.energy - energy)
+                        .abs()
+                        .total_cmp(&(b.energy - energy).abs())
+                })
+        }
+    }
+
+    pub fn gamma_lines_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Gamma Lines", |ui| {
+            let settings = &mut self.plot_settings.gamma_lines;
+            ui.label("Known lines of calibration and background sources in keV")
+                .on_hover_text("Drawn at their energy on the calibrated axis, or at x when the histogram is not calibrated");
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.show, "Show");
+                ui.checkbox(&mut settings.labels, "Labels");
+            });
+
+            ui.add(
+                egui::DragValue::new(&mut settings.min_intensity)
+                    .range(0.0..=100.0)
+                    .speed(0.1)
+                    .prefix("Min intensity: ")
+                    .suffix(" %"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut settings.tolerance)
+                    .range(0.0..=f64::INFINITY)
+                    .speed(0.1)
+                    .prefix("Suggest within: ")
+                    .suffix(" keV"),
+            )
+            .on_hover_text("Peaks this close to a line get it suggested as their energy in the Calibration menu");
+
+            ui.separator();
+
+            ui.horizontal_wrapped(|ui| {
+                for isotope in nuclear_data::isotopes() {
+                    let mut selected = settings.isotopes.iter().any(|i| i == isotope);
+                    if ui.toggle_value(&mut selected, isotope).changed() {
+                        if selected {
+                            settings.isotopes.push(isotope.to_string());
+                            settings.show = true;
+                        } else {
+                            settings.isotopes.retain(|i| i != isotope);
+                        }
+                    }
+                }
+            });
+        });
+    }
+}
@@ -3,6 +3,15 @@ use crate::egui_plot_stuff::egui_line::EguiLine;
 use crate::fitter::common::Data;
 use crate::fitter::fit_handler::Fits;
 use crate::fitter::main_fitter::{FitModel, Fitter};
+use crate::histoer::jobs::JobQueue;
+
+// Contents and uncertainties of the original bins for histograms made by
+// arithmetic, which can be negative or fractional
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+pub struct BinValues {
+    pub contents: Vec<f64>,
+    pub errors: Vec<f64>,
+}
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct Histogram {
@@ -16,6 +25,14 @@ pub struct Histogram {
     pub plot_settings: PlotSettings,
     pub fits: Fits,
     pub original_bins: Vec<u64>,
+    #[serde(default)]
+    pub edges: Vec<f64>, // bin edges of the original bins for variable width bins, empty for fixed width bins
+    #[serde(default)]
+    pub values: Option<BinValues>, // bins holds the rounded contents when set
+    #[serde(skip)]
+    pub thumbnail: (u64, Vec<f32>), // total counts the thumbnail was made from, downsampled counts
+    #[serde(skip)]
+    pub jobs: JobQueue,
 }
 
 impl Histogram {
@@ -35,22 +52,165 @@ impl Histogram {
             plot_settings: PlotSettings::default(),
             fits: Fits::new(),
             original_bins: vec![0; number_of_bins],
+            edges: Vec::new(),
+            values: None,
+            thumbnail: (0, Vec::new()),
+            jobs: JobQueue::default(),
+        }
+    }
+
+    // The legend entry of the line follows the name
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+        self.line.name = name.to_string();
+    }
+
+    // Create a new Histogram from explicit bin edges, e.g. log spaced or custom bins
+    pub fn with_edges(name: &str, edges: &[f64]) -> Self {
+        let range = (edges[0], edges[edges.len() - 1]);
+        let mut hist = Histogram::new(name, edges.len() - 1, range);
+        hist.edges = edges.to_vec();
+        hist
+    }
+
+    // Content of a bin of the current bins, summed over the original bins when rebinned
+    pub fn bin_content(&self, index: usize) -> f64 {
+        match &self.values {
+            Some(values) => {
+                let factor = self.plot_settings.rebin_factor.max(1);
+                let end = ((index + 1) * factor).min(values.contents.len());
+                values.contents[(index * factor).min(end)..end].iter().sum()
+            }
+            None => self.bins.get(index).copied().unwrap_or(0) as f64,
+        }
+    }
+
+    // Uncertainty of a bin, Poisson for filled histograms
+    pub fn bin_error(&self, index: usize) -> f64 {
+        match &self.values {
+            Some(values) => {
+                let factor = self.plot_settings.rebin_factor.max(1);
+                let end = ((index + 1) * factor).min(values.errors.len());
+                values.errors[(index * factor).min(end)..end]
+                    .iter()
+                    .map(|error| error * error)
+                    .sum::<f64>()
+                    .sqrt()
+            }
+            None => self.bin_content(index).sqrt(),
+        }
+    }
+
+    // Contents and uncertainties of the original bins
+    pub fn original_values(&self) -> BinValues {
+        match &self.values {
+            Some(values) => values.clone(),
+            None => BinValues {
+                contents: self
+                    .original_bins
+                    .iter()
+                    .map(|&count| count as f64)
+                    .collect(),
+                errors: self
+                    .original_bins
+                    .iter()
+                    .map(|&count| (count as f64).sqrt())
+                    .collect(),
+            },
+        }
+    }
+
+    pub fn set_values(&mut self, values: BinValues) {
+        self.original_bins = values
+            .contents
+            .iter()
+            .map(|content| content.round().max(0.0) as u64)
+            .collect();
+        self.values = Some(values);
+        self.rebin();
+    }
+
+    pub fn is_variable_width(&self) -> bool {
+        !self.edges.is_empty()
+    }
+
+    // Lower edge of a bin of the current (possibly rebinned) bins, the last bin
+    // is narrower when the rebin factor does not divide the number of bins
+    pub fn bin_edge(&self, index: usize) -> f64 {
+        if self.edges.is_empty() {
+            (self.range.0 + index as f64 * self.bin_width).min(self.range.1)
+        } else {
+            let factor = self.plot_settings.rebin_factor.max(1);
+            self.edges[(index * factor).min(self.edges.len() - 1)]
+        }
+    }
+
+    pub fn bin_center(&self, index: usize) -> f64 {
+        0.5 * (self.bin_edge(index) + self.bin_edge(index + 1))
+    }
+
+    // Mean bin width between two x values, the fixed bin width for fixed width bins
+    pub fn mean_bin_width(&self, start_x: f64, end_x: f64) -> f64 {
+        if self.edges.is_empty() || self.bins.is_empty() {
+            return self.bin_width;
         }
+
+        let start_bin = self.get_bin_index(start_x).unwrap_or(0);
+        let end_bin = self
+            .get_bin_index(end_x)
+            .unwrap_or(self.bins.len() - 1)
+            .max(start_bin);
+        (self.bin_edge(end_bin + 1) - self.bin_edge(start_bin)) / (end_bin + 1 - start_bin) as f64
+    }
+
+    // Position `offset` bins above the bin containing start_x
+    pub fn position_from(&self, start_x: f64, offset: f64) -> f64 {
+        if self.edges.is_empty() {
+            return start_x + self.bin_width * offset;
+        }
+
+        let index = self.get_bin_index(start_x).unwrap_or(0) + offset.floor().max(0.0) as usize;
+        let low = self.bin_edge(index);
+        let high = self.bin_edge(index + 1);
+        low + offset.fract() * (high - low)
     }
 
     pub fn reset(&mut self) {
         self.bins = vec![0; self.bins.len()];
         self.original_bins = vec![0; self.original_bins.len()];
+        if let Some(values) = &mut self.values {
+            values
+                .contents
+                .iter_mut()
+                .for_each(|content| *content = 0.0);
+            values.errors.iter_mut().for_each(|error| *error = 0.0);
+        }
         self.overflow = 0;
         self.underflow = 0;
     }
 
     pub fn fill(&mut self, value: f64) {
+        if self.plot_settings.auto_range.enabled && !(self.range.0..self.range.1).contains(&value) {
+            self.extend_range(value);
+        }
+
         if value >= self.range.0 && value < self.range.1 {
-            let index = ((value - self.range.0) / self.bin_width) as usize;
-            if index < self.bins.len() {
-                self.bins[index] += 1;
+            // fill the original bins and the matching rebinned bin
+            let index = if self.edges.is_empty() {
+                let original_width =
+                    (self.range.1 - self.range.0) / self.original_bins.len() as f64;
+                ((value - self.range.0) / original_width) as usize
+            } else {
+                self.edges.partition_point(|&edge| edge <= value) - 1
+            };
+            if index < self.original_bins.len() {
                 self.original_bins[index] += 1;
+                if let Some(bin) = self
+                    .bins
+                    .get_mut(index / self.plot_settings.rebin_factor.max(1))
+                {
+                    *bin += 1;
+                }
             }
         } else if value >= self.range.1 {
             self.overflow += 1;
@@ -63,10 +223,36 @@ impl Histogram {
         self.bins = counts;
     }
 
+    // Downsampled counts for the tree thumbnail, recomputed only when the counts change
+    pub fn thumbnail_points(&mut self) -> &[f32] {
+        const THUMBNAIL_POINTS: usize = 64;
+
+        let total: u64 = self.bins.iter().sum();
+        if self.thumbnail.0 != total || self.thumbnail.1.is_empty() {
+            let chunk_size = self.bins.len().div_ceil(THUMBNAIL_POINTS).max(1);
+            self.thumbnail = (
+                total,
+                self.bins
+                    .chunks(chunk_size)
+                    .map(|chunk| chunk.iter().sum::<u64>() as f32)
+                    .collect(),
+            );
+        }
+
+        &self.thumbnail.1
+    }
+
     pub fn get_bin_edges(&self) -> Vec<f64> {
-        (0..=self.bins.len())
-            .map(|i| self.range.0 + i as f64 * self.bin_width)
-            .collect()
+        (0..=self.bins.len()).map(|i| self.bin_edge(i)).collect()
+    }
+
+    // Edges of the current bins for file export, empty for fixed width bins
+    pub fn export_edges(&self) -> Vec<f64> {
+        if self.is_variable_width() {
+            self.get_bin_edges()
+        } else {
+            Vec::new()
+        }
     }
 
     pub fn update_line_points(&mut self) {
@@ -74,11 +260,11 @@ impl Histogram {
             .bins
             .iter()
             .enumerate()
-            .flat_map(|(index, &count)| {
-                let start = self.range.0 + index as f64 * self.bin_width;
-                let end = start + self.bin_width;
-                let y_value = count as f64;
-                vec![[start, y_value], [end, y_value]]
+            .flat_map(|(index, _)| {
+                let start = self.bin_edge(index);
+                let end = self.bin_edge(index + 1);
+                let y_value = self.bin_content(index);
+                [[start, y_value], [end, y_value]]
             })
             .collect();
     }
@@ -88,17 +274,48 @@ impl Histogram {
             return None;
         }
 
+        if self.is_variable_width() {
+            let edges = self.get_bin_edges();
+            let bin_index = edges.partition_point(|&edge| edge <= x).saturating_sub(1);
+            return Some(bin_index.min(self.bins.len().saturating_sub(1)));
+        }
+
         let bin_index: usize = ((x - self.range.0) / self.bin_width).floor() as usize;
 
         Some(bin_index)
     }
 
+    // Returns the center of the bin with the most counts within `window` bins of x
+    pub fn snap_to_local_maximum(&self, x: f64, window: usize) -> f64 {
+        let Some(index) = self.get_bin_index(x) else {
+            return x;
+        };
+        if self.bins.is_empty() {
+            return x;
+        }
+
+        let index = index.min(self.bins.len() - 1);
+        let start = index.saturating_sub(window);
+        let end = (index + window).min(self.bins.len() - 1);
+
+        // prefer the bin closest to the cursor when several share the maximum
+        let max_index = (start..=end)
+            .max_by(|&a, &b| {
+                self.bins[a]
+                    .cmp(&self.bins[b])
+                    .then_with(|| index.abs_diff(b).cmp(&index.abs_diff(a)))
+            })
+            .unwrap_or(index);
+
+        self.bin_center(max_index)
+    }
+
     pub fn get_bin_centers_between(&self, start_x: f64, end_x: f64) -> Vec<f64> {
         let start_bin = self.get_bin_index(start_x).unwrap_or(0);
         let end_bin = self.get_bin_index(end_x).unwrap_or(self.bins.len() - 1);
 
         (start_bin..=end_bin)
-            .map(|bin| self.range.0 + (bin as f64 * self.bin_width) + self.bin_width * 0.5)
+            .map(|bin| self.bin_center(bin))
             .collect()
     }
 
@@ -107,14 +324,14 @@ impl Histogram {
         let end_bin = self.get_bin_index(end_x).unwrap_or(self.bins.len() - 1);
 
         (start_bin..=end_bin)
-            .map(|bin| self.bins[bin] as f64)
+            .map(|bin| self.bin_content(bin))
             .collect()
     }
 
     pub fn get_bin_count_and_center(&self, x: f64) -> Option<(f64, f64)> {
         self.get_bin_index(x).map(|bin| {
-            let bin_center = self.range.0 + (bin as f64 * self.bin_width) + self.bin_width * 0.5;
-            let bin_count = self.bins[bin] as f64;
+            let bin_center = self.bin_center(bin);
+            let bin_count = self.bin_content(bin);
             (bin_center, bin_count)
         })
     }
@@ -190,10 +407,12 @@ impl Histogram {
 
         let equal_stdev = self.fits.settings.equal_stddev;
         let free_position = self.fits.settings.free_position;
-        let bin_width = self.bin_width;
+        let bin_width = self.mean_bin_width(start_x, end_x);
 
         fitter.background_model = background_model;
         fitter.background_result = background_result;
+        fitter.backend = self.fits.settings.backend;
+        fitter.shape = self.fits.settings.shape;
 
         fitter.fit_model = FitModel::Gaussian(
             peak_positions.clone(),
@@ -203,6 +422,7 @@ impl Histogram {
         );
 
         fitter.fit();
+        fitter.update_fit_points(&self.fits.settings);
 
         self.plot_settings.markers.clear_peak_markers();
         let updated_markers = fitter.get_peak_markers();
@@ -214,20 +434,98 @@ impl Histogram {
         self.fits.temp_fit = Some(fitter);
     }
 
+    // Re-run the stored fits on the current counts and compare them to the stored results
+    pub fn refit_stored_fits(&mut self) {
+        let mut refits = Vec::new();
+
+        for stored in &self.fits.stored_fits {
+            let (Some(&start_x), Some(&end_x)) = (stored.data.x.first(), stored.data.x.last())
+            else {
+                continue;
+            };
+
+            let mut fitter = Fitter::new(Data {
+                x: self.get_bin_centers_between(start_x, end_x),
+                y: self.get_bin_counts_between(start_x, end_x),
+            });
+            fitter.background_model = stored.background_model.clone();
+            fitter.backend = stored.backend;
+            fitter.shape = stored.shape;
+
+            // backgrounds fit to markers are refit at the same positions
+            if let Some(background_result) = &stored.background_result {
+                let (x, y): (Vec<f64>, Vec<f64>) = background_result
+                    .data()
+                    .x
+                    .iter()
+                    .filter_map(|&pos| self.get_bin_count_and_center(pos))
+                    .unzip();
+
+                if !x.is_empty() {
+                    let mut background = Fitter::new(Data { x, y });
+                    background.background_model = stored.background_model.clone();
+                    background.fit_background();
+                    fitter.background_result = background.background_result;
+                    fitter.background_line.points = background.background_line.points;
+                }
+            }
+
+            fitter.fit_model = match &stored.fit_model {
+                FitModel::Gaussian(peak_markers, equal_stdev, free_position, _) => {
+                    FitModel::Gaussian(
+                        peak_markers.clone(),
+                        *equal_stdev,
+                        *free_position,
+                        self.mean_bin_width(start_x, end_x),
+                    )
+                }
+                FitModel::None => FitModel::None,
+            };
+
+            fitter.fit();
+            fitter.update_fit_points(&self.fits.settings);
+            fitter.set_background_color(stored.background_line.color);
+            fitter.set_composition_color(stored.composition_line.color);
+            if let Some(line) = stored.decomposition_lines.first() {
+                fitter.set_decomposition_color(line.color);
+            }
+            fitter.set_name(stored.name.clone());
+
+            refits.push(fitter);
+        }
+
+        let stored_fits = self.fits.stored_fits.clone();
+        self.fits.regression.compare(&stored_fits, refits);
+    }
+
     pub fn draw(&mut self, plot_ui: &mut egui_plot::PlotUi) {
         // update the histogram and fit lines with the log setting and draw
         let log_y = self.plot_settings.egui_settings.log_y;
         let log_x = self.plot_settings.egui_settings.log_x;
 
+        let y_transform = self.plot_settings.egui_settings.y_transform;
+
         self.line.log_y = log_y;
         self.line.log_x = log_x;
-        self.line.draw(plot_ui);
+        self.line.y_transform = y_transform;
+        self.draw_stack(plot_ui);
+        if let Some(points) = self.downsampled_points(plot_ui, |index| self.bin_content(index)) {
+            let full = std::mem::replace(&mut self.line.points, points);
+            self.line.draw(plot_ui);
+            self.line.points = full;
+        } else {
+            self.line.draw(plot_ui);
+        }
+        self.draw_error_band(plot_ui);
+        self.draw_reference(plot_ui);
+        self.draw_efficiency_corrected(plot_ui);
+        self.draw_gamma_lines(plot_ui);
+        self.draw_annotations(plot_ui);
 
         self.fits.set_log(log_y, log_x);
+        self.fits.set_y_transform(y_transform);
         self.fits.draw(plot_ui);
 
-        self.show_stats(plot_ui);
-
         self.plot_settings.markers.draw_all_markers(plot_ui);
         // Check if markers are being dragged
         if self.plot_settings.markers.is_dragging() {
@@ -243,9 +541,16 @@ impl Histogram {
         } else {
             self.plot_settings.cursor_position = None;
         }
+        self.link_cursor(plot_ui);
 
-        if self.plot_settings.egui_settings.reset_axis {
+        if self.plot_settings.egui_settings.update_bounds(plot_ui) {
+            // manual bounds take priority over resetting and clamping
+        } else if self.plot_settings.egui_settings.reset_axis {
             self.plot_settings.egui_settings.reset_axis_lims(plot_ui);
+            let settings = &self.plot_settings.egui_settings;
+            if settings.log_y && settings.decade_bounds && !settings.lock_bounds {
+                plot_ui.set_plot_bounds(self.data_bounds());
+            }
         } else {
             self.limit_scrolling(plot_ui);
         }
@@ -253,6 +558,36 @@ impl Histogram {
         // self.plot_settings.egui_settings.y_label = format!("Counts/{:.}", self.bin_width);
     }
 
+    // Uncertainty band for histograms made by arithmetic
+    fn draw_error_band(&self, plot_ui: &mut egui_plot::PlotUi) {
+        if self.values.is_none() || !self.line.draw {
+            return;
+        }
+
+        for sign in [-1.0, 1.0] {
+            let mut band = self.line.clone();
+            band.name = format!("{} Uncertainty", self.name);
+            band.name_in_legend = false;
+            band.color = self.line.color.gamma_multiply(0.4);
+            band.stroke.color = band.color;
+            let content = |index| self.bin_content(index) + sign * self.bin_error(index);
+            band.points = self
+                .downsampled_points(plot_ui, content)
+                .unwrap_or_else(|| {
+                    (0..self.bins.len())
+                        .flat_map(|index| {
+                            let y_value = content(index);
+                            [
+                                [self.bin_edge(index), y_value],
+                                [self.bin_edge(index + 1), y_value],
+                            ]
+                        })
+                        .collect()
+                });
+            band.draw(plot_ui);
+        }
+    }
+
     pub fn draw_other_histograms(
         &mut self,
         plot_ui: &mut egui_plot::PlotUi,
@@ -264,6 +599,40 @@ impl Histogram {
         }
     }
 
+    // Extent of the data in plot coordinates. With log y and decade bounds the counts are
+    // taken out to whole decades, e.g. 1 to 10^4 for a peak of 3000 counts.
+    pub fn data_bounds(&self) -> egui_plot::PlotBounds {
+        let settings = &self.plot_settings.egui_settings;
+
+        let (x_min, x_max) = if settings.log_x {
+            let low = self.range.0.max(0.5 * self.bin_width.min(self.range.1));
+            (low.max(1e-10).log10(), self.range.1.max(1e-10).log10())
+        } else {
+            self.range
+        };
+
+        let y_max = self.bins.iter().max().cloned().unwrap_or(0) as f64;
+        let (y_min, y_max) = if settings.log_y {
+            let positive_min = self.bins.iter().filter(|&&c| c > 0).min().cloned();
+            let low = positive_min.unwrap_or(1) as f64;
+            let high = y_max.max(low);
+            if settings.decade_bounds {
+                let low = low.log10().floor();
+                (low, high.log10().ceil().max(low + 1.0))
+            } else {
+                (low.log10() - 0.1, high.log10() + 0.1)
+            }
+        } else {
+            let y_min = self.bins.iter().min().cloned().unwrap_or(0) as f64;
+            (
+                settings.y_transform.forward(y_min),
+                settings.y_transform.forward(y_max),
+            )
+        };
+
+        egui_plot::PlotBounds::from_min_max([x_min, y_min], [x_max, y_max])
+    }
+
     pub fn limit_scrolling(&self, plot_ui: &mut egui_plot::PlotUi) {
         let plot_bounds = plot_ui.plot_bounds();
 
@@ -272,26 +641,36 @@ impl Histogram {
         let current_y_min = plot_bounds.min()[1];
         let current_y_max = plot_bounds.max()[1];
 
-        let y_max = self.bins.iter().max().cloned().unwrap_or(0) as f64;
-        let y_min = self.bins.iter().min().cloned().unwrap_or(0) as f64;
-
         if current_x_min == -1.0
             && current_x_max == 1.0
             && current_y_min == 0.0
             && current_y_max == 1.0
         {
-            let default_bounds =
-                egui_plot::PlotBounds::from_min_max([self.range.0, y_min], [self.range.1, y_max]);
-
-            plot_ui.set_plot_bounds(default_bounds);
+            plot_ui.set_plot_bounds(self.data_bounds());
             return;
         }
 
-        // Clamping bounds only for scrolling
-        let new_x_min = current_x_min.max(self.range.0 * 1.1);
-        let new_x_max = current_x_max.min(self.range.1 * 1.1);
-        let new_y_min = current_y_min.max(y_min * 1.1);
-        let new_y_max = current_y_max.min(y_max * 1.1);
+        // Clamping bounds only for scrolling, log axes are clamped a tenth of the
+        // data extent outside of it
+        let data = self.data_bounds();
+        let settings = &self.plot_settings.egui_settings;
+        let (x_low, x_high) = if settings.log_x {
+            let margin = 0.1 * data.width();
+            (data.min()[0] - margin, data.max()[0] + margin)
+        } else {
+            (self.range.0 * 1.1, self.range.1 * 1.1)
+        };
+        let (y_low, y_high) = if settings.log_y {
+            let margin = 0.1 * data.height();
+            (data.min()[1] - margin, data.max()[1] + margin)
+        } else {
+            (data.min()[1] * 1.1, data.max()[1] * 1.1)
+        };
+
+        let new_x_min = current_x_min.max(x_low);
+        let new_x_max = current_x_max.min(x_high);
+        let new_y_min = current_y_min.max(y_low);
+        let new_y_max = current_y_max.min(y_high);
 
         if new_x_min != current_x_min
             || new_x_max != current_x_max
@@ -309,13 +688,39 @@ impl Histogram {
         // disabled since the row calculation is done in chucks
         // self.plot_settings.progress_ui(ui);
 
+        self.run_queued_jobs();
         self.update_line_points(); // Ensure line points are updated for projections
         self.keybinds(ui); // Handle interactive elements
 
+        let calibration = &self.plot_settings.calibration;
+        self.plot_settings.egui_settings.x_calibration =
+            calibration.active.then(|| calibration.coefficients());
+        self.fits.calibration = calibration.active.then(|| calibration.clone());
+
         let mut plot = egui_plot::Plot::new(self.name.clone());
         plot = self.plot_settings.egui_settings.apply_to_plot(plot);
 
+        // the comparison plot takes the bottom of the pane, with the x axis linked
+        let comparison_link = egui::Id::new(&self.name).with("comparison");
+        if self.comparison_active() {
+            plot = plot
+                .link_axis(comparison_link, egui::Vec2b::new(true, false))
+                .link_cursor(comparison_link, egui::Vec2b::new(true, false));
+        } else if let Some((link, axes)) = self.plot_settings.axis_link {
+            // a plot has one axis link, the comparison keeps its own
+            plot = plot.link_axis(link, axes);
+        }
+
+        self.jobs.status_ui(ui);
+        self.auto_range_status_ui(ui);
+        self.rebin_status_ui(ui);
         self.fits.fit_stats_ui(ui);
+        self.region_estimates_ui(ui);
+        self.comparison_stats_ui(ui);
+        if let Some(refits) = self.fits.regression.ui(ui, &self.name) {
+            self.fits.stored_fits = refits;
+        }
+        self.plot_settings.notes.ui(ui, &self.name);
 
         let (scroll, _pointer_down, _modifiers) = ui.input(|i| {
             let scroll = i.events.iter().find_map(|e| match e {
@@ -329,10 +734,16 @@ impl Histogram {
             (scroll, i.pointer.primary_down(), i.modifiers)
         });
 
+        if self.comparison_active() {
+            plot = plot.height(0.7 * ui.available_height());
+        }
+
         let plot_response = plot.show(ui, |plot_ui| {
             self.draw(plot_ui);
 
-            if self.plot_settings.progress.is_some() {
+            if self.plot_settings.progress.is_some()
+                && !self.plot_settings.egui_settings.lock_bounds
+            {
                 let y_max = self.bins.iter().max().cloned().unwrap_or(0) as f64;
                 let mut plot_bounds = plot_ui.plot_bounds();
                 plot_bounds.extend_with_y(y_max * 1.1);
@@ -340,11 +751,12 @@ impl Histogram {
             }
 
             if self.plot_settings.egui_settings.reset_axis {
-                plot_ui.auto_bounds();
-                self.plot_settings.egui_settings.reset_axis = false;
+                self.plot_settings.egui_settings.reset_axis_lims(plot_ui);
             }
 
-            if self.plot_settings.cursor_position.is_some() {
+            if self.plot_settings.cursor_position.is_some()
+                && !self.plot_settings.egui_settings.lock_bounds
+            {
                 if let Some(delta_pos) = scroll {
                     if delta_pos.y > 0.0 {
                         plot_ui.zoom_bounds_around_hovered(egui::Vec2::new(1.1, 1.0));
@@ -359,6 +771,17 @@ impl Histogram {
             }
         });
 
+        if let Some(fraction) = self.plot_settings.preview {
+            crate::histoer::preview::watermark(ui, plot_response.response.rect, fraction);
+        }
+
+        self.show_stats(ui, &plot_response.transform);
+        self.show_crosshair(ui, &plot_response.transform);
+
+        if self.comparison_active() {
+            self.comparison_plot(ui, comparison_link);
+        }
+
         plot_response.response.context_menu(|ui| {
             self.context_menu(ui);
         });
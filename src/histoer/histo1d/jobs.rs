@@ -0,0 +1,28 @@
+use super::histogram1d::Histogram;
+use crate::histoer::jobs::Job;
+
+impl Histogram {
+    // Runs the job now, or queues it while the histogram is being filled
+    pub fn submit(&mut self, job: Job) {
+        if let Some(job) = self.jobs.submit(job) {
+            self.run_job(job);
+        }
+    }
+
+    pub fn run_queued_jobs(&mut self) {
+        for job in self.jobs.take_ready() {
+            self.run_job(job);
+        }
+    }
+
+    fn run_job(&mut self, job: Job) {
+        match job {
+            Job::FitBackground => self.fit_background(),
+            Job::FitGaussians => self.fit_gaussians(),
+            Job::RefitStored => self.refit_stored_fits(),
+            Job::FindPeaks => self.find_peaks(),
+            Job::Rebin(factor) => self.set_rebin_factor(factor),
+            Job::Rebin2D(..) => {}
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use super::histogram1d::Histogram;
+use crate::histoer::jobs::Job;
 
 impl Histogram {
     // Handles the interactive elements of the histogram
@@ -7,9 +8,15 @@ impl Histogram {
 
         if let Some(cursor_position) = self.plot_settings.cursor_position {
             if ui.input(|i| i.key_pressed(egui::Key::P)) {
-                self.plot_settings
-                    .markers
-                    .add_peak_marker(cursor_position.x);
+                let x = if self.plot_settings.markers.snap_to_peak {
+                    self.snap_to_local_maximum(
+                        cursor_position.x,
+                        self.plot_settings.markers.snap_window,
+                    )
+                } else {
+                    cursor_position.x
+                };
+                self.plot_settings.markers.add_peak_marker(x);
             }
 
             if ui.input(|i| i.key_pressed(egui::Key::B)) {
@@ -42,11 +49,11 @@ impl Histogram {
             }
 
             if ui.input(|i| i.key_pressed(egui::Key::G)) {
-                self.fit_background();
+                self.submit(Job::FitBackground);
             }
 
             if ui.input(|i| i.key_pressed(egui::Key::F)) {
-                self.fit_gaussians();
+                self.submit(Job::FitGaussians);
             }
 
             if ui.input(|i| i.key_pressed(egui::Key::S)) {
@@ -59,10 +66,19 @@ impl Histogram {
 
             if ui.input(|i| i.key_pressed(egui::Key::L)) {
                 self.plot_settings.egui_settings.log_y = !self.plot_settings.egui_settings.log_y;
+                self.plot_settings.egui_settings.reset_axis = true;
             }
 
             if ui.input(|i| i.key_pressed(egui::Key::O)) {
-                self.find_peaks();
+                self.submit(Job::FindPeaks);
+            }
+
+            if ui.input(|i| i.key_pressed(egui::Key::CloseBracket)) {
+                self.submit(Job::Rebin(self.plot_settings.rebin_factor * 2));
+            }
+
+            if ui.input(|i| i.key_pressed(egui::Key::OpenBracket)) {
+                self.submit(Job::Rebin(self.plot_settings.rebin_factor / 2));
             }
         }
     }
@@ -92,6 +108,8 @@ impl Histogram {
                 ui.label("Plot");
                 ui.label("I: Toggle Stats");
                 ui.label("L: Toggle Log Y");
+                ui.label("]: Rebin x2").on_hover_text("Merge neighbouring bins, the original bins are kept");
+                ui.label("[: Undo x2 Rebin");
                 ui.separator();
                 ui.label("Peak Finder");
                 ui.label("O: Detect Peaks").on_hover_text("Detect peaks in the spectrum using the peak finding parameters");
@@ -1,12 +1,35 @@
 use crate::egui_plot_stuff::egui_vertical_line::EguiVerticalLine;
 use egui_plot::{PlotPoint, PlotUi};
 
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+// Marker positions saved under a name, kept apart from the fits so clearing
+// a fit or the markers does not lose them
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MarkerSet {
+    pub name: String,
+    pub region: Vec<f64>,
+    pub peak: Vec<f64>,
+    pub background: Vec<f64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct FitMarkers {
     pub region_markers: Vec<EguiVerticalLine>,
     pub peak_markers: Vec<EguiVerticalLine>,
     pub background_markers: Vec<EguiVerticalLine>,
 
+    #[serde(default)]
+    pub snap_to_peak: bool, // move new peak markers to the local maximum
+    #[serde(default = "default_snap_window")]
+    pub snap_window: usize, // number of bins searched on each side of the cursor
+
+    #[serde(default)]
+    pub sets: Vec<MarkerSet>,
+    #[serde(skip)]
+    pub set_name: String,
+    #[serde(skip)]
+    pub shared: Vec<MarkerSet>, // sets waiting to be copied to the rest of the family
+
     #[serde(skip)]
     pub cursor_position: Option<PlotPoint>,
 
@@ -14,6 +37,27 @@ pub struct FitMarkers {
     pub manual_marker_position: f64,
 }
 
+impl Default for FitMarkers {
+    fn default() -> Self {
+        Self {
+            region_markers: Vec::new(),
+            peak_markers: Vec::new(),
+            background_markers: Vec::new(),
+            snap_to_peak: false,
+            snap_window: 3,
+            sets: Vec::new(),
+            set_name: String::new(),
+            shared: Vec::new(),
+            cursor_position: None,
+            manual_marker_position: 0.0,
+        }
+    }
+}
+
+fn default_snap_window() -> usize {
+    3
+}
+
 impl FitMarkers {
     pub fn new() -> Self {
         Self::default()
@@ -139,6 +183,100 @@ impl FitMarkers {
         });
     }
 
+    // Saves the current markers, replacing a set with the same name
+    pub fn save_set(&mut self, name: &str) {
+        let set = MarkerSet {
+            name: name.to_string(),
+            region: self.get_region_marker_positions(),
+            peak: self.get_peak_marker_positions(),
+            background: self.get_background_marker_positions(),
+        };
+        self.insert_set(set);
+    }
+
+    pub fn insert_set(&mut self, set: MarkerSet) {
+        match self
+            .sets
+            .iter_mut()
+            .find(|existing| existing.name == set.name)
+        {
+            Some(existing) => *existing = set,
+            None => self.sets.push(set),
+        }
+    }
+
+    // Replaces the current markers with the ones in the set
+    pub fn restore_set(&mut self, set: &MarkerSet) {
+        self.clear_region_markers();
+        self.clear_peak_markers();
+        self.clear_background_markers();
+
+        for &x in &set.region {
+            self.add_region_marker(x);
+        }
+        for &x in &set.peak {
+            self.add_peak_marker(x);
+        }
+        for &x in &set.background {
+            self.add_background_marker(x);
+        }
+    }
+
+    fn marker_sets_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Marker Sets");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.set_name)
+                    .hint_text("Name")
+                    .desired_width(100.0),
+            );
+            if ui
+                .add_enabled(!self.set_name.is_empty(), egui::Button::new("Save"))
+                .on_hover_text("Save the current markers, a set with the same name is replaced")
+                .clicked()
+            {
+                let name = self.set_name.clone();
+                self.save_set(&name);
+            }
+        });
+
+        let mut restore = None;
+        let mut remove = None;
+        for (index, set) in self.sets.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(&set.name).on_hover_text(format!(
+                    "{} region, {} peak, {} background markers",
+                    set.region.len(),
+                    set.peak.len(),
+                    set.background.len()
+                ));
+
+                if ui.button("Restore").clicked() {
+                    restore = Some(set.clone());
+                }
+
+                if ui
+                    .button("Share")
+                    .on_hover_text("Copy the set to the other histograms in the same folder")
+                    .clicked()
+                {
+                    self.shared.push(set.clone());
+                }
+
+                if ui.button("🗙").clicked() {
+                    remove = Some(index);
+                }
+            });
+        }
+
+        if let Some(set) = restore {
+            self.restore_set(&set);
+        }
+        if let Some(index) = remove {
+            self.sets.remove(index);
+        }
+    }
+
     pub fn draw_all_markers(&mut self, plot_ui: &mut PlotUi) {
         for marker in &mut self.background_markers {
             marker.draw(plot_ui);
@@ -197,6 +335,21 @@ impl FitMarkers {
                     }
                 });
 
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.snap_to_peak, "Snap to Peak")
+                        .on_hover_text(
+                        "Peak markers placed with 'P' move to the local maximum within the window",
+                    );
+                    ui.add_enabled(
+                        self.snap_to_peak,
+                        egui::DragValue::new(&mut self.snap_window)
+                            .range(1..=usize::MAX)
+                            .speed(1)
+                            .prefix("±")
+                            .suffix(" bins"),
+                    );
+                });
+
                 ui.separator();
 
                 ui.horizontal(|ui| {
@@ -220,6 +373,10 @@ impl FitMarkers {
                         self.clear_background_markers();
                     }
                 });
+
+                ui.separator();
+
+                self.marker_sets_ui(ui);
             });
 
             ui.separator();
@@ -1,8 +1,18 @@
+pub mod annotations;
+pub mod auto_range;
+pub mod calibration;
+pub mod comparison;
 pub mod context_menu;
+pub mod crosshair;
+pub mod downsample;
+pub mod efficiency;
+pub mod gamma_lines;
 pub mod histogram1d;
+pub mod jobs;
 pub mod keybinds;
 pub mod markers;
 pub mod peak_finder;
 pub mod plot_settings;
 pub mod rebinning;
+pub mod stack;
 pub mod statistics;
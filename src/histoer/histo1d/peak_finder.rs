@@ -1,4 +1,3 @@
-use find_peaks::Peak;
 use find_peaks::PeakFinder;
 
 use super::histogram1d::Histogram;
@@ -66,25 +65,23 @@ impl Histogram {
         if let Some(y_data) = y_data {
             let peaks = self.plot_settings.find_peaks_settings.find_peaks(y_data);
             // Add peak markers at detected peaks
-            for peak in &peaks {
-                let peak_position = peak.middle_position();
+            for &peak_position in &peaks {
                 log::info!("Peak at position: {}", peak_position);
                 // Adjust peak position relative to the first background marker
                 if peaks_found_with_background {
                     let adjusted_peak_position =
-                        self.bin_width * peak_position as f64 + background_marker_positions[0];
+                        self.position_from(background_marker_positions[0], peak_position);
                     self.plot_settings
                         .markers
                         .add_peak_marker(adjusted_peak_position);
                 } else if peaks_found_with_region {
                     let adjusted_peak_position =
-                        self.bin_width * peak_position as f64 + region_marker_positions[0];
+                        self.position_from(region_marker_positions[0], peak_position);
                     self.plot_settings
                         .markers
                         .add_peak_marker(adjusted_peak_position);
                 } else {
-                    let adjusted_peak_position =
-                        self.bin_width * peak_position as f64 + self.range.0;
+                    let adjusted_peak_position = self.position_from(self.range.0, peak_position);
                     self.plot_settings
                         .markers
                         .add_peak_marker(adjusted_peak_position);
@@ -94,8 +91,26 @@ impl Histogram {
     }
 }
 
+// Prominence uses the `find_peaks` crate with the limits below. Second difference
+// convolves the spectrum with the second derivative of a Gaussian of the expected
+// FWHM (Mariscotti 1967), which is zero for a constant or linear background, and
+// keeps the maxima that are `sensitivity` standard deviations above zero.
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub enum PeakSearchMethod {
+    #[default]
+    Prominence,
+    SecondDifference,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PeakFindingSettings {
+    #[serde(default)]
+    method: PeakSearchMethod,
+    #[serde(default = "default_sensitivity")]
+    sensitivity: f64, // standard deviations
+    #[serde(default = "default_fwhm")]
+    fwhm: f64, // bins
+
     min_height: f64,
     max_height: f64,
     min_prominence: f64,
@@ -119,9 +134,20 @@ pub struct PeakFindingSettings {
     enable_max_distance: bool,
 }
 
+fn default_sensitivity() -> f64 {
+    5.0
+}
+
+fn default_fwhm() -> f64 {
+    5.0
+}
+
 impl Default for PeakFindingSettings {
     fn default() -> Self {
         PeakFindingSettings {
+            method: PeakSearchMethod::default(),
+            sensitivity: default_sensitivity(),
+            fwhm: default_fwhm(),
             min_height: 20.0,
             max_height: 0.0,
             min_prominence: 1.0,
@@ -148,7 +174,10 @@ impl Default for PeakFindingSettings {
 }
 
 impl PeakFindingSettings {
-    pub fn menu_button(&mut self, ui: &mut egui::Ui) {
+    // Returns true when a second difference setting changed so the peaks can be searched again
+    pub fn menu_button(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
         ui.menu_button("Peak Finder Parameters", |ui| {
             ui.heading("Peak Finder Settings");
 
@@ -158,6 +187,40 @@ impl PeakFindingSettings {
 
             ui.separator();
 
+            ui.horizontal(|ui| {
+                ui.label("Method: ");
+                ui.radio_value(&mut self.method, PeakSearchMethod::Prominence, "Prominence");
+                changed |= ui
+                    .radio_value(
+                        &mut self.method,
+                        PeakSearchMethod::SecondDifference,
+                        "Second Difference",
+                    )
+                    .on_hover_text("Smoothed second difference, insensitive to a linear background")
+                    .changed();
+            });
+
+            if self.method == PeakSearchMethod::SecondDifference {
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut self.sensitivity, 1.0..=20.0)
+                            .text("Sensitivity (σ)"),
+                    )
+                    .on_hover_text("Lower values propose more, smaller peaks")
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut self.fwhm)
+                            .speed(0.1)
+                            .range(1.0..=f64::INFINITY)
+                            .prefix("FWHM: ")
+                            .suffix(" bins"),
+                    )
+                    .on_hover_text("Expected peak width, also the minimum separation of two peaks")
+                    .changed();
+                return;
+            }
+
             egui::ScrollArea::vertical().show(ui, |ui| {
                 ui.horizontal(|ui| {
                     ui.checkbox(&mut self.enable_min_height, "Enable Min Height");
@@ -270,10 +333,26 @@ impl PeakFindingSettings {
                 });
             });
         });
+
+        changed && self.method == PeakSearchMethod::SecondDifference
     }
 
-    pub fn find_peaks(&self, y_data: Vec<f64>) -> Vec<Peak<f64>> {
-        let mut peak_finder = PeakFinder::new(&y_data);
+    // Peak positions in bins from the start of the data
+    pub fn find_peaks(&self, y_data: Vec<f64>) -> Vec<f64> {
+        match self.method {
+            PeakSearchMethod::Prominence => self
+                .find_prominent_peaks(&y_data)
+                .iter()
+                .map(|peak| peak.middle_position() as f64)
+                .collect(),
+            PeakSearchMethod::SecondDifference => {
+                second_difference_peaks(&y_data, self.fwhm, self.sensitivity)
+            }
+        }
+    }
+
+    fn find_prominent_peaks(&self, y_data: &[f64]) -> Vec<find_peaks::Peak<f64>> {
+        let mut peak_finder = PeakFinder::new(y_data);
 
         if self.enable_min_height {
             peak_finder.with_min_height(self.min_height);
@@ -318,3 +397,66 @@ impl PeakFindingSettings {
         peak_finder.find_peaks()
     }
 }
+
+fn second_difference_peaks(y_data: &[f64], fwhm: f64, sensitivity: f64) -> Vec<f64> {
+    let sigma = (fwhm / 2.35482).max(0.5);
+    let half_width = (3.0 * sigma).ceil() as usize;
+    if y_data.len() < 2 * half_width + 3 {
+        return Vec::new();
+    }
+
+    // negative second derivative of a Gaussian, shifted so the weights sum to zero
+    let mut kernel: Vec<f64> = (0..=2 * half_width)
+        .map(|j| {
+            let u = (j as f64 - half_width as f64) / sigma;
+            (1.0 - u * u) * (-0.5 * u * u).exp()
+        })
+        .collect();
+    let mean = kernel.iter().sum::<f64>() / kernel.len() as f64;
+    kernel.iter_mut().for_each(|c| *c -= mean);
+
+    // filtered spectrum and its Poisson standard deviation
+    let mut filtered = vec![0.0; y_data.len()];
+    let mut significance = vec![0.0; y_data.len()];
+    for i in half_width..y_data.len() - half_width {
+        let window = &y_data[i - half_width..=i + half_width];
+        let value: f64 = kernel.iter().zip(window).map(|(c, y)| c * y).sum();
+        let variance: f64 = kernel
+            .iter()
+            .zip(window)
+            .map(|(c, y)| c * c * y.max(1.0))
+            .sum();
+        filtered[i] = value;
+        significance[i] = value / variance.sqrt();
+    }
+
+    // local maxima above the threshold, strongest first
+    let mut candidates: Vec<usize> = (half_width + 1..y_data.len() - half_width - 1)
+        .filter(|&i| {
+            significance[i] > sensitivity
+                && significance[i] >= significance[i - 1]
+                && significance[i] > significance[i + 1]
+        })
+        .collect();
+    candidates.sort_by(|&a, &b| significance[b].total_cmp(&significance[a]));
+
+    let mut peaks: Vec<f64> = Vec::new();
+    for i in candidates {
+        // parabola through the filtered spectrum for the centroid
+        let (left, center, right) = (filtered[i - 1], filtered[i], filtered[i + 1]);
+        let curvature = left - 2.0 * center + right;
+        let offset = if curvature < 0.0 {
+            (0.5 * (left - right) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+        let position = i as f64 + offset;
+
+        if peaks.iter().all(|&peak| (peak - position).abs() >= fwhm) {
+            peaks.push(position);
+        }
+    }
+
+    peaks.sort_by(f64::total_cmp);
+    peaks
+}
@@ -1,17 +1,57 @@
+use super::annotations::Annotation;
+use super::auto_range::AutoRange;
+use super::comparison::ComparisonSettings;
+use super::downsample::DOWNSAMPLE_BINS;
+use super::gamma_lines::GammaLineSettings;
 use super::markers::FitMarkers;
 use super::peak_finder::PeakFindingSettings;
+use super::stack::StackSettings;
 use crate::egui_plot_stuff::egui_plot_settings::EguiPlotSettings;
+use crate::fitter::calibration::Calibration;
+use crate::fitter::efficiency::EfficiencyCurve;
+use crate::histoer::figure::settings::FigureSettings;
+use crate::histoer::notes::Notes;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct PlotSettings {
     #[serde(skip)]
     pub cursor_position: Option<egui_plot::PlotPoint>,
     pub egui_settings: EguiPlotSettings,
     pub stats_info: bool,
+    #[serde(default)]
+    pub crosshair: bool,
+    #[serde(skip)]
+    pub cursor_link: Option<egui::Id>, // set by the tree when the grid of the pane links cursors
+    #[serde(skip)]
+    pub linked_x: Option<f64>, // cursor of another pane of the grid
+    #[serde(skip)]
+    pub axis_link: Option<(egui::Id, egui::Vec2b)>, // set by the tree when the grid links axes
     pub markers: FitMarkers,
     pub rebin_factor: usize,
     pub find_peaks_settings: PeakFindingSettings,
+    #[serde(default)]
+    pub notes: Notes,
+    #[serde(default)]
+    pub efficiency: EfficiencyCurve,
+    #[serde(default)]
+    pub calibration: Calibration,
+    #[serde(default)]
+    pub auto_range: AutoRange,
+    #[serde(default)]
+    pub stack: StackSettings,
+    #[serde(default)]
+    pub comparison: ComparisonSettings,
+    #[serde(default)]
+    pub gamma_lines: GammaLineSettings,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    #[serde(default)]
+    pub figure: FigureSettings,
+    #[serde(default = "default_downsample")]
+    pub downsample: bool, // draw the min and max per pixel column for very many bins
 
+    pub preview: Option<f64>, // sample fraction when filled by a preview
     #[serde(skip)] // Skip serialization for progress
     pub progress: Option<f32>, // Optional progress tracking
 }
@@ -21,17 +61,45 @@ impl Default for PlotSettings {
             cursor_position: None,
             egui_settings: EguiPlotSettings::default(),
             stats_info: false,
+            crosshair: false,
+            cursor_link: None,
+            linked_x: None,
+            axis_link: None,
             markers: FitMarkers::new(),
             rebin_factor: 1,
             find_peaks_settings: PeakFindingSettings::default(),
+            notes: Notes::default(),
+            efficiency: EfficiencyCurve::default(),
+            calibration: Calibration::default(),
+            auto_range: AutoRange::default(),
+            stack: StackSettings::default(),
+            comparison: ComparisonSettings::default(),
+            gamma_lines: GammaLineSettings::default(),
+            annotations: Vec::new(),
+            figure: FigureSettings::default(),
+            downsample: true,
+            preview: None,
             progress: None,
         }
     }
 }
+fn default_downsample() -> bool {
+    true
+}
+
 impl PlotSettings {
     pub fn settings_ui(&mut self, ui: &mut egui::Ui) {
         // self.egui_settings.menu_button(ui);
+        self.egui_settings.axes_ui(ui, "histogram1d");
+        self.egui_settings.bounds_ui(ui);
         ui.checkbox(&mut self.stats_info, "Show Statistics");
+        ui.checkbox(&mut self.crosshair, "Crosshair")
+            .on_hover_text("Show x, y, and the bin content under the cursor");
+        ui.checkbox(&mut self.downsample, "Downsample").on_hover_text(format!(
+            "Draw histograms with more than {} bins with the smallest and largest count of each pixel column. Fits and exports use every bin",
+            DOWNSAMPLE_BINS
+        ));
+        self.notes.menu_button(ui);
         self.markers.menu_button(ui);
     }
 
@@ -1,4 +1,5 @@
 use super::histogram1d::Histogram;
+use crate::histoer::jobs::Job;
 
 impl Histogram {
     // Compute the possible rebin factors based on the initial number of bins
@@ -18,10 +19,17 @@ impl Histogram {
         factors
     }
 
-    // Rebin the histogram according to the rebin factor
+    // Rebin the histogram according to the rebin factor, the original bins are kept so it can be undone.
+    // Factors that do not divide the number of bins leave a narrower last bin.
     pub fn rebin(&mut self) {
-        let rebin_factor = self.plot_settings.rebin_factor;
-        let new_bin_count = self.original_bins.len() / rebin_factor;
+        let original_count = self.original_bins.len();
+        let rebin_factor = self
+            .plot_settings
+            .rebin_factor
+            .clamp(1, original_count.max(1));
+        self.plot_settings.rebin_factor = rebin_factor;
+
+        let new_bin_count = original_count.div_ceil(rebin_factor);
         let mut new_bins = vec![0; new_bin_count];
 
         for (i, &count) in self.original_bins.iter().enumerate() {
@@ -30,7 +38,83 @@ impl Histogram {
         }
 
         self.bins = new_bins;
-        self.bin_width = (self.range.1 - self.range.0) / new_bin_count as f64;
+        self.bin_width =
+            (self.range.1 - self.range.0) / original_count as f64 * rebin_factor as f64;
         self.update_line_points();
     }
+
+    pub fn set_rebin_factor(&mut self, factor: usize) {
+        if factor != self.plot_settings.rebin_factor {
+            self.plot_settings.rebin_factor = factor;
+            self.rebin();
+        }
+    }
+
+    // Rebin controls for the context menu: power of two factors and a custom factor
+    pub fn rebin_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Rebin Factor:");
+
+        ui.horizontal_wrapped(|ui| {
+            for factor in self.possible_rebin_factors() {
+                if ui
+                    .selectable_label(
+                        self.plot_settings.rebin_factor == factor,
+                        format!("x{}", factor),
+                    )
+                    .clicked()
+                {
+                    self.submit(Job::Rebin(factor));
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let mut factor = self.plot_settings.rebin_factor;
+            if ui
+                .add(
+                    egui::DragValue::new(&mut factor)
+                        .range(1..=self.original_bins.len().max(1))
+                        .prefix("Custom: x"),
+                )
+                .changed()
+            {
+                self.submit(Job::Rebin(factor));
+            }
+
+            ui.label(format!(
+                "{} of {} bins",
+                self.bins.len(),
+                self.original_bins.len()
+            ));
+        });
+    }
+
+    // Shown above the plot while the histogram is rebinned
+    pub fn rebin_status_ui(&mut self, ui: &mut egui::Ui) {
+        if self.plot_settings.rebin_factor <= 1 {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "Rebinned x{}: {} of {} bins",
+                self.plot_settings.rebin_factor,
+                self.bins.len(),
+                self.original_bins.len()
+            ));
+            if ui.small_button("x2").clicked() {
+                self.submit(Job::Rebin(self.plot_settings.rebin_factor * 2));
+            }
+            if ui.small_button("÷2").clicked() {
+                self.submit(Job::Rebin(self.plot_settings.rebin_factor / 2));
+            }
+            if ui
+                .small_button("Undo")
+                .on_hover_text("Restore the original bins")
+                .clicked()
+            {
+                self.submit(Job::Rebin(1));
+            }
+        });
+    }
 }
@@ -0,0 +1,172 @@
+use super::histogram1d::Histogram;
+use egui_plot::{Bar, BarChart};
+
+// One histogram drawn in the stack, copied from its pane by the Histogrammer
+#[derive(Debug, Clone)]
+pub struct StackLayer {
+    pub name: String,
+    pub color: egui::Color32,
+    pub counts: Vec<f64>,
+}
+
+// Stacks the histograms whose name matches the pattern (e.g. the per-cut contributions)
+// under this one so the total can be compared to the sum of its parts.
+// Components are stacked in name order with the color of their own pane.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct StackSettings {
+    pub enabled: bool,
+    pub pattern: String, // regex of the component histogram names
+    pub remainder: bool, // add the total minus the components as "Other"
+    #[serde(skip)]
+    pub layers: Vec<StackLayer>,
+    #[serde(skip)]
+    pub skipped: Vec<String>, // matched, but the bins differ from this histogram
+}
+
+impl StackSettings {
+    pub fn regex(&self) -> Option<regex::Regex> {
+        if !self.enabled || self.pattern.is_empty() {
+            return None;
+        }
+        regex::Regex::new(&self.pattern).ok()
+    }
+}
+
+impl Histogram {
+    // Whether the other histogram can be stacked on this one bin by bin
+    pub fn same_bins(&self, other: &Histogram) -> bool {
+        self.bins.len() == other.bins.len()
+            && (0..=self.bins.len()).all(|index| {
+                (self.bin_edge(index) - other.bin_edge(index)).abs()
+                    <= 1e-9 * self.bin_width.abs().max(1.0)
+            })
+    }
+
+    pub fn stack_layer(&self) -> StackLayer {
+        StackLayer {
+            name: self.name.clone(),
+            color: self.line.color,
+            counts: (0..self.bins.len())
+                .map(|index| self.bin_content(index))
+                .collect(),
+        }
+    }
+
+    pub fn stack_ui(&mut self, ui: &mut egui::Ui) {
+        let stack = &mut self.plot_settings.stack;
+        ui.menu_button("Stack", |ui| {
+            ui.checkbox(&mut stack.enabled, "Stack components")
+                .on_hover_text("Draw the matching histograms stacked under this one");
+
+            ui.horizontal(|ui| {
+                ui.label("Components: ");
+                ui.add(
+                    egui::TextEdit::singleline(&mut stack.pattern)
+                        .hint_text("Histogram name (regex)")
+                        .desired_width(200.0),
+                );
+            });
+            if let Err(e) = regex::Regex::new(&stack.pattern) {
+                ui.colored_label(egui::Color32::RED, e.to_string());
+            }
+
+            ui.checkbox(&mut stack.remainder, "Show remainder")
+                .on_hover_text("Counts of this histogram not in any component, drawn as \"Other\"");
+
+            if stack.enabled {
+                ui.separator();
+                if stack.layers.is_empty() {
+                    ui.label("No matching histograms");
+                }
+                for layer in &stack.layers {
+                    ui.colored_label(layer.color, &layer.name);
+                }
+                for name in &stack.skipped {
+                    ui.label(format!("{} (different bins, skipped)", name));
+                }
+            }
+        });
+    }
+
+    pub fn draw_stack(&self, plot_ui: &mut egui_plot::PlotUi) {
+        let stack = &self.plot_settings.stack;
+        if !stack.enabled || stack.layers.is_empty() {
+            return;
+        }
+
+        let log_x = self.plot_settings.egui_settings.log_x;
+        let log_y = self.plot_settings.egui_settings.log_y;
+        let y_transform = self.plot_settings.egui_settings.y_transform;
+
+        // Same transforms as the histogram line. In log y an empty base sits at 1 count,
+        // so the first layer with counts starts at the bottom of the plot.
+        let transform_x = |x: f64| {
+            if log_x && x > 0.0 {
+                x.log10().max(0.0001)
+            } else {
+                x
+            }
+        };
+        let transform_y = |y: f64| {
+            if log_y {
+                if y > 0.0 {
+                    y.log10().max(0.0001)
+                } else {
+                    0.0
+                }
+            } else {
+                y_transform.forward(y)
+            }
+        };
+
+        let mut layers: Vec<(&str, egui::Color32, Vec<f64>)> = stack
+            .layers
+            .iter()
+            .map(|layer| (layer.name.as_str(), layer.color, layer.counts.clone()))
+            .collect();
+
+        if stack.remainder {
+            let other = (0..self.bins.len())
+                .map(|index| {
+                    let components: f64 = stack
+                        .layers
+                        .iter()
+                        .map(|layer| layer.counts.get(index).copied().unwrap_or(0.0))
+                        .sum();
+                    (self.bin_content(index) - components).max(0.0)
+                })
+                .collect();
+            layers.push(("Other", egui::Color32::GRAY, other));
+        }
+
+        let mut lower = vec![0.0; self.bins.len()];
+        for (name, color, counts) in layers {
+            let bars = (0..self.bins.len())
+                .filter_map(|index| {
+                    let upper = lower[index] + counts.get(index).copied().unwrap_or(0.0);
+                    let base = transform_y(lower[index]);
+                    let top = transform_y(upper);
+                    lower[index] = upper;
+                    if top <= base {
+                        return None;
+                    }
+
+                    let start = transform_x(self.bin_edge(index));
+                    let end = transform_x(self.bin_edge(index + 1));
+                    Some(
+                        Bar::new(0.5 * (start + end), top - base)
+                            .width(end - start)
+                            .base_offset(base),
+                    )
+                })
+                .collect();
+
+            plot_ui.bar_chart(
+                BarChart::new(bars)
+                    .name(name)
+                    .color(color)
+                    .allow_hover(false),
+            );
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use super::histogram1d::Histogram;
+use crate::histoer::stats_box::{format_value, stats_box};
 
 impl Histogram {
     // Calculate the statistics for the histogram within the specified x range.
@@ -11,8 +12,7 @@ impl Histogram {
 
         for bin in start_bin..=end_bin {
             if bin < self.bins.len() {
-                let bin_center =
-                    self.range.0 + (bin as f64 * self.bin_width) + self.bin_width * 0.5;
+                let bin_center = self.bin_center(bin);
                 sum_product += self.bins[bin] as f64 * bin_center;
                 total_count += self.bins[bin];
             } else {
@@ -29,8 +29,7 @@ impl Histogram {
 
             for bin in start_bin..=end_bin {
                 if bin < self.bins.len() {
-                    let bin_center =
-                        self.range.0 + (bin as f64 * self.bin_width) + (self.bin_width * 0.5);
+                    let bin_center = self.bin_center(bin);
                     let diff = bin_center - mean;
                     sum_squared_diff += self.bins[bin] as f64 * diff * diff;
                 } else {
@@ -44,29 +43,96 @@ impl Histogram {
         }
     }
 
-    // Get the legend stat entries for the histogram
-    pub fn show_stats(&self, plot_ui: &mut egui_plot::PlotUi) {
-        if self.plot_settings.stats_info {
-            let plot_min_x = plot_ui.plot_bounds().min()[0];
-            let plot_max_x = plot_ui.plot_bounds().max()[0];
-
-            let (integral, mean, stdev) = self.get_statistics(plot_min_x, plot_max_x);
-            let stats_entries = [
-                format!("Integral: {}", integral),
-                format!("Mean: {:.2}", mean),
-                format!("Stdev: {:.2}", stdev),
-                format!("Overflow: {:}", self.overflow),
-                format!("Underflow: {:}", self.underflow),
-            ];
-
-            for entry in stats_entries.iter() {
-                plot_ui.text(
-                    egui_plot::Text::new(egui_plot::PlotPoint::new(0, 0), " ") // Placeholder for positioning; adjust as needed
-                        .highlight(false)
-                        .color(self.line.color)
-                        .name(entry),
-                );
-            }
+    // Estimate the centroid, FWHM, and net area between the region markers before fitting.
+    // The background comes from the temp fit if one exists, otherwise a line between the region edges.
+    pub fn region_estimates(&self) -> Option<(f64, f64, f64)> {
+        let region = self.plot_settings.markers.get_region_marker_positions();
+        if region.len() != 2 || self.bins.is_empty() {
+            return None;
+        }
+
+        let x = self.get_bin_centers_between(region[0], region[1]);
+        let y = self.get_bin_counts_between(region[0], region[1]);
+        if x.len() < 3 || x.len() != y.len() {
+            return None;
+        }
+
+        let background_result = self
+            .fits
+            .temp_fit
+            .as_ref()
+            .and_then(|fit| fit.background_result.as_ref());
+
+        let (x0, y0) = (x[0], y[0]);
+        let (x1, y1) = (x[x.len() - 1], y[y.len() - 1]);
+        let slope = (y1 - y0) / (x1 - x0);
+
+        let net: Vec<f64> = x
+            .iter()
+            .zip(y.iter())
+            .map(|(&x, &y)| {
+                let background = match background_result {
+                    Some(result) => result.evaluate(x),
+                    None => y0 + slope * (x - x0),
+                };
+                (y - background).max(0.0)
+            })
+            .collect();
+
+        let area: f64 = net.iter().sum();
+        if area <= 0.0 {
+            return None;
+        }
+
+        let centroid = x.iter().zip(net.iter()).map(|(x, n)| x * n).sum::<f64>() / area;
+        let variance = x
+            .iter()
+            .zip(net.iter())
+            .map(|(x, n)| n * (x - centroid).powi(2))
+            .sum::<f64>()
+            / area;
+        let fwhm = 2.0 * (2.0 * 2.0_f64.ln()).sqrt() * variance.sqrt();
+
+        Some((centroid, fwhm, area))
+    }
+
+    pub fn region_estimates_ui(&self, ui: &mut egui::Ui) {
+        if !self.fits.settings.show_estimates {
+            return;
+        }
+
+        if let Some((centroid, fwhm, area)) = self.region_estimates() {
+            ui.horizontal(|ui| {
+                ui.label("Estimate:");
+                ui.label(format!("Centroid: {:.2}", centroid));
+                ui.separator();
+                ui.label(format!("FWHM: {:.2}", fwhm));
+                ui.separator();
+                ui.label(format!("Area: {:.0}", area));
+            })
+            .response
+            .on_hover_text("Background subtracted moments of the data between the region markers");
         }
     }
+
+    // Stats box over the plot. Mean, RMS, and the integral are of the visible x range.
+    pub fn show_stats(&self, ui: &egui::Ui, transform: &egui_plot::PlotTransform) {
+        if !self.plot_settings.stats_info {
+            return;
+        }
+
+        let bounds = transform.bounds();
+        let (integral, mean, stdev) = self.get_statistics(bounds.min()[0], bounds.max()[0]);
+        let entries = self.bins.iter().sum::<u64>() + self.underflow + self.overflow;
+
+        let rows = [
+            ("Entries", entries.to_string()),
+            ("Mean", format_value(mean)),
+            ("RMS", format_value(stdev)),
+            ("Underflow", self.underflow.to_string()),
+            ("Overflow", self.overflow.to_string()),
+            ("Integral", integral.to_string()),
+        ];
+        stats_box(ui, *transform.frame(), &self.name, &rows);
+    }
 }
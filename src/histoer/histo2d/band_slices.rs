@@ -0,0 +1,206 @@
+use crate::egui_plot_stuff::egui_polygon::EguiPolygon;
+
+use super::gate_sweep::GateAxis;
+use super::histogram2d::Histogram2D;
+
+// A rectangular or polygonal band on the 2D histogram that is cut into slices
+// along one axis, every slice is projected onto the other axis as a 1D pane.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BandSlices {
+    pub band: EguiPolygon,
+    pub axis: GateAxis, // Y: slices along y projected onto x
+    pub bins_per_slice: usize,
+    #[serde(skip)]
+    pub pending: Vec<(String, Vec<u64>, (f64, f64))>, // name, counts, range of slices waiting for a pane
+}
+
+impl Default for BandSlices {
+    fn default() -> Self {
+        let mut band = EguiPolygon::new("Band");
+        band.interactive_clicking = false;
+        band.stroke = egui::Stroke::new(1.0, egui::Color32::GOLD);
+        band.style = Some(egui_plot::LineStyle::dashed_loose());
+
+        BandSlices {
+            band,
+            axis: GateAxis::Y,
+            bins_per_slice: 16,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl BandSlices {
+    pub fn draw(&mut self, plot_ui: &mut egui_plot::PlotUi) {
+        if !self.band.vertices.is_empty() || self.band.interactive_clicking {
+            self.band.draw(plot_ui);
+        }
+    }
+
+    pub fn interactions(&mut self, plot_response: &egui_plot::PlotResponse<()>) {
+        if !self.band.vertices.is_empty() || self.band.interactive_clicking {
+            self.band.handle_interactions(plot_response);
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.band.is_dragging || self.band.interactive_clicking
+    }
+}
+
+impl Histogram2D {
+    // Menu to place the band and make the slices
+    pub fn band_slices_menu(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Band Slices");
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Rectangle")
+                .on_hover_text("Band over the middle of the histogram, drag the corners to move it")
+                .clicked()
+            {
+                let (x, y) = (&self.range.x, &self.range.y);
+                let (x_low, x_high) = (
+                    x.min + 0.25 * (x.max - x.min),
+                    x.min + 0.75 * (x.max - x.min),
+                );
+                let band = &mut self.plot_settings.band_slices.band;
+                band.vertices = vec![
+                    [x_low, y.min],
+                    [x_high, y.min],
+                    [x_high, y.max],
+                    [x_low, y.max],
+                ];
+                band.interactive_clicking = false;
+                band.interactive_dragging = true;
+            }
+
+            if ui
+                .button("Polygon")
+                .on_hover_text("Click to add vertices, double click to finish")
+                .clicked()
+            {
+                let band = &mut self.plot_settings.band_slices.band;
+                band.clear_vertices();
+                band.interactive_clicking = true;
+                band.interactive_dragging = true;
+                ui.close_menu();
+            }
+
+            if ui.button("Clear").clicked() {
+                let band = &mut self.plot_settings.band_slices.band;
+                band.clear_vertices();
+                band.interactive_clicking = false;
+            }
+        });
+
+        if !self.plot_settings.cuts.is_empty() {
+            ui.menu_button("From Cut", |ui| {
+                for cut in &self.plot_settings.cuts {
+                    let cut = cut.lock().unwrap();
+                    if ui.button(&cut.polygon.name).clicked() {
+                        let band = &mut self.plot_settings.band_slices.band;
+                        band.vertices = cut.polygon.vertices.clone();
+                        band.interactive_clicking = false;
+                        band.interactive_dragging = true;
+                        ui.close_menu();
+                    }
+                }
+            });
+        }
+
+        let settings = &mut self.plot_settings.band_slices;
+        ui.horizontal(|ui| {
+            ui.label("Slice along:");
+            ui.radio_value(&mut settings.axis, GateAxis::Y, "Y");
+            ui.radio_value(&mut settings.axis, GateAxis::X, "X");
+        });
+
+        ui.add(
+            egui::DragValue::new(&mut settings.bins_per_slice)
+                .range(1..=usize::MAX)
+                .prefix("Bins per slice: "),
+        );
+
+        if ui
+            .add_enabled(
+                settings.band.vertices.len() >= 3 && !settings.band.interactive_clicking,
+                egui::Button::new("Make Slices"),
+            )
+            .on_hover_text("Project every slice of the band into a new tab")
+            .clicked()
+        {
+            self.band_slices();
+            ui.close_menu();
+        }
+    }
+
+    // Projects the counts inside the band slice by slice, the panes are added by the histogrammer
+    pub fn band_slices(&mut self) {
+        let settings = &self.plot_settings.band_slices;
+        let per_slice = settings.bins_per_slice.max(1);
+
+        // (sliced axis bins, min, width), (projected axis bins, min, max)
+        let (sliced, projected) = match settings.axis {
+            GateAxis::Y => (
+                (self.bins.y, self.range.y.min, self.bins.y_width),
+                (self.bins.x, self.range.x.min, self.range.x.max),
+            ),
+            GateAxis::X => (
+                (self.bins.x, self.range.x.min, self.bins.x_width),
+                (self.bins.y, self.range.y.min, self.range.y.max),
+            ),
+        };
+
+        let mut slices = vec![vec![0u64; projected.0]; sliced.0.div_ceil(per_slice)];
+        for ((x_index, y_index), count) in self.bins.counts.iter() {
+            let x = self.range.x.min + (x_index as f64 + 0.5) * self.bins.x_width;
+            let y = self.range.y.min + (y_index as f64 + 0.5) * self.bins.y_width;
+            if !settings.band.is_inside(x, y) {
+                continue;
+            }
+
+            let (slice_index, projected_index) = match settings.axis {
+                GateAxis::Y => (y_index, x_index),
+                GateAxis::X => (x_index, y_index),
+            };
+            if let Some(bin) = slices
+                .get_mut(slice_index / per_slice)
+                .and_then(|slice| slice.get_mut(projected_index))
+            {
+                *bin += count;
+            }
+        }
+
+        let axis = match settings.axis {
+            GateAxis::Y => "Y",
+            GateAxis::X => "X",
+        };
+        let name = self.name.rsplit('/').next().unwrap_or(&self.name);
+        let prefix = match self.name.rsplit_once('/') {
+            Some((path, _)) => format!("{}/{} Slices", path, name),
+            None => format!("{} Slices", name),
+        };
+
+        let mut pending = Vec::new();
+        for (index, counts) in slices.into_iter().enumerate() {
+            if counts.iter().all(|&count| count == 0) {
+                continue;
+            }
+            let low = sliced.1 + (index * per_slice) as f64 * sliced.2;
+            let high = low + per_slice as f64 * sliced.2;
+            pending.push((
+                format!("{}/{} [{:.2}, {:.2})", prefix, axis, low, high),
+                counts,
+                (projected.1, projected.2),
+            ));
+        }
+
+        log::info!(
+            "Band slices of '{}': {} non-empty slices",
+            self.name,
+            pending.len()
+        );
+        self.plot_settings.band_slices.pending = pending;
+    }
+}
@@ -0,0 +1,259 @@
+use fnv::FnvHashMap;
+
+use super::histogram2d::{Bins, Histogram2D};
+
+// Counts of the bins of a 2D histogram. A sparse histogram keeps a hash map of its filled bins
+// (no memory for the empty ones), a full one a dense row-major Vec<u32>, which takes 4 bytes
+// per bin instead of ~40 per filled bin and is much faster to fill and to draw. With the Auto
+// storage the counts move to the dense vector once more than DENSE_FRACTION of the bins are
+// filled. The counts are saved as a map either way.
+
+pub const DENSE_FRACTION: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Storage {
+    #[default]
+    Auto,
+    Sparse,
+    Dense,
+}
+
+#[derive(Debug, Clone)]
+pub enum BinCounts {
+    Sparse(FnvHashMap<(usize, usize), u64>),
+    Dense {
+        x_bins: usize,
+        y_bins: usize,
+        counts: Vec<u32>, // index x + y * x_bins
+        filled: usize,    // bins that are not 0
+    },
+}
+
+impl Default for BinCounts {
+    fn default() -> Self {
+        BinCounts::Sparse(FnvHashMap::default())
+    }
+}
+
+impl BinCounts {
+    pub fn get(&self, x: usize, y: usize) -> u64 {
+        match self {
+            BinCounts::Sparse(map) => map.get(&(x, y)).copied().unwrap_or(0),
+            BinCounts::Dense {
+                x_bins,
+                y_bins,
+                counts,
+                ..
+            } => {
+                if x < *x_bins && y < *y_bins {
+                    counts[x + y * x_bins] as u64
+                } else {
+                    0
+                }
+            }
+        }
+    }
+
+    // Adds `count` to the bin and returns its new count
+    pub fn add(&mut self, x: usize, y: usize, count: u64) -> u64 {
+        if let BinCounts::Dense {
+            x_bins,
+            y_bins,
+            counts,
+            filled,
+        } = self
+        {
+            if x < *x_bins && y < *y_bins {
+                let bin = &mut counts[x + y * *x_bins];
+                if let Ok(total) = u32::try_from(*bin as u64 + count) {
+                    if *bin == 0 && total > 0 {
+                        *filled += 1;
+                    }
+                    *bin = total;
+                    return total as u64;
+                }
+            }
+            // outside of the bins or too many counts for a u32
+            self.to_sparse();
+        }
+
+        let BinCounts::Sparse(map) = self else {
+            unreachable!()
+        };
+        let bin = map.entry((x, y)).or_insert(0);
+        *bin += count;
+        *bin
+    }
+
+    // Filled bins with their counts
+    pub fn iter(&self) -> Box<dyn Iterator<Item = ((usize, usize), u64)> + '_> {
+        match self {
+            BinCounts::Sparse(map) => Box::new(map.iter().map(|(&bin, &count)| (bin, count))),
+            BinCounts::Dense { x_bins, counts, .. } => Box::new(
+                counts
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &count)| count > 0)
+                    .map(move |(index, &count)| ((index % x_bins, index / x_bins), count as u64)),
+            ),
+        }
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = u64> + '_ {
+        self.iter().map(|(_, count)| count)
+    }
+
+    // Number of filled bins
+    pub fn len(&self) -> usize {
+        match self {
+            BinCounts::Sparse(map) => map.len(),
+            BinCounts::Dense { filled, .. } => *filled,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        match self {
+            BinCounts::Sparse(map) => map.clear(),
+            BinCounts::Dense { counts, filled, .. } => {
+                counts.iter_mut().for_each(|count| *count = 0);
+                *filled = 0;
+            }
+        }
+    }
+
+    pub fn is_dense(&self) -> bool {
+        matches!(self, BinCounts::Dense { .. })
+    }
+
+    // Stays sparse when a bin is outside of the bins or has too many counts for a u32
+    pub fn to_dense(&mut self, x_bins: usize, y_bins: usize) {
+        let BinCounts::Sparse(map) = self else {
+            return;
+        };
+
+        let mut counts = vec![0u32; x_bins * y_bins];
+        for (&(x, y), &count) in map.iter() {
+            match u32::try_from(count) {
+                Ok(count) if x < x_bins && y < y_bins => counts[x + y * x_bins] = count,
+                _ => return,
+            }
+        }
+
+        *self = BinCounts::Dense {
+            x_bins,
+            y_bins,
+            filled: map.values().filter(|&&count| count > 0).count(),
+            counts,
+        };
+    }
+
+    pub fn to_sparse(&mut self) {
+        if self.is_dense() {
+            *self = BinCounts::Sparse(self.to_map());
+        }
+    }
+
+    pub fn to_map(&self) -> FnvHashMap<(usize, usize), u64> {
+        match self {
+            BinCounts::Sparse(map) => map.clone(),
+            BinCounts::Dense { .. } => self.iter().collect(),
+        }
+    }
+
+    // Memory taken by the counts, roughly
+    pub fn bytes(&self) -> usize {
+        match self {
+            BinCounts::Sparse(map) => map.capacity() * (3 * std::mem::size_of::<u64>() + 8),
+            BinCounts::Dense { counts, .. } => counts.len() * std::mem::size_of::<u32>(),
+        }
+    }
+}
+
+impl From<FnvHashMap<(usize, usize), u64>> for BinCounts {
+    fn from(map: FnvHashMap<(usize, usize), u64>) -> Self {
+        BinCounts::Sparse(map)
+    }
+}
+
+impl FromIterator<((usize, usize), u64)> for BinCounts {
+    fn from_iter<I: IntoIterator<Item = ((usize, usize), u64)>>(iter: I) -> Self {
+        BinCounts::Sparse(iter.into_iter().collect())
+    }
+}
+
+impl serde::Serialize for BinCounts {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for BinCounts {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        FnvHashMap::deserialize(deserializer).map(BinCounts::Sparse)
+    }
+}
+
+impl Bins {
+    // Moves the counts to the storage that suits them
+    pub fn update_storage(&mut self, storage: Storage) {
+        let dense = match storage {
+            Storage::Sparse => false,
+            Storage::Dense => true,
+            Storage::Auto => {
+                self.counts.is_dense()
+                    || self.counts.len() as f64 > DENSE_FRACTION * (self.x * self.y) as f64
+            }
+        };
+
+        if dense {
+            self.counts.to_dense(self.x, self.y);
+        } else {
+            self.counts.to_sparse();
+        }
+    }
+}
+
+impl Histogram2D {
+    pub fn storage_ui(&mut self, ui: &mut egui::Ui) {
+        let storage = &mut self.plot_settings.storage;
+        let mut changed = false;
+        changed |= ui
+            .radio_value(storage, Storage::Auto, "Auto")
+            .on_hover_text(format!(
+                "Dense once more than {}% of the bins are filled",
+                DENSE_FRACTION * 100.0
+            ))
+            .changed();
+        changed |= ui
+            .radio_value(storage, Storage::Sparse, "Sparse")
+            .on_hover_text("Only the filled bins are stored")
+            .changed();
+        changed |= ui
+            .radio_value(storage, Storage::Dense, "Dense")
+            .on_hover_text("Every bin is stored, faster for full matrices")
+            .changed();
+
+        if changed {
+            let storage = *storage;
+            self.bins.update_storage(storage);
+            if let Some(backup_bins) = &mut self.backup_bins {
+                backup_bins.update_storage(storage);
+            }
+        }
+
+        ui.label(format!(
+            "{}: {} filled bins, {:.1} MB",
+            if self.bins.counts.is_dense() {
+                "Dense"
+            } else {
+                "Sparse"
+            },
+            self.bins.counts.len(),
+            self.bins.counts.bytes() as f64 / 1_048_576.0
+        ));
+    }
+}
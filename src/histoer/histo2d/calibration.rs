@@ -0,0 +1,174 @@
+use super::histogram2d::Histogram2D;
+
+// Linear calibration of one axis: calibrated = slope * raw + offset
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AxisCalibration {
+    pub slope: f64,
+    pub offset: f64,
+}
+
+impl Default for AxisCalibration {
+    fn default() -> Self {
+        AxisCalibration {
+            slope: 1.0,
+            offset: 0.0,
+        }
+    }
+}
+
+impl AxisCalibration {
+    pub fn calibrate(&self, raw: f64) -> f64 {
+        self.slope * raw + self.offset
+    }
+
+    pub fn uncalibrate(&self, calibrated: f64) -> f64 {
+        (calibrated - self.offset) / self.slope
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.slope == 1.0 && self.offset == 0.0
+    }
+
+    // Converts a value in this calibration to the other calibration
+    fn convert(&self, value: f64, other: &AxisCalibration) -> f64 {
+        other.calibrate(self.uncalibrate(value))
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, label: &str) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.add(
+                egui::DragValue::new(&mut self.slope)
+                    .speed(0.001)
+                    .range(1e-12..=f64::INFINITY)
+                    .prefix("Slope: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.offset)
+                    .speed(0.1)
+                    .prefix("Offset: "),
+            );
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Calibration2D {
+    pub x: AxisCalibration,
+    pub y: AxisCalibration,
+    pub applied_x: AxisCalibration, // calibration the histogram is currently displayed in
+    pub applied_y: AxisCalibration,
+}
+
+impl Calibration2D {
+    pub fn is_applied(&self) -> bool {
+        !self.applied_x.is_identity() || !self.applied_y.is_identity()
+    }
+
+    // Returns true when the calibration should be applied
+    pub fn menu_button(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut apply = false;
+
+        ui.heading("Axis Calibration");
+
+        self.x.ui(ui, "X: ");
+        self.y.ui(ui, "Y: ");
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Apply")
+                .on_hover_text("Rescale the axes, cuts, and projections into calibrated units")
+                .clicked()
+            {
+                apply = true;
+            }
+
+            if ui
+                .add_enabled(self.is_applied(), egui::Button::new("Reset"))
+                .on_hover_text("Return to raw channel units")
+                .clicked()
+            {
+                self.x = AxisCalibration::default();
+                self.y = AxisCalibration::default();
+                apply = true;
+            }
+        });
+
+        if self.is_applied() {
+            ui.label(format!(
+                "Current: X = {:.4}·x + {:.4}, Y = {:.4}·y + {:.4}",
+                self.applied_x.slope,
+                self.applied_x.offset,
+                self.applied_y.slope,
+                self.applied_y.offset
+            ));
+        }
+
+        ui.separator();
+
+        apply
+    }
+}
+
+impl Histogram2D {
+    // Converts raw column values into the displayed (calibrated) coordinates
+    pub fn calibrate_point(&self, x: f64, y: f64) -> (f64, f64) {
+        let calibration = &self.plot_settings.calibration;
+        (
+            calibration.applied_x.calibrate(x),
+            calibration.applied_y.calibrate(y),
+        )
+    }
+
+    // Moves the histogram from the applied calibration into the requested one.
+    // Bin contents are untouched since a linear calibration keeps the binning uniform.
+    pub fn apply_calibration(&mut self) {
+        let old_x = self.plot_settings.calibration.applied_x;
+        let old_y = self.plot_settings.calibration.applied_y;
+        let new_x = self.plot_settings.calibration.x;
+        let new_y = self.plot_settings.calibration.y;
+
+        if old_x == new_x && old_y == new_y {
+            return;
+        }
+
+        self.range.x.min = old_x.convert(self.range.x.min, &new_x);
+        self.range.x.max = old_x.convert(self.range.x.max, &new_x);
+        self.range.y.min = old_y.convert(self.range.y.min, &new_y);
+        self.range.y.max = old_y.convert(self.range.y.max, &new_y);
+
+        self.bins.x_width = (self.range.x.max - self.range.x.min) / self.bins.x as f64;
+        self.bins.y_width = (self.range.y.max - self.range.y.min) / self.bins.y as f64;
+
+        if let Some(backup_bins) = &mut self.backup_bins {
+            backup_bins.x_width = (self.range.x.max - self.range.x.min) / backup_bins.x as f64;
+            backup_bins.y_width = (self.range.y.max - self.range.y.min) / backup_bins.y as f64;
+        }
+
+        // calibrated cuts are no longer shared with panes in other units
+        for shared in &mut self.plot_settings.cuts {
+            let mut cut = shared.lock().unwrap().clone();
+            for vertex in cut.polygon.vertices_mut() {
+                vertex[0] = old_x.convert(vertex[0], &new_x);
+                vertex[1] = old_y.convert(vertex[1], &new_y);
+            }
+            *shared = std::sync::Arc::new(std::sync::Mutex::new(cut));
+        }
+
+        // projections are rebuilt over the new range the next time they are drawn
+        self.plot_settings.projections.y_projection = None;
+        self.plot_settings.projections.x_projection = None;
+
+        self.image.image_width = (self.range.x.max - self.range.x.min) as f32;
+        self.image.image_height = (self.range.y.max - self.range.y.min) as f32;
+        self.image.image_center = [
+            (self.range.x.min + self.range.x.max) / 2.0,
+            (self.range.y.min + self.range.y.max) / 2.0,
+        ];
+
+        self.plot_settings.calibration.applied_x = new_x;
+        self.plot_settings.calibration.applied_y = new_y;
+        self.plot_settings.egui_settings.reset_axis = true;
+        self.plot_settings.recalculate_image = true;
+    }
+}
@@ -0,0 +1,167 @@
+use egui::{Align2, Color32, ColorImage, FontId, Pos2, Rect, Stroke};
+use std::sync::Arc;
+
+use super::colormaps::ColorMap;
+use super::histogram2d::Histogram2D;
+use crate::histoer::figure::axes::ticks;
+use crate::histoer::figure::scene::{Anchor, Item};
+use crate::histoer::figure::settings::FigureSettings;
+use crate::histoer::stats_box::format_value;
+
+// Width kept free to the right of the plot for the bar and its labels
+pub const COLOR_BAR_WIDTH: f32 = 70.0;
+const BAR_WIDTH: f32 = 14.0;
+
+// Extra space taken from the right of a figure panel for the bar, in font sizes
+pub const FIGURE_COLOR_BAR_MARGIN: f32 = 3.4;
+
+impl Histogram2D {
+    // Counts at the bottom and top of the color bar
+    pub fn color_bar_range(&self) -> (u64, u64) {
+        let options = &self.plot_settings.colormap_options;
+        let (min, max) = options.display_range(self.bins.min_count, self.bins.max_count);
+        if options.log_norm {
+            (min.max(1), max.max(1))
+        } else {
+            (min, max)
+        }
+    }
+
+    // Labeled counts on the bar with their height on it from 0 (bottom) to 1 (top),
+    // decades with a log Z scale and round counts otherwise
+    pub fn color_bar_ticks(&self) -> Vec<(f32, String)> {
+        let options = &self.plot_settings.colormap_options;
+        let (min, max) = self.color_bar_range();
+        let (min, max) = (min as f64, max as f64);
+
+        let ticks: Vec<(f32, String)> = ticks(min, max, options.log_norm)
+            .into_iter()
+            .filter(|(value, _)| value.fract() == 0.0)
+            .map(|(value, label)| (options.normalize(value, min, max) as f32, label))
+            .collect();
+        if ticks.len() >= 2 {
+            return ticks;
+        }
+
+        // too narrow a range for round counts, label both ends
+        if max > min {
+            vec![(0.0, format_value(min)), (1.0, format_value(max))]
+        } else {
+            vec![(0.0, format_value(min))]
+        }
+    }
+
+    // Bar colors from the bottom to the top, one pixel per row
+    fn color_bar_image(&self, rows: usize) -> ColorImage {
+        let stops = self.plot_settings.colormap.stops(
+            &self.plot_settings.custom_colormap,
+            self.plot_settings.colormap_options,
+        );
+        let rows = rows.max(1);
+        ColorImage {
+            size: [1, rows],
+            pixels: (0..rows)
+                .map(|row| {
+                    let t = 1.0 - (row as f32 + 0.5) / rows as f32;
+                    ColorMap::interpolate(&stops, t)
+                })
+                .collect(),
+        }
+    }
+
+    // Color scale next to the plot, `frame` is the plot area
+    pub fn color_bar(&self, ui: &egui::Ui, frame: Rect) {
+        if !self.plot_settings.color_bar || self.bins.counts.is_empty() {
+            return;
+        }
+
+        let bar = Rect::from_min_max(
+            Pos2::new(frame.max.x + 8.0, frame.min.y),
+            Pos2::new(frame.max.x + 8.0 + BAR_WIDTH, frame.max.y),
+        );
+        let painter = ui.painter();
+
+        let image = self.color_bar_image(bar.height().max(1.0) as usize);
+        let band_height = bar.height() / image.size[1] as f32;
+        for (row, color) in image.pixels.iter().enumerate() {
+            let y = bar.min.y + row as f32 * band_height;
+            let band = Rect::from_min_max(
+                Pos2::new(bar.min.x, y),
+                Pos2::new(bar.max.x, y + band_height),
+            );
+            painter.rect_filled(band, 0.0, *color);
+        }
+        let stroke_color = ui.visuals().weak_text_color();
+        painter.rect_stroke(bar, 0.0, Stroke::new(1.0, stroke_color));
+
+        let font = FontId::proportional(11.0);
+        let color: Color32 = ui.visuals().text_color();
+        for (t, label) in self.color_bar_ticks() {
+            let y = bar.max.y - t * bar.height();
+            painter.line_segment(
+                [Pos2::new(bar.max.x - 4.0, y), Pos2::new(bar.max.x, y)],
+                Stroke::new(1.0, stroke_color),
+            );
+            painter.text(
+                Pos2::new(bar.max.x + 3.0, y),
+                Align2::LEFT_CENTER,
+                label,
+                font.clone(),
+                color,
+            );
+        }
+    }
+
+    // Color bar of a figure, in the margin to the right of the plot area `rect`
+    pub fn color_bar_items(&self, rect: Rect, settings: &FigureSettings) -> Vec<Item> {
+        if !self.plot_settings.color_bar || self.bins.counts.is_empty() {
+            return Vec::new();
+        }
+
+        let size = settings.font_size;
+        let black = Color32::BLACK;
+        let bar = Rect::from_min_max(
+            Pos2::new(rect.max.x + 0.8 * size, rect.min.y),
+            Pos2::new(rect.max.x + 1.6 * size, rect.max.y),
+        );
+
+        let mut items = vec![
+            Item::Image {
+                rect: bar,
+                image: Arc::new(self.color_bar_image(256)),
+            },
+            Item::Line {
+                points: vec![
+                    bar.left_top(),
+                    bar.right_top(),
+                    bar.right_bottom(),
+                    bar.left_bottom(),
+                    bar.left_top(),
+                ],
+                color: black,
+                width: 1.0,
+            },
+        ];
+
+        for (t, label) in self.color_bar_ticks() {
+            let y = bar.max.y - t * bar.height();
+            items.push(Item::Line {
+                points: vec![
+                    Pos2::new(bar.max.x - 0.3 * size, y),
+                    Pos2::new(bar.max.x, y),
+                ],
+                color: black,
+                width: 1.0,
+            });
+            items.push(Item::Text {
+                pos: Pos2::new(bar.max.x + 0.3 * size, y + 0.35 * size),
+                text: label,
+                size,
+                color: black,
+                anchor: Anchor::Start,
+                vertical: false,
+            });
+        }
+        items
+    }
+}
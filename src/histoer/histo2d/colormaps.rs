@@ -12,11 +12,15 @@ pub enum ColorMap {
     ExtendedKindlmann,
     Turbo,
     Jet,
+    Grayscale,
+    Custom, // the stops of the pane's CustomColormap
 }
 
 #[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ColormapOptions {
     pub log_norm: bool,
+    #[serde(default)]
+    pub sqrt_norm: bool, // ignored with log_norm
     pub reverse: bool,
     pub custom_display_range: bool,
     pub remove: bool,
@@ -28,6 +32,7 @@ impl Default for ColormapOptions {
     fn default() -> Self {
         ColormapOptions {
             log_norm: true,
+            sqrt_norm: false,
             reverse: false,
             custom_display_range: false,
             remove: false,
@@ -39,13 +44,31 @@ impl Default for ColormapOptions {
 
 impl ColormapOptions {
     pub fn ui(&mut self, ui: &mut egui::Ui, recalculate_image: &mut bool, max_z_range: u64) {
-        if ui
-            .checkbox(&mut self.log_norm, "Log Z")
-            .on_hover_text("Log the Z values. 0 bins will become transparent")
-            .changed()
-        {
-            *recalculate_image = true;
-        };
+        ui.horizontal(|ui| {
+            ui.label("Z Scale");
+            let linear = !self.log_norm && !self.sqrt_norm;
+            if ui.radio(linear, "Linear").clicked() && !linear {
+                self.log_norm = false;
+                self.sqrt_norm = false;
+                *recalculate_image = true;
+            }
+            if ui
+                .radio(self.log_norm, "Log")
+                .on_hover_text("Log the Z values. 0 bins will become transparent")
+                .clicked()
+                && !self.log_norm
+            {
+                self.log_norm = true;
+                self.sqrt_norm = false;
+                *recalculate_image = true;
+            }
+            let sqrt = self.sqrt_norm && !self.log_norm;
+            if ui.radio(sqrt, "Sqrt").clicked() && !sqrt {
+                self.log_norm = false;
+                self.sqrt_norm = true;
+                *recalculate_image = true;
+            }
+        });
         if ui
             .checkbox(&mut self.reverse, "Reverse")
             .on_hover_text("Reverse the color map intensity values")
@@ -100,38 +123,157 @@ impl ColormapOptions {
             };
         }
     }
+
+    // Range of counts spanning the colormap
+    pub fn display_range(&self, min: u64, max: u64) -> (u64, u64) {
+        if self.custom_display_range {
+            (self.display_min, self.display_max)
+        } else {
+            (min, max)
+        }
+    }
+
+    // Position of a count on the colormap, from 0 to 1
+    pub fn normalize(&self, value: f64, min: f64, max: f64) -> f64 {
+        // Handle case where min == max to avoid division by zero
+        if max <= min {
+            return 0.0;
+        }
+
+        if self.log_norm {
+            let min = min.max(1.0);
+            if max <= min {
+                return 0.0;
+            }
+            (value.log10() - min.log10()) / (max.log10() - min.log10())
+        } else if self.sqrt_norm {
+            (value.max(0.0).sqrt() - min.max(0.0).sqrt()) / (max.sqrt() - min.max(0.0).sqrt())
+        } else {
+            (value - min) / (max - min)
+        }
+        .clamp(0.0, 1.0)
+    }
+}
+
+// Colormap made of user defined stops, used by ColorMap::Custom
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CustomColormap {
+    pub stops: Vec<(f32, egui::Color32)>, // position from 0 to 1, color
+}
+
+impl Default for CustomColormap {
+    fn default() -> Self {
+        CustomColormap {
+            stops: vec![
+                (0.0, egui::Color32::from_rgb(0, 0, 128)),
+                (0.5, egui::Color32::WHITE),
+                (1.0, egui::Color32::from_rgb(128, 0, 0)),
+            ],
+        }
+    }
+}
+
+impl CustomColormap {
+    fn scalar_stops(&self) -> Vec<(f32, i32, i32, i32)> {
+        let mut stops: Vec<_> = self
+            .stops
+            .iter()
+            .map(|(position, color)| {
+                (
+                    position.clamp(0.0, 1.0),
+                    color.r() as i32,
+                    color.g() as i32,
+                    color.b() as i32,
+                )
+            })
+            .collect();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if stops.is_empty() {
+            stops = grayscale();
+        }
+        stops
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, recalculate_image: &mut bool) {
+        let mut remove = None;
+        for (index, (position, color)) in self.stops.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .add(
+                        egui::DragValue::new(position)
+                            .range(0.0..=1.0)
+                            .speed(0.01)
+                            .max_decimals(3),
+                    )
+                    .changed()
+                {
+                    *recalculate_image = true;
+                }
+                if ui.color_edit_button_srgba(color).changed() {
+                    *recalculate_image = true;
+                }
+                if ui.button("🗙").clicked() {
+                    remove = Some(index);
+                }
+            });
+        }
+        if let Some(index) = remove {
+            self.stops.remove(index);
+            *recalculate_image = true;
+        }
+
+        if ui.button("Add Stop").clicked() {
+            // halfway between the last two stops
+            let position = match self.stops.len() {
+                0 => 0.0,
+                1 => 1.0,
+                n => (self.stops[n - 2].0 + self.stops[n - 1].0) / 2.0,
+            };
+            self.stops.push((position, egui::Color32::GRAY));
+            *recalculate_image = true;
+        }
+    }
 }
 
 impl ColorMap {
-    pub fn color(
+    // Stops of the colormap, with the colors reversed when set in the options
+    pub fn stops(
         &self,
-        count: u64,
-        min_count: u64,
-        max_count: u64,
+        custom: &CustomColormap,
         options: ColormapOptions,
-    ) -> egui::Color32 {
-        match self {
-            ColorMap::Viridis => Self::colormap(viridis(), count, min_count, max_count, options),
-            ColorMap::Fast => Self::colormap(fast(), count, min_count, max_count, options),
-            ColorMap::SmoothCoolWarm => {
-                Self::colormap(smooth_cool_warm(), count, min_count, max_count, options)
-            }
-            ColorMap::BentCoolWarm => {
-                Self::colormap(bent_cool_warm(), count, min_count, max_count, options)
-            }
-            ColorMap::Plasma => Self::colormap(plasma(), count, min_count, max_count, options),
-            ColorMap::Blackbody => {
-                Self::colormap(blackbody(), count, min_count, max_count, options)
-            }
-            ColorMap::Inferno => Self::colormap(inferno(), count, min_count, max_count, options),
-            ColorMap::Kindlmann => {
-                Self::colormap(kindlmann(), count, min_count, max_count, options)
-            }
-            ColorMap::ExtendedKindlmann => {
-                Self::colormap(extended_kindlmann(), count, min_count, max_count, options)
-            }
-            ColorMap::Turbo => Self::colormap(turbo(), count, min_count, max_count, options),
-            ColorMap::Jet => Self::colormap(jet(), count, min_count, max_count, options),
+    ) -> Vec<(f32, i32, i32, i32)> {
+        let color_data = match self {
+            ColorMap::Viridis => viridis(),
+            ColorMap::Fast => fast(),
+            ColorMap::SmoothCoolWarm => smooth_cool_warm(),
+            ColorMap::BentCoolWarm => bent_cool_warm(),
+            ColorMap::Plasma => plasma(),
+            ColorMap::Blackbody => blackbody(),
+            ColorMap::Inferno => inferno(),
+            ColorMap::Kindlmann => kindlmann(),
+            ColorMap::ExtendedKindlmann => extended_kindlmann(),
+            ColorMap::Turbo => turbo(),
+            ColorMap::Jet => jet(),
+            ColorMap::Grayscale => grayscale(),
+            ColorMap::Custom => custom.scalar_stops(),
+        };
+
+        // Reverse RGB values while keeping scalar values the same
+        if options.reverse {
+            let mut reversed_rgb = color_data
+                .iter()
+                .map(|&(_s, r, g, b)| (r, g, b))
+                .collect::<Vec<_>>();
+            reversed_rgb.reverse();
+            color_data
+                .iter()
+                .enumerate()
+                .map(|(i, &(s, _, _, _))| {
+                    (s, reversed_rgb[i].0, reversed_rgb[i].1, reversed_rgb[i].2)
+                })
+                .collect::<Vec<_>>()
+        } else {
+            color_data
         }
     }
 
@@ -151,6 +293,8 @@ impl ColorMap {
             ui.radio_value(self, ColorMap::ExtendedKindlmann, "Extended Kindlmann");
             ui.radio_value(self, ColorMap::Turbo, "Turbo");
             ui.radio_value(self, ColorMap::Jet, "Jet");
+            ui.radio_value(self, ColorMap::Grayscale, "Grayscale");
+            ui.radio_value(self, ColorMap::Custom, "Custom");
         });
 
         if new_colormap != *self {
@@ -170,12 +314,15 @@ impl ColorMap {
             ColorMap::Kindlmann => ColorMap::ExtendedKindlmann,
             ColorMap::ExtendedKindlmann => ColorMap::Turbo,
             ColorMap::Turbo => ColorMap::Jet,
-            ColorMap::Jet => ColorMap::Viridis,
+            ColorMap::Jet => ColorMap::Grayscale,
+            ColorMap::Grayscale => ColorMap::Custom,
+            ColorMap::Custom => ColorMap::Viridis,
         };
     }
 
-    fn colormap(
-        color_data: Vec<(f32, i32, i32, i32)>,
+    // Color of a bin from the stops of the colormap
+    pub fn color(
+        stops: &[(f32, i32, i32, i32)],
         value: u64,
         min: u64,
         max: u64,
@@ -187,11 +334,7 @@ impl ColorMap {
         }
 
         // Handle display range options
-        let (display_min, display_max) = if options.custom_display_range {
-            (options.display_min, options.display_max)
-        } else {
-            (min, max)
-        };
+        let (display_min, display_max) = options.display_range(min, max);
 
         if options.custom_display_range {
             if options.remove && value < display_min {
@@ -205,54 +348,27 @@ impl ColorMap {
             }
         }
 
-        // Reverse RGB values while keeping scalar values the same
-        let color_data = if options.reverse {
-            let mut reversed_rgb = color_data
-                .iter()
-                .map(|&(_s, r, g, b)| (r, g, b))
-                .collect::<Vec<_>>();
-            reversed_rgb.reverse();
-            color_data
-                .iter()
-                .enumerate()
-                .map(|(i, &(s, _, _, _))| {
-                    (s, reversed_rgb[i].0, reversed_rgb[i].1, reversed_rgb[i].2)
-                })
-                .collect::<Vec<_>>()
-        } else {
-            color_data
-        };
-
-        // Convert min and max to f64 for calculations
-        let (min_f64, max_f64) = (display_min as f64, display_max as f64);
-
-        // Handle case where min == max to avoid division by zero
-        let normalized: f64 = if max_f64 > min_f64 {
-            let value_f64 = value as f64;
-            if options.log_norm {
-                // Use logarithmic scale
-                (value_f64.log10() - min_f64.log10()) / (max_f64.log10() - min_f64.log10())
-            } else {
-                // Use linear scale
-                (value_f64 - min_f64) / (max_f64 - min_f64)
-            }
-        } else {
-            0.0
-        }
-        .clamp(0.0, 1.0);
+        let normalized = options.normalize(value as f64, display_min as f64, display_max as f64);
+        Self::interpolate(stops, normalized as f32)
+    }
 
-        // Interpolate between colors in the colormap based on the scale value
-        let mut lower = color_data[0];
-        let mut upper = color_data[0];
-        for color in color_data.iter().skip(1) {
-            if color.0 >= normalized as f32 {
+    // Interpolate between colors in the colormap based on the scale value
+    pub fn interpolate(stops: &[(f32, i32, i32, i32)], normalized: f32) -> egui::Color32 {
+        let mut lower = stops[0];
+        let mut upper = stops[0];
+        for color in stops.iter().skip(1) {
+            if color.0 >= normalized {
                 upper = *color;
                 break;
             }
             lower = *color;
         }
 
-        let scale = (normalized as f32 - lower.0) / (upper.0 - lower.0);
+        let scale = if upper.0 > lower.0 {
+            ((normalized - lower.0) / (upper.0 - lower.0)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
         let red = (lower.1 as f32 + scale * (upper.1 as f32 - lower.1 as f32)).clamp(0.0, 255.0);
         let green = (lower.2 as f32 + scale * (upper.2 as f32 - lower.2 as f32)).clamp(0.0, 255.0);
         let blue = (lower.3 as f32 + scale * (upper.3 as f32 - lower.3 as f32)).clamp(0.0, 255.0);
@@ -261,6 +377,10 @@ impl ColorMap {
     }
 }
 
+fn grayscale() -> Vec<(f32, i32, i32, i32)> {
+    vec![(0.0, 0, 0, 0), (1.0, 255, 255, 255)]
+}
+
 // scalar,RGB_r,RGB_g,RGB_b
 fn viridis() -> Vec<(f32, i32, i32, i32)> {
     vec![
@@ -1,11 +1,38 @@
 use super::histogram2d::Histogram2D;
 use crate::histoer::cuts::Cut2D;
+use crate::histoer::jobs::Job;
+use std::sync::{Arc, Mutex};
 
 impl Histogram2D {
     // Context menu for the plot (when you right-click on the plot)
     pub fn context_menu(&mut self, ui: &mut egui::Ui) {
         self.image.menu_button(ui);
         self.plot_settings.settings_ui(ui, self.bins.max_count);
+        if let Some(extension) = self.plot_settings.figure.menu_button(ui) {
+            self.save_figure(extension);
+        }
+
+        ui.menu_button("Calibration", |ui| {
+            if self.plot_settings.calibration.menu_button(ui) {
+                self.apply_calibration();
+            }
+        });
+
+        ui.menu_button("Gate Sweep", |ui| {
+            if self.plot_settings.gate_sweep.menu_button(ui) {
+                self.gate_sweep();
+            }
+        });
+
+        ui.menu_button("Band Slices", |ui| {
+            self.band_slices_menu(ui);
+        });
+
+        ui.menu_button("Storage", |ui| {
+            self.storage_ui(ui);
+        });
+
+        ui.separator();
 
         ui.horizontal(|ui| {
             ui.heading("Cuts");
@@ -13,6 +40,9 @@ impl Histogram2D {
             if ui.button("+").clicked() {
                 self.new_cut();
             }
+
+            ui.checkbox(&mut self.plot_settings.freehand_cuts, "Freehand")
+                .on_hover_text("Draw new cuts by pressing and dragging around the region instead of clicking each vertex");
         });
 
         ui.horizontal(|ui| {
@@ -32,21 +62,35 @@ impl Histogram2D {
         });
 
         let mut to_remove = None;
+        let mut to_promote = None;
 
-        for (index, cut) in self.plot_settings.cuts.iter_mut().enumerate() {
+        for (index, cut) in self.plot_settings.cuts.iter().enumerate() {
             ui.horizontal(|ui| {
                 if ui.button("X").clicked() {
                     to_remove = Some(index);
                 }
 
+                if ui
+                    .button("Promote")
+                    .on_hover_text("Add the cut to the workspace cuts so other histograms can use it on the next fill")
+                    .clicked()
+                {
+                    to_promote = Some(index);
+                }
+
                 ui.separator();
 
-                cut.ui(ui);
+                cut.lock().unwrap().ui(ui);
             });
         }
 
+        if let Some(index) = to_promote {
+            self.promote_cut(index);
+        }
+
         if let Some(index) = to_remove {
-            self.plot_settings.cuts.remove(index);
+            let cut = self.plot_settings.cuts.remove(index);
+            self.plot_settings.removed_cuts.push((index, cut));
         }
 
         ui.separator();
@@ -68,8 +112,7 @@ impl Histogram2D {
                     )
                     .clicked()
                 {
-                    self.plot_settings.rebin_x_factor = factor;
-                    self.rebin();
+                    self.submit(Job::Rebin2D(factor, self.plot_settings.rebin_y_factor));
                 }
             }
         });
@@ -84,15 +127,64 @@ impl Histogram2D {
                     )
                     .clicked()
                 {
-                    self.plot_settings.rebin_y_factor = factor;
-                    self.rebin();
+                    self.submit(Job::Rebin2D(self.plot_settings.rebin_x_factor, factor));
                 }
             }
         });
+
+        if ui
+            .button("Swap Axes")
+            .on_hover_text("Transpose the histogram, later fills keep the swapped axes")
+            .clicked()
+        {
+            self.swap_axes();
+            ui.close_menu();
+        }
+
+        ui.separator();
+
+        ui.heading("Merge");
+        self.merge_ui(ui);
+
+        ui.separator();
+        if ui
+            .button("Export Data…")
+            .on_hover_text("Filled bins as x, y, count rows in CSV or JSON (by the file extension)")
+            .clicked()
+        {
+            if let Err(e) = self.export_data() {
+                log::error!("Error exporting '{}': {}", self.name, e);
+            }
+            ui.close_menu();
+        }
+    }
+
+    // Queues a copy of the cut for the workspace cuts, with the columns of the histogram
+    // when the cut has none
+    pub fn promote_cut(&mut self, index: usize) {
+        let mut cut = self.plot_settings.cuts[index].lock().unwrap().clone();
+        if cut.x_column.is_empty() {
+            cut.x_column = self.plot_settings.x_column.clone();
+        }
+        if cut.y_column.is_empty() {
+            cut.y_column = self.plot_settings.y_column.clone();
+        }
+        if cut.x_column.is_empty() || cut.y_column.is_empty() {
+            log::error!(
+                "Cut '{}' needs x and y columns to be used in a fill",
+                cut.polygon.name
+            );
+            return;
+        }
+
+        cut.polygon.interactive_clicking = false;
+        cut.polygon.interactive_dragging = false;
+        self.plot_settings.promoted_cuts.push(cut);
     }
 
     pub fn new_cut(&mut self) {
-        for cut in &mut self.plot_settings.cuts {
+        for cut in &self.plot_settings.cuts {
+            let mut cut = cut.lock().unwrap();
             cut.polygon.interactive_clicking = false;
             cut.polygon.interactive_dragging = false;
         }
@@ -105,6 +197,7 @@ impl Histogram2D {
         cut.polygon.name = format!("Cut {}", self.plot_settings.cuts.len());
 
         cut.polygon.interactive_clicking = true;
-        self.plot_settings.cuts.push(cut);
+        cut.polygon.freehand = self.plot_settings.freehand_cuts;
+        self.plot_settings.cuts.push(Arc::new(Mutex::new(cut)));
     }
 }
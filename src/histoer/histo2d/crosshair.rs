@@ -0,0 +1,33 @@
+use super::histogram2d::Histogram2D;
+use crate::histoer::stats_box::{format_value, readout_box};
+
+impl Histogram2D {
+    // x, y, and bin content under the mouse
+    pub fn show_crosshair(&self, ui: &egui::Ui, transform: &egui_plot::PlotTransform) {
+        if !self.plot_settings.crosshair {
+            return;
+        }
+        let Some(cursor) = self.plot_settings.cursor_position else {
+            return;
+        };
+
+        let bin = self
+            .get_bin_index_x(cursor.x)
+            .zip(self.get_bin_index_y(cursor.y));
+        let rows = [
+            ("X", format_value(cursor.x)),
+            ("Y", format_value(cursor.y)),
+            (
+                "Bin",
+                bin.map(|(x, y)| format!("{}, {}", x, y))
+                    .unwrap_or("-".to_string()),
+            ),
+            (
+                "Content",
+                bin.map(|(x, y)| self.bins.counts.get(x, y).to_string())
+                    .unwrap_or("-".to_string()),
+            ),
+        ];
+        readout_box(ui, *transform.frame(), "Cursor", &rows);
+    }
+}
@@ -0,0 +1,224 @@
+use super::histogram2d::Histogram2D;
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum GateAxis {
+    X, // gate on X, project onto Y
+    Y, // gate on Y, project onto X
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum SweepQuantity {
+    Integral,
+    NetArea, // integral minus a linear background from the region edges
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GateSweep {
+    pub show: bool,
+    pub axis: GateAxis,
+    pub quantity: SweepQuantity,
+    pub start: f64,
+    pub end: f64,
+    pub width: f64,
+    pub step: f64,
+    pub region: (f64, f64), // region of the gated spectrum the quantity is computed in
+    #[serde(skip)]
+    pub results: Vec<[f64; 3]>, // gate center, value, uncertainty
+}
+
+impl Default for GateSweep {
+    fn default() -> Self {
+        GateSweep {
+            show: false,
+            axis: GateAxis::X,
+            quantity: SweepQuantity::Integral,
+            start: 0.0,
+            end: 4096.0,
+            width: 100.0,
+            step: 50.0,
+            region: (0.0, 4096.0),
+            results: Vec::new(),
+        }
+    }
+}
+
+impl GateSweep {
+    // Returns true when the sweep should be (re)calculated
+    pub fn menu_button(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut run = false;
+
+        ui.heading("Gate Sweep");
+
+        ui.horizontal(|ui| {
+            ui.label("Gate on:");
+            ui.radio_value(&mut self.axis, GateAxis::X, "X");
+            ui.radio_value(&mut self.axis, GateAxis::Y, "Y");
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.start)
+                    .speed(1.0)
+                    .prefix("Start: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.end)
+                    .speed(1.0)
+                    .prefix("End: "),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.width)
+                    .speed(1.0)
+                    .range(0.0..=f64::INFINITY)
+                    .prefix("Width: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.step)
+                    .speed(1.0)
+                    .range(0.0..=f64::INFINITY)
+                    .prefix("Step: "),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Region:");
+            ui.add(egui::DragValue::new(&mut self.region.0).speed(1.0));
+            ui.add(egui::DragValue::new(&mut self.region.1).speed(1.0));
+        })
+        .response
+        .on_hover_text("Range of the gated spectrum used for the quantity");
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.quantity, SweepQuantity::Integral, "Integral");
+            ui.radio_value(&mut self.quantity, SweepQuantity::NetArea, "Net Area")
+                .on_hover_text(
+                    "Integral minus a linear background estimated from the region edges",
+                );
+        });
+
+        if ui
+            .add_enabled(
+                self.step > 0.0 && self.width > 0.0 && self.end > self.start,
+                egui::Button::new("Sweep"),
+            )
+            .clicked()
+        {
+            run = true;
+            self.show = true;
+        }
+
+        ui.separator();
+
+        run
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, name: &str) {
+        if !self.show {
+            return;
+        }
+
+        let mut show = self.show;
+        let ctx = ui.ctx().clone();
+        egui::Window::new(format!("Gate Sweep of {}", name))
+            .open(&mut show)
+            .show(&ctx, |ui| {
+                if ui
+                    .button("Copy")
+                    .on_hover_text("Copy center, value, uncertainty")
+                    .clicked()
+                {
+                    let text = self
+                        .results
+                        .iter()
+                        .map(|[c, v, u]| format!("{}\t{}\t{}", c, v, u))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    ui.ctx().copy_text(text);
+                }
+
+                let points: Vec<[f64; 2]> = self.results.iter().map(|[c, v, _]| [*c, *v]).collect();
+
+                egui_plot::Plot::new(format!("gate_sweep_{}", name))
+                    .x_axis_label("Gate Center")
+                    .y_axis_label(match self.quantity {
+                        SweepQuantity::Integral => "Integral",
+                        SweepQuantity::NetArea => "Net Area",
+                    })
+                    .show(ui, |plot_ui| {
+                        for [center, value, uncertainty] in &self.results {
+                            plot_ui.line(
+                                egui_plot::Line::new(vec![
+                                    [*center, value - uncertainty],
+                                    [*center, value + uncertainty],
+                                ])
+                                .color(egui::Color32::GRAY),
+                            );
+                        }
+                        plot_ui.line(egui_plot::Line::new(points.clone()).name(name));
+                        plot_ui.points(egui_plot::Points::new(points).radius(3.0));
+                    });
+            });
+        self.show = show;
+    }
+}
+
+impl Histogram2D {
+    pub fn gate_sweep(&mut self) {
+        let sweep = self.plot_settings.gate_sweep.clone();
+        let mut results = Vec::new();
+
+        // the gated spectrum lies along the other axis
+        let (projected_min, projected_width) = match sweep.axis {
+            GateAxis::X => (self.range.y.min, self.bins.y_width),
+            GateAxis::Y => (self.range.x.min, self.bins.x_width),
+        };
+
+        let (region_min, region_max) = if sweep.region.0 < sweep.region.1 {
+            sweep.region
+        } else {
+            (sweep.region.1, sweep.region.0)
+        };
+
+        let mut low = sweep.start;
+        while low + sweep.width <= sweep.end + f64::EPSILON {
+            let high = low + sweep.width;
+            let gated = match sweep.axis {
+                GateAxis::X => self.y_projection(low, high),
+                GateAxis::Y => self.x_projection(low, high),
+            };
+
+            let counts: Vec<f64> = gated
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| {
+                    let center = projected_min + (*index as f64 + 0.5) * projected_width;
+                    center >= region_min && center < region_max
+                })
+                .map(|(_, &count)| count as f64)
+                .collect();
+
+            let total: f64 = counts.iter().sum();
+            let (value, uncertainty) = match sweep.quantity {
+                SweepQuantity::Integral => (total, total.sqrt()),
+                SweepQuantity::NetArea => {
+                    let edges = counts.first().unwrap_or(&0.0) + counts.last().unwrap_or(&0.0);
+                    let n = counts.len() as f64;
+                    let background = edges / 2.0 * n;
+                    (
+                        total - background,
+                        (total + (n / 2.0).powi(2) * edges).sqrt(),
+                    )
+                }
+            };
+
+            results.push([(low + high) / 2.0, value, uncertainty]);
+            low += sweep.step;
+        }
+
+        log::info!("Gate sweep of '{}': {} steps", self.name, results.len());
+        self.plot_settings.gate_sweep.results = results;
+    }
+}
@@ -1,8 +1,11 @@
-use fnv::FnvHashMap;
 use rayon::prelude::*;
 
 use crate::egui_plot_stuff::egui_image::EguiImage;
+use crate::histoer::jobs::JobQueue;
 
+use super::bin_counts::BinCounts;
+use super::color_bar::COLOR_BAR_WIDTH;
+use super::colormaps::ColorMap;
 use super::plot_settings::PlotSettings;
 
 #[derive(Clone, serde::Deserialize, serde::Serialize)]
@@ -15,6 +18,10 @@ pub struct Histogram2D {
     pub plot_settings: PlotSettings,
     pub image: EguiImage,
     pub backup_bins: Option<Bins>,
+    #[serde(default)]
+    pub swapped: bool, // filled with (y, x) after the axes were swapped
+    #[serde(skip)]
+    pub jobs: JobQueue,
 }
 
 impl Histogram2D {
@@ -27,7 +34,7 @@ impl Histogram2D {
                 x_width: (range.0 .1 - range.0 .0) / bins.0 as f64,
                 y: bins.1,
                 y_width: (range.1 .1 - range.1 .0) / bins.1 as f64,
-                counts: FnvHashMap::default(),
+                counts: BinCounts::default(),
                 min_count: u64::MAX,
                 max_count: u64::MIN,
             },
@@ -50,9 +57,16 @@ impl Histogram2D {
                 [range.1 .0, range.1 .1],
             ),
             backup_bins: None,
+            swapped: false,
+            jobs: JobQueue::default(),
         }
     }
 
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+        self.image.name = name.to_string();
+    }
+
     pub fn reset(&mut self) {
         self.bins.counts.clear();
         self.bins.min_count = u64::MAX;
@@ -61,6 +75,13 @@ impl Histogram2D {
     }
 
     pub fn fill(&mut self, x_value: f64, y_value: f64) {
+        let (x_value, y_value) = if self.swapped {
+            (y_value, x_value)
+        } else {
+            (x_value, y_value)
+        };
+        let (x_value, y_value) = self.calibrate_point(x_value, y_value);
+
         if x_value < self.range.x.min {
             self.underflow.0 += 1; // Increment x-axis underflow
         } else if x_value >= self.range.x.max {
@@ -74,11 +95,13 @@ impl Histogram2D {
             let x_index = ((x_value - self.range.x.min) / self.bins.x_width) as usize;
             let y_index = ((y_value - self.range.y.min) / self.bins.y_width) as usize;
 
-            let count = self.bins.counts.entry((x_index, y_index)).or_insert(0);
-            *count += 1;
+            let count = self.bins.counts.add(x_index, y_index, 1);
 
-            self.bins.min_count = self.bins.min_count.min(*count);
-            self.bins.max_count = self.bins.max_count.max(*count);
+            self.bins.min_count = self.bins.min_count.min(count);
+            self.bins.max_count = self.bins.max_count.max(count);
+            if count == 1 && !self.bins.counts.is_dense() {
+                self.bins.update_storage(self.plot_settings.storage);
+            }
         }
     }
 
@@ -105,11 +128,15 @@ impl Histogram2D {
     }
 
     // Convert histogram data to a ColorImage in parallel using Rayon
-    fn data_2_image(&self) -> egui::ColorImage {
+    pub fn data_2_image(&self) -> egui::ColorImage {
         let width = ((self.range.x.max - self.range.x.min) / self.bins.x_width) as usize;
         let height = ((self.range.y.max - self.range.y.min) / self.bins.y_width) as usize;
 
         let colormap_options = self.plot_settings.colormap_options;
+        let stops = self
+            .plot_settings
+            .colormap
+            .stops(&self.plot_settings.custom_colormap, colormap_options);
 
         // Parallelize over rows, and for each row, compute pixel colors for all columns
         let pixels: Vec<_> = (0..height)
@@ -117,13 +144,9 @@ impl Histogram2D {
             .map(|y| {
                 (0..width)
                     .map(|x| {
-                        let count = self
-                            .bins
-                            .counts
-                            .get(&(x, height - y - 1))
-                            .cloned()
-                            .unwrap_or(0);
-                        self.plot_settings.colormap.color(
+                        let count = self.bins.counts.get(x, height - y - 1);
+                        ColorMap::color(
+                            &stops,
                             count,
                             self.bins.min_count,
                             self.bins.max_count,
@@ -190,8 +213,6 @@ impl Histogram2D {
 
     // Draw the histogram on the plot
     fn draw(&mut self, plot_ui: &mut egui_plot::PlotUi) {
-        self.show_stats(plot_ui);
-
         let heatmap_image = self.image.get_plot_image_from_texture();
 
         if let Some(image) = heatmap_image {
@@ -209,7 +230,9 @@ impl Histogram2D {
 
         self.plot_settings.egui_settings.allow_drag = !self.plot_settings.projections.dragging;
 
-        if self.plot_settings.egui_settings.reset_axis {
+        if self.plot_settings.egui_settings.update_bounds(plot_ui) {
+            // manual bounds take priority over resetting and clamping
+        } else if self.plot_settings.egui_settings.reset_axis {
             self.plot_settings.egui_settings.reset_axis_lims(plot_ui);
         } else if self.plot_settings.egui_settings.limit_scrolling {
             self.limit_scrolling(plot_ui);
@@ -218,6 +241,9 @@ impl Histogram2D {
 
     // Render the histogram using egui_plot
     pub fn render(&mut self, ui: &mut egui::Ui) {
+        self.run_queued_jobs();
+        self.jobs.status_ui(ui);
+
         // Recalculate the image if the settings have changed, like the colormap
         if self.plot_settings.recalculate_image {
             self.calculate_image(ui);
@@ -238,6 +264,12 @@ impl Histogram2D {
 
         let mut plot = egui_plot::Plot::new(self.name.clone());
         plot = self.plot_settings.egui_settings.apply_to_plot(plot);
+        if let Some((link, axes)) = self.plot_settings.axis_link {
+            plot = plot.link_axis(link, axes);
+        }
+        if self.plot_settings.color_bar {
+            plot = plot.width((ui.available_width() - COLOR_BAR_WIDTH).max(100.0));
+        }
 
         if self.image.texture.is_none() {
             self.calculate_image(ui);
@@ -245,11 +277,15 @@ impl Histogram2D {
 
         self.check_projections();
         self.plot_settings.projections.show(ui);
+        self.plot_settings.gate_sweep.show(ui, &self.name);
+        self.plot_settings.notes.ui(ui, &self.name);
 
         let plot_response = plot.show(ui, |plot_ui| {
             self.draw(plot_ui);
 
-            if self.plot_settings.cursor_position.is_some() {
+            if self.plot_settings.cursor_position.is_some()
+                && !self.plot_settings.egui_settings.lock_bounds
+            {
                 if let Some(delta_pos) = scroll {
                     if delta_pos.y > 0.0 {
                         plot_ui.zoom_bounds_around_hovered(egui::Vec2::new(1.1, 1.1));
@@ -264,6 +300,14 @@ impl Histogram2D {
             }
         });
 
+        if let Some(fraction) = self.plot_settings.preview {
+            crate::histoer::preview::watermark(ui, plot_response.response.rect, fraction);
+        }
+
+        self.show_stats(ui, &plot_response.transform);
+        self.show_crosshair(ui, &plot_response.transform);
+        self.color_bar(ui, *plot_response.transform.frame());
+
         plot_response.response.context_menu(|ui| {
             self.context_menu(ui);
         });
@@ -280,7 +324,7 @@ pub struct Bins {
     pub x_width: f64,
     pub y: usize,
     pub y_width: f64,
-    pub counts: FnvHashMap<(usize, usize), u64>, // uses a hash map to store the histogram data (zero overhead for empty bins)
+    pub counts: BinCounts, // hash map of the filled bins, or a dense vector for full histograms
     pub min_count: u64,
     pub max_count: u64,
 }
@@ -0,0 +1,25 @@
+use super::histogram2d::Histogram2D;
+use crate::histoer::jobs::Job;
+
+impl Histogram2D {
+    // Runs the job now, or queues it while the histogram is being filled
+    pub fn submit(&mut self, job: Job) {
+        if let Some(job) = self.jobs.submit(job) {
+            self.run_job(job);
+        }
+    }
+
+    pub fn run_queued_jobs(&mut self) {
+        for job in self.jobs.take_ready() {
+            self.run_job(job);
+        }
+    }
+
+    fn run_job(&mut self, job: Job) {
+        if let Job::Rebin2D(x_factor, y_factor) = job {
+            self.plot_settings.rebin_x_factor = x_factor;
+            self.plot_settings.rebin_y_factor = y_factor;
+            self.rebin();
+        }
+    }
+}
@@ -1,8 +1,17 @@
+pub mod band_slices;
+pub mod bin_counts;
+pub mod calibration;
+pub mod color_bar;
 pub mod colormaps;
 pub mod context_menu;
+pub mod crosshair;
+pub mod gate_sweep;
 pub mod histogram2d;
+pub mod jobs;
 pub mod keybinds;
 pub mod plot_settings;
 pub mod projections;
+pub mod pulls;
 pub mod rebinning;
 pub mod statistics;
+pub mod swap_axes;
@@ -1,24 +1,59 @@
 use crate::histoer::cuts::Cut2D;
+use crate::histoer::figure::settings::FigureSettings;
+use crate::histoer::notes::Notes;
+use std::sync::{Arc, Mutex};
 
 use crate::egui_plot_stuff::egui_plot_settings::EguiPlotSettings;
 
-use super::colormaps::{ColorMap, ColormapOptions};
+use super::band_slices::BandSlices;
+use super::bin_counts::Storage;
+use super::calibration::Calibration2D;
+use super::colormaps::{ColorMap, ColormapOptions, CustomColormap};
+use super::gate_sweep::GateSweep;
 use super::projections::Projections;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct PlotSettings {
     #[serde(skip)]
     pub cursor_position: Option<egui_plot::PlotPoint>,
     pub egui_settings: EguiPlotSettings,
     pub x_column: String,
     pub y_column: String,
-    pub cuts: Vec<Cut2D>,
+    pub cuts: Vec<Arc<Mutex<Cut2D>>>, // shared with other panes drawing the same cut
+    #[serde(skip)]
+    pub removed_cuts: Vec<(usize, Arc<Mutex<Cut2D>>)>, // removed in the UI, collected for undo
+    #[serde(skip)]
+    pub promoted_cuts: Vec<Cut2D>, // promoted in the UI, collected into the workspace cuts
+    #[serde(default)]
+    pub freehand_cuts: bool, // new cuts are drawn by dragging around the region
     pub stats_info: bool,
+    #[serde(default)]
+    pub crosshair: bool,
+    #[serde(skip)]
+    pub axis_link: Option<(egui::Id, egui::Vec2b)>, // set by the tree when the grid links axes
     pub colormap: ColorMap,
     pub colormap_options: ColormapOptions,
+    #[serde(default)]
+    pub custom_colormap: CustomColormap,
+    #[serde(default = "default_color_bar")]
+    pub color_bar: bool,
     pub projections: Projections,
+    #[serde(default)]
+    pub gate_sweep: GateSweep,
+    #[serde(default)]
+    pub band_slices: BandSlices,
+    #[serde(default)]
+    pub figure: FigureSettings,
+    #[serde(default)]
+    pub calibration: Calibration2D,
+    #[serde(default)]
+    pub notes: Notes,
     pub rebin_x_factor: usize,
     pub rebin_y_factor: usize,
+    pub preview: Option<f64>, // sample fraction when filled by a preview
+    #[serde(default)]
+    pub storage: Storage,
     #[serde(skip)]
     pub recalculate_image: bool,
 }
@@ -30,16 +65,34 @@ impl Default for PlotSettings {
             x_column: String::new(),
             y_column: String::new(),
             cuts: vec![],
+            removed_cuts: vec![],
+            promoted_cuts: vec![],
+            freehand_cuts: false,
             stats_info: false,
+            crosshair: false,
+            axis_link: None,
             colormap: ColorMap::default(),
             colormap_options: ColormapOptions::default(),
+            custom_colormap: CustomColormap::default(),
+            color_bar: true,
             projections: Projections::new(),
+            gate_sweep: GateSweep::default(),
+            band_slices: BandSlices::default(),
+            figure: FigureSettings::default(),
+            calibration: Calibration2D::default(),
+            notes: Notes::default(),
             rebin_x_factor: 1,
             rebin_y_factor: 1,
+            preview: None,
+            storage: Storage::default(),
             recalculate_image: false,
         }
     }
 }
+fn default_color_bar() -> bool {
+    true
+}
+
 impl PlotSettings {
     pub fn settings_ui(&mut self, ui: &mut egui::Ui, max_z_range: u64) {
         ui.menu_button("Colormaps", |ui| {
@@ -47,11 +100,22 @@ impl PlotSettings {
                 .ui(ui, &mut self.recalculate_image, max_z_range);
             ui.separator();
             self.colormap.color_maps_ui(ui, &mut self.recalculate_image);
+            if self.colormap == ColorMap::Custom {
+                ui.separator();
+                ui.label("Custom Stops");
+                self.custom_colormap.ui(ui, &mut self.recalculate_image);
+            }
+            ui.separator();
+            ui.checkbox(&mut self.color_bar, "Color Bar");
         });
 
         ui.separator();
 
-        ui.checkbox(&mut self.stats_info, "Show Statitics");
+        ui.checkbox(&mut self.stats_info, "Show Statistics");
+        ui.checkbox(&mut self.crosshair, "Crosshair")
+            .on_hover_text("Show x, y, and the bin content under the cursor");
+        self.egui_settings.bounds_ui(ui);
+        self.notes.menu_button(ui);
         // self.egui_settings.menu_button(ui);
 
         ui.separator();
@@ -62,19 +126,32 @@ impl PlotSettings {
     }
 
     pub fn draw(&mut self, plot_ui: &mut egui_plot::PlotUi) {
-        for cut in &mut self.cuts {
-            cut.draw(plot_ui);
+        for cut in &self.cuts {
+            cut.lock().unwrap().draw(plot_ui);
         }
         self.projections.draw(plot_ui);
+        self.band_slices.draw(plot_ui);
     }
 
     pub fn interactive_response(&mut self, plot_response: &egui_plot::PlotResponse<()>) {
         self.projections.interactive_dragging(plot_response);
 
-        for cut in &mut self.cuts {
+        // shared cuts only respond to the pane the pointer is in
+        if !plot_response.response.contains_pointer() {
+            return;
+        }
+
+        for cut in &self.cuts {
+            let mut cut = cut.lock().unwrap();
             self.egui_settings.allow_drag = !cut.is_dragging();
             self.egui_settings.allow_double_click_reset = !cut.is_clicking();
             cut.interactions(plot_response);
         }
+
+        self.band_slices.interactions(plot_response);
+        if self.band_slices.is_active() {
+            self.egui_settings.allow_drag = false;
+            self.egui_settings.allow_double_click_reset = false;
+        }
     }
 }
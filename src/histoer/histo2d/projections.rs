@@ -12,10 +12,10 @@ impl Histogram2D {
         // Extract the y-projection data
         let mut y_bins = vec![0; self.bins.y];
 
-        for ((x_index, y_index), &count) in &self.bins.counts {
-            let x_center = self.range.x.min + (*x_index as f64 + 0.5) * self.bins.x_width;
-            if x_center >= x_min && x_center < x_max && *y_index < y_bins.len() {
-                y_bins[*y_index] += count;
+        for ((x_index, y_index), count) in self.bins.counts.iter() {
+            let x_center = self.range.x.min + (x_index as f64 + 0.5) * self.bins.x_width;
+            if x_center >= x_min && x_center < x_max && y_index < y_bins.len() {
+                y_bins[y_index] += count;
             }
         }
 
@@ -26,10 +26,10 @@ impl Histogram2D {
         // Extract the x-projection data
         let mut x_bins = vec![0; self.bins.x];
 
-        for ((x_index, y_index), &count) in &self.bins.counts {
-            let y_center = self.range.y.min + (*y_index as f64 + 0.5) * self.bins.y_width;
-            if y_center >= y_min && y_center < y_max && *x_index < x_bins.len() {
-                x_bins[*x_index] += count;
+        for ((x_index, y_index), count) in self.bins.counts.iter() {
+            let y_center = self.range.y.min + (y_index as f64 + 0.5) * self.bins.y_width;
+            if y_center >= y_min && y_center < y_max && x_index < x_bins.len() {
+                x_bins[x_index] += count;
             }
         }
 
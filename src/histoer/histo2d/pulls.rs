@@ -0,0 +1,260 @@
+use crate::egui_plot_stuff::egui_image::EguiImage;
+use crate::egui_plot_stuff::egui_plot_settings::EguiPlotSettings;
+
+use super::histogram2d::Histogram2D;
+
+// Bin-by-bin comparison of two 2D histograms with identical binning: (A - B) / sigma
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct PullsMap {
+    pub name: String,
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+    pub bins: (usize, usize),
+    pub pulls: Vec<Option<f64>>, // row major (y * bins.0 + x), None where both histograms are empty
+    pub scale: f64,              // factor B was scaled by before comparing
+    pub max_pull: f64,           // symmetric color scale limit
+    pub chi2: f64,
+    pub ndf: usize,
+    pub image: EguiImage,
+    pub egui_settings: EguiPlotSettings,
+    pub recalculate_image: bool,
+}
+
+impl PullsMap {
+    pub fn new(a: &Histogram2D, b: &Histogram2D, scale_b: bool) -> Result<Self, String> {
+        if a.bins.x != b.bins.x
+            || a.bins.y != b.bins.y
+            || a.range.x.min != b.range.x.min
+            || a.range.x.max != b.range.x.max
+            || a.range.y.min != b.range.y.min
+            || a.range.y.max != b.range.y.max
+        {
+            return Err(format!(
+                "'{}' and '{}' do not have identical binning",
+                a.name, b.name
+            ));
+        }
+
+        let total_a: u64 = a.bins.counts.values().sum();
+        let total_b: u64 = b.bins.counts.values().sum();
+
+        // scale B to the integral of A, useful when comparing simulation to data
+        let scale = if scale_b && total_b > 0 {
+            total_a as f64 / total_b as f64
+        } else {
+            1.0
+        };
+
+        let (nx, ny) = (a.bins.x, a.bins.y);
+        let mut pulls = vec![None; nx * ny];
+        let mut chi2 = 0.0;
+        let mut ndf = 0;
+        let mut max_pull: f64 = 0.0;
+
+        for y in 0..ny {
+            for x in 0..nx {
+                let count_a = a.bins.counts.get(x, y) as f64;
+                let count_b = b.bins.counts.get(x, y) as f64;
+
+                let variance = count_a + scale * scale * count_b;
+                if variance <= 0.0 {
+                    continue;
+                }
+
+                let pull = (count_a - scale * count_b) / variance.sqrt();
+                chi2 += pull * pull;
+                ndf += 1;
+                max_pull = max_pull.max(pull.abs());
+                pulls[y * nx + x] = Some(pull);
+            }
+        }
+
+        Ok(Self {
+            name: format!("Pulls: ({} - {})", a.name, b.name),
+            x_range: (a.range.x.min, a.range.x.max),
+            y_range: (a.range.y.min, a.range.y.max),
+            bins: (nx, ny),
+            pulls,
+            scale,
+            max_pull: if max_pull > 0.0 {
+                max_pull.min(5.0)
+            } else {
+                1.0
+            },
+            chi2,
+            ndf,
+            image: EguiImage::heatmap(
+                format!("Pulls: ({} - {})", a.name, b.name),
+                [a.range.x.min, a.range.x.max],
+                [a.range.y.min, a.range.y.max],
+            ),
+            egui_settings: EguiPlotSettings::default(),
+            recalculate_image: true,
+        })
+    }
+
+    // Diverging blue-white-red colormap centered on zero
+    fn diverging_color(pull: f64, max_pull: f64) -> egui::Color32 {
+        let t = (pull / max_pull).clamp(-1.0, 1.0);
+        let fade = (255.0 * (1.0 - t.abs())) as u8;
+        if t >= 0.0 {
+            egui::Color32::from_rgb(255, fade, fade)
+        } else {
+            egui::Color32::from_rgb(fade, fade, 255)
+        }
+    }
+
+    fn data_2_image(&self) -> egui::ColorImage {
+        let (width, height) = self.bins;
+
+        let pixels = (0..height)
+            .flat_map(|row| {
+                let y = height - row - 1;
+                (0..width).map(move |x| match self.pulls[y * width + x] {
+                    Some(pull) => Self::diverging_color(pull, self.max_pull),
+                    None => egui::Color32::TRANSPARENT,
+                })
+            })
+            .collect();
+
+        egui::ColorImage {
+            size: [width, height],
+            pixels,
+        }
+    }
+
+    pub fn get_pull(&self, x: f64, y: f64) -> Option<f64> {
+        if x < self.x_range.0 || x >= self.x_range.1 || y < self.y_range.0 || y >= self.y_range.1 {
+            return None;
+        }
+
+        let x_index = ((x - self.x_range.0) / (self.x_range.1 - self.x_range.0)
+            * self.bins.0 as f64) as usize;
+        let y_index = ((y - self.y_range.0) / (self.y_range.1 - self.y_range.0)
+            * self.bins.1 as f64) as usize;
+
+        self.pulls
+            .get(y_index * self.bins.0 + x_index)
+            .copied()
+            .flatten()
+    }
+
+    pub fn render(&mut self, ui: &mut egui::Ui) {
+        if self.recalculate_image || self.image.texture.is_none() {
+            self.image.texture = None;
+            let color_image = self.data_2_image();
+            self.image.get_texture(ui, color_image);
+            self.recalculate_image = false;
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .add(
+                    egui::DragValue::new(&mut self.max_pull)
+                        .speed(0.1)
+                        .range(0.1..=f64::INFINITY)
+                        .prefix("|Pull| Scale: "),
+                )
+                .changed()
+            {
+                self.recalculate_image = true;
+            }
+
+            ui.separator();
+
+            ui.label(format!(
+                "χ²/ndf = {:.2}/{} = {:.3}",
+                self.chi2,
+                self.ndf,
+                if self.ndf > 0 {
+                    self.chi2 / self.ndf as f64
+                } else {
+                    0.0
+                }
+            ));
+
+            if self.scale != 1.0 {
+                ui.separator();
+                ui.label(format!("B scaled by {:.4}", self.scale));
+            }
+        });
+
+        let plot = egui_plot::Plot::new(self.name.clone());
+        let plot = self.egui_settings.apply_to_plot(plot);
+
+        let response = plot.show(ui, |plot_ui| {
+            if let Some(image) = self.image.get_plot_image_from_texture() {
+                self.image.draw(plot_ui, image);
+            }
+
+            if self.egui_settings.reset_axis {
+                self.egui_settings.reset_axis_lims(plot_ui);
+            }
+
+            plot_ui.pointer_coordinate()
+        });
+
+        if let Some(pointer) = response.inner {
+            if let Some(pull) = self.get_pull(pointer.x, pointer.y) {
+                response
+                    .response
+                    .on_hover_text(format!("Pull: {:.2}", pull));
+            }
+        }
+    }
+}
+
+#[derive(Default, Clone, serde::Deserialize, serde::Serialize)]
+pub struct PullsComparison {
+    pub hist_a: String,
+    pub hist_b: String,
+    pub scale_b: bool,
+    #[serde(skip)]
+    pub maps: Vec<PullsMap>,
+}
+
+impl PullsComparison {
+    // Returns true when the user asked for a new pulls map
+    pub fn menu_ui(&mut self, ui: &mut egui::Ui, names: &[String]) -> bool {
+        ui.heading("Compare 2D Histograms");
+
+        egui::ComboBox::from_label("A")
+            .selected_text(self.hist_a.clone())
+            .show_ui(ui, |ui| {
+                for name in names {
+                    ui.selectable_value(&mut self.hist_a, name.clone(), name);
+                }
+            });
+
+        egui::ComboBox::from_label("B")
+            .selected_text(self.hist_b.clone())
+            .show_ui(ui, |ui| {
+                for name in names {
+                    ui.selectable_value(&mut self.hist_b, name.clone(), name);
+                }
+            });
+
+        ui.checkbox(&mut self.scale_b, "Scale B to A")
+            .on_hover_text("Normalize B to the integral of A before comparing");
+
+        ui.add_enabled(
+            !self.hist_a.is_empty() && !self.hist_b.is_empty() && self.hist_a != self.hist_b,
+            egui::Button::new("Pulls Map"),
+        )
+        .on_hover_text("(A - B)/σ for each bin. Requires identical binning.")
+        .clicked()
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        self.maps.retain_mut(|map| {
+            let mut open = true;
+            egui::Window::new(map.name.clone())
+                .open(&mut open)
+                .show(&ctx, |ui| {
+                    map.render(ui);
+                });
+            open
+        });
+    }
+}
@@ -1,7 +1,6 @@
+use super::bin_counts::BinCounts;
 use super::histogram2d::{Bins, Histogram2D};
 
-use fnv::FnvHashMap;
-
 impl Histogram2D {
     // Compute the possible rebin factors based on the initial number of bins
     pub fn possible_x_rebin_factors(&self) -> Vec<usize> {
@@ -84,30 +83,27 @@ impl Histogram2D {
                 x_width: (self.range.x.max - self.range.x.min) / new_x_bins as f64,
                 y: new_y_bins,
                 y_width: (self.range.y.max - self.range.y.min) / new_y_bins as f64,
-                counts: FnvHashMap::default(),
+                counts: BinCounts::default(),
                 min_count: u64::MAX,
                 max_count: u64::MIN,
             };
 
             // Transfer counts to new bins
-            for ((old_x_index, old_y_index), &count) in &backup_bins.counts {
-                let old_x_value = self.range.x.min + *old_x_index as f64 * backup_bins.x_width;
-                let old_y_value = self.range.y.min + *old_y_index as f64 * backup_bins.y_width;
+            for ((old_x_index, old_y_index), count) in backup_bins.counts.iter() {
+                let old_x_value = self.range.x.min + old_x_index as f64 * backup_bins.x_width;
+                let old_y_value = self.range.y.min + old_y_index as f64 * backup_bins.y_width;
 
                 let new_x_index =
                     ((old_x_value - self.range.x.min) / new_bins.x_width).floor() as usize;
                 let new_y_index =
                     ((old_y_value - self.range.y.min) / new_bins.y_width).floor() as usize;
 
-                let new_count = new_bins
-                    .counts
-                    .entry((new_x_index, new_y_index))
-                    .or_insert(0);
-                *new_count += count;
+                let new_count = new_bins.counts.add(new_x_index, new_y_index, count);
 
-                new_bins.min_count = new_bins.min_count.min(*new_count);
-                new_bins.max_count = new_bins.max_count.max(*new_count);
+                new_bins.min_count = new_bins.min_count.min(new_count);
+                new_bins.max_count = new_bins.max_count.max(new_count);
             }
+            new_bins.update_storage(self.plot_settings.storage);
 
             self.bins = new_bins;
             self.plot_settings.recalculate_image = true;
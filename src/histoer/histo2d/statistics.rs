@@ -1,4 +1,5 @@
 use super::histogram2d::Histogram2D;
+use crate::histoer::stats_box::{format_value, stats_box};
 
 impl Histogram2D {
     // Calculate statistics for a given range (Integral, Mean X, Stdev X, Mean Y, Stdev Y)
@@ -13,18 +14,20 @@ impl Histogram2D {
         let end_x_index = self.get_bin_index_x(end_x).unwrap_or_else(|| {
             self.bins
                 .counts
-                .keys()
-                .max_by_key(|k| k.0)
-                .map_or(0, |k| k.0)
+                .iter()
+                .map(|((x, _), _)| x)
+                .max()
+                .unwrap_or(0)
         });
 
         let start_y_index = self.get_bin_index_y(start_y).unwrap_or(0);
         let end_y_index = self.get_bin_index_y(end_y).unwrap_or_else(|| {
             self.bins
                 .counts
-                .keys()
-                .max_by_key(|k| k.1)
-                .map_or(0, |k| k.1)
+                .iter()
+                .map(|((_, y), _)| y)
+                .max()
+                .unwrap_or(0)
         });
 
         let mut total_count = 0;
@@ -32,7 +35,7 @@ impl Histogram2D {
         let mut sum_product_x = 0.0;
         let mut sum_product_y = 0.0;
 
-        for (&(x_index, y_index), &count) in self.bins.counts.iter() {
+        for ((x_index, y_index), count) in self.bins.counts.iter() {
             if x_index >= start_x_index
                 && x_index <= end_x_index
                 && y_index >= start_y_index
@@ -61,7 +64,7 @@ impl Histogram2D {
             let mut sum_squared_diff_x = 0.0;
             let mut sum_squared_diff_y = 0.0;
 
-            for (&(x_index, y_index), &count) in self.bins.counts.iter() {
+            for ((x_index, y_index), count) in self.bins.counts.iter() {
                 if x_index >= start_x_index
                     && x_index <= end_x_index
                     && y_index >= start_y_index
@@ -89,33 +92,42 @@ impl Histogram2D {
         }
     }
 
-    // Show statistics on the plot
-    pub fn show_stats(&self, plot_ui: &mut egui_plot::PlotUi) {
+    // Stats box over the plot. Means, RMS, and the integral are of the visible range.
+    pub fn show_stats(&self, ui: &egui::Ui, transform: &egui_plot::PlotTransform) {
         if !self.plot_settings.stats_info {
             return;
         }
 
-        let plot_min_x = plot_ui.plot_bounds().min()[0];
-        let plot_max_x = plot_ui.plot_bounds().max()[0];
-        let plot_min_y = plot_ui.plot_bounds().min()[1];
-        let plot_max_y = plot_ui.plot_bounds().max()[1];
-
-        let stats = self.get_statistics(plot_min_x, plot_max_x, plot_min_y, plot_max_y);
-
-        let stats_entries = [
-            format!("Integral: {}", stats.0),
-            format!("Mean: ({:.2}, {:.2})", stats.1, stats.3),
-            format!("Stdev: ({:.2}, {:.2})", stats.2, stats.4),
-            format!("Overflow: ({:}, {:})", self.overflow.0, self.overflow.1),
-            format!("Underflow: ({:}, {:})", self.underflow.0, self.underflow.1),
+        let bounds = transform.bounds();
+        let (integral, mean_x, rms_x, mean_y, rms_y) = self.get_statistics(
+            bounds.min()[0],
+            bounds.max()[0],
+            bounds.min()[1],
+            bounds.max()[1],
+        );
+        // an event outside the range is counted once, in the first axis it is outside of
+        let entries = self.bins.counts.values().sum::<u64>()
+            + self.underflow.0
+            + self.underflow.1
+            + self.overflow.0
+            + self.overflow.1;
+
+        let rows = [
+            ("Entries", entries.to_string()),
+            ("Mean x", format_value(mean_x)),
+            ("Mean y", format_value(mean_y)),
+            ("RMS x", format_value(rms_x)),
+            ("RMS y", format_value(rms_y)),
+            (
+                "Underflow",
+                format!("{}, {}", self.underflow.0, self.underflow.1),
+            ),
+            (
+                "Overflow",
+                format!("{}, {}", self.overflow.0, self.overflow.1),
+            ),
+            ("Integral", integral.to_string()),
         ];
-
-        for entry in stats_entries.iter() {
-            plot_ui.text(
-                egui_plot::Text::new(egui_plot::PlotPoint::new(0, 0), " ") // Placeholder for positioning; adjust as needed
-                    .highlight(false)
-                    .name(entry),
-            );
-        }
+        stats_box(ui, *transform.frame(), &self.name, &rows);
     }
 }
@@ -0,0 +1,101 @@
+use super::bin_counts::BinCounts;
+use super::histogram2d::{Bins, Histogram2D};
+use crate::histoer::cuts::Cut2D;
+
+use std::sync::{Arc, Mutex};
+
+impl Bins {
+    // Same bins with x and y exchanged
+    pub fn transposed(&self) -> Bins {
+        let mut counts: BinCounts = self
+            .counts
+            .iter()
+            .map(|((x, y), count)| ((y, x), count))
+            .collect();
+        if self.counts.is_dense() {
+            counts.to_dense(self.y, self.x);
+        }
+
+        Bins {
+            x: self.y,
+            x_width: self.y_width,
+            y: self.x,
+            y_width: self.x_width,
+            counts,
+            min_count: self.min_count,
+            max_count: self.max_count,
+        }
+    }
+}
+
+impl Histogram2D {
+    // Transpose the stored counts without touching the ranges, used when a
+    // swapped pane copies the counts of a pane that is not swapped
+    pub fn transpose_counts(&mut self) {
+        self.bins = self.bins.transposed();
+        self.backup_bins = self.backup_bins.as_ref().map(|bins| bins.transposed());
+        self.overflow = (self.overflow.1, self.overflow.0);
+        self.underflow = (self.underflow.1, self.underflow.0);
+        self.plot_settings.recalculate_image = true;
+    }
+
+    // Exchange the x and y axes of the stored counts, the next fill keeps the swapped orientation
+    pub fn swap_axes(&mut self) {
+        self.swapped = !self.swapped;
+        self.transpose_counts();
+
+        std::mem::swap(&mut self.range.x, &mut self.range.y);
+        self.image = crate::egui_plot_stuff::egui_image::EguiImage::heatmap(
+            self.name.clone(),
+            [self.range.x.min, self.range.x.max],
+            [self.range.y.min, self.range.y.max],
+        );
+
+        let settings = &mut self.plot_settings;
+        std::mem::swap(&mut settings.x_column, &mut settings.y_column);
+        std::mem::swap(&mut settings.rebin_x_factor, &mut settings.rebin_y_factor);
+
+        let calibration = &mut settings.calibration;
+        std::mem::swap(&mut calibration.x, &mut calibration.y);
+        std::mem::swap(&mut calibration.applied_x, &mut calibration.applied_y);
+
+        let egui_settings = &mut settings.egui_settings;
+        std::mem::swap(&mut egui_settings.log_x, &mut egui_settings.log_y);
+        std::mem::swap(&mut egui_settings.x_label, &mut egui_settings.y_label);
+        std::mem::swap(&mut egui_settings.x_bounds, &mut egui_settings.y_bounds);
+        egui_settings.reset_axis = true;
+
+        // the projection lines move to the other axis, the projections are remade from them
+        let projections = &mut settings.projections;
+        std::mem::swap(
+            &mut projections.add_x_projection,
+            &mut projections.add_y_projection,
+        );
+        let y_line_1 = projections.y_projection_line_1.x_value;
+        let y_line_2 = projections.y_projection_line_2.x_value;
+        projections.y_projection_line_1.x_value = projections.x_projection_line_1.y_value;
+        projections.y_projection_line_2.x_value = projections.x_projection_line_2.y_value;
+        projections.x_projection_line_1.y_value = y_line_1;
+        projections.x_projection_line_2.y_value = y_line_2;
+        projections.x_projection = None;
+        projections.y_projection = None;
+
+        for vertex in &mut settings.band_slices.band.vertices {
+            vertex.swap(0, 1);
+        }
+
+        // cuts shared with other panes are copied so the other panes keep their orientation
+        settings.cuts = settings
+            .cuts
+            .iter()
+            .map(|cut| {
+                let mut cut: Cut2D = cut.lock().unwrap().clone();
+                std::mem::swap(&mut cut.x_column, &mut cut.y_column);
+                for vertex in cut.polygon.vertices_mut() {
+                    vertex.swap(0, 1);
+                }
+                Arc::new(Mutex::new(cut))
+            })
+            .collect();
+    }
+}
@@ -0,0 +1,262 @@
+use fnv::FnvHashMap;
+
+use crate::histoer::histo2d::histogram2d::Histogram2D;
+use crate::histoer::jobs::JobQueue;
+
+// Axis the cube is projected along, the view shows the other two axes
+#[derive(Clone, Copy, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
+pub enum ProjectionAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl ProjectionAxis {
+    fn index(&self) -> usize {
+        match self {
+            ProjectionAxis::X => 0,
+            ProjectionAxis::Y => 1,
+            ProjectionAxis::Z => 2,
+        }
+    }
+
+    // (x, y) axes of the 2D view
+    fn view_axes(&self) -> (usize, usize) {
+        match self {
+            ProjectionAxis::X => (1, 2),
+            ProjectionAxis::Y => (0, 2),
+            ProjectionAxis::Z => (0, 1),
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct SliceSettings {
+    pub axis: ProjectionAxis,
+    pub slice: bool,          // only sum the bins inside `range` along the axis
+    pub range: (f64, f64),    // slice range in axis units
+    pub columns: [String; 3], // x, y, z column names for the axis labels
+    pub preview: Option<f64>, // sample fraction when filled by a preview
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct Histogram3D {
+    pub name: String,
+    pub bins: [usize; 3],
+    pub range: [(f64, f64); 3],
+    pub counts: FnvHashMap<(usize, usize, usize), u64>, // only filled bins are stored
+    pub entries: u64,
+    pub overflow: u64,
+    pub underflow: u64,
+    pub settings: SliceSettings,
+    pub view: Box<Histogram2D>, // projection or slice currently shown
+    #[serde(skip)]
+    pub update_view: bool,
+    #[serde(skip)]
+    pub jobs: JobQueue, // only its filling state is shown, fits and rebins run on the view
+}
+
+impl Histogram3D {
+    pub fn new(name: &str, bins: (usize, usize, usize), range: [(f64, f64); 3]) -> Self {
+        let settings = SliceSettings {
+            axis: ProjectionAxis::Z,
+            slice: false,
+            range: range[2],
+            columns: Default::default(),
+            preview: None,
+        };
+
+        let mut hist = Histogram3D {
+            name: name.to_string(),
+            bins: [bins.0, bins.1, bins.2],
+            range,
+            counts: FnvHashMap::default(),
+            entries: 0,
+            overflow: 0,
+            underflow: 0,
+            settings,
+            view: Box::new(Histogram2D::new(
+                name,
+                (bins.0, bins.1),
+                (range[0], range[1]),
+            )),
+            update_view: false,
+            jobs: JobQueue::default(),
+        };
+        hist.project();
+        hist
+    }
+
+    pub fn reset(&mut self) {
+        self.counts.clear();
+        self.entries = 0;
+        self.overflow = 0;
+        self.underflow = 0;
+        self.update_view = true;
+    }
+
+    fn bin_width(&self, axis: usize) -> f64 {
+        (self.range[axis].1 - self.range[axis].0) / self.bins[axis] as f64
+    }
+
+    fn bin_index(&self, axis: usize, value: f64) -> usize {
+        ((value - self.range[axis].0) / self.bin_width(axis)) as usize
+    }
+
+    pub fn fill(&mut self, x: f64, y: f64, z: f64) {
+        let values = [x, y, z];
+
+        if (0..3).any(|axis| values[axis] < self.range[axis].0) {
+            self.underflow += 1;
+        } else if (0..3).any(|axis| values[axis] >= self.range[axis].1) {
+            self.overflow += 1;
+        } else {
+            let index = (
+                self.bin_index(0, x),
+                self.bin_index(1, y),
+                self.bin_index(2, z),
+            );
+            *self.counts.entry(index).or_insert(0) += 1;
+            self.entries += 1;
+        }
+    }
+
+    // Sum the cube along the selected axis (inside the slice range when slicing) into the 2D view
+    pub fn project(&mut self) {
+        let axis = self.settings.axis.index();
+        let (vx, vy) = self.settings.axis.view_axes();
+
+        let slice_bins = if self.settings.slice {
+            let last = self.bins[axis].saturating_sub(1);
+            let low = self.settings.range.0.max(self.range[axis].0);
+            let high = self.settings.range.1.min(self.range[axis].1);
+            (
+                self.bin_index(axis, low).min(last),
+                self.bin_index(axis, high).min(last),
+            )
+        } else {
+            (0, usize::MAX)
+        };
+
+        let mut counts: FnvHashMap<(usize, usize), u64> = FnvHashMap::default();
+        for (&(i, j, k), &count) in &self.counts {
+            let index = [i, j, k];
+            if index[axis] >= slice_bins.0 && index[axis] <= slice_bins.1 {
+                *counts.entry((index[vx], index[vy])).or_insert(0) += count;
+            }
+        }
+
+        // keep the colormap, cuts, and notes when the view is only refilled
+        let same_axes = self.view.range.x.min == self.range[vx].0
+            && self.view.range.x.max == self.range[vx].1
+            && self.view.range.y.min == self.range[vy].0
+            && self.view.range.y.max == self.range[vy].1
+            && self.view.bins.x == self.bins[vx]
+            && self.view.bins.y == self.bins[vy];
+
+        let mut view = Histogram2D::new(
+            &self.view_name(),
+            (self.bins[vx], self.bins[vy]),
+            (self.range[vx], self.range[vy]),
+        );
+        if same_axes {
+            view.plot_settings = self.view.plot_settings.clone();
+        } else {
+            view.plot_settings.colormap = self.view.plot_settings.colormap;
+            view.plot_settings.colormap_options = self.view.plot_settings.colormap_options;
+            view.plot_settings.custom_colormap = self.view.plot_settings.custom_colormap.clone();
+        }
+
+        view.bins.min_count = counts.values().copied().min().unwrap_or(u64::MAX);
+        view.bins.max_count = counts.values().copied().max().unwrap_or(u64::MIN);
+        view.bins.counts = counts.into();
+        view.bins.update_storage(view.plot_settings.storage);
+        view.plot_settings.x_column = self.settings.columns[vx].clone();
+        view.plot_settings.y_column = self.settings.columns[vy].clone();
+        view.plot_settings.preview = self.settings.preview;
+        view.plot_settings.recalculate_image = true;
+        view.plot_settings.egui_settings.reset_axis = !same_axes;
+
+        self.view = Box::new(view);
+        self.update_view = false;
+    }
+
+    fn view_name(&self) -> String {
+        let label = |axis: usize| {
+            let column = &self.settings.columns[axis];
+            if column.is_empty() {
+                ["X", "Y", "Z"][axis].to_string()
+            } else {
+                column.clone()
+            }
+        };
+
+        // the slice range is left out so the plot keeps its zoom while the slice is dragged
+        let (vx, vy) = self.settings.axis.view_axes();
+        format!("{} [{} vs {}]", self.name, label(vy), label(vx))
+    }
+
+    fn slice_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let axis = self.settings.axis;
+            ui.label("Project along");
+            for (option, text) in [
+                (ProjectionAxis::X, "X"),
+                (ProjectionAxis::Y, "Y"),
+                (ProjectionAxis::Z, "Z"),
+            ] {
+                ui.selectable_value(&mut self.settings.axis, option, text);
+            }
+            if self.settings.axis != axis {
+                self.settings.range = self.range[self.settings.axis.index()];
+                self.update_view = true;
+            }
+
+            ui.separator();
+
+            if ui.checkbox(&mut self.settings.slice, "Slice").changed() {
+                self.update_view = true;
+            }
+
+            let axis_range = self.range[self.settings.axis.index()];
+            let speed = self.bin_width(self.settings.axis.index());
+            ui.add_enabled_ui(self.settings.slice, |ui| {
+                let low = ui.add(
+                    egui::DragValue::new(&mut self.settings.range.0)
+                        .speed(speed)
+                        .range(axis_range.0..=self.settings.range.1)
+                        .prefix("("),
+                );
+                let high = ui.add(
+                    egui::DragValue::new(&mut self.settings.range.1)
+                        .speed(speed)
+                        .range(self.settings.range.0..=axis_range.1)
+                        .prefix(" ")
+                        .suffix(")"),
+                );
+                if low.changed() || high.changed() {
+                    self.update_view = true;
+                }
+            });
+
+            ui.separator();
+
+            ui.label(format!(
+                "Entries: {}  Underflow: {}  Overflow: {}",
+                self.entries, self.underflow, self.overflow
+            ));
+        });
+    }
+
+    // Render the slice controls above the 2D view of the cube
+    pub fn render(&mut self, ui: &mut egui::Ui) {
+        self.jobs.status_ui(ui);
+        self.slice_ui(ui);
+
+        if self.update_view {
+            self.project();
+        }
+
+        self.view.render(ui);
+    }
+}
@@ -0,0 +1 @@
+pub mod histogram3d;
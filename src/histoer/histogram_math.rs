@@ -0,0 +1,190 @@
+use super::histo1d::histogram1d::{BinValues, Histogram};
+
+// Bin by bin arithmetic between 1D histograms with identical binning. The
+// uncertainties are propagated assuming A and B are independent.
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Operation {
+    #[default]
+    Add,
+    Subtract,
+    Divide,
+    Scale,
+}
+
+impl Operation {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Operation::Add => "+",
+            Operation::Subtract => "-",
+            Operation::Divide => "/",
+            Operation::Scale => "*",
+        }
+    }
+
+    fn uses_b(&self) -> bool {
+        *self != Operation::Scale
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct HistogramMath {
+    pub hist_a: String,
+    pub hist_b: String,
+    pub operation: Operation,
+    pub scale: f64,     // k for k*A, B is scaled by k for the other operations
+    pub result: String, // name of the new pane, generated when empty
+}
+
+impl Default for HistogramMath {
+    fn default() -> Self {
+        HistogramMath {
+            hist_a: String::new(),
+            hist_b: String::new(),
+            operation: Operation::Add,
+            scale: 1.0,
+            result: String::new(),
+        }
+    }
+}
+
+impl HistogramMath {
+    // Returns true when the user asked for the result
+    pub fn menu_ui(&mut self, ui: &mut egui::Ui, names: &[String]) -> bool {
+        ui.heading("Histogram Math");
+
+        egui::ComboBox::from_label("A ")
+            .selected_text(self.hist_a.clone())
+            .show_ui(ui, |ui| {
+                for name in names {
+                    ui.selectable_value(&mut self.hist_a, name.clone(), name);
+                }
+            });
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.operation, Operation::Add, "A + kB");
+            ui.radio_value(&mut self.operation, Operation::Subtract, "A - kB");
+            ui.radio_value(&mut self.operation, Operation::Divide, "A / kB")
+                .on_hover_text("Bins where B is empty are set to zero, e.g. for efficiencies");
+            ui.radio_value(&mut self.operation, Operation::Scale, "kA");
+        });
+
+        ui.add_enabled_ui(self.operation.uses_b(), |ui| {
+            egui::ComboBox::from_label("B ")
+                .selected_text(self.hist_b.clone())
+                .show_ui(ui, |ui| {
+                    for name in names {
+                        ui.selectable_value(&mut self.hist_b, name.clone(), name);
+                    }
+                });
+        });
+
+        ui.add(
+            egui::DragValue::new(&mut self.scale)
+                .speed(0.01)
+                .prefix("k: "),
+        );
+
+        let hint = self.result_name();
+        ui.add(
+            egui::TextEdit::singleline(&mut self.result)
+                .hint_text(hint)
+                .clip_text(false),
+        );
+
+        ui.add_enabled(
+            !self.hist_a.is_empty() && (!self.operation.uses_b() || !self.hist_b.is_empty()),
+            egui::Button::new("Calculate"),
+        )
+        .on_hover_text("Add the result as a new pane. Requires identical binning.")
+        .clicked()
+    }
+
+    pub fn result_name(&self) -> String {
+        if !self.result.is_empty() {
+            return self.result.clone();
+        }
+
+        let short = |name: &str| name.rsplit('/').next().unwrap_or(name).to_string();
+        let k = if self.scale == 1.0 {
+            String::new()
+        } else {
+            format!("{}*", self.scale)
+        };
+        if self.operation.uses_b() {
+            format!(
+                "Math/{} {} {}{}",
+                short(&self.hist_a),
+                self.operation.symbol(),
+                k,
+                short(&self.hist_b)
+            )
+        } else {
+            format!("Math/{}{}", k, short(&self.hist_a))
+        }
+    }
+
+    pub fn calculate(&self, a: &Histogram, b: Option<&Histogram>) -> Result<Histogram, String> {
+        let values_a = a.original_values();
+        let k = self.scale;
+
+        let values = match (self.operation, b) {
+            (Operation::Scale, _) => BinValues {
+                contents: values_a.contents.iter().map(|value| k * value).collect(),
+                errors: values_a
+                    .errors
+                    .iter()
+                    .map(|error| k.abs() * error)
+                    .collect(),
+            },
+            (_, None) => return Err("Histogram B is missing".to_string()),
+            (operation, Some(b)) => {
+                if a.original_bins.len() != b.original_bins.len()
+                    || a.range != b.range
+                    || a.edges != b.edges
+                {
+                    return Err(format!(
+                        "'{}' and '{}' do not have identical binning",
+                        a.name, b.name
+                    ));
+                }
+
+                let values_b = b.original_values();
+                let mut values = BinValues::default();
+                for index in 0..values_a.contents.len() {
+                    let (value_a, error_a) = (values_a.contents[index], values_a.errors[index]);
+                    let (value_b, error_b) = (
+                        k * values_b.contents[index],
+                        k.abs() * values_b.errors[index],
+                    );
+
+                    let (content, error) = match operation {
+                        Operation::Add => (value_a + value_b, error_a.hypot(error_b)),
+                        Operation::Subtract => (value_a - value_b, error_a.hypot(error_b)),
+                        _ if value_b == 0.0 => (0.0, 0.0),
+                        _ => {
+                            let ratio = value_a / value_b;
+                            let relative_a = if value_a == 0.0 {
+                                0.0
+                            } else {
+                                error_a / value_a
+                            };
+                            (ratio, ratio.abs() * relative_a.hypot(error_b / value_b))
+                        }
+                    };
+                    values.contents.push(content);
+                    values.errors.push(error);
+                }
+                values
+            }
+        };
+
+        let mut hist = if a.is_variable_width() {
+            Histogram::with_edges(&self.result_name(), &a.edges)
+        } else {
+            Histogram::new(&self.result_name(), a.original_bins.len(), a.range)
+        };
+        hist.set_values(values);
+        Ok(hist)
+    }
+}
@@ -3,11 +3,15 @@ use egui_tiles::TileId;
 use fnv::FnvHashMap;
 use indicatif::{ProgressBar, ProgressStyle};
 use polars::prelude::*;
-use pyo3::{prelude::*, types::PyModule};
+#[cfg(feature = "python")]
+use pyo3::{
+    prelude::*,
+    types::{PyBytes, PyModule},
+};
 use rayon::prelude::*;
 
 // Standard library
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -15,11 +19,35 @@ use std::sync::{
 };
 
 // Project modules
-use super::configs::{Config, Configs};
-use super::histo1d::histogram1d::Histogram;
+use super::color_policy::{ColorBy, ColorPolicy};
+use super::configs::{Config, Configs, Hist1DConfig, Hist2DConfig, Hist3DConfig, HistNDConfig};
+use super::cut_stats::CutStats;
+use super::cuts::{Cut2D, Cuts};
+use super::figure::composer::FigureComposer;
+use super::fill_buffer::{Buffer1D, Buffer2D, Buffer3D, BufferND};
+use super::fill_priority::FillPriority;
+use super::fill_progress::FillProgress;
+use super::fit_library::FitLibrary;
+use super::fit_summary::FitSummary;
+use super::histo1d::histogram1d::{BinValues, Histogram};
 use super::histo2d::histogram2d::Histogram2D;
+use super::histo2d::pulls::{PullsComparison, PullsMap};
+use super::histo3d::histogram3d::Histogram3D;
+use super::histogram_math::HistogramMath;
+use super::histond::histogramnd::HistogramND;
+use super::kinematics::KinematicsCalculator;
+use super::mca_formats::McaSpectrum;
+use super::memory_budget::{self, MemoryBudget};
 use super::pane::Pane;
+use super::presets::PlotPresets;
+use super::preview;
+use super::root_writer::{RootFile, WriteProgress};
+use super::sum_spectra::SumSpectra;
+use super::summary_stats::SummaryStats;
+use super::text_spectrum::TextSpectrum;
 use super::tree::TreeBehavior;
+use super::undo::UndoStack;
+use egui_tiles::Behavior as _;
 
 #[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug)]
 pub enum ContainerType {
@@ -39,6 +67,7 @@ pub struct ContainerInfo {
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct Histogrammer {
     pub name: String,
     pub tree: egui_tiles::Tree<Pane>,
@@ -48,8 +77,40 @@ pub struct Histogrammer {
     #[serde(skip)]
     pub abort_flag: Arc<AtomicBool>, // Use AtomicBool for thread-safe abort flag
     #[serde(skip)]
-    pub progress: Arc<Mutex<f32>>,
+    pub progress: Arc<Mutex<FillProgress>>,
+    #[serde(skip)]
+    pub cut_stats: Arc<Mutex<CutStats>>,
+    #[serde(skip)]
+    pub fill_error: Arc<Mutex<Option<String>>>, // why the last fill stopped before the end
     pub histogram_map: HashMap<String, ContainerInfo>, // Map full path to TabInfo
+    #[serde(default)]
+    pub pulls: PullsComparison,
+    #[serde(default)]
+    pub math: HistogramMath,
+    #[serde(default)]
+    pub presets: PlotPresets,
+    #[serde(default)]
+    pub colors: ColorPolicy,
+    #[serde(default)]
+    pub fill_priority: FillPriority,
+    #[serde(default)]
+    pub fill_mode: FillMode,
+    #[serde(default)]
+    pub fit_summary: FitSummary,
+    #[serde(default)]
+    pub fit_library: FitLibrary,
+    #[serde(default)]
+    pub kinematics: KinematicsCalculator,
+    #[serde(default)]
+    pub summary_stats: SummaryStats,
+    #[serde(default)]
+    pub sum_spectra: SumSpectra,
+    #[serde(default)]
+    pub composer: FigureComposer,
+    #[serde(default)]
+    pub root_use_uproot: bool, // write ROOT files through Python/uproot instead of the native writer
+    #[serde(skip)]
+    pub undo: UndoStack,
 }
 
 impl Default for Histogrammer {
@@ -60,8 +121,24 @@ impl Default for Histogrammer {
             behavior: Default::default(),
             calculating: Arc::new(AtomicBool::new(false)),
             abort_flag: Arc::new(AtomicBool::new(false)),
-            progress: Arc::new(Mutex::new(0.0)),
+            progress: Arc::new(Mutex::new(FillProgress::default())),
+            cut_stats: Arc::new(Mutex::new(CutStats::default())),
+            fill_error: Arc::new(Mutex::new(None)),
             histogram_map: HashMap::new(),
+            pulls: PullsComparison::default(),
+            math: HistogramMath::default(),
+            presets: PlotPresets::default(),
+            colors: ColorPolicy::default(),
+            fill_priority: FillPriority::default(),
+            fill_mode: FillMode::default(),
+            fit_summary: FitSummary::default(),
+            fit_library: FitLibrary::default(),
+            kinematics: KinematicsCalculator::default(),
+            summary_stats: SummaryStats::default(),
+            sum_spectra: SumSpectra::default(),
+            composer: FigureComposer::default(),
+            root_use_uproot: false,
+            undo: UndoStack::default(),
         }
     }
 }
@@ -80,12 +157,80 @@ impl Histogrammer {
                         return Some(*id);
                     }
                 }
+                egui_tiles::Tile::Pane(Pane::Histogram3D(hist)) => {
+                    if hist.lock().unwrap().name == name {
+                        return Some(*id);
+                    }
+                }
+                egui_tiles::Tile::Pane(Pane::HistogramND(hist)) => {
+                    if hist.lock().unwrap().name == name {
+                        return Some(*id);
+                    }
+                }
                 _ => {}
             }
             None
         })
     }
 
+    fn find_hist1d(&self, name: &str) -> Option<Arc<Mutex<Box<Histogram>>>> {
+        self.tree.tiles.iter().find_map(|(_id, tile)| match tile {
+            egui_tiles::Tile::Pane(Pane::Histogram(hist)) if hist.lock().unwrap().name == name => {
+                Some(Arc::clone(hist))
+            }
+            _ => None,
+        })
+    }
+
+    fn find_hist2d(&self, name: &str) -> Option<Arc<Mutex<Box<Histogram2D>>>> {
+        self.tree.tiles.iter().find_map(|(_id, tile)| match tile {
+            egui_tiles::Tile::Pane(Pane::Histogram2D(hist))
+                if hist.lock().unwrap().name == name =>
+            {
+                Some(Arc::clone(hist))
+            }
+            _ => None,
+        })
+    }
+
+    fn find_hist3d(&self, name: &str) -> Option<Arc<Mutex<Box<Histogram3D>>>> {
+        self.tree.tiles.iter().find_map(|(_id, tile)| match tile {
+            egui_tiles::Tile::Pane(Pane::Histogram3D(hist))
+                if hist.lock().unwrap().name == name =>
+            {
+                Some(Arc::clone(hist))
+            }
+            _ => None,
+        })
+    }
+
+    fn find_histnd(&self, name: &str) -> Option<Arc<Mutex<Box<HistogramND>>>> {
+        self.tree.tiles.iter().find_map(|(_id, tile)| match tile {
+            egui_tiles::Tile::Pane(Pane::HistogramND(hist))
+                if hist.lock().unwrap().name == name =>
+            {
+                Some(Arc::clone(hist))
+            }
+            _ => None,
+        })
+    }
+
+    fn hist1d_panes(&self) -> Vec<Arc<Mutex<Box<Histogram>>>> {
+        self.tree
+            .tiles
+            .iter()
+            .filter_map(|(_id, tile)| match tile {
+                egui_tiles::Tile::Pane(Pane::Histogram(hist)) => Some(Arc::clone(hist)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn apply_color_policy(&mut self) {
+        let hists = self.hist1d_panes();
+        self.colors.apply(&hists);
+    }
+
     pub fn reset_histograms(&mut self) {
         for (_id, tile) in self.tree.tiles.iter_mut() {
             match tile {
@@ -95,12 +240,18 @@ impl Histogrammer {
                 egui_tiles::Tile::Pane(Pane::Histogram2D(hist)) => {
                     hist.lock().unwrap().reset();
                 }
+                egui_tiles::Tile::Pane(Pane::Histogram3D(hist)) => {
+                    hist.lock().unwrap().reset();
+                }
+                egui_tiles::Tile::Pane(Pane::HistogramND(hist)) => {
+                    hist.lock().unwrap().reset();
+                }
                 _ => {}
             }
         }
     }
 
-    fn reset_histogram(&mut self, pane_id: TileId) {
+    pub fn reset_histogram(&mut self, pane_id: TileId) {
         if let Some((_id, tile)) = self.tree.tiles.iter_mut().find(|(id, _)| **id == pane_id) {
             match tile {
                 egui_tiles::Tile::Pane(Pane::Histogram(hist)) => {
@@ -109,16 +260,27 @@ impl Histogrammer {
                 egui_tiles::Tile::Pane(Pane::Histogram2D(hist)) => {
                     hist.lock().unwrap().reset();
                 }
+                egui_tiles::Tile::Pane(Pane::Histogram3D(hist)) => {
+                    hist.lock().unwrap().reset();
+                }
+                egui_tiles::Tile::Pane(Pane::HistogramND(hist)) => {
+                    hist.lock().unwrap().reset();
+                }
                 _ => {}
             }
         }
     }
 
     fn create_1d_pane(&mut self, name: &str, bins: usize, range: (f64, f64)) -> TileId {
-        let hist = Histogram::new(name, bins, range);
+        self.insert_1d_pane(Histogram::new(name, bins, range))
+    }
+
+    fn insert_1d_pane(&mut self, mut hist: Histogram) -> TileId {
+        self.presets.apply_1d(&mut hist);
+        let name = hist.name.clone();
         let pane = Pane::Histogram(Arc::new(Mutex::new(Box::new(hist))));
         let pane_id = self.tree.tiles.insert_pane(pane);
-        self.format_pane_in_containers(name, pane_id);
+        self.format_pane_in_containers(&name, pane_id);
 
         pane_id
     }
@@ -129,7 +291,8 @@ impl Histogrammer {
         bins: (usize, usize),
         range: ((f64, f64), (f64, f64)),
     ) -> TileId {
-        let hist = Histogram2D::new(name, bins, range);
+        let mut hist = Histogram2D::new(name, bins, range);
+        self.presets.apply_2d(&mut hist);
         let pane = Pane::Histogram2D(Arc::new(Mutex::new(Box::new(hist))));
         let pane_id = self.tree.tiles.insert_pane(pane);
         self.format_pane_in_containers(name, pane_id);
@@ -137,6 +300,29 @@ impl Histogrammer {
         pane_id
     }
 
+    fn create_3d_pane(
+        &mut self,
+        name: &str,
+        bins: (usize, usize, usize),
+        range: [(f64, f64); 3],
+    ) -> TileId {
+        let hist = Histogram3D::new(name, bins, range);
+        let pane = Pane::Histogram3D(Arc::new(Mutex::new(Box::new(hist))));
+        let pane_id = self.tree.tiles.insert_pane(pane);
+        self.format_pane_in_containers(name, pane_id);
+
+        pane_id
+    }
+
+    fn create_nd_pane(&mut self, name: &str, bins: &[usize], ranges: &[(f64, f64)]) -> TileId {
+        let hist = HistogramND::new(name, bins, ranges);
+        let pane = Pane::HistogramND(Arc::new(Mutex::new(Box::new(hist))));
+        let pane_id = self.tree.tiles.insert_pane(pane);
+        self.format_pane_in_containers(name, pane_id);
+
+        pane_id
+    }
+
     fn format_pane_in_containers(&mut self, name: &str, pane_id: TileId) {
         // Parse the name to determine its hierarchical structure (e.g., "Tab1/Tab2/Histogram")
         let grid_id = self.create_tabs(name.to_string());
@@ -155,6 +341,54 @@ impl Histogrammer {
         }
     }
 
+    // Moves the pane to the tabs of the new name, the name may include new folders
+    pub fn rename_histogram(&mut self, old_name: &str, new_name: &str) -> Result<(), String> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() || new_name.split('/').any(|part| part.is_empty()) {
+            return Err(format!("'{}' is not a valid histogram name", new_name));
+        }
+        if self.find_existing_histogram(new_name).is_some() {
+            return Err(format!("A histogram named '{}' already exists", new_name));
+        }
+        let pane_id = self
+            .find_existing_histogram(old_name)
+            .ok_or_else(|| format!("Histogram '{}' not found", old_name))?;
+        if let Some(egui_tiles::Tile::Pane(pane)) = self.tree.tiles.get(pane_id) {
+            pane.set_name(new_name);
+        }
+
+        self.histogram_map.remove(old_name);
+        let grid_id = self.create_tabs(new_name.to_string());
+        self.tree
+            .move_tile_to_container(pane_id, grid_id, usize::MAX, true);
+        self.histogram_map
+            .entry(new_name.to_string())
+            .and_modify(|container_info| container_info.children.push(pane_id));
+        Ok(())
+    }
+
+    // Copy of a pane next to it, named "<name> copy" (or "<name> copy 2", ...)
+    pub fn duplicate_histogram(&mut self, name: &str) -> Result<String, String> {
+        let pane_id = self
+            .find_existing_histogram(name)
+            .ok_or_else(|| format!("Histogram '{}' not found", name))?;
+        let Some(egui_tiles::Tile::Pane(pane)) = self.tree.tiles.get(pane_id) else {
+            return Err(format!("Histogram '{}' not found", name));
+        };
+
+        let mut copy_name = format!("{} copy", name);
+        let mut number = 2;
+        while self.find_existing_histogram(&copy_name).is_some() {
+            copy_name = format!("{} copy {}", name, number);
+            number += 1;
+        }
+
+        let copy = pane.duplicate(&copy_name);
+        let copy_id = self.tree.tiles.insert_pane(copy);
+        self.format_pane_in_containers(&copy_name, copy_id);
+        Ok(copy_name)
+    }
+
     pub fn add_hist1d(&mut self, name: &str, bins: usize, range: (f64, f64)) {
         log::debug!("Creating or updating 1D histogram '{}'", name);
 
@@ -170,6 +404,20 @@ impl Histogrammer {
         }
     }
 
+    // Variable width bins, the edges are only used when the pane is created
+    pub fn add_hist1d_with_edges(&mut self, name: &str, edges: &[f64]) {
+        log::debug!(
+            "Creating or updating variable width 1D histogram '{}'",
+            name
+        );
+
+        if let Some(pane_id) = self.find_existing_histogram(name) {
+            self.reset_histogram(pane_id);
+        } else {
+            self.insert_1d_pane(Histogram::with_edges(name, edges));
+        }
+    }
+
     pub fn add_hist2d(
         &mut self,
         name: &str,
@@ -190,48 +438,118 @@ impl Histogrammer {
         }
     }
 
+    pub fn add_hist3d(&mut self, name: &str, bins: (usize, usize, usize), range: [(f64, f64); 3]) {
+        log::debug!("Creating or updating 3D histogram '{}'", name);
+
+        if let Some(pane_id) = self.find_existing_histogram(name) {
+            log::debug!("Resetting existing 3D histogram '{}'", name);
+            self.reset_histogram(pane_id);
+        } else {
+            log::debug!(
+                "No existing histogram found; creating new 3D histogram '{}'",
+                name
+            );
+            self.create_3d_pane(name, bins, range);
+        }
+    }
+
+    pub fn add_histnd(&mut self, name: &str, bins: &[usize], ranges: &[(f64, f64)]) {
+        log::debug!("Creating or updating ND histogram '{}'", name);
+
+        if let Some(pane_id) = self.find_existing_histogram(name) {
+            log::debug!("Resetting existing ND histogram '{}'", name);
+            self.reset_histogram(pane_id);
+        } else {
+            log::debug!(
+                "No existing histogram found; creating new ND histogram '{}'",
+                name
+            );
+            self.create_nd_pane(name, bins, ranges);
+        }
+    }
+
     pub fn fill_histograms(
         &mut self,
         mut configs: Configs,
         lf: &LazyFrame,
-        estimated_memory: f64, // chunk size in GB
-    ) {
+        estimated_memory: f64, // chunk size in GB, unless the budget is automatic
+        memory: &MemoryBudget,
+        sample: Option<f64>, // fraction of rows to fill for a preview
+        partial: bool, // only the panes of the configs are reset, the others keep their counts
+    ) -> Result<(), String> {
+        // a histogram whose cuts cannot be applied would be left empty
+        let cut_errors = configs.cut_errors();
+        if !cut_errors.is_empty() {
+            return Err(format!(
+                "Histograms were not filled, fix the cuts first: {}",
+                cut_errors.join("; ")
+            ));
+        }
+
         let calculating = Arc::clone(&self.calculating);
         let abort_flag = Arc::clone(&self.abort_flag);
         let progress = Arc::clone(&self.progress);
 
+        let fill_error = Arc::clone(&self.fill_error);
+
         // Set calculating to true at the start
         calculating.store(true, Ordering::SeqCst);
         abort_flag.store(false, Ordering::SeqCst);
+        *fill_error.lock().unwrap() = None;
 
         let mut lf = lf.clone();
 
-        let row_count = lf
-            .clone()
-            .select([len().alias("count")])
-            .collect()
-            .unwrap()
-            .column("count")
-            .unwrap()
-            .u32()
-            .unwrap()
-            .get(0)
-            .unwrap();
-
         // Validate configurations and prepare histograms
         let valid_configs = configs.valid_configs(&mut lf);
-        valid_configs.check_and_add_panes(self);
+        if partial {
+            valid_configs.reset_panes(self);
+            valid_configs.add_missing_panes(self);
+        } else {
+            valid_configs.check_and_add_panes(self);
+        }
+
+        // after the new columns are added, so the time column can be one of them. The rows are
+        // counted and sliced before the gate, each batch is gated on its own.
+        let time_gate = valid_configs.time_gate(&lf);
+
+        let row_count = match count_rows(&lf) {
+            Ok(row_count) => row_count,
+            Err(e) => {
+                calculating.store(false, Ordering::SeqCst);
+                return Err(format!("Failed to count the rows: {}", e));
+            }
+        };
+
+        // remember the cuts of each 1D histogram for coloring by cut
+        for config in &valid_configs.configs {
+            if let Config::Hist1D(hist1d) = config {
+                let cut_names = hist1d.cuts.cuts.iter().map(|cut| cut.name().to_string());
+                self.colors
+                    .set_cut_label(&hist1d.name, cut_names.clone().collect());
+                for (alias, _) in valid_configs
+                    .aliases
+                    .iter()
+                    .filter(|(_, s)| *s == hist1d.name)
+                {
+                    self.colors
+                        .set_cut_label(alias, cut_names.clone().collect());
+                }
+            }
+        }
+        self.apply_color_policy();
 
         // if valid configs is empty, return early
         if valid_configs.is_empty() {
             calculating.store(false, Ordering::SeqCst);
-            log::error!("No valid configurations found for histograms.");
-            return;
+            return Err("No valid configurations found for histograms.".to_string());
         }
 
-        // Select required columns from the LazyFrame
-        let used_columns = valid_configs.get_used_columns();
-        let selected_columns: Vec<_> = used_columns.iter().map(col).collect();
+        // Columns read from the LazyFrame, each group selects the ones it needs
+        let mut used_columns = valid_configs.get_used_columns();
+        // the time column is read to gate the batches, also from the spill files
+        if time_gate.is_some() && !used_columns.contains(&valid_configs.time_column) {
+            used_columns.push(valid_configs.time_column.clone());
+        }
 
         let columns = used_columns.len() as u64;
         let rows = row_count as u64;
@@ -239,10 +557,14 @@ impl Histogrammer {
 
         // Estimate rows per chunk
         let bytes_per_row = columns as f64 * 8.0; // Each f64 is 8 bytes
-        let chunk_size_bytes = estimated_memory * 1_073_741_824.0;
+        let chunk_gb = memory.chunk_gb(estimated_memory);
+        let chunk_size_bytes = chunk_gb * 1_073_741_824.0;
         let rows_per_chunk = (chunk_size_bytes / bytes_per_row).floor() as usize;
 
-        let progress_bar = ProgressBar::new(row_count as u64);
+        let chunks = preview::chunk_starts(row_count as usize, rows_per_chunk, sample);
+        let rows_to_fill: usize = chunks.iter().map(|(_, rows)| rows).sum();
+
+        let progress_bar = ProgressBar::new(rows_to_fill as u64);
         progress_bar.set_style(
             ProgressStyle::default_bar()
                 .template(
@@ -251,147 +573,296 @@ impl Histogrammer {
                 .expect("Failed to set progress bar template")
                 .progress_chars("#>-"),
         );
-        progress_bar.println(format!("Processing ~{:.2} GB of raw data", estimated_gb));
-
-        // Apply the selection to the LazyFrame
-        let lf = Arc::new(lf.clone().select(selected_columns.clone()));
+        progress_bar.println(format!(
+            "Processing ~{:.2} GB of raw data in chunks of {:.2} GB",
+            estimated_gb, chunk_gb
+        ));
+        if let Some(fraction) = sample {
+            progress_bar.println(format!(
+                "Preview: filling {} of {} rows ({:.1}%)",
+                rows_to_fill,
+                row_count,
+                fraction * 100.0
+            ));
+        }
 
-        // Initialize histogram maps
-        let hist1d_map: Vec<_> = valid_configs
-            .configs
+        // Histograms with priority are filled over all the data before the others
+        let mut groups: Vec<FillGroup> = self
+            .split_by_priority(&valid_configs)
             .iter()
-            .filter_map(|config| {
-                if let Config::Hist1D(hist1d) = config {
-                    self.tree.tiles.iter().find_map(|(_id, tile)| match tile {
-                        egui_tiles::Tile::Pane(Pane::Histogram(hist))
-                            if hist.lock().unwrap().name == hist1d.name =>
-                        {
-                            Some((Arc::clone(hist), hist1d.clone()))
-                        }
-                        _ => None,
-                    })
-                } else {
-                    None
-                }
-            })
+            .map(|configs| FillGroup::new(self, configs))
+            .filter(|group| !group.is_empty())
             .collect();
 
-        let hist2d_map: Vec<_> = valid_configs
-            .configs
-            .iter()
-            .filter_map(|config| {
-                if let Config::Hist2D(hist2d) = config {
-                    self.tree.tiles.iter().find_map(|(_id, tile)| match tile {
-                        egui_tiles::Tile::Pane(Pane::Histogram2D(hist))
-                            if hist.lock().unwrap().name == hist2d.name =>
-                        {
-                            Some((Arc::clone(hist), hist2d.clone()))
-                        }
-                        _ => None,
-                    })
-                } else {
-                    None
-                }
-            })
-            .collect();
+        // every group goes over all rows, the cuts shared by groups are counted by the first
+        self.cut_stats.lock().unwrap().clear();
+        let mut counted = HashSet::new();
+        for group in &mut groups {
+            for (cuts, count) in group.cut_sets.iter().zip(&mut group.count_cuts) {
+                *count = counted.insert(cuts.generate_key());
+            }
+        }
+        if groups.len() > 1 {
+            progress_bar.println(format!(
+                "Filling {} prioritized histograms first",
+                groups[0].len()
+            ));
+        }
+        progress_bar.set_length((rows_to_fill * groups.len()) as u64);
+        *progress.lock().unwrap() = FillProgress::start(
+            "Filling",
+            rows_to_fill * groups.len(),
+            groups.iter().map(FillGroup::names).collect(),
+        );
+
+        // Fits and rebins of these histograms wait in their queue until their group is filled
+        for group in &groups {
+            group.set_filling(true);
+        }
+
+        let mut lf = Arc::new(lf);
+        let fill_mode = self.fill_mode;
+        let memory = memory.clone();
+        // a preview reads too few rows to be worth writing all of them to disk
+        let spill = memory.spill && sample.is_none();
 
         // Spawn the batch processing task asynchronously
         rayon::spawn({
             let calculating = Arc::clone(&calculating);
-            let lf = Arc::clone(&lf); // Clone lf to move into the spawn closure
             let progress_bar = progress_bar.clone();
-            let total_rows = row_count as f32;
 
             move || {
-                let mut row_start = 0;
-                loop {
-                    if abort_flag.load(Ordering::SeqCst) {
-                        println!("Processing aborted by user.");
-                        break;
-                    }
-                    // Slice the LazyFrame into batches
-                    let batch_lf = lf
-                        .as_ref()
-                        .clone()
-                        .slice(row_start as i64, rows_per_chunk.try_into().unwrap());
-
-                    // Break if no rows are left to process
-                    if batch_lf.clone().limit(1).collect().unwrap().height() == 0 {
-                        break;
-                    }
-
-                    if let Ok(df) = batch_lf.collect() {
-                        let height = df.height();
-
-                        // Fill 1D histograms in parallel
-                        hist1d_map.par_iter().for_each(|(hist, meta)| {
-                            if let Ok(column) = df.column(&meta.column_name).and_then(|c| c.f64()) {
-                                let mut hist = hist.lock().unwrap();
-                                column.into_no_null_iter().enumerate().for_each(
-                                    |(index, value)| {
-                                        if value != -1e6 && meta.cuts.valid(&df, index) {
-                                            hist.fill(value);
-                                            hist.plot_settings.egui_settings.reset_axis = true;
-                                        }
-                                    },
-                                );
+                let mut spill_files = Vec::new();
+                if spill {
+                    let names = progress.lock().unwrap().groups.clone();
+                    *progress.lock().unwrap() =
+                        FillProgress::start("Spilling to disk", row_count as usize, names.clone());
+                    let spilled = memory_budget::spill_to_disk(
+                        &lf,
+                        &used_columns,
+                        row_count as usize,
+                        memory.batch_rows(bytes_per_row, COLLECT_ROWS),
+                        &memory.spill_dir,
+                        &abort_flag,
+                        |rows| progress.lock().unwrap().add_rows(rows),
+                    );
+                    match spilled {
+                        None => {
+                            println!("Processing aborted by user.");
+                            *fill_error.lock().unwrap() =
+                                Some("Processing aborted by user.".to_string());
+                            calculating.store(false, Ordering::SeqCst);
+                            for group in &groups {
+                                group.set_filling(false);
                             }
-                        });
-
-                        // Fill 2D histograms in parallel
-                        hist2d_map.par_iter().for_each(|(hist, meta)| {
-                            if let (Ok(x_col), Ok(y_col)) = (
-                                df.column(&meta.x_column_name).and_then(|c| c.f64()),
-                                df.column(&meta.y_column_name).and_then(|c| c.f64()),
+                            return;
+                        }
+                        Some(Ok(files)) => {
+                            let files_arc: Arc<[std::path::PathBuf]> = Arc::from(files.clone());
+                            match LazyFrame::scan_parquet_files(
+                                files_arc,
+                                ScanArgsParquet::default(),
                             ) {
-                                let mut hist = hist.lock().unwrap();
-                                x_col
-                                    .into_no_null_iter()
-                                    .zip(y_col.into_no_null_iter())
-                                    .enumerate()
-                                    .for_each(|(index, (x, y))| {
-                                        if x != -1e6 && y != -1e6 && meta.cuts.valid(&df, index) {
-                                            hist.fill(x, y);
-                                        }
-                                    });
+                                Ok(spilled) => {
+                                    lf = Arc::new(spilled);
+                                    spill_files = files;
+                                }
+                                Err(e) => {
+                                    log::error!("Failed to read the spill files: {}", e);
+                                    memory_budget::remove_spill(&files);
+                                }
                             }
-                        });
-
-                        hist2d_map.par_iter().for_each(|(hist, meta)| {
-                            let mut hist = hist.lock().unwrap();
-                            hist.plot_settings.recalculate_image = true;
-                            hist.plot_settings.egui_settings.reset_axis = true;
-                            hist.plot_settings.x_column = meta.x_column_name.clone();
-                            hist.plot_settings.y_column = meta.y_column_name.clone();
-                        });
-
-                        hist1d_map.par_iter().for_each(|(hist, _)| {
-                            let mut hist = hist.lock().unwrap();
-                            hist.plot_settings.egui_settings.reset_axis = true;
-                        });
-
-                        progress_bar.inc(height as u64);
-
-                        // Update progress as a percentage
-                        let completed_rows = row_start as f32 + height as f32;
-                        let percentage = completed_rows / total_rows;
-                        {
-                            let mut progress_lock = progress.lock().unwrap();
-                            *progress_lock = percentage;
                         }
+                        Some(Err(e)) => {
+                            log::error!("Failed to spill to disk, filling without: {}", e)
+                        }
+                    }
+                    *progress.lock().unwrap() =
+                        FillProgress::start("Filling", rows_to_fill * groups.len(), names);
+                }
+
+                'groups: for (index, group) in groups.iter().enumerate() {
+                    progress.lock().unwrap().group = index;
+
+                    // Select only the columns this group needs
+                    let group_columns: Vec<Expr> = group.columns.iter().map(col).collect();
+
+                    // Each chunk is collected in batches of at most COLLECT_ROWS rows,
+                    // so an abort is noticed between batches and not only between chunks.
+                    // With an automatic budget the batches shrink while the free RAM is low.
+                    let group_bytes_per_row = group.columns.len() as f64 * 8.0;
+                    let memory = &memory;
+                    let batches = chunks.iter().flat_map(|&(chunk_start, chunk_rows)| {
+                        let chunk_end = chunk_start + chunk_rows;
+                        let mut start = chunk_start;
+                        std::iter::from_fn(move || {
+                            (start < chunk_end).then(|| {
+                                let rows = memory
+                                    .batch_rows(group_bytes_per_row, COLLECT_ROWS)
+                                    .min(chunk_end - start);
+                                start += rows;
+                                (start - rows, rows)
+                            })
+                        })
+                    });
+
+                    let mut stopped = false;
+                    for (row_start, batch_rows) in batches {
+                        if abort_flag.load(Ordering::SeqCst) {
+                            println!("Processing aborted by user.");
+                            *fill_error.lock().unwrap() =
+                                Some("Processing aborted by user.".to_string());
+                            stopped = true;
+                            break;
+                        }
+                        // Slice the LazyFrame into batches, the slice goes before the time gate
+                        // so it is pushed down to the scan and does not read the rows before it
+                        let mut batch_lf = lf
+                            .as_ref()
+                            .clone()
+                            .slice(row_start as i64, batch_rows.try_into().unwrap());
+                        if let Some(gate) = &time_gate {
+                            batch_lf = batch_lf.filter(gate.clone());
+                        }
+                        let batch_lf = batch_lf.select(group_columns.clone());
+
+                        let df = match batch_lf.collect() {
+                            Ok(df) => df,
+                            Err(e) => {
+                                let e = format!("Failed to read rows {}: {}", row_start, e);
+                                log::error!("{}", e);
+                                *fill_error.lock().unwrap() = Some(e);
+                                stopped = true;
+                                break;
+                            }
+                        };
+
+                        group.fill(&df, sample, fill_mode);
+
+                        // rows of the source, the gate may have dropped some of them
+                        progress_bar.inc(batch_rows as u64);
+                        progress.lock().unwrap().add_rows(batch_rows);
                     }
 
-                    row_start += rows_per_chunk;
+                    // the duplicates show what was filled, also when the fill stopped early
+                    group.copy_aliases(sample);
+                    group.set_filling(false);
+                    if stopped {
+                        break 'groups;
+                    }
                 }
 
-                let mut progress_lock = progress.lock().unwrap();
-                *progress_lock = 1.0;
+                progress.lock().unwrap().fraction = 1.0;
+                memory_budget::remove_spill(&spill_files);
 
                 progress_bar.finish_with_message("Processing complete.");
+                for group in &groups {
+                    group.set_filling(false);
+                }
                 // Set calculating to false when processing is complete
                 calculating.store(false, Ordering::SeqCst);
             }
         });
+        Ok(())
+    }
+
+    // Adds rows to the histograms without resetting them, e.g. the rows received in online mode
+    pub fn append_rows(&mut self, mut configs: Configs, df: DataFrame) {
+        let cut_errors = configs.cut_errors();
+        if !cut_errors.is_empty() {
+            for e in cut_errors {
+                log::error!("{}", e);
+            }
+            return;
+        }
+
+        let mut lf = df.lazy();
+        let valid_configs = configs.valid_configs(&mut lf);
+        if valid_configs.is_empty() {
+            return;
+        }
+        valid_configs.add_missing_panes(self);
+
+        let columns: Vec<Expr> = valid_configs.get_used_columns().iter().map(col).collect();
+        let df = match valid_configs.time_gated(lf).select(columns).collect() {
+            Ok(df) => df,
+            Err(e) => {
+                log::error!("Error adding rows: {}", e);
+                return;
+            }
+        };
+
+        let group = FillGroup::new(self, &valid_configs);
+        group.fill(&df, None, self.fill_mode);
+        group.copy_aliases(None);
+    }
+
+    // Names of the histograms in the panes that are currently shown
+    fn visible_pane_names(&self) -> HashSet<String> {
+        self.tree
+            .active_tiles()
+            .into_iter()
+            .filter_map(|id| match self.tree.tiles.get(id) {
+                Some(egui_tiles::Tile::Pane(Pane::Histogram(hist))) => {
+                    Some(hist.lock().unwrap().name.clone())
+                }
+                Some(egui_tiles::Tile::Pane(Pane::Histogram2D(hist))) => {
+                    Some(hist.lock().unwrap().name.clone())
+                }
+                Some(egui_tiles::Tile::Pane(Pane::Histogram3D(hist))) => {
+                    Some(hist.lock().unwrap().name.clone())
+                }
+                Some(egui_tiles::Tile::Pane(Pane::HistogramND(hist))) => {
+                    Some(hist.lock().unwrap().name.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    // The prioritized configs and the rest, duplicates follow the histogram they are filled from
+    fn split_by_priority(&self, configs: &Configs) -> Vec<Configs> {
+        if !self.fill_priority.is_active() {
+            return vec![configs.clone()];
+        }
+
+        let visible = self.visible_pane_names();
+        let is_first = self.fill_priority.matcher(&visible);
+
+        let prioritized: HashSet<String> = configs
+            .configs
+            .iter()
+            .filter(|config| {
+                let cuts: Vec<&str> = config.cuts().cuts.iter().map(|cut| cut.name()).collect();
+                is_first(config.name(), &cuts)
+            })
+            .map(|config| config.name().to_string())
+            .chain(
+                configs
+                    .aliases
+                    .iter()
+                    .filter(|(alias, _)| is_first(alias, &[]))
+                    .map(|(_, source)| source.clone()),
+            )
+            .collect();
+
+        let (mut first, mut rest) = (Configs::default(), Configs::default());
+        for config in &configs.configs {
+            if prioritized.contains(config.name()) {
+                first.configs.push(config.clone());
+            } else {
+                rest.configs.push(config.clone());
+            }
+        }
+        for (alias, source) in &configs.aliases {
+            if prioritized.contains(source) {
+                first.aliases.push((alias.clone(), source.clone()));
+            } else {
+                rest.aliases.push((alias.clone(), source.clone()));
+            }
+        }
+
+        vec![first, rest]
     }
 
     pub fn add_hist1d_with_bin_values(
@@ -414,10 +885,11 @@ impl Histogrammer {
                 }
             })
         {
-            hist.lock().unwrap().bins = bins.clone();
-            hist.lock().unwrap().original_bins = bins;
-            hist.lock().unwrap().underflow = underflow;
-            hist.lock().unwrap().overflow = overflow;
+            let mut hist = hist.lock().unwrap();
+            hist.original_bins = bins;
+            hist.underflow = underflow;
+            hist.overflow = overflow;
+            hist.rebin();
         }
     }
 
@@ -467,9 +939,11 @@ impl Histogrammer {
             })
         {
             let mut hist = hist.lock().unwrap();
-            hist.bins.counts = bin_map;
+            hist.bins.counts = bin_map.into();
             hist.bins.min_count = min_value;
             hist.bins.max_count = max_value;
+            let storage = hist.plot_settings.storage;
+            hist.bins.update_storage(storage);
 
             // Flag the image to be recalculated due to new bin values
             hist.plot_settings.recalculate_image = true;
@@ -479,18 +953,222 @@ impl Histogrammer {
     }
 
     pub fn ui(&mut self, ui: &mut egui::Ui) {
+        // the legend is not saved, rebuild it after a restart
+        if self.colors.shared_legend
+            && self.colors.legend.is_empty()
+            && self.colors.color_by != ColorBy::Pane
+        {
+            self.apply_color_policy();
+        }
+        self.colors.legend_ui(ui);
+        self.behavior.update_grids(&self.tree.tiles);
         self.tree.ui(&mut self.behavior, ui);
+        self.record_removals();
+        self.undo_keybinds(ui.ctx());
+        self.link_shared_cuts();
+        self.add_band_slices();
+        self.share_marker_sets();
+        self.update_stacks();
+        self.update_comparisons();
+        self.pulls.show(ui);
+        if self.fit_summary.open {
+            let hists = self.hist1d_panes();
+            self.fit_summary.show(ui.ctx(), &hists);
+        }
+        if self.fit_library.open {
+            let hists = self.hist1d_panes();
+            self.fit_library.show(ui.ctx(), &hists);
+        }
+        if self.kinematics.open {
+            let hists = self.hist1d_panes();
+            self.kinematics.show(ui.ctx(), &hists);
+        }
+        if self.composer.open {
+            let mut names = self.hist1d_names();
+            names.extend(self.hist2d_names());
+            let panes: Vec<Pane> = self
+                .tree
+                .tiles
+                .iter()
+                .filter_map(|(_id, tile)| match tile {
+                    egui_tiles::Tile::Pane(pane) => Some(pane.clone()),
+                    _ => None,
+                })
+                .collect();
+            self.composer.show(ui.ctx(), &names, &panes);
+        }
     }
 
-    pub fn menu_ui(&mut self, ui: &mut egui::Ui) {
-        // self.behavior.ui(ui);
+    // Collects the notes of every histogram into a markdown report
+    pub fn notes_report(&self) -> String {
+        let mut sections: Vec<(String, String)> = self
+            .tree
+            .tiles
+            .iter()
+            .filter_map(|(_id, tile)| match tile {
+                egui_tiles::Tile::Pane(Pane::Histogram(hist)) => {
+                    let hist = hist.lock().unwrap();
+                    (!hist.plot_settings.notes.is_empty()).then(|| {
+                        (
+                            hist.name.clone(),
+                            hist.plot_settings.notes.report(&hist.name),
+                        )
+                    })
+                }
+                egui_tiles::Tile::Pane(Pane::Histogram2D(hist)) => {
+                    let hist = hist.lock().unwrap();
+                    (!hist.plot_settings.notes.is_empty()).then(|| {
+                        (
+                            hist.name.clone(),
+                            hist.plot_settings.notes.report(&hist.name),
+                        )
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        sections.sort_by(|a, b| a.0.cmp(&b.0));
 
-        ui.menu_button("Histogrammer", |ui| {
-            if let Some(root) = self.tree.root() {
-                ui.horizontal(|ui| {
-                    ui.heading("Tree");
+        let mut report = "# Notes\n\n".to_string();
+        for (_name, section) in sections {
+            report.push_str(&section);
+            report.push('\n');
+        }
+        report
+    }
 
-                    ui.separator();
+    fn hist2d_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .tree
+            .tiles
+            .iter()
+            .filter_map(|(_id, tile)| match tile {
+                egui_tiles::Tile::Pane(Pane::Histogram2D(hist)) => {
+                    Some(hist.lock().unwrap().name.clone())
+                }
+                _ => None,
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn hist1d_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .hist1d_panes()
+            .iter()
+            .map(|hist| hist.lock().unwrap().name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    // Adds the result of the histogram math as a pane, an existing result is replaced
+    pub fn add_histogram_math(&mut self) {
+        let Some(hist_a) = self.find_hist1d(&self.math.hist_a) else {
+            log::error!("Could not find 1D histogram '{}'", self.math.hist_a);
+            return;
+        };
+        let hist_b = self.find_hist1d(&self.math.hist_b);
+
+        let result = {
+            let a = hist_a.lock().unwrap().clone();
+            let b = hist_b.map(|hist| hist.lock().unwrap().clone());
+            self.math.calculate(&a, b.as_deref())
+        };
+
+        match result {
+            Ok(hist) => match self.find_hist1d(&hist.name) {
+                Some(existing) => {
+                    let mut existing = existing.lock().unwrap();
+                    existing.range = hist.range;
+                    existing.edges = hist.edges.clone();
+                    existing.plot_settings.rebin_factor = 1;
+                    if let Some(values) = hist.values {
+                        existing.set_values(values);
+                    }
+                    existing.plot_settings.egui_settings.reset_axis = true;
+                }
+                None => {
+                    self.insert_1d_pane(hist);
+                }
+            },
+            Err(e) => log::error!("Histogram math failed: {}", e),
+        }
+    }
+
+    pub fn add_summed_spectrum(&mut self) {
+        let names = self.hist1d_names();
+        let matches = match self.sum_spectra.matches(&names) {
+            Ok(matches) => matches,
+            Err(e) => {
+                log::error!("Invalid sum pattern '{}': {}", self.sum_spectra.pattern, e);
+                return;
+            }
+        };
+
+        // one lock at a time, a fill may hold the others
+        let hists: Vec<Histogram> = matches
+            .iter()
+            .filter_map(|name| self.find_hist1d(name))
+            .map(|hist| *hist.lock().unwrap().clone())
+            .collect();
+
+        match self.sum_spectra.sum(&hists) {
+            Ok(hist) => {
+                log::info!("Summed {} histograms into '{}'", hists.len(), hist.name);
+                match self.find_hist1d(&hist.name) {
+                    Some(existing) => {
+                        let mut existing = existing.lock().unwrap();
+                        existing.range = hist.range;
+                        existing.bin_width = hist.bin_width;
+                        existing.edges = hist.edges.clone();
+                        existing.original_bins = hist.original_bins.clone();
+                        existing.underflow = hist.underflow;
+                        existing.overflow = hist.overflow;
+                        existing.values = None;
+                        existing.plot_settings.rebin_factor = 1;
+                        match hist.values {
+                            Some(values) => existing.set_values(values),
+                            None => existing.rebin(),
+                        }
+                        existing.plot_settings.egui_settings.reset_axis = true;
+                    }
+                    None => {
+                        self.insert_1d_pane(hist);
+                    }
+                }
+            }
+            Err(e) => log::error!("Summing histograms failed: {}", e),
+        }
+    }
+
+    pub fn add_pulls_map(&mut self, a: &str, b: &str, scale_b: bool) {
+        let (Some(hist_a), Some(hist_b)) = (self.find_hist2d(a), self.find_hist2d(b)) else {
+            log::error!("Could not find 2D histograms '{}' and '{}'", a, b);
+            return;
+        };
+
+        // one lock at a time, a fill may hold the other histogram
+        let hist_a = hist_a.lock().unwrap().clone();
+        let hist_b = hist_b.lock().unwrap().clone();
+        let result = PullsMap::new(&hist_a, &hist_b, scale_b);
+
+        match result {
+            Ok(map) => self.pulls.maps.push(map),
+            Err(e) => log::error!("Failed to create pulls map: {}", e),
+        }
+    }
+
+    pub fn menu_ui(&mut self, ui: &mut egui::Ui) {
+        // self.behavior.ui(ui);
+
+        ui.menu_button("Histogrammer", |ui| {
+            if let Some(root) = self.tree.root() {
+                ui.horizontal(|ui| {
+                    ui.heading("Tree");
+
+                    ui.separator();
 
                     if ui.button("Reorganize").clicked() {
                         self.reorganize();
@@ -499,8 +1177,12 @@ impl Histogrammer {
                     ui.separator();
 
                     if ui.button("Reset").clicked() {
-                        *self = Default::default();
+                        self.reset_workspace();
                     }
+
+                    ui.separator();
+
+                    self.undo_ui(ui);
                 });
                 ui.separator();
 
@@ -508,10 +1190,99 @@ impl Histogrammer {
 
                 ui.separator();
 
-                tree_ui(ui, &mut self.behavior, &mut self.tree.tiles, root);
+                self.presets.menu_button(ui);
+
+                if self.colors.menu_button(ui) {
+                    self.apply_color_policy();
+                }
+
+                self.fill_priority.menu_button(ui);
+                self.fill_mode.menu_button(ui);
+
+                ui.separator();
+
+                let mut action = None;
+                tree_ui(
+                    ui,
+                    &mut self.behavior,
+                    &mut self.tree.tiles,
+                    root,
+                    &mut action,
+                );
+                let result = match action {
+                    Some(PaneAction::Rename(old_name, new_name)) => {
+                        self.rename_histogram(&old_name, &new_name)
+                    }
+                    Some(PaneAction::Duplicate(name)) => {
+                        self.duplicate_histogram(&name).map(|_| ())
+                    }
+                    None => Ok(()),
+                };
+                if let Err(e) = result {
+                    log::error!("{}", e);
+                }
 
                 ui.separator();
 
+                if ui
+                    .button("Copy Notes Report")
+                    .on_hover_text("Copy the notes of all histograms as markdown")
+                    .clicked()
+                {
+                    ui.ctx().copy_text(self.notes_report());
+                }
+
+                ui.toggle_value(&mut self.fit_summary.open, "Fit Summary")
+                    .on_hover_text("Table of the stored fits of every histogram, with CSV/Parquet export");
+
+                ui.toggle_value(&mut self.fit_library.open, "Fit Library")
+                    .on_hover_text("Save and load the fits of each histogram as fit archives");
+
+                ui.toggle_value(&mut self.kinematics.open, "Kinematics")
+                    .on_hover_text("Predict the focal plane positions of the states of a two-body reaction");
+
+                ui.toggle_value(&mut self.composer.open, "Figure Composer")
+                    .on_hover_text("Arrange several histograms in a grid and export them as one PNG, SVG, or PDF figure");
+
+                let panes: Vec<&Pane> = self
+                    .tree
+                    .tiles
+                    .iter()
+                    .filter_map(|(_id, tile)| match tile {
+                        egui_tiles::Tile::Pane(pane) => Some(pane),
+                        _ => None,
+                    })
+                    .collect();
+                self.summary_stats.menu_button(ui, &panes);
+
+                ui.separator();
+
+                let names = self.hist2d_names();
+                if self.pulls.menu_ui(ui, &names) {
+                    let (a, b) = (self.pulls.hist_a.clone(), self.pulls.hist_b.clone());
+                    self.add_pulls_map(&a, &b, self.pulls.scale_b);
+                }
+
+                ui.separator();
+
+                let names = self.hist1d_names();
+                if self.math.menu_ui(ui, &names) {
+                    self.add_histogram_math();
+                }
+
+                ui.separator();
+
+                let names = self.hist1d_names();
+                if self.sum_spectra.menu_ui(ui, &names) {
+                    self.add_summed_spectrum();
+                }
+
+                ui.separator();
+
+                #[cfg(feature = "python")]
+                ui.checkbox(&mut self.root_use_uproot, "Use uproot")
+                    .on_hover_text("Write the ROOT file with the Python uproot module instead of the native writer");
+
                 if ui.button("Create ROOT File").clicked() {
                     // Use rfd to open a file save dialog
                     let file_dialog = rfd::FileDialog::new()
@@ -523,10 +1294,7 @@ impl Histogrammer {
                     if let Some(path) = file_dialog {
                         // Convert path to a string and call the function
                         if let Some(output_file) = path.to_str() {
-                            match self.histograms_to_root(output_file) {
-                                Ok(_) => println!("ROOT file created at: {}", output_file),
-                                Err(e) => eprintln!("Error creating ROOT file: {:?}", e),
-                            }
+                            self.write_root_file(output_file);
                         } else {
                             eprintln!("Invalid file path selected.");
                         }
@@ -535,9 +1303,108 @@ impl Histogrammer {
                     }
                 }
             }
+
+            ui.separator();
+
+            if ui
+                .button("Import MCA Spectra…")
+                .on_hover_text("ORTEC .Chn or .Spe files, added under 'Imported'")
+                .clicked()
+            {
+                if let Some(paths) = rfd::FileDialog::new()
+                    .add_filter("MCA spectrum", &["Chn", "chn", "Spe", "spe"])
+                    .pick_files()
+                {
+                    for path in paths {
+                        self.import_mca(&path);
+                    }
+                }
+            }
+
+            if ui
+                .button("Import ASCII Spectra…")
+                .on_hover_text("Text files with one bin per line, \"counts\" or \"x counts\", added under 'Imported'")
+                .clicked()
+            {
+                if let Some(paths) = rfd::FileDialog::new()
+                    .add_filter("Text spectrum", &["txt", "dat", "csv", "tsv", "asc"])
+                    .add_filter("All files", &["*"])
+                    .pick_files()
+                {
+                    for path in paths {
+                        self.import_text_spectrum(&path);
+                    }
+                }
+            }
         });
     }
 
+    pub fn import_mca(&mut self, path: &std::path::Path) {
+        match McaSpectrum::read(path) {
+            Ok(spectrum) => {
+                let stem = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "Spectrum".to_string());
+                let name = format!("Imported/{}", stem);
+                if self.find_hist1d(&name).is_some() {
+                    log::error!("A histogram named '{}' already exists", name);
+                    return;
+                }
+                self.insert_1d_pane(spectrum.to_histogram(&name));
+            }
+            Err(e) => log::error!("Error reading {}: {}", path.display(), e),
+        }
+    }
+
+    // Integer counts on equal width bins are added like a filled histogram. Otherwise the
+    // x column sets variable width bins, and non-integer counts (e.g. a corrected reference
+    // spectrum) are kept as bin values with sqrt(counts) uncertainties.
+    pub fn import_text_spectrum(&mut self, path: &std::path::Path) {
+        let spectrum = match TextSpectrum::read(path) {
+            Ok(spectrum) => spectrum,
+            Err(e) => {
+                log::error!("Error reading {}: {}", path.display(), e);
+                return;
+            }
+        };
+
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Spectrum".to_string());
+        let name = format!("Imported/{}", stem);
+        if self.find_hist1d(&name).is_some() {
+            log::error!("A histogram named '{}' already exists", name);
+            return;
+        }
+
+        if let (true, Some(range)) = (spectrum.is_integer(), spectrum.range()) {
+            let bins = spectrum.counts.iter().map(|&count| count as u64).collect();
+            self.add_hist1d_with_bin_values(&name, bins, 0, 0, range);
+            return;
+        }
+
+        let mut hist = match spectrum.range() {
+            Some(range) => Histogram::new(&name, spectrum.counts.len(), range),
+            None => Histogram::with_edges(&name, &spectrum.edges()),
+        };
+        if spectrum.is_integer() {
+            hist.original_bins = spectrum.counts.iter().map(|&count| count as u64).collect();
+            hist.rebin();
+        } else {
+            hist.set_values(BinValues {
+                errors: spectrum
+                    .counts
+                    .iter()
+                    .map(|count| count.abs().sqrt())
+                    .collect(),
+                contents: spectrum.counts,
+            });
+        }
+        self.insert_1d_pane(hist);
+    }
+
     fn ensure_root(&mut self) -> TileId {
         // Ensure that `self.tree.root` has been initialized
         if let Some(root_id) = self.tree.root {
@@ -810,19 +1677,274 @@ impl Histogrammer {
         log::info!("Reorganization complete.");
     }
 
+    // Adds the slices made on 2D panes as 1D panes, slices made again replace the old counts
+    fn add_band_slices(&mut self) {
+        let mut slices = Vec::new();
+        for (_id, tile) in self.tree.tiles.iter() {
+            if let egui_tiles::Tile::Pane(Pane::Histogram2D(hist)) = tile {
+                slices.append(&mut hist.lock().unwrap().plot_settings.band_slices.pending);
+            }
+        }
+
+        for (name, counts, range) in slices {
+            self.add_hist1d_with_bin_values(&name, counts, 0, 0, range);
+        }
+    }
+
+    // Copies the components of every stacked 1D histogram into its stack layers
+    fn update_stacks(&mut self) {
+        let hists = self.hist1d_panes();
+        for hist in &hists {
+            let Some(re) = hist.lock().unwrap().plot_settings.stack.regex() else {
+                let mut hist = hist.lock().unwrap();
+                hist.plot_settings.stack.layers.clear();
+                hist.plot_settings.stack.skipped.clear();
+                continue;
+            };
+
+            let mut components: Vec<Arc<Mutex<Box<Histogram>>>> = hists
+                .iter()
+                .filter(|other| !Arc::ptr_eq(hist, other))
+                .filter(|other| re.is_match(&other.lock().unwrap().name))
+                .cloned()
+                .collect();
+            components.sort_by_cached_key(|other| other.lock().unwrap().name.clone());
+            components.dedup_by(|a, b| Arc::ptr_eq(a, b));
+
+            let mut layers = Vec::new();
+            let mut skipped = Vec::new();
+            {
+                let total = hist.lock().unwrap();
+                for other in &components {
+                    let other = other.lock().unwrap();
+                    if total.same_bins(&other) {
+                        layers.push(other.stack_layer());
+                    } else {
+                        skipped.push(other.name.clone());
+                    }
+                }
+            }
+
+            let mut hist = hist.lock().unwrap();
+            hist.plot_settings.stack.layers = layers;
+            hist.plot_settings.stack.skipped = skipped;
+        }
+    }
+
+    // Copies the reference of every comparison from its pane, so it follows refills of either
+    fn update_comparisons(&mut self) {
+        for hist in self.hist1d_panes() {
+            let (enabled, reference) = {
+                let comparison = &hist.lock().unwrap().plot_settings.comparison;
+                (comparison.enabled, comparison.reference.clone())
+            };
+            if !enabled {
+                continue;
+            }
+
+            let (layer, status) = match self.find_hist1d(&reference) {
+                _ if reference.is_empty() => (None, Some("No reference histogram".to_string())),
+                Some(other) if Arc::ptr_eq(&hist, &other) => {
+                    (None, Some("The reference is this histogram".to_string()))
+                }
+                Some(other) => {
+                    let other = other.lock().unwrap();
+                    if hist.lock().unwrap().same_bins(&other) {
+                        (Some(other.reference_layer()), None)
+                    } else {
+                        (None, Some(format!("'{}' has different bins", reference)))
+                    }
+                }
+                None => (None, Some(format!("Histogram '{}' not found", reference))),
+            };
+
+            let mut hist = hist.lock().unwrap();
+            hist.plot_settings.comparison.layer = layer;
+            hist.plot_settings.comparison.status = status;
+        }
+    }
+
+    // Copies the marker sets shared from a 1D pane to the other 1D histograms in its folder
+    fn share_marker_sets(&mut self) {
+        let family = |name: &str| {
+            name.rsplit_once('/')
+                .map_or("", |(folder, _)| folder)
+                .to_string()
+        };
+
+        let mut shared = Vec::new();
+        for (_id, tile) in self.tree.tiles.iter() {
+            if let egui_tiles::Tile::Pane(Pane::Histogram(hist)) = tile {
+                let mut hist = hist.lock().unwrap();
+                let name = hist.name.clone();
+                for set in hist.plot_settings.markers.shared.drain(..) {
+                    shared.push((name.clone(), set));
+                }
+            }
+        }
+
+        for (source, set) in shared {
+            for (_id, tile) in self.tree.tiles.iter() {
+                if let egui_tiles::Tile::Pane(Pane::Histogram(hist)) = tile {
+                    let mut hist = hist.lock().unwrap();
+                    if hist.name != source && family(&hist.name) == family(&source) {
+                        log::info!("Sharing marker set '{}' with '{}'", set.name, hist.name);
+                        hist.plot_settings.markers.insert_set(set.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    // Panes drawing a cut with the same name and axis columns share one copy, so editing
+    // the cut in one pane updates every other pane. Cuts still being drawn and cuts on
+    // calibrated histograms are left alone.
+    pub fn link_shared_cuts(&mut self) {
+        type CutKey = (String, String, String);
+        let mut shared: HashMap<CutKey, Arc<Mutex<Cut2D>>> = HashMap::new();
+
+        for (_id, tile) in self.tree.tiles.iter() {
+            if let egui_tiles::Tile::Pane(Pane::Histogram2D(hist)) = tile {
+                let mut hist = hist.lock().unwrap();
+                if hist.plot_settings.calibration.is_applied() {
+                    continue;
+                }
+
+                for cut in &mut hist.plot_settings.cuts {
+                    let key = {
+                        let cut = cut.lock().unwrap();
+                        if cut.is_clicking() || cut.x_column.is_empty() || cut.y_column.is_empty() {
+                            continue;
+                        }
+                        (
+                            cut.polygon.name.clone(),
+                            cut.x_column.clone(),
+                            cut.y_column.clone(),
+                        )
+                    };
+
+                    match shared.get(&key) {
+                        Some(existing) if !Arc::ptr_eq(existing, cut) => {
+                            log::info!("Linking cut '{}' across panes", key.0);
+                            *cut = Arc::clone(existing);
+                        }
+                        Some(_) => {}
+                        None => {
+                            shared.insert(key, Arc::clone(cut));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Cuts promoted on 2D panes since the last call, for the workspace cuts
+    pub fn take_promoted_cuts(&self) -> Vec<Cut2D> {
+        let mut promoted = Vec::new();
+        for (_id, tile) in self.tree.tiles.iter() {
+            if let egui_tiles::Tile::Pane(Pane::Histogram2D(hist)) = tile {
+                promoted.append(&mut hist.lock().unwrap().plot_settings.promoted_cuts);
+            }
+        }
+        promoted
+    }
+
     pub fn retrieve_active_2d_cuts(&self) {
         let mut active_cuts = Vec::new();
         for (_id, tile) in self.tree.tiles.iter() {
             if let egui_tiles::Tile::Pane(Pane::Histogram2D(hist)) = tile {
                 let hist = hist.lock().unwrap();
                 for cut in &hist.plot_settings.cuts {
-                    active_cuts.push(cut.clone());
+                    active_cuts.push(cut.lock().unwrap().clone());
+                }
+            }
+        }
+    }
+
+    // Copies the current bin contents so the file can be written off the UI thread
+    pub fn root_file(&self) -> RootFile {
+        let mut root_file = RootFile::default();
+
+        for (_id, tile) in self.tree.tiles.iter() {
+            match tile {
+                egui_tiles::Tile::Pane(Pane::Histogram(hist)) => {
+                    let hist = hist.lock().unwrap();
+                    root_file.add_hist1d(
+                        &hist.name,
+                        &hist.bins,
+                        hist.underflow,
+                        hist.overflow,
+                        hist.range,
+                        &hist.export_edges(),
+                    );
+                }
+                egui_tiles::Tile::Pane(Pane::Histogram2D(hist)) => {
+                    let hist = hist.lock().unwrap();
+
+                    // Use backup bins if available
+                    let bins = hist.backup_bins.as_ref().unwrap_or(&hist.bins);
+                    root_file.add_hist2d(
+                        &hist.name,
+                        (bins.x, bins.y),
+                        bins.counts.to_map(),
+                        (hist.range.x.min, hist.range.x.max),
+                        (hist.range.y.min, hist.range.y.max),
+                    );
                 }
+                _ => {}
+            }
+        }
+
+        root_file
+    }
+
+    // Writes the ROOT file in the background, reusing the fill progress bar and cancel button
+    pub fn histograms_to_root(&self, output_file: &str) {
+        if self.calculating.load(Ordering::SeqCst) {
+            log::error!("Can not create a ROOT file while histograms are being filled.");
+            return;
+        }
+
+        let root_file = self.root_file();
+        let output_file = output_file.to_string();
+        let calculating = Arc::clone(&self.calculating);
+        let abort_flag = Arc::clone(&self.abort_flag);
+        let progress = Arc::clone(&self.progress);
+
+        calculating.store(true, Ordering::SeqCst);
+        abort_flag.store(false, Ordering::SeqCst);
+        *progress.lock().unwrap() = FillProgress::start("Writing ROOT file", 0, Vec::new());
+
+        rayon::spawn(move || {
+            let write_progress = WriteProgress {
+                progress: &progress,
+                abort: &abort_flag,
+            };
+
+            match root_file.write(&output_file, &write_progress) {
+                Ok(_) => println!("ROOT file created at: {}", output_file),
+                Err(e) => eprintln!("Error creating ROOT file: {:?}", e),
+            }
+
+            calculating.store(false, Ordering::SeqCst);
+        });
+    }
+
+    // With the native writer, or uproot when it is chosen and built in
+    fn write_root_file(&mut self, output_file: &str) {
+        #[cfg(feature = "python")]
+        if self.root_use_uproot {
+            match self.histograms_to_root_uproot(output_file) {
+                Ok(_) => println!("ROOT file created at: {}", output_file),
+                Err(e) => eprintln!("Error creating ROOT file: {:?}", e),
             }
+            return;
         }
+        self.histograms_to_root(output_file);
     }
 
-    pub fn histograms_to_root(&mut self, output_file: &str) -> PyResult<()> {
+    #[cfg(feature = "python")]
+    pub fn histograms_to_root_uproot(&mut self, output_file: &str) -> PyResult<()> {
         // python3 -m venv .venv
         // source .venv/bin/activate
         // export PYO3_PYTHON=$(pwd)/.venv/bin/python
@@ -867,17 +1989,22 @@ def write_histograms(output_file, hist1d_data, hist2d_data):
             - underflow (int): Underflow count.
             - overflow (int): Overflow count.
             - range (tuple): Range of the histogram as (min, max).
+            - edges (list of float): Bin edges for variable width bins, empty for fixed width bins.
         hist2d_data (list): List of tuples for 2D histograms. Each tuple contains:
             - name (str): Histogram name.
             - title (str): Histogram title.
-            - bins (list of list of int): Bin counts (2D array).
+            - data (bytes): Bin counts as little endian float64, row-major in y.
+            - shape (tuple): Shape of the counts as (y bins, x bins).
             - range_x (tuple): Range of the X-axis as (min, max).
             - range_y (tuple): Range of the Y-axis as (min, max).
     """
     with uproot.recreate(output_file) as file:
-        for name, title, bins, underflow, overflow, range in hist1d_data:
-            # Create bin edges for the histogram
-            bin_edges = np.linspace(range[0], range[1], len(bins) + 1)
+        for name, title, bins, underflow, overflow, range, edges in hist1d_data:
+            # Create bin edges for the histogram, variable width histograms pass their edges
+            if len(edges) == len(bins) + 1:
+                bin_edges = np.array(edges, dtype=np.float64)
+            else:
+                bin_edges = np.linspace(range[0], range[1], len(bins) + 1)
             
             # Include underflow and overflow in the data array
             data = np.array([underflow] + bins + [overflow], dtype=np.float32)
@@ -916,8 +2043,8 @@ def write_histograms(output_file, hist1d_data, hist2d_data):
             )
             
         # Write 2D histograms
-        for name, title, bins, range_x, range_y in hist2d_data:
-            bins = np.array(bins, dtype=np.float32)
+        for name, title, data, shape, range_x, range_y in hist2d_data:
+            bins = np.frombuffer(data, dtype="<f8").reshape(shape)
             # Flatten the 2D array with added underflow/overflow bins
             bins_with_overflow = np.zeros((bins.shape[0] + 2, bins.shape[1] + 2), dtype=np.float32)
             bins_with_overflow[1:-1, 1:-1] = bins
@@ -996,6 +2123,7 @@ def write_histograms(output_file, hist1d_data, hist2d_data):
                         hist.underflow,
                         hist.overflow,
                         hist.range,
+                        hist.export_edges(),
                     ));
                 }
             }
@@ -1008,14 +2136,17 @@ def write_histograms(output_file, hist1d_data, hist2d_data):
                     // Use backup bins if available
                     let bins = hist.backup_bins.as_ref().unwrap_or(&hist.bins);
 
-                    let mut counts_2d = vec![vec![0; bins.x]; bins.y];
-
-                    // Populate the counts, setting empty bins to 0
-                    for ((x_idx, y_idx), &count) in &bins.counts {
-                        if *x_idx < bins.x && *y_idx < bins.y {
-                            counts_2d[*y_idx][*x_idx] = count;
+                    // Flat row-major f64 buffer, read with numpy.frombuffer on the python side
+                    let mut counts_2d = vec![0.0f64; bins.x * bins.y];
+                    for ((x_idx, y_idx), count) in bins.counts.iter() {
+                        if x_idx < bins.x && y_idx < bins.y {
+                            counts_2d[y_idx * bins.x + x_idx] = count as f64;
                         }
                     }
+                    let bytes: Vec<u8> = counts_2d
+                        .iter()
+                        .flat_map(|count| count.to_le_bytes())
+                        .collect();
 
                     // Extract the range for x and y axes
                     let range_x = (hist.range.x.min, hist.range.x.max);
@@ -1027,11 +2158,12 @@ def write_histograms(output_file, hist1d_data, hist2d_data):
 
                     // Add to the data vector
                     hist2d_data.push((
-                        hist.name.clone(), // Full histogram name
-                        title,             // Human-readable title
-                        counts_2d,         // 2D bin counts
-                        range_x,           // Range for x-axis
-                        range_y,           // Range for y-axis
+                        hist.name.clone(),              // Full histogram name
+                        title,                          // Human-readable title
+                        PyBytes::new_bound(py, &bytes), // 2D bin counts
+                        (bins.y, bins.x),               // shape of the counts
+                        range_x,                        // Range for x-axis
+                        range_y,                        // Range for y-axis
                     ));
                 }
             }
@@ -1049,11 +2181,18 @@ def write_histograms(output_file, hist1d_data, hist2d_data):
     }
 }
 
+// Requested from the context menu of a pane in the tree
+enum PaneAction {
+    Rename(String, String), // old name, new name
+    Duplicate(String),
+}
+
 fn tree_ui(
     ui: &mut egui::Ui,
-    behavior: &mut dyn egui_tiles::Behavior<Pane>,
+    behavior: &mut TreeBehavior,
     tiles: &mut egui_tiles::Tiles<Pane>,
     tile_id: egui_tiles::TileId,
+    action: &mut Option<PaneAction>,
 ) {
     // Get the name BEFORE we remove the tile below!
     let text = format!(
@@ -1073,10 +2212,20 @@ fn tree_ui(
         false,
     )
     .show_header(ui, |ui| {
-        ui.label(text);
+        if let egui_tiles::Tile::Pane(pane) = &tile {
+            pane.thumbnail(ui, egui::vec2(48.0, 24.0));
+        }
+        let label = ui.label(text);
+        if let egui_tiles::Tile::Pane(pane) = &tile {
+            label.context_menu(|ui| pane_menu(ui, &pane.name(), action));
+        }
         let mut visible = tiles.is_visible(tile_id);
         ui.checkbox(&mut visible, "Visible");
         tiles.set_visible(tile_id, visible);
+
+        if let egui_tiles::Tile::Container(egui_tiles::Container::Grid(_)) = &tile {
+            behavior.grid_links_ui(ui, tile_id);
+        }
     })
     .body(|ui| match &mut tile {
         egui_tiles::Tile::Pane(_) => {}
@@ -1095,7 +2244,7 @@ fn tree_ui(
             // }
 
             for &child in container.children() {
-                tree_ui(ui, behavior, tiles, child);
+                tree_ui(ui, behavior, tiles, child, action);
             }
         }
     });
@@ -1104,9 +2253,719 @@ fn tree_ui(
     tiles.insert(tile_id, tile);
 }
 
+fn pane_menu(ui: &mut egui::Ui, name: &str, action: &mut Option<PaneAction>) {
+    let id = egui::Id::new((name, "rename"));
+    let mut new_name = ui.data_mut(|data| {
+        data.get_temp_mut_or_insert_with(id, || name.to_string())
+            .clone()
+    });
+
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut new_name)
+            .on_hover_text("Use '/' to move the histogram to other tabs");
+        if ui
+            .add_enabled(new_name != name, egui::Button::new("Rename"))
+            .clicked()
+        {
+            *action = Some(PaneAction::Rename(name.to_string(), new_name.clone()));
+            ui.close_menu();
+        }
+    });
+    ui.data_mut(|data| data.insert_temp(id, new_name));
+
+    if ui
+        .button("Duplicate")
+        .on_hover_text("Copy the histogram with its contents, fits, and cuts")
+        .clicked()
+    {
+        *action = Some(PaneAction::Duplicate(name.to_string()));
+        ui.close_menu();
+    }
+}
+
 fn estimate_gb(rows: u64, columns: u64) -> f64 {
     // Each f64 takes 8 bytes
     let total_bytes = rows * columns * 8;
     // Convert bytes to gigabytes
     total_bytes as f64 / 1024.0 / 1024.0 / 1024.0
 }
+
+fn count_rows(lf: &LazyFrame) -> PolarsResult<u32> {
+    let df = lf.clone().select([len().alias("count")]).collect()?;
+    Ok(df.column("count")?.u32()?.get(0).unwrap_or(0))
+}
+
+// Largest slice of a chunk collected at once, small enough to be read in about a second so an
+// abort is noticed quickly between the slices
+const COLLECT_ROWS: usize = 1_000_000;
+
+type Shared<T> = Arc<Mutex<Box<T>>>;
+
+// How each chunk of rows is split over the threads
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum FillMode {
+    #[default]
+    PerHistogram, // each thread fills whole histograms, best with many histograms
+    RowSlices, // each thread fills every histogram from a slice of the rows, best with few
+}
+
+impl FillMode {
+    pub fn menu_button(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Fill Mode", |ui| {
+            ui.radio_value(self, FillMode::PerHistogram, "Per histogram")
+                .on_hover_text("Histograms are filled in parallel, one thread per histogram");
+            ui.radio_value(self, FillMode::RowSlices, "Row slices")
+                .on_hover_text("Each chunk is split into row slices that are filled in parallel.\nFaster when a few histograms or many cuts on the same histograms are filled.");
+        });
+    }
+}
+
+// Counts of one row slice for every histogram of a fill group, in the order of its maps
+#[derive(Clone)]
+struct SliceBuffers {
+    hist1d: Vec<Buffer1D>,
+    hist2d: Vec<Buffer2D>,
+    hist3d: Vec<Buffer3D>,
+    histnd: Vec<BufferND>,
+}
+
+impl SliceBuffers {
+    fn merge(&mut self, other: SliceBuffers) {
+        for (buffer, other) in self.hist1d.iter_mut().zip(other.hist1d) {
+            buffer.merge(other);
+        }
+        for (buffer, other) in self.hist2d.iter_mut().zip(other.hist2d) {
+            buffer.merge(other);
+        }
+        for (buffer, other) in self.hist3d.iter_mut().zip(other.hist3d) {
+            buffer.merge(other);
+        }
+        for (buffer, other) in self.histnd.iter_mut().zip(other.histnd) {
+            buffer.merge(other);
+        }
+    }
+}
+
+// The rows that pass the cuts and have a value (not -1e6) in every column of the histogram
+
+fn rows_1d(
+    df: &DataFrame,
+    meta: &Hist1DConfig,
+    mask: Option<&Vec<bool>>,
+    mut fill: impl FnMut(f64),
+) {
+    if let Ok(column) = df.column(&meta.column_name).and_then(|c| c.f64()) {
+        column
+            .into_no_null_iter()
+            .enumerate()
+            .for_each(|(index, value)| {
+                let passed = mask.map_or(true, |mask| mask[index]);
+                if value != -1e6 && passed {
+                    fill(value);
+                }
+            });
+    }
+}
+
+fn rows_2d(
+    df: &DataFrame,
+    meta: &Hist2DConfig,
+    mask: Option<&Vec<bool>>,
+    mut fill: impl FnMut(f64, f64),
+) {
+    if let (Ok(x_col), Ok(y_col)) = (
+        df.column(&meta.x_column_name).and_then(|c| c.f64()),
+        df.column(&meta.y_column_name).and_then(|c| c.f64()),
+    ) {
+        x_col
+            .into_no_null_iter()
+            .zip(y_col.into_no_null_iter())
+            .enumerate()
+            .for_each(|(index, (x, y))| {
+                let passed = mask.map_or(true, |mask| mask[index]);
+                if x != -1e6 && y != -1e6 && passed {
+                    fill(x, y);
+                }
+            });
+    }
+}
+
+fn rows_3d(
+    df: &DataFrame,
+    meta: &Hist3DConfig,
+    mask: Option<&Vec<bool>>,
+    mut fill: impl FnMut(f64, f64, f64),
+) {
+    if let (Ok(x_col), Ok(y_col), Ok(z_col)) = (
+        df.column(&meta.x_column_name).and_then(|c| c.f64()),
+        df.column(&meta.y_column_name).and_then(|c| c.f64()),
+        df.column(&meta.z_column_name).and_then(|c| c.f64()),
+    ) {
+        x_col
+            .into_no_null_iter()
+            .zip(y_col.into_no_null_iter())
+            .zip(z_col.into_no_null_iter())
+            .enumerate()
+            .for_each(|(index, ((x, y), z))| {
+                let passed = mask.map_or(true, |mask| mask[index]);
+                if x != -1e6 && y != -1e6 && z != -1e6 && passed {
+                    fill(x, y, z);
+                }
+            });
+    }
+}
+
+fn rows_nd(
+    df: &DataFrame,
+    meta: &HistNDConfig,
+    mask: Option<&Vec<bool>>,
+    mut fill: impl FnMut(&[f64]),
+) {
+    let columns: Result<Vec<_>, _> = meta
+        .column_names
+        .iter()
+        .map(|name| df.column(name).and_then(|c| c.f64()))
+        .collect();
+
+    if let Ok(columns) = columns {
+        let mut values = vec![0.0; columns.len()];
+        for index in 0..df.height() {
+            let passed = mask.map_or(true, |mask| mask[index]);
+            if !passed {
+                continue;
+            }
+
+            let mut valid = true;
+            for (value, column) in values.iter_mut().zip(&columns) {
+                match column.get(index) {
+                    Some(v) if v != -1e6 => *value = v,
+                    _ => valid = false,
+                }
+            }
+            if valid {
+                fill(&values);
+            }
+        }
+    }
+}
+
+// Histograms filled together in one pass over the data
+struct FillGroup {
+    columns: Vec<String>,
+    hist1d_map: Vec<(Shared<Histogram>, Hist1DConfig)>,
+    hist2d_map: Vec<(Shared<Histogram2D>, Hist2DConfig)>,
+    hist3d_map: Vec<(Shared<Histogram3D>, Hist3DConfig)>,
+    histnd_map: Vec<(Shared<HistogramND>, HistNDConfig)>,
+    hist1d_aliases: Vec<(Shared<Histogram>, Shared<Histogram>)>,
+    hist2d_aliases: Vec<(Shared<Histogram2D>, Shared<Histogram2D>)>,
+    hist3d_aliases: Vec<(Shared<Histogram3D>, Shared<Histogram3D>)>,
+    histnd_aliases: Vec<(Shared<HistogramND>, Shared<HistogramND>)>,
+    cut_sets: Vec<Cuts>,               // distinct cuts of the histograms
+    cut_index: HashMap<String, usize>, // histogram name -> its cuts in cut_sets
+    cut_stats: Arc<Mutex<CutStats>>,
+    count_cuts: Vec<bool>, // whether the passed events of each cut set are counted
+}
+
+impl FillGroup {
+    fn new(h: &Histogrammer, configs: &Configs) -> Self {
+        // Initialize histogram maps
+        let hist1d_map: Vec<_> = configs
+            .configs
+            .iter()
+            .filter_map(|config| {
+                if let Config::Hist1D(hist1d) = config {
+                    h.tree.tiles.iter().find_map(|(_id, tile)| match tile {
+                        egui_tiles::Tile::Pane(Pane::Histogram(hist))
+                            if hist.lock().unwrap().name == hist1d.name =>
+                        {
+                            Some((Arc::clone(hist), hist1d.clone()))
+                        }
+                        _ => None,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let hist2d_map: Vec<_> = configs
+            .configs
+            .iter()
+            .filter_map(|config| {
+                if let Config::Hist2D(hist2d) = config {
+                    h.tree.tiles.iter().find_map(|(_id, tile)| match tile {
+                        egui_tiles::Tile::Pane(Pane::Histogram2D(hist))
+                            if hist.lock().unwrap().name == hist2d.name =>
+                        {
+                            Some((Arc::clone(hist), hist2d.clone()))
+                        }
+                        _ => None,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let hist3d_map: Vec<_> = configs
+            .configs
+            .iter()
+            .filter_map(|config| {
+                if let Config::Hist3D(hist3d) = config {
+                    h.find_hist3d(&hist3d.name)
+                        .map(|hist| (hist, hist3d.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let histnd_map: Vec<_> = configs
+            .configs
+            .iter()
+            .filter_map(|config| {
+                if let Config::HistND(histnd) = config {
+                    h.find_histnd(&histnd.name)
+                        .map(|hist| (hist, histnd.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        // Duplicate histograms are copied from the histogram they alias after each chunk
+        let hist1d_aliases: Vec<_> = configs
+            .aliases
+            .iter()
+            .filter_map(
+                |(alias, source)| match (h.find_hist1d(source), h.find_hist1d(alias)) {
+                    (Some(source), Some(alias)) => Some((source, alias)),
+                    _ => None,
+                },
+            )
+            .collect();
+
+        let hist2d_aliases: Vec<_> = configs
+            .aliases
+            .iter()
+            .filter_map(
+                |(alias, source)| match (h.find_hist2d(source), h.find_hist2d(alias)) {
+                    (Some(source), Some(alias)) => Some((source, alias)),
+                    _ => None,
+                },
+            )
+            .collect();
+
+        let hist3d_aliases: Vec<_> = configs
+            .aliases
+            .iter()
+            .filter_map(
+                |(alias, source)| match (h.find_hist3d(source), h.find_hist3d(alias)) {
+                    (Some(source), Some(alias)) => Some((source, alias)),
+                    _ => None,
+                },
+            )
+            .collect();
+
+        let histnd_aliases: Vec<_> = configs
+            .aliases
+            .iter()
+            .filter_map(
+                |(alias, source)| match (h.find_histnd(source), h.find_histnd(alias)) {
+                    (Some(source), Some(alias)) => Some((source, alias)),
+                    _ => None,
+                },
+            )
+            .collect();
+
+        // histograms with the same cuts share one mask per chunk
+        let mut cut_sets: Vec<Cuts> = Vec::new();
+        let mut cut_keys: HashMap<String, usize> = HashMap::new();
+        let mut cut_index = HashMap::new();
+        for config in &configs.configs {
+            let cuts = config.cuts();
+            let key = serde_json::to_string(cuts).unwrap_or_default();
+            let index = *cut_keys.entry(key).or_insert_with(|| {
+                cut_sets.push(cuts.clone());
+                cut_sets.len() - 1
+            });
+            cut_index.insert(config.name().to_string(), index);
+        }
+
+        FillGroup {
+            columns: configs.get_used_columns(),
+            hist1d_map,
+            hist2d_map,
+            hist3d_map,
+            histnd_map,
+            hist1d_aliases,
+            hist2d_aliases,
+            hist3d_aliases,
+            histnd_aliases,
+            count_cuts: vec![true; cut_sets.len()],
+            cut_sets,
+            cut_index,
+            cut_stats: Arc::clone(&h.cut_stats),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.hist1d_map.len()
+            + self.hist2d_map.len()
+            + self.hist3d_map.len()
+            + self.histnd_map.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.hist1d_map
+            .iter()
+            .map(|(_, config)| config.name.clone())
+            .chain(
+                self.hist2d_map
+                    .iter()
+                    .map(|(_, config)| config.name.clone()),
+            )
+            .chain(
+                self.hist3d_map
+                    .iter()
+                    .map(|(_, config)| config.name.clone()),
+            )
+            .chain(
+                self.histnd_map
+                    .iter()
+                    .map(|(_, config)| config.name.clone()),
+            )
+            .collect()
+    }
+
+    fn set_filling(&self, filling: bool) {
+        let hists1d = self
+            .hist1d_map
+            .iter()
+            .map(|(hist, _)| hist)
+            .chain(self.hist1d_aliases.iter().map(|(_, alias)| alias));
+        for hist in hists1d {
+            hist.lock().unwrap().jobs.filling = filling;
+        }
+        let hists2d = self
+            .hist2d_map
+            .iter()
+            .map(|(hist, _)| hist)
+            .chain(self.hist2d_aliases.iter().map(|(_, alias)| alias));
+        for hist in hists2d {
+            hist.lock().unwrap().jobs.filling = filling;
+        }
+        let hists3d = self
+            .hist3d_map
+            .iter()
+            .map(|(hist, _)| hist)
+            .chain(self.hist3d_aliases.iter().map(|(_, alias)| alias));
+        for hist in hists3d {
+            hist.lock().unwrap().jobs.filling = filling;
+        }
+        let histsnd = self
+            .histnd_map
+            .iter()
+            .map(|(hist, _)| hist)
+            .chain(self.histnd_aliases.iter().map(|(_, alias)| alias));
+        for hist in histsnd {
+            hist.lock().unwrap().jobs.filling = filling;
+        }
+    }
+
+    fn fill(&self, df: &DataFrame, sample: Option<f64>, mode: FillMode) {
+        match mode {
+            FillMode::PerHistogram => self.fill_counts(df),
+            FillMode::RowSlices => {
+                // one slice per thread, each slice counts every histogram into its own buffers
+                // which are merged and added to the histograms once, so the threads never wait
+                // on the histogram locks
+                let empty = self.empty_buffers();
+                let slice_rows = df.height().div_ceil(rayon::current_num_threads()).max(1);
+                let starts: Vec<usize> = (0..df.height()).step_by(slice_rows).collect();
+                let buffers = starts
+                    .par_iter()
+                    .map(|&start| {
+                        let mut buffers = empty.clone();
+                        self.count_slice(&df.slice(start as i64, slice_rows), &mut buffers);
+                        buffers
+                    })
+                    .reduce_with(|mut buffers, other| {
+                        buffers.merge(other);
+                        buffers
+                    });
+                if let Some(buffers) = buffers {
+                    self.add_buffers(buffers);
+                }
+            }
+        }
+        self.finish(sample);
+    }
+
+    // Each distinct set of cuts is evaluated once per chunk into a mask, the passed events are
+    // added to the cut statistics
+    fn masks(&self, df: &DataFrame) -> Vec<PolarsResult<Option<Vec<bool>>>> {
+        let masks: Vec<PolarsResult<Option<Vec<bool>>>> =
+            self.cut_sets.par_iter().map(|cuts| cuts.mask(df)).collect();
+
+        let mut stats = self.cut_stats.lock().unwrap();
+        for ((cuts, mask), count) in self.cut_sets.iter().zip(&masks).zip(&self.count_cuts) {
+            if let (true, Ok(Some(mask))) = (count, mask) {
+                stats.add(
+                    cuts,
+                    mask.iter().filter(|&&passed| passed).count(),
+                    df.height(),
+                );
+            }
+        }
+        masks
+    }
+
+    fn mask_of<'a>(
+        &self,
+        masks: &'a [PolarsResult<Option<Vec<bool>>>],
+        name: &str,
+    ) -> Option<Option<&'a Vec<bool>>> {
+        match &masks[self.cut_index[name]] {
+            Ok(mask) => Some(mask.as_ref()),
+            Err(e) => {
+                log::error!("Failed to apply cuts to '{}': {}", name, e);
+                None
+            }
+        }
+    }
+
+    fn fill_counts(&self, df: &DataFrame) {
+        let masks = self.masks(df);
+
+        // Fill 1D histograms in parallel
+        let fill_1d = || {
+            self.hist1d_map.par_iter().for_each(|(hist, meta)| {
+                if let Some(mask) = self.mask_of(&masks, &meta.name) {
+                    let mut hist = hist.lock().unwrap();
+                    rows_1d(df, meta, mask, |value| hist.fill(value));
+                }
+            })
+        };
+
+        // Fill 2D histograms in parallel
+        let fill_2d = || {
+            self.hist2d_map.par_iter().for_each(|(hist, meta)| {
+                if let Some(mask) = self.mask_of(&masks, &meta.name) {
+                    let mut hist = hist.lock().unwrap();
+                    rows_2d(df, meta, mask, |x, y| hist.fill(x, y));
+                }
+            })
+        };
+
+        // Fill 3D histograms in parallel
+        let fill_3d = || {
+            self.hist3d_map.par_iter().for_each(|(hist, meta)| {
+                if let Some(mask) = self.mask_of(&masks, &meta.name) {
+                    let mut hist = hist.lock().unwrap();
+                    rows_3d(df, meta, mask, |x, y, z| hist.fill(x, y, z));
+                }
+            })
+        };
+
+        // Fill ND histograms in parallel
+        let fill_nd = || {
+            self.histnd_map.par_iter().for_each(|(hist, meta)| {
+                if let Some(mask) = self.mask_of(&masks, &meta.name) {
+                    let mut hist = hist.lock().unwrap();
+                    rows_nd(df, meta, mask, |values| hist.fill(values));
+                }
+            })
+        };
+
+        // all dimensions at the same time, so the threads are not idle between them
+        rayon::join(
+            || rayon::join(fill_1d, fill_2d),
+            || rayon::join(fill_3d, fill_nd),
+        );
+    }
+
+    // Buffers with the binning of each histogram and no counts
+    fn empty_buffers(&self) -> SliceBuffers {
+        SliceBuffers {
+            hist1d: self
+                .hist1d_map
+                .iter()
+                .map(|(hist, _)| Buffer1D::new(&hist.lock().unwrap()))
+                .collect(),
+            hist2d: self
+                .hist2d_map
+                .iter()
+                .map(|(hist, _)| Buffer2D::new(&hist.lock().unwrap()))
+                .collect(),
+            hist3d: self
+                .hist3d_map
+                .iter()
+                .map(|(hist, _)| Buffer3D::new(&hist.lock().unwrap()))
+                .collect(),
+            histnd: self
+                .histnd_map
+                .iter()
+                .map(|(hist, _)| BufferND::new(&hist.lock().unwrap()))
+                .collect(),
+        }
+    }
+
+    // Counts a slice of rows into the buffers, on the calling thread
+    fn count_slice(&self, df: &DataFrame, buffers: &mut SliceBuffers) {
+        let masks = self.masks(df);
+
+        for ((_, meta), buffer) in self.hist1d_map.iter().zip(&mut buffers.hist1d) {
+            if let Some(mask) = self.mask_of(&masks, &meta.name) {
+                rows_1d(df, meta, mask, |value| buffer.fill(value));
+            }
+        }
+        for ((_, meta), buffer) in self.hist2d_map.iter().zip(&mut buffers.hist2d) {
+            if let Some(mask) = self.mask_of(&masks, &meta.name) {
+                rows_2d(df, meta, mask, |x, y| buffer.fill(x, y));
+            }
+        }
+        for ((_, meta), buffer) in self.hist3d_map.iter().zip(&mut buffers.hist3d) {
+            if let Some(mask) = self.mask_of(&masks, &meta.name) {
+                rows_3d(df, meta, mask, |x, y, z| buffer.fill(x, y, z));
+            }
+        }
+        for ((_, meta), buffer) in self.histnd_map.iter().zip(&mut buffers.histnd) {
+            if let Some(mask) = self.mask_of(&masks, &meta.name) {
+                rows_nd(df, meta, mask, |values| buffer.fill(values));
+            }
+        }
+    }
+
+    // Adds the merged buffers of all slices, locking each histogram once
+    fn add_buffers(&self, buffers: SliceBuffers) {
+        let add_1d = || {
+            self.hist1d_map
+                .par_iter()
+                .zip(buffers.hist1d)
+                .for_each(|((hist, _), buffer)| hist.lock().unwrap().add_buffer(buffer))
+        };
+        let add_2d = || {
+            self.hist2d_map
+                .par_iter()
+                .zip(buffers.hist2d)
+                .for_each(|((hist, _), buffer)| hist.lock().unwrap().add_buffer(buffer))
+        };
+        let add_3d = || {
+            self.hist3d_map
+                .par_iter()
+                .zip(buffers.hist3d)
+                .for_each(|((hist, _), buffer)| hist.lock().unwrap().add_buffer(buffer))
+        };
+        let add_nd = || {
+            self.histnd_map
+                .par_iter()
+                .zip(buffers.histnd)
+                .for_each(|((hist, _), buffer)| hist.lock().unwrap().add_buffer(buffer))
+        };
+
+        rayon::join(
+            || rayon::join(add_1d, add_2d),
+            || rayon::join(add_3d, add_nd),
+        );
+    }
+
+    // Marks the histograms as filled
+    fn finish(&self, sample: Option<f64>) {
+        self.hist2d_map.par_iter().for_each(|(hist, meta)| {
+            let mut hist = hist.lock().unwrap();
+            hist.plot_settings.preview = sample;
+            hist.plot_settings.recalculate_image = true;
+            hist.plot_settings.egui_settings.reset_axis = true;
+            hist.plot_settings.x_column = meta.x_column_name.clone();
+            hist.plot_settings.y_column = meta.y_column_name.clone();
+        });
+
+        self.hist1d_map.par_iter().for_each(|(hist, _)| {
+            let mut hist = hist.lock().unwrap();
+            hist.plot_settings.preview = sample;
+            hist.plot_settings.egui_settings.reset_axis = true;
+        });
+
+        self.hist3d_map.par_iter().for_each(|(hist, meta)| {
+            let mut hist = hist.lock().unwrap();
+            hist.settings.preview = sample;
+            hist.settings.columns = [
+                meta.x_column_name.clone(),
+                meta.y_column_name.clone(),
+                meta.z_column_name.clone(),
+            ];
+            hist.update_view = true;
+        });
+
+        self.histnd_map.par_iter().for_each(|(hist, meta)| {
+            let mut hist = hist.lock().unwrap();
+            hist.settings.preview = sample;
+            hist.columns = meta.column_names.clone();
+            hist.update_view = true;
+        });
+    }
+
+    // Copies the counts to the duplicates, once after the last batch since it clones every bin
+    fn copy_aliases(&self, sample: Option<f64>) {
+        for (source, alias) in &self.hist1d_aliases {
+            let source = source.lock().unwrap();
+            let mut alias = alias.lock().unwrap();
+            alias.range = source.range; // the source range may have been extended
+            alias.original_bins = source.original_bins.clone();
+            alias.overflow = source.overflow;
+            alias.underflow = source.underflow;
+            alias.rebin();
+            alias.plot_settings.preview = sample;
+            alias.plot_settings.egui_settings.reset_axis = true;
+        }
+
+        for (source, alias) in &self.hist2d_aliases {
+            let source = source.lock().unwrap();
+            let mut alias = alias.lock().unwrap();
+            alias.bins = source.bins.clone();
+            alias.backup_bins = source.backup_bins.clone();
+            alias.overflow = source.overflow;
+            alias.underflow = source.underflow;
+            alias.plot_settings.preview = sample;
+            alias.plot_settings.recalculate_image = true;
+            alias.plot_settings.egui_settings.reset_axis = true;
+            if alias.swapped == source.swapped {
+                alias.plot_settings.x_column = source.plot_settings.x_column.clone();
+                alias.plot_settings.y_column = source.plot_settings.y_column.clone();
+            } else {
+                alias.transpose_counts();
+                alias.plot_settings.x_column = source.plot_settings.y_column.clone();
+                alias.plot_settings.y_column = source.plot_settings.x_column.clone();
+            }
+        }
+
+        for (source, alias) in &self.hist3d_aliases {
+            let source = source.lock().unwrap();
+            let mut alias = alias.lock().unwrap();
+            alias.counts = source.counts.clone();
+            alias.entries = source.entries;
+            alias.overflow = source.overflow;
+            alias.underflow = source.underflow;
+            alias.settings.preview = sample;
+            alias.settings.columns = source.settings.columns.clone();
+            alias.update_view = true;
+        }
+
+        for (source, alias) in &self.histnd_aliases {
+            let source = source.lock().unwrap();
+            let mut alias = alias.lock().unwrap();
+            alias.counts = source.counts.clone();
+            alias.entries = source.entries;
+            alias.overflow = source.overflow;
+            alias.underflow = source.underflow;
+            alias.settings.preview = sample;
+            alias.columns = source.columns.clone();
+            alias.update_view = true;
+        }
+    }
+}
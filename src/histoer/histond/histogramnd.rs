@@ -0,0 +1,318 @@
+use fnv::FnvHashMap;
+
+use crate::histoer::histo1d::histogram1d::Histogram;
+use crate::histoer::histo2d::histogram2d::Histogram2D;
+use crate::histoer::jobs::JobQueue;
+
+// The ND histogram is filled once, any 1D or 2D combination of its axes is then
+// projected from the stored bins instead of making another pass over the data.
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub enum ProjectionView {
+    Hist1D(Box<Histogram>),
+    Hist2D(Box<Histogram2D>),
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct ProjectionSettings {
+    pub x: usize,                       // axis shown on x
+    pub y: Option<usize>,               // axis shown on y, None for a 1D projection
+    pub gates: Vec<Option<(f64, f64)>>, // only sum bins inside these ranges on the other axes
+    pub preview: Option<f64>,           // sample fraction when filled by a preview
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct HistogramND {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub bins: Vec<usize>,
+    pub ranges: Vec<(f64, f64)>,
+    pub counts: FnvHashMap<Vec<usize>, u64>, // only filled bins are stored
+    pub entries: u64,
+    pub overflow: u64,
+    pub underflow: u64,
+    pub settings: ProjectionSettings,
+    pub view: ProjectionView,
+    #[serde(skip)]
+    pub update_view: bool,
+    #[serde(skip)]
+    pub jobs: JobQueue, // only its filling state is shown, fits and rebins run on the view
+}
+
+impl HistogramND {
+    pub fn new(name: &str, bins: &[usize], ranges: &[(f64, f64)]) -> Self {
+        let settings = ProjectionSettings {
+            x: 0,
+            y: (bins.len() > 1).then_some(1),
+            gates: vec![None; bins.len()],
+            preview: None,
+        };
+
+        let mut hist = HistogramND {
+            name: name.to_string(),
+            columns: vec![String::new(); bins.len()],
+            bins: bins.to_vec(),
+            ranges: ranges.to_vec(),
+            counts: FnvHashMap::default(),
+            entries: 0,
+            overflow: 0,
+            underflow: 0,
+            settings,
+            view: ProjectionView::Hist1D(Box::new(Histogram::new(name, 1, (0.0, 1.0)))),
+            update_view: false,
+            jobs: JobQueue::default(),
+        };
+        hist.project();
+        hist
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.bins.len()
+    }
+
+    pub fn reset(&mut self) {
+        self.counts.clear();
+        self.entries = 0;
+        self.overflow = 0;
+        self.underflow = 0;
+        self.update_view = true;
+    }
+
+    fn bin_width(&self, axis: usize) -> f64 {
+        (self.ranges[axis].1 - self.ranges[axis].0) / self.bins[axis] as f64
+    }
+
+    fn bin_index(&self, axis: usize, value: f64) -> usize {
+        ((value - self.ranges[axis].0) / self.bin_width(axis)) as usize
+    }
+
+    pub fn fill(&mut self, values: &[f64]) {
+        if values
+            .iter()
+            .zip(&self.ranges)
+            .any(|(value, range)| *value < range.0)
+        {
+            self.underflow += 1;
+        } else if values
+            .iter()
+            .zip(&self.ranges)
+            .any(|(value, range)| *value >= range.1)
+        {
+            self.overflow += 1;
+        } else {
+            let index: Vec<usize> = values
+                .iter()
+                .enumerate()
+                .map(|(axis, value)| self.bin_index(axis, *value))
+                .collect();
+            *self.counts.entry(index).or_insert(0) += 1;
+            self.entries += 1;
+        }
+    }
+
+    fn axis_label(&self, axis: usize) -> String {
+        if self.columns[axis].is_empty() {
+            format!("Axis {}", axis)
+        } else {
+            self.columns[axis].clone()
+        }
+    }
+
+    // Bin index ranges of the gates, the projected axes are never gated
+    fn gate_bins(&self) -> Vec<(usize, usize)> {
+        (0..self.dimensions())
+            .map(|axis| {
+                let projected = axis == self.settings.x || Some(axis) == self.settings.y;
+                match self.settings.gates[axis] {
+                    Some((low, high)) if !projected => {
+                        let last = self.bins[axis].saturating_sub(1);
+                        let low = low.max(self.ranges[axis].0);
+                        let high = high.min(self.ranges[axis].1);
+                        (
+                            self.bin_index(axis, low).min(last),
+                            self.bin_index(axis, high).min(last),
+                        )
+                    }
+                    _ => (0, usize::MAX),
+                }
+            })
+            .collect()
+    }
+
+    // Project the stored bins onto the selected axes
+    pub fn project(&mut self) {
+        let gates = self.gate_bins();
+        let passes = |index: &[usize]| {
+            index
+                .iter()
+                .zip(&gates)
+                .all(|(bin, (low, high))| bin >= low && bin <= high)
+        };
+
+        let x = self.settings.x;
+        match self.settings.y {
+            None => {
+                let name = format!("{} [{}]", self.name, self.axis_label(x));
+                let mut hist = Histogram::new(&name, self.bins[x], self.ranges[x]);
+                for (index, &count) in &self.counts {
+                    if passes(index) {
+                        hist.bins[index[x]] += count;
+                    }
+                }
+                hist.original_bins = hist.bins.clone();
+
+                // keep the fits, markers, and zoom when only the gates changed
+                match &self.view {
+                    ProjectionView::Hist1D(old)
+                        if old.name == hist.name && old.bins.len() == hist.bins.len() =>
+                    {
+                        hist.plot_settings = old.plot_settings.clone();
+                        hist.fits = old.fits.clone();
+                        hist.line = old.line.clone();
+                    }
+                    _ => hist.plot_settings.egui_settings.reset_axis = true,
+                }
+                hist.rebin(); // keep the rebin factor of the previous view
+                hist.plot_settings.preview = self.settings.preview;
+
+                self.view = ProjectionView::Hist1D(Box::new(hist));
+            }
+            Some(y) => {
+                let name = format!(
+                    "{} [{} vs {}]",
+                    self.name,
+                    self.axis_label(y),
+                    self.axis_label(x)
+                );
+                let mut hist = Histogram2D::new(
+                    &name,
+                    (self.bins[x], self.bins[y]),
+                    (self.ranges[x], self.ranges[y]),
+                );
+
+                let mut counts: FnvHashMap<(usize, usize), u64> = FnvHashMap::default();
+                for (index, &count) in &self.counts {
+                    if passes(index) {
+                        *counts.entry((index[x], index[y])).or_insert(0) += count;
+                    }
+                }
+
+                match &self.view {
+                    ProjectionView::Hist2D(old) if old.name == hist.name => {
+                        hist.plot_settings = old.plot_settings.clone();
+                    }
+                    _ => hist.plot_settings.egui_settings.reset_axis = true,
+                }
+                hist.bins.min_count = counts.values().copied().min().unwrap_or(u64::MAX);
+                hist.bins.max_count = counts.values().copied().max().unwrap_or(u64::MIN);
+                hist.bins.counts = counts.into();
+                hist.bins.update_storage(hist.plot_settings.storage);
+                hist.plot_settings.x_column = self.columns[x].clone();
+                hist.plot_settings.y_column = self.columns[y].clone();
+                hist.plot_settings.preview = self.settings.preview;
+                hist.plot_settings.recalculate_image = true;
+
+                self.view = ProjectionView::Hist2D(Box::new(hist));
+            }
+        }
+
+        self.update_view = false;
+    }
+
+    fn projection_ui(&mut self, ui: &mut egui::Ui) {
+        let before = (self.settings.x, self.settings.y);
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt(format!("{}_nd_x", self.name))
+                .selected_text(format!("X: {}", self.axis_label(self.settings.x)))
+                .show_ui(ui, |ui| {
+                    for axis in 0..self.dimensions() {
+                        let label = self.axis_label(axis);
+                        ui.selectable_value(&mut self.settings.x, axis, label);
+                    }
+                });
+
+            let y_text = match self.settings.y {
+                Some(axis) => format!("Y: {}", self.axis_label(axis)),
+                None => "Y: None (1D)".to_string(),
+            };
+            egui::ComboBox::from_id_salt(format!("{}_nd_y", self.name))
+                .selected_text(y_text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.settings.y, None, "None (1D)");
+                    for axis in 0..self.dimensions() {
+                        if axis != self.settings.x {
+                            let label = self.axis_label(axis);
+                            ui.selectable_value(&mut self.settings.y, Some(axis), label);
+                        }
+                    }
+                });
+
+            ui.menu_button("Gates", |ui| {
+                for axis in 0..self.dimensions() {
+                    if axis == self.settings.x || Some(axis) == self.settings.y {
+                        continue;
+                    }
+
+                    ui.horizontal(|ui| {
+                        let mut gated = self.settings.gates[axis].is_some();
+                        if ui.checkbox(&mut gated, self.axis_label(axis)).changed() {
+                            self.settings.gates[axis] = gated.then_some(self.ranges[axis]);
+                            self.update_view = true;
+                        }
+
+                        if let Some((low, high)) = &mut self.settings.gates[axis] {
+                            let speed = (self.ranges[axis].1 - self.ranges[axis].0)
+                                / self.bins[axis] as f64;
+                            let low_response = ui.add(
+                                egui::DragValue::new(low)
+                                    .speed(speed)
+                                    .prefix("(")
+                                    .suffix(","),
+                            );
+                            let high_response = ui.add(
+                                egui::DragValue::new(high)
+                                    .speed(speed)
+                                    .prefix(" ")
+                                    .suffix(")"),
+                            );
+                            if low_response.changed() || high_response.changed() {
+                                self.update_view = true;
+                            }
+                        }
+                    });
+                }
+            });
+
+            ui.separator();
+
+            ui.label(format!(
+                "Entries: {}  Underflow: {}  Overflow: {}",
+                self.entries, self.underflow, self.overflow
+            ));
+        });
+
+        if self.settings.y == Some(self.settings.x) {
+            self.settings.y = None;
+        }
+
+        if (self.settings.x, self.settings.y) != before {
+            self.update_view = true;
+        }
+    }
+
+    // Render the axis selection above the current projection
+    pub fn render(&mut self, ui: &mut egui::Ui) {
+        self.jobs.status_ui(ui);
+        self.projection_ui(ui);
+
+        if self.update_view {
+            self.project();
+        }
+
+        match &mut self.view {
+            ProjectionView::Hist1D(hist) => hist.render(ui),
+            ProjectionView::Hist2D(hist) => hist.render(ui),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+
+// Operations on a histogram are serialized: while a fill is writing to a histogram,
+// fits and rebins requested from the UI wait in its queue and run in order once the
+// fill is done. Every histogram has its own queue, so the others are not held up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Job {
+    FitBackground,
+    FitGaussians,
+    RefitStored,
+    FindPeaks,
+    Rebin(usize),
+    Rebin2D(usize, usize),
+}
+
+impl Job {
+    fn label(&self) -> String {
+        match self {
+            Job::FitBackground => "Fit background".to_string(),
+            Job::FitGaussians => "Fit Gaussians".to_string(),
+            Job::RefitStored => "Refit stored fits".to_string(),
+            Job::FindPeaks => "Find peaks".to_string(),
+            Job::Rebin(factor) => format!("Rebin x{}", factor),
+            Job::Rebin2D(x, y) => format!("Rebin x{} y{}", x, y),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct JobQueue {
+    pub filling: bool, // set by the fill thread while it owns the counts
+    pub queued: VecDeque<Job>,
+}
+
+impl JobQueue {
+    // Returns the job when it can run right away, otherwise it is queued
+    pub fn submit(&mut self, job: Job) -> Option<Job> {
+        if self.filling {
+            // a newer rebin replaces a queued one
+            if let Some(queued) = self.queued.iter_mut().find(|queued| {
+                matches!(
+                    (**queued, job),
+                    (Job::Rebin(_), Job::Rebin(_)) | (Job::Rebin2D(..), Job::Rebin2D(..))
+                )
+            }) {
+                *queued = job;
+            } else if !self.queued.contains(&job) {
+                self.queued.push_back(job);
+            }
+            None
+        } else {
+            Some(job)
+        }
+    }
+
+    // Jobs that can run now that the fill is done
+    pub fn take_ready(&mut self) -> Vec<Job> {
+        if self.filling {
+            Vec::new()
+        } else {
+            self.queued.drain(..).collect()
+        }
+    }
+
+    pub fn status_ui(&mut self, ui: &mut egui::Ui) {
+        if !self.filling && self.queued.is_empty() {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            if self.filling {
+                ui.spinner();
+                ui.label("Filling…");
+            }
+
+            if !self.queued.is_empty() {
+                let jobs: Vec<String> = self.queued.iter().map(|job| job.label()).collect();
+                ui.label(format!("{} queued", self.queued.len()))
+                    .on_hover_text(jobs.join("\n"));
+                if ui.small_button("Cancel").clicked() {
+                    self.queued.clear();
+                }
+            }
+        });
+
+        ui.ctx().request_repaint();
+    }
+}
@@ -0,0 +1,311 @@
+use std::sync::{Arc, Mutex};
+
+use super::histo1d::annotations::Annotation;
+use super::histo1d::histogram1d::Histogram;
+use crate::fitter::main_fitter::FitResult;
+use crate::util::kinematics::{FocalPlane, Kinematics, Reaction};
+
+// Window that predicts where the states of the residual land on the spectrograph focal plane
+// for a two-body reaction. The focal plane is taken as linear in the bending radius,
+// x = D (rho - rho0), with D and rho0 fit from peaks of known excitation energy. Predictions are
+// drawn on a focal plane histogram and assigned to its stored fits as their energies.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct KinematicsCalculator {
+    pub open: bool,
+    pub reaction: Reaction,
+    pub focal_plane: FocalPlane,
+    pub states: Vec<State>,
+    pub histogram: String,
+    pub tolerance: f64, // mm between a peak and a state for it to be assigned
+}
+
+impl Default for KinematicsCalculator {
+    fn default() -> Self {
+        KinematicsCalculator {
+            open: false,
+            reaction: Reaction::default(),
+            focal_plane: FocalPlane::default(),
+            states: vec![State {
+                excitation: 0.0,
+                label: "g.s.".to_string(),
+            }],
+            histogram: "No Cuts/SE-SPS/Focal Plane/Xavg".to_string(),
+            tolerance: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct State {
+    pub excitation: f64, // keV
+    pub label: String,
+}
+
+// One state of the residual on the focal plane
+pub struct Prediction {
+    pub energy: f64, // ejectile kinetic energy in MeV
+    pub rho: f64,    // cm
+    pub x: f64,      // mm
+}
+
+impl KinematicsCalculator {
+    // Focal plane prediction of a state, excitation in keV
+    pub fn predict(&self, kinematics: &Kinematics, excitation: f64) -> Option<Prediction> {
+        let momentum = kinematics.ejectile_momentum(excitation / 1000.0)?;
+        let rho = kinematics.rho(momentum);
+        Some(Prediction {
+            energy: kinematics.ejectile_energy(momentum),
+            rho,
+            x: self.focal_plane.x(kinematics, rho)?,
+        })
+    }
+
+    fn find<'a>(
+        &self,
+        hists: &'a [Arc<Mutex<Box<Histogram>>>],
+    ) -> Option<&'a Arc<Mutex<Box<Histogram>>>> {
+        let hist = hists
+            .iter()
+            .find(|hist| hist.lock().unwrap().name == self.histogram);
+        if hist.is_none() {
+            log::error!("No 1D histogram named '{}'", self.histogram);
+        }
+        hist
+    }
+
+    fn annotate(&self, kinematics: &Kinematics, hists: &[Arc<Mutex<Box<Histogram>>>]) {
+        let Some(hist) = self.find(hists) else {
+            return;
+        };
+
+        hist.lock().unwrap().plot_settings.annotations = self
+            .states
+            .iter()
+            .filter_map(|state| {
+                let prediction = self.predict(kinematics, state.excitation)?;
+                Some(Annotation {
+                    x: prediction.x,
+                    label: format!("{} {:.0}", state.label, state.excitation),
+                })
+            })
+            .collect();
+    }
+
+    // Gives the stored peaks of the histogram the excitation energy of the nearest state
+    fn assign(&self, kinematics: &Kinematics, hists: &[Arc<Mutex<Box<Histogram>>>]) {
+        let Some(hist) = self.find(hists) else {
+            return;
+        };
+
+        let positions: Vec<(f64, f64)> = self
+            .states
+            .iter()
+            .filter_map(|state| {
+                let prediction = self.predict(kinematics, state.excitation)?;
+                Some((prediction.x, state.excitation))
+            })
+            .collect();
+
+        let mut hist = hist.lock().unwrap();
+        let mut assigned = 0;
+        for fit in &mut hist.fits.stored_fits {
+            let Some(FitResult::Gaussian(gaussian)) = &mut fit.fit_result else {
+                continue;
+            };
+            for peak in &mut gaussian.fit_result {
+                let Some(mean) = peak.mean.value else {
+                    continue;
+                };
+                let nearest = positions
+                    .iter()
+                    .filter(|(x, _)| (x - mean).abs() <= self.tolerance)
+                    .min_by(|a, b| (a.0 - mean).abs().total_cmp(&(b.0 - mean).abs()));
+                if let Some((_, excitation)) = nearest {
+                    peak.energy.value = Some(*excitation);
+                    peak.energy.uncertainty = Some(0.0);
+                    assigned += 1;
+                }
+            }
+        }
+        log::info!("Assigned excitation energies to {} peaks", assigned);
+    }
+
+    // Least squares x = D rho + b over the stored peaks with an excitation energy
+    fn fit_focal_plane(
+        &mut self,
+        kinematics: &Kinematics,
+        hists: &[Arc<Mutex<Box<Histogram>>>],
+    ) -> Result<(), String> {
+        let hist = self
+            .find(hists)
+            .ok_or_else(|| format!("No 1D histogram named '{}'", self.histogram))?;
+
+        let points: Vec<(f64, f64)> = hist
+            .lock()
+            .unwrap()
+            .calibration_points()
+            .iter()
+            .filter_map(|&[x, _, excitation, _]| {
+                let momentum = kinematics.ejectile_momentum(excitation / 1000.0)?;
+                Some((kinematics.rho(momentum), x))
+            })
+            .collect();
+        if points.len() < 2 {
+            return Err("Need at least 2 peaks with an excitation energy".to_string());
+        }
+
+        let n = points.len() as f64;
+        let mean_rho = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_x = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let sxx: f64 = points.iter().map(|p| (p.0 - mean_rho).powi(2)).sum();
+        let sxy: f64 = points
+            .iter()
+            .map(|p| (p.0 - mean_rho) * (p.1 - mean_x))
+            .sum();
+        if sxx == 0.0 {
+            return Err("The peaks all have the same excitation energy".to_string());
+        }
+
+        let slope = sxy / sxx;
+        self.focal_plane.dispersion = slope;
+        self.focal_plane.rho_center = Some(mean_rho - mean_x / slope);
+        Ok(())
+    }
+
+    fn states_ui(&mut self, ui: &mut egui::Ui, kinematics: Option<&Kinematics>) {
+        let predictions: Vec<Option<Prediction>> = match kinematics {
+            Some(kinematics) => self
+                .states
+                .iter()
+                .map(|state| self.predict(kinematics, state.excitation))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let mut remove = None;
+        egui::Grid::new("kinematics_states")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("State");
+                ui.label("Ex");
+                ui.label("Ejectile");
+                ui.label("ρ");
+                ui.label("x");
+                ui.end_row();
+
+                for (index, state) in self.states.iter_mut().enumerate() {
+                    ui.add(egui::TextEdit::singleline(&mut state.label).desired_width(60.0));
+                    ui.add(
+                        egui::DragValue::new(&mut state.excitation)
+                            .speed(1.0)
+                            .range(0.0..=f64::INFINITY)
+                            .suffix(" keV"),
+                    );
+
+                    match predictions.get(index).and_then(Option::as_ref) {
+                        Some(prediction) => {
+                            ui.label(format!("{:.3} MeV", prediction.energy));
+                            ui.label(format!("{:.3} cm", prediction.rho));
+                            ui.label(format!("{:.1} mm", prediction.x));
+                        }
+                        None => {
+                            ui.label("-");
+                            ui.label("-");
+                            ui.label("-");
+                        }
+                    }
+
+                    if ui.button("🗙").clicked() {
+                        remove = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+
+        if let Some(index) = remove {
+            self.states.remove(index);
+        }
+
+        if ui.button("Add State").clicked() {
+            let excitation = self.states.last().map_or(0.0, |s| s.excitation + 1000.0);
+            self.states.push(State {
+                excitation,
+                label: String::new(),
+            });
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, hists: &[Arc<Mutex<Box<Histogram>>>]) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Kinematics")
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                self.reaction.ui(ui);
+                let kinematics = self.reaction.kinematics().ok();
+
+                ui.separator();
+                self.focal_plane.ui(ui, kinematics.as_ref());
+
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(250.0)
+                    .show(ui, |ui| self.states_ui(ui, kinematics.as_ref()));
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Histogram: ");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.histogram)
+                            .hint_text("Focal plane histogram")
+                            .desired_width(200.0),
+                    );
+                });
+
+                let Some(kinematics) = kinematics else {
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Annotate")
+                        .on_hover_text("Draw the predicted positions of the states on the histogram")
+                        .clicked()
+                    {
+                        self.annotate(&kinematics, hists);
+                    }
+
+                    if ui
+                        .button("Assign to Fits")
+                        .on_hover_text("Give each stored peak of the histogram the excitation energy of the nearest state")
+                        .clicked()
+                    {
+                        self.assign(&kinematics, hists);
+                    }
+                    ui.add(
+                        egui::DragValue::new(&mut self.tolerance)
+                            .speed(0.1)
+                            .range(0.0..=f64::INFINITY)
+                            .prefix("within ")
+                            .suffix(" mm"),
+                    );
+
+                    if ui
+                        .button("Fit Focal Plane")
+                        .on_hover_text("Fit D and ρ0 to the stored peaks of the histogram with an excitation energy in keV")
+                        .clicked()
+                    {
+                        if let Err(e) = self.fit_focal_plane(&kinematics, hists) {
+                            log::error!("{}", e);
+                        }
+                    }
+                });
+            });
+
+        self.open = open;
+    }
+}
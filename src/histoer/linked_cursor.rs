@@ -0,0 +1,24 @@
+// Cursor x shared by the 1D panes of a grid with linked cursors. The pane under the mouse
+// stores its x in the egui memory, the other panes of the grid draw it on the next frame.
+
+#[derive(Clone, Copy)]
+struct SharedCursor {
+    x: f64, // data units, also with log x
+    frame: u64,
+}
+
+// Id of the link of the panes in a grid container
+pub fn link_id(container: egui_tiles::TileId) -> egui::Id {
+    egui::Id::new(("linked_cursor", container))
+}
+
+pub fn share_cursor(ctx: &egui::Context, link: egui::Id, x: f64) {
+    let frame = ctx.cumulative_pass_nr();
+    ctx.data_mut(|data| data.insert_temp(link, SharedCursor { x, frame }));
+}
+
+// Only set while a pane of the link is hovered, it is dropped a frame after the mouse leaves
+pub fn shared_cursor(ctx: &egui::Context, link: egui::Id) -> Option<f64> {
+    let cursor = ctx.data(|data| data.get_temp::<SharedCursor>(link))?;
+    (cursor.frame + 1 >= ctx.cumulative_pass_nr()).then_some(cursor.x)
+}
@@ -0,0 +1,310 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use super::histo1d::histogram1d::Histogram;
+
+// Spectra in the MCA vendor formats used by ORTEC software (Maestro, GammaVision):
+// the binary integer .Chn and the ASCII .Spe. Canberra and most other packages can
+// convert from these, so they are the common ground for teaching labs.
+#[derive(Debug, Clone, Default)]
+pub struct McaSpectrum {
+    pub counts: Vec<u64>,
+    pub live_time: f64, // seconds
+    pub real_time: f64,
+    pub calibration: [f64; 3], // E = c[0] + c[1] ch + c[2] ch^2, zero when not calibrated
+    pub description: String,
+}
+
+impl McaSpectrum {
+    // Channel i of the spectrum is bin i of the histogram. The channel calibration maps
+    // onto the histogram axis, or onto energy when the histogram is calibrated.
+    pub fn from_histogram(hist: &Histogram) -> Result<Self, String> {
+        if hist.is_variable_width() {
+            return Err(format!(
+                "'{}' has variable width bins, which MCA formats cannot store",
+                hist.name
+            ));
+        }
+
+        let width = (hist.range.1 - hist.range.0) / hist.original_bins.len() as f64;
+        let (p, q) = (hist.range.0 + 0.5 * width, width); // x = p + q ch
+        let calibration = if hist.plot_settings.calibration.active {
+            let [a, b, c] = hist.plot_settings.calibration.coefficients();
+            [a * p * p + b * p + c, 2.0 * a * p * q + b * q, a * q * q]
+        } else {
+            [p, q, 0.0]
+        };
+
+        Ok(McaSpectrum {
+            counts: hist.original_bins.clone(),
+            live_time: 0.0,
+            real_time: 0.0,
+            calibration,
+            description: hist.name.clone(),
+        })
+    }
+
+    // Histogram in channels with the calibration of the file on its axis
+    pub fn to_histogram(&self, name: &str) -> Histogram {
+        let channels = self.counts.len();
+        let mut hist = Histogram::new(name, channels, (-0.5, channels as f64 - 0.5));
+        hist.original_bins = self.counts.clone();
+        hist.rebin();
+
+        let [c, b, a] = self.calibration;
+        if b != 0.0 && (b != 1.0 || c != 0.0 || a != 0.0) {
+            let calibration = &mut hist.plot_settings.calibration;
+            calibration.a = a;
+            calibration.b = b;
+            calibration.c = c;
+            calibration.active = true;
+        }
+        hist
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        match extension(path).as_str() {
+            "chn" => Self::read_chn(path),
+            "spe" => Self::read_spe(path),
+            other => Err(format!("Unknown spectrum format '.{}'", other).into()),
+        }
+    }
+
+    // .Spe when the path says so, .Chn otherwise
+    pub fn write(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        match extension(path).as_str() {
+            "spe" => self.write_spe(path),
+            _ => self.write_chn(path),
+        }
+    }
+
+    // 32 byte header, 4 byte counts, and the 512 byte trailer with the calibration
+    pub fn write_chn(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if self.counts.len() > u16::MAX as usize {
+            return Err(format!("{} channels do not fit in a .Chn file", self.counts.len()).into());
+        }
+
+        let (date, time) = chn_date_time();
+        let mut bytes = Vec::with_capacity(32 + 4 * self.counts.len() + 512);
+        bytes.extend_from_slice(&(-1i16).to_le_bytes()); // file type
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // MCA number
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // segment
+        bytes.extend_from_slice(&time[4..6]); // start seconds
+        bytes.extend_from_slice(&((self.real_time * 50.0) as i32).to_le_bytes()); // 20 ms ticks
+        bytes.extend_from_slice(&((self.live_time * 50.0) as i32).to_le_bytes());
+        bytes.extend_from_slice(&date);
+        bytes.extend_from_slice(&time[0..4]);
+        bytes.extend_from_slice(&0i16.to_le_bytes()); // channel offset
+        bytes.extend_from_slice(&(self.counts.len() as u16).to_le_bytes());
+
+        for &count in &self.counts {
+            bytes.extend_from_slice(&(count.min(u32::MAX as u64) as u32).to_le_bytes());
+        }
+
+        let mut trailer = [0u8; 512];
+        trailer[0..2].copy_from_slice(&(-101i16).to_le_bytes());
+        for (index, coefficient) in self.calibration.iter().enumerate() {
+            let offset = 4 + 4 * index;
+            trailer[offset..offset + 4].copy_from_slice(&(*coefficient as f32).to_le_bytes());
+        }
+        let description = self.description.as_bytes();
+        let length = description.len().min(63);
+        trailer[320] = length as u8;
+        trailer[321..321 + length].copy_from_slice(&description[..length]);
+        bytes.extend_from_slice(&trailer);
+
+        std::fs::File::create(path)?.write_all(&bytes)?;
+        Ok(())
+    }
+
+    pub fn read_chn(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut bytes = Vec::new();
+        std::fs::File::open(path)?.read_to_end(&mut bytes)?;
+        if bytes.len() < 32 || i16::from_le_bytes([bytes[0], bytes[1]]) != -1 {
+            return Err(format!("{} is not an integer .Chn file", path.display()).into());
+        }
+
+        let i32_at = |offset: usize| {
+            i32::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ])
+        };
+        let real_time = i32_at(8) as f64 / 50.0;
+        let live_time = i32_at(12) as f64 / 50.0;
+        let channels = u16::from_le_bytes([bytes[30], bytes[31]]) as usize;
+
+        let end = 32 + 4 * channels;
+        if bytes.len() < end {
+            return Err(format!("{} is truncated", path.display()).into());
+        }
+        let counts = (0..channels)
+            .map(|channel| i32_at(32 + 4 * channel) as u32 as u64)
+            .collect();
+
+        let mut spectrum = McaSpectrum {
+            counts,
+            live_time,
+            real_time,
+            ..Default::default()
+        };
+
+        if bytes.len() >= end + 512 {
+            let trailer = &bytes[end..end + 512];
+            let kind = i16::from_le_bytes([trailer[0], trailer[1]]);
+            if kind == -101 || kind == -102 {
+                for index in 0..3 {
+                    let offset = 4 + 4 * index;
+                    spectrum.calibration[index] = f32::from_le_bytes([
+                        trailer[offset],
+                        trailer[offset + 1],
+                        trailer[offset + 2],
+                        trailer[offset + 3],
+                    ]) as f64;
+                }
+                let length = (trailer[320] as usize).min(63);
+                spectrum.description =
+                    String::from_utf8_lossy(&trailer[321..321 + length]).to_string();
+            }
+        }
+
+        Ok(spectrum)
+    }
+
+    pub fn write_spe(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut text = String::new();
+        text.push_str(&format!("$SPEC_ID:\n{}\n", self.description));
+        text.push_str("$SPEC_REM:\nExported from spectrix\n");
+        text.push_str(&format!(
+            "$MEAS_TIM:\n{:.0} {:.0}\n",
+            self.live_time, self.real_time
+        ));
+        text.push_str(&format!(
+            "$DATA:\n0 {}\n",
+            self.counts.len().saturating_sub(1)
+        ));
+        for count in &self.counts {
+            text.push_str(&format!("{:>8}\n", count));
+        }
+        let [c, b, a] = self.calibration;
+        text.push_str(&format!("$ENER_FIT:\n{} {}\n", c, b));
+        text.push_str(&format!("$MCA_CAL:\n3\n{} {} {} keV\n", c, b, a));
+        text.push_str("$ENDRECORD:\n");
+
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn read_spe(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path)?;
+        let mut spectrum = McaSpectrum::default();
+        let mut lines = text.lines().map(str::trim).peekable();
+
+        let numbers = |line: Option<&str>| -> Vec<f64> {
+            line.unwrap_or_default()
+                .split_whitespace()
+                .filter_map(|value| value.parse().ok())
+                .collect()
+        };
+
+        while let Some(line) = lines.next() {
+            match line {
+                "$SPEC_ID:" => {
+                    spectrum.description = lines.next().unwrap_or_default().to_string();
+                }
+                "$MEAS_TIM:" => {
+                    if let [live, real] = numbers(lines.next())[..] {
+                        spectrum.live_time = live;
+                        spectrum.real_time = real;
+                    }
+                }
+                "$DATA:" => {
+                    let range = numbers(lines.next());
+                    let channels = match range[..] {
+                        [first, last] => (last - first) as usize + 1,
+                        _ => return Err("Invalid $DATA range in .Spe file".into()),
+                    };
+                    for _ in 0..channels {
+                        let count = lines
+                            .next()
+                            .and_then(|line| line.parse::<f64>().ok())
+                            .ok_or("Missing channel in .Spe file")?;
+                        spectrum.counts.push(count.max(0.0) as u64);
+                    }
+                }
+                "$MCA_CAL:" => {
+                    lines.next(); // number of coefficients
+                    let coefficients = numbers(lines.next());
+                    for (index, coefficient) in coefficients.iter().take(3).enumerate() {
+                        spectrum.calibration[index] = *coefficient;
+                    }
+                }
+                "$ENER_FIT:" if spectrum.calibration == [0.0; 3] => {
+                    if let [c, b] = numbers(lines.peek().copied())[..] {
+                        spectrum.calibration = [c, b, 0.0];
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if spectrum.counts.is_empty() {
+            return Err(format!("No $DATA found in {}", path.display()).into());
+        }
+        Ok(spectrum)
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+// Start date as "DDMMMYY*" (* is '1' after 1999) and time as "HHMMSS", in UTC
+fn chn_date_time() -> ([u8; 8], [u8; 6]) {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+    let (days, seconds_of_day) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+    // civil date from days since 1970-01-01
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    let date = format!(
+        "{:02}{}{:02}{}",
+        day,
+        MONTHS[(month - 1) as usize],
+        year % 100,
+        if year >= 2000 { '1' } else { '0' }
+    );
+    let time = format!(
+        "{:02}{:02}{:02}",
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    );
+
+    let mut date_bytes = [b' '; 8];
+    date_bytes.copy_from_slice(&date.as_bytes()[..8]);
+    let mut time_bytes = [b'0'; 6];
+    time_bytes.copy_from_slice(&time.as_bytes()[..6]);
+    (date_bytes, time_bytes)
+}
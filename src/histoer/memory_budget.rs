@@ -0,0 +1,210 @@
+use polars::prelude::*;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// How much memory a fill may use. With "auto" the chunks are sized from the free RAM when the
+// fill starts and every batch is shrunk while the free RAM runs low, so the manual chunk size
+// does not have to be guessed for each machine. With "spill" the columns of the fill (after
+// Column Creation and the time window) are first written to parquet files on disk and the
+// histograms are filled from those, so the new columns are computed once instead of once per
+// fill group and only one batch is held in memory at a time.
+
+const GB: f64 = 1_073_741_824.0;
+const MIN_BATCH_ROWS: usize = 10_000;
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct MemoryBudget {
+    pub auto: bool,
+    pub fraction: f64, // of the free RAM that one batch may take
+    pub spill: bool,
+    pub spill_dir: String,
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        MemoryBudget {
+            auto: true,
+            fraction: 0.25,
+            spill: false,
+            spill_dir: std::env::temp_dir().display().to_string(),
+        }
+    }
+}
+
+impl MemoryBudget {
+    // Chunk size in GB, `manual_gb` is used when the budget is not automatic
+    pub fn chunk_gb(&self, manual_gb: f64) -> f64 {
+        if !self.auto {
+            return manual_gb;
+        }
+        (MemoryUsage::read().available_gb * self.fraction).max(0.1)
+    }
+
+    // Rows of the next batch, at most `max_rows`
+    pub fn batch_rows(&self, bytes_per_row: f64, max_rows: usize) -> usize {
+        if !self.auto {
+            return max_rows;
+        }
+        let budget = MemoryUsage::read().available_gb * GB * self.fraction;
+        ((budget / bytes_per_row.max(1.0)) as usize).clamp(MIN_BATCH_ROWS, max_rows.max(1))
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.auto, "Auto Chunk Size").on_hover_text(
+            "Size the chunks from the free RAM instead of the estimated memory, batches get smaller while the free RAM runs low",
+        );
+        ui.add_enabled(
+            self.auto,
+            egui::Slider::new(&mut self.fraction, 0.05..=0.9).text("of free RAM"),
+        );
+
+        ui.checkbox(&mut self.spill, "Spill to Disk").on_hover_text(
+            "Write the columns of the fill to parquet files first and fill from those. Uses less memory and computes new columns once, at the cost of disk space",
+        );
+        ui.add_enabled_ui(self.spill, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Spill Folder:");
+                ui.add(egui::TextEdit::singleline(&mut self.spill_dir).desired_width(150.0));
+                if ui.button("…").clicked() {
+                    if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                        self.spill_dir = dir.display().to_string();
+                    }
+                }
+            });
+        });
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryUsage {
+    pub total_gb: f64,
+    pub available_gb: f64,
+    pub process_gb: f64, // resident memory of spectrix
+}
+
+impl MemoryUsage {
+    pub fn read() -> Self {
+        let mut system = sysinfo::System::new();
+        system.refresh_memory();
+
+        let process_gb = sysinfo::get_current_pid()
+            .ok()
+            .and_then(|pid| {
+                system.refresh_processes_specifics(
+                    sysinfo::ProcessesToUpdate::Some(&[pid]),
+                    false,
+                    sysinfo::ProcessRefreshKind::nothing().with_memory(),
+                );
+                system
+                    .process(pid)
+                    .map(|process| process.memory() as f64 / GB)
+            })
+            .unwrap_or(0.0);
+
+        MemoryUsage {
+            total_gb: system.total_memory() as f64 / GB,
+            available_gb: system.available_memory() as f64 / GB,
+            process_gb,
+        }
+    }
+}
+
+// Memory use in the status bar, read once a second
+#[derive(Default)]
+pub struct MemoryIndicator {
+    last: Option<(Instant, MemoryUsage)>,
+}
+
+impl MemoryIndicator {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let usage = match self.last {
+            Some((read, usage)) if read.elapsed() < Duration::from_secs(1) => usage,
+            _ => {
+                let usage = MemoryUsage::read();
+                self.last = Some((Instant::now(), usage));
+                usage
+            }
+        };
+        ui.ctx().request_repaint_after(Duration::from_secs(1));
+
+        let used = 1.0 - usage.available_gb / usage.total_gb.max(f64::MIN_POSITIVE);
+        let color = if used > 0.9 {
+            egui::Color32::RED
+        } else if used > 0.75 {
+            egui::Color32::ORANGE
+        } else {
+            ui.visuals().text_color()
+        };
+        ui.colored_label(
+            color,
+            format!(
+                "Memory: {:.1} GB used by spectrix, {:.1} of {:.1} GB free",
+                usage.process_gb, usage.available_gb, usage.total_gb
+            ),
+        );
+    }
+}
+
+// Writes `columns` of the frame to parquet files of at most `batch_rows` rows in `dir`.
+// None when aborted, the files written so far are removed.
+pub fn spill_to_disk(
+    lf: &LazyFrame,
+    columns: &[String],
+    row_count: usize,
+    batch_rows: usize,
+    dir: &str,
+    abort_flag: &AtomicBool,
+    mut on_batch: impl FnMut(usize),
+) -> Option<PolarsResult<Vec<PathBuf>>> {
+    let dir = PathBuf::from(dir);
+    let selected = lf
+        .clone()
+        .select(columns.iter().map(col).collect::<Vec<_>>());
+    let mut files = Vec::new();
+
+    let result = (|| {
+        for (index, start) in (0..row_count).step_by(batch_rows.max(1)).enumerate() {
+            if abort_flag.load(Ordering::SeqCst) {
+                return Ok(false);
+            }
+            let rows = batch_rows.min(row_count - start);
+            let mut df = selected
+                .clone()
+                .slice(start as i64, rows as IdxSize)
+                .collect()?;
+
+            let path = dir.join(format!(
+                "spectrix_spill_{}_{}.parquet",
+                std::process::id(),
+                index
+            ));
+            let file = std::fs::File::create(&path)?;
+            files.push(path);
+            ParquetWriter::new(file).finish(&mut df)?;
+            on_batch(rows);
+        }
+        Ok(true)
+    })();
+
+    match result {
+        Ok(true) => Some(Ok(files)),
+        Ok(false) => {
+            remove_spill(&files);
+            None
+        }
+        Err(e) => {
+            remove_spill(&files);
+            Some(Err(e))
+        }
+    }
+}
+
+pub fn remove_spill(files: &[PathBuf]) {
+    for file in files {
+        if let Err(e) = std::fs::remove_file(file) {
+            log::error!("Failed to remove spill file {}: {}", file.display(), e);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::fs::File;
+use std::io::{BufReader, Write};
+
+use super::histo1d::histogram1d::Histogram;
+use super::histo2d::histogram2d::Histogram2D;
+
+// Merging histograms filled from different file subsets (e.g. in separate sessions).
+// Counts, overflow, and underflow are summed, so the binning has to be identical.
+
+// Unrebinned counts of a 1D histogram as written to/read from JSON
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HistogramCounts {
+    pub name: String,
+    pub range: (f64, f64),
+    pub counts: Vec<u64>,
+    pub overflow: u64,
+    pub underflow: u64,
+}
+
+// Unrebinned counts of a 2D histogram, bins are stored as (x index, y index, count)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Histogram2DCounts {
+    pub name: String,
+    pub bins: (usize, usize),
+    pub range: ((f64, f64), (f64, f64)),
+    pub counts: Vec<(usize, usize, u64)>,
+    pub overflow: (u64, u64),
+    pub underflow: (u64, u64),
+}
+
+fn save_json<T: serde::Serialize>(value: &T, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let file_name = format!("{}.json", name.replace('/', "_"));
+    if let Some(file_path) = rfd::FileDialog::new()
+        .set_file_name(file_name)
+        .add_filter("JSON Files", &["json"])
+        .save_file()
+    {
+        let serialized = serde_json::to_string(value)?;
+        let mut file = File::create(file_path)?;
+        file.write_all(serialized.as_bytes())?;
+    }
+    Ok(())
+}
+
+fn load_json<T: serde::de::DeserializeOwned>() -> Result<Option<T>, Box<dyn std::error::Error>> {
+    if let Some(file_path) = rfd::FileDialog::new()
+        .add_filter("JSON Files", &["json"])
+        .pick_file()
+    {
+        let reader = BufReader::new(File::open(file_path)?);
+        return Ok(Some(serde_json::from_reader(reader)?));
+    }
+    Ok(None)
+}
+
+impl Histogram {
+    pub fn to_counts(&self) -> HistogramCounts {
+        HistogramCounts {
+            name: self.name.clone(),
+            range: self.range,
+            counts: self.original_bins.clone(),
+            overflow: self.overflow,
+            underflow: self.underflow,
+        }
+    }
+
+    pub fn merge(&mut self, other: &HistogramCounts) -> Result<(), String> {
+        if self.original_bins.len() != other.counts.len() || self.range != other.range {
+            return Err(format!(
+                "Can not merge '{}' into '{}': binning differs ({} bins over {:?} vs {} bins over {:?})",
+                other.name,
+                self.name,
+                other.counts.len(),
+                other.range,
+                self.original_bins.len(),
+                self.range
+            ));
+        }
+
+        for (count, other_count) in self.original_bins.iter_mut().zip(&other.counts) {
+            *count += other_count;
+        }
+        self.overflow += other.overflow;
+        self.underflow += other.underflow;
+
+        self.rebin();
+
+        Ok(())
+    }
+
+    pub fn merge_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .button("Save JSON")
+                .on_hover_text("Save the histogram so it can be merged into another session")
+                .clicked()
+            {
+                if let Err(e) = save_json(&self.to_counts(), &self.name) {
+                    log::error!("Error saving histogram: {:?}", e);
+                }
+            }
+
+            if ui
+                .button("Merge JSON")
+                .on_hover_text("Add the counts of a saved histogram with identical binning")
+                .clicked()
+            {
+                match load_json::<HistogramCounts>() {
+                    Ok(Some(other)) => {
+                        if let Err(e) = self.merge(&other) {
+                            log::error!("{}", e);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => log::error!("Error loading histogram: {:?}", e),
+                }
+            }
+        });
+    }
+}
+
+impl Histogram2D {
+    pub fn to_counts(&self) -> Histogram2DCounts {
+        let bins = self.backup_bins.as_ref().unwrap_or(&self.bins);
+        Histogram2DCounts {
+            name: self.name.clone(),
+            bins: (bins.x, bins.y),
+            range: (
+                (self.range.x.min, self.range.x.max),
+                (self.range.y.min, self.range.y.max),
+            ),
+            counts: bins
+                .counts
+                .iter()
+                .map(|((x, y), count)| (x, y, count))
+                .collect(),
+            overflow: self.overflow,
+            underflow: self.underflow,
+        }
+    }
+
+    pub fn merge(&mut self, other: &Histogram2DCounts) -> Result<(), String> {
+        let (x_bins, y_bins) = {
+            let bins = self.backup_bins.as_ref().unwrap_or(&self.bins);
+            (bins.x, bins.y)
+        };
+        let range = (
+            (self.range.x.min, self.range.x.max),
+            (self.range.y.min, self.range.y.max),
+        );
+
+        if (x_bins, y_bins) != other.bins || range != other.range {
+            return Err(format!(
+                "Can not merge '{}' into '{}': binning differs",
+                other.name, self.name
+            ));
+        }
+
+        let bins = self.backup_bins.as_mut().unwrap_or(&mut self.bins);
+        for &(x, y, count) in &other.counts {
+            bins.counts.add(x, y, count);
+        }
+        bins.min_count = bins.counts.values().min().unwrap_or(u64::MAX);
+        bins.max_count = bins.counts.values().max().unwrap_or(u64::MIN);
+        bins.update_storage(self.plot_settings.storage);
+
+        self.overflow.0 += other.overflow.0;
+        self.overflow.1 += other.overflow.1;
+        self.underflow.0 += other.underflow.0;
+        self.underflow.1 += other.underflow.1;
+
+        if self.backup_bins.is_some() {
+            self.rebin();
+        }
+        self.plot_settings.recalculate_image = true;
+
+        Ok(())
+    }
+
+    pub fn merge_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .button("Save JSON")
+                .on_hover_text("Save the histogram so it can be merged into another session")
+                .clicked()
+            {
+                if let Err(e) = save_json(&self.to_counts(), &self.name) {
+                    log::error!("Error saving histogram: {:?}", e);
+                }
+            }
+
+            if ui
+                .button("Merge JSON")
+                .on_hover_text("Add the counts of a saved histogram with identical binning")
+                .clicked()
+            {
+                match load_json::<Histogram2DCounts>() {
+                    Ok(Some(other)) => {
+                        if let Err(e) = self.merge(&other) {
+                            log::error!("{}", e);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => log::error!("Error loading histogram: {:?}", e),
+                }
+            }
+        });
+    }
+}
@@ -1,7 +1,40 @@
+pub mod calibration_table;
+pub mod color_policy;
+pub mod column_script;
 pub mod configs;
+pub mod cut_expression;
+pub mod cut_stats;
 pub mod cuts;
+pub mod data_export;
+pub mod excitation_energy;
+pub mod figure;
+pub mod fill_buffer;
+pub mod fill_priority;
+pub mod fill_progress;
+pub mod fit_library;
+pub mod fit_summary;
 pub mod histo1d;
 pub mod histo2d;
+pub mod histo3d;
+pub mod histogram_math;
 pub mod histogrammer;
+pub mod histond;
+pub mod jobs;
+pub mod kinematics;
+pub mod linked_cursor;
+pub mod mca_formats;
+pub mod memory_budget;
+pub mod merge;
+pub mod notes;
 pub mod pane;
+pub mod presets;
+pub mod preview;
+pub mod root_writer;
+pub mod stats_box;
+pub mod sum_spectra;
+pub mod summary_stats;
+pub mod text_spectrum;
+pub mod time_difference;
 pub mod tree;
+pub mod undo;
+pub mod variables;
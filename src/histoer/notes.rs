@@ -0,0 +1,159 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Note {
+    pub timestamp: u64, // seconds since the unix epoch
+    pub text: String,
+}
+
+impl Note {
+    pub fn new(text: &str) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            timestamp,
+            text: text.to_string(),
+        }
+    }
+
+    pub fn time_string(&self) -> String {
+        format_timestamp(self.timestamp)
+    }
+}
+
+// Free-text notes attached to a histogram, stored with the histogram so they persist with the project
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Notes {
+    pub notes: Vec<Note>,
+    pub show: bool,
+    #[serde(skip)]
+    pub new_note: String,
+}
+
+impl Notes {
+    pub fn add(&mut self, text: &str) {
+        if text.trim().is_empty() {
+            return;
+        }
+        self.notes.push(Note::new(text.trim()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+
+    // Markdown section for the histogram used when generating reports
+    pub fn report(&self, histogram_name: &str) -> String {
+        let mut report = format!("## {}\n\n", histogram_name);
+        for note in &self.notes {
+            report.push_str(&format!("- **{}**: {}\n", note.time_string(), note.text));
+        }
+        report
+    }
+
+    pub fn menu_button(&mut self, ui: &mut egui::Ui) {
+        let label = if self.notes.is_empty() {
+            "Notes".to_string()
+        } else {
+            format!("Notes ({})", self.notes.len())
+        };
+
+        if ui
+            .selectable_label(self.show, label)
+            .on_hover_text("Observations saved with this histogram")
+            .clicked()
+        {
+            self.show = !self.show;
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, histogram_name: &str) {
+        if !self.show {
+            return;
+        }
+
+        let mut show = self.show;
+        let ctx = ui.ctx().clone();
+        egui::Window::new(format!("Notes: {}", histogram_name))
+            .open(&mut show)
+            .default_width(300.0)
+            .show(&ctx, |ui| {
+                let mut to_remove = None;
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for (index, note) in self.notes.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.label(egui::RichText::new(note.time_string()).weak());
+                                if ui.small_button("X").clicked() {
+                                    to_remove = Some(index);
+                                }
+                            });
+                            ui.add(
+                                egui::TextEdit::multiline(&mut note.text)
+                                    .desired_rows(1)
+                                    .desired_width(f32::INFINITY),
+                            );
+                            ui.separator();
+                        }
+                    });
+
+                if let Some(index) = to_remove {
+                    self.notes.remove(index);
+                }
+
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.new_note)
+                        .hint_text("New note")
+                        .desired_rows(2)
+                        .desired_width(f32::INFINITY),
+                );
+
+                ui.horizontal(|ui| {
+                    if ui.button("Add").clicked() {
+                        let text = std::mem::take(&mut self.new_note);
+                        self.add(&text);
+                    }
+
+                    if ui
+                        .add_enabled(!self.notes.is_empty(), egui::Button::new("Copy Report"))
+                        .clicked()
+                    {
+                        ui.ctx().copy_text(self.report(histogram_name));
+                    }
+                });
+            });
+        self.show = show;
+    }
+}
+
+// Formats seconds since the unix epoch as "YYYY-MM-DD HH:MM:SS UTC"
+fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds = timestamp % 86_400;
+
+    // civil-from-days conversion (proleptic Gregorian calendar)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        (seconds % 3_600) / 60,
+        seconds % 60
+    )
+}
@@ -1,19 +1,149 @@
 use crate::histoer::histo1d::histogram1d::Histogram;
 use crate::histoer::histo2d::histogram2d::Histogram2D;
+use crate::histoer::histo3d::histogram3d::Histogram3D;
+use crate::histoer::histond::histogramnd::{HistogramND, ProjectionView};
 use std::sync::{Arc, Mutex};
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub enum Pane {
     Histogram(Arc<Mutex<Box<Histogram>>>),
     Histogram2D(Arc<Mutex<Box<Histogram2D>>>),
+    Histogram3D(Arc<Mutex<Box<Histogram3D>>>),
+    HistogramND(Arc<Mutex<Box<HistogramND>>>),
 }
 
 impl Pane {
-    pub fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
-        let hist_name = match self {
+    pub fn name(&self) -> String {
+        match self {
             Pane::Histogram(hist) => hist.lock().unwrap().name.clone(),
             Pane::Histogram2D(hist) => hist.lock().unwrap().name.clone(),
+            Pane::Histogram3D(hist) => hist.lock().unwrap().name.clone(),
+            Pane::HistogramND(hist) => hist.lock().unwrap().name.clone(),
+        }
+    }
+
+    pub fn set_name(&self, name: &str) {
+        match self {
+            Pane::Histogram(hist) => hist.lock().unwrap().set_name(name),
+            Pane::Histogram2D(hist) => hist.lock().unwrap().set_name(name),
+            Pane::Histogram3D(hist) => {
+                let mut hist = hist.lock().unwrap();
+                hist.name = name.to_string();
+                hist.view.set_name(name);
+            }
+            Pane::HistogramND(hist) => {
+                let mut hist = hist.lock().unwrap();
+                hist.name = name.to_string();
+                match &mut hist.view {
+                    ProjectionView::Hist1D(view) => view.set_name(name),
+                    ProjectionView::Hist2D(view) => view.set_name(name),
+                }
+            }
+        }
+    }
+
+    // Copy with its own contents, fits, and cuts, nothing is shared with this pane
+    pub fn duplicate(&self, name: &str) -> Pane {
+        let pane = match self {
+            Pane::Histogram(hist) => {
+                let mut copy = hist.lock().unwrap().clone();
+                copy.fits.removed.clear();
+                Pane::Histogram(Arc::new(Mutex::new(copy)))
+            }
+            Pane::Histogram2D(hist) => {
+                let mut copy = hist.lock().unwrap().clone();
+                copy.plot_settings.cuts = copy
+                    .plot_settings
+                    .cuts
+                    .iter()
+                    .map(|cut| Arc::new(Mutex::new(cut.lock().unwrap().clone())))
+                    .collect();
+                copy.plot_settings.removed_cuts.clear();
+                copy.plot_settings.recalculate_image = true;
+                Pane::Histogram2D(Arc::new(Mutex::new(copy)))
+            }
+            Pane::Histogram3D(hist) => {
+                let mut copy = hist.lock().unwrap().clone();
+                copy.update_view = true;
+                Pane::Histogram3D(Arc::new(Mutex::new(copy)))
+            }
+            Pane::HistogramND(hist) => {
+                Pane::HistogramND(Arc::new(Mutex::new(hist.lock().unwrap().clone())))
+            }
         };
+        pane.set_name(name);
+        pane
+    }
+
+    // Small preview of the histogram drawn next to its entry in the tree UI
+    pub fn thumbnail(&self, ui: &mut egui::Ui, size: egui::Vec2) {
+        let (rect, _response) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_stroke(rect, 0.0, ui.visuals().widgets.noninteractive.bg_stroke);
+
+        match self {
+            Pane::Histogram(hist) => {
+                let mut hist = hist.lock().unwrap();
+                let color = hist.line.color;
+                let counts = hist.thumbnail_points();
+                let max = counts.iter().cloned().fold(0.0, f32::max);
+                if counts.len() < 2 || max <= 0.0 {
+                    return;
+                }
+
+                let step = rect.width() / (counts.len() - 1) as f32;
+                let points: Vec<egui::Pos2> = counts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &count)| {
+                        egui::pos2(
+                            rect.left() + i as f32 * step,
+                            rect.bottom() - count / max * rect.height(),
+                        )
+                    })
+                    .collect();
+
+                painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+            }
+            Pane::Histogram2D(hist) => {
+                // the heatmap texture is only available once the pane has been drawn
+                if let Some(texture) = &hist.lock().unwrap().image.texture {
+                    painter.image(
+                        texture.id(),
+                        rect,
+                        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                        egui::Color32::WHITE,
+                    );
+                }
+            }
+            Pane::Histogram3D(hist) => {
+                if let Some(texture) = &hist.lock().unwrap().view.image.texture {
+                    painter.image(
+                        texture.id(),
+                        rect,
+                        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                        egui::Color32::WHITE,
+                    );
+                }
+            }
+            Pane::HistogramND(hist) => {
+                // only 2D projections have a texture to show
+                if let ProjectionView::Hist2D(view) = &hist.lock().unwrap().view {
+                    if let Some(texture) = &view.image.texture {
+                        painter.image(
+                            texture.id(),
+                            rect,
+                            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                            egui::Color32::WHITE,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        let hist_name = self.name();
 
         let button = egui::Button::new(hist_name)
             .min_size(egui::Vec2::new(ui.available_width(), 0.0))
@@ -29,6 +159,14 @@ impl Pane {
                 Pane::Histogram2D(hist) => {
                     hist.lock().unwrap().render(ui);
                 }
+
+                Pane::Histogram3D(hist) => {
+                    hist.lock().unwrap().render(ui);
+                }
+
+                Pane::HistogramND(hist) => {
+                    hist.lock().unwrap().render(ui);
+                }
             }
 
             egui_tiles::UiResponse::DragStarted
@@ -41,6 +179,14 @@ impl Pane {
                 Pane::Histogram2D(hist) => {
                     hist.lock().unwrap().render(ui);
                 }
+
+                Pane::Histogram3D(hist) => {
+                    hist.lock().unwrap().render(ui);
+                }
+
+                Pane::HistogramND(hist) => {
+                    hist.lock().unwrap().render(ui);
+                }
             }
 
             egui_tiles::UiResponse::None
@@ -0,0 +1,147 @@
+use super::histo1d::histogram1d::Histogram;
+use super::histo2d::colormaps::{ColorMap, ColormapOptions};
+use super::histo2d::histogram2d::Histogram2D;
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum PresetTarget {
+    Hist1D,
+    Hist2D,
+}
+
+// Plot settings applied to newly created panes whose name matches the pattern
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PlotPreset {
+    pub pattern: String, // regex matched against the histogram name, empty matches everything
+    pub target: PresetTarget,
+    pub enabled: bool,
+    pub log_y: bool,
+    pub stats_info: bool,
+    pub colormap: ColorMap,
+    pub colormap_options: ColormapOptions,
+}
+
+impl PlotPreset {
+    pub fn new(target: PresetTarget) -> Self {
+        Self {
+            pattern: String::new(),
+            target,
+            enabled: true,
+            log_y: false,
+            stats_info: false,
+            colormap: ColorMap::default(),
+            colormap_options: ColormapOptions::default(),
+        }
+    }
+
+    fn matches(&self, name: &str, target: PresetTarget) -> bool {
+        if !self.enabled || self.target != target {
+            return false;
+        }
+
+        if self.pattern.is_empty() {
+            return true;
+        }
+
+        match regex::Regex::new(&self.pattern) {
+            Ok(re) => re.is_match(name),
+            Err(e) => {
+                log::error!("Invalid preset pattern '{}': {}", self.pattern, e);
+                false
+            }
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, index: usize) {
+        ui.checkbox(&mut self.enabled, "");
+
+        ui.add(
+            egui::TextEdit::singleline(&mut self.pattern)
+                .hint_text("Name pattern (regex)")
+                .desired_width(150.0),
+        );
+
+        match self.target {
+            PresetTarget::Hist1D => {
+                ui.label("1D");
+                ui.checkbox(&mut self.log_y, "Log Y");
+            }
+            PresetTarget::Hist2D => {
+                ui.label("2D");
+                ui.checkbox(&mut self.colormap_options.log_norm, "Log Z");
+                ui.checkbox(&mut self.colormap_options.reverse, "Reverse");
+                egui::ComboBox::from_id_salt(format!("preset_colormap_{}", index))
+                    .selected_text(format!("{:?}", self.colormap))
+                    .show_ui(ui, |ui| {
+                        let mut recalculate = false;
+                        self.colormap.color_maps_ui(ui, &mut recalculate);
+                    });
+            }
+        }
+
+        ui.checkbox(&mut self.stats_info, "Stats");
+    }
+}
+
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PlotPresets {
+    pub presets: Vec<PlotPreset>,
+}
+
+impl PlotPresets {
+    // The first matching preset wins
+    pub fn apply_1d(&self, hist: &mut Histogram) {
+        if let Some(preset) = self
+            .presets
+            .iter()
+            .find(|p| p.matches(&hist.name, PresetTarget::Hist1D))
+        {
+            hist.plot_settings.egui_settings.log_y = preset.log_y;
+            hist.plot_settings.stats_info = preset.stats_info;
+        }
+    }
+
+    pub fn apply_2d(&self, hist: &mut Histogram2D) {
+        if let Some(preset) = self
+            .presets
+            .iter()
+            .find(|p| p.matches(&hist.name, PresetTarget::Hist2D))
+        {
+            hist.plot_settings.colormap = preset.colormap;
+            hist.plot_settings.colormap_options = preset.colormap_options;
+            hist.plot_settings.stats_info = preset.stats_info;
+            hist.plot_settings.recalculate_image = true;
+        }
+    }
+
+    pub fn menu_button(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Plot Presets", |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Plot Presets");
+                if ui.button("+1D").clicked() {
+                    self.presets.push(PlotPreset::new(PresetTarget::Hist1D));
+                }
+                if ui.button("+2D").clicked() {
+                    self.presets.push(PlotPreset::new(PresetTarget::Hist2D));
+                }
+            })
+            .response
+            .on_hover_text("Settings applied to new histograms whose name matches the pattern. The first match is used.");
+
+            ui.separator();
+
+            let mut to_remove = None;
+            for (index, preset) in self.presets.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    preset.ui(ui, index);
+                    if ui.button("X").clicked() {
+                        to_remove = Some(index);
+                    }
+                });
+            }
+
+            if let Some(index) = to_remove {
+                self.presets.remove(index);
+            }
+        });
+    }
+}
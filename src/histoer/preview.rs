@@ -0,0 +1,82 @@
+// Preview fills histogram a random subset of the rows to check ranges, cuts,
+// and expressions before a full fill. Rows are sampled in blocks so only the
+// sampled blocks have to be read from the files.
+
+const PREVIEW_BLOCKS: usize = 200;
+const MIN_BLOCK_ROWS: usize = 1000;
+
+// xorshift64*, seeded from the clock so every preview draws different blocks
+struct Rng(u64);
+
+impl Rng {
+    fn from_time() -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0x9E37_79B9_7F4A_7C15);
+        Rng(seed | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+// Returns the (start row, rows) of the chunks to fill, in file order
+pub fn chunk_starts(
+    row_count: usize,
+    rows_per_chunk: usize,
+    sample: Option<f64>,
+) -> Vec<(usize, usize)> {
+    let rows_per_chunk = rows_per_chunk.max(1);
+
+    let Some(fraction) = sample else {
+        return (0..row_count)
+            .step_by(rows_per_chunk)
+            .map(|start| (start, rows_per_chunk.min(row_count - start)))
+            .collect();
+    };
+
+    let block_rows = (row_count / PREVIEW_BLOCKS)
+        .max(MIN_BLOCK_ROWS)
+        .min(rows_per_chunk);
+    let blocks = row_count.div_ceil(block_rows);
+    let selected = ((fraction * blocks as f64).round() as usize).clamp(1, blocks.max(1));
+
+    // partial Fisher-Yates shuffle of the block indices
+    let mut rng = Rng::from_time();
+    let mut indices: Vec<usize> = (0..blocks).collect();
+    for i in 0..selected.min(blocks) {
+        let j = i + rng.below(blocks - i);
+        indices.swap(i, j);
+    }
+    indices.truncate(selected);
+    indices.sort_unstable();
+
+    indices
+        .into_iter()
+        .map(|block| {
+            let start = block * block_rows;
+            (start, block_rows.min(row_count - start))
+        })
+        .collect()
+}
+
+// Drawn over panes filled by a preview so they are not mistaken for the full data
+pub fn watermark(ui: &egui::Ui, rect: egui::Rect, fraction: f64) {
+    let size = (rect.width().min(rect.height()) / 8.0).clamp(12.0, 48.0);
+    ui.painter_at(rect).text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        format!("PREVIEW ({:.1}% sample)", fraction * 100.0),
+        egui::FontId::proportional(size),
+        egui::Color32::from_rgba_unmultiplied(255, 80, 80, 70),
+    );
+}
@@ -0,0 +1,841 @@
+use super::fill_progress::FillProgress;
+use fnv::FnvHashMap;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Native writer for ROOT files holding TH1D and TH2D histograms.
+// Files use the small (32 bit seek) format without compression. Only an empty
+// StreamerInfo list is written, readers fall back on their built in class versions.
+
+const BEGIN: usize = 100;
+const FILE_VERSION: i32 = 62206;
+const KEY_VERSION: i16 = 4;
+const DIRECTORY_VERSION: i16 = 5;
+const DIRECTORY_RECORD_LEN: usize = 60;
+const FREE_RECORD_LEN: usize = 10;
+const BYTE_COUNT_MASK: u32 = 0x4000_0000;
+const NEW_CLASS_TAG: u32 = 0xFFFF_FFFF;
+const OBJECT_BITS: u32 = 0x0300_0000;
+
+// Offsets of the fields patched once the keys of a directory are written
+const RECORD_NBYTES_KEYS: usize = 10;
+const RECORD_SEEK_KEYS: usize = 26;
+
+#[derive(Default)]
+struct Buffer {
+    data: Vec<u8>,
+}
+
+impl Buffer {
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn i16(&mut self, value: i16) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn patch_u32(&mut self, position: usize, value: u32) {
+        self.data[position..position + 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    // TString: one byte length, or 255 followed by a four byte length
+    fn string(&mut self, value: &str) {
+        if value.len() < 255 {
+            self.u8(value.len() as u8);
+        } else {
+            self.u8(255);
+            self.i32(value.len() as i32);
+        }
+        self.bytes(value.as_bytes());
+    }
+
+    // Reserves the byte count and writes the class version
+    fn begin_object(&mut self, version: i16) -> usize {
+        let position = self.len();
+        self.u32(0);
+        self.i16(version);
+        position
+    }
+
+    fn end_object(&mut self, position: usize) {
+        let count = (self.len() - position - 4) as u32;
+        self.patch_u32(position, count | BYTE_COUNT_MASK);
+    }
+
+    fn tobject(&mut self) {
+        self.i16(1);
+        self.u32(0); // fUniqueID
+        self.u32(OBJECT_BITS);
+    }
+
+    fn tnamed(&mut self, name: &str, title: &str) {
+        let position = self.begin_object(1);
+        self.tobject();
+        self.string(name);
+        self.string(title);
+        self.end_object(position);
+    }
+
+    fn empty_tlist(&mut self) {
+        let position = self.begin_object(5);
+        self.tobject();
+        self.string("");
+        self.i32(0);
+        self.end_object(position);
+    }
+
+    fn tarray_d(&mut self, values: &[f64]) {
+        self.i32(values.len() as i32);
+        for &value in values {
+            self.f64(value);
+        }
+    }
+
+    fn taxis(&mut self, name: &str, axis: &Axis) {
+        let position = self.begin_object(10);
+        self.tnamed(name, "");
+
+        // TAttAxis with the ROOT defaults
+        let att_axis = self.begin_object(4);
+        self.i32(510); // fNdivisions
+        self.i16(1); // fAxisColor
+        self.i16(1); // fLabelColor
+        self.i16(42); // fLabelFont
+        self.f32(0.005); // fLabelOffset
+        self.f32(0.035); // fLabelSize
+        self.f32(0.03); // fTickLength
+        self.f32(1.0); // fTitleOffset
+        self.f32(0.035); // fTitleSize
+        self.i16(1); // fTitleColor
+        self.i16(42); // fTitleFont
+        self.end_object(att_axis);
+
+        self.i32(axis.bins as i32);
+        self.f64(axis.min);
+        self.f64(axis.max);
+        self.tarray_d(&axis.edges); // fXbins, empty for fixed width bins
+        self.i32(0); // fFirst
+        self.i32(0); // fLast
+        self.u16(0); // fBits2
+        self.u8(0); // fTimeDisplay
+        self.string(""); // fTimeFormat
+        self.u32(0); // fLabels
+        self.u32(0); // fModLabs
+        self.end_object(position);
+    }
+
+    fn th1(&mut self, name: &str, title: &str, axes: &[Axis; 3], cells: usize, stats: &Stats) {
+        let position = self.begin_object(8);
+        self.tnamed(name, title);
+
+        let att_line = self.begin_object(2);
+        self.i16(602);
+        self.i16(1);
+        self.i16(1);
+        self.end_object(att_line);
+
+        let att_fill = self.begin_object(2);
+        self.i16(0);
+        self.i16(1001);
+        self.end_object(att_fill);
+
+        let att_marker = self.begin_object(2);
+        self.i16(1);
+        self.i16(1);
+        self.f32(1.0);
+        self.end_object(att_marker);
+
+        self.i32(cells as i32); // fNcells, including underflow and overflow
+
+        self.taxis("xaxis", &axes[0]);
+        self.taxis("yaxis", &axes[1]);
+        self.taxis("zaxis", &axes[2]);
+
+        self.i16(0); // fBarOffset
+        self.i16(1000); // fBarWidth
+        self.f64(stats.entries);
+        self.f64(stats.tsumw);
+        self.f64(stats.tsumw2);
+        self.f64(stats.tsumwx);
+        self.f64(stats.tsumwx2);
+        self.f64(-1111.0); // fMaximum
+        self.f64(-1111.0); // fMinimum
+        self.f64(0.0); // fNormFactor
+        self.tarray_d(&[]); // fContour
+        self.tarray_d(&[]); // fSumw2, errors default to sqrt(counts)
+        self.string(""); // fOption
+
+        // fFunctions is an empty TList written with its class name
+        let functions = self.len();
+        self.u32(0);
+        self.u32(NEW_CLASS_TAG);
+        self.bytes(b"TList\0");
+        self.empty_tlist();
+        self.end_object(functions);
+
+        self.i32(0); // fBufferSize
+        self.u8(0); // fBuffer is empty
+        self.i32(0); // fBinStatErrOpt
+        self.i32(2); // fStatOverflows
+        self.end_object(position);
+    }
+
+    fn key_header(&mut self, key: &Key, seek_key: usize, seek_pdir: usize, datime: u32) {
+        let key_len = key.len();
+        self.i32((key_len + key.object_len) as i32);
+        self.i16(KEY_VERSION);
+        self.i32(key.object_len as i32);
+        self.u32(datime);
+        self.i16(key_len as i16);
+        self.i16(1); // cycle
+        self.u32(seek_key as u32);
+        self.u32(seek_pdir as u32);
+        self.string(key.class_name);
+        self.string(&key.name);
+        self.string(&key.title);
+    }
+
+    fn directory_record(
+        &mut self,
+        datime: u32,
+        nbytes_name: usize,
+        seek_dir: usize,
+        seek_parent: usize,
+    ) -> usize {
+        let position = self.len();
+        self.i16(DIRECTORY_VERSION);
+        self.u32(datime); // created
+        self.u32(datime); // modified
+        self.i32(0); // fNbytesKeys, patched later
+        self.i32(nbytes_name as i32);
+        self.u32(seek_dir as u32);
+        self.u32(seek_parent as u32);
+        self.u32(0); // fSeekKeys, patched later
+        self.u16(1); // UUID version
+        self.bytes(&[0; 16]);
+        self.bytes(&[0; 12]); // reserved for the large file format
+        position
+    }
+}
+
+fn string_len(value: &str) -> usize {
+    if value.len() < 255 {
+        1 + value.len()
+    } else {
+        5 + value.len()
+    }
+}
+
+struct Key {
+    class_name: &'static str,
+    name: String,
+    title: String,
+    object_len: usize,
+}
+
+impl Key {
+    fn len(&self) -> usize {
+        26 + string_len(self.class_name) + string_len(&self.name) + string_len(&self.title)
+    }
+}
+
+struct Axis {
+    bins: usize,
+    min: f64,
+    max: f64,
+    edges: Vec<f64>, // empty for fixed width bins
+}
+
+impl Axis {
+    fn unused() -> Self {
+        Axis {
+            bins: 1,
+            min: 0.0,
+            max: 1.0,
+            edges: Vec::new(),
+        }
+    }
+
+    fn center(&self, index: usize) -> f64 {
+        if self.edges.len() == self.bins + 1 {
+            return 0.5 * (self.edges[index] + self.edges[index + 1]);
+        }
+        self.min + (index as f64 + 0.5) * (self.max - self.min) / self.bins as f64
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    entries: f64,
+    tsumw: f64,
+    tsumw2: f64,
+    tsumwx: f64,
+    tsumwx2: f64,
+    tsumwy: f64,
+    tsumwy2: f64,
+    tsumwxy: f64,
+}
+
+impl Stats {
+    // Every fill has unit weight, so the sum of squared weights equals the sum of weights
+    fn add(&mut self, count: u64, x: f64, y: f64) {
+        let w = count as f64;
+        self.tsumw += w;
+        self.tsumw2 += w;
+        self.tsumwx += w * x;
+        self.tsumwx2 += w * x * x;
+        self.tsumwy += w * y;
+        self.tsumwy2 += w * y * y;
+        self.tsumwxy += w * x * y;
+    }
+}
+
+// 2D contents are kept sparse and streamed row by row when the file is written
+enum ObjectData {
+    Serialized(Vec<u8>),
+    Hist2D {
+        header: Vec<u8>, // everything before the cell contents
+        bins: (usize, usize),
+        counts: FnvHashMap<(usize, usize), u64>,
+    },
+}
+
+struct RootObject {
+    path: Vec<String>, // directories above the object
+    key: Key,
+    data: ObjectData,
+}
+
+#[derive(Default)]
+struct Directory {
+    subdirectories: BTreeMap<String, Directory>,
+    objects: Vec<usize>,
+}
+
+// Tracks the write position so records can be patched once their targets are known
+struct Output {
+    writer: BufWriter<File>,
+    position: usize,
+    patches: Vec<(usize, Vec<u8>)>,
+    written: usize, // object bytes, for progress
+    total: usize,
+}
+
+impl Output {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.position += bytes.len();
+        Ok(())
+    }
+
+    fn patch_u32(&mut self, position: usize, value: u32) {
+        self.patches.push((position, value.to_be_bytes().to_vec()));
+    }
+
+    fn finish(mut self) -> std::io::Result<()> {
+        for (position, bytes) in &self.patches {
+            self.writer.seek(SeekFrom::Start(*position as u64))?;
+            self.writer.write_all(bytes)?;
+        }
+        self.writer.flush()
+    }
+}
+
+// Progress reporting and cancellation for long writes
+pub struct WriteProgress<'a> {
+    pub progress: &'a Mutex<FillProgress>,
+    pub abort: &'a AtomicBool,
+}
+
+impl WriteProgress<'_> {
+    fn update(&self, out: &Output) -> std::io::Result<()> {
+        if self.abort.load(Ordering::Relaxed) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "ROOT file export aborted",
+            ));
+        }
+        if let Ok(mut progress) = self.progress.lock() {
+            progress.fraction = out.written as f32 / out.total.max(1) as f32;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct RootFile {
+    objects: Vec<RootObject>,
+}
+
+impl RootFile {
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    // Histogram names are paths, e.g. "Cebra/Cebr0/Energy" is written to the Cebra/Cebr0 directory
+    fn add_object(
+        &mut self,
+        path: &str,
+        class_name: &'static str,
+        object_len: usize,
+        data: ObjectData,
+    ) {
+        let mut path: Vec<String> = path
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .map(|part| part.to_string())
+            .collect();
+        let name = path.pop().unwrap_or_default();
+
+        // aliased panes share a histogram, only write it once
+        if self
+            .objects
+            .iter()
+            .any(|object| object.path == path && object.key.name == name)
+        {
+            return;
+        }
+
+        self.objects.push(RootObject {
+            path,
+            key: Key {
+                class_name,
+                name: name.clone(),
+                title: name,
+                object_len,
+            },
+            data,
+        });
+    }
+
+    pub fn add_hist1d(
+        &mut self,
+        path: &str,
+        bins: &[u64],
+        underflow: u64,
+        overflow: u64,
+        range: (f64, f64),
+        edges: &[f64],
+    ) {
+        let axis = Axis {
+            bins: bins.len(),
+            min: range.0,
+            max: range.1,
+            edges: edges.to_vec(),
+        };
+
+        let mut stats = Stats::default();
+        for (index, &count) in bins.iter().enumerate() {
+            stats.add(count, axis.center(index), 0.0);
+        }
+        stats.entries = stats.tsumw + (underflow + overflow) as f64;
+
+        let mut contents = Vec::with_capacity(bins.len() + 2);
+        contents.push(underflow as f64);
+        contents.extend(bins.iter().map(|&count| count as f64));
+        contents.push(overflow as f64);
+
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let mut buffer = Buffer::default();
+        let position = buffer.begin_object(3);
+        buffer.th1(
+            name,
+            name,
+            &[axis, Axis::unused(), Axis::unused()],
+            contents.len(),
+            &stats,
+        );
+        buffer.tarray_d(&contents);
+        buffer.end_object(position);
+
+        self.add_object(
+            path,
+            "TH1D",
+            buffer.len(),
+            ObjectData::Serialized(buffer.data),
+        );
+    }
+
+    pub fn add_hist2d(
+        &mut self,
+        path: &str,
+        bins: (usize, usize),
+        counts: FnvHashMap<(usize, usize), u64>,
+        range_x: (f64, f64),
+        range_y: (f64, f64),
+    ) {
+        let x_axis = Axis {
+            bins: bins.0,
+            min: range_x.0,
+            max: range_x.1,
+            edges: Vec::new(),
+        };
+        let y_axis = Axis {
+            bins: bins.1,
+            min: range_y.0,
+            max: range_y.1,
+            edges: Vec::new(),
+        };
+
+        let mut stats = Stats::default();
+        for (&(x, y), &count) in &counts {
+            if x < bins.0 && y < bins.1 {
+                stats.add(count, x_axis.center(x), y_axis.center(y));
+            }
+        }
+        stats.entries = stats.tsumw;
+
+        // cells include the underflow and overflow bins of both axes
+        let cells = (bins.0 + 2) * (bins.1 + 2);
+
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let mut header = Buffer::default();
+        let position = header.begin_object(4);
+        let th2 = header.begin_object(5);
+        header.th1(name, name, &[x_axis, y_axis, Axis::unused()], cells, &stats);
+        header.f64(1.0); // fScalefactor
+        header.f64(stats.tsumwy);
+        header.f64(stats.tsumwy2);
+        header.f64(stats.tsumwxy);
+        header.end_object(th2);
+        header.i32(cells as i32);
+
+        // the byte count covers the contents that follow the header
+        let object_len = header.len() + 8 * cells;
+        header.patch_u32(
+            position,
+            (object_len - position - 4) as u32 | BYTE_COUNT_MASK,
+        );
+
+        self.add_object(
+            path,
+            "TH2D",
+            object_len,
+            ObjectData::Hist2D {
+                header: header.data,
+                bins,
+                counts,
+            },
+        );
+    }
+
+    fn directory_tree(&self) -> Directory {
+        let mut root = Directory::default();
+        for (index, object) in self.objects.iter().enumerate() {
+            let mut directory = &mut root;
+            for part in &object.path {
+                directory = directory.subdirectories.entry(part.clone()).or_default();
+            }
+            directory.objects.push(index);
+        }
+        root
+    }
+
+    fn write_object(
+        &self,
+        out: &mut Output,
+        object: &RootObject,
+        progress: &WriteProgress<'_>,
+    ) -> std::io::Result<()> {
+        match &object.data {
+            ObjectData::Serialized(data) => {
+                out.write(data)?;
+                out.written += data.len();
+            }
+            ObjectData::Hist2D {
+                header,
+                bins,
+                counts,
+            } => {
+                out.write(header)?;
+                out.written += header.len();
+
+                let row_len = bins.0 + 2;
+                let mut row = Buffer::default();
+                for y in 0..bins.1 + 2 {
+                    row.data.clear();
+                    for x in 0..row_len {
+                        let count = if x == 0 || y == 0 || x > bins.0 || y > bins.1 {
+                            0
+                        } else {
+                            counts.get(&(x - 1, y - 1)).copied().unwrap_or(0)
+                        };
+                        row.f64(count as f64);
+                    }
+                    out.write(&row.data)?;
+                    out.written += row.len();
+
+                    if y % 64 == 0 {
+                        progress.update(out)?;
+                    }
+                }
+            }
+        }
+        progress.update(out)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn write_directory(
+        &self,
+        out: &mut Output,
+        directory: &Directory,
+        record: usize,
+        seek_dir: usize,
+        class_name: &'static str,
+        name: &str,
+        datime: u32,
+        progress: &WriteProgress<'_>,
+    ) -> std::io::Result<()> {
+        let mut keys = Buffer::default();
+        let mut nkeys = 0;
+
+        for (subdirectory_name, subdirectory) in &directory.subdirectories {
+            let key = Key {
+                class_name: "TDirectory",
+                name: subdirectory_name.clone(),
+                title: subdirectory_name.clone(),
+                object_len: DIRECTORY_RECORD_LEN,
+            };
+            let seek_key = out.position;
+            let mut record = Buffer::default();
+            record.key_header(&key, seek_key, seek_dir, datime);
+            keys.key_header(&key, seek_key, seek_dir, datime);
+            nkeys += 1;
+
+            let subdirectory_record = seek_key + record.len();
+            record.directory_record(datime, key.len(), seek_key, seek_dir);
+            out.write(&record.data)?;
+
+            self.write_directory(
+                out,
+                subdirectory,
+                subdirectory_record,
+                seek_key,
+                "TDirectory",
+                subdirectory_name,
+                datime,
+                progress,
+            )?;
+        }
+
+        for &index in &directory.objects {
+            let object = &self.objects[index];
+            let seek_key = out.position;
+            let mut header = Buffer::default();
+            header.key_header(&object.key, seek_key, seek_dir, datime);
+            out.write(&header.data)?;
+            self.write_object(out, object, progress)?;
+            keys.key_header(&object.key, seek_key, seek_dir, datime);
+            nkeys += 1;
+        }
+
+        // list of keys in this directory
+        let key = Key {
+            class_name,
+            name: name.to_string(),
+            title: if class_name == "TFile" {
+                String::new()
+            } else {
+                name.to_string()
+            },
+            object_len: 4 + keys.len(),
+        };
+        let seek_keys = out.position;
+        let mut list = Buffer::default();
+        list.key_header(&key, seek_keys, seek_dir, datime);
+        list.i32(nkeys);
+        list.bytes(&keys.data);
+        out.write(&list.data)?;
+
+        out.patch_u32(record + RECORD_NBYTES_KEYS, list.len() as u32);
+        out.patch_u32(record + RECORD_SEEK_KEYS, seek_keys as u32);
+        Ok(())
+    }
+
+    // Streams the file to disk, a partially written file is removed on error or abort
+    pub fn write(&self, output_file: &str, progress: &WriteProgress<'_>) -> std::io::Result<()> {
+        let result = self.write_file(output_file, progress);
+        if result.is_err() {
+            let _ = std::fs::remove_file(output_file);
+        }
+        result
+    }
+
+    fn write_file(&self, output_file: &str, progress: &WriteProgress<'_>) -> std::io::Result<()> {
+        let file_name = std::path::Path::new(output_file)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(output_file)
+            .to_string();
+        let datime = datime_now();
+
+        let total: usize = self
+            .objects
+            .iter()
+            .map(|object| object.key.object_len)
+            .sum();
+        if total > i32::MAX as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "ROOT file larger than 2 GB, use the uproot writer instead",
+            ));
+        }
+
+        let mut out = Output {
+            writer: BufWriter::new(File::create(output_file)?),
+            position: 0,
+            patches: Vec::new(),
+            written: 0,
+            total,
+        };
+        out.write(&[0; BEGIN])?; // file header, patched last
+
+        // top directory: key, file name and title, directory record
+        let name_len = string_len(&file_name) + string_len("");
+        let top_key = Key {
+            class_name: "TFile",
+            name: file_name.clone(),
+            title: String::new(),
+            object_len: name_len + DIRECTORY_RECORD_LEN,
+        };
+        let nbytes_name = top_key.len() + name_len;
+        let mut top = Buffer::default();
+        top.key_header(&top_key, BEGIN, 0, datime);
+        top.string(&file_name);
+        top.string("");
+        let top_record = BEGIN + top.len();
+        top.directory_record(datime, nbytes_name, BEGIN, 0);
+        out.write(&top.data)?;
+
+        // empty StreamerInfo list
+        let mut streamer_info = Buffer::default();
+        streamer_info.empty_tlist();
+        let info_key = Key {
+            class_name: "TList",
+            name: "StreamerInfo".to_string(),
+            title: "Doubly linked list".to_string(),
+            object_len: streamer_info.len(),
+        };
+        let seek_info = out.position;
+        let mut info = Buffer::default();
+        info.key_header(&info_key, seek_info, BEGIN, datime);
+        info.bytes(&streamer_info.data);
+        out.write(&info.data)?;
+        let nbytes_info = info.len();
+
+        self.write_directory(
+            &mut out,
+            &self.directory_tree(),
+            top_record,
+            BEGIN,
+            "TFile",
+            &file_name,
+            datime,
+            progress,
+        )?;
+
+        // free segments: everything past the end of the file
+        let free_key = Key {
+            class_name: "TFile",
+            name: file_name.clone(),
+            title: String::new(),
+            object_len: FREE_RECORD_LEN,
+        };
+        let seek_free = out.position;
+        let nbytes_free = free_key.len() + FREE_RECORD_LEN;
+        let end = seek_free + nbytes_free;
+        let mut free = Buffer::default();
+        free.key_header(&free_key, seek_free, BEGIN, datime);
+        free.i16(1);
+        free.u32(end as u32);
+        free.u32(2_000_000_000);
+        out.write(&free.data)?;
+
+        if end > i32::MAX as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "ROOT file larger than 2 GB, use the uproot writer instead",
+            ));
+        }
+
+        let mut header = Buffer::default();
+        header.bytes(b"root");
+        header.i32(FILE_VERSION);
+        header.i32(BEGIN as i32);
+        header.u32(end as u32);
+        header.u32(seek_free as u32);
+        header.i32(nbytes_free as i32);
+        header.i32(1); // nfree
+        header.i32(nbytes_name as i32);
+        header.u8(4); // fUnits
+        header.i32(0); // fCompress
+        header.u32(seek_info as u32);
+        header.i32(nbytes_info as i32);
+        header.u16(1); // UUID version
+        header.bytes(&[0; 16]);
+        out.patches.push((0, header.data));
+
+        out.finish()
+    }
+}
+
+// ROOT TDatime packing of the current UTC time
+fn datime_now() -> u32 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+    let (days, time) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+
+    // civil date from days since 1970-01-01
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (((year - 1995) << 26)
+        | (month << 22)
+        | (day << 17)
+        | ((time / 3600) << 12)
+        | ((time % 3600 / 60) << 6)
+        | (time % 60)) as u32
+}
@@ -0,0 +1,85 @@
+use egui::{Align2, Color32, FontId, Pos2, Rect, Stroke, Vec2};
+
+// Box of name/value rows in the top right corner of a plot, like the stats box of ROOT
+pub fn stats_box(ui: &egui::Ui, rect: Rect, title: &str, rows: &[(&str, String)]) {
+    info_box(ui, rect, title, rows, false);
+}
+
+// Same box in the top left corner, for the crosshair readout
+pub fn readout_box(ui: &egui::Ui, rect: Rect, title: &str, rows: &[(&str, String)]) {
+    info_box(ui, rect, title, rows, true);
+}
+
+fn info_box(ui: &egui::Ui, rect: Rect, title: &str, rows: &[(&str, String)], left: bool) {
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    let font = FontId::monospace(11.0);
+    let text_color = visuals.text_color();
+
+    let layout = |text: String| painter.layout_no_wrap(text, font.clone(), text_color);
+    let title = layout(title.to_string());
+    let rows: Vec<_> = rows
+        .iter()
+        .map(|(name, value)| (layout(name.to_string()), layout(value.clone())))
+        .collect();
+
+    let padding = 4.0;
+    let gap = 12.0;
+    let row_height = title.size().y;
+    let name_width = rows.iter().map(|(n, _)| n.size().x).fold(0.0, f32::max);
+    let value_width = rows.iter().map(|(_, v)| v.size().x).fold(0.0, f32::max);
+    let width = (name_width + gap + value_width).max(title.size().x) + 2.0 * padding;
+    let height = row_height * (rows.len() + 1) as f32 + 2.0 * padding + 2.0;
+
+    let x = if left {
+        rect.min.x + 8.0
+    } else {
+        rect.max.x - width - 8.0
+    };
+    let min = Pos2::new(x, rect.min.y + 8.0);
+    let frame = Rect::from_min_size(min, Vec2::new(width, height));
+    let background = visuals.extreme_bg_color;
+    painter.rect(
+        frame,
+        2.0,
+        Color32::from_rgba_unmultiplied(background.r(), background.g(), background.b(), 220),
+        Stroke::new(1.0, visuals.weak_text_color()),
+    );
+
+    let mut y = frame.min.y + padding;
+    painter.galley(
+        Pos2::new(frame.center().x - title.size().x / 2.0, y),
+        title,
+        text_color,
+    );
+    y += row_height + 1.0;
+    painter.hline(
+        frame.x_range(),
+        y,
+        Stroke::new(1.0, visuals.weak_text_color()),
+    );
+    y += 1.0;
+
+    for (name, value) in rows {
+        painter.galley(Pos2::new(frame.min.x + padding, y), name, text_color);
+        let right = Pos2::new(frame.max.x - padding, y);
+        painter.galley(
+            Align2::RIGHT_TOP.anchor_size(right, value.size()).min,
+            value,
+            text_color,
+        );
+        y += row_height;
+    }
+}
+
+// Short form of a number for the box: integers as they are, others with 4 significant digits
+pub fn format_value(value: f64) -> String {
+    if value == 0.0 || !value.is_finite() {
+        format!("{}", value)
+    } else if value.abs() >= 1e5 || value.abs() < 1e-3 {
+        format!("{:.3e}", value)
+    } else {
+        let decimals = (3 - value.abs().log10().floor() as i32).max(0) as usize;
+        format!("{:.*}", decimals, value)
+    }
+}
@@ -0,0 +1,182 @@
+use super::histo1d::histogram1d::{BinValues, Histogram};
+
+// Sum of the 1D histograms whose names match a pattern, e.g. the same spectrum of every
+// detector of an array. `*` matches within a folder, `?` one character, `{0-15}` a number
+// in the range, and `{a,b}` either alternative. The histograms need identical binning.
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+pub struct SumSpectra {
+    pub pattern: String,
+    pub result: String, // name of the new pane, generated when empty
+}
+
+// Inclusive range of a `{low-high}` group
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NumberRange(u64, u64);
+
+impl SumSpectra {
+    // Regex of the pattern with a capture group per numeric range
+    fn compile(&self) -> Result<(regex::Regex, Vec<NumberRange>), String> {
+        let mut expression = "^".to_string();
+        let mut ranges = Vec::new();
+        let mut chars = self.pattern.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '*' => expression.push_str("[^/]*"),
+                '?' => expression.push_str("[^/]"),
+                '{' => {
+                    let group: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    let range = group.split_once('-').and_then(|(low, high)| {
+                        Some(NumberRange(
+                            low.trim().parse().ok()?,
+                            high.trim().parse().ok()?,
+                        ))
+                    });
+                    match range {
+                        Some(range) => {
+                            expression.push_str(r"(\d+)");
+                            ranges.push(range);
+                        }
+                        None => {
+                            let alternatives: Vec<String> =
+                                group.split(',').map(regex::escape).collect();
+                            expression.push_str(&format!("(?:{})", alternatives.join("|")));
+                        }
+                    }
+                }
+                c => expression.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        expression.push('$');
+
+        regex::Regex::new(&expression)
+            .map(|re| (re, ranges))
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn matches(&self, names: &[String]) -> Result<Vec<String>, String> {
+        if self.pattern.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (re, ranges) = self.compile()?;
+        let result = self.result_name();
+        Ok(names
+            .iter()
+            .filter(|name| **name != result)
+            .filter(|name| {
+                re.captures(name).is_some_and(|captures| {
+                    ranges.iter().enumerate().all(|(index, range)| {
+                        captures
+                            .get(index + 1)
+                            .and_then(|number| number.as_str().parse::<u64>().ok())
+                            .is_some_and(|number| (range.0..=range.1).contains(&number))
+                    })
+                })
+            })
+            .cloned()
+            .collect())
+    }
+
+    pub fn result_name(&self) -> String {
+        if !self.result.is_empty() {
+            return self.result.clone();
+        }
+        let short = self.pattern.rsplit('/').next().unwrap_or(&self.pattern);
+        format!("Sum/{}", short)
+    }
+
+    // Returns true when the user asked for the sum
+    pub fn menu_ui(&mut self, ui: &mut egui::Ui, names: &[String]) -> bool {
+        ui.heading("Sum Spectra");
+
+        ui.add(
+            egui::TextEdit::singleline(&mut self.pattern)
+                .hint_text("Run*/Gamma/Det_{0-15}")
+                .clip_text(false),
+        )
+        .on_hover_text("* matches within a folder, ? one character, {0-15} a number in the range, {a,b} either name");
+
+        let hint = self.result_name();
+        ui.add(
+            egui::TextEdit::singleline(&mut self.result)
+                .hint_text(hint)
+                .clip_text(false),
+        );
+
+        let matches = match self.matches(names) {
+            Ok(matches) => {
+                ui.label(format!("{} matching histograms", matches.len()))
+                    .on_hover_text(matches.join("\n"));
+                matches
+            }
+            Err(e) => {
+                ui.colored_label(egui::Color32::RED, e);
+                Vec::new()
+            }
+        };
+
+        ui.add_enabled(!matches.is_empty(), egui::Button::new("Sum"))
+            .on_hover_text("Add the sum as a new pane. Requires identical binning.")
+            .clicked()
+    }
+
+    // Counts, underflow, and overflow are summed. Histograms made by arithmetic carry
+    // their uncertainties, which are added in quadrature.
+    pub fn sum(&self, hists: &[Histogram]) -> Result<Histogram, String> {
+        let Some(first) = hists.first() else {
+            return Err(format!("No histograms match '{}'", self.pattern));
+        };
+
+        for hist in &hists[1..] {
+            if hist.original_bins.len() != first.original_bins.len()
+                || hist.range != first.range
+                || hist.edges != first.edges
+            {
+                return Err(format!(
+                    "'{}' and '{}' do not have identical binning ({} bins over {:?} vs {} bins over {:?})",
+                    first.name,
+                    hist.name,
+                    first.original_bins.len(),
+                    first.range,
+                    hist.original_bins.len(),
+                    hist.range
+                ));
+            }
+        }
+
+        let mut sum = if first.is_variable_width() {
+            Histogram::with_edges(&self.result_name(), &first.edges)
+        } else {
+            Histogram::new(&self.result_name(), first.original_bins.len(), first.range)
+        };
+
+        if hists.iter().all(|hist| hist.values.is_none()) {
+            for hist in hists {
+                for (count, other) in sum.original_bins.iter_mut().zip(&hist.original_bins) {
+                    *count += other;
+                }
+                sum.underflow += hist.underflow;
+                sum.overflow += hist.overflow;
+            }
+            sum.rebin();
+        } else {
+            let mut values = BinValues {
+                contents: vec![0.0; first.original_bins.len()],
+                errors: vec![0.0; first.original_bins.len()],
+            };
+            for hist in hists {
+                let other = hist.original_values();
+                for index in 0..values.contents.len() {
+                    values.contents[index] += other.contents[index];
+                    values.errors[index] = values.errors[index].hypot(other.errors[index]);
+                }
+                sum.underflow += hist.underflow;
+                sum.overflow += hist.overflow;
+            }
+            sum.set_values(values);
+        }
+
+        Ok(sum)
+    }
+}
@@ -0,0 +1,224 @@
+use super::pane::Pane;
+
+// One line of the summary statistics export. Means and RMS are in the calibrated
+// units of 1D histograms with an active calibration, NaN where they do not apply.
+#[derive(Debug, Clone)]
+pub struct StatsRow {
+    pub name: String,
+    pub kind: &'static str,
+    pub entries: u64, // in range plus under/overflow
+    pub integral: u64,
+    pub mean: (f64, f64), // x, y
+    pub rms: (f64, f64),
+    pub underflow: (u64, u64), // x, y
+    pub overflow: (u64, u64),
+    pub window_integral: Option<u64>,
+}
+
+// Export of the summary statistics of every histogram in the tree, from the Histogrammer menu
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+pub struct SummaryStats {
+    pub use_window: bool,
+    pub window: (f64, f64), // x range of 1D histograms, calibrated units when the calibration is active
+}
+
+impl SummaryStats {
+    pub fn row(&self, pane: &Pane) -> StatsRow {
+        match pane {
+            Pane::Histogram(hist) => {
+                let hist = hist.lock().unwrap();
+                let calibration = &hist.plot_settings.calibration;
+                let x = |index: usize| {
+                    let center = hist.bin_center(index);
+                    if calibration.active {
+                        calibration.calibrate(center)
+                    } else {
+                        center
+                    }
+                };
+
+                let integral: u64 = hist.bins.iter().sum();
+                let mean = if integral == 0 {
+                    0.0
+                } else {
+                    hist.bins
+                        .iter()
+                        .enumerate()
+                        .map(|(index, &count)| count as f64 * x(index))
+                        .sum::<f64>()
+                        / integral as f64
+                };
+                let rms = if integral == 0 {
+                    0.0
+                } else {
+                    (hist
+                        .bins
+                        .iter()
+                        .enumerate()
+                        .map(|(index, &count)| count as f64 * (x(index) - mean).powi(2))
+                        .sum::<f64>()
+                        / integral as f64)
+                        .sqrt()
+                };
+
+                let (low, high) = (
+                    self.window.0.min(self.window.1),
+                    self.window.0.max(self.window.1),
+                );
+                let window_integral = self.use_window.then(|| {
+                    hist.bins
+                        .iter()
+                        .enumerate()
+                        .filter(|(index, _)| (low..=high).contains(&x(*index)))
+                        .map(|(_, &count)| count)
+                        .sum()
+                });
+
+                StatsRow {
+                    name: hist.name.clone(),
+                    kind: "1D",
+                    entries: integral + hist.underflow + hist.overflow,
+                    integral,
+                    mean: (mean, f64::NAN),
+                    rms: (rms, f64::NAN),
+                    underflow: (hist.underflow, 0),
+                    overflow: (hist.overflow, 0),
+                    window_integral,
+                }
+            }
+            Pane::Histogram2D(hist) => {
+                let hist = hist.lock().unwrap();
+                let (integral, mean_x, rms_x, mean_y, rms_y) = hist.get_statistics(
+                    hist.range.x.min,
+                    hist.range.x.max,
+                    hist.range.y.min,
+                    hist.range.y.max,
+                );
+                StatsRow {
+                    name: hist.name.clone(),
+                    kind: "2D",
+                    entries: integral
+                        + hist.underflow.0
+                        + hist.underflow.1
+                        + hist.overflow.0
+                        + hist.overflow.1,
+                    integral,
+                    mean: (mean_x, mean_y),
+                    rms: (rms_x, rms_y),
+                    underflow: hist.underflow,
+                    overflow: hist.overflow,
+                    window_integral: None,
+                }
+            }
+            Pane::Histogram3D(hist) => {
+                let hist = hist.lock().unwrap();
+                StatsRow {
+                    name: hist.name.clone(),
+                    kind: "3D",
+                    entries: hist.entries,
+                    integral: hist.counts.values().sum(),
+                    mean: (f64::NAN, f64::NAN),
+                    rms: (f64::NAN, f64::NAN),
+                    underflow: (hist.underflow, 0),
+                    overflow: (hist.overflow, 0),
+                    window_integral: None,
+                }
+            }
+            Pane::HistogramND(hist) => {
+                let hist = hist.lock().unwrap();
+                StatsRow {
+                    name: hist.name.clone(),
+                    kind: "ND",
+                    entries: hist.entries,
+                    integral: hist.counts.values().sum(),
+                    mean: (f64::NAN, f64::NAN),
+                    rms: (f64::NAN, f64::NAN),
+                    underflow: (hist.underflow, 0),
+                    overflow: (hist.overflow, 0),
+                    window_integral: None,
+                }
+            }
+        }
+    }
+
+    pub fn csv(rows: &[StatsRow]) -> String {
+        let number = |v: f64| {
+            if v.is_nan() {
+                String::new()
+            } else {
+                v.to_string()
+            }
+        };
+
+        let mut text = "name,type,entries,integral,mean_x,rms_x,mean_y,rms_y,underflow_x,overflow_x,underflow_y,overflow_y,window_integral\n".to_string();
+        for row in rows {
+            let fields = [
+                format!("\"{}\"", row.name.replace('"', "\"\"")),
+                row.kind.to_string(),
+                row.entries.to_string(),
+                row.integral.to_string(),
+                number(row.mean.0),
+                number(row.rms.0),
+                number(row.mean.1),
+                number(row.rms.1),
+                row.underflow.0.to_string(),
+                row.overflow.0.to_string(),
+                row.underflow.1.to_string(),
+                row.overflow.1.to_string(),
+                row.window_integral
+                    .map_or(String::new(), |integral| integral.to_string()),
+            ];
+            text.push_str(&fields.join(","));
+            text.push('\n');
+        }
+        text
+    }
+
+    fn export(&self, panes: &[&Pane]) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name("histogram_stats.csv")
+            .add_filter("CSV", &["csv"])
+            .save_file()
+        else {
+            return Ok(());
+        };
+
+        let mut rows: Vec<StatsRow> = panes.iter().map(|pane| self.row(pane)).collect();
+        rows.sort_by(|a, b| a.name.cmp(&b.name));
+        std::fs::write(&path, Self::csv(&rows))?;
+
+        log::info!(
+            "Wrote statistics of {} histograms to {}",
+            rows.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    pub fn menu_button(&mut self, ui: &mut egui::Ui, panes: &[&Pane]) {
+        ui.menu_button("Export Statistics", |ui| {
+            ui.label("One CSV row per histogram: entries, mean, RMS, under/overflow");
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.use_window, "Window integral")
+                    .on_hover_text("Counts of 1D histograms between these x values (calibrated when the calibration is active)");
+                ui.add_enabled(
+                    self.use_window,
+                    egui::DragValue::new(&mut self.window.0).speed(1.0),
+                );
+                ui.label("to");
+                ui.add_enabled(
+                    self.use_window,
+                    egui::DragValue::new(&mut self.window.1).speed(1.0),
+                );
+            });
+
+            if ui.button("Export CSV…").clicked() {
+                if let Err(e) = self.export(panes) {
+                    log::error!("Error exporting histogram statistics: {}", e);
+                }
+                ui.close_menu();
+            }
+        });
+    }
+}
@@ -0,0 +1,97 @@
+use std::path::Path;
+
+// Spectra in plain text with one bin per line, either "counts" or "x counts" separated by
+// spaces, tabs, commas, or semicolons, e.g. text exports of RadWare or Maestro spectra.
+// Lines that do not start with a number (headers, comments) are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct TextSpectrum {
+    pub centers: Option<Vec<f64>>, // x of each bin in two-column files
+    pub counts: Vec<f64>,
+}
+
+impl TextSpectrum {
+    pub fn read(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&text)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut rows: Vec<Vec<f64>> = Vec::new();
+        for line in text.lines() {
+            let values: Vec<f64> = line
+                .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+                .filter(|field| !field.is_empty())
+                .map_while(|field| field.parse().ok())
+                .collect();
+            if !values.is_empty() {
+                rows.push(values);
+            }
+        }
+
+        let Some(first) = rows.first() else {
+            return Err("No numeric rows found".to_string());
+        };
+
+        // the columns of the first row decide the layout of the file
+        if first.len() == 1 {
+            return Ok(TextSpectrum {
+                centers: None,
+                counts: rows.iter().map(|row| row[0]).collect(),
+            });
+        }
+
+        if let Some(line) = rows.iter().position(|row| row.len() < 2) {
+            return Err(format!("Data row {} has no counts column", line + 1));
+        }
+        let centers: Vec<f64> = rows.iter().map(|row| row[0]).collect();
+        if centers.windows(2).any(|pair| pair[1] <= pair[0]) {
+            return Err("The x column must be increasing".to_string());
+        }
+
+        Ok(TextSpectrum {
+            centers: Some(centers),
+            counts: rows.iter().map(|row| row[1]).collect(),
+        })
+    }
+
+    // Whether the counts fit in the integer bins of a filled histogram
+    pub fn is_integer(&self) -> bool {
+        self.counts
+            .iter()
+            .all(|&count| count >= 0.0 && count.fract() == 0.0)
+    }
+
+    // Range of equal width bins around the centers, None when the spacing varies.
+    // Without an x column the bins are channels.
+    pub fn range(&self) -> Option<(f64, f64)> {
+        let Some(centers) = &self.centers else {
+            return Some((-0.5, self.counts.len() as f64 - 0.5));
+        };
+
+        let (first, last) = (centers[0], centers[centers.len() - 1]);
+        let width = if centers.len() > 1 {
+            (last - first) / (centers.len() - 1) as f64
+        } else {
+            1.0
+        };
+        let even = centers.iter().enumerate().all(|(index, &center)| {
+            (center - (first + index as f64 * width)).abs() <= 1e-6 * width
+        });
+        even.then_some((first - 0.5 * width, last + 0.5 * width))
+    }
+
+    // Bin edges halfway between the centers, the outer edges as far out as the inner ones
+    pub fn edges(&self) -> Vec<f64> {
+        let Some(centers) = self.centers.as_ref().filter(|centers| centers.len() > 1) else {
+            let (low, high) = self.range().unwrap_or((-0.5, 0.5));
+            return vec![low, high];
+        };
+
+        let n = centers.len();
+        let mut edges = Vec::with_capacity(n + 1);
+        edges.push(centers[0] - 0.5 * (centers[1] - centers[0]));
+        edges.extend(centers.windows(2).map(|pair| 0.5 * (pair[0] + pair[1])));
+        edges.push(centers[n - 1] + 0.5 * (centers[n - 1] - centers[n - 2]));
+        edges
+    }
+}
@@ -0,0 +1,159 @@
+use super::column_script::{compile_script, ColumnScript};
+use super::configs::{Config, Configs};
+
+// Helper for timing spectra: writes the column script of the time difference between two
+// timestamp (or TAC) columns and adds a 1D histogram of it. The difference is
+//
+//     alias = (stop - start) * scale
+//
+// where the scale converts the raw units to ns, e.g. the ns per channel of a TAC. Timestamps of
+// a counter that wraps around are folded into (-period/2, period/2] before scaling, so a stop
+// just after the rollover is still close to its start. Rows where either time is -1e6 stay
+// -1e6 and fall out of the histogram.
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct TimeDifference {
+    pub alias: String,
+    pub start: String,
+    pub stop: String,
+    pub scale: f64,  // ns per unit of the time columns
+    pub wrap: bool,  // fold the difference with the period of the counter
+    pub period: f64, // in the units of the time columns
+    pub range: (f64, f64),
+    pub bins: usize,
+}
+
+impl Default for TimeDifference {
+    fn default() -> Self {
+        TimeDifference {
+            alias: "DeltaT".to_string(),
+            start: "StartTime".to_string(),
+            stop: "StopTime".to_string(),
+            scale: 1.0,
+            wrap: false,
+            period: 2f64.powi(48),
+            range: (-1000.0, 1000.0),
+            bins: 2000,
+        }
+    }
+}
+
+impl TimeDifference {
+    pub fn script(&self) -> String {
+        let mut script = format!(
+            "// {} - {} in ns\nlet raw = ({}) - ({});\n",
+            self.stop, self.start, self.stop, self.start
+        );
+        if self.wrap && self.period > 0.0 {
+            script.push_str(&format!(
+                "let dt = raw - {} * round(raw / {});\n",
+                self.period, self.period
+            ));
+        } else {
+            script.push_str("let dt = raw;\n");
+        }
+        script.push_str(&format!(
+            "if ({}) == -1e6 || ({}) == -1e6 {{ -1e6 }} else {{ dt * {} }}",
+            self.start, self.stop, self.scale
+        ));
+        script
+    }
+
+    // Adds the column script, replacing one with the same alias, and the 1D histogram if it
+    // is not there yet
+    pub fn add_to(&self, configs: &mut Configs) {
+        let script = ColumnScript {
+            alias: self.alias.clone(),
+            script: self.script(),
+        };
+        match configs
+            .column_scripts
+            .iter_mut()
+            .find(|existing| existing.alias == self.alias)
+        {
+            Some(existing) => *existing = script,
+            None => configs.column_scripts.push(script),
+        }
+
+        let name = format!("Timing/{}", self.alias);
+        let exists = configs
+            .configs
+            .iter()
+            .any(|config| matches!(config, Config::Hist1D(hist) if hist.name == name));
+        if !exists {
+            configs.hist1d(&name, &self.alias, self.range, self.bins, None);
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, configs: &mut Configs) {
+        egui::Grid::new("time_difference").show(ui, |ui| {
+            ui.label("Alias:");
+            ui.add(egui::TextEdit::singleline(&mut self.alias).desired_width(150.0));
+            ui.end_row();
+
+            ui.label("Start:");
+            ui.add(egui::TextEdit::singleline(&mut self.start).desired_width(150.0));
+            ui.end_row();
+
+            ui.label("Stop:");
+            ui.add(egui::TextEdit::singleline(&mut self.stop).desired_width(150.0));
+            ui.end_row();
+
+            ui.label("Scale:");
+            ui.add(
+                egui::DragValue::new(&mut self.scale)
+                    .speed(0.001)
+                    .suffix(" ns/unit"),
+            )
+            .on_hover_text("ns per unit of the time columns, e.g. ns per channel of a TAC");
+            ui.end_row();
+
+            ui.checkbox(&mut self.wrap, "Wraparound")
+                .on_hover_text("Fold the difference into ±period/2 for counters that roll over");
+            ui.add_enabled(
+                self.wrap,
+                egui::DragValue::new(&mut self.period)
+                    .range(0.0..=f64::INFINITY)
+                    .prefix("Period: "),
+            );
+            ui.end_row();
+
+            ui.label("Histogram:");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut self.range.0)
+                        .speed(1.0)
+                        .prefix("("),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.range.1)
+                        .speed(1.0)
+                        .suffix(") ns"),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.bins)
+                        .range(1..=usize::MAX)
+                        .suffix(" bins"),
+                );
+            });
+            ui.end_row();
+        });
+
+        let valid = compile_script(&self.script());
+        ui.horizontal(|ui| {
+            let add = ui
+                .add_enabled(
+                    valid.is_ok() && !self.alias.trim().is_empty(),
+                    egui::Button::new("Add"),
+                )
+                .on_hover_text(self.script());
+            if add.clicked() {
+                self.add_to(configs);
+            }
+            if let Err(e) = &valid {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+        });
+    }
+}
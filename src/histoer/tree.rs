@@ -1,5 +1,7 @@
+use super::linked_cursor::link_id;
 use super::pane::Pane;
-use egui_tiles::{Tile, TileId, Tiles};
+use egui_tiles::{Container, Tile, TileId, Tiles};
+use std::collections::{HashMap, HashSet};
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct TreeBehavior {
@@ -8,6 +10,21 @@ pub struct TreeBehavior {
     min_size: f32,
     preview_dragged_panes: bool,
     pub tile_map: std::collections::HashMap<egui_tiles::TileId, String>,
+    #[serde(default)]
+    pub linked_cursors: HashSet<TileId>, // grid containers whose 1D panes share the cursor
+    #[serde(default)]
+    pub linked_axes: HashMap<TileId, AxisLink>, // grid containers whose panes zoom together
+    #[serde(skip)]
+    grid_of_pane: HashMap<TileId, TileId>,
+    #[serde(skip)]
+    pub closed: Vec<TileId>, // tabs closed this frame, collected for undo
+}
+
+// Axes kept the same in all panes of a grid, zooming or panning one pane moves the others
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AxisLink {
+    pub x: bool,
+    pub y: bool,
 }
 
 impl Default for TreeBehavior {
@@ -18,6 +35,10 @@ impl Default for TreeBehavior {
             min_size: 50.0,
             preview_dragged_panes: true,
             tile_map: std::collections::HashMap::new(),
+            linked_cursors: HashSet::new(),
+            linked_axes: HashMap::new(),
+            grid_of_pane: HashMap::new(),
+            closed: Vec::new(),
         }
     }
 }
@@ -66,15 +87,87 @@ impl TreeBehavior {
     pub fn get_tab_name(&self, tile_id: &egui_tiles::TileId) -> Option<&String> {
         self.tile_map.get(tile_id)
     }
+
+    // Grid container of every pane, called before the tree is drawn
+    pub fn update_grids(&mut self, tiles: &Tiles<Pane>) {
+        self.grid_of_pane.clear();
+        for (grid_id, tile) in tiles.iter() {
+            if let Tile::Container(Container::Grid(grid)) = tile {
+                for child in grid.children() {
+                    if let Some(Tile::Pane(_)) = tiles.get(*child) {
+                        self.grid_of_pane.insert(*child, *grid_id);
+                    }
+                }
+            }
+        }
+    }
+
+    fn cursor_link(&self, tile_id: TileId) -> Option<egui::Id> {
+        let grid = self.grid_of_pane.get(&tile_id)?;
+        self.linked_cursors.contains(grid).then(|| link_id(*grid))
+    }
+
+    fn axis_link(&self, tile_id: TileId) -> Option<(egui::Id, egui::Vec2b)> {
+        let grid = self.grid_of_pane.get(&tile_id)?;
+        let link = self.linked_axes.get(grid)?;
+        (link.x || link.y).then(|| {
+            (
+                egui::Id::new(("linked_axes", grid)),
+                egui::Vec2b::new(link.x, link.y),
+            )
+        })
+    }
+
+    // Cursor and axis links of a grid container, shown next to it in the tree
+    pub fn grid_links_ui(&mut self, ui: &mut egui::Ui, grid: TileId) {
+        let mut linked = self.linked_cursors.contains(&grid);
+        if ui
+            .checkbox(&mut linked, "Link Cursors")
+            .on_hover_text("Show the cursor x of the hovered 1D histogram in all of the grid")
+            .changed()
+        {
+            if linked {
+                self.linked_cursors.insert(grid);
+            } else {
+                self.linked_cursors.remove(&grid);
+            }
+        }
+
+        let mut link = self.linked_axes.get(&grid).copied().unwrap_or_default();
+        let x = ui
+            .checkbox(&mut link.x, "Link X")
+            .on_hover_text("Zooming or panning one histogram of the grid sets the x range of all");
+        let y = ui
+            .checkbox(&mut link.y, "Link Y")
+            .on_hover_text("Zooming or panning one histogram of the grid sets the y range of all");
+        if x.changed() || y.changed() {
+            if link == AxisLink::default() {
+                self.linked_axes.remove(&grid);
+            } else {
+                self.linked_axes.insert(grid, link);
+            }
+        }
+    }
 }
 
 impl egui_tiles::Behavior<Pane> for TreeBehavior {
     fn pane_ui(
         &mut self,
         ui: &mut egui::Ui,
-        _tile_id: egui_tiles::TileId,
+        tile_id: egui_tiles::TileId,
         pane: &mut Pane,
     ) -> egui_tiles::UiResponse {
+        match pane {
+            Pane::Histogram(hist) => {
+                let mut hist = hist.lock().unwrap();
+                hist.plot_settings.cursor_link = self.cursor_link(tile_id);
+                hist.plot_settings.axis_link = self.axis_link(tile_id);
+            }
+            Pane::Histogram2D(hist) => {
+                hist.lock().unwrap().plot_settings.axis_link = self.axis_link(tile_id);
+            }
+            _ => {}
+        }
         pane.ui(ui)
     }
 
@@ -82,6 +175,8 @@ impl egui_tiles::Behavior<Pane> for TreeBehavior {
         match pane {
             Pane::Histogram(hist) => hist.lock().unwrap().name.clone().into(),
             Pane::Histogram2D(hist) => hist.lock().unwrap().name.clone().into(),
+            Pane::Histogram3D(hist) => hist.lock().unwrap().name.clone().into(),
+            Pane::HistogramND(hist) => hist.lock().unwrap().name.clone().into(),
         }
     }
 
@@ -122,6 +217,7 @@ impl egui_tiles::Behavior<Pane> for TreeBehavior {
 
     fn on_tab_close(&mut self, tiles: &mut Tiles<Pane>, tile_id: TileId) -> bool {
         tiles.set_visible(tile_id, false);
+        self.closed.push(tile_id);
         if let Some(tile) = tiles.get(tile_id) {
             match tile {
                 Tile::Pane(pane) => {
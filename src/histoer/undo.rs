@@ -0,0 +1,235 @@
+use egui_tiles::TileId;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use super::cuts::Cut2D;
+use super::histogrammer::Histogrammer;
+use super::pane::Pane;
+use crate::fitter::main_fitter::Fitter;
+
+// Destructive actions of the histogrammer that can be undone. Each action keeps what was
+// removed, undoing puts it back and redoing removes it again.
+
+const MAX_ACTIONS: usize = 50;
+
+pub enum UndoAction {
+    ClosePane(TileId),
+    RemoveFit {
+        histogram: String,
+        index: usize,
+        fit: Box<Fitter>,
+    },
+    RemoveCut {
+        histogram: String,
+        index: usize,
+        cut: Arc<Mutex<Cut2D>>,
+    },
+    // the workspace on the other side of the reset, swapped in by undo and redo
+    Reset(Box<Histogrammer>),
+}
+
+impl UndoAction {
+    pub fn description(&self) -> String {
+        match self {
+            UndoAction::ClosePane(_) => "Close Pane".to_string(),
+            UndoAction::RemoveFit { histogram, .. } => format!("Remove Fit of '{}'", histogram),
+            UndoAction::RemoveCut { histogram, cut, .. } => {
+                format!(
+                    "Remove Cut '{}' of '{}'",
+                    cut.lock().unwrap().polygon.name,
+                    histogram
+                )
+            }
+            UndoAction::Reset(_) => "Reset Workspace".to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct UndoStack {
+    undo: VecDeque<UndoAction>,
+    redo: Vec<UndoAction>,
+}
+
+impl UndoStack {
+    pub fn push(&mut self, action: UndoAction) {
+        self.redo.clear();
+        self.undo.push_back(action);
+        if self.undo.len() > MAX_ACTIONS {
+            self.undo.pop_front();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.undo.is_empty() && self.redo.is_empty()
+    }
+}
+
+impl Histogrammer {
+    // Clears the workspace, the previous one is kept for undo
+    pub fn reset_workspace(&mut self) {
+        let mut previous = std::mem::take(self);
+        std::mem::swap(&mut self.undo, &mut previous.undo);
+        self.undo.push(UndoAction::Reset(Box::new(previous)));
+    }
+
+    // Collects what the panes and tabs removed this frame
+    pub fn record_removals(&mut self) {
+        for tile_id in self.behavior.closed.drain(..) {
+            self.undo.push(UndoAction::ClosePane(tile_id));
+        }
+
+        for (_id, tile) in self.tree.tiles.iter() {
+            match tile {
+                egui_tiles::Tile::Pane(Pane::Histogram(hist)) => {
+                    let mut hist = hist.lock().unwrap();
+                    let histogram = hist.name.clone();
+                    for (index, fit) in hist.fits.removed.drain(..) {
+                        self.undo.push(UndoAction::RemoveFit {
+                            histogram: histogram.clone(),
+                            index,
+                            fit: Box::new(fit),
+                        });
+                    }
+                }
+                egui_tiles::Tile::Pane(Pane::Histogram2D(hist)) => {
+                    let mut hist = hist.lock().unwrap();
+                    let histogram = hist.name.clone();
+                    for (index, cut) in hist.plot_settings.removed_cuts.drain(..) {
+                        self.undo.push(UndoAction::RemoveCut {
+                            histogram: histogram.clone(),
+                            index,
+                            cut,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(action) = self.undo.undo.pop_back() {
+            let action = self.apply_undo(action, true);
+            self.undo.redo.push(action);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(action) = self.undo.redo.pop() {
+            let action = self.apply_undo(action, false);
+            self.undo.undo.push_back(action);
+        }
+    }
+
+    // Puts back (undo) or removes again (redo) what the action removed
+    fn apply_undo(&mut self, action: UndoAction, undo: bool) -> UndoAction {
+        match action {
+            UndoAction::ClosePane(tile_id) => {
+                self.tree.tiles.set_visible(tile_id, undo);
+                UndoAction::ClosePane(tile_id)
+            }
+            UndoAction::RemoveFit {
+                histogram,
+                index,
+                fit,
+            } => {
+                if let Some(Pane::Histogram(hist)) = self.find_pane(&histogram) {
+                    let fits = &mut hist.lock().unwrap().fits.stored_fits;
+                    if undo {
+                        fits.insert(index.min(fits.len()), (*fit).clone());
+                    } else if index < fits.len() {
+                        fits.remove(index);
+                    }
+                } else {
+                    log::error!("Histogram '{}' is no longer in the tree", histogram);
+                }
+                UndoAction::RemoveFit {
+                    histogram,
+                    index,
+                    fit,
+                }
+            }
+            UndoAction::RemoveCut {
+                histogram,
+                index,
+                cut,
+            } => {
+                if let Some(Pane::Histogram2D(hist)) = self.find_pane(&histogram) {
+                    let mut hist = hist.lock().unwrap();
+                    let cuts = &mut hist.plot_settings.cuts;
+                    if undo {
+                        cuts.insert(index.min(cuts.len()), Arc::clone(&cut));
+                    } else {
+                        cuts.retain(|c| !Arc::ptr_eq(c, &cut));
+                    }
+                    hist.plot_settings.recalculate_image = true;
+                } else {
+                    log::error!("Histogram '{}' is no longer in the tree", histogram);
+                }
+                UndoAction::RemoveCut {
+                    histogram,
+                    index,
+                    cut,
+                }
+            }
+            UndoAction::Reset(mut other) => {
+                std::mem::swap(self, &mut other);
+                std::mem::swap(&mut self.undo, &mut other.undo);
+                UndoAction::Reset(other)
+            }
+        }
+    }
+
+    fn find_pane(&self, name: &str) -> Option<Pane> {
+        let tile_id = self.find_existing_histogram(name)?;
+        match self.tree.tiles.get(tile_id) {
+            Some(egui_tiles::Tile::Pane(pane)) => Some(pane.clone()),
+            _ => None,
+        }
+    }
+
+    // Undo and Redo buttons with the action they act on
+    pub fn undo_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let undo = self.undo.undo.back().map(|action| action.description());
+            if ui
+                .add_enabled(undo.is_some(), egui::Button::new("⟲ Undo"))
+                .on_hover_text(undo.unwrap_or_default() + " (Ctrl+Z)")
+                .clicked()
+            {
+                self.undo();
+            }
+
+            let redo = self.undo.redo.last().map(|action| action.description());
+            if ui
+                .add_enabled(redo.is_some(), egui::Button::new("⟳ Redo"))
+                .on_hover_text(redo.unwrap_or_default() + " (Ctrl+Shift+Z)")
+                .clicked()
+            {
+                self.redo();
+            }
+        });
+    }
+
+    // Ctrl+Z and Ctrl+Shift+Z (or Ctrl+Y), unless a text field has the keyboard
+    pub fn undo_keybinds(&mut self, ctx: &egui::Context) {
+        if self.undo.is_empty() || ctx.wants_keyboard_input() {
+            return;
+        }
+
+        let (undo, redo) = ctx.input_mut(|i| {
+            let redo = i.consume_key(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::Z,
+            ) || i.consume_key(egui::Modifiers::COMMAND, egui::Key::Y);
+            let undo = i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z);
+            (undo, redo)
+        });
+        if redo {
+            self.redo();
+        } else if undo {
+            self.undo();
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use regex::{Captures, Regex};
+
+use super::configs::{Config, Configs};
+use super::cuts::{Cut, Cuts};
+
+// Named constants of the workspace, e.g. beam_energy = 16.0 or gain_12 = 0.9873. A variable
+// can be used by name in column expressions, column scripts, and 1D cut expressions, and as
+// {name} in histogram names. The values are put in when the histograms are filled, so a
+// variable with the same name as a column hides the column.
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub value: f64,
+}
+
+// Replaces the variable names in an expression with their values
+pub fn substitute(expression: &str, variables: &[Variable]) -> String {
+    if variables.is_empty() {
+        return expression.to_string();
+    }
+
+    let identifier = Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").unwrap();
+    identifier
+        .replace_all(expression, |caps: &Captures<'_>| {
+            match variables.iter().find(|variable| variable.name == caps[0]) {
+                // f64 Display never uses an exponent, which every parser reads
+                Some(variable) => format!(" {} ", variable.value),
+                None => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+// Replaces {name} in a histogram name with the value of the variable
+pub fn substitute_name(name: &str, variables: &[Variable]) -> String {
+    let placeholder = Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+    placeholder
+        .replace_all(name, |caps: &Captures<'_>| {
+            match variables.iter().find(|variable| variable.name == caps[1]) {
+                Some(variable) => variable.value.to_string(),
+                None => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+fn substitute_cuts(cuts: &mut Cuts, variables: &[Variable]) {
+    for cut in &mut cuts.cuts {
+        if let Cut::Cut1D(cut1d) = cut {
+            cut1d.expression = substitute(&cut1d.expression, variables);
+        }
+    }
+}
+
+impl Configs {
+    // The configs with the values of the variables put in, used for filling
+    pub fn with_variables(&self) -> Configs {
+        let mut configs = self.clone();
+        let variables = &self.variables;
+        if variables.is_empty() {
+            return configs;
+        }
+
+        for (expression, _) in &mut configs.columns {
+            *expression = substitute(expression, variables);
+        }
+        for script in &mut configs.column_scripts {
+            script.script = substitute(&script.script, variables);
+        }
+        for table in &mut configs.calibration_tables {
+            table.channel = substitute(&table.channel, variables);
+            table.value = substitute(&table.value, variables);
+        }
+        substitute_cuts(&mut configs.cuts, variables);
+
+        for config in &mut configs.configs {
+            let (name, cuts) = match config {
+                Config::Hist1D(config) => (&mut config.name, &mut config.cuts),
+                Config::Hist2D(config) => (&mut config.name, &mut config.cuts),
+                Config::Hist3D(config) => (&mut config.name, &mut config.cuts),
+                Config::HistND(config) => (&mut config.name, &mut config.cuts),
+            };
+            *name = substitute_name(name, variables);
+            substitute_cuts(cuts, variables);
+        }
+
+        configs
+    }
+
+    // Variables panel. `filled` are the variables of the last fill, a change to them marks
+    // the histograms as out of date. Returns true when a refill was asked for.
+    pub fn variables_ui(&mut self, ui: &mut egui::Ui, filled: Option<&[Variable]>) -> bool {
+        let mut index_to_remove = None;
+        egui::Grid::new("workspace_variables")
+            .striped(true)
+            .show(ui, |ui| {
+                for (index, variable) in self.variables.iter_mut().enumerate() {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut variable.name)
+                            .hint_text("Name")
+                            .desired_width(100.0),
+                    );
+                    ui.add(egui::DragValue::new(&mut variable.value).speed(0.01));
+                    if ui.button("X").clicked() {
+                        index_to_remove = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(index) = index_to_remove {
+            self.variables.remove(index);
+        }
+
+        if ui
+            .button("+")
+            .on_hover_text("Use a variable by name in column expressions, column scripts, and 1D cuts, and as {name} in histogram names")
+            .clicked()
+        {
+            self.variables.push(Variable::default());
+        }
+
+        let changed = filled.is_some_and(|filled| filled != self.variables.as_slice());
+        if changed {
+            ui.horizontal(|ui| {
+                ui.colored_label(
+                    egui::Color32::ORANGE,
+                    "Variables changed since the last fill",
+                );
+                ui.button("Refill").clicked()
+            })
+            .inner
+        } else {
+            false
+        }
+    }
+}
@@ -1,11 +1,13 @@
 use crate::histoer::{
     configs::{Config, Configs},
     cuts::{Cut, Cuts},
+    excitation_energy::ExcitationEnergy,
 };
 use egui_extras::{Column, TableBuilder};
 use std::f64::consts::PI;
 
 #[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct CustomConfigs {
     pub sps: SPSConfig,
     pub cebra: CeBrAConfig,
@@ -95,6 +97,7 @@ impl CustomConfigs {
                 active: true,
             },
             cuts: Cuts::default(),
+            excitation: ExcitationEnergy::default(),
         };
 
         self.cebra.active = true;
@@ -324,6 +327,7 @@ impl Cebr3 {
 }
 
 #[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct CeBrAConfig {
     pub active: bool,
     pub detectors: Vec<Cebr3>,
@@ -713,6 +717,45 @@ impl CeBrAConfig {
                             );
                         }
                     }
+                    Config::Hist3D(hist) => {
+                        let mut cuts = hist.cuts.clone();
+
+                        updated_configs.hist3d(
+                            &format!("No Cuts/{}", hist.name),
+                            &hist.x_column_name,
+                            &hist.y_column_name,
+                            &hist.z_column_name,
+                            hist.ranges,
+                            hist.bins,
+                            Some(cuts.clone()),
+                        );
+
+                        cuts.merge(&sps_cuts.clone());
+
+                        if !sps_cuts.is_empty() {
+                            updated_configs.hist3d(
+                                &format!("Cuts/{}", hist.name),
+                                &hist.x_column_name,
+                                &hist.y_column_name,
+                                &hist.z_column_name,
+                                hist.ranges,
+                                hist.bins,
+                                Some(cuts.clone()),
+                            );
+                        }
+                    }
+                    Config::HistND(hist) => {
+                        let mut no_cuts = hist.clone();
+                        no_cuts.name = format!("No Cuts/{}", hist.name);
+                        updated_configs.configs.push(Config::HistND(no_cuts));
+
+                        if !sps_cuts.is_empty() {
+                            let mut with_cuts = hist.clone();
+                            with_cuts.name = format!("Cuts/{}", hist.name);
+                            with_cuts.cuts.merge(&sps_cuts.clone());
+                            updated_configs.configs.push(Config::HistND(with_cuts));
+                        }
+                    }
                 }
             }
 
@@ -811,10 +854,13 @@ impl CeBrAConfig {
 }
 /*************************** SE-SPS Custom Struct ***************************/
 #[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct SPSConfig {
     active: bool,
     xavg: Calibration,
     cuts: Cuts,
+    #[serde(default)]
+    excitation: ExcitationEnergy,
 }
 
 impl Default for SPSConfig {
@@ -830,6 +876,7 @@ impl Default for SPSConfig {
                 active: false,
             },
             cuts: Cuts::default(),
+            excitation: ExcitationEnergy::default(),
         }
     }
 }
@@ -850,6 +897,9 @@ impl SPSConfig {
         });
         ui.separator();
 
+        self.excitation.ui(ui);
+        ui.separator();
+
         self.cuts.ui(ui);
     }
 
@@ -902,6 +952,9 @@ impl SPSConfig {
         configs.hist1d("SE-SPS/Focal Plane/X1", "X1", fp_range, fp_bins, None);
         configs.hist1d("SE-SPS/Focal Plane/X2", "X2", fp_range, fp_bins, None);
         configs.hist1d("SE-SPS/Focal Plane/Xavg", "Xavg", fp_range, fp_bins, None);
+        if self.excitation.active {
+            self.excitation.add_to(&mut configs);
+        }
         if self.xavg.active {
             configs.hist1d("SE-SPS/Focal Plane/Xavg Energy Calibrated", "XavgEnergyCalibrated", self.xavg.range, self.xavg.bins, None);
         }
@@ -1074,10 +1127,50 @@ impl SPSConfig {
                         );
                     }
                 }
+                Config::Hist3D(hist) => {
+                    let mut cuts = hist.cuts.clone();
+
+                    updated_configs.hist3d(
+                        &format!("No Cuts/{}", hist.name),
+                        &hist.x_column_name,
+                        &hist.y_column_name,
+                        &hist.z_column_name,
+                        hist.ranges,
+                        hist.bins,
+                        Some(cuts.clone()),
+                    );
+
+                    cuts.merge(&active_cuts.clone());
+
+                    if !active_cuts.is_empty() {
+                        updated_configs.hist3d(
+                            &format!("Cuts/{}", hist.name),
+                            &hist.x_column_name,
+                            &hist.y_column_name,
+                            &hist.z_column_name,
+                            hist.ranges,
+                            hist.bins,
+                            Some(cuts.clone()),
+                        );
+                    }
+                }
+                Config::HistND(hist) => {
+                    let mut no_cuts = hist.clone();
+                    no_cuts.name = format!("No Cuts/{}", hist.name);
+                    updated_configs.configs.push(Config::HistND(no_cuts));
+
+                    if !active_cuts.is_empty() {
+                        let mut with_cuts = hist.clone();
+                        with_cuts.name = format!("Cuts/{}", hist.name);
+                        with_cuts.cuts.merge(&active_cuts.clone());
+                        updated_configs.configs.push(Config::HistND(with_cuts));
+                    }
+                }
             }
         }
 
         updated_configs.columns = original_configs.columns.clone();
+        updated_configs.column_scripts = original_configs.column_scripts.clone();
 
         updated_configs.cuts = original_configs.cuts.clone();
         updated_configs.cuts.merge(&active_cuts);
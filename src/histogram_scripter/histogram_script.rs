@@ -1,12 +1,34 @@
 use super::custom_scripts::CustomConfigs;
 
 use crate::histoer::configs::Configs;
+use crate::histoer::cut_stats::CutStats;
+use crate::histoer::cuts::Cut;
 use crate::histoer::histogrammer::Histogrammer;
+use crate::histoer::memory_budget::MemoryBudget;
+use crate::histoer::time_difference::TimeDifference;
+use crate::histoer::variables::Variable;
 use polars::prelude::*;
+
+use std::fs::File;
+use std::io::{BufReader, Write};
+
 #[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct HistogramScript {
+    #[serde(default)]
     pub configs: Configs,
+    #[serde(default)]
     pub custom_scripts: CustomConfigs,
+    #[serde(default)]
+    pub time_difference: TimeDifference, // last settings of the timing helper
+    #[serde(skip)]
+    pub filled_variables: Option<Vec<Variable>>, // variables of the last fill
+    #[serde(skip)]
+    pub refill_requested: bool, // set by the UI, the processor refills the histograms
+    #[serde(skip)]
+    pub filled_cuts: Option<Vec<Cut>>, // workspace cuts of the last fill
+    #[serde(skip)]
+    pub refill_cuts_requested: bool, // set by the UI, the processor refills the affected histograms
 }
 
 impl HistogramScript {
@@ -14,19 +36,120 @@ impl HistogramScript {
         Self {
             configs: Configs::default(),
             custom_scripts: CustomConfigs::default(),
+            time_difference: TimeDifference::default(),
+            filled_variables: None,
+            refill_requested: false,
+            filled_cuts: None,
+            refill_cuts_requested: false,
+        }
+    }
+
+    // JSON or YAML by the extension chosen in the dialog, YAML is easier to read and diff
+    pub fn save_to_file(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(file_path) = rfd::FileDialog::new()
+            .set_file_name("histogram_script.yaml")
+            .add_filter("YAML Files", &["yaml", "yml"])
+            .add_filter("JSON Files", &["json"])
+            .save_file()
+        {
+            let serialized = if is_yaml(&file_path) {
+                serde_yaml::to_string(self)?
+            } else {
+                serde_json::to_string_pretty(self)?
+            };
+            let mut file = File::create(file_path)?;
+            file.write_all(serialized.as_bytes())?;
         }
+        Ok(())
     }
 
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Histogram Script");
+    pub fn load_from_file(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(file_path) = rfd::FileDialog::new()
+            .add_filter("Histogram Scripts", &["json", "yaml", "yml"])
+            .pick_file()
+        {
+            *self = Self::from_file(&file_path)?;
+        }
+        Ok(())
+    }
+
+    pub fn from_file(path: &std::path::Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let reader = BufReader::new(File::open(path)?);
+        if is_yaml(path) {
+            Ok(serde_yaml::from_reader(reader)?)
+        } else {
+            Ok(serde_json::from_reader(reader)?)
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, cut_stats: &CutStats) {
+        ui.horizontal(|ui| {
+            ui.heading("Histogram Script");
+
+            if ui
+                .button("Save")
+                .on_hover_text(
+                    "Save the script as YAML or JSON, e.g. to keep it in git or for use with `spectrix --batch`",
+                )
+                .clicked()
+            {
+                if let Err(e) = self.save_to_file() {
+                    log::error!("Error saving histogram script: {:?}", e);
+                }
+            }
+
+            if ui.button("Load").clicked() {
+                if let Err(e) = self.load_from_file() {
+                    log::error!("Error loading histogram script: {:?}", e);
+                }
+            }
+        });
 
         ui.separator();
 
+        let affected = self.affected_histograms();
+        if !affected.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                ui.colored_label(egui::Color32::ORANGE, "Cuts changed since the last fill");
+                if ui
+                    .button(format!(
+                        "Refill {} affected histogram{}",
+                        affected.len(),
+                        if affected.len() == 1 { "" } else { "s" }
+                    ))
+                    .on_hover_text(affected.join("\n"))
+                    .clicked()
+                {
+                    self.refill_cuts_requested = true;
+                }
+            });
+            ui.separator();
+        }
+
         egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::CollapsingHeader::new("Variables")
+                .default_open(false)
+                .show(ui, |ui| {
+                    if self
+                        .configs
+                        .variables_ui(ui, self.filled_variables.as_deref())
+                    {
+                        self.refill_requested = true;
+                    }
+                });
+
+            egui::CollapsingHeader::new("Time Differences")
+                .default_open(false)
+                .show(ui, |ui| {
+                    self.time_difference.ui(ui, &mut self.configs);
+                })
+                .header_response
+                .on_hover_text("Add a Δt column between two time columns and its 1D histogram");
+
             egui::CollapsingHeader::new("General")
                 .default_open(false)
                 .show(ui, |ui| {
-                    self.configs.ui(ui);
+                    self.configs.ui(ui, cut_stats);
                 });
 
             ui.separator();
@@ -35,13 +158,80 @@ impl HistogramScript {
         });
     }
 
-    pub fn add_histograms(&mut self, h: &mut Histogrammer, lf: LazyFrame, estimated_memory: f64) {
+    pub fn add_histograms(
+        &mut self,
+        h: &mut Histogrammer,
+        lf: LazyFrame,
+        estimated_memory: f64,
+        memory: &MemoryBudget,
+        sample: Option<f64>,
+    ) -> Result<(), String> {
         let active_custom_configs = self.custom_scripts.merge_active_configs();
 
         let mut cloned_configs = self.configs.clone();
         cloned_configs.merge(active_custom_configs);
-        let merged_configs = cloned_configs;
+        let merged_configs = cloned_configs.with_variables();
+        self.filled_variables = Some(self.configs.variables.clone());
+        self.filled_cuts = Some(self.configs.cuts.cuts.clone());
+
+        h.fill_histograms(
+            merged_configs.clone(),
+            &lf,
+            estimated_memory,
+            memory,
+            sample,
+            false,
+        )
+    }
+
+    // Histograms that use a workspace cut changed since the last fill
+    pub fn affected_histograms(&self) -> Vec<String> {
+        let Some(filled) = &self.filled_cuts else {
+            return Vec::new();
+        };
+        let changed = self.configs.changed_cuts(filled);
+        if changed.is_empty() {
+            return Vec::new();
+        }
+        self.configs.affected_by(&changed)
+    }
+
+    // Refills only the histograms of the changed cuts, the other panes keep their counts
+    pub fn refill_affected_histograms(
+        &mut self,
+        h: &mut Histogrammer,
+        lf: LazyFrame,
+        estimated_memory: f64,
+        memory: &MemoryBudget,
+    ) -> Result<(), String> {
+        let affected = self.affected_histograms();
+        if affected.is_empty() {
+            return Ok(());
+        }
+        log::info!(
+            "Refilling {} histograms affected by changed cuts",
+            affected.len()
+        );
+
+        let mut cloned_configs = self.configs.clone();
+        cloned_configs.merge(self.custom_scripts.merge_active_configs());
+        let configs = cloned_configs.only(&affected).with_variables();
+        self.filled_cuts = Some(self.configs.cuts.cuts.clone());
 
-        h.fill_histograms(merged_configs.clone(), &lf, estimated_memory);
+        h.fill_histograms(configs, &lf, estimated_memory, memory, None, true)
     }
+
+    // Adds rows to the existing histograms instead of refilling them
+    pub fn append_rows(&mut self, h: &mut Histogrammer, df: DataFrame) {
+        let mut configs = self.configs.clone();
+        configs.merge(self.custom_scripts.merge_active_configs());
+        h.append_rows(configs.with_variables(), df);
+    }
+}
+
+fn is_yaml(path: &std::path::Path) -> bool {
+    matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("yaml" | "yml")
+    )
 }
@@ -5,6 +5,45 @@ use spectrix::ui::Spectrix;
 fn main() -> eframe::Result {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`). windows: $env:RUST_LOG="info"; cargo run
 
+    // `spectrix --batch config.toml` fills and writes the histograms without opening a window
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--batch") {
+        let Some(config) = args.get(2) else {
+            eprintln!("Usage: spectrix --batch <config.toml>");
+            std::process::exit(2);
+        };
+
+        if let Err(e) = spectrix::util::batch::run(std::path::Path::new(config)) {
+            eprintln!("Batch run failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // `spectrix inspect run.parquet` prints a summary of the files to the terminal
+    if args.get(1).map(String::as_str) == Some("inspect") {
+        if let Err(e) = spectrix::util::inspect::run(&args[2..]) {
+            eprintln!("Inspect failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // `spectrix run_1.parquet run_2.parquet` (or opening files with spectrix) starts with the files selected
+    let files: Vec<std::path::PathBuf> = args
+        .iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(std::path::PathBuf::from)
+        .filter(|path| {
+            let exists = path.is_file();
+            if !exists {
+                log::error!("File not found: {}", path.display());
+            }
+            exists
+        })
+        .collect();
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([800.0, 600.0])
@@ -19,6 +58,6 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "Spectrix",
         native_options,
-        Box::new(|cc| Ok(Box::new(Spectrix::new(cc)))),
+        Box::new(|cc| Ok(Box::new(Spectrix::new(cc, files)))),
     )
 }
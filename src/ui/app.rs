@@ -1,3 +1,4 @@
+use super::session;
 use crate::util::processer::Processor;
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)]
@@ -14,15 +15,26 @@ impl Default for Spectrix {
 }
 
 impl Spectrix {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+    pub fn new(cc: &eframe::CreationContext<'_>, files: Vec<std::path::PathBuf>) -> Self {
+        let mut app: Self = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+            .unwrap_or_default();
+
+        // files given on the command line replace the restored file list
+        if !files.is_empty() {
+            app.processor.selected_files = files;
         }
-        Default::default()
+
+        app
     }
 
+    // The histograms of the old workspace can be brought back with undo
     pub fn reset_to_default(&mut self) {
+        let histogrammer = std::mem::take(&mut self.processor.histogrammer);
         *self = Default::default();
+        self.processor.histogrammer = histogrammer;
+        self.processor.histogrammer.reset_workspace();
     }
 }
 
@@ -41,6 +53,8 @@ impl eframe::App for Spectrix {
 
                 ui.separator();
 
+                session::session_menu(ui, &mut self.processor);
+
                 self.processor.histogrammer.menu_ui(ui);
 
                 ui.add_space(ui.available_width() - 50.0);
@@ -1,2 +1,3 @@
 mod app;
+pub mod session;
 pub use app::Spectrix;
@@ -0,0 +1,97 @@
+use crate::util::processer::Processor;
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+// Sessions hold the full processor state: panes with their bin contents, cuts,
+// fits, and the histogram script. RON is used since it is also what eframe
+// persists the app state with, so everything that survives a restart also
+// survives a session file.
+//
+// The version is raised when the saved state changes. Version 2 added settings that
+// version 1 sessions do not have, the structs of the session are #[serde(default)] so
+// those are filled in with their defaults when an older session is opened.
+
+const SESSION_VERSION: u32 = 2;
+
+#[derive(serde::Serialize)]
+struct SessionRef<'a> {
+    version: u32,
+    processor: &'a Processor,
+}
+
+#[derive(serde::Deserialize)]
+struct Session {
+    #[serde(default = "default_version")]
+    version: u32,
+    processor: Processor,
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+pub fn save_session(processor: &Processor, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let writer = BufWriter::new(File::create(path)?);
+    ron::ser::to_writer(
+        writer,
+        &SessionRef {
+            version: SESSION_VERSION,
+            processor,
+        },
+    )?;
+    Ok(())
+}
+
+pub fn load_session(path: &Path) -> Result<Processor, Box<dyn std::error::Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    let session: Session = ron::de::from_reader(reader)?;
+
+    if session.version > SESSION_VERSION {
+        return Err(format!(
+            "Session version {} is newer than the supported version {}",
+            session.version, SESSION_VERSION
+        )
+        .into());
+    }
+
+    let mut processor = session.processor;
+    processor.file_dialog = Processor::new().file_dialog; // the file filters are not saved
+    Ok(processor)
+}
+
+pub fn session_menu(ui: &mut egui::Ui, processor: &mut Processor) {
+    ui.menu_button("Session", |ui| {
+        if ui
+            .button("Save Session As…")
+            .on_hover_text("Save the panes, bin contents, cuts, fits, and histogram script")
+            .clicked()
+        {
+            if let Some(path) = rfd::FileDialog::new()
+                .set_file_name("session.spectrix")
+                .add_filter("Spectrix Session", &["spectrix"])
+                .save_file()
+            {
+                match save_session(processor, &path) {
+                    Ok(_) => log::info!("Session saved to {}", path.display()),
+                    Err(e) => log::error!("Error saving session: {}", e),
+                }
+            }
+            ui.close_menu();
+        }
+
+        if ui.button("Open Session…").clicked() {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("Spectrix Session", &["spectrix"])
+                .pick_file()
+            {
+                match load_session(&path) {
+                    Ok(loaded) => *processor = loaded,
+                    Err(e) => log::error!("Error opening session: {}", e),
+                }
+            }
+            ui.close_menu();
+        }
+    });
+}
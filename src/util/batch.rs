@@ -0,0 +1,266 @@
+use crate::histoer::fill_progress::FillProgress;
+use crate::histoer::histogrammer::Histogrammer;
+use crate::histoer::pane::Pane;
+use crate::histoer::root_writer::WriteProgress;
+use crate::histogram_scripter::histogram_script::HistogramScript;
+use crate::util::coincidence::CoincidenceSettings;
+use crate::util::csv_source::CsvSettings;
+use crate::util::processer::Processor;
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+// Headless batch mode: `spectrix --batch config.toml`
+//
+// inputs = ["/data/run_1.parquet", "/data/run_2.parquet"]
+// script = "histogram_script.yaml"  # saved from the Histogram Script panel (YAML or JSON)
+// estimated_memory = 4.0            # chunk size in GB, sized from the free RAM when left out
+// spill_dir = "/scratch"            # write the columns to disk first and fill from there
+//
+// [csv_input]                       # delimiter and header of .csv/.tsv inputs
+// delimiter = "Tab"
+// has_header = false
+//
+// [coincidences]                    # build events from hits, see Coincidences in the UI
+// window = 200.0
+// output = "Pairs"
+//
+// [outputs]
+// root = "histograms.root"
+// json = "histograms.json"
+// csv = "csv_dir"
+//
+// Configs with a .yaml or .yml extension are read as YAML with the same keys. The run exits
+// with 1 and writes no outputs when the histograms could not be filled.
+
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct BatchOutputs {
+    pub root: Option<PathBuf>,
+    pub json: Option<PathBuf>,
+    pub csv: Option<PathBuf>, // directory, one file per histogram
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct BatchConfig {
+    pub inputs: Vec<PathBuf>,
+    pub script: Option<PathBuf>, // not needed when only reading ROOT histograms
+    #[serde(default)]
+    pub estimated_memory: Option<f64>,
+    #[serde(default)]
+    pub spill_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub csv_input: CsvSettings,
+    #[serde(default)]
+    pub coincidences: Option<CoincidenceSettings>,
+    #[serde(default)]
+    pub outputs: BatchOutputs,
+}
+
+impl BatchConfig {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config: BatchConfig = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_reader(BufReader::new(File::open(path)?))?,
+            _ => {
+                let document: toml_edit::DocumentMut = std::fs::read_to_string(path)?.parse()?;
+                serde_json::from_value(toml_table(document.as_table()))?
+            }
+        };
+
+        // relative paths are taken relative to the config file
+        let base = path.parent().unwrap_or(Path::new("."));
+        let resolve = |p: &mut PathBuf| {
+            if p.is_relative() {
+                *p = base.join(&*p);
+            }
+        };
+        config.inputs.iter_mut().for_each(resolve);
+        config.script.iter_mut().for_each(resolve);
+        config.spill_dir.iter_mut().for_each(resolve);
+        config.outputs.root.iter_mut().for_each(resolve);
+        config.outputs.json.iter_mut().for_each(resolve);
+        config.outputs.csv.iter_mut().for_each(resolve);
+
+        Ok(config)
+    }
+}
+
+// TOML values as JSON, so the config is deserialized the same way whatever its format
+fn toml_table<'a>(
+    entries: impl IntoIterator<Item = (&'a str, &'a toml_edit::Item)>,
+) -> serde_json::Value {
+    serde_json::Value::Object(
+        entries
+            .into_iter()
+            .map(|(key, item)| (key.to_string(), toml_item(item)))
+            .collect(),
+    )
+}
+
+fn toml_item(item: &toml_edit::Item) -> serde_json::Value {
+    match item {
+        toml_edit::Item::None => serde_json::Value::Null,
+        toml_edit::Item::Value(value) => toml_value(value),
+        toml_edit::Item::Table(table) => toml_table(table.iter()),
+        toml_edit::Item::ArrayOfTables(tables) => tables
+            .iter()
+            .map(|table| toml_table(table.iter()))
+            .collect(),
+    }
+}
+
+fn toml_value(value: &toml_edit::Value) -> serde_json::Value {
+    match value {
+        toml_edit::Value::String(s) => serde_json::Value::from(s.value().as_str()),
+        toml_edit::Value::Integer(i) => serde_json::Value::from(*i.value()),
+        toml_edit::Value::Float(f) => serde_json::Value::from(*f.value()),
+        toml_edit::Value::Boolean(b) => serde_json::Value::from(*b.value()),
+        toml_edit::Value::Datetime(d) => serde_json::Value::from(d.value().to_string()),
+        toml_edit::Value::Array(array) => array.iter().map(toml_value).collect(),
+        toml_edit::Value::InlineTable(table) => serde_json::Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), toml_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+pub fn run(config_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let config = BatchConfig::from_file(config_path)?;
+
+    if let Some(missing) = config.inputs.iter().find(|input| !input.exists()) {
+        return Err(format!("Input file not found: {}", missing.display()).into());
+    }
+
+    let mut processor = Processor::new();
+    processor.selected_files = config.inputs.clone();
+    if let Some(estimated_memory) = config.estimated_memory {
+        processor.settings.estimated_memory = estimated_memory;
+        processor.settings.memory.auto = false;
+    }
+    if let Some(spill_dir) = &config.spill_dir {
+        processor.settings.memory.spill = true;
+        processor.settings.memory.spill_dir = spill_dir.display().to_string();
+    }
+    processor.settings.csv = config.csv_input.clone();
+    if let Some(coincidences) = &config.coincidences {
+        processor.settings.coincidences = coincidences.clone();
+        processor.settings.coincidences.enabled = true;
+    }
+    if let Some(script) = &config.script {
+        processor.histogram_script = HistogramScript::from_file(script)
+            .map_err(|e| format!("Error reading script {}: {}", script.display(), e))?;
+    }
+
+    processor.calculate_histograms()?;
+
+    // the histograms are filled on the rayon pool
+    while processor.histogrammer.calculating.load(Ordering::SeqCst) {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    if let Some(e) = processor.histogrammer.fill_error.lock().unwrap().take() {
+        return Err(format!("The histograms were not filled: {}", e).into());
+    }
+
+    let histogrammer = &processor.histogrammer;
+    let outputs = &config.outputs;
+
+    if let Some(path) = &outputs.root {
+        let path = path.to_str().ok_or("Invalid ROOT output path")?;
+        let (progress, abort) = (Mutex::new(FillProgress::default()), AtomicBool::new(false));
+        histogrammer.root_file().write(
+            path,
+            &WriteProgress {
+                progress: &progress,
+                abort: &abort,
+            },
+        )?;
+        println!("ROOT file created at: {}", path);
+    }
+
+    if let Some(path) = &outputs.json {
+        write_json(histogrammer, path)?;
+        println!("JSON file created at: {}", path.display());
+    }
+
+    if let Some(dir) = &outputs.csv {
+        write_csv(histogrammer, dir)?;
+        println!("CSV files created in: {}", dir.display());
+    }
+
+    Ok(())
+}
+
+// Same counts format as "Save JSON" in the histogram context menus
+fn write_json(histogrammer: &Histogrammer, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut hist1d = Vec::new();
+    let mut hist2d = Vec::new();
+    for (_id, tile) in histogrammer.tree.tiles.iter() {
+        match tile {
+            egui_tiles::Tile::Pane(Pane::Histogram(hist)) => {
+                hist1d.push(hist.lock().unwrap().to_counts());
+            }
+            egui_tiles::Tile::Pane(Pane::Histogram2D(hist)) => {
+                hist2d.push(hist.lock().unwrap().to_counts());
+            }
+            _ => {}
+        }
+    }
+
+    let json = serde_json::json!({ "hist1d": hist1d, "hist2d": hist2d });
+    let mut file = File::create(path)?;
+    file.write_all(serde_json::to_string(&json)?.as_bytes())?;
+    Ok(())
+}
+
+fn write_csv(histogrammer: &Histogrammer, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut file_names = HashSet::new();
+    for (_id, tile) in histogrammer.tree.tiles.iter() {
+        let (name, csv) = match tile {
+            egui_tiles::Tile::Pane(Pane::Histogram(hist)) => {
+                let hist = hist.lock().unwrap();
+                let mut csv = String::from("bin_center,count\n");
+                for (index, count) in hist.bins.iter().enumerate() {
+                    let center = hist.bin_center(index);
+                    csv.push_str(&format!("{},{}\n", center, count));
+                }
+                (hist.name.clone(), csv)
+            }
+            egui_tiles::Tile::Pane(Pane::Histogram2D(hist)) => {
+                let hist = hist.lock().unwrap();
+                let bins = hist.backup_bins.as_ref().unwrap_or(&hist.bins);
+                let mut csv = String::from("x_center,y_center,count\n");
+                let mut counts: Vec<_> = bins.counts.iter().collect();
+                counts.sort_by_key(|&((x, y), _)| (y, x));
+                for ((x, y), count) in counts {
+                    let x_center = hist.range.x.min + (x as f64 + 0.5) * bins.x_width;
+                    let y_center = hist.range.y.min + (y as f64 + 0.5) * bins.y_width;
+                    csv.push_str(&format!("{},{},{}\n", x_center, y_center, count));
+                }
+                (hist.name.clone(), csv)
+            }
+            _ => continue,
+        };
+
+        // histograms with the same name after the / are replaced get a number, so none is
+        // overwritten
+        let stem = name.replace('/', "_");
+        let mut file_name = format!("{}.csv", stem);
+        let mut number = 1;
+        while !file_names.insert(file_name.clone()) {
+            number += 1;
+            file_name = format!("{}_{}.csv", stem, number);
+        }
+
+        let mut file = File::create(dir.join(file_name))?;
+        file.write_all(csv.as_bytes())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,277 @@
+use fnv::FnvHashMap;
+use polars::prelude::*;
+
+use crate::histoer::column_script::compile_script;
+use crate::util::raw_source::{Columns, MISSING};
+
+// Event building from singles lists, e.g. the hits of CoMPASS files. The rows are sorted by
+// time and a hit within the coincidence window of the first hit of an event joins it. The
+// built frame replaces the rows of the files, so the histogram configs use its columns:
+//
+// Events: one row per event with EventTime, EventLength, Multiplicity, and SumEnergy, and
+//   <Name>Energy, <Name>Time (from EventTime), and <Name>Hits for every named detector
+//   (the first hit of the detector in the event).
+// Pairs: one row per ordered pair of hits in an event with Energy1, Energy2, DeltaT,
+//   Detector1, Detector2, Multiplicity, and EventTime, so "Energy1" vs "Energy2" is a
+//   symmetric γ-γ matrix.
+//
+// The time, energy, and detector are column expressions, e.g. "Board * 16 + Channel".
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum CoincidenceOutput {
+    #[default]
+    Events,
+    Pairs,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Detector {
+    pub id: i64, // value of the detector expression
+    pub name: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CoincidenceSettings {
+    pub enabled: bool,
+    pub time: String,
+    pub energy: String,   // empty for no energies
+    pub detector: String, // empty for no detectors
+    pub window: f64,      // in the units of the time column
+    pub output: CoincidenceOutput,
+    pub detectors: Vec<Detector>,
+    #[serde(skip)]
+    pub built: Option<(usize, usize)>, // hits and events of the last build
+}
+
+impl Default for CoincidenceSettings {
+    fn default() -> Self {
+        CoincidenceSettings {
+            enabled: false,
+            time: "Timestamp".to_string(),
+            energy: "Energy".to_string(),
+            detector: "Channel".to_string(),
+            window: 100.0,
+            output: CoincidenceOutput::Events,
+            detectors: Vec::new(),
+            built: None,
+        }
+    }
+}
+
+// A sorted hit
+struct Hit {
+    time: f64,
+    energy: f64,
+    detector: f64,
+}
+
+impl CoincidenceSettings {
+    pub fn build(&mut self, lf: LazyFrame) -> PolarsResult<LazyFrame> {
+        let hits = self.sorted_hits(lf)?;
+
+        let detectors: FnvHashMap<i64, usize> = self
+            .detectors
+            .iter()
+            .enumerate()
+            .map(|(index, detector)| (detector.id, index))
+            .collect();
+
+        let mut columns = Columns::default();
+        let mut events = 0;
+        let mut start = 0;
+        while start < hits.len() {
+            let mut end = start + 1;
+            while end < hits.len() && hits[end].time - hits[start].time <= self.window {
+                end += 1;
+            }
+
+            let event = &hits[start..end];
+            match self.output {
+                CoincidenceOutput::Events => self.event_row(event, &detectors, &mut columns),
+                CoincidenceOutput::Pairs => self.pair_rows(event, &mut columns),
+            }
+            events += 1;
+            start = end;
+        }
+
+        log::info!(
+            "Built {} events from {} hits, {} rows",
+            events,
+            hits.len(),
+            columns.rows
+        );
+        self.built = Some((hits.len(), events));
+        Ok(columns.finish()?.lazy())
+    }
+
+    // Time, energy, and detector of every hit with a time, in time order
+    fn sorted_hits(&self, lf: LazyFrame) -> PolarsResult<Vec<Hit>> {
+        let expr = |script: &str, alias: &str| -> PolarsResult<Expr> {
+            let expr = if script.trim().is_empty() {
+                lit(MISSING)
+            } else {
+                compile_script(script).map_err(|e| polars_err!(ComputeError: "{}: {}", alias, e))?
+            };
+            Ok(expr.cast(DataType::Float64).alias(alias))
+        };
+
+        let df = lf
+            .select([
+                expr(&self.time, "time")?,
+                expr(&self.energy, "energy")?,
+                expr(&self.detector, "detector")?,
+            ])
+            .filter(col("time").is_not_null().and(col("time").neq(lit(MISSING))))
+            .sort(["time"], SortMultipleOptions::default())
+            .collect()?;
+
+        let values = |name: &str| -> PolarsResult<Vec<f64>> {
+            Ok(df
+                .column(name)?
+                .f64()?
+                .iter()
+                .map(|value| value.unwrap_or(MISSING))
+                .collect())
+        };
+        let (time, energy, detector) = (values("time")?, values("energy")?, values("detector")?);
+
+        Ok((0..df.height())
+            .map(|index| Hit {
+                time: time[index],
+                energy: energy[index],
+                detector: detector[index],
+            })
+            .collect())
+    }
+
+    fn event_row(&self, event: &[Hit], detectors: &FnvHashMap<i64, usize>, columns: &mut Columns) {
+        let event_time = event[0].time;
+        columns.set("EventTime", event_time);
+        columns.set("EventLength", event[event.len() - 1].time - event_time);
+        columns.set("Multiplicity", event.len() as f64);
+        if !self.energy.trim().is_empty() {
+            let sum = event
+                .iter()
+                .filter(|hit| hit.energy != MISSING)
+                .map(|hit| hit.energy)
+                .sum();
+            columns.set("SumEnergy", sum);
+        }
+
+        let mut hits = vec![0; self.detectors.len()];
+        for hit in event {
+            let Some(&index) = detectors.get(&(hit.detector.round() as i64)) else {
+                continue;
+            };
+            hits[index] += 1;
+            // the first hit of the detector
+            if hits[index] == 1 {
+                let name = &self.detectors[index].name;
+                columns.set(&format!("{}Energy", name), hit.energy);
+                columns.set(&format!("{}Time", name), hit.time - event_time);
+            }
+        }
+        for (detector, hits) in self.detectors.iter().zip(hits) {
+            columns.set(&format!("{}Hits", detector.name), hits as f64);
+        }
+        columns.end_row();
+    }
+
+    fn pair_rows(&self, event: &[Hit], columns: &mut Columns) {
+        for (i, first) in event.iter().enumerate() {
+            for (j, second) in event.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                columns.set("Energy1", first.energy);
+                columns.set("Energy2", second.energy);
+                columns.set("DeltaT", second.time - first.time);
+                columns.set("Detector1", first.detector);
+                columns.set("Detector2", second.detector);
+                columns.set("Multiplicity", event.len() as f64);
+                columns.set("EventTime", event[0].time);
+                columns.end_row();
+            }
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, "Build Events").on_hover_text(
+            "Group the rows of the files into events by time, the histograms are filled from the events",
+        );
+
+        ui.add_enabled_ui(self.enabled, |ui| {
+            egui::Grid::new("coincidence_columns").show(ui, |ui| {
+                ui.label("Time:");
+                ui.add(egui::TextEdit::singleline(&mut self.time).desired_width(150.0));
+                ui.end_row();
+
+                ui.label("Energy:");
+                ui.add(egui::TextEdit::singleline(&mut self.energy).desired_width(150.0));
+                ui.end_row();
+
+                ui.label("Detector:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.detector)
+                        .hint_text("Board * 16 + Channel")
+                        .desired_width(150.0),
+                );
+                ui.end_row();
+            });
+
+            ui.add(
+                egui::DragValue::new(&mut self.window)
+                    .range(0.0..=f64::INFINITY)
+                    .speed(1.0)
+                    .prefix("Window: "),
+            )
+            .on_hover_text("Hits within this time of the first hit of an event join it, in the units of the time column");
+
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.output, CoincidenceOutput::Events, "Events")
+                    .on_hover_text("One row per event with the multiplicity, summed energy, and the hits of the named detectors");
+                ui.radio_value(&mut self.output, CoincidenceOutput::Pairs, "Pairs")
+                    .on_hover_text("One row per pair of hits in an event, Energy1 vs Energy2 is a γ-γ matrix");
+            });
+
+            ui.label("Detectors").on_hover_text(
+                "Named detectors get <Name>Energy, <Name>Time, and <Name>Hits columns in the events",
+            );
+            let mut index_to_remove = None;
+            egui::Grid::new("coincidence_detectors")
+                .striped(true)
+                .show(ui, |ui| {
+                    for (index, detector) in self.detectors.iter_mut().enumerate() {
+                        ui.add(egui::DragValue::new(&mut detector.id).prefix("ID: "));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut detector.name).desired_width(100.0),
+                        );
+                        if ui.button("X").clicked() {
+                            index_to_remove = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+            if let Some(index) = index_to_remove {
+                self.detectors.remove(index);
+            }
+            if ui.button("+").clicked() {
+                let id = self.detectors.last().map_or(0, |last| last.id + 1);
+                self.detectors.push(Detector {
+                    id,
+                    name: String::new(),
+                });
+            }
+
+            if let Some((hits, events)) = self.built {
+                ui.label(format!(
+                    "Last build: {} events from {} hits ({:.2} hits per event)",
+                    events,
+                    hits,
+                    hits as f64 / events.max(1) as f64
+                ));
+            }
+        });
+    }
+}
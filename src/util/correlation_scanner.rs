@@ -0,0 +1,394 @@
+use crate::histoer::configs::Configs;
+use crate::histoer::fill_progress::FillProgress;
+use crate::histoer::preview;
+use polars::prelude::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug)]
+pub struct ColumnCorrelation {
+    pub x_column: String,
+    pub y_column: String,
+    pub coefficient: f64,
+    pub samples: usize,
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+}
+
+// Rows are read in this many random blocks, so the sample spreads over the runs and files
+// without reading the rest of the data
+const SAMPLE_BLOCKS: usize = 100;
+const MIN_BLOCK_ROWS: usize = 1000;
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CorrelationScanner {
+    pub open: bool,
+    pub selected_columns: Vec<String>,
+    pub sample_size: usize,
+    pub min_correlation: f64,
+    pub bins: (usize, usize),
+    #[serde(skip)]
+    pub results: Vec<ColumnCorrelation>,
+    #[serde(skip)]
+    pub scanning: Arc<AtomicBool>,
+    #[serde(skip)]
+    pub abort_flag: Arc<AtomicBool>,
+    #[serde(skip)]
+    pub progress: Arc<Mutex<FillProgress>>,
+    #[serde(skip)]
+    finished: Arc<Mutex<Option<Vec<ColumnCorrelation>>>>, // results of the scan thread
+}
+
+impl Default for CorrelationScanner {
+    fn default() -> Self {
+        Self {
+            open: false,
+            selected_columns: Vec::new(),
+            sample_size: 100_000,
+            min_correlation: 0.3,
+            bins: (512, 512),
+            results: Vec::new(),
+            scanning: Arc::new(AtomicBool::new(false)),
+            abort_flag: Arc::new(AtomicBool::new(false)),
+            progress: Arc::new(Mutex::new(FillProgress::default())),
+            finished: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl CorrelationScanner {
+    // Samples the rows and computes the correlations on a background thread
+    pub fn scan(&mut self, lf: &LazyFrame) {
+        if self.scanning.load(Ordering::SeqCst) {
+            return;
+        }
+        if self.selected_columns.len() < 2 {
+            log::error!("Select at least two columns to scan for correlations.");
+            return;
+        }
+
+        self.results.clear();
+        self.scanning.store(true, Ordering::SeqCst);
+        self.abort_flag.store(false, Ordering::SeqCst);
+        *self.progress.lock().unwrap() = FillProgress::start("Sampling", 0, Vec::new());
+
+        let lf = lf.clone();
+        let columns = self.selected_columns.clone();
+        let sample_size = self.sample_size;
+        let scanning = Arc::clone(&self.scanning);
+        let abort_flag = Arc::clone(&self.abort_flag);
+        let progress = Arc::clone(&self.progress);
+        let finished = Arc::clone(&self.finished);
+
+        rayon::spawn(move || {
+            match sample_rows(&lf, &columns, sample_size, &abort_flag, &progress) {
+                Ok(Some(df)) => {
+                    let results = correlations(&df, &columns);
+                    log::info!("Correlation scan finished: {} column pairs", results.len());
+                    *finished.lock().unwrap() = Some(results);
+                }
+                Ok(None) => log::info!("Correlation scan aborted."),
+                Err(e) => log::error!("Failed to collect sample for correlation scan: {}", e),
+            }
+            scanning.store(false, Ordering::SeqCst);
+        });
+    }
+
+    pub fn ui(
+        &mut self,
+        ctx: &egui::Context,
+        column_names: &[String],
+        lf: Option<&LazyFrame>,
+        configs: &mut Configs,
+    ) {
+        if let Some(results) = self.finished.lock().unwrap().take() {
+            self.results = results;
+        }
+        let scanning = self.scanning.load(Ordering::SeqCst);
+
+        let mut open = self.open;
+        egui::Window::new("Correlation Scanner")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut self.sample_size)
+                            .range(100..=usize::MAX)
+                            .speed(1000)
+                            .prefix("Sample Size: "),
+                    )
+                    .on_hover_text("Number of randomly sampled rows used to compute the correlations");
+
+                    ui.add(
+                        egui::DragValue::new(&mut self.min_correlation)
+                            .range(0.0..=1.0)
+                            .speed(0.01)
+                            .prefix("Suggest |r| ≥ "),
+                    );
+
+                    ui.separator();
+
+                    if scanning {
+                        if ui.button("Abort").clicked() {
+                            self.abort_flag.store(true, Ordering::SeqCst);
+                        }
+                    } else if ui
+                        .add_enabled(lf.is_some(), egui::Button::new("Scan"))
+                        .on_disabled_hover_text("Load parquet files first.")
+                        .clicked()
+                    {
+                        if let Some(lf) = lf {
+                            self.scan(lf);
+                        }
+                    }
+                });
+
+                if scanning {
+                    self.progress.lock().unwrap().ui(ui);
+                    ctx.request_repaint_after(std::time::Duration::from_millis(100));
+                }
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("Columns");
+                    if ui.button("All").clicked() {
+                        self.selected_columns = column_names.to_vec();
+                    }
+                    if ui.button("None").clicked() {
+                        self.selected_columns.clear();
+                    }
+                });
+
+                egui::ScrollArea::vertical()
+                    .id_salt("correlation_columns")
+                    .max_height(150.0)
+                    .show(ui, |ui| {
+                        for name in column_names {
+                            let mut selected = self.selected_columns.contains(name);
+                            if ui.checkbox(&mut selected, name).changed() {
+                                if selected {
+                                    self.selected_columns.push(name.clone());
+                                } else {
+                                    self.selected_columns.retain(|c| c != name);
+                                }
+                            }
+                        }
+                    });
+
+                if self.results.is_empty() {
+                    return;
+                }
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("Suggested 2D Histograms");
+                    ui.add(egui::DragValue::new(&mut self.bins.0).prefix("X Bins: "));
+                    ui.add(egui::DragValue::new(&mut self.bins.1).prefix("Y Bins: "));
+                });
+
+                egui::ScrollArea::vertical()
+                    .id_salt("correlation_results")
+                    .show(ui, |ui| {
+                        egui::Grid::new("correlation_results_grid")
+                            .striped(true)
+                            .num_columns(4)
+                            .show(ui, |ui| {
+                                ui.label("X Column");
+                                ui.label("Y Column");
+                                ui.label("r");
+                                ui.label("Samples");
+                                ui.end_row();
+
+                                for result in &self.results {
+                                    if result.coefficient.abs() < self.min_correlation {
+                                        continue;
+                                    }
+
+                                    ui.label(&result.x_column);
+                                    ui.label(&result.y_column);
+                                    ui.label(format!("{:.3}", result.coefficient));
+                                    ui.label(format!("{}", result.samples));
+
+                                    if ui
+                                        .button("+2D")
+                                        .on_hover_text("Add a 2D histogram to the general histogram script using the sampled ranges")
+                                        .clicked()
+                                    {
+                                        configs.hist2d(
+                                            &format!(
+                                                "Correlations/{} v {}",
+                                                result.y_column, result.x_column
+                                            ),
+                                            &result.x_column,
+                                            &result.y_column,
+                                            result.x_range,
+                                            result.y_range,
+                                            self.bins,
+                                            None,
+                                        );
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        self.open = open;
+    }
+}
+
+// Random blocks of rows of the selected columns, about `sample_size` rows in total. Only the
+// blocks are read, the abort flag is checked between them. None when aborted.
+fn sample_rows(
+    lf: &LazyFrame,
+    columns: &[String],
+    sample_size: usize,
+    abort_flag: &AtomicBool,
+    progress: &Mutex<FillProgress>,
+) -> PolarsResult<Option<DataFrame>> {
+    let selected: Vec<Expr> = columns
+        .iter()
+        .map(|name| col(name).cast(DataType::Float64))
+        .collect();
+    let lf = lf.clone().select(selected);
+
+    let row_count = lf
+        .clone()
+        .select([len().alias("count")])
+        .collect()?
+        .column("count")?
+        .u32()?
+        .get(0)
+        .unwrap_or(0) as usize;
+
+    let size = sample_size.min(IdxSize::MAX as usize).max(1);
+    let block_rows = (size / SAMPLE_BLOCKS).max(MIN_BLOCK_ROWS);
+    let fraction = (size as f64 / row_count.max(1) as f64).min(1.0);
+    let blocks = preview::chunk_starts(row_count, block_rows, Some(fraction));
+    let rows_total: usize = blocks.iter().map(|(_, rows)| rows).sum();
+    *progress.lock().unwrap() = FillProgress::start("Sampling", rows_total, Vec::new());
+
+    let mut sample: Option<DataFrame> = None;
+    for (start, rows) in blocks {
+        if abort_flag.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        let block = lf.clone().slice(start as i64, rows as IdxSize).collect()?;
+        match &mut sample {
+            Some(sample) => {
+                sample.vstack_mut(&block)?;
+            }
+            None => sample = Some(block),
+        }
+        progress.lock().unwrap().add_rows(rows);
+    }
+
+    let Some(sample) = sample else {
+        return Ok(Some(DataFrame::empty()));
+    };
+    // the blocks are rounded up to whole blocks, random rows of them are kept
+    if sample.height() > size {
+        Ok(Some(sample.sample_n_literal(size, false, true, None)?))
+    } else {
+        Ok(Some(sample))
+    }
+}
+
+// Pearson coefficients of every pair of columns, strongest first. Nulls and the -1e6
+// sentinel are treated as missing.
+fn correlations(df: &DataFrame, names: &[String]) -> Vec<ColumnCorrelation> {
+    let columns: Vec<(String, Vec<Option<f64>>)> = names
+        .iter()
+        .filter_map(|name| match df.column(name).and_then(|c| c.f64()) {
+            Ok(values) => Some((
+                name.clone(),
+                values
+                    .into_iter()
+                    .map(|v| v.filter(|&v| v != -1e6 && v.is_finite()))
+                    .collect(),
+            )),
+            Err(e) => {
+                log::error!("Column '{}' can not be used for correlations: {}", name, e);
+                None
+            }
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for i in 0..columns.len() {
+        for j in (i + 1)..columns.len() {
+            let (x_name, x_values) = &columns[i];
+            let (y_name, y_values) = &columns[j];
+
+            let pairs: Vec<(f64, f64)> = x_values
+                .iter()
+                .zip(y_values.iter())
+                .filter_map(|(x, y)| match (x, y) {
+                    (Some(x), Some(y)) => Some((*x, *y)),
+                    _ => None,
+                })
+                .collect();
+
+            if let Some(coefficient) = pearson(&pairs) {
+                results.push(ColumnCorrelation {
+                    x_column: x_name.clone(),
+                    y_column: y_name.clone(),
+                    coefficient,
+                    samples: pairs.len(),
+                    x_range: min_max(pairs.iter().map(|(x, _)| *x)),
+                    y_range: min_max(pairs.iter().map(|(_, y)| *y)),
+                });
+            }
+        }
+    }
+
+    results.sort_by(|a, b| {
+        b.coefficient
+            .abs()
+            .partial_cmp(&a.coefficient.abs())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    results
+}
+
+fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < 2 {
+        return None;
+    }
+
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let mut covariance = 0.0;
+    let mut variance_x = 0.0;
+    let mut variance_y = 0.0;
+    for (x, y) in pairs {
+        let dx = x - mean_x;
+        let dy = y - mean_y;
+        covariance += dx * dy;
+        variance_x += dx * dx;
+        variance_y += dy * dy;
+    }
+
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+
+    Some(covariance / (variance_x.sqrt() * variance_y.sqrt()))
+}
+
+fn min_max(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+        (min.min(v), max.max(v))
+    });
+
+    if min < max {
+        (min, max)
+    } else {
+        (min - 0.5, min + 0.5)
+    }
+}
@@ -0,0 +1,122 @@
+use polars::prelude::*;
+use std::path::{Path, PathBuf};
+
+// Tabular ASCII data (e.g. from a legacy DAQ) read as a LazyFrame like the parquet files.
+// Every column is cast to f64, values that are missing or not numbers become -1e6 so the
+// fill skips them.
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Delimiter {
+    #[default]
+    Auto, // tab for .tsv files, comma otherwise
+    Comma,
+    Tab,
+    Semicolon,
+    Space,
+}
+
+impl Delimiter {
+    fn byte(&self, path: &Path) -> u8 {
+        match self {
+            Delimiter::Auto => {
+                if path.extension().is_some_and(|ext| ext == "tsv") {
+                    b'\t'
+                } else {
+                    b','
+                }
+            }
+            Delimiter::Comma => b',',
+            Delimiter::Tab => b'\t',
+            Delimiter::Semicolon => b';',
+            Delimiter::Space => b' ',
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CsvSettings {
+    pub delimiter: Delimiter,
+    pub has_header: bool, // otherwise the columns are named column_1, column_2, ...
+    pub comment_prefix: String,
+    pub skip_rows: usize, // lines before the header
+}
+
+impl Default for CsvSettings {
+    fn default() -> Self {
+        CsvSettings {
+            delimiter: Delimiter::Auto,
+            has_header: true,
+            comment_prefix: "#".to_string(),
+            skip_rows: 0,
+        }
+    }
+}
+
+pub fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext, "csv" | "tsv" | "txt" | "dat"))
+}
+
+impl CsvSettings {
+    pub fn scan(&self, files: &[PathBuf]) -> PolarsResult<LazyFrame> {
+        let Some(first) = files.first() else {
+            polars_bail!(NoData: "no CSV files selected");
+        };
+
+        let comment_prefix =
+            (!self.comment_prefix.is_empty()).then(|| PlSmallStr::from_str(&self.comment_prefix));
+
+        let lf = LazyCsvReader::new_paths(Arc::from(files.to_vec()))
+            .with_separator(self.delimiter.byte(first))
+            .with_has_header(self.has_header)
+            .with_comment_prefix(comment_prefix)
+            .with_skip_rows(self.skip_rows)
+            .with_infer_schema_length(Some(1000))
+            .with_ignore_errors(true)
+            .finish()?;
+
+        Ok(lf.select([all().cast(DataType::Float64).fill_null(lit(-1e6))]))
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Delimiter:");
+            egui::ComboBox::from_id_salt("csv_delimiter")
+                .selected_text(format!("{:?}", self.delimiter))
+                .show_ui(ui, |ui| {
+                    for delimiter in [
+                        Delimiter::Auto,
+                        Delimiter::Comma,
+                        Delimiter::Tab,
+                        Delimiter::Semicolon,
+                        Delimiter::Space,
+                    ] {
+                        ui.selectable_value(
+                            &mut self.delimiter,
+                            delimiter,
+                            format!("{:?}", delimiter),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text("Auto uses tabs for .tsv files and commas otherwise");
+        });
+
+        ui.checkbox(&mut self.has_header, "Header row")
+            .on_hover_text("Without a header the columns are named column_1, column_2, ...");
+
+        ui.horizontal(|ui| {
+            ui.label("Comment prefix:");
+            ui.add(egui::TextEdit::singleline(&mut self.comment_prefix).desired_width(30.0));
+        });
+
+        ui.add(
+            egui::DragValue::new(&mut self.skip_rows)
+                .speed(1)
+                .prefix("Skip lines: "),
+        )
+        .on_hover_text("Lines at the top of each file before the header or data");
+    }
+}
@@ -0,0 +1,64 @@
+use polars::prelude::*;
+use std::path::{Path, PathBuf};
+
+// Columns telling which of the selected files a row came from, so histograms and cuts can be
+// made per run, e.g. a 1D cut "RunNumber >= 80 & RunNumber <= 95". The values are f64 like
+// every other column, a file without a number in its name gets a run number of -1e6.
+
+pub const FILE_INDEX: &str = "FileIndex"; // position of the file in the selection
+pub const RUN_NUMBER: &str = "RunNumber";
+
+// The digits after "run" in the file name, or else its last group of digits,
+// e.g. "run_83.parquet" and "Run83_sps.parquet" are run 83, "sps_2024_12.parquet" is run 12
+pub fn run_number(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?.to_lowercase();
+
+    let digits_after = |start: usize| -> Option<u64> {
+        let rest = stem[start..].trim_start_matches(['_', '-', ' ']);
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        rest[..end].parse().ok()
+    };
+
+    if let Some(run) = stem
+        .match_indices("run")
+        .find_map(|(index, _)| digits_after(index + 3))
+    {
+        return Some(run);
+    }
+
+    let end = stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = stem[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |index| index + 1);
+    stem[start..end].parse().ok()
+}
+
+// Scans the files one by one with `scan` and chains them with the file columns added.
+// Columns of the same name already in the files are kept as they are.
+pub fn scan_with_file_columns(
+    files: &[PathBuf],
+    scan: impl Fn(&Path) -> PolarsResult<LazyFrame>,
+) -> PolarsResult<LazyFrame> {
+    let mut frames = Vec::with_capacity(files.len());
+    for (index, file) in files.iter().enumerate() {
+        let mut lf = scan(file)?;
+        let schema = lf.collect_schema()?;
+
+        let mut columns = Vec::new();
+        if !schema.contains(FILE_INDEX) {
+            columns.push(lit(index as f64).alias(FILE_INDEX));
+        }
+        if !schema.contains(RUN_NUMBER) {
+            let run = run_number(file).map_or(-1e6, |run| run as f64);
+            columns.push(lit(run).alias(RUN_NUMBER));
+        }
+        frames.push(lf.with_columns(columns));
+    }
+
+    if frames.is_empty() {
+        polars_bail!(NoData: "no files selected");
+    }
+    concat(frames, UnionArgs::default())
+}
@@ -0,0 +1,159 @@
+use crate::histoer::configs::{Config, Hist1DConfig};
+use crate::histogram_scripter::histogram_script::HistogramScript;
+
+use polars::prelude::*;
+use std::path::Path;
+
+// Terminal summary of parquet files: `spectrix inspect run_1.parquet [run_2.parquet ...]`
+//
+// Prints the schema, row count, and per column statistics, then a histogram script
+// with a 1D histogram for every numeric column that can be saved and used with
+// `spectrix --batch` or loaded in the Histogram Script panel.
+
+const SENTINEL: f64 = -1e6; // value used for missing detector hits
+
+struct ColumnStats {
+    name: String,
+    dtype: DataType,
+    nulls: u64,
+    sentinels: u64,
+    min: Option<f64>,
+    max: Option<f64>,
+    mean: Option<f64>,
+    std: Option<f64>,
+}
+
+pub fn run(paths: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if paths.is_empty() {
+        return Err("Usage: spectrix inspect <file.parquet> [more.parquet ...]".into());
+    }
+
+    for (index, path) in paths.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        inspect(Path::new(path))?;
+    }
+    Ok(())
+}
+
+fn inspect(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if path.extension().and_then(|ext| ext.to_str()) != Some("parquet") {
+        return Err(format!("{} is not a parquet file", path.display()).into());
+    }
+
+    let mut lf = LazyFrame::scan_parquet(path, ScanArgsParquet::default())?;
+    let schema = lf.collect_schema()?;
+
+    // one pass over the file for all the statistics
+    let mut exprs = vec![len().cast(DataType::Float64).alias("__rows")];
+    for (name, dtype) in schema.iter() {
+        let name = name.as_str();
+        exprs.push(
+            col(name)
+                .null_count()
+                .cast(DataType::Float64)
+                .alias(format!("{}__nulls", name)),
+        );
+        if dtype.is_numeric() {
+            let value = col(name).cast(DataType::Float64);
+            let valid = value.clone().filter(value.clone().neq(lit(SENTINEL)));
+            exprs.extend([
+                value
+                    .clone()
+                    .eq(lit(SENTINEL))
+                    .sum()
+                    .cast(DataType::Float64)
+                    .alias(format!("{}__sentinels", name)),
+                valid.clone().min().alias(format!("{}__min", name)),
+                valid.clone().max().alias(format!("{}__max", name)),
+                valid.clone().mean().alias(format!("{}__mean", name)),
+                valid.std(1).alias(format!("{}__std", name)),
+            ]);
+        }
+    }
+    let df = lf.select(exprs).collect()?;
+
+    let value = |key: String| -> Option<f64> { df.column(&key).ok()?.f64().ok()?.get(0) };
+    let rows = value("__rows".to_string()).unwrap_or(0.0) as u64;
+
+    let stats: Vec<ColumnStats> = schema
+        .iter()
+        .map(|(name, dtype)| ColumnStats {
+            name: name.to_string(),
+            dtype: dtype.clone(),
+            nulls: value(format!("{}__nulls", name)).unwrap_or(0.0) as u64,
+            sentinels: value(format!("{}__sentinels", name)).unwrap_or(0.0) as u64,
+            min: value(format!("{}__min", name)),
+            max: value(format!("{}__max", name)),
+            mean: value(format!("{}__mean", name)),
+            std: value(format!("{}__std", name)),
+        })
+        .collect();
+
+    println!("{}", path.display());
+    println!("  rows:    {}", rows);
+    println!("  columns: {}", stats.len());
+    println!();
+
+    let width = stats
+        .iter()
+        .map(|column| column.name.len())
+        .max()
+        .unwrap_or(6)
+        .max(6);
+    println!(
+        "  {:<width$}  {:<8}  {:>10}  {:>10}  {:>12}  {:>12}  {:>12}  {:>12}",
+        "column", "type", "nulls", "-1e6", "min", "max", "mean", "std"
+    );
+    let number = |value: Option<f64>| match value {
+        Some(v) if v != 0.0 && (v.abs() >= 1e6 || v.abs() < 1e-3) => format!("{:.5e}", v),
+        Some(v) => format!("{:.5}", v),
+        None => "-".to_string(),
+    };
+    for column in &stats {
+        println!(
+            "  {:<width$}  {:<8}  {:>10}  {:>10}  {:>12}  {:>12}  {:>12}  {:>12}",
+            column.name,
+            column.dtype.to_string(),
+            column.nulls,
+            column.sentinels,
+            number(column.min),
+            number(column.max),
+            number(column.mean),
+            number(column.std),
+        );
+    }
+
+    let mut script = HistogramScript::new();
+    script.configs.configs = stats.iter().filter_map(suggest_config).collect();
+
+    println!();
+    println!("Suggested histogram script (-1e6 values are left out of the ranges):");
+    println!("{}", serde_json::to_string_pretty(&script)?);
+
+    Ok(())
+}
+
+// One bin per value for integer columns with a small span, 1024 bins otherwise
+fn suggest_config(column: &ColumnStats) -> Option<Config> {
+    let (min, max) = (column.min?, column.max?);
+    if !column.dtype.is_numeric() || !min.is_finite() || !max.is_finite() || min >= max {
+        return None;
+    }
+
+    let (range, bins) = if column.dtype.is_integer() && max - min < 4096.0 {
+        ((min - 0.5, max + 0.5), (max - min) as usize + 1)
+    } else if max - min > 10.0 {
+        ((min.floor(), max.ceil()), 1024)
+    } else {
+        ((min, max), 1024)
+    };
+
+    Some(Config::Hist1D(Hist1DConfig::new(
+        &column.name,
+        &column.name,
+        range,
+        bins,
+    )))
+}
@@ -0,0 +1,474 @@
+use fnv::FnvHashMap;
+use std::path::{Path, PathBuf};
+
+// Relativistic two-body reaction kinematics, A(a,b)B, for the focal plane of a magnetic
+// spectrograph. Masses come from atomic mass excesses (AME) in keV, energies are in MeV.
+
+const AMU: f64 = 931.49410242; // MeV
+const ELECTRON: f64 = 0.51099895; // MeV
+const C: f64 = 299.792458; // MeV/c per (T m) per unit charge
+
+const ELEMENTS: [&str; 119] = [
+    "n", "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S",
+    "Cl", "Ar", "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge",
+    "As", "Se", "Br", "Kr", "Rb", "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd",
+    "In", "Sn", "Sb", "Te", "I", "Xe", "Cs", "Ba", "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd",
+    "Tb", "Dy", "Ho", "Er", "Tm", "Yb", "Lu", "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg",
+    "Tl", "Pb", "Bi", "Po", "At", "Rn", "Fr", "Ra", "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm",
+    "Bk", "Cf", "Es", "Fm", "Md", "No", "Lr", "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn",
+    "Nh", "Fl", "Mc", "Lv", "Ts", "Og",
+];
+
+// AME2020 mass excesses (keV) of the light ions, so common reactions only need the target and
+// residual from a mass table
+const LIGHT_IONS: [(u32, u32, f64); 7] = [
+    (0, 1, 8071.31806),
+    (1, 1, 7288.971064),
+    (1, 2, 13135.722895),
+    (1, 3, 14949.81090),
+    (2, 3, 14931.21888),
+    (2, 4, 2424.91587),
+    (6, 12, 0.0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Nucleus {
+    pub z: u32,
+    pub a: u32,
+}
+
+impl Nucleus {
+    // "12C", "3He", or the light ion shorthands n, p, d, t, a
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (z, a) = match text {
+            "n" => (0, 1),
+            "p" => (1, 1),
+            "d" => (1, 2),
+            "t" => (1, 3),
+            "a" | "alpha" => (2, 4),
+            _ => {
+                let split = text.find(|c: char| !c.is_ascii_digit())?;
+                let a = text[..split].parse().ok()?;
+                let symbol = &text[split..];
+                let z = ELEMENTS
+                    .iter()
+                    .skip(1)
+                    .position(|element| element.eq_ignore_ascii_case(symbol))?
+                    + 1;
+                (z as u32, a)
+            }
+        };
+        (a >= z.max(1)).then_some(Nucleus { z, a })
+    }
+
+    pub fn name(&self) -> String {
+        match ELEMENTS.get(self.z as usize) {
+            Some(symbol) if self.z > 0 => format!("{}{}", self.a, symbol),
+            Some(_) => "n".to_string(),
+            None => format!("Z={} A={}", self.z, self.a),
+        }
+    }
+
+    // Nuclear mass in MeV from the atomic mass excess in keV, electron binding neglected
+    pub fn mass(&self, excess: f64) -> f64 {
+        self.a as f64 * AMU + excess / 1000.0 - self.z as f64 * ELECTRON
+    }
+}
+
+// Atomic mass excesses in keV by nucleus
+#[derive(Debug, Clone)]
+pub struct MassTable {
+    excesses: FnvHashMap<Nucleus, f64>,
+}
+
+impl Default for MassTable {
+    fn default() -> Self {
+        let excesses = LIGHT_IONS
+            .iter()
+            .map(|&(z, a, excess)| (Nucleus { z, a }, excess))
+            .collect();
+        MassTable { excesses }
+    }
+}
+
+impl MassTable {
+    // Reads the AME mass_1.mas20 (or mas16) table on top of the light ions. Estimated values
+    // marked with '#' are kept.
+    pub fn load_ame(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let mut table = MassTable::default();
+        for line in text.lines() {
+            let (Some(z), Some(a), Some(rest)) =
+                (line.get(9..14), line.get(14..19), line.get(28..))
+            else {
+                continue;
+            };
+            let (Ok(z), Ok(a)) = (z.trim().parse(), a.trim().parse()) else {
+                continue;
+            };
+            let Some(Ok(excess)) = rest
+                .split_whitespace()
+                .next()
+                .map(|value| value.replace('#', ".").parse::<f64>())
+            else {
+                continue;
+            };
+            table.excesses.insert(Nucleus { z, a }, excess);
+        }
+
+        log::info!(
+            "Loaded {} mass excesses from {}",
+            table.excesses.len(),
+            path.display()
+        );
+        Ok(table)
+    }
+
+    pub fn excess(&self, nucleus: &Nucleus) -> Option<f64> {
+        self.excesses.get(nucleus).copied()
+    }
+}
+
+// Reaction with resolved nuclear masses in MeV
+#[derive(Debug, Clone, Copy)]
+pub struct Kinematics {
+    pub target: f64,
+    pub projectile: f64,
+    pub ejectile: f64,
+    pub residual: f64,
+    pub ejectile_charge: u32,
+    pub beam_energy: f64, // MeV
+    pub angle: f64,       // degrees
+    pub field: f64,       // kG
+}
+
+impl Kinematics {
+    pub fn q_value(&self) -> f64 {
+        self.target + self.projectile - self.ejectile - self.residual
+    }
+
+    // Total energy and momentum of the entrance channel in the lab
+    fn entrance(&self) -> (f64, f64) {
+        let energy = self.beam_energy + self.projectile + self.target;
+        let momentum = (self.beam_energy * (self.beam_energy + 2.0 * self.projectile)).sqrt();
+        (energy, momentum)
+    }
+
+    // Ejectile momentum (MeV/c) with the residual at an excitation energy in MeV, None when the
+    // state is not reached at this beam energy and angle
+    pub fn ejectile_momentum(&self, excitation: f64) -> Option<f64> {
+        let (energy, momentum) = self.entrance();
+        let residual = self.residual + excitation;
+        let cos = self.angle.to_radians().cos();
+
+        let half =
+            (energy.powi(2) - momentum.powi(2) + self.ejectile.powi(2) - residual.powi(2)) / 2.0;
+        let denominator = energy.powi(2) - (momentum * cos).powi(2);
+        let discriminant = half.powi(2) - self.ejectile.powi(2) * denominator;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let p = (half * momentum * cos + energy * discriminant.sqrt()) / denominator;
+        (p > 0.0).then_some(p)
+    }
+
+    pub fn ejectile_energy(&self, momentum: f64) -> f64 {
+        (momentum.powi(2) + self.ejectile.powi(2)).sqrt() - self.ejectile
+    }
+
+    // Excitation energy (MeV) of the residual from the ejectile momentum, by missing mass
+    pub fn excitation(&self, ejectile_momentum: f64) -> Option<f64> {
+        let (energy, momentum) = self.entrance();
+        let cos = self.angle.to_radians().cos();
+
+        let residual_energy = energy - (ejectile_momentum.powi(2) + self.ejectile.powi(2)).sqrt();
+        let residual_momentum2 =
+            momentum.powi(2) + ejectile_momentum.powi(2) - 2.0 * momentum * ejectile_momentum * cos;
+        let invariant2 = residual_energy.powi(2) - residual_momentum2;
+        (invariant2 > 0.0).then(|| invariant2.sqrt() - self.residual)
+    }
+
+    // Ejectile momentum in MeV/c per cm of bending radius and kG of field
+    pub fn momentum_per_rho(&self) -> f64 {
+        C * self.ejectile_charge as f64 / 1000.0
+    }
+
+    // Bending radius in cm of the ejectile in the spectrograph field
+    pub fn rho(&self, momentum: f64) -> f64 {
+        momentum / (self.momentum_per_rho() * self.field)
+    }
+
+    pub fn momentum(&self, rho: f64) -> f64 {
+        rho * self.momentum_per_rho() * self.field
+    }
+}
+
+// Reaction settings with the masses resolved from an AME file, or typed in for nuclei missing
+// from it. Shared by the kinematics calculator and the SE-SPS excitation energy column.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Reaction {
+    pub target: String,
+    pub projectile: String,
+    pub ejectile: String,
+    pub beam_energy: f64, // MeV
+    pub angle: f64,       // degrees
+    pub field: f64,       // kG
+    pub mass_file: Option<PathBuf>,
+    pub manual_masses: Vec<(String, f64)>, // mass excesses in keV of nuclei missing from the table
+    #[serde(skip)]
+    masses: Option<MassTable>,
+}
+
+impl Default for Reaction {
+    fn default() -> Self {
+        Reaction {
+            target: "12C".to_string(),
+            projectile: "d".to_string(),
+            ejectile: "p".to_string(),
+            beam_energy: 16.0,
+            angle: 20.0,
+            field: 8.0,
+            mass_file: None,
+            manual_masses: Vec::new(),
+            masses: None,
+        }
+    }
+}
+
+impl Reaction {
+    fn mass_table(&mut self) -> &MassTable {
+        let file = self.mass_file.clone();
+        self.masses.get_or_insert_with(|| match file {
+            Some(path) => MassTable::load_ame(&path).unwrap_or_else(|e| {
+                log::error!("Error reading the mass table {}: {}", path.display(), e);
+                MassTable::default()
+            }),
+            None => MassTable::default(),
+        })
+    }
+
+    fn excess(&mut self, nucleus: &Nucleus) -> Option<f64> {
+        self.mass_table().excess(nucleus).or_else(|| {
+            self.manual_masses
+                .iter()
+                .find(|(name, _)| Nucleus::parse(name).as_ref() == Some(nucleus))
+                .map(|(_, excess)| *excess)
+        })
+    }
+
+    // Target, projectile, ejectile, and residual
+    pub fn nuclei(&self) -> Result<[Nucleus; 4], String> {
+        let parse =
+            |text: &str| Nucleus::parse(text).ok_or_else(|| format!("'{}' is not a nucleus", text));
+        let target = parse(&self.target)?;
+        let projectile = parse(&self.projectile)?;
+        let ejectile = parse(&self.ejectile)?;
+
+        let z = (target.z + projectile.z).checked_sub(ejectile.z);
+        let a = (target.a + projectile.a).checked_sub(ejectile.a);
+        match (z, a) {
+            (Some(z), Some(a)) if a > 0 => Ok([target, projectile, ejectile, Nucleus { z, a }]),
+            _ => Err("The ejectile is heavier than the entrance channel".to_string()),
+        }
+    }
+
+    pub fn kinematics(&mut self) -> Result<Kinematics, String> {
+        let nuclei = self.nuclei()?;
+        let mut masses = [0.0; 4];
+        for (mass, nucleus) in masses.iter_mut().zip(&nuclei) {
+            let excess = self
+                .excess(nucleus)
+                .ok_or_else(|| format!("No mass excess for {}", nucleus.name()))?;
+            *mass = nucleus.mass(excess);
+        }
+
+        Ok(Kinematics {
+            target: masses[0],
+            projectile: masses[1],
+            ejectile: masses[2],
+            residual: masses[3],
+            ejectile_charge: nuclei[2].z,
+            beam_energy: self.beam_energy,
+            angle: self.angle,
+            field: self.field,
+        })
+    }
+
+    // Short form like "12C(d,p)13C"
+    pub fn label(&self) -> String {
+        match self.nuclei() {
+            Ok([.., residual]) => format!(
+                "{}({},{}){}",
+                self.target,
+                self.projectile,
+                self.ejectile,
+                residual.name()
+            ),
+            Err(_) => format!("{}({},{})", self.target, self.projectile, self.ejectile),
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("reaction").show(ui, |ui| {
+            ui.label("Reaction");
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.target).desired_width(40.0));
+                ui.label("(");
+                ui.add(egui::TextEdit::singleline(&mut self.projectile).desired_width(30.0));
+                ui.label(",");
+                ui.add(egui::TextEdit::singleline(&mut self.ejectile).desired_width(30.0));
+                ui.label(")");
+                match self.nuclei() {
+                    Ok([.., residual]) => ui.label(residual.name()),
+                    Err(e) => ui.colored_label(egui::Color32::RED, e),
+                };
+            });
+            ui.end_row();
+
+            ui.label("Beam energy");
+            ui.add(
+                egui::DragValue::new(&mut self.beam_energy)
+                    .speed(0.1)
+                    .range(0.0..=f64::INFINITY)
+                    .suffix(" MeV"),
+            );
+            ui.end_row();
+
+            ui.label("Angle");
+            ui.add(
+                egui::DragValue::new(&mut self.angle)
+                    .speed(0.1)
+                    .range(0.0..=180.0)
+                    .suffix("°"),
+            );
+            ui.end_row();
+
+            ui.label("Field");
+            ui.add(
+                egui::DragValue::new(&mut self.field)
+                    .speed(0.01)
+                    .range(0.0..=f64::INFINITY)
+                    .suffix(" kG"),
+            );
+            ui.end_row();
+        });
+
+        ui.horizontal(|ui| {
+            match &self.mass_file {
+                Some(path) => ui.label(format!("Masses: {}", path.display())),
+                None => ui.label("Masses: light ions only"),
+            };
+            if ui
+                .button("Load AME…")
+                .on_hover_text("AME mass_1.mas20 table for the target and residual masses")
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                    self.mass_file = Some(path);
+                    self.masses = None;
+                }
+            }
+        });
+
+        // nuclei of the reaction missing from the table get a mass excess field
+        let Ok(nuclei) = self.nuclei() else {
+            return;
+        };
+        for nucleus in nuclei {
+            if self.mass_table().excess(&nucleus).is_some() {
+                continue;
+            }
+            let name = nucleus.name();
+            let index = match self
+                .manual_masses
+                .iter()
+                .position(|(n, _)| Nucleus::parse(n) == Some(nucleus))
+            {
+                Some(index) => index,
+                None => {
+                    self.manual_masses.push((name.clone(), 0.0));
+                    self.manual_masses.len() - 1
+                }
+            };
+            ui.horizontal(|ui| {
+                ui.label(format!("Mass excess of {}", name));
+                ui.add(
+                    egui::DragValue::new(&mut self.manual_masses[index].1)
+                        .speed(1.0)
+                        .suffix(" keV"),
+                );
+            });
+        }
+
+        match self.kinematics() {
+            Ok(kinematics) => {
+                ui.label(format!("Q = {:.3} MeV", kinematics.q_value()));
+            }
+            Err(e) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+        }
+    }
+}
+
+// Focal plane position linear in the bending radius, x = D (rho - rho0), x in mm and rho in cm
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct FocalPlane {
+    pub dispersion: f64,         // mm of focal plane per cm of rho
+    pub rho_center: Option<f64>, // cm at x = 0, the ground state when not set
+}
+
+impl Default for FocalPlane {
+    fn default() -> Self {
+        FocalPlane {
+            dispersion: 19.6,
+            rho_center: None,
+        }
+    }
+}
+
+impl FocalPlane {
+    pub fn center(&self, kinematics: &Kinematics) -> Option<f64> {
+        self.rho_center.or_else(|| {
+            kinematics
+                .ejectile_momentum(0.0)
+                .map(|momentum| kinematics.rho(momentum))
+        })
+    }
+
+    pub fn x(&self, kinematics: &Kinematics, rho: f64) -> Option<f64> {
+        Some(self.dispersion * (rho - self.center(kinematics)?))
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, kinematics: Option<&Kinematics>) {
+        ui.horizontal(|ui| {
+            ui.label("x = D (ρ - ρ0)");
+            ui.add(
+                egui::DragValue::new(&mut self.dispersion)
+                    .speed(0.01)
+                    .prefix("D: ")
+                    .suffix(" mm/cm"),
+            );
+
+            let mut fixed = self.rho_center.is_some();
+            if ui
+                .checkbox(&mut fixed, "ρ0")
+                .on_hover_text("Unchecked puts the ground state at x = 0")
+                .changed()
+            {
+                self.rho_center = match (fixed, kinematics) {
+                    (true, Some(kinematics)) => Some(self.center(kinematics).unwrap_or(0.0)),
+                    (true, None) => Some(0.0),
+                    (false, _) => None,
+                };
+            }
+            if let Some(center) = &mut self.rho_center {
+                ui.add(egui::DragValue::new(center).speed(0.01).suffix(" cm"));
+            }
+        });
+    }
+}
@@ -1 +1,12 @@
+pub mod batch;
+pub mod coincidence;
+pub mod correlation_scanner;
+pub mod csv_source;
+pub mod file_columns;
+pub mod inspect;
+pub mod kinematics;
+pub mod nuclear_data;
+pub mod online;
 pub mod processer;
+pub mod raw_source;
+pub mod remote_source;
@@ -0,0 +1,54 @@
+use std::sync::OnceLock;
+
+// Offline table of the gamma lines of common calibration and background sources, bundled from
+// assets/gamma_lines.csv. Energies are in keV.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GammaLine {
+    pub isotope: String,
+    pub energy: f64,      // keV
+    pub uncertainty: f64, // keV
+    pub intensity: f64,   // photons per 100 decays
+}
+
+pub fn gamma_lines() -> &'static [GammaLine] {
+    static LINES: OnceLock<Vec<GammaLine>> = OnceLock::new();
+    LINES.get_or_init(|| parse(include_str!("../../assets/gamma_lines.csv")))
+}
+
+fn parse(text: &str) -> Vec<GammaLine> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .skip(1) // header
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [isotope, energy, uncertainty, intensity] = fields[..] else {
+                log::error!("Invalid gamma line '{}'", line);
+                return None;
+            };
+            Some(GammaLine {
+                isotope: isotope.to_string(),
+                energy: energy.parse().ok()?,
+                uncertainty: uncertainty.parse().ok()?,
+                intensity: intensity.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+// Isotopes of the table in the order they appear
+pub fn isotopes() -> Vec<&'static str> {
+    let mut isotopes: Vec<&str> = Vec::new();
+    for line in gamma_lines() {
+        if !isotopes.contains(&line.isotope.as_str()) {
+            isotopes.push(&line.isotope);
+        }
+    }
+    isotopes
+}
+
+pub fn lines_of(isotopes: &[String]) -> impl Iterator<Item = &'static GammaLine> + '_ {
+    gamma_lines()
+        .iter()
+        .filter(|line| isotopes.contains(&line.isotope))
+}
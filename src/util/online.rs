@@ -0,0 +1,340 @@
+use polars::prelude::*;
+
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Online mode: rows arrive as JSON lines (one object of column -> number per event)
+// from a file that is being written or from a TCP socket, and are added to the
+// existing histograms every refresh interval.
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum OnlineSource {
+    #[default]
+    FileTail,
+    Tcp,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct OnlineSettings {
+    pub source: OnlineSource,
+    pub path: String,     // JSONL file that is appended to
+    pub address: String,  // host:port sending JSON lines
+    pub from_start: bool, // read the rows already in the file
+    pub refresh_secs: f64,
+}
+
+impl Default for OnlineSettings {
+    fn default() -> Self {
+        OnlineSettings {
+            source: OnlineSource::FileTail,
+            path: String::new(),
+            address: "127.0.0.1:9090".to_string(),
+            from_start: false,
+            refresh_secs: 2.0,
+        }
+    }
+}
+
+// Reader thread of a running stream, lines are collected until the next refresh
+struct OnlineStream {
+    lines: Arc<Mutex<Vec<String>>>,
+    error: Arc<Mutex<Option<String>>>,
+    stop: Arc<AtomicBool>,
+    started: Instant,
+    last_refresh: Instant,
+    rows: u64,
+}
+
+impl Drop for OnlineStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+pub struct OnlineMode {
+    pub settings: OnlineSettings,
+    #[serde(skip)]
+    stream: Option<OnlineStream>,
+}
+
+impl OnlineMode {
+    pub fn is_online(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    pub fn start(&mut self) {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let error = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let settings = self.settings.clone();
+        std::thread::spawn({
+            let (lines, error, stop) = (Arc::clone(&lines), Arc::clone(&error), Arc::clone(&stop));
+            move || {
+                let result = match settings.source {
+                    OnlineSource::FileTail => tail_file(&settings, &lines, &stop),
+                    OnlineSource::Tcp => read_socket(&settings, &lines, &stop),
+                };
+                if let Err(e) = result {
+                    log::error!("Online source stopped: {}", e);
+                    *error.lock().unwrap() = Some(e.to_string());
+                }
+            }
+        });
+
+        log::info!("Online mode started");
+        self.stream = Some(OnlineStream {
+            lines,
+            error,
+            stop,
+            started: Instant::now(),
+            last_refresh: Instant::now(),
+            rows: 0,
+        });
+    }
+
+    pub fn stop(&mut self) {
+        if self.stream.take().is_some() {
+            log::info!("Online mode stopped");
+        }
+    }
+
+    // The rows received since the last refresh, once the refresh interval has passed
+    pub fn poll(&mut self) -> Option<DataFrame> {
+        let refresh = Duration::from_secs_f64(self.settings.refresh_secs.max(0.1));
+        let stream = self.stream.as_mut()?;
+        if stream.last_refresh.elapsed() < refresh {
+            return None;
+        }
+        stream.last_refresh = Instant::now();
+
+        let lines = std::mem::take(&mut *stream.lines.lock().unwrap());
+        if lines.is_empty() {
+            return None;
+        }
+
+        match rows_to_dataframe(&lines) {
+            Ok(df) => {
+                stream.rows += df.height() as u64;
+                Some(df)
+            }
+            Err(e) => {
+                log::error!("Error reading online rows: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let online = self.is_online();
+
+        ui.add_enabled_ui(!online, |ui| {
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.settings.source, OnlineSource::FileTail, "File")
+                    .on_hover_text("Follow a JSON lines file as it is written");
+                ui.radio_value(&mut self.settings.source, OnlineSource::Tcp, "TCP")
+                    .on_hover_text("Read JSON lines from a socket");
+            });
+
+            match self.settings.source {
+                OnlineSource::FileTail => {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.settings.path)
+                                .hint_text("run.jsonl")
+                                .desired_width(150.0),
+                        );
+                        if ui.button("…").clicked() {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("JSON Lines", &["jsonl", "json", "txt"])
+                                .pick_file()
+                            {
+                                self.settings.path = path.display().to_string();
+                            }
+                        }
+                    });
+                    ui.checkbox(&mut self.settings.from_start, "Read existing rows")
+                        .on_hover_text(
+                            "Fill the rows already in the file, otherwise start at its end",
+                        );
+                }
+                OnlineSource::Tcp => {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.settings.address)
+                            .hint_text("host:port")
+                            .desired_width(150.0),
+                    );
+                }
+            }
+        });
+
+        ui.add(
+            egui::DragValue::new(&mut self.settings.refresh_secs)
+                .range(0.1..=3600.0)
+                .speed(0.1)
+                .prefix("Refresh: ")
+                .suffix(" s"),
+        );
+
+        ui.horizontal(|ui| {
+            if online {
+                if ui.button("Stop").clicked() {
+                    self.stop();
+                }
+            } else if ui
+                .button("Go Online")
+                .on_hover_text("Add the incoming rows to the histograms of the script")
+                .clicked()
+            {
+                self.start();
+            }
+            self.indicator(ui);
+        });
+    }
+
+    // Green dot with the rows received while online, red after an error
+    pub fn indicator(&self, ui: &mut egui::Ui) {
+        let Some(stream) = &self.stream else {
+            return;
+        };
+
+        if let Some(error) = stream.error.lock().unwrap().as_ref() {
+            ui.colored_label(egui::Color32::RED, "● Offline")
+                .on_hover_text(error);
+        } else {
+            ui.colored_label(egui::Color32::GREEN, "● Online")
+                .on_hover_text(format!(
+                    "{} rows in {:.0} s",
+                    stream.rows,
+                    stream.started.elapsed().as_secs_f64()
+                ));
+        }
+    }
+}
+
+fn tail_file(
+    settings: &OnlineSettings,
+    lines: &Mutex<Vec<String>>,
+    stop: &AtomicBool,
+) -> std::io::Result<()> {
+    let mut file = std::fs::File::open(&settings.path)?;
+    let mut position = if settings.from_start {
+        0
+    } else {
+        file.seek(SeekFrom::End(0))?
+    };
+
+    let mut partial = String::new();
+    while !stop.load(Ordering::Relaxed) {
+        // start over when the file was truncated or replaced by a new run
+        let length = std::fs::metadata(&settings.path)?.len();
+        if length < position {
+            log::info!("{} was truncated, reading from the start", settings.path);
+            file = std::fs::File::open(&settings.path)?;
+            position = 0;
+            partial.clear();
+        }
+
+        file.seek(SeekFrom::Start(position))?;
+        let mut reader = BufReader::new(&file);
+        let mut new_lines = Vec::new();
+        loop {
+            let read = reader.read_line(&mut partial)?;
+            if read == 0 {
+                break;
+            }
+            position += read as u64;
+            // an incomplete last line is kept until the rest is written
+            if partial.ends_with('\n') {
+                new_lines.push(std::mem::take(&mut partial));
+            }
+        }
+        if !new_lines.is_empty() {
+            lines.lock().unwrap().extend(new_lines);
+        }
+
+        std::thread::sleep(Duration::from_millis(200));
+    }
+    Ok(())
+}
+
+fn read_socket(
+    settings: &OnlineSettings,
+    lines: &Mutex<Vec<String>>,
+    stop: &AtomicBool,
+) -> std::io::Result<()> {
+    let stream = std::net::TcpStream::connect(&settings.address)?;
+    // wake up regularly to check if the stream was stopped
+    stream.set_read_timeout(Some(Duration::from_millis(500)))?;
+    log::info!("Connected to {}", settings.address);
+
+    let mut reader = BufReader::new(stream);
+    let mut partial = String::new();
+    while !stop.load(Ordering::Relaxed) {
+        match reader.read_line(&mut partial) {
+            Ok(0) => return Err(std::io::ErrorKind::ConnectionAborted.into()),
+            Ok(_) => {
+                if partial.ends_with('\n') {
+                    lines.lock().unwrap().push(std::mem::take(&mut partial));
+                }
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+// One column per key. Rows without a key or with a value that is not a number get -1e6,
+// the value the fill skips like in the event builder output
+pub fn rows_to_dataframe(lines: &[String]) -> PolarsResult<DataFrame> {
+    let mut names: Vec<String> = Vec::new();
+    let mut values: Vec<Vec<f64>> = Vec::new();
+    let mut height = 0;
+
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let row: serde_json::Map<String, serde_json::Value> = match serde_json::from_str(line) {
+            Ok(row) => row,
+            Err(e) => {
+                log::warn!("Skipping online row that is not a JSON object: {}", e);
+                continue;
+            }
+        };
+
+        for (name, value) in row {
+            let index = match names.iter().position(|existing| *existing == name) {
+                Some(index) => index,
+                None => {
+                    names.push(name);
+                    values.push(vec![-1e6; height]);
+                    names.len() - 1
+                }
+            };
+            values[index].resize(height, -1e6);
+            values[index].push(value.as_f64().unwrap_or(-1e6));
+        }
+        height += 1;
+    }
+
+    DataFrame::new(
+        names
+            .into_iter()
+            .zip(values)
+            .map(|(name, mut column)| {
+                column.resize(height, -1e6);
+                Series::new(name.into(), column).into_column()
+            })
+            .collect(),
+    )
+}
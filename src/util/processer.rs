@@ -1,5 +1,14 @@
 use crate::histoer::histogrammer::Histogrammer;
+use crate::histoer::memory_budget::{MemoryBudget, MemoryIndicator};
 use crate::histogram_scripter::histogram_script::HistogramScript;
+use crate::util::coincidence::CoincidenceSettings;
+use crate::util::correlation_scanner::CorrelationScanner;
+use crate::util::csv_source::{self, CsvSettings};
+use crate::util::file_columns;
+use crate::util::online::OnlineMode;
+use crate::util::raw_source::{self, RawSettings};
+use crate::util::remote_source::RemoteSources;
+#[cfg(feature = "python")]
 use pyo3::{prelude::*, types::PyModule};
 
 use egui_file_dialog::FileDialog;
@@ -8,11 +17,26 @@ use polars::prelude::*;
 use std::sync::atomic::Ordering;
 
 #[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct ProcessorSettings {
     pub left_panel_open: bool,
     pub histogram_script_open: bool,
     pub column_names: Vec<String>,
     pub estimated_memory: f64,
+    #[serde(default = "default_preview_percent")]
+    pub preview_percent: f64,
+    #[serde(default)]
+    pub csv: CsvSettings,
+    #[serde(default)]
+    pub raw: RawSettings,
+    #[serde(default)]
+    pub coincidences: CoincidenceSettings,
+    #[serde(default)]
+    pub memory: MemoryBudget,
+    #[serde(default = "default_file_columns")]
+    pub file_columns: bool, // add the FileIndex and RunNumber columns of each file
+    #[serde(skip)]
+    pub time_extent: Option<(f64, f64)>, // min and max of the time column, from "Scan"
 }
 
 impl Default for ProcessorSettings {
@@ -22,20 +46,46 @@ impl Default for ProcessorSettings {
             histogram_script_open: true,
             column_names: Vec::new(),
             estimated_memory: 4.0,
+            preview_percent: 1.0,
+            csv: CsvSettings::default(),
+            raw: RawSettings::default(),
+            coincidences: CoincidenceSettings::default(),
+            memory: MemoryBudget::default(),
+            file_columns: true,
+            time_extent: None,
         }
     }
 }
 
+fn default_preview_percent() -> f64 {
+    1.0
+}
+
+fn default_file_columns() -> bool {
+    true
+}
+
 #[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct Processor {
     #[serde(skip)]
     pub file_dialog: FileDialog,
     pub selected_files: Vec<std::path::PathBuf>,
     #[serde(skip)]
     pub lazyframe: Option<LazyFrame>,
+    #[serde(skip)]
+    loaded_files: Vec<std::path::PathBuf>, // files the lazyframe was made from
     pub histogrammer: Histogrammer,
     pub histogram_script: HistogramScript,
     pub settings: ProcessorSettings,
+    #[serde(default)]
+    pub correlation_scanner: CorrelationScanner,
+    #[serde(default)]
+    pub online: OnlineMode,
+    #[serde(default)]
+    pub remote: RemoteSources,
+    #[serde(skip)]
+    memory_indicator: MemoryIndicator,
 }
 
 impl Processor {
@@ -49,12 +99,19 @@ impl Processor {
                 .add_file_filter(
                     "Parquet files",
                     Arc::new(|p| p.extension().unwrap_or_default() == "parquet"),
-                ),
+                )
+                .add_file_filter("CSV/TSV files", Arc::new(csv_source::is_csv))
+                .add_file_filter("CoMPASS/MIDAS files", Arc::new(raw_source::is_raw)),
             selected_files: Vec::new(),
             lazyframe: None,
+            loaded_files: Vec::new(),
             histogrammer: Histogrammer::default(),
             histogram_script: HistogramScript::new(),
             settings: ProcessorSettings::default(),
+            correlation_scanner: CorrelationScanner::default(),
+            online: OnlineMode::default(),
+            remote: RemoteSources::default(),
+            memory_indicator: MemoryIndicator::default(),
         }
     }
 
@@ -62,6 +119,7 @@ impl Processor {
         *self = Self::new();
     }
 
+    #[cfg(feature = "python")]
     pub fn get_histograms_from_root_files(&mut self) -> PyResult<()> {
         // python3 -m venv .venv
         // source .venv/bin/activate
@@ -258,26 +316,91 @@ def get_2d_histograms(file_name):
         })
     }
 
+    // Whether the selected files can be histogrammed, i.e. parquet, CSV/TSV, or raw DAQ files
+    fn has_data_files(&self) -> bool {
+        self.selected_files.iter().any(|file| {
+            file.extension().is_some_and(|ext| ext == "parquet")
+                || csv_source::is_csv(file)
+                || raw_source::is_raw(file)
+        })
+    }
+
+    // The rows of the selected files, built into events when "Build Events" is on
     fn create_lazyframe(&mut self) {
+        self.loaded_files = self.selected_files.clone();
+        self.load_files();
+
+        if !self.settings.coincidences.enabled {
+            return;
+        }
+        if let Some(lf) = self.lazyframe.take() {
+            match self.settings.coincidences.build(lf) {
+                Ok(events) => {
+                    self.settings.column_names = Self::get_column_names_from_lazyframe(&events);
+                    self.lazyframe = Some(events);
+                }
+                Err(e) => log::error!("Failed to build events: {}", e),
+            }
+        }
+    }
+
+    fn load_files(&mut self) {
         // get all the parquet files from the selected files
         let parquet_files: Vec<std::path::PathBuf> = self
             .selected_files
             .iter()
-            .filter(|file| file.extension().unwrap() == "parquet")
+            .filter(|file| file.extension().is_some_and(|ext| ext == "parquet"))
+            .cloned()
+            .collect();
+
+        let csv_files: Vec<std::path::PathBuf> = self
+            .selected_files
+            .iter()
+            .filter(|file| csv_source::is_csv(file))
+            .cloned()
+            .collect();
+
+        let raw_files: Vec<std::path::PathBuf> = self
+            .selected_files
+            .iter()
+            .filter(|file| raw_source::is_raw(file))
             .cloned()
             .collect();
 
         // warn if no parquet files are selected
         if parquet_files.is_empty() {
-            log::warn!("No Parquet files selected.");
+            if !csv_files.is_empty() {
+                if !raw_files.is_empty() {
+                    log::warn!(
+                        "CSV and raw files can not be combined, only the CSV files are used."
+                    );
+                }
+                self.create_lazyframe_from_csv(&csv_files);
+            } else if !raw_files.is_empty() {
+                self.create_lazyframe_from_raw(&raw_files);
+            } else {
+                log::warn!("No Parquet, CSV, or raw files selected.");
+            }
             return;
         }
 
-        let files_arc: Arc<[std::path::PathBuf]> = Arc::from(parquet_files);
-        let args = ScanArgsParquet::default();
-        log::info!("Files {:?}", files_arc);
+        if !csv_files.is_empty() || !raw_files.is_empty() {
+            log::warn!(
+                "Parquet files can not be combined with CSV or raw files, only the Parquet files are used."
+            );
+        }
+
+        log::info!("Files {:?}", parquet_files);
+        let scan = if self.settings.file_columns {
+            file_columns::scan_with_file_columns(&parquet_files, |file| {
+                LazyFrame::scan_parquet(file, ScanArgsParquet::default())
+            })
+        } else {
+            let files_arc: Arc<[std::path::PathBuf]> = Arc::from(parquet_files);
+            LazyFrame::scan_parquet_files(files_arc, ScanArgsParquet::default())
+        };
 
-        match LazyFrame::scan_parquet_files(files_arc, args) {
+        match scan {
             Ok(lf) => {
                 log::info!("Loaded Parquet files");
                 let column_names = Self::get_column_names_from_lazyframe(&lf);
@@ -292,6 +415,53 @@ def get_2d_histograms(file_name):
         }
     }
 
+    fn create_lazyframe_from_csv(&mut self, csv_files: &[std::path::PathBuf]) {
+        log::info!("Files {:?}", csv_files);
+
+        let csv = &self.settings.csv;
+        let scan = if self.settings.file_columns {
+            file_columns::scan_with_file_columns(csv_files, |file| csv.scan(&[file.to_path_buf()]))
+        } else {
+            csv.scan(csv_files)
+        };
+
+        match scan {
+            Ok(lf) => {
+                log::info!("Loaded CSV files");
+                let column_names = Self::get_column_names_from_lazyframe(&lf);
+
+                self.lazyframe = Some(lf);
+                self.settings.column_names = column_names;
+            }
+            Err(e) => {
+                self.lazyframe = None;
+                log::error!("Failed to load CSV files: {}", e);
+            }
+        }
+    }
+
+    fn create_lazyframe_from_raw(&mut self, raw_files: &[std::path::PathBuf]) {
+        log::info!("Files {:?}", raw_files);
+
+        match self
+            .settings
+            .raw
+            .scan(raw_files, self.settings.file_columns)
+        {
+            Ok(lf) => {
+                log::info!("Decoded raw files");
+                let column_names = Self::get_column_names_from_lazyframe(&lf);
+
+                self.lazyframe = Some(lf);
+                self.settings.column_names = column_names;
+            }
+            Err(e) => {
+                self.lazyframe = None;
+                log::error!("Failed to decode raw files: {}", e);
+            }
+        }
+    }
+
     fn get_column_names_from_lazyframe(lazyframe: &LazyFrame) -> Vec<String> {
         let lf: LazyFrame = lazyframe.clone().limit(1);
 
@@ -308,30 +478,48 @@ def get_2d_histograms(file_name):
         }
     }
 
-    fn perform_histogrammer_from_lazyframe(&mut self) {
+    fn perform_histogrammer_from_lazyframe(&mut self, sample: Option<f64>) -> Result<(), String> {
+        let Some(lf) = &self.lazyframe else {
+            return Err("Failed to preform histogrammer: LazyFrame is None.".to_string());
+        };
+        self.histogram_script.add_histograms(
+            &mut self.histogrammer,
+            lf.clone(),
+            self.settings.estimated_memory,
+            &self.settings.memory,
+            sample,
+        )
+    }
+
+    // Refills the histograms of the changed cuts from the loaded rows, the files are only
+    // read again when nothing is loaded or the selection changed
+    fn refill_affected_histograms(&mut self) {
+        if self.lazyframe.is_none() || self.loaded_files != self.selected_files {
+            if !self.has_data_files() {
+                log::error!("No Parquet, CSV, or raw files selected to refill from.");
+                return;
+            }
+            self.create_lazyframe();
+        }
+
         if let Some(lf) = &self.lazyframe {
-            self.histogram_script.add_histograms(
+            if let Err(e) = self.histogram_script.refill_affected_histograms(
                 &mut self.histogrammer,
                 lf.clone(),
                 self.settings.estimated_memory,
-            );
-        } else {
-            log::error!("Failed to preform histogrammer: LazyFrame is None.");
+                &self.settings.memory,
+            ) {
+                log::error!("{}", e);
+            }
         }
     }
 
-    pub fn calculate_histograms(&mut self) {
-        // Check if the files are Parquet files
-        if self
-            .selected_files
-            .iter()
-            .any(|file| match file.extension() {
-                Some(ext) => ext == "parquet",
-                None => false,
-            })
-        {
+    // Starts the fill, the histograms are filled on the rayon pool while `calculating` is set
+    pub fn calculate_histograms(&mut self) -> Result<(), String> {
+        // Check if the files are Parquet or CSV files
+        if self.has_data_files() {
             self.create_lazyframe();
-            self.perform_histogrammer_from_lazyframe();
+            self.perform_histogrammer_from_lazyframe(None)
         }
         // Check if the files are ROOT files
         else if self
@@ -342,11 +530,153 @@ def get_2d_histograms(file_name):
                 None => false,
             })
         {
-            let _ = self.get_histograms_from_root_files();
+            #[cfg(feature = "python")]
+            return self
+                .get_histograms_from_root_files()
+                .map_err(|e| format!("Failed to read the ROOT files: {}", e));
+            #[cfg(not(feature = "python"))]
+            Err("Reading ROOT files needs uproot, build with `--features python`".to_string())
         }
         // No valid files selected
         else {
-            log::error!("No Parquet files or ROOT files selected.");
+            Err("No Parquet, CSV, raw, or ROOT files selected.".to_string())
+        }
+    }
+
+    // Fills the histograms from a random sample of the rows
+    pub fn preview_histograms(&mut self) -> Result<(), String> {
+        self.create_lazyframe();
+        self.perform_histogrammer_from_lazyframe(Some(self.settings.preview_percent / 100.0))
+    }
+
+    // Min and max of the time column over the selected files
+    fn scan_time_extent(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.create_lazyframe();
+        let Some(lf) = &self.lazyframe else {
+            return Err("No Parquet or CSV files loaded".into());
+        };
+
+        // the time column can be one made in Column Creation
+        let configs = &self.histogram_script.configs;
+        let mut lf = lf.clone();
+        configs.add_computed_columns(&mut lf);
+
+        let column = &configs.time_column;
+        let time = col(column).cast(DataType::Float64);
+        let df = lf
+            .select([time.clone().min().alias("min"), time.max().alias("max")])
+            .collect()?;
+        let min = df.column("min")?.f64()?.get(0);
+        let max = df.column("max")?.f64()?.get(0);
+
+        match (min, max) {
+            (Some(min), Some(max)) => {
+                log::info!("{} runs from {} to {}", column, min, max);
+                self.settings.time_extent = Some((min, max));
+                Ok(())
+            }
+            _ => Err(format!("Column '{}' has no values", column).into()),
+        }
+    }
+
+    // Refill the histograms with the rows in a window of the time column
+    fn time_window_ui(&mut self, ui: &mut egui::Ui) {
+        let configs = &mut self.histogram_script.configs;
+        let names: Vec<String> = self
+            .settings
+            .column_names
+            .iter()
+            .cloned()
+            .chain(configs.computed_column_names())
+            .collect();
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Time Column")
+                .selected_text(configs.time_column.clone())
+                .show_ui(ui, |ui| {
+                    if ui
+                        .selectable_value(&mut configs.time_column, String::new(), "None")
+                        .clicked()
+                    {
+                        configs.time_window = None;
+                        self.settings.time_extent = None;
+                    }
+                    for name in &names {
+                        if ui
+                            .selectable_value(&mut configs.time_column, name.clone(), name)
+                            .clicked()
+                        {
+                            self.settings.time_extent = None;
+                        }
+                    }
+                });
+        });
+
+        if configs.time_column.is_empty() {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Scan")
+                .on_hover_text("Read the first and last time from the files")
+                .clicked()
+            {
+                if let Err(e) = self.scan_time_extent() {
+                    log::error!("Error scanning the time column: {}", e);
+                }
+            }
+
+            let configs = &mut self.histogram_script.configs;
+            let mut gated = configs.time_window.is_some();
+            if ui
+                .checkbox(&mut gated, "Gate")
+                .on_hover_text("Only fill rows inside the window")
+                .changed()
+            {
+                configs.time_window = if gated {
+                    self.settings.time_extent.or(Some((0.0, 1.0)))
+                } else {
+                    None
+                };
+            }
+        });
+
+        let configs = &mut self.histogram_script.configs;
+        let Some((start, end)) = &mut configs.time_window else {
+            return;
+        };
+
+        let (min, max) = self.settings.time_extent.unwrap_or((*start, *end));
+        let mut width = *end - *start;
+        let slider = ui.add(
+            egui::Slider::new(start, min..=max.max(min))
+                .text("Start")
+                .clamping(egui::SliderClamping::Never),
+        );
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut width)
+                    .range(0.0..=f64::INFINITY)
+                    .speed((max - min).abs() / 1000.0)
+                    .prefix("Width: "),
+            );
+            ui.label(format!("End: {:.3}", *start + width));
+        });
+        *end = *start + width;
+
+        let calculating = self.histogrammer.calculating.load(Ordering::Relaxed);
+        let replay = ui
+            .add_enabled(!calculating, egui::Button::new("Replay"))
+            .on_hover_text("Refill the histograms with the rows in the window")
+            .clicked();
+
+        // refill when the slider is let go, so the run can be scrubbed through
+        if (replay || slider.drag_stopped()) && !calculating {
+            self.create_lazyframe();
+            if let Err(e) = self.perform_histogrammer_from_lazyframe(None) {
+                log::error!("{}", e);
+            }
         }
     }
 
@@ -373,7 +703,10 @@ def get_2d_histograms(file_name):
 
                     ui.vertical(|ui| {
 
-                        ui.label("Processor");
+                        ui.horizontal(|ui| {
+                            ui.label("Processor");
+                            self.online.indicator(ui);
+                        });
 
                         if ui
                             .add_enabled(
@@ -383,10 +716,33 @@ def get_2d_histograms(file_name):
                             .on_disabled_hover_text("No files selected.")
                             .clicked()
                         {
-                            self.calculate_histograms();
+                            if let Err(e) = self.calculate_histograms() {
+                                log::error!("{}", e);
+                            }
                         }
 
-                        ui.add(
+                        ui.horizontal(|ui| {
+                            if ui
+                                .add_enabled(self.has_data_files(), egui::Button::new("Preview"))
+                                .on_hover_text("Fill the histograms from a random sample of the rows to check ranges, cuts, and expressions. Preview histograms are watermarked until a full fill.")
+                                .on_disabled_hover_text("No Parquet, CSV, or raw files selected.")
+                                .clicked()
+                            {
+                                if let Err(e) = self.preview_histograms() {
+                                    log::error!("{}", e);
+                                }
+                            }
+
+                            ui.add(
+                                egui::DragValue::new(&mut self.settings.preview_percent)
+                                    .range(0.01..=100.0)
+                                    .speed(0.1)
+                                    .suffix(" %"),
+                            );
+                        });
+
+                        ui.add_enabled(
+                            !self.settings.memory.auto,
                             egui::DragValue::new(&mut self.settings.estimated_memory)
                                 .range(0.1..=f64::INFINITY)
                                 .speed(1)
@@ -394,6 +750,51 @@ def get_2d_histograms(file_name):
                                 .suffix(" GB"),
                         ).on_hover_text("Estimated memory in GB. This is an approximation based off the rows and columns in a lazyframe, so set it lower that the actual memory to avoid crashes.");
 
+                        egui::CollapsingHeader::new("Memory")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                self.settings.memory.ui(ui);
+                            });
+
+                        ui.checkbox(&mut self.settings.file_columns, "File Columns")
+                            .on_hover_text("Add FileIndex and RunNumber columns with the file each row came from, the run number is read from the file name");
+
+                        egui::CollapsingHeader::new("CSV Options")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                self.settings.csv.ui(ui);
+                            });
+
+                        egui::CollapsingHeader::new("Raw DAQ Options")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                self.settings.raw.ui(ui);
+                            });
+
+                        egui::CollapsingHeader::new("Coincidences")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                self.settings.coincidences.ui(ui);
+                            });
+
+                        egui::CollapsingHeader::new("Remote Sources")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                self.remote.ui(ui);
+                            });
+
+                        egui::CollapsingHeader::new("Online")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                self.online.ui(ui);
+                            });
+
+                        egui::CollapsingHeader::new("Time Window")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                self.time_window_ui(ui);
+                            });
+
                         if self.histogrammer.calculating.load(Ordering::Relaxed) {
                             // Show spinner while `calculating` is true
                             ui.horizontal(|ui| {
@@ -414,6 +815,17 @@ def get_2d_histograms(file_name):
                     {
                         self.settings.histogram_script_open = !self.settings.histogram_script_open;
                     }
+
+                    if ui
+                        .selectable_label(self.correlation_scanner.open, "Correlations")
+                        .on_hover_text("Scan a sample of the data for correlated columns")
+                        .clicked()
+                    {
+                        if self.lazyframe.is_none() {
+                            self.create_lazyframe();
+                        }
+                        self.correlation_scanner.open = !self.correlation_scanner.open;
+                    }
                 });
 
                 ui.separator();
@@ -425,20 +837,40 @@ def get_2d_histograms(file_name):
                 // scrollable list of selected files
                 egui::ScrollArea::vertical().show(ui, |ui| {
                     for file in self.selected_files.iter() {
-                        ui.label(file.to_str().unwrap());
+                        let label = ui.label(file.to_str().unwrap());
+                        if self.settings.file_columns {
+                            let run = file_columns::run_number(file)
+                                .map_or("-1e6".to_string(), |run| run.to_string());
+                            label.on_hover_text(format!("RunNumber {}", run));
+                        }
                     }
                 });
             },
         );
 
+        let cut_stats = self.histogrammer.cut_stats.lock().unwrap().clone();
         egui::SidePanel::left("spectrix_histogram_panel").show_animated(
             ctx,
             self.settings.histogram_script_open && self.settings.left_panel_open,
             |ui| {
-                self.histogram_script.ui(ui);
+                self.histogram_script.ui(ui, &cut_stats);
             },
         );
 
+        for cut in self.histogrammer.take_promoted_cuts() {
+            self.histogram_script.configs.promote_cut(cut);
+        }
+
+        if std::mem::take(&mut self.histogram_script.refill_cuts_requested) {
+            self.refill_affected_histograms();
+        }
+
+        if std::mem::take(&mut self.histogram_script.refill_requested) && self.has_data_files() {
+            if let Err(e) = self.calculate_histograms() {
+                log::error!("{}", e);
+            }
+        }
+
         // Secondary left panel for the toggle button
         egui::SidePanel::left("spectrix_toggle_left_panel")
             .resizable(false)
@@ -462,18 +894,18 @@ def get_2d_histograms(file_name):
     }
 
     pub fn bottom_panel(&mut self, ctx: &egui::Context) {
-        if self.histogrammer.calculating.load(Ordering::Relaxed) {
-            egui::TopBottomPanel::bottom("spectrix_bottom_panel").show(ctx, |ui| {
-                ui.add(
-                    egui::widgets::ProgressBar::new(match self.histogrammer.progress.lock() {
-                        Ok(x) => *x,
-                        Err(_) => 0.0,
-                    })
-                    .animate(true)
-                    .show_percentage(),
-                );
+        egui::TopBottomPanel::bottom("spectrix_bottom_panel").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if self.histogrammer.calculating.load(Ordering::Relaxed) {
+                    if let Ok(progress) = self.histogrammer.progress.lock() {
+                        progress.ui(ui);
+                    }
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    self.memory_indicator.ui(ui);
+                });
             });
-        }
+        });
     }
 
     fn central_panel_ui(&mut self, ctx: &egui::Context) {
@@ -482,11 +914,47 @@ def get_2d_histograms(file_name):
         });
     }
 
+    // Adds the rows received in online mode, a full fill takes precedence
+    fn update_online(&mut self, ctx: &egui::Context) {
+        if !self.online.is_online() {
+            return;
+        }
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+
+        if self.histogrammer.calculating.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Some(df) = self.online.poll() {
+            self.histogram_script
+                .append_rows(&mut self.histogrammer, df);
+        }
+    }
+
+    // Selects the files of a finished remote fetch
+    fn update_remote(&mut self) {
+        if let Some(files) = self.remote.poll() {
+            for file in files {
+                if !self.selected_files.contains(&file) {
+                    self.selected_files.push(file);
+                }
+            }
+        }
+    }
+
     pub fn ui(&mut self, ctx: &egui::Context) {
         self.left_side_panels_ui(ctx);
+        self.update_remote();
+        self.update_online(ctx);
         self.bottom_panel(ctx);
         self.central_panel_ui(ctx);
 
+        self.correlation_scanner.ui(
+            ctx,
+            &self.settings.column_names,
+            self.lazyframe.as_ref(),
+            &mut self.histogram_script.configs,
+        );
+
         self.file_dialog.update(ctx);
     }
 }
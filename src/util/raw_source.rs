@@ -0,0 +1,531 @@
+use fnv::FnvHashMap;
+use polars::prelude::*;
+use std::path::{Path, PathBuf};
+
+// Raw DAQ files decoded straight into a frame, so spectra can be made without running an
+// event builder first. Values are f64 like every other column, missing values are -1e6.
+//
+// CAEN CoMPASS binary files (.BIN) give one row per hit with the columns Board, Channel,
+// Timestamp (ns), Energy, EnergyShort, EnergyCalibrated (when saved), and Flags. A channel
+// named in the channel map also gets <Name>Energy, <Name>EnergyShort, and <Name>Time columns,
+// set on the hits of that channel only.
+//
+// MIDAS files (.mid) give one row per event with EventId, Serial, and MidasTime (s) and one
+// column per value of the numeric banks, <BANK>_<index>, or only the mapped values when a
+// channel map is given.
+
+pub(crate) const MISSING: f64 = -1e6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum RawFormat {
+    #[default]
+    Auto, // by the file extension
+    Compass,
+    Midas,
+}
+
+impl RawFormat {
+    fn of(&self, path: &Path) -> RawFormat {
+        match self {
+            RawFormat::Auto => {
+                if extension(path).is_some_and(|ext| ext == "mid") {
+                    RawFormat::Midas
+                } else {
+                    RawFormat::Compass
+                }
+            }
+            format => *format,
+        }
+    }
+}
+
+// A named channel: a CoMPASS board number or a MIDAS bank name, and the channel in it
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ChannelName {
+    pub source: String,
+    pub channel: usize,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RawSettings {
+    pub format: RawFormat,
+    pub channels: Vec<ChannelName>,
+    pub bank_values: usize, // values per MIDAS bank made into columns without a channel map
+}
+
+impl Default for RawSettings {
+    fn default() -> Self {
+        RawSettings {
+            format: RawFormat::Auto,
+            channels: Vec::new(),
+            bank_values: 64,
+        }
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+}
+
+pub fn is_raw(path: &Path) -> bool {
+    extension(path).is_some_and(|ext| matches!(ext.as_str(), "bin" | "mid"))
+}
+
+impl RawSettings {
+    // Decodes every file, frames missing a column of another file get it filled with -1e6.
+    // With `file_columns` the FileIndex and RunNumber columns are added.
+    pub fn scan(&self, files: &[PathBuf], file_columns: bool) -> PolarsResult<LazyFrame> {
+        let mut frames = Vec::with_capacity(files.len());
+        for file in files {
+            frames.push(self.decode(file)?);
+        }
+
+        let mut names: Vec<String> = Vec::new();
+        for df in &frames {
+            for name in df.get_column_names() {
+                if !names.iter().any(|n| n == name.as_str()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+
+        let aligned: Vec<LazyFrame> = frames
+            .into_iter()
+            .map(|df| {
+                let columns: Vec<Expr> = names
+                    .iter()
+                    .map(|name| {
+                        if df.get_column_index(name).is_some() {
+                            col(name.as_str())
+                        } else {
+                            lit(MISSING).alias(name.as_str())
+                        }
+                    })
+                    .collect();
+                df.lazy().select(columns)
+            })
+            .collect();
+
+        if file_columns {
+            crate::util::file_columns::scan_with_file_columns(files, |file| {
+                let index = files.iter().position(|f| f == file).unwrap_or_default();
+                Ok(aligned[index].clone())
+            })
+        } else if aligned.is_empty() {
+            polars_bail!(NoData: "no raw files selected");
+        } else {
+            concat(aligned, UnionArgs::default())
+        }
+    }
+
+    pub fn decode(&self, path: &Path) -> PolarsResult<DataFrame> {
+        let data = std::fs::read(path)?;
+        let mut columns = Columns::default();
+        match self.format.of(path) {
+            RawFormat::Midas => self.decode_midas(&data, &mut columns),
+            _ => self.decode_compass(&data, &mut columns),
+        }
+        log::info!("Decoded {} rows from {}", columns.rows, path.display());
+        columns.finish()
+    }
+
+    fn decode_compass(&self, data: &[u8], columns: &mut Columns) {
+        // column names of the mapped channels by (board, channel)
+        let mapped: FnvHashMap<(u16, u16), [String; 3]> = self
+            .channels
+            .iter()
+            .filter_map(|channel| {
+                let board = channel.source.trim().parse().ok()?;
+                let name = &channel.name;
+                Some((
+                    (board, channel.channel as u16),
+                    [
+                        format!("{}Energy", name),
+                        format!("{}EnergyShort", name),
+                        format!("{}Time", name),
+                    ],
+                ))
+            })
+            .collect();
+
+        let mut bytes = Bytes { data, pos: 0 };
+        // CoMPASS 2 files start with 0xCAEx, the low bits tell which fields each hit has
+        let header = bytes.u16().filter(|header| header & 0xFFF0 == 0xCAE0);
+        if header.is_none() {
+            bytes.pos = 0;
+        }
+
+        while bytes.pos < data.len() {
+            let Some(hit) = CompassHit::read(&mut bytes, header) else {
+                log::warn!(
+                    "Truncated CoMPASS hit at byte {}, the rest is skipped",
+                    bytes.pos
+                );
+                break;
+            };
+
+            let time = hit.timestamp as f64 / 1000.0; // ps to ns
+            columns.set("Board", hit.board as f64);
+            columns.set("Channel", hit.channel as f64);
+            columns.set("Timestamp", time);
+            if let Some(energy) = hit.energy {
+                columns.set("Energy", energy as f64);
+            }
+            if let Some(short) = hit.energy_short {
+                columns.set("EnergyShort", short as f64);
+            }
+            if let Some(calibrated) = hit.energy_calibrated {
+                columns.set("EnergyCalibrated", calibrated);
+            }
+            columns.set("Flags", hit.flags as f64);
+
+            if let Some([energy, short, time_name]) = mapped.get(&(hit.board, hit.channel)) {
+                if let Some(value) = hit.energy {
+                    columns.set(energy, value as f64);
+                }
+                if let Some(value) = hit.energy_short {
+                    columns.set(short, value as f64);
+                }
+                columns.set(time_name, time);
+            }
+            columns.end_row();
+        }
+    }
+
+    fn decode_midas(&self, data: &[u8], columns: &mut Columns) {
+        let mapped: FnvHashMap<(String, usize), &str> = self
+            .channels
+            .iter()
+            .map(|channel| {
+                (
+                    (channel.source.trim().to_string(), channel.channel),
+                    channel.name.as_str(),
+                )
+            })
+            .collect();
+
+        let mut bytes = Bytes { data, pos: 0 };
+        while bytes.pos < data.len() {
+            let (Some(event_id), Some(_trigger_mask), Some(serial), Some(time), Some(size)) = (
+                bytes.u16(),
+                bytes.u16(),
+                bytes.u32(),
+                bytes.u32(),
+                bytes.u32(),
+            ) else {
+                log::warn!("Truncated MIDAS event header, the rest is skipped");
+                break;
+            };
+            let Some(event) = bytes.take(size as usize) else {
+                log::warn!("Truncated MIDAS event {}, the rest is skipped", serial);
+                break;
+            };
+            // begin and end of run and message events
+            if event_id & 0x8000 != 0 {
+                continue;
+            }
+
+            columns.set("EventId", event_id as f64);
+            columns.set("Serial", serial as f64);
+            columns.set("MidasTime", time as f64);
+            self.decode_banks(event, &mapped, columns);
+            columns.end_row();
+        }
+    }
+
+    fn decode_banks(
+        &self,
+        event: &[u8],
+        mapped: &FnvHashMap<(String, usize), &str>,
+        columns: &mut Columns,
+    ) {
+        let mut bytes = Bytes {
+            data: event,
+            pos: 0,
+        };
+        let (Some(banks_size), Some(flags)) = (bytes.u32(), bytes.u32()) else {
+            return;
+        };
+        let end = (8 + banks_size as usize).min(event.len());
+        let bank_32 = flags & 0x10 != 0;
+        let bank_32_aligned = flags & 0x20 != 0;
+
+        while bytes.pos < end {
+            let Some(name) = bytes.take(4) else {
+                return;
+            };
+            let name = String::from_utf8_lossy(name)
+                .trim_end_matches('\0')
+                .to_string();
+            let (kind, size) = if bank_32 || bank_32_aligned {
+                let kind = bytes.u32();
+                let size = bytes.u32();
+                if bank_32_aligned {
+                    bytes.u32(); // reserved
+                }
+                (kind, size)
+            } else {
+                (bytes.u16().map(u32::from), bytes.u16().map(u32::from))
+            };
+            let (Some(kind), Some(size)) = (kind, size) else {
+                return;
+            };
+            let Some(values) = bytes.take(size as usize) else {
+                return;
+            };
+            // bank data is padded to 8 bytes
+            bytes.pos += (8 - size as usize % 8) % 8;
+
+            let Some(width) = type_width(kind) else {
+                continue;
+            };
+            for (index, value) in values.chunks_exact(width).enumerate() {
+                let column = if mapped.is_empty() {
+                    if index >= self.bank_values {
+                        break;
+                    }
+                    format!("{}_{}", name, index)
+                } else if let Some(column) = mapped.get(&(name.clone(), index)) {
+                    column.to_string()
+                } else {
+                    continue;
+                };
+                columns.set(&column, type_value(kind, value));
+            }
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Format:");
+            egui::ComboBox::from_id_salt("raw_format")
+                .selected_text(format!("{:?}", self.format))
+                .show_ui(ui, |ui| {
+                    for format in [RawFormat::Auto, RawFormat::Compass, RawFormat::Midas] {
+                        ui.selectable_value(&mut self.format, format, format!("{:?}", format));
+                    }
+                })
+                .response
+                .on_hover_text("Auto reads .mid files as MIDAS and .bin files as CoMPASS");
+        });
+
+        ui.add(
+            egui::DragValue::new(&mut self.bank_values)
+                .speed(1)
+                .prefix("Values per bank: "),
+        )
+        .on_hover_text("MIDAS bank values made into columns when no channel is named");
+
+        ui.label("Channel Names").on_hover_text(
+            "Source is the CoMPASS board number or the MIDAS bank name. A named CoMPASS channel gets <Name>Energy, <Name>EnergyShort, and <Name>Time columns, with a MIDAS map only the named bank values become columns",
+        );
+        let mut index_to_remove = None;
+        egui::Grid::new("raw_channel_names")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Source");
+                ui.label("Channel");
+                ui.label("Name");
+                ui.end_row();
+
+                for (index, channel) in self.channels.iter_mut().enumerate() {
+                    ui.add(egui::TextEdit::singleline(&mut channel.source).desired_width(50.0));
+                    ui.add(egui::DragValue::new(&mut channel.channel).speed(1));
+                    ui.add(egui::TextEdit::singleline(&mut channel.name).desired_width(100.0));
+                    if ui.button("X").clicked() {
+                        index_to_remove = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(index) = index_to_remove {
+            self.channels.remove(index);
+        }
+
+        if ui.button("+").clicked() {
+            // the next channel of the last source
+            let channel = self
+                .channels
+                .last()
+                .map(|last| ChannelName {
+                    source: last.source.clone(),
+                    channel: last.channel + 1,
+                    name: String::new(),
+                })
+                .unwrap_or_default();
+            self.channels.push(channel);
+        }
+    }
+}
+
+struct CompassHit {
+    board: u16,
+    channel: u16,
+    timestamp: u64, // ps
+    energy: Option<u16>,
+    energy_calibrated: Option<f64>,
+    energy_short: Option<u16>,
+    flags: u32,
+}
+
+impl CompassHit {
+    // A hit of a CoMPASS 2 file with its header, or of an older file without one
+    fn read(bytes: &mut Bytes<'_>, header: Option<u16>) -> Option<CompassHit> {
+        let board = bytes.u16()?;
+        let channel = bytes.u16()?;
+        let timestamp = bytes.u64()?;
+
+        let Some(header) = header else {
+            let energy = bytes.u16()?;
+            let energy_short = bytes.u16()?;
+            let flags = bytes.u32()?;
+            let samples = bytes.u32()?;
+            bytes.take(2 * samples as usize)?;
+            return Some(CompassHit {
+                board,
+                channel,
+                timestamp,
+                energy: Some(energy),
+                energy_calibrated: None,
+                energy_short: Some(energy_short),
+                flags,
+            });
+        };
+
+        let energy = if header & 0x1 != 0 {
+            Some(bytes.u16()?)
+        } else {
+            None
+        };
+        let energy_calibrated = if header & 0x2 != 0 {
+            Some(f64::from_le_bytes(bytes.take(8)?.try_into().ok()?))
+        } else {
+            None
+        };
+        let energy_short = if header & 0x4 != 0 {
+            Some(bytes.u16()?)
+        } else {
+            None
+        };
+        let flags = bytes.u32()?;
+        if header & 0x8 != 0 {
+            bytes.take(1)?; // waveform code
+            let samples = bytes.u32()?;
+            bytes.take(2 * samples as usize)?;
+        }
+
+        Some(CompassHit {
+            board,
+            channel,
+            timestamp,
+            energy,
+            energy_calibrated,
+            energy_short,
+            flags,
+        })
+    }
+}
+
+// Bytes of each value of a numeric MIDAS bank type, None for the other types
+fn type_width(kind: u32) -> Option<usize> {
+    match kind {
+        1 | 2 => Some(1),        // BYTE, SBYTE
+        4 | 5 => Some(2),        // WORD, SHORT
+        6..=9 => Some(4),        // DWORD, INT, BOOL, FLOAT
+        10 | 17 | 18 => Some(8), // DOUBLE, INT64, UINT64
+        _ => None,
+    }
+}
+
+fn type_value(kind: u32, bytes: &[u8]) -> f64 {
+    match kind {
+        1 => bytes[0] as f64,
+        2 => bytes[0] as i8 as f64,
+        4 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        5 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        6 | 8 => u32::from_le_bytes(bytes.try_into().unwrap_or_default()) as f64,
+        7 => i32::from_le_bytes(bytes.try_into().unwrap_or_default()) as f64,
+        9 => f32::from_le_bytes(bytes.try_into().unwrap_or_default()) as f64,
+        10 => f64::from_le_bytes(bytes.try_into().unwrap_or_default()),
+        17 => i64::from_le_bytes(bytes.try_into().unwrap_or_default()) as f64,
+        _ => u64::from_le_bytes(bytes.try_into().unwrap_or_default()) as f64,
+    }
+}
+
+// Little endian reader over the bytes of a file
+struct Bytes<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+}
+
+// Columns built row by row, a column not set in a row is -1e6 there
+#[derive(Default)]
+pub(crate) struct Columns {
+    names: Vec<String>,
+    index: FnvHashMap<String, usize>,
+    values: Vec<Vec<f64>>,
+    pub(crate) rows: usize, // finished rows
+}
+
+impl Columns {
+    pub(crate) fn set(&mut self, name: &str, value: f64) {
+        let index = match self.index.get(name) {
+            Some(&index) => index,
+            None => {
+                self.names.push(name.to_string());
+                self.index.insert(name.to_string(), self.values.len());
+                self.values.push(Vec::new());
+                self.values.len() - 1
+            }
+        };
+        let column = &mut self.values[index];
+        if column.len() > self.rows {
+            column[self.rows] = value; // set twice in the row, the last value is kept
+        } else {
+            column.resize(self.rows, MISSING);
+            column.push(value);
+        }
+    }
+
+    pub(crate) fn end_row(&mut self) {
+        self.rows += 1;
+    }
+
+    pub(crate) fn finish(self) -> PolarsResult<DataFrame> {
+        let rows = self.rows;
+        let columns = self
+            .names
+            .into_iter()
+            .zip(self.values)
+            .map(|(name, mut values)| {
+                values.resize(rows, MISSING);
+                Column::new(name.into(), values)
+            })
+            .collect();
+        DataFrame::new(columns)
+    }
+}
@@ -0,0 +1,264 @@
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+// Parquet files on a remote server (https:// or sftp://) are downloaded with curl into a
+// local cache and then selected like local files. An sftp URL ending in "/" is a directory,
+// every .parquet file in it is fetched. Cached files are only downloaded again on "Refresh".
+// sftp logs in with the ssh keys in ~/.ssh, the user goes in the URL: sftp://user@host/data/
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RemoteSettings {
+    pub urls: Vec<String>,
+    pub cache_dir: String,
+}
+
+impl Default for RemoteSettings {
+    fn default() -> Self {
+        RemoteSettings {
+            urls: Vec::new(),
+            cache_dir: std::env::temp_dir()
+                .join("spectrix_cache")
+                .display()
+                .to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct FetchStatus {
+    message: String,     // what is being downloaded
+    files: Vec<PathBuf>, // local copies, in the order of the URLs
+    errors: Vec<String>, // URLs that could not be fetched
+    done: bool,
+}
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+pub struct RemoteSources {
+    #[serde(default)]
+    pub settings: RemoteSettings,
+    #[serde(skip)]
+    new_url: String,
+    #[serde(skip)]
+    fetch: Option<Arc<Mutex<FetchStatus>>>,
+}
+
+impl RemoteSources {
+    pub fn is_fetching(&self) -> bool {
+        self.fetch.is_some()
+    }
+
+    // Downloads the files of every URL on its own thread, `refresh` replaces the cached copies
+    pub fn fetch(&mut self, refresh: bool) {
+        if self.is_fetching() {
+            return;
+        }
+
+        let status = Arc::new(Mutex::new(FetchStatus::default()));
+        let settings = self.settings.clone();
+        std::thread::spawn({
+            let status = Arc::clone(&status);
+            move || {
+                let cache_dir = PathBuf::from(&settings.cache_dir);
+                for url in settings.urls.iter().map(|url| url.trim()) {
+                    if url.is_empty() {
+                        continue;
+                    }
+                    status.lock().unwrap().message = url.to_string();
+                    match fetch_url(url, &cache_dir, refresh) {
+                        Ok(files) => status.lock().unwrap().files.extend(files),
+                        Err(e) => {
+                            log::error!("Failed to fetch '{}': {}", url, e);
+                            status.lock().unwrap().errors.push(url.to_string());
+                        }
+                    }
+                }
+                status.lock().unwrap().done = true;
+            }
+        });
+
+        self.fetch = Some(status);
+    }
+
+    // The local files once a fetch has finished
+    pub fn poll(&mut self) -> Option<Vec<PathBuf>> {
+        let done = self.fetch.as_ref()?.lock().unwrap().done;
+        if !done {
+            return None;
+        }
+
+        let status = self.fetch.take()?;
+        let mut status = status.lock().unwrap();
+        log::info!(
+            "Fetched {} remote file(s), {} URL(s) failed",
+            status.files.len(),
+            status.errors.len()
+        );
+        Some(std::mem::take(&mut status.files))
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let fetching = self.is_fetching();
+
+        ui.add_enabled_ui(!fetching, |ui| {
+            let mut index_to_remove = None;
+            for (index, url) in self.settings.urls.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(url).desired_width(200.0));
+                    if ui.button("X").clicked() {
+                        index_to_remove = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = index_to_remove {
+                self.settings.urls.remove(index);
+            }
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.new_url)
+                        .hint_text("sftp://user@host/data/")
+                        .desired_width(200.0),
+                );
+                if ui
+                    .add_enabled(!self.new_url.trim().is_empty(), egui::Button::new("+"))
+                    .clicked()
+                {
+                    self.settings
+                        .urls
+                        .push(std::mem::take(&mut self.new_url).trim().to_string());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Cache:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.settings.cache_dir).desired_width(150.0),
+                );
+                if ui.button("…").clicked() {
+                    if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                        self.settings.cache_dir = dir.display().to_string();
+                    }
+                }
+            });
+        });
+
+        ui.horizontal(|ui| {
+            let can_fetch = !fetching && !self.settings.urls.is_empty();
+            if ui
+                .add_enabled(can_fetch, egui::Button::new("Fetch"))
+                .on_hover_text("Download the files that are not in the cache and select them")
+                .clicked()
+            {
+                self.fetch(false);
+            }
+            if ui
+                .add_enabled(can_fetch, egui::Button::new("Refresh"))
+                .on_hover_text("Download every file again, e.g. after a run was rewritten")
+                .clicked()
+            {
+                self.fetch(true);
+            }
+
+            if let Some(status) = &self.fetch {
+                ui.spinner();
+                ui.label(status.lock().unwrap().message.clone());
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_millis(250));
+            }
+        });
+    }
+}
+
+// Local copies of the files at `url`, downloaded unless they are cached
+fn fetch_url(url: &str, cache_dir: &Path, refresh: bool) -> Result<Vec<PathBuf>, String> {
+    let urls = if url.starts_with("sftp://") && url.ends_with('/') {
+        list_directory(url)?
+            .into_iter()
+            .map(|name| format!("{}{}", url, name))
+            .collect()
+    } else {
+        vec![url.to_string()]
+    };
+
+    let mut files = Vec::new();
+    for url in urls {
+        let path = cache_path(cache_dir, &url)?;
+        if refresh || !path.exists() {
+            download(&url, &path)?;
+        }
+        files.push(path);
+    }
+    Ok(files)
+}
+
+// Where a URL is kept in the cache: <cache>/<host>/<path on the server>
+pub fn cache_path(cache_dir: &Path, url: &str) -> Result<PathBuf, String> {
+    let Some(rest) = ["https://", "http://", "sftp://"]
+        .iter()
+        .find_map(|scheme| url.strip_prefix(scheme))
+    else {
+        return Err("Only https://, http://, and sftp:// URLs are supported".to_string());
+    };
+
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = host.rsplit('@').next().unwrap_or(host).replace(':', "_");
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+
+    let relative = Path::new(path);
+    if path.is_empty()
+        || relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err(format!("'{}' does not name a file", url));
+    }
+    Ok(cache_dir.join(host).join(relative))
+}
+
+// Names of the .parquet files in a directory on an sftp server
+fn list_directory(url: &str) -> Result<Vec<String>, String> {
+    let listing = curl(&["--list-only", url])?;
+    let mut names: Vec<String> = String::from_utf8_lossy(&listing)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|name| name.ends_with(".parquet"))
+        .collect();
+    names.sort();
+
+    if names.is_empty() {
+        return Err("No .parquet files in the directory".to_string());
+    }
+    Ok(names)
+}
+
+// Downloads next to the destination first, so a failed download never looks cached
+fn download(url: &str, path: &Path) -> Result<(), String> {
+    log::info!("Downloading '{}' to '{}'", url, path.display());
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+
+    let partial = path.with_extension("part");
+    let result = curl(&["--output", &partial.display().to_string(), url]);
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+    std::fs::rename(&partial, path).map_err(|e| e.to_string())
+}
+
+fn curl(args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        .args(args)
+        .output()
+        .map_err(|e| format!("Could not run curl: {}", e))?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}